@@ -1,4 +1,42 @@
+use std::{env, fs, path::PathBuf};
+
+/// Bundles the pages of the website for `:NeovideHelp`. They are packaged with the published crate
+/// too, only the rest of the website is left out.
+fn bundle_docs() {
+    println!("cargo:rerun-if-changed=website/docs");
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let mut pages = fs::read_dir(manifest_dir.join("website/docs"))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension().is_some_and(|extension| extension == "md")
+                        && path.file_name().is_some_and(|name| name != "SUMMARY.md")
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    pages.sort();
+
+    let mut code = String::from("&[\n");
+    for page in pages {
+        let name = page.file_name().unwrap().to_string_lossy().to_string();
+        let path = page.to_string_lossy().to_string();
+        code.push_str(&format!("    ({name:?}, include_str!({path:?})),\n"));
+    }
+    code.push_str("]\n");
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("help_pages.rs"), code).expect("Could not bundle the documentation");
+}
+
 fn main() {
+    // Any rerun-if-changed limits the reruns to the files listed, so the icon and the script
+    // itself have to be there too
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=assets/neovide.ico");
+    bundle_docs();
+
     #[cfg(windows)]
     {
         let mut res = winres::WindowsResource::new();
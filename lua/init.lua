@@ -4,6 +4,7 @@
 ---@field register_clipboard boolean
 ---@field register_right_click boolean
 ---@field enable_focus_command boolean
+---@field register_restart boolean
 ---@field global_variable_settings string[]
 ---@field option_settings string[]
 
@@ -26,8 +27,8 @@ local function rpcrequest(method, ...)
 end
 
 local function set_clipboard(register)
-    return function(lines)
-        rpcrequest("neovide.set_clipboard", lines, register)
+    return function(lines, regtype)
+        rpcrequest("neovide.set_clipboard", lines, register, regtype)
     end
 end
 
@@ -37,7 +38,13 @@ local function get_clipboard(register)
     end
 end
 
-if args.register_clipboard and not vim.g.neovide_no_custom_clipboard then
+local custom_clipboard = args.register_clipboard and not vim.g.neovide_no_custom_clipboard
+
+if custom_clipboard then
+    -- Keep the provider of a server, to give it back once Neovide detaches from it
+    if not (vim.g.clipboard and vim.g.clipboard.name == "neovide") then
+        vim.g.neovide_previous_clipboard = vim.g.clipboard
+    end
     vim.g.clipboard = {
         name = "neovide",
         copy = {
@@ -52,8 +59,47 @@ if args.register_clipboard and not vim.g.neovide_no_custom_clipboard then
     }
     vim.g.loaded_clipboard_provider = nil
     vim.cmd.runtime("autoload/provider/clipboard.vim")
+
+    vim.api.nvim_create_autocmd("UILeave", {
+        group = vim.api.nvim_create_augroup("NeovideClipboard", { clear = true }),
+        callback = function()
+            -- Another Neovide may have attached since
+            if vim.v.event.chan ~= vim.g.neovide_channel_id then
+                return
+            end
+            vim.g.clipboard = vim.g.neovide_previous_clipboard
+            vim.g.neovide_previous_clipboard = nil
+            vim.g.loaded_clipboard_provider = nil
+            vim.cmd.runtime("autoload/provider/clipboard.vim")
+        end,
+    })
+else
+    -- The copies don't go through Neovide, so record the clipboard yanks for the history here
+    vim.api.nvim_create_autocmd("TextYankPost", {
+        callback = function()
+            if (vim.g.neovide_clipboard_history_size or 0) <= 0 then
+                return
+            end
+            local event = vim.v.event
+            local register = event.regname
+            if
+                register == "+"
+                or register == "*"
+                or (register == "" and vim.o.clipboard:find("unnamed"))
+            then
+                rpcnotify("neovide.clipboard_yank", event.regcontents, event.regtype)
+            end
+        end,
+    })
 end
 
+vim.api.nvim_create_user_command("NeovideClipboardHistory", function(opts)
+    rpcnotify("neovide.clipboard_history", opts.args)
+end, {
+    nargs = "?",
+    desc = "Paste an entry from the clipboard history",
+})
+
 if args.register_right_click then
     vim.api.nvim_create_user_command("NeovideRegisterRightClick", function()
         rpcnotify("neovide.register_right_click")
@@ -61,12 +107,249 @@ if args.register_right_click then
     vim.api.nvim_create_user_command("NeovideUnregisterRightClick", function()
         rpcnotify("neovide.unregister_right_click")
     end, {})
+    -- The URL scheme is also registered in the registry of Windows
+    vim.api.nvim_create_user_command("NeovideRegisterUrlHandler", function()
+        rpcnotify("neovide.register_url_handler")
+    end, {})
+    vim.api.nvim_create_user_command("NeovideUnregisterUrlHandler", function()
+        rpcnotify("neovide.unregister_url_handler")
+    end, {})
 end
 
+vim.api.nvim_create_user_command("NeovideLogLevel", function(opts)
+    local levels = rpcrequest("neovide.log_level", opts.args)
+    vim.notify("Neovide log levels: " .. levels, vim.log.levels.INFO)
+end, {
+    nargs = "*",
+    desc = "Show or change the log level of Neovide, or of one of its modules",
+    complete = function(_, line)
+        if #vim.split(line, "%s+", { trimempty = true }) > 2 then
+            return {}
+        end
+        return { "off", "error", "warn", "info", "debug", "trace" }
+    end,
+})
+
+vim.api.nvim_create_user_command("NeovideExportSettings", function(opts)
+    local file = vim.fn.fnamemodify(vim.fn.expand(opts.args), ":p")
+    local ok, error = pcall(rpcrequest, "neovide.export_settings", file)
+    if not ok then
+        vim.notify(tostring(error), vim.log.levels.ERROR)
+        return
+    end
+    vim.notify("Neovide settings exported to " .. file, vim.log.levels.INFO)
+end, {
+    nargs = 1,
+    complete = "file",
+    desc = "Write the current Neovide settings to a TOML file",
+})
+
+vim.api.nvim_create_user_command("NeovideImportSettings", function(opts)
+    local file = vim.fn.fnamemodify(vim.fn.expand(opts.args), ":p")
+    local ok, values = pcall(rpcrequest, "neovide.import_settings", file)
+    if not ok then
+        vim.notify(tostring(values), vim.log.levels.ERROR)
+        return
+    end
+    -- Setting the variables notifies Neovide like any other change, so both sides stay in sync
+    local count = 0
+    for name, value in pairs(values) do
+        vim.g["neovide_" .. name] = value
+        count = count + 1
+    end
+    vim.notify(string.format("%d Neovide settings imported from %s", count, file), vim.log.levels.INFO)
+end, {
+    nargs = 1,
+    complete = "file",
+    desc = "Apply the Neovide settings of a TOML file written by :NeovideExportSettings",
+})
+
+vim.api.nvim_create_user_command("NeovideTour", function()
+    rpcnotify("neovide.tour")
+end, { desc = "Show a tour of the features of Neovide" })
+
+vim.api.nvim_create_user_command("NeovideHelp", function(args)
+    rpcnotify("neovide.help", args.args)
+end, {
+    nargs = "?",
+    complete = function(lead)
+        local names = {}
+        for name, _ in pairs(vim.g) do
+            if vim.startswith(name, "neovide_") and vim.startswith(name, lead) then
+                table.insert(names, name)
+            end
+        end
+        table.sort(names)
+        return names
+    end,
+    desc = "Show the documentation of Neovide about a topic or a setting",
+})
+
+vim.api.nvim_create_user_command("NeovideInfo", function()
+    rpcnotify("neovide.info")
+end, { desc = "Show the version of Neovide and the GPU memory it uses" })
+
+vim.api.nvim_create_user_command("NeovideTogglePip", function()
+    rpcnotify("neovide.toggle_pip")
+end, { desc = "Toggle a small window above the other ones" })
+
+vim.api.nvim_create_user_command("NeovideToggleHints", function()
+    vim.g.neovide_hide_hints = not vim.g.neovide_hide_hints
+end, { desc = "Hide or show the inlay hints and other virtual text" })
+
+vim.api.nvim_create_user_command("NeovideNewInstance", function(opts)
+    -- The directory, then any NAME=VALUE environment variables
+    local params = { env = vim.empty_dict() }
+    for _, arg in ipairs(opts.fargs) do
+        local name, value = arg:match("^([^=]+)=(.*)$")
+        if name then
+            params.env[name] = value
+        else
+            params.cwd = vim.fn.fnamemodify(vim.fn.expand(arg), ":p")
+        end
+    end
+    params.cwd = params.cwd or vim.fn.getcwd()
+    rpcnotify("neovide.new_instance", params)
+end, {
+    nargs = "*",
+    complete = "dir",
+    desc = "Open another Neovide, with its own working directory and environment variables",
+})
+
 vim.api.nvim_create_user_command("NeovideFocus", function()
     rpcnotify("neovide.focus_window")
 end, {})
 
+if args.register_restart then
+    vim.api.nvim_create_user_command("NeovideRestart", function()
+        rpcrequest("neovide.restart", true)
+        vim.cmd("confirm qall")
+        -- Still running, so quitting was cancelled
+        rpcrequest("neovide.restart", false)
+    end, {})
+    vim.api.nvim_create_user_command("NeovideToggleMultigrid", function()
+        rpcrequest("neovide.toggle_multigrid", true)
+        vim.cmd("confirm qall")
+        rpcrequest("neovide.toggle_multigrid", false)
+    end, { desc = "Restart Neovim with multigrid toggled, to compare how both render" })
+end
+
+-- Keep track of the open files, so that they can be recovered if Neovim crashes.
+local function notify_buffers_changed()
+    local files = {}
+    for _, buffer in ipairs(vim.api.nvim_list_bufs()) do
+        if vim.bo[buffer].buflisted and vim.bo[buffer].buftype == "" then
+            local name = vim.api.nvim_buf_get_name(buffer)
+            if name ~= "" then
+                table.insert(files, name)
+            end
+        end
+    end
+    rpcnotify("neovide.buffers_changed", files)
+end
+
+vim.api.nvim_create_autocmd({ "VimEnter", "BufAdd", "BufDelete", "BufFilePost" }, {
+    callback = function()
+        -- The buffer list is only up to date after BufDelete has finished
+        vim.schedule(notify_buffers_changed)
+    end,
+})
+
+-- Let Neovide know which windows show a terminal, so that they can use g:neovide_terminal_font.
+local function notify_terminal_windows()
+    local windows = {}
+    for _, window in ipairs(vim.api.nvim_list_wins()) do
+        local buffer = vim.api.nvim_win_get_buf(window)
+        if vim.bo[buffer].buftype == "terminal" then
+            table.insert(windows, window)
+        end
+    end
+    rpcnotify("neovide.terminal_windows", windows)
+end
+
+vim.api.nvim_create_autocmd({ "TermOpen", "TermClose", "BufWinEnter", "WinClosed" }, {
+    callback = function()
+        vim.schedule(notify_terminal_windows)
+    end,
+})
+
+-- Let Neovide know the file of the current buffer, so that it can be dragged from the title bar.
+local represented_file
+local function notify_represented_file()
+    local name = vim.api.nvim_buf_get_name(0)
+    local file = ""
+    if vim.bo.buftype == "" and name ~= "" and vim.fn.getftype(name) ~= "" then
+        file = vim.fn.fnamemodify(name, ":p")
+    end
+    if file ~= represented_file then
+        represented_file = file
+        rpcnotify("neovide.represented_file", file)
+    end
+end
+
+vim.api.nvim_create_autocmd({ "VimEnter", "BufEnter", "BufFilePost", "BufWritePost" }, {
+    callback = function()
+        vim.schedule(notify_represented_file)
+    end,
+})
+
+-- Let Neovide know when the current buffer is a help file, which the 'h' flag of 'mouse' enables the
+-- mouse in.
+local help_buffer = false
+local function notify_help_buffer()
+    local is_help = vim.bo.buftype == "help"
+    if is_help ~= help_buffer then
+        help_buffer = is_help
+        rpcnotify("neovide.help_buffer", is_help)
+    end
+end
+
+vim.api.nvim_create_autocmd({ "BufEnter", "FileType" }, {
+    callback = function()
+        vim.schedule(notify_help_buffer)
+    end,
+})
+
+-- Let Neovide know what g:neovide_title_template can show, the file being relative to the current
+-- directory when it's inside of it.
+local function notify_title_info()
+    local name = vim.api.nvim_buf_get_name(0)
+    local file = name ~= "" and vim.fn.fnamemodify(name, ":~:.") or ""
+    local cwd = vim.fn.fnamemodify(vim.fn.getcwd(), ":~")
+    rpcnotify("neovide.title_info", file, cwd, vim.bo.modified)
+end
+
+vim.api.nvim_create_autocmd(
+    { "VimEnter", "BufEnter", "BufFilePost", "BufModifiedSet", "DirChanged", "TermOpen" },
+    {
+        callback = function()
+            vim.schedule(notify_title_info)
+        end,
+    }
+)
+
+-- With g:neovide_file_changed_prompt, files changed outside of Neovim are asked about in a prompt
+-- drawn by Neovide instead of the command line.
+vim.api.nvim_create_autocmd({ "FileChangedShell" }, {
+    callback = function(event)
+        local reason = vim.v.fcs_reason
+        if
+            not vim.g.neovide_file_changed_prompt
+            or (reason ~= "changed" and reason ~= "conflict" and reason ~= "deleted")
+        then
+            vim.v.fcs_choice = "ask"
+            return
+        end
+        -- Like without the prompt, unmodified buffers are reloaded with 'autoread'
+        if reason == "changed" and vim.o.autoread and not vim.bo[event.buf].modified then
+            vim.v.fcs_choice = "reload"
+            return
+        end
+        vim.v.fcs_choice = ""
+        rpcnotify("neovide.file_changed", event.buf, vim.fn.fnamemodify(event.file, ":p"), reason)
+    end,
+})
+
 vim.api.nvim_exec(
     [[
 function! WatchGlobal(variable, callback)
@@ -114,6 +397,7 @@ vim.api.nvim_create_autocmd({ "VimLeavePre" }, {
     once = true,
     nested = true,
     callback = function()
-        rpcrequest("neovide.quit", vim.v.exiting)
+        -- The directory is kept for a restarted Neovim
+        rpcrequest("neovide.quit", vim.v.exiting, vim.fn.getcwd(-1, -1))
     end,
 })
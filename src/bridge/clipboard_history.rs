@@ -0,0 +1,256 @@
+use std::{collections::VecDeque, fs, path::Path, sync::OnceLock};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use keyring::Entry;
+use parking_lot::Mutex;
+use rmpv::Value;
+
+use crate::settings::*;
+
+const HISTORY_FILE: &str = "clipboard_history";
+/// The entry of the OS keyring, like the macOS Keychain or the Windows Credential Manager, with
+/// the key of the saved history.
+const KEYRING_SERVICE: &str = "neovide";
+const KEYRING_USER: &str = "clipboard_history";
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+
+#[derive(Clone, Default, SettingGroup)]
+#[setting_prefix = "clipboard"]
+pub struct ClipboardSettings {
+    history_size: u32,
+    history_persist: bool,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ClipboardHistory {
+    /// The most recent entry first.
+    entries: VecDeque<String>,
+    /// If the saved history was read, which happens the first time it's needed after
+    /// `history_persist` is enabled. It's never overwritten before that.
+    loaded: bool,
+}
+
+impl ClipboardHistory {
+    /// Adds the text at the top of the history, moving it there when it's already in it. Returns
+    /// `true` if the history changed.
+    fn push(&mut self, text: String, max_size: usize) -> bool {
+        if text.trim().is_empty() || self.entries.front() == Some(&text) {
+            return false;
+        }
+        self.entries.retain(|entry| *entry != text);
+        self.entries.push_front(text);
+        self.entries.truncate(max_size);
+        true
+    }
+
+    /// Adds the older entries from the saved history after the ones copied since the start.
+    fn merge(&mut self, saved: VecDeque<String>, max_size: usize) {
+        for entry in saved {
+            if !self.entries.contains(&entry) {
+                self.entries.push_back(entry);
+            }
+        }
+        self.entries.truncate(max_size);
+    }
+
+    /// Reads the saved history unless it was already. Returns `false` when it can't be read, and
+    /// then it's left alone instead of being replaced.
+    fn load(&mut self, max_size: usize) -> bool {
+        if self.loaded {
+            return true;
+        }
+        match load_history() {
+            Ok(saved) => {
+                self.merge(saved, max_size);
+                self.loaded = true;
+                true
+            }
+            Err(error) => {
+                log::error!("Could not load the clipboard history, it won't be saved: {error:?}");
+                false
+            }
+        }
+    }
+}
+
+static HISTORY: OnceLock<Mutex<ClipboardHistory>> = OnceLock::new();
+
+fn history() -> &'static Mutex<ClipboardHistory> {
+    HISTORY.get_or_init(Mutex::default)
+}
+
+/// Records copied lines, in the format used by Neovim's clipboard provider, in the history when
+/// it's enabled.
+pub fn record_clipboard_history(lines: &Value, register_type: Option<&Value>, settings: &Settings) {
+    let clipboard_settings = settings.get::<ClipboardSettings>();
+    let max_size = clipboard_settings.history_size as usize;
+    if max_size == 0 {
+        return;
+    }
+    let Some(lines) = lines.as_array() else {
+        return;
+    };
+    let mut text = lines
+        .iter()
+        .filter_map(Value::as_str)
+        .collect::<Vec<_>>()
+        .join("\n");
+    // Keep line-wise yanks line-wise when they are pasted back
+    if register_type.and_then(Value::as_str) == Some("V") {
+        text.push('\n');
+    }
+
+    let mut history = history().lock();
+    // The setting can change at any time, so it's checked for every copy
+    let persist = clipboard_settings.history_persist && history.load(max_size);
+    if history.push(text, max_size) && persist {
+        if let Err(error) = save_history(&history) {
+            log::warn!("Could not save the clipboard history: {error:?}");
+        }
+    }
+}
+
+/// Returns the history entries, the most recent first.
+pub fn clipboard_history_entries(settings: &Settings) -> Vec<String> {
+    let clipboard_settings = settings.get::<ClipboardSettings>();
+    let mut history = history().lock();
+    if clipboard_settings.history_persist {
+        history.load(clipboard_settings.history_size as usize);
+    }
+    history.entries.iter().cloned().collect()
+}
+
+fn load_history() -> Result<VecDeque<String>> {
+    let path = neovide_std_datapath().join(HISTORY_FILE);
+    if !path.exists() {
+        return Ok(VecDeque::new());
+    }
+    let data = fs::read(&path).with_context(|| format!("Could not read {path:?}"))?;
+    let key =
+        load_key()?.context("The key of the clipboard history is missing from the keyring")?;
+    serde_json::from_slice(&decrypt(&key, &data)?)
+        .with_context(|| format!("Could not parse {path:?}"))
+}
+
+fn save_history(history: &ClipboardHistory) -> Result<()> {
+    let key = match load_key()? {
+        Some(key) => key,
+        None => create_key()?,
+    };
+    let data = encrypt(&key, &serde_json::to_vec(&history.entries)?)?;
+    write_private(&neovide_std_datapath().join(HISTORY_FILE), &data)
+}
+
+fn keyring_entry() -> Result<Entry> {
+    Entry::new(KEYRING_SERVICE, KEYRING_USER).context("Could not open the keyring")
+}
+
+/// The key is kept in the OS keyring rather than next to the history, so that the history can't
+/// be read from a backup or a synced folder. It's never replaced when it can't be read, since
+/// that would lose the history.
+fn load_key() -> Result<Option<[u8; KEY_SIZE]>> {
+    match keyring_entry()?.get_secret() {
+        Ok(secret) => <[u8; KEY_SIZE]>::try_from(secret)
+            .map(Some)
+            .map_err(|_| anyhow!("The key of the clipboard history in the keyring is invalid")),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(error) => Err(error).context("Could not read the key of the clipboard history"),
+    }
+}
+
+fn create_key() -> Result<[u8; KEY_SIZE]> {
+    let key = rand::random::<[u8; KEY_SIZE]>();
+    keyring_entry()?
+        .set_secret(&key)
+        .context("Could not store the key of the clipboard history")?;
+    Ok(key)
+}
+
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    fs::create_dir_all(neovide_std_datapath())?;
+    #[cfg(unix)]
+    {
+        use std::{
+            io::Write,
+            os::unix::fs::{OpenOptionsExt, PermissionsExt},
+        };
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .with_context(|| format!("Could not open {path:?}"))?;
+        // The mode is only used when the file is created
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(data)?;
+    }
+    #[cfg(not(unix))]
+    fs::write(path, data).with_context(|| format!("Could not write {path:?}"))?;
+    Ok(())
+}
+
+fn encrypt(key: &[u8; KEY_SIZE], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = rand::random::<[u8; NONCE_SIZE]>();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    Ok([nonce.as_slice(), ciphertext.as_slice()].concat())
+}
+
+fn decrypt(key: &[u8; KEY_SIZE], data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < NONCE_SIZE {
+        bail!("The data is too short");
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Decryption failed, the key doesn't match"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_moves_duplicates_to_the_top() {
+        let mut history = ClipboardHistory::default();
+        assert!(history.push("one".to_string(), 3));
+        assert!(history.push("two".to_string(), 3));
+        assert!(!history.push("two".to_string(), 3));
+        assert!(!history.push("  ".to_string(), 3));
+        assert!(history.push("one".to_string(), 3));
+        assert_eq!(history.entries, ["one", "two"]);
+
+        history.push("three".to_string(), 3);
+        history.push("four".to_string(), 3);
+        assert_eq!(history.entries, ["four", "three", "one"]);
+    }
+
+    #[test]
+    fn merge_keeps_the_new_entries_first() {
+        let mut history = ClipboardHistory::default();
+        history.push("new".to_string(), 3);
+        history.push("both".to_string(), 3);
+        history.merge(["both", "old", "older"].map(str::to_string).into(), 3);
+        assert_eq!(history.entries, ["both", "new", "old"]);
+    }
+
+    #[test]
+    fn encrypted_history_round_trips() {
+        let key = [7; KEY_SIZE];
+        let data = encrypt(&key, b"secret").unwrap();
+        assert!(!data.windows(6).any(|window| window == b"secret"));
+        assert_eq!(decrypt(&key, &data).unwrap(), b"secret");
+        assert!(decrypt(&[8; KEY_SIZE], &data).is_err());
+        assert!(decrypt(&key, &data[..NONCE_SIZE - 1]).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+-- Finds the window shown at a screen cell, the floating windows on top first
+local function window_at(row, column)
+    local found, found_zindex
+    for _, window in ipairs(vim.api.nvim_tabpage_list_wins(0)) do
+        local config = vim.api.nvim_win_get_config(window)
+        local position = vim.fn.win_screenpos(window)
+        local top, left = position[1] - 1, position[2] - 1
+        local zindex = config.relative ~= "" and (config.zindex or 50) or 0
+        if
+            config.focusable ~= false
+            and row >= top
+            and row < top + vim.api.nvim_win_get_height(window)
+            and column >= left
+            and column < left + vim.api.nvim_win_get_width(window)
+            and (not found or zindex > found_zindex)
+        then
+            found, found_zindex = window, zindex
+        end
+    end
+    return found
+end
+
+-- The line and byte column of the text shown at a cell of the window, following folds and
+-- wrapped lines
+local function buffer_position(window, row, column)
+    return vim.api.nvim_win_call(window, function()
+        local info = vim.fn.getwininfo(window)[1]
+        local line = info.topline
+        local top = 0
+        while line < info.botline do
+            local fold_end = vim.fn.foldclosedend(line)
+            local height = fold_end == -1
+                    and vim.api.nvim_win_text_height(window, { start_row = line - 1, end_row = line - 1 }).all
+                or 1
+            if top + height > row then
+                break
+            end
+            top = top + height
+            line = (fold_end == -1 and line or fold_end) + 1
+        end
+        local text_width = info.width - info.textoff
+        local virtual_column = (row - top) * text_width
+            + math.max(column - info.textoff, 0)
+            + vim.fn.winsaveview().leftcol
+            + 1
+        local byte_column = vim.fn.virtcol2col(window, line, virtual_column)
+        return line, math.max(byte_column - 1, 0)
+    end)
+end
+
+local function drop_text(text, row, column)
+    local window = row and window_at(row, column)
+    if window then
+        local position = vim.fn.win_screenpos(window)
+        local line, byte_column = buffer_position(window, row - position[1] + 1, column - position[2] + 1)
+        vim.api.nvim_set_current_win(window)
+        vim.api.nvim_win_set_cursor(window, { line, byte_column })
+    end
+    if not vim.bo.modifiable then
+        vim.notify("Can't drop text into a buffer that isn't modifiable", vim.log.levels.WARN)
+        return
+    end
+    vim.api.nvim_put(vim.split(text, "\n", { plain = true }), "c", false, true)
+end
+
+return drop_text(...)
@@ -9,7 +9,7 @@ use rmpv::Value;
 use skia_safe::Color4f;
 use strum::AsRefStr;
 
-use crate::editor::{Colors, CursorMode, CursorShape, Style, UnderlineStyle};
+use crate::editor::{Colors, CursorMode, CursorShape, Style, TitleInfo, UnderlineStyle};
 
 #[derive(Clone, Debug)]
 pub enum ParseError {
@@ -60,7 +60,7 @@ pub struct GridLineCell {
 
 pub type StyledContent = Vec<(u64, String)>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MessageKind {
     Unknown,
     Confirm,
@@ -113,6 +113,21 @@ pub enum GuiOption {
     Unknown(String, Value),
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct TablineTab {
+    pub handle: u64,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PopupMenuItem {
+    pub word: String,
+    pub kind: String,
+    pub menu: String,
+    #[allow(unused)]
+    pub info: String,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum WindowAnchor {
     NorthWest,
@@ -156,6 +171,9 @@ pub enum RedrawEvent {
     BusyStart,
     BusyStop,
     Flush,
+    Bell {
+        visual: bool,
+    },
     Resize {
         grid: u64,
         width: u64,
@@ -168,6 +186,10 @@ pub enum RedrawEvent {
         id: u64,
         style: Style,
     },
+    HighlightGroupSet {
+        name: String,
+        id: u64,
+    },
     GridLine {
         grid: u64,
         row: u64,
@@ -196,6 +218,7 @@ pub enum RedrawEvent {
     },
     WindowPosition {
         grid: u64,
+        window: u64,
         start_row: u64,
         start_column: u64,
         width: u64,
@@ -203,6 +226,7 @@ pub enum RedrawEvent {
     },
     WindowFloatPosition {
         grid: u64,
+        window: u64,
         anchor: WindowAnchor,
         anchor_grid: u64,
         anchor_row: f64,
@@ -230,15 +254,12 @@ pub enum RedrawEvent {
     },
     WindowViewport {
         grid: u64,
-        #[allow(unused)]
         top_line: f64,
-        #[allow(unused)]
         bottom_line: f64,
         #[allow(unused)]
         current_line: f64,
         #[allow(unused)]
         current_column: f64,
-        #[allow(unused)]
         line_count: Option<f64>,
         scroll_delta: Option<f64>,
     },
@@ -249,7 +270,6 @@ pub enum RedrawEvent {
         left: u64,
         right: u64,
     },
-    #[allow(unused)]
     CommandLineShow {
         content: StyledContent,
         position: u64,
@@ -258,30 +278,49 @@ pub enum RedrawEvent {
         indent: u64,
         level: u64,
     },
-    #[allow(unused)]
     CommandLinePosition {
         position: u64,
         level: u64,
     },
-    #[allow(unused)]
     CommandLineSpecialCharacter {
         character: String,
         shift: bool,
         level: u64,
     },
-    #[allow(unused)]
-    CommandLineHide,
-    #[allow(unused)]
+    CommandLineHide {
+        /// Missing before Neovim 0.10, which only hides the innermost level.
+        level: Option<u64>,
+    },
     CommandLineBlockShow {
         lines: Vec<StyledContent>,
     },
-    #[allow(unused)]
     CommandLineBlockAppend {
         line: StyledContent,
     },
-    #[allow(unused)]
     CommandLineBlockHide,
-    #[allow(unused)]
+    TablineUpdate {
+        current_tab: u64,
+        tabs: Vec<TablineTab>,
+    },
+    PopupMenuShow {
+        items: Vec<PopupMenuItem>,
+        selected: Option<u64>,
+        row: u64,
+        column: u64,
+        /// `None` when the menu completes the command line.
+        grid: Option<u64>,
+    },
+    PopupMenuSelect {
+        selected: Option<u64>,
+    },
+    PopupMenuHide,
+    WildmenuShow {
+        items: Vec<String>,
+    },
+    WildmenuSelect {
+        selected: Option<u64>,
+    },
+    WildmenuHide,
     MessageShow {
         kind: MessageKind,
         content: StyledContent,
@@ -305,6 +344,16 @@ pub enum RedrawEvent {
         entries: Vec<(MessageKind, StyledContent)>,
     },
     Suspend,
+    /// Not part of the redraw notification, the runtime sends the handles of the windows showing
+    /// a terminal whenever they change.
+    TerminalWindows {
+        windows: Vec<u64>,
+    },
+    /// Not part of the redraw notification either, the file, directory and modified state shown by
+    /// `g:neovide_title_template`.
+    TitleInfo {
+        info: TitleInfo,
+    },
 }
 
 fn unpack_color(packed_color: u64) -> Color4f {
@@ -501,10 +550,28 @@ fn parse_default_colors(default_colors_arguments: Vec<Value>) -> Result<RedrawEv
     })
 }
 
-fn parse_style(style_map: Value, _info_array: Value) -> Result<Style> {
+/// Parses the highlight groups of the `ext_hlstate` info of a style. Each entry is a map with the
+/// `kind` of highlight, the `hi_name` of the group and for the UI highlights their `ui_name`.
+fn parse_highlight_groups(info_array: Value) -> Result<Vec<String>> {
+    let mut groups = Vec::new();
+    for info in parse_array(info_array)? {
+        for (key, value) in parse_map(info)? {
+            if matches!(key.as_str(), Some("hi_name" | "ui_name")) {
+                let name = parse_string(value)?;
+                if !name.is_empty() && !groups.contains(&name) {
+                    groups.push(name);
+                }
+            }
+        }
+    }
+    Ok(groups)
+}
+
+fn parse_style(style_map: Value, info_array: Value) -> Result<Style> {
     let attributes = parse_map(style_map)?;
 
     let mut style = Style::new(Colors::new(None, None, None));
+    style.highlight_groups = parse_highlight_groups(info_array)?;
 
     for attribute in attributes {
         if let (Value::String(name), value) = attribute {
@@ -562,6 +629,15 @@ fn parse_hl_attr_define(hl_attr_define_arguments: Vec<Value>) -> Result<RedrawEv
     })
 }
 
+fn parse_hl_group_set(hl_group_set_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [name, id] = extract_values(hl_group_set_arguments)?;
+
+    Ok(RedrawEvent::HighlightGroupSet {
+        name: parse_string(name)?,
+        id: parse_u64(id)?,
+    })
+}
+
 fn parse_grid_line_cell(grid_line_cell: Value) -> Result<GridLineCell> {
     fn take_value(val: &mut Value) -> Value {
         std::mem::replace(val, Value::Nil)
@@ -653,11 +729,11 @@ fn parse_grid_scroll(grid_scroll_arguments: Vec<Value>) -> Result<RedrawEvent> {
 }
 
 fn parse_win_pos(win_pos_arguments: Vec<Value>) -> Result<RedrawEvent> {
-    let [grid, _window, start_row, start_column, width, height] =
-        extract_values(win_pos_arguments)?;
+    let [grid, window, start_row, start_column, width, height] = extract_values(win_pos_arguments)?;
 
     Ok(RedrawEvent::WindowPosition {
         grid: parse_u64(grid)?,
+        window: parse_handle(window)?,
         start_row: parse_u64(start_row)?,
         start_column: parse_u64(start_column)?,
         width: parse_u64(width)?,
@@ -665,6 +741,18 @@ fn parse_win_pos(win_pos_arguments: Vec<Value>) -> Result<RedrawEvent> {
     })
 }
 
+/// Window handles are sent as msgpack extension values wrapping the integer handle.
+/// Parses the handle of a window, buffer or tabpage, sent as a msgpack extension.
+fn parse_handle(handle: Value) -> Result<u64> {
+    match handle {
+        Value::Ext(_, data) => rmpv::decode::read_value(&mut data.as_slice())
+            .ok()
+            .and_then(|value| value.as_u64())
+            .ok_or_else(|| ParseError::Format(format!("Invalid handle {data:?}"))),
+        value => parse_u64(value),
+    }
+}
+
 fn parse_window_anchor(value: Value) -> Result<WindowAnchor> {
     let value_str = parse_string(value)?;
     match value_str.as_str() {
@@ -677,11 +765,12 @@ fn parse_window_anchor(value: Value) -> Result<WindowAnchor> {
 }
 
 fn parse_win_float_pos(win_float_pos_arguments: Vec<Value>) -> Result<RedrawEvent> {
-    let [grid, _window, anchor, anchor_grid, anchor_row, anchor_column, focusable, z_index] =
+    let [grid, window, anchor, anchor_grid, anchor_row, anchor_column, focusable, z_index] =
         extract_values(win_float_pos_arguments)?;
 
     Ok(RedrawEvent::WindowFloatPosition {
         grid: parse_u64(grid)?,
+        window: parse_handle(window)?,
         anchor: parse_window_anchor(anchor)?,
         anchor_grid: parse_u64(anchor_grid)?,
         anchor_row: parse_f64(anchor_row)?,
@@ -789,6 +878,14 @@ fn parse_cmdline_pos(cmdline_pos_arguments: Vec<Value>) -> Result<RedrawEvent> {
     })
 }
 
+fn parse_cmdline_hide(cmdline_hide_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let ([], [level]) = extract_values_with_optional(cmdline_hide_arguments)?;
+
+    Ok(RedrawEvent::CommandLineHide {
+        level: level.map(parse_u64).transpose()?,
+    })
+}
+
 fn parse_cmdline_special_char(cmdline_special_char_arguments: Vec<Value>) -> Result<RedrawEvent> {
     let [character, shift, level] = extract_values(cmdline_special_char_arguments)?;
 
@@ -818,6 +915,95 @@ fn parse_cmdline_block_append(cmdline_block_append_arguments: Vec<Value>) -> Res
     })
 }
 
+fn parse_tabline_tab(tab: Value) -> Result<TablineTab> {
+    let mut handle = None;
+    let mut name = None;
+    for (key, value) in parse_map(tab)? {
+        match parse_string(key)?.as_str() {
+            "tab" => handle = Some(parse_handle(value)?),
+            "name" => name = Some(parse_string(value)?),
+            _ => {}
+        }
+    }
+
+    Ok(TablineTab {
+        handle: handle.ok_or_else(|| ParseError::Format("Tab without a handle".to_string()))?,
+        name: name.unwrap_or_default(),
+    })
+}
+
+fn parse_tabline_update(tabline_update_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    // Newer versions also send the buffers, which aren't shown
+    let [current_tab, tabs] = extract_values(tabline_update_arguments)?;
+
+    Ok(RedrawEvent::TablineUpdate {
+        current_tab: parse_handle(current_tab)?,
+        tabs: parse_array(tabs)?
+            .into_iter()
+            .map(parse_tabline_tab)
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn parse_popupmenu_item(item: Value) -> Result<PopupMenuItem> {
+    let [word, kind, menu, info] = extract_values(parse_array(item)?)?;
+
+    Ok(PopupMenuItem {
+        word: parse_string(word)?,
+        kind: parse_string(kind)?,
+        menu: parse_string(menu)?,
+        info: parse_string(info)?,
+    })
+}
+
+/// Neovim uses -1 for "nothing", like no selected item or the command line grid.
+fn parse_optional_index(value: Value) -> Result<Option<u64>> {
+    let index = parse_i64(value)?;
+    Ok((index >= 0).then_some(index as u64))
+}
+
+fn parse_popupmenu_show(popupmenu_show_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [items, selected, row, column, grid] = extract_values(popupmenu_show_arguments)?;
+
+    Ok(RedrawEvent::PopupMenuShow {
+        items: parse_array(items)?
+            .into_iter()
+            .map(parse_popupmenu_item)
+            .collect::<Result<_>>()?,
+        selected: parse_optional_index(selected)?,
+        row: parse_u64(row)?,
+        column: parse_u64(column)?,
+        grid: parse_optional_index(grid)?,
+    })
+}
+
+fn parse_popupmenu_select(popupmenu_select_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [selected] = extract_values(popupmenu_select_arguments)?;
+
+    Ok(RedrawEvent::PopupMenuSelect {
+        selected: parse_optional_index(selected)?,
+    })
+}
+
+fn parse_wildmenu_show(wildmenu_show_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [items] = extract_values(wildmenu_show_arguments)?;
+
+    Ok(RedrawEvent::WildmenuShow {
+        items: parse_array(items)?
+            .into_iter()
+            .map(parse_string)
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn parse_wildmenu_select(wildmenu_select_arguments: Vec<Value>) -> Result<RedrawEvent> {
+    let [selected] = extract_values(wildmenu_select_arguments)?;
+
+    Ok(RedrawEvent::WildmenuSelect {
+        selected: parse_optional_index(selected)?,
+    })
+}
+
 fn parse_msg_show(msg_show_arguments: Vec<Value>) -> Result<RedrawEvent> {
     let [kind, content, replace_last] = extract_values(msg_show_arguments)?;
 
@@ -896,9 +1082,12 @@ pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
             "busy_start" => Some(Ok(RedrawEvent::BusyStart)),
             "busy_stop" => Some(Ok(RedrawEvent::BusyStop)),
             "flush" => Some(Ok(RedrawEvent::Flush)),
+            "bell" => Some(Ok(RedrawEvent::Bell { visual: false })),
+            "visual_bell" => Some(Ok(RedrawEvent::Bell { visual: true })),
             "grid_resize" => Some(parse_grid_resize(event_parameters)),
             "default_colors_set" => Some(parse_default_colors(event_parameters)),
             "hl_attr_define" => Some(parse_hl_attr_define(event_parameters)),
+            "hl_group_set" => Some(parse_hl_group_set(event_parameters)),
             "grid_line" => Some(parse_grid_line(event_parameters)),
             "grid_clear" => Some(parse_grid_clear(event_parameters)),
             "grid_destroy" => Some(parse_grid_destroy(event_parameters)),
@@ -915,10 +1104,17 @@ pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
             "cmdline_show" => Some(parse_cmdline_show(event_parameters)),
             "cmdline_pos" => Some(parse_cmdline_pos(event_parameters)),
             "cmdline_special_char" => Some(parse_cmdline_special_char(event_parameters)),
-            "cmdline_hide" => Some(Ok(RedrawEvent::CommandLineHide)),
+            "cmdline_hide" => Some(parse_cmdline_hide(event_parameters)),
             "cmdline_block_show" => Some(parse_cmdline_block_show(event_parameters)),
             "cmdline_block_append" => Some(parse_cmdline_block_append(event_parameters)),
             "cmdline_block_hide" => Some(Ok(RedrawEvent::CommandLineBlockHide)),
+            "tabline_update" => Some(parse_tabline_update(event_parameters)),
+            "popupmenu_show" => Some(parse_popupmenu_show(event_parameters)),
+            "popupmenu_select" => Some(parse_popupmenu_select(event_parameters)),
+            "popupmenu_hide" => Some(Ok(RedrawEvent::PopupMenuHide)),
+            "wildmenu_show" => Some(parse_wildmenu_show(event_parameters)),
+            "wildmenu_select" => Some(parse_wildmenu_select(event_parameters)),
+            "wildmenu_hide" => Some(Ok(RedrawEvent::WildmenuHide)),
             "msg_show" => Some(parse_msg_show(event_parameters)),
             "msg_clear" => Some(Ok(RedrawEvent::MessageClear)),
             "msg_showmode" => Some(parse_msg_showmode(event_parameters)),
@@ -944,3 +1140,64 @@ pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
 
     Ok(parsed_events)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_groups_are_parsed_from_hlstate() {
+        let info = |entries: &[(&str, &str)]| {
+            Value::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| (Value::from(*key), Value::from(*value)))
+                    .collect(),
+            )
+        };
+        let infos = Value::Array(vec![
+            info(&[("kind", "syntax"), ("hi_name", "Comment")]),
+            info(&[
+                ("kind", "ui"),
+                ("ui_name", "CursorLine"),
+                ("hi_name", "CursorLine"),
+            ]),
+            info(&[("kind", "terminal")]),
+        ]);
+        let style = parse_style(Value::Map(vec![]), infos).unwrap();
+        assert_eq!(style.highlight_groups, vec!["Comment", "CursorLine"]);
+        assert!(style.has_highlight_group("CursorLine"));
+        assert!(!style.has_highlight_group("Normal"));
+
+        let style = parse_style(Value::Map(vec![]), Value::Array(vec![])).unwrap();
+        assert!(style.highlight_groups.is_empty());
+    }
+
+    #[test]
+    fn window_handles_are_unpacked() {
+        let mut data = Vec::new();
+        rmpv::encode::write_value(&mut data, &Value::from(1001)).unwrap();
+        assert_eq!(parse_window_handle(Value::Ext(1, data)).unwrap(), 1001);
+        assert_eq!(parse_window_handle(Value::from(1002)).unwrap(), 1002);
+        assert!(parse_window_handle(Value::Ext(1, vec![])).is_err());
+
+        let arguments = vec![
+            Value::from(4),
+            Value::from(1003),
+            Value::from(1),
+            Value::from(2),
+            Value::from(80),
+            Value::from(20),
+        ];
+        assert!(matches!(
+            parse_win_pos(arguments).unwrap(),
+            RedrawEvent::WindowPosition {
+                grid: 4,
+                window: 1003,
+                width: 80,
+                height: 20,
+                ..
+            }
+        ));
+    }
+}
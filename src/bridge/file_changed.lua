@@ -0,0 +1,26 @@
+-- Answers the prompt for a file changed outside of Neovim
+local buffer, action = ...
+
+if not vim.api.nvim_buf_is_valid(buffer) then
+    return
+end
+
+if action == "reload" then
+    vim.api.nvim_buf_call(buffer, function()
+        vim.cmd("edit!")
+    end)
+elseif action == "diff" then
+    -- Compare the buffer with the file on disk, side by side in a new tab
+    local file = vim.api.nvim_buf_get_name(buffer)
+    vim.cmd("tab sbuffer " .. buffer)
+    vim.cmd("diffthis")
+    vim.cmd("vertical new")
+    local on_disk = vim.api.nvim_get_current_buf()
+    vim.bo[on_disk].buftype = "nofile"
+    vim.bo[on_disk].bufhidden = "wipe"
+    vim.bo[on_disk].swapfile = false
+    vim.api.nvim_buf_set_lines(on_disk, 0, -1, false, vim.fn.readfile(file))
+    vim.bo[on_disk].filetype = vim.bo[buffer].filetype
+    vim.bo[on_disk].modifiable = false
+    vim.cmd("diffthis")
+end
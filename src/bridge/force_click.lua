@@ -0,0 +1,61 @@
+-- Runs a force click of the trackpad on a word, a URL or a file. The click before it already
+-- moved the cursor, so getmousepos() tells where it was.
+local kind, text, action = ...
+
+local mouse = vim.fn.getmousepos()
+vim.api.nvim_exec_autocmds("User", {
+    pattern = "NeovideForceClick",
+    modeline = false,
+    data = {
+        kind = kind,
+        text = text,
+        winid = mouse.winid,
+        line = mouse.line,
+        column = mouse.column,
+    },
+})
+
+if action == "" then
+    return
+end
+
+if mouse.winid ~= 0 and vim.api.nvim_win_is_valid(mouse.winid) then
+    vim.api.nvim_set_current_win(mouse.winid)
+    if mouse.line > 0 then
+        vim.api.nvim_win_set_cursor(mouse.winid, { mouse.line, math.max(mouse.column - 1, 0) })
+    end
+end
+
+local has_lsp = #vim.lsp.get_clients({ bufnr = 0 }) > 0
+
+if action:sub(1, 1) == ":" then
+    vim.cmd(action:sub(2))
+elseif action == "hover" then
+    if has_lsp then
+        vim.lsp.buf.hover()
+    end
+elseif action == "definition" then
+    if has_lsp then
+        vim.lsp.buf.definition()
+    else
+        vim.cmd("normal! gd")
+    end
+elseif action == "open" then
+    if kind == "url" then
+        vim.ui.open(text)
+    else
+        -- Paths followed by a line number, like in the compiler errors
+        local path, line = text:match("^(.-):(%d+)")
+        path = vim.fn.expand(path or text)
+        if vim.fn.filereadable(path) == 1 then
+            vim.cmd.edit(vim.fn.fnameescape(path))
+            if line then
+                vim.api.nvim_win_set_cursor(0, { math.min(tonumber(line), vim.api.nvim_buf_line_count(0)), 0 })
+            end
+        elseif vim.fn.isdirectory(path) == 1 then
+            vim.ui.open(path)
+        end
+    end
+else
+    vim.notify("Unknown force click action " .. action, vim.log.levels.WARN)
+end
@@ -1,4 +1,7 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
 use log::trace;
@@ -9,11 +12,19 @@ use winit::event_loop::EventLoopProxy;
 
 use crate::{
     bridge::clipboard::{get_clipboard_contents, set_clipboard_contents},
+    bridge::clipboard_history::{clipboard_history_entries, record_clipboard_history},
     bridge::{events::parse_redraw_event, NeovimWriter, RedrawEvent},
+    editor::TitleInfo,
     error_handling::ResultPanicExplanation,
+    ipc::{buffer_closed, spawn_instance_from_neovim},
+    logging::handle_log_level_command,
+    renderer::{
+        file_changed::FileChange,
+        overlay::{queue_layer, OverlayPrimitive},
+    },
     running_tracker::RunningTracker,
-    settings::Settings,
-    window::{UserEvent, WindowCommand},
+    settings::{export_settings, import_settings, Settings},
+    window::{OsNotification, TaskbarProgress, UserEvent, WindowCommand},
     LoggingSender,
 };
 
@@ -58,14 +69,66 @@ impl Handler for NeovimHandler {
         match event_name.as_ref() {
             "neovide.get_clipboard" => get_clipboard_contents(&arguments[0])
                 .map_err(|_| Value::from("cannot get clipboard contents")),
-            "neovide.set_clipboard" => set_clipboard_contents(&arguments[0], &arguments[1])
-                .map_err(|_| Value::from("cannot set clipboard contents")),
+            "neovide.set_clipboard" => {
+                let result = set_clipboard_contents(&arguments[0], &arguments[1])
+                    .map_err(|_| Value::from("cannot set clipboard contents"));
+                if result.is_ok() {
+                    record_clipboard_history(&arguments[0], arguments.get(2), &self.settings);
+                }
+                result
+            }
+            "neovide.log_level" => {
+                let arguments = arguments
+                    .first()
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                handle_log_level_command(arguments)
+                    .map(Value::from)
+                    .map_err(Value::from)
+            }
+            "neovide.export_settings" => {
+                let file = arguments
+                    .first()
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                export_settings(&self.settings, Path::new(file))
+                    .map(|_| Value::Nil)
+                    .map_err(|error| Value::from(format!("{error:#}")))
+            }
+            "neovide.import_settings" => {
+                let file = arguments
+                    .first()
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                import_settings(&self.settings, Path::new(file))
+                    .map(|values| {
+                        Value::Map(
+                            values
+                                .into_iter()
+                                .map(|(name, value)| (Value::from(name), value))
+                                .collect(),
+                        )
+                    })
+                    .map_err(|error| Value::from(format!("{error:#}")))
+            }
             "neovide.quit" => {
                 let error_code = arguments[0]
                     .as_i64()
                     .expect("Could not parse error code from neovim");
                 self.running_tracker
                     .quit_with_code(error_code as u8, "Quit from neovim");
+                let cwd = arguments.get(1).and_then(Value::as_str).map(str::to_owned);
+                self.running_tracker.set_cwd(cwd);
+                Ok(Value::Nil)
+            }
+            "neovide.restart" => {
+                let restart = arguments.first().and_then(Value::as_bool).unwrap_or(true);
+                self.running_tracker.set_restart_requested(restart);
+                Ok(Value::Nil)
+            }
+            "neovide.toggle_multigrid" => {
+                let toggle = arguments.first().and_then(Value::as_bool).unwrap_or(true);
+                self.running_tracker.set_multigrid_toggle_requested(toggle);
                 Ok(Value::Nil)
             }
             _ => Ok(Value::from("rpcrequest not handled")),
@@ -115,6 +178,145 @@ impl Handler for NeovimHandler {
                     .unwrap()
                     .send_event(WindowCommand::UnregisterRightClick.into());
             }
+            #[cfg(windows)]
+            "neovide.register_url_handler" => {
+                let _ = self
+                    .proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(WindowCommand::RegisterUrlHandler.into());
+            }
+            #[cfg(windows)]
+            "neovide.unregister_url_handler" => {
+                let _ = self
+                    .proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(WindowCommand::UnregisterUrlHandler.into());
+            }
+            "neovide.buffers_changed" => {
+                let files = arguments
+                    .first()
+                    .and_then(Value::as_array)
+                    .map(|files| {
+                        files
+                            .iter()
+                            .filter_map(|file| file.as_str().map(str::to_owned))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                self.running_tracker.set_open_files(files);
+            }
+            "neovide.clipboard_yank" => {
+                if let Some(lines) = arguments.first() {
+                    record_clipboard_history(lines, arguments.get(1), &self.settings);
+                }
+            }
+            "neovide.terminal_windows" => {
+                let windows = arguments
+                    .first()
+                    .and_then(Value::as_array)
+                    .map(|windows| windows.iter().filter_map(Value::as_u64).collect())
+                    .unwrap_or_default();
+                let _ = self.sender.send(RedrawEvent::TerminalWindows { windows });
+            }
+            "neovide.title_info" => {
+                if let [file, cwd, modified] = arguments.as_slice() {
+                    let info = TitleInfo {
+                        file: file.as_str().unwrap_or_default().to_string(),
+                        cwd: cwd.as_str().unwrap_or_default().to_string(),
+                        modified: modified.as_bool().unwrap_or_default(),
+                    };
+                    let _ = self.sender.send(RedrawEvent::TitleInfo { info });
+                }
+            }
+            "neovide.represented_file" => {
+                let file = arguments
+                    .first()
+                    .and_then(Value::as_str)
+                    .filter(|file| !file.is_empty())
+                    .map(PathBuf::from);
+                let _ = self
+                    .proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(WindowCommand::RepresentedFileChanged(file).into());
+            }
+            "neovide.help_buffer" => {
+                let help_buffer = arguments.first().and_then(Value::as_bool);
+                if let Some(help_buffer) = help_buffer {
+                    let _ = self
+                        .proxy
+                        .lock()
+                        .unwrap()
+                        .send_event(WindowCommand::HelpBufferChanged(help_buffer).into());
+                }
+            }
+            "neovide.file_changed" => {
+                if let [buffer, file, reason] = arguments.as_slice() {
+                    let (Some(buffer), Some(file), Some(reason)) =
+                        (buffer.as_u64(), file.as_str(), reason.as_str())
+                    else {
+                        return;
+                    };
+                    let change = FileChange {
+                        buffer,
+                        file: file.to_string(),
+                        reason: reason.to_string(),
+                    };
+                    let _ = self
+                        .proxy
+                        .lock()
+                        .unwrap()
+                        .send_event(WindowCommand::FileChanged(change).into());
+                }
+            }
+            "neovide.tour" => {
+                let _ = self
+                    .proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(WindowCommand::ShowTour.into());
+            }
+            "neovide.help" => {
+                let topic = arguments
+                    .first()
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let _ = self
+                    .proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(WindowCommand::ShowHelp(topic).into());
+            }
+            "neovide.clipboard_history" => {
+                let query = arguments
+                    .first()
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let entries = clipboard_history_entries(&self.settings);
+                let _ = self
+                    .proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(WindowCommand::ShowClipboardHistory { entries, query }.into());
+            }
+            "neovide.info" => {
+                let _ = self
+                    .proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(WindowCommand::ShowInfo.into());
+            }
+            "neovide.toggle_pip" => {
+                let _ = self
+                    .proxy
+                    .lock()
+                    .unwrap()
+                    .send_event(WindowCommand::TogglePictureInPicture.into());
+            }
             "neovide.focus_window" => {
                 let _ = self
                     .proxy
@@ -122,6 +324,63 @@ impl Handler for NeovimHandler {
                     .unwrap()
                     .send_event(WindowCommand::FocusWindow.into());
             }
+            "neovide.buffer_closed" => {
+                if let Some(id) = arguments.first().and_then(Value::as_u64) {
+                    buffer_closed(id);
+                }
+            }
+            "neovide.new_instance" => {
+                if let Some(params) = arguments.into_iter().next() {
+                    spawn_instance_from_neovim(params);
+                }
+            }
+            "neovide.draw" => {
+                if let [layer, primitives] = arguments.as_slice() {
+                    let (Some(layer), Some(primitives)) = (layer.as_str(), primitives.as_array())
+                    else {
+                        return;
+                    };
+                    let primitives = primitives
+                        .iter()
+                        .filter_map(OverlayPrimitive::parse)
+                        .collect();
+                    if queue_layer(layer.to_string(), primitives) {
+                        let _ = self
+                            .proxy
+                            .lock()
+                            .unwrap()
+                            .send_event(WindowCommand::DrawOverlays.into());
+                    }
+                }
+            }
+            "neovide.progress" => {
+                let progress = arguments.first().unwrap_or(&Value::Nil);
+                if let Some(progress) = TaskbarProgress::parse(progress) {
+                    let _ = self
+                        .proxy
+                        .lock()
+                        .unwrap()
+                        .send_event(WindowCommand::ShowProgressBar(progress).into());
+                }
+            }
+            "neovide.notify" => {
+                if let Some(notification) = OsNotification::from_arguments(&arguments) {
+                    let _ = self
+                        .proxy
+                        .lock()
+                        .unwrap()
+                        .send_event(WindowCommand::OsNotify(notification).into());
+                }
+            }
+            "neovide.os_notify" => {
+                if let Some(notification) = arguments.first().and_then(OsNotification::parse) {
+                    let _ = self
+                        .proxy
+                        .lock()
+                        .unwrap()
+                        .send_event(WindowCommand::OsNotify(notification).into());
+                }
+            }
             _ => {}
         }
     }
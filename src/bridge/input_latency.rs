@@ -0,0 +1,70 @@
+//! How long Neovim takes to acknowledge the keys, which only matters for the remote `--server`
+//! sessions. Holding a key there sends repeats faster than they come back, so that the grid lags
+//! further and further behind, and the repeats are paced by the round trip instead.
+
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// Connections faster than this are left alone, like a server on the same machine.
+const LOW_LATENCY: Duration = Duration::from_millis(30);
+/// How many keys can wait for Neovim before the next repeat is dropped.
+const MAX_PENDING_REPEATS: usize = 2;
+/// The weight of the latest round trip in the average.
+const SMOOTHING: f64 = 0.2;
+
+static PENDING_KEYS: AtomicUsize = AtomicUsize::new(0);
+static ROUND_TRIP_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// A key was sent to the serial commands.
+pub fn key_queued() {
+    PENDING_KEYS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Neovim acknowledged a key, `elapsed` after it was sent to it.
+pub fn key_acknowledged(elapsed: Duration) {
+    let _ = PENDING_KEYS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |pending| {
+        Some(pending.saturating_sub(1))
+    });
+    let _ = ROUND_TRIP_MICROS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+        let elapsed = elapsed.as_micros() as f64;
+        Some(if average == 0 {
+            elapsed as u64
+        } else {
+            (average as f64 * (1.0 - SMOOTHING) + elapsed * SMOOTHING) as u64
+        })
+    });
+}
+
+/// Forgets the keys of the previous Neovim when it's restarted.
+pub fn reset() {
+    PENDING_KEYS.store(0, Ordering::Relaxed);
+    ROUND_TRIP_MICROS.store(0, Ordering::Relaxed);
+}
+
+fn should_send_repeat_with(pending: usize, round_trip: Duration) -> bool {
+    round_trip < LOW_LATENCY || pending < MAX_PENDING_REPEATS
+}
+
+/// Whether a repeated key should be sent now, or dropped to let Neovim catch up.
+pub fn should_send_repeat() -> bool {
+    should_send_repeat_with(
+        PENDING_KEYS.load(Ordering::Relaxed),
+        Duration::from_micros(ROUND_TRIP_MICROS.load(Ordering::Relaxed)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_wait_for_slow_connections() {
+        let slow = Duration::from_millis(120);
+        assert!(should_send_repeat_with(0, slow));
+        assert!(should_send_repeat_with(1, slow));
+        assert!(!should_send_repeat_with(2, slow));
+        assert!(should_send_repeat_with(10, Duration::from_millis(5)));
+    }
+}
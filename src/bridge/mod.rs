@@ -1,8 +1,10 @@
 mod api_info;
 mod clipboard;
+mod clipboard_history;
 mod command;
 mod events;
 mod handler;
+pub mod input_latency;
 pub mod session;
 mod setup;
 mod ui_commands;
@@ -22,17 +24,28 @@ use tokio::{
 use winit::event_loop::EventLoopProxy;
 
 use crate::{
-    cmd_line::CmdLineSettings, editor::start_editor, running_tracker::RunningTracker, settings::*,
-    units::GridSize, window::UserEvent,
+    cmd_line::CmdLineSettings,
+    editor::start_editor,
+    renderer::{
+        cmdline_renderer::CommandLineSettings, messages_renderer::MessagesSettings,
+        popupmenu::PopupMenuSettings, tabline::TablineSettings, wildmenu::WildmenuSettings,
+    },
+    running_tracker::RunningTracker,
+    settings::*,
+    units::GridSize,
+    window::UserEvent,
 };
 pub use handler::NeovimHandler;
 use session::{NeovimInstance, NeovimSession};
 use setup::{get_api_information, setup_neovide_specific_state};
 
+pub use clipboard_history::{ClipboardSettings, ClipboardSettingsChanged};
 pub use command::create_nvim_command;
 pub use events::*;
 pub use session::NeovimWriter;
-pub use ui_commands::{send_ui, start_ui_command_handler, ParallelCommand, SerialCommand};
+pub use ui_commands::{
+    send_ui, start_ui_command_handler, ParallelCommand, ReplySender, SerialCommand,
+};
 
 const NEOVIM_REQUIRED_VERSION: &str = "0.10.0";
 
@@ -77,6 +90,7 @@ async fn launch(
     handler: NeovimHandler,
     grid_size: Option<GridSize<u32>>,
     settings: Arc<Settings>,
+    cwd: Option<String>,
 ) -> Result<NeovimSession> {
     let neovim_instance = neovim_instance(settings.as_ref())?;
 
@@ -122,6 +136,12 @@ async fn launch(
     options.set_linegrid_external(true);
     options.set_multigrid_external(!cmdline_settings.no_multi_grid);
     options.set_rgb(true);
+    options.set_hlstate_external(true);
+    options.set_cmdline_external(settings.get::<CommandLineSettings>().enabled);
+    options.set_messages_external(settings.get::<MessagesSettings>().enabled);
+    options.set_popupmenu_external(settings.get::<PopupMenuSettings>().enabled);
+    options.set_tabline_external(settings.get::<TablineSettings>().enabled);
+    options.set_wildmenu_external(settings.get::<WildmenuSettings>().enabled);
 
     // Triggers loading the user config
 
@@ -133,12 +153,18 @@ async fn launch(
         .context("Could not attach ui to neovim process");
 
     info!("Neovim process attached");
-    res.map(|()| session)
-}
+    res?;
 
-async fn run(session: NeovimSession, proxy: EventLoopProxy<UserEvent>) {
-    let mut session = session;
+    // A restarted Neovim continues in the directory of the previous one
+    if let Some(cwd) = cwd {
+        if let Err(error) = session.neovim.set_current_dir(&cwd).await {
+            log::error!("Could not change the directory to {cwd}: {error}");
+        }
+    }
+    Ok(session)
+}
 
+async fn wait_for_exit(session: &mut NeovimSession) {
     if let Some(process) = session.neovim_process.as_mut() {
         // We primarily wait for the stdio to finish, but due to bugs,
         // for example, this one in in Neovim 0.9.5
@@ -161,8 +187,89 @@ async fn run(session: NeovimSession, proxy: EventLoopProxy<UserEvent>) {
             }
         };
     } else {
-        session.io_handle.await.ok();
+        (&mut session.io_handle).await.ok();
+    }
+}
+
+/// Opens the files of the previous instance in a restarted one.
+async fn reopen_files(nvim: &Neovim<NeovimWriter>, files: Vec<String>) {
+    if files.is_empty() {
+        return;
+    }
+    let files = Value::Array(files.into_iter().map(Value::from).collect());
+    if let Err(error) = nvim
+        .exec_lua(include_str!("reopen_files.lua"), vec![files])
+        .await
+    {
+        log::error!("Could not reopen the files after restarting: {error}");
+    }
+}
+
+/// Switches between multigrid and a single grid for the next Neovim instance.
+fn toggle_multigrid_setting(settings: &Settings) {
+    let mut cmdline_settings = settings.get::<CmdLineSettings>();
+    cmdline_settings.no_multi_grid = !cmdline_settings.no_multi_grid;
+    log::info!("Multigrid enabled: {}", !cmdline_settings.no_multi_grid);
+    settings.set(&cmdline_settings);
+}
+
+async fn run(
+    session: NeovimSession,
+    proxy: EventLoopProxy<UserEvent>,
+    running_tracker: RunningTracker,
+    settings: Arc<Settings>,
+) {
+    let mut session = session;
+
+    loop {
+        wait_for_exit(&mut session).await;
+        let quit_requested = running_tracker.take_quit_request();
+        let embedded = session.neovim_process.is_some();
+        let mut restart = running_tracker.take_restart_request();
+        if !restart && embedded {
+            // An embedded Neovim always asks to quit through the VimLeavePre autocommand, so
+            // exiting without it means that it crashed
+            if !quit_requested {
+                running_tracker.mark_crashed();
+                break;
+            }
+            // Quitting with an error, like with `:cquit` or a broken config, leaves the window
+            // open until the user chooses to restart Neovim or to close it
+            if let Some(exit_code) = running_tracker.failed_exit_code() {
+                proxy.send_event(UserEvent::NeovimFailed(exit_code)).ok();
+                restart = running_tracker.wait_for_exit_prompt().await;
+            }
+        }
+        if !restart {
+            break;
+        }
+
+        // Switching between multigrid and a single grid needs a new instance, which gets the
+        // files of the previous one
+        let toggle_multigrid = running_tracker.take_multigrid_toggle_request();
+        let open_files = running_tracker.open_files();
+        if toggle_multigrid {
+            toggle_multigrid_setting(&settings);
+        }
+
+        // Keep the window and start a fresh embedded Neovim in it
+        log::info!("Restarting Neovim");
+        proxy.send_event(UserEvent::NeovimRestarted).ok();
+        let handler = start_editor(proxy.clone(), running_tracker.clone(), settings.clone());
+        match launch(handler, None, settings.clone(), running_tracker.cwd()).await {
+            Ok(new_session) => {
+                if toggle_multigrid {
+                    reopen_files(&new_session.neovim, open_files).await;
+                }
+                session = new_session;
+            }
+            Err(error) => {
+                log::error!("Failed to restart Neovim: {error:?}");
+                break;
+            }
+        }
     }
+
     log::info!("Neovim has quit");
     proxy.send_event(UserEvent::NeovimExited).ok();
 }
@@ -181,11 +288,35 @@ impl NeovimRuntime {
         running_tracker: RunningTracker,
         settings: Arc<Settings>,
     ) -> Result<()> {
-        let handler = start_editor(event_loop_proxy.clone(), running_tracker, settings.clone());
+        let handler = start_editor(
+            event_loop_proxy.clone(),
+            running_tracker.clone(),
+            settings.clone(),
+        );
         let session = self
             .runtime
-            .block_on(launch(handler, grid_size, settings))?;
-        self.runtime.spawn(run(session, event_loop_proxy));
+            .block_on(launch(handler, grid_size, settings.clone(), None))?;
+        self.runtime
+            .spawn(run(session, event_loop_proxy, running_tracker, settings));
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn multigrid_toggles_back_and_forth() {
+        let settings = Settings::new();
+        settings.set(&CmdLineSettings::parse_from(["neovide"]));
+        let multigrid = !settings.get::<CmdLineSettings>().no_multi_grid;
+
+        toggle_multigrid_setting(&settings);
+        assert_eq!(!settings.get::<CmdLineSettings>().no_multi_grid, !multigrid);
+        toggle_multigrid_setting(&settings);
+        assert_eq!(!settings.get::<CmdLineSettings>().no_multi_grid, multigrid);
+    }
+}
@@ -0,0 +1,10 @@
+local files = ...
+
+for _, file in ipairs(files) do
+    vim.cmd.badd(vim.fn.fnameescape(file))
+end
+
+-- Show the first file unless the arguments of Neovim already opened one
+if vim.api.nvim_buf_get_name(0) == "" then
+    vim.cmd.buffer(vim.fn.bufnr(files[1]))
+end
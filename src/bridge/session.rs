@@ -8,16 +8,20 @@ use std::{
     process::Stdio,
 };
 
+use futures::{SinkExt, StreamExt};
 use nvim_rs::{error::LoopError, neovim::Neovim, Handler};
 use tokio::{
-    io::{split, AsyncRead, AsyncWrite},
+    io::{duplex, split, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::TcpStream,
     process::{Child, Command},
     spawn,
     task::JoinHandle,
 };
+use tokio_tungstenite::{connect_async, tungstenite::Message};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
+const WEBSOCKET_BUFFER_SIZE: usize = 64 * 1024;
+
 pub type NeovimWriter = Box<dyn futures::AsyncWrite + Send + Unpin + 'static>;
 
 type BoxedReader = Box<dyn AsyncRead + Send + Unpin + 'static>;
@@ -67,6 +71,9 @@ pub enum NeovimInstance {
     /// Interprets `address` in the same way as `:help --server`: If it contains a `:` it's
     /// interpreted as a TCP/IPv4/IPv6 address. Otherwise it's interpreted as a named pipe or Unix
     /// domain socket path. Spawns and connects to an embedded Neovim instance.
+    ///
+    /// Addresses starting with `ws://` or `wss://` are connected to over a WebSocket, with every
+    /// msgpack chunk sent as a binary message.
     Server { address: String },
 }
 
@@ -99,7 +106,9 @@ impl NeovimInstance {
     }
 
     async fn connect_to_server(address: String) -> Result<(BoxedReader, BoxedWriter)> {
-        if address.contains(':') {
+        if address.starts_with("ws://") || address.starts_with("wss://") {
+            Self::connect_to_websocket(&address).await
+        } else if address.contains(':') {
             Ok(Self::split(TcpStream::connect(address).await?))
         } else {
             #[cfg(unix)]
@@ -126,6 +135,55 @@ impl NeovimInstance {
         }
     }
 
+    async fn connect_to_websocket(address: &str) -> Result<(BoxedReader, BoxedWriter)> {
+        let (stream, _) = connect_async(address)
+            .await
+            .map_err(|error| Error::new(ErrorKind::ConnectionRefused, error))?;
+        let (mut ws_writer, mut ws_reader) = stream.split();
+
+        // The rpc session expects a byte stream, so pump the WebSocket messages through an
+        // in-memory pipe.
+        let (local, remote) = duplex(WEBSOCKET_BUFFER_SIZE);
+        let (mut remote_reader, mut remote_writer) = split(remote);
+
+        spawn(async move {
+            while let Some(message) = ws_reader.next().await {
+                match message {
+                    Ok(Message::Binary(data)) => {
+                        if remote_writer.write_all(&data).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Message::Close(..)) => break,
+                    Ok(..) => {}
+                    Err(error) => {
+                        log::error!("WebSocket connection failed: {error}");
+                        break;
+                    }
+                }
+            }
+            let _ = remote_writer.shutdown().await;
+        });
+
+        spawn(async move {
+            let mut buffer = vec![0; WEBSOCKET_BUFFER_SIZE];
+            loop {
+                match remote_reader.read(&mut buffer).await {
+                    Ok(0) | Err(..) => break,
+                    Ok(len) => {
+                        let message = Message::Binary(buffer[..len].to_vec());
+                        if ws_writer.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            let _ = ws_writer.close().await;
+        });
+
+        Ok(Self::split(local))
+    }
+
     fn split(
         stream: impl AsyncRead + AsyncWrite + Send + Unpin + 'static,
     ) -> (BoxedReader, BoxedWriter) {
@@ -5,6 +5,7 @@ use rmpv::Value;
 use super::api_info::{parse_api_info, ApiInformation};
 use crate::{
     bridge::NeovimWriter,
+    cmd_line::CmdLineSettings,
     settings::{SettingLocation, Settings},
 };
 
@@ -61,6 +62,8 @@ pub async fn setup_neovide_specific_state(
 
     let register_clipboard = should_handle_clipboard;
     let register_right_click = cfg!(target_os = "windows");
+    // Only an embedded Neovim can be started again
+    let register_restart = settings.get::<CmdLineSettings>().server.is_none();
 
     let setting_locations = settings.setting_locations();
     let global_variable_settings = setting_locations
@@ -95,6 +98,10 @@ pub async fn setup_neovide_specific_state(
             Value::from("register_right_click"),
             Value::from(register_right_click),
         ),
+        (
+            Value::from("register_restart"),
+            Value::from(register_restart),
+        ),
         (
             Value::from("global_variable_settings"),
             Value::from(global_variable_settings),
@@ -108,3 +115,84 @@ pub async fn setup_neovide_specific_state(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use nvim_rs::{Handler, UiAttachOptions};
+
+    use super::*;
+    use crate::{
+        bridge::{
+            create_nvim_command,
+            session::{NeovimInstance, NeovimSession},
+        },
+        error_handling::ResultPanicExplanation,
+    };
+
+    #[derive(Clone)]
+    struct NeovimHandler();
+
+    #[async_trait]
+    impl Handler for NeovimHandler {
+        type Writer = NeovimWriter;
+
+        async fn handle_notify(
+            &self,
+            _event_name: String,
+            _arguments: Vec<Value>,
+            _neovim: Neovim<NeovimWriter>,
+        ) {
+        }
+    }
+
+    async fn clipboard_name(nvim: &Neovim<NeovimWriter>) -> Value {
+        nvim.exec_lua("return vim.g.clipboard and vim.g.clipboard.name", vec![])
+            .await
+            .expect("Could not read the clipboard provider")
+    }
+
+    #[tokio::test]
+    async fn server_gets_its_clipboard_back_on_detach() {
+        let settings = Settings::new();
+        settings.set::<CmdLineSettings>(&CmdLineSettings::default());
+
+        let command = create_nvim_command(&settings)
+            .unwrap_or_explained_panic("Could not create nvim command");
+        let instance = NeovimInstance::Embedded(command);
+        let NeovimSession { neovim: nvim, .. } = NeovimSession::new(instance, NeovimHandler())
+            .await
+            .unwrap_or_explained_panic("Could not locate or start the neovim process");
+
+        // The provider the server had before Neovide attached
+        nvim.exec_lua(
+            r#"vim.g.clipboard = {
+                name = "server",
+                copy = { ["+"] = "cat", ["*"] = "cat" },
+                paste = { ["+"] = "cat", ["*"] = "cat" },
+            }"#,
+            vec![],
+        )
+        .await
+        .expect("Could not set the clipboard provider");
+
+        let api_information = get_api_information(&nvim)
+            .await
+            .expect("Could not get the API information");
+        // Setting up again, like after attaching a second time, still remembers the first provider
+        for _ in 0..2 {
+            setup_neovide_specific_state(&nvim, true, &api_information, &settings)
+                .await
+                .expect("Could not set up Neovim");
+        }
+        assert_eq!(clipboard_name(&nvim).await, Value::from("neovide"));
+
+        let mut options = UiAttachOptions::new();
+        options.set_linegrid_external(true);
+        nvim.ui_attach(80, 24, &options)
+            .await
+            .expect("Could not attach the UI");
+        nvim.ui_detach().await.expect("Could not detach the UI");
+        assert_eq!(clipboard_name(&nvim).await, Value::from("server"));
+    }
+}
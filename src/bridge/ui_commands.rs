@@ -1,17 +1,19 @@
-use std::sync::{Arc, OnceLock};
+use std::{sync::Arc, time::Instant};
 
 use log::trace;
+use parking_lot::RwLock;
 
 use anyhow::{Context, Result};
 use nvim_rs::{call_args, error::CallError, rpc::model::IntoVal, Neovim, Value};
 use strum::AsRefStr;
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 
 use super::{show_error_message, Settings};
 use crate::{
-    bridge::NeovimWriter,
+    bridge::{input_latency, NeovimWriter},
     cmd_line::CmdLineSettings,
     profiling::{tracy_dynamic_zone, tracy_fiber_enter, tracy_fiber_leave},
+    window::{ForceClickKind, MouseSettings},
     LoggingSender,
 };
 
@@ -53,10 +55,10 @@ impl SerialCommand {
         let result = match self {
             SerialCommand::Keyboard(input_command) => {
                 trace!("Keyboard Input Sent: {}", input_command);
-                nvim.input(&input_command)
-                    .await
-                    .map(|_| ())
-                    .context("Input failed")
+                let start = Instant::now();
+                let result = nvim.input(&input_command).await;
+                input_latency::key_acknowledged(start.elapsed());
+                result.map(|_| ()).context("Input failed")
             }
             SerialCommand::MouseButton {
                 button,
@@ -119,14 +121,42 @@ impl SerialCommand {
 pub enum ParallelCommand {
     Quit,
     Resize { width: u64, height: u64 },
+    ResizeGrid { grid: u64, width: u64, height: u64 },
     FileDrop(String),
+    // Inserts text at a `(row, column)` cell of the screen grid, or at the cursor without one.
+    TextDrop(String, Option<(u64, u64)>),
+    // Pastes text at the cursor with `nvim_paste`, which also sends it to the job of a terminal.
+    Paste(String),
+    // Puts text after the cursor like `p`, linewise when it ends with a line break.
+    Put(String),
+    // Answers the prompt for a file changed outside of Neovim, with `reload` or `diff`.
+    ResolveFileChange { buffer: u64, action: String },
+    // A force click of the trackpad on a word, an URL or a file, running the action configured
+    // for its kind after the `NeovideForceClick` autocommands of the plugins.
+    ForceClick { kind: ForceClickKind, text: String },
+    // Notifies `neovide.buffer_closed` with `id` once the buffer of `path` is closed, for
+    // `--wait`.
+    WaitForBuffer { path: String, id: u64 },
     FocusLost,
     FocusGained,
     DisplayAvailableFonts(Vec<String>),
     SetBackground(String),
+    SetUiOption { name: String, value: bool },
+    // Sets `g:neovide_*` variables, named without the prefix. Neovim notifies the changes back
+    // like any other, which keeps both sides in sync.
+    SetSettings(Vec<(String, Value)>),
+    Command(String),
+    // Runs a command for another process, and replies with its output.
+    CommandOutput { command: String, reply: ReplySender },
+    // Evaluates a Vim expression for another process, and replies with its value.
+    Eval { expr: String, reply: ReplySender },
     ShowError { lines: Vec<String> },
+    ShowInfo { lines: Vec<String> },
 }
 
+/// Where the result of a command run for another process is sent.
+pub type ReplySender = UnboundedSender<Result<Value, String>>;
+
 async fn display_available_fonts(
     nvim: &Neovim<NeovimWriter>,
     fonts: Vec<String>,
@@ -196,6 +226,14 @@ impl ParallelCommand {
                 .ui_try_resize(width.max(10) as i64, height.max(3) as i64)
                 .await
                 .context("Resize failed"),
+            ParallelCommand::ResizeGrid {
+                grid,
+                width,
+                height,
+            } => nvim
+                .ui_try_resize_grid(grid as i64, width as i64, height as i64)
+                .await
+                .context("ResizeGrid failed"),
             ParallelCommand::FocusLost => {
                 nvim.ui_set_focus(false).await.context("FocusLost failed")
             }
@@ -220,14 +258,108 @@ impl ParallelCommand {
                 .await
                 .map(|_| ()) // We don't care about the result
                 .context("FileDrop failed"),
+            ParallelCommand::TextDrop(text, position) => {
+                let (row, column) = position.map_or((Value::Nil, Value::Nil), |(row, column)| {
+                    (row.into(), column.into())
+                });
+                nvim.exec_lua(
+                    include_str!("drop_text.lua"),
+                    vec![text.into(), row, column],
+                )
+                .await
+                .map(|_| ())
+                .context("TextDrop failed")
+            }
+            ParallelCommand::Paste(text) => nvim
+                .paste(&text, true, -1)
+                .await
+                .map(|_| ())
+                .context("Paste failed"),
+            ParallelCommand::Put(text) => nvim
+                .put(
+                    text.split('\n').map(str::to_string).collect(),
+                    "",
+                    true,
+                    true,
+                )
+                .await
+                .context("Put failed"),
+            ParallelCommand::ResolveFileChange { buffer, action } => nvim
+                .exec_lua(
+                    include_str!("file_changed.lua"),
+                    vec![buffer.into(), action.into()],
+                )
+                .await
+                .map(|_| ())
+                .context("ResolveFileChange failed"),
+            ParallelCommand::ForceClick { kind, text } => {
+                let action = settings
+                    .get::<MouseSettings>()
+                    .force_click_action(kind)
+                    .to_string();
+                nvim.exec_lua(
+                    include_str!("force_click.lua"),
+                    vec![kind.as_str().into(), text.into(), action.into()],
+                )
+                .await
+                .map(|_| ())
+                .context("ForceClick failed")
+            }
+            ParallelCommand::WaitForBuffer { path, id } => nvim
+                .exec_lua(
+                    include_str!("wait_for_buffer.lua"),
+                    vec![path.into(), id.into()],
+                )
+                .await
+                .map(|_| ())
+                .context("WaitForBuffer failed"),
             ParallelCommand::SetBackground(background) => nvim
                 .command(format!("set background={background}").as_str())
                 .await
                 .context("SetBackground failed"),
+            ParallelCommand::SetUiOption { name, value } => nvim
+                .ui_set_option(&name, Value::Boolean(value))
+                .await
+                .context("SetUiOption failed"),
+            ParallelCommand::SetSettings(values) => {
+                for (name, value) in values {
+                    nvim.set_var(&format!("neovide_{name}"), value)
+                        .await
+                        .with_context(|| format!("Could not set neovide_{name}"))?;
+                }
+                Ok(())
+            }
+            ParallelCommand::Command(command) => {
+                nvim.command(&command).await.context("Command failed")
+            }
+            ParallelCommand::CommandOutput { command, reply } => {
+                let result = nvim
+                    .exec_lua(
+                        "return vim.api.nvim_exec2(..., { output = true }).output",
+                        vec![command.into()],
+                    )
+                    .await
+                    .map_err(|error| error.to_string());
+                let _ = reply.send(result);
+                Ok(())
+            }
+            ParallelCommand::Eval { expr, reply } => {
+                let result = nvim.eval(&expr).await.map_err(|error| error.to_string());
+                let _ = reply.send(result);
+                Ok(())
+            }
             ParallelCommand::DisplayAvailableFonts(fonts) => display_available_fonts(nvim, fonts)
                 .await
                 .context("DisplayAvailableFonts failed"),
 
+            ParallelCommand::ShowInfo { lines } => nvim
+                .echo(
+                    vec![Value::Array(vec![lines.join("\n").into()])],
+                    true,
+                    vec![],
+                )
+                .await
+                .context("ShowInfo failed"),
             ParallelCommand::ShowError { lines } => {
                 // nvim.err_write(&message).await.ok();
                 // NOTE: https://github.com/neovim/neovim/issues/5067
@@ -272,15 +404,15 @@ impl AsRef<str> for UiCommand {
     }
 }
 
-static UI_COMMAND_CHANNEL: OnceLock<LoggingSender<UiCommand>> = OnceLock::new();
+// Replaced when Neovim is restarted, which stops the handlers of the previous instance.
+static UI_COMMAND_CHANNEL: RwLock<Option<LoggingSender<UiCommand>>> = RwLock::new(None);
 
 pub fn start_ui_command_handler(nvim: Neovim<NeovimWriter>, settings: Arc<Settings>) {
     let (serial_tx, mut serial_rx) = unbounded_channel::<SerialCommand>();
     let ui_command_nvim = nvim.clone();
     let (sender, mut ui_command_receiver) = unbounded_channel();
-    UI_COMMAND_CHANNEL
-        .set(LoggingSender::attach(sender, "UIComand"))
-        .expect("The UI command channel is already created");
+    *UI_COMMAND_CHANNEL.write() = Some(LoggingSender::attach(sender, "UIComand"));
+    input_latency::reset();
     tokio::spawn(async move {
         loop {
             match ui_command_receiver.recv().await {
@@ -330,8 +462,12 @@ where
     T: Into<UiCommand>,
 {
     let command: UiCommand = command.into();
+    if let UiCommand::Serial(SerialCommand::Keyboard(_)) = &command {
+        input_latency::key_queued();
+    }
     let _ = UI_COMMAND_CHANNEL
-        .get()
+        .read()
+        .as_ref()
         .expect("The UI command channel has not been initialized")
         .send(command);
 }
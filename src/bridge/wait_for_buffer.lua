@@ -0,0 +1,12 @@
+-- Tells Neovide once a file opened with `--wait` is closed, so that the process waiting for it
+-- can return. The buffer may not be shown yet, the file is opened by another command.
+local path, id = ...
+
+local buffer = vim.fn.bufadd(vim.fn.fnamemodify(path, ":p"))
+vim.api.nvim_create_autocmd({ "BufHidden", "BufUnload" }, {
+    buffer = buffer,
+    once = true,
+    callback = function()
+        vim.rpcnotify(vim.g.neovide_channel_id, "neovide.buffer_closed", id)
+    end,
+})
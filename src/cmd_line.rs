@@ -1,11 +1,14 @@
-use std::{iter, mem};
+use std::{fs, iter, path::Path};
 
-use crate::{dimensions::Dimensions, frame::Frame, settings::*};
+use crate::{
+    dimensions::Dimensions, frame::Frame, renderer::frame_diff::compare_frames, settings::*,
+};
 
 use anyhow::Result;
 use clap::{
     builder::{styling, FalseyValueParser, Styles},
-    ArgAction, Parser,
+    parser::ValueSource,
+    ArgAction, ArgMatches, CommandFactory, Parser, Subcommand, ValueEnum,
 };
 use winit::window::CursorIcon;
 #[cfg(target_os = "windows")]
@@ -24,8 +27,18 @@ fn get_styles() -> Styles {
         .placeholder(styling::AnsiColor::Cyan.on_default())
 }
 
+// Every option can also be set with an environment variable, for the desktop files and the
+// launchers that can't pass arguments. The command line takes precedence over the config file,
+// which takes precedence over the environment. The negations like `--no-vsync` share the variable
+// of the option they negate, and the options printing something and exiting don't have one.
 #[derive(Clone, Debug, Parser)]
-#[command(version, about, long_about = None, styles = get_styles())]
+#[command(
+    version,
+    about,
+    long_about = None,
+    styles = get_styles(),
+    after_help = "Run `neovide remote --help` to control the Neovide already running."
+)]
 pub struct CmdLineSettings {
     /// Files to open (usually plainly appended to NeoVim args, except when --wsl is used)
     #[arg(
@@ -44,11 +57,16 @@ pub struct CmdLineSettings {
     pub neovim_args: Vec<String>,
 
     /// If to enable logging to a file in the current directory
-    #[arg(long = "log")]
+    #[arg(long = "log", env = "NEOVIDE_LOG", value_parser = FalseyValueParser::new())]
     pub log_to_file: bool,
 
     /// Connect to the named pipe or socket at ADDRESS
-    #[arg(long, alias = "remote-tcp", value_name = "ADDRESS")]
+    #[arg(
+        long,
+        alias = "remote-tcp",
+        value_name = "ADDRESS",
+        env = "NEOVIDE_SERVER"
+    )]
     pub server: Option<String>,
 
     /// Run NeoVim in WSL rather than on the host
@@ -97,6 +115,19 @@ pub struct CmdLineSettings {
     #[arg(long = "no-tabs", action = ArgAction::SetTrue, value_parser = FalseyValueParser::new())]
     _no_tabs: bool,
 
+    /// Block until the files are closed, to be used as $EDITOR or the editor of git. Implies
+    /// `--no-fork`
+    #[arg(long = "wait", visible_alias = "nofork-until-done", value_parser = FalseyValueParser::new())]
+    pub wait: bool,
+
+    /// Open the files in the Neovide already running, if there's one, instead of starting another
+    #[arg(long = "single-instance", env = "NEOVIDE_SINGLE_INSTANCE", value_parser = FalseyValueParser::new())]
+    pub single_instance: bool,
+
+    /// Listen for the requests of `neovide remote`, which `--single-instance` does too
+    #[arg(long = "remote-control", env = "NEOVIDE_REMOTE_CONTROL", value_parser = FalseyValueParser::new())]
+    pub remote_control: bool,
+
     /// Request sRGB when initializing the window, may help with GPUs with weird pixel
     /// formats. Default on Windows.
     #[arg(long = "srgb", env = "NEOVIDE_SRGB", action = ArgAction::SetTrue, default_value = SRGB_DEFAULT, value_parser = FalseyValueParser::new())]
@@ -146,6 +177,44 @@ pub struct CmdLineSettings {
     #[command(flatten)]
     pub geometry: GeometryArgs,
 
+    /// Print the configuration resolved from the config file, the environment, the command line
+    /// and the defaults, with where each value comes from, and exit
+    #[arg(long = "print-config")]
+    pub print_config: bool,
+
+    /// Print the JSON schema of the config file and exit
+    #[arg(long = "config-schema")]
+    pub config_schema: bool,
+
+    /// Check the keys, the values and the fonts of the config file without opening a window, print
+    /// the problems found as JSON and exit with a non-zero status if there are any
+    #[arg(long = "verify-config")]
+    pub verify_config: bool,
+
+    /// Compare two screenshots of Neovide, write a heatmap of their differences to OUTPUT, or
+    /// frame-diff.png, print how much they differ as JSON and exit with a non-zero status if they
+    /// do
+    #[arg(
+        long = "compare-frames",
+        num_args = 2..=3,
+        value_names = ["FIRST", "SECOND", "OUTPUT"]
+    )]
+    pub compare_frames: Vec<String>,
+
+    /// The line to open the first file at, given by a `nvim://` URL
+    #[arg(skip)]
+    pub line: Option<u64>,
+
+    /// If one of the files was given as a `nvim://` link, which browsers and other apps launch us
+    /// with. The `file://` URLs of the desktop entry are plain files and don't count.
+    #[arg(skip)]
+    pub opened_url: bool,
+
+    /// The arguments without the files to open, ending with the ones passed down to Neovim, to
+    /// start Neovide again with other files
+    #[arg(skip)]
+    pub relaunch_args: Vec<String>,
+
     /// Force opengl on Windows or macOS
     #[cfg(any(target_os = "windows", target_os = "macos"))]
     #[arg(long = "opengl", env = "NEOVIDE_OPENGL", action = ArgAction::SetTrue, value_parser = FalseyValueParser::new())]
@@ -158,11 +227,11 @@ pub struct CmdLineSettings {
 pub struct GeometryArgs {
     /// The initial grid size of the window [<columns>x<lines>]. Defaults to columns/lines from init.vim/lua if no value is given.
     /// If --grid is not set then it's inferred from the window size
-    #[arg(long)]
+    #[arg(long, env = "NEOVIDE_GRID")]
     pub grid: Option<Option<Dimensions>>,
 
     /// The size of the window in pixels.
-    #[arg(long)]
+    #[arg(long, env = "NEOVIDE_SIZE")]
     pub size: Option<Dimensions>,
 
     /// Maximize the window on startup (not equivalent to fullscreen)
@@ -170,6 +239,56 @@ pub struct GeometryArgs {
     pub maximized: bool,
 }
 
+/// `neovide remote`, which sends a request to the IPC server of the running Neovide.
+#[derive(Debug, Parser, PartialEq)]
+#[command(name = "neovide remote", about = "Control the Neovide already running", styles = get_styles())]
+pub struct RemoteArgs {
+    #[command(subcommand)]
+    pub command: RemoteCommand,
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum RemoteCommand {
+    /// Open files and focus the window
+    Open {
+        files: Vec<String>,
+        /// The line to go to in the first file
+        #[arg(long)]
+        line: Option<u64>,
+        /// Only return once the files are closed
+        #[arg(long)]
+        wait: bool,
+    },
+    /// Run a Neovim command and print its output
+    Command { command: String },
+    /// Print the value of a Vim expression as JSON
+    Eval { expression: String },
+    /// List the Neovim windows with their buffers, then the Neovide instances with their process
+    /// id, working directory and NVIM_APPNAME
+    ListWindows,
+    /// Start another Neovide with its own working directory and environment, and print its
+    /// process id
+    NewInstance {
+        files: Vec<String>,
+        /// The working directory of the instance and its Neovim
+        #[arg(long)]
+        cwd: Option<String>,
+        /// An environment variable of the instance and its Neovim, can be repeated
+        #[arg(long, value_name = "NAME=VALUE", value_parser = parse_env_variable)]
+        env: Vec<(String, String)>,
+        /// The NVIM_APPNAME of its Neovim, to use another config
+        #[arg(long)]
+        appname: Option<String>,
+    },
+}
+
+fn parse_env_variable(variable: &str) -> Result<(String, String), String> {
+    match variable.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("{variable} isn't NAME=VALUE")),
+    }
+}
+
 #[derive(ValueEnum, Clone, Debug)]
 pub enum MouseCursorIcon {
     Arrow,
@@ -214,14 +333,35 @@ fn handle_wslpaths(paths: Vec<String>, wsl: bool) -> Vec<String> {
         .collect()
 }
 
+/// Removes the files to open from the arguments, in the order they were given. Everything after
+/// `--` is kept, and it's added when missing, so that more arguments can be passed to Neovim.
+fn relaunch_args(args: &[String], files: &[String]) -> Vec<String> {
+    let mut files = files.iter().peekable();
+    let mut relaunch_args = Vec::new();
+    let mut passthrough = false;
+    for arg in args.iter().skip(1) {
+        if !passthrough && files.peek() == Some(&arg) {
+            files.next();
+            continue;
+        }
+        passthrough = passthrough || arg == "--";
+        relaunch_args.push(arg.clone());
+    }
+    if !passthrough {
+        relaunch_args.push("--".to_string());
+    }
+    relaunch_args
+}
+
 pub fn handle_command_line_arguments(args: Vec<String>, settings: &Settings) -> Result<()> {
-    let mut cmdline = CmdLineSettings::try_parse_from(args)?;
+    let mut cmdline = CmdLineSettings::try_parse_from(&args)?;
+    cmdline.relaunch_args = relaunch_args(&args, &cmdline.files_to_open);
 
     if cmdline._no_tabs {
         cmdline.tabs = false;
     }
 
-    if cmdline._no_fork {
+    if cmdline._no_fork || cmdline.wait {
         cmdline.fork = false;
     }
 
@@ -233,14 +373,20 @@ pub fn handle_command_line_arguments(args: Vec<String>, settings: &Settings) ->
         cmdline.vsync = false;
     }
 
+    for file in &mut cmdline.files_to_open {
+        if let Some(target) = parse_url(file) {
+            cmdline.opened_url |= file.starts_with("nvim://");
+            *file = target.file;
+            cmdline.line = cmdline.line.or(target.line);
+        }
+    }
+
     cmdline.neovim_args = cmdline
         .tabs
         .then(|| "-p".to_string())
         .into_iter()
-        .chain(handle_wslpaths(
-            mem::take(&mut cmdline.files_to_open),
-            cmdline.wsl,
-        ))
+        .chain(cmdline.line.map(|line| format!("+{line}")))
+        .chain(handle_wslpaths(cmdline.files_to_open.clone(), cmdline.wsl))
         .chain(cmdline.neovim_args)
         .collect();
 
@@ -248,6 +394,293 @@ pub fn handle_command_line_arguments(args: Vec<String>, settings: &Settings) ->
     Ok(())
 }
 
+/// Where a value of the resolved configuration comes from, `negation` being the `--no-*` flag of a
+/// switch.
+fn value_source(
+    matches: &ArgMatches,
+    id: &str,
+    negation: Option<&str>,
+    in_config_file: bool,
+) -> &'static str {
+    let negated = negation
+        .is_some_and(|negation| matches.value_source(negation) == Some(ValueSource::CommandLine));
+    match matches.value_source(id) {
+        _ if negated => "command line",
+        Some(ValueSource::CommandLine) => "command line",
+        // The config file is applied through the environment variables, and overrides them
+        Some(ValueSource::EnvVariable) if in_config_file => "config file",
+        Some(ValueSource::EnvVariable) => "environment",
+        _ => "default",
+    }
+}
+
+fn resolved_config(settings: &Settings, config: &Config, matches: &ArgMatches) -> String {
+    let cmdline = settings.get::<CmdLineSettings>();
+    let mouse_cursor_icon = cmdline
+        .mouse_cursor_icon
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+    let values: [(&str, &str, Option<&str>, toml::Value, bool); 12] = [
+        (
+            "fork",
+            "fork",
+            Some("_no_fork"),
+            cmdline.fork.into(),
+            config.fork.is_some(),
+        ),
+        (
+            "frame",
+            "frame",
+            None,
+            <&str>::from(&cmdline.frame).into(),
+            config.frame.is_some(),
+        ),
+        (
+            "idle",
+            "idle",
+            None,
+            cmdline.idle.into(),
+            config.idle.is_some(),
+        ),
+        (
+            "maximized",
+            "maximized",
+            None,
+            cmdline.geometry.maximized.into(),
+            config.maximized.is_some(),
+        ),
+        (
+            "mouse-cursor-icon",
+            "mouse_cursor_icon",
+            None,
+            mouse_cursor_icon.into(),
+            config.mouse_cursor_icon.is_some(),
+        ),
+        (
+            "no-multigrid",
+            "no_multi_grid",
+            None,
+            cmdline.no_multi_grid.into(),
+            config.no_multigrid.is_some(),
+        ),
+        (
+            "remote-control",
+            "remote_control",
+            None,
+            cmdline.remote_control.into(),
+            config.remote_control.is_some(),
+        ),
+        (
+            "single-instance",
+            "single_instance",
+            None,
+            cmdline.single_instance.into(),
+            config.single_instance.is_some(),
+        ),
+        (
+            "srgb",
+            "srgb",
+            Some("_no_srgb"),
+            cmdline.srgb.into(),
+            config.srgb.is_some(),
+        ),
+        (
+            "tabs",
+            "tabs",
+            Some("_no_tabs"),
+            cmdline.tabs.into(),
+            config.tabs.is_some(),
+        ),
+        (
+            "title-hidden",
+            "title_hidden",
+            None,
+            cmdline.title_hidden.into(),
+            config.title_hidden.is_some(),
+        ),
+        (
+            "vsync",
+            "vsync",
+            Some("_no_vsync"),
+            cmdline.vsync.into(),
+            config.vsync.is_some(),
+        ),
+        ("wsl", "wsl", None, cmdline.wsl.into(), config.wsl.is_some()),
+    ];
+
+    let mut lines = vec![
+        format!(
+            "# The configuration of Neovide, read from {}",
+            config_path().display()
+        ),
+        "# The g:neovide_* variables set by init.lua take precedence over [settings]".to_string(),
+    ];
+    for (name, id, negation, value, in_config_file) in values {
+        let source = value_source(matches, id, negation, in_config_file);
+        lines.push(format!("{name} = {value} # {source}"));
+    }
+    match &cmdline.neovim_bin {
+        Some(neovim_bin) => {
+            let source = value_source(matches, "neovim_bin", None, config.neovim_bin.is_some());
+            lines.push(format!(
+                "neovim-bin = {} # {source}",
+                toml::Value::from(neovim_bin.as_str())
+            ));
+        }
+        None => lines.push("# neovim-bin isn't set, nvim is found on $PATH".to_string()),
+    }
+
+    // The fonts only come from the config file, printed as they were written
+    let font = fs::read_to_string(config_path())
+        .ok()
+        .and_then(|text| text.parse::<toml::Table>().ok())
+        .and_then(|table| merge_platform_tables(table).remove("font"));
+    if let Some(font) = font {
+        let table = toml::Table::from_iter([("font".to_string(), font)]);
+        if let Ok(text) = toml::to_string(&table) {
+            lines.push(String::new());
+            lines.push(text.trim_end().to_string());
+        }
+    }
+
+    lines.push(String::new());
+    lines.push("[settings]".to_string());
+    lines.extend(resolved_settings(settings, config.settings.as_ref()));
+    lines.join("\n")
+}
+
+/// Prints the configuration with `--print-config`, the schema of the config file with
+/// `--config-schema` or the problems of the config file with `--verify-config`, or compares two
+/// frames with `--compare-frames`. Returns the exit code when Neovide should exit afterwards.
+pub fn handle_config_dumps(args: Vec<String>, settings: &Settings, config: &Config) -> Option<i32> {
+    let cmdline = settings.get::<CmdLineSettings>();
+    if let [first, second, rest @ ..] = cmdline.compare_frames.as_slice() {
+        let output = rest.first().map_or("frame-diff.png", String::as_str);
+        return Some(
+            match compare_frames(Path::new(first), Path::new(second), Path::new(output)) {
+                Ok(diff) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&diff).unwrap_or_default()
+                    );
+                    if diff.different_pixels == 0 {
+                        0
+                    } else {
+                        1
+                    }
+                }
+                Err(error) => {
+                    eprintln!("{error:#}");
+                    2
+                }
+            },
+        );
+    }
+    if cmdline.verify_config {
+        let path = config_path();
+        let problems = verify_config(settings, &path);
+        let report = serde_json::json!({
+            "path": path,
+            "valid": problems.is_empty(),
+            "problems": problems,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        );
+        return Some(if problems.is_empty() { 0 } else { 1 });
+    }
+    if cmdline.config_schema {
+        let schema = config_schema(settings);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&schema).unwrap_or_default()
+        );
+        return Some(0);
+    }
+    if cmdline.print_config {
+        let matches = CmdLineSettings::command().try_get_matches_from(args).ok()?;
+        println!("{}", resolved_config(settings, config, &matches));
+        return Some(0);
+    }
+    None
+}
+
+/// A file to open, given by an URL.
+#[derive(Debug, PartialEq)]
+pub struct UrlTarget {
+    pub file: String,
+    pub line: Option<u64>,
+}
+
+/// Parses `nvim://open?file=<path>&line=<line>` links and the `file://` URLs desktop environments
+/// pass, or returns `None` for anything else.
+pub fn parse_url(url: &str) -> Option<UrlTarget> {
+    if let Some(rest) = url.strip_prefix("nvim://") {
+        let query = rest
+            .strip_prefix("open")?
+            .trim_start_matches('/')
+            .strip_prefix('?')?;
+        let mut file = None;
+        let mut line = None;
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "file" => file = Some(percent_decode(value)),
+                "line" => line = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(UrlTarget { file: file?, line })
+    } else if let Some(rest) = url.strip_prefix("file://") {
+        // Only local files, with an empty host or localhost
+        let path = percent_decode(rest.strip_prefix("localhost").unwrap_or(rest));
+        // file:///C:/path is C:/path on Windows
+        #[cfg(target_os = "windows")]
+        let path = match path.strip_prefix('/') {
+            Some(windows_path) if windows_path.get(1..2) == Some(":") => windows_path.to_string(),
+            _ => path,
+        };
+        Some(UrlTarget {
+            file: path,
+            line: None,
+        })
+    } else {
+        None
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Parses the arguments of `neovide remote`, or returns `None` for any other command line. Exits
+/// with the usage when they're not valid.
+pub fn parse_remote_arguments(args: &[String]) -> Option<RemoteArgs> {
+    (args.get(1).map(String::as_str) == Some("remote"))
+        .then(|| RemoteArgs::parse_from(args.iter().skip(1)))
+}
+
 #[cfg(test)]
 #[allow(clippy::bool_assert_comparison)] // useful here since the explicit true/false comparison matters
 #[serial_test::serial]
@@ -335,6 +768,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_relaunch_args_without_files() {
+        let settings = Settings::new();
+        let args: Vec<String> = [
+            "neovide",
+            "--grid=42x24",
+            "./foo.txt",
+            "--no-tabs",
+            "./bar.md",
+            "--",
+            "--clean",
+            "./bar.md",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert_eq!(
+            settings.get::<CmdLineSettings>().relaunch_args,
+            vec!["--grid=42x24", "--no-tabs", "--", "--clean", "./bar.md"]
+        );
+
+        let args: Vec<String> = ["neovide", "--no-fork", "./foo.txt"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert_eq!(
+            settings.get::<CmdLineSettings>().relaunch_args,
+            vec!["--no-fork", "--"]
+        );
+    }
+
     #[test]
     fn test_files_to_open_with_flag() {
         let settings = Settings::new();
@@ -594,4 +1061,266 @@ mod tests {
         handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
         assert_eq!(settings.get::<CmdLineSettings>().vsync, true,);
     }
+
+    #[test]
+    fn test_every_option_has_an_environment_variable() {
+        // The options printing something and exiting would make every launch exit
+        let exiting = [
+            "help",
+            "version",
+            "print_config",
+            "config_schema",
+            "verify_config",
+            "compare_frames",
+        ];
+        let command = CmdLineSettings::command();
+        for arg in command.get_arguments() {
+            let id = arg.get_id().as_str();
+            if arg.is_positional() || id.starts_with('_') || exiting.contains(&id) {
+                continue;
+            }
+            assert!(
+                arg.get_env().is_some(),
+                "--{} doesn't have an environment variable",
+                arg.get_long().unwrap_or(id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_log_environment_variable() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide"].iter().map(|s| s.to_string()).collect();
+
+        let _env = ScopedEnv::set("NEOVIDE_LOG", "1");
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert!(settings.get::<CmdLineSettings>().log_to_file);
+    }
+
+    #[test]
+    fn test_no_log_environment_variable() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide"].iter().map(|s| s.to_string()).collect();
+
+        let _env = ScopedEnv::set("NEOVIDE_LOG", "0");
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert!(!settings.get::<CmdLineSettings>().log_to_file);
+    }
+
+    #[test]
+    fn test_server_environment_variable() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide"].iter().map(|s| s.to_string()).collect();
+
+        let _env = ScopedEnv::set("NEOVIDE_SERVER", "localhost:6666");
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert_eq!(
+            settings.get::<CmdLineSettings>().server,
+            Some("localhost:6666".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_override_server_environment_variable() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide", "--server", "/tmp/nvim.sock"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let _env = ScopedEnv::set("NEOVIDE_SERVER", "localhost:6666");
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert_eq!(
+            settings.get::<CmdLineSettings>().server,
+            Some("/tmp/nvim.sock".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_grid_environment_variable() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide"].iter().map(|s| s.to_string()).collect();
+
+        let _env = ScopedEnv::set("NEOVIDE_GRID", "120x40");
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert_eq!(
+            settings.get::<CmdLineSettings>().geometry.grid,
+            Some(Some(Dimensions {
+                width: 120,
+                height: 40
+            })),
+        );
+    }
+
+    #[test]
+    fn test_size_environment_variable() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide"].iter().map(|s| s.to_string()).collect();
+
+        let _env = ScopedEnv::set("NEOVIDE_SIZE", "800x600");
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert_eq!(
+            settings.get::<CmdLineSettings>().geometry.size,
+            Some(Dimensions {
+                width: 800,
+                height: 600,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_wsl_environment_variable() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide"].iter().map(|s| s.to_string()).collect();
+
+        let _env = ScopedEnv::set("NEOVIDE_WSL", "true");
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        assert!(settings.get::<CmdLineSettings>().wsl);
+    }
+
+    #[test]
+    fn test_print_config_sources() {
+        let settings = Settings::new();
+        settings.register::<crate::renderer::RendererSettings>();
+        let args: Vec<String> = ["neovide", "--no-srgb", "--frame", "none", "--print-config"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        handle_command_line_arguments(args.clone(), &settings).expect("Could not parse arguments");
+        let matches = CmdLineSettings::command().get_matches_from(args);
+        let config = Config::default();
+        let text = resolved_config(&settings, &config, &matches);
+        assert!(text.contains("\nsrgb = false # command line\n"));
+        assert!(text.contains("\nframe = \"none\" # command line\n"));
+        assert!(text.contains("\n[settings]\n"));
+        assert!(text.contains("\ntext_gamma = 0.0 # default\n"));
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            parse_url("nvim://open?file=/home/me/my%20file.rs&line=42"),
+            Some(UrlTarget {
+                file: "/home/me/my file.rs".to_string(),
+                line: Some(42),
+            })
+        );
+        assert_eq!(
+            parse_url("nvim://open/?file=main.rs"),
+            Some(UrlTarget {
+                file: "main.rs".to_string(),
+                line: None,
+            })
+        );
+        assert_eq!(
+            parse_url("file:///tmp/a%23b.txt"),
+            Some(UrlTarget {
+                file: "/tmp/a#b.txt".to_string(),
+                line: None,
+            })
+        );
+        assert_eq!(parse_url("nvim://open?line=3"), None);
+        assert_eq!(parse_url("/tmp/file.txt"), None);
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    #[test]
+    fn test_files_to_open_from_url() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide", "nvim://open?file=foo.rs&line=7"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        let cmdline = settings.get::<CmdLineSettings>();
+        assert_eq!(cmdline.files_to_open, vec!["foo.rs"]);
+        assert!(cmdline.opened_url);
+        assert_eq!(cmdline.neovim_args, vec!["-p", "+7", "foo.rs"]);
+    }
+
+    #[test]
+    fn test_file_urls_are_plain_files() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide", "file:///tmp/foo.rs"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        let cmdline = settings.get::<CmdLineSettings>();
+        assert_eq!(cmdline.files_to_open, vec!["/tmp/foo.rs"]);
+        // They are left to --single-instance, like the paths given on the command line
+        assert!(!cmdline.opened_url);
+    }
+
+    #[test]
+    fn test_remote_arguments() {
+        let args: Vec<String> = ["neovide", "remote", "open", "foo.rs", "--line", "10"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            parse_remote_arguments(&args),
+            Some(RemoteArgs {
+                command: RemoteCommand::Open {
+                    files: vec!["foo.rs".to_string()],
+                    line: Some(10),
+                    wait: false,
+                }
+            })
+        );
+
+        let args: Vec<String> = ["neovide", "foo.rs", "remote"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(parse_remote_arguments(&args), None);
+    }
+
+    #[test]
+    fn test_wait_does_not_fork() {
+        let settings = Settings::new();
+        let args: Vec<String> = ["neovide", "--fork", "--wait", "COMMIT_EDITMSG"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        handle_command_line_arguments(args, &settings).expect("Could not parse arguments");
+        let cmdline = settings.get::<CmdLineSettings>();
+        assert!(cmdline.wait);
+        assert!(!cmdline.fork);
+    }
+
+    #[test]
+    fn test_remote_new_instance_arguments() {
+        let args: Vec<String> = [
+            "neovide",
+            "remote",
+            "new-instance",
+            "--cwd",
+            "~/notes",
+            "--env",
+            "RUST_LOG=debug",
+            "--appname",
+            "nvim-writing",
+            "todo.md",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            parse_remote_arguments(&args),
+            Some(RemoteArgs {
+                command: RemoteCommand::NewInstance {
+                    files: vec!["todo.md".to_string()],
+                    cwd: Some("~/notes".to_string()),
+                    env: vec![("RUST_LOG".to_string(), "debug".to_string())],
+                    appname: Some("nvim-writing".to_string()),
+                }
+            })
+        );
+        assert!(parse_env_variable("=debug").is_err());
+    }
 }
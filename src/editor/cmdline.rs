@@ -0,0 +1,360 @@
+use std::{collections::HashMap, sync::Arc};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{bridge::StyledContent, editor::style::Style};
+
+/// A piece of command line text sharing the same highlight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StyledText {
+    pub text: String,
+    pub style: Option<Arc<Style>>,
+}
+
+pub(super) fn styled_text(
+    content: StyledContent,
+    defined_styles: &HashMap<u64, Arc<Style>>,
+) -> Vec<StyledText> {
+    content
+        .into_iter()
+        .map(|(style_id, text)| StyledText {
+            text,
+            style: defined_styles.get(&style_id).cloned(),
+        })
+        .collect()
+}
+
+/// A single line of the command line, as laid out by [`CommandLine::lines`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandLineLine {
+    pub fragments: Vec<StyledText>,
+    /// Column of the cursor, in grapheme clusters, when this is the line being edited.
+    pub cursor_column: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CommandLineLevel {
+    content: Vec<StyledText>,
+    /// Byte offset of the cursor inside the content.
+    position: usize,
+    first_character: String,
+    prompt: String,
+    indent: usize,
+    level: u64,
+    special_character: Option<(String, bool)>,
+}
+
+impl CommandLineLevel {
+    fn line(&self) -> CommandLineLine {
+        let mut fragments = Vec::new();
+        let mut prefix = format!("{}{}", self.first_character, self.prompt);
+        prefix.push_str(&" ".repeat(self.indent));
+        let mut cursor_column = prefix.graphemes(true).count();
+        if !prefix.is_empty() {
+            fragments.push(StyledText {
+                text: prefix,
+                style: None,
+            });
+        }
+
+        // Split the content at the cursor so that the special character can be shown in place.
+        let mut remaining = self.position;
+        let mut after_cursor = Vec::new();
+        for fragment in &self.content {
+            if remaining >= fragment.text.len() {
+                remaining -= fragment.text.len();
+                cursor_column += fragment.text.graphemes(true).count();
+                fragments.push(fragment.clone());
+            } else if remaining > 0 {
+                // Keep the cursor on a character boundary, before the character it's inside of
+                let split = (0..=remaining)
+                    .rev()
+                    .find(|index| fragment.text.is_char_boundary(*index))
+                    .unwrap_or(0);
+                let (before, after) = fragment.text.split_at(split);
+                cursor_column += before.graphemes(true).count();
+                if !before.is_empty() {
+                    fragments.push(StyledText {
+                        text: before.to_string(),
+                        style: fragment.style.clone(),
+                    });
+                }
+                after_cursor.push(StyledText {
+                    text: after.to_string(),
+                    style: fragment.style.clone(),
+                });
+                remaining = 0;
+            } else {
+                remaining = 0;
+                after_cursor.push(fragment.clone());
+            }
+        }
+
+        if let Some((character, shift)) = &self.special_character {
+            fragments.push(StyledText {
+                text: character.clone(),
+                style: None,
+            });
+            // Without shift the special character overwrites the text under the cursor
+            if !shift {
+                if let Some(first) = after_cursor.first_mut() {
+                    let skip = first.text.graphemes(true).next().map_or(0, str::len);
+                    first.text.drain(..skip);
+                }
+            }
+        }
+        fragments.extend(after_cursor.into_iter().filter(|f| !f.text.is_empty()));
+
+        CommandLineLine {
+            fragments,
+            cursor_column: Some(cursor_column),
+        }
+    }
+}
+
+/// The state of Neovim's externalized command line (`ext_cmdline`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandLine {
+    levels: Vec<CommandLineLevel>,
+    block: Vec<Vec<StyledText>>,
+}
+
+impl CommandLine {
+    #[allow(clippy::too_many_arguments)]
+    pub fn show(
+        &mut self,
+        content: StyledContent,
+        position: u64,
+        first_character: String,
+        prompt: String,
+        indent: u64,
+        level: u64,
+        defined_styles: &HashMap<u64, Arc<Style>>,
+    ) {
+        // A new show for an existing level replaces it and everything nested inside
+        self.levels.retain(|existing| existing.level < level);
+        self.levels.push(CommandLineLevel {
+            content: styled_text(content, defined_styles),
+            position: position as usize,
+            first_character,
+            prompt,
+            indent: indent as usize,
+            level,
+            special_character: None,
+        });
+    }
+
+    pub fn set_position(&mut self, position: u64, level: u64) {
+        if let Some(current) = self.level_mut(level) {
+            current.position = position as usize;
+            current.special_character = None;
+        }
+    }
+
+    pub fn set_special_character(&mut self, character: String, shift: bool, level: u64) {
+        if let Some(current) = self.level_mut(level) {
+            current.special_character = Some((character, shift));
+        }
+    }
+
+    /// Hides `level` and the levels nested inside of it, or the innermost level when Neovim
+    /// doesn't say which.
+    pub fn hide(&mut self, level: Option<u64>) {
+        match level {
+            Some(level) => self.levels.retain(|existing| existing.level < level),
+            None => {
+                self.levels.pop();
+            }
+        }
+    }
+
+    pub fn show_block(
+        &mut self,
+        lines: Vec<StyledContent>,
+        defined_styles: &HashMap<u64, Arc<Style>>,
+    ) {
+        self.block = lines
+            .into_iter()
+            .map(|line| styled_text(line, defined_styles))
+            .collect();
+    }
+
+    pub fn append_block(&mut self, line: StyledContent, defined_styles: &HashMap<u64, Arc<Style>>) {
+        self.block.push(styled_text(line, defined_styles));
+    }
+
+    pub fn hide_block(&mut self) {
+        self.block.clear();
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.levels.is_empty()
+    }
+
+    /// The lines to display, the block lines first followed by the line being edited.
+    pub fn lines(&self) -> Vec<CommandLineLine> {
+        let mut lines = self
+            .block
+            .iter()
+            .map(|fragments| CommandLineLine {
+                fragments: fragments.clone(),
+                cursor_column: None,
+            })
+            .collect::<Vec<_>>();
+        if let Some(current) = self.levels.last() {
+            lines.push(current.line());
+        }
+        lines
+    }
+
+    fn level_mut(&mut self, level: u64) -> Option<&mut CommandLineLevel> {
+        self.levels
+            .iter_mut()
+            .rev()
+            .find(|existing| existing.level == level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(text: &str) -> StyledContent {
+        vec![(0, text.to_string())]
+    }
+
+    fn text(line: &CommandLineLine) -> String {
+        line.fragments.iter().map(|f| f.text.as_str()).collect()
+    }
+
+    #[test]
+    fn show_builds_prompt_and_cursor() {
+        let mut cmdline = CommandLine::default();
+        cmdline.show(
+            content("wq"),
+            1,
+            ":".into(),
+            "".into(),
+            0,
+            1,
+            &HashMap::new(),
+        );
+
+        let lines = cmdline.lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(text(&lines[0]), ":wq");
+        assert_eq!(lines[0].cursor_column, Some(2));
+    }
+
+    #[test]
+    fn nested_levels_are_hidden_in_order() {
+        let mut cmdline = CommandLine::default();
+        cmdline.show(
+            content("echo "),
+            5,
+            ":".into(),
+            "".into(),
+            0,
+            1,
+            &HashMap::new(),
+        );
+        cmdline.show(
+            content("1+1"),
+            3,
+            "=".into(),
+            "".into(),
+            0,
+            2,
+            &HashMap::new(),
+        );
+        assert_eq!(text(&cmdline.lines()[0]), "=1+1");
+
+        cmdline.hide(Some(2));
+        assert_eq!(text(&cmdline.lines()[0]), ":echo ");
+        cmdline.hide(None);
+        assert!(!cmdline.is_visible());
+    }
+
+    #[test]
+    fn hide_removes_the_level_given() {
+        let mut cmdline = CommandLine::default();
+        for level in 1..=3 {
+            cmdline.show(
+                content(&level.to_string()),
+                1,
+                ":".into(),
+                "".into(),
+                0,
+                level,
+                &HashMap::new(),
+            );
+        }
+        // Hiding a level that's gone already leaves the others alone
+        cmdline.hide(Some(4));
+        assert_eq!(text(&cmdline.lines()[0]), ":3");
+        cmdline.hide(Some(2));
+        assert_eq!(text(&cmdline.lines()[0]), ":1");
+        cmdline.hide(Some(1));
+        assert!(!cmdline.is_visible());
+    }
+
+    #[test]
+    fn cursor_inside_a_character_is_moved_to_its_start() {
+        let mut cmdline = CommandLine::default();
+        // The cursor in the middle of the two bytes of é
+        cmdline.show(
+            content("aéb"),
+            2,
+            ":".into(),
+            "".into(),
+            0,
+            1,
+            &HashMap::new(),
+        );
+        let line = &cmdline.lines()[0];
+        assert_eq!(text(line), ":aéb");
+        assert_eq!(line.cursor_column, Some(2));
+
+        cmdline.set_position(3, 1);
+        assert_eq!(cmdline.lines()[0].cursor_column, Some(3));
+    }
+
+    #[test]
+    fn special_character_is_shown_at_the_cursor() {
+        let mut cmdline = CommandLine::default();
+        cmdline.show(
+            content("ab"),
+            1,
+            ":".into(),
+            "".into(),
+            0,
+            1,
+            &HashMap::new(),
+        );
+
+        cmdline.set_special_character("^".into(), true, 1);
+        assert_eq!(text(&cmdline.lines()[0]), ":a^b");
+
+        cmdline.set_special_character("^".into(), false, 1);
+        assert_eq!(text(&cmdline.lines()[0]), ":a^");
+
+        cmdline.set_position(2, 1);
+        assert_eq!(text(&cmdline.lines()[0]), ":ab");
+    }
+
+    #[test]
+    fn block_lines_come_before_the_current_line() {
+        let mut cmdline = CommandLine::default();
+        cmdline.show_block(vec![content("function F()")], &HashMap::new());
+        cmdline.append_block(content("  return 1"), &HashMap::new());
+        cmdline.show(content(""), 0, ":".into(), "".into(), 2, 1, &HashMap::new());
+
+        let lines = cmdline.lines();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].cursor_column, None);
+        assert_eq!(lines[2].cursor_column, Some(3));
+
+        cmdline.hide_block();
+        assert_eq!(cmdline.lines().len(), 1);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::SortOrder;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Layer {
+    grid: u64,
+    z_index: u64,
+}
+
+/// The stacking of the floating windows and the messages, from the bottom up. Neovim only composes
+/// the grids itself for the UIs without `ext_multigrid`, so the rules of its compositor are
+/// followed here, see `ui_comp_put_grid` and `ui_comp_grid_cursor_goto` in `ui_compositor.c`:
+///
+/// - The windows are stacked by z-index first.
+/// - A new float goes on top of the ones with the same z-index, except under the current window
+///   when that's the topmost of them, like a notification opened while typing in a prompt.
+/// - Positioning a float again keeps its place, unless its z-index changed.
+/// - The float the cursor enters is raised to the top of its z-index.
+/// - A hidden float is put back on top when it's shown again.
+#[derive(Debug, Default)]
+pub struct CompositionOrder {
+    layers: Vec<Layer>,
+    /// The sort orders last sent to the renderer.
+    published: HashMap<u64, SortOrder>,
+}
+
+impl CompositionOrder {
+    /// Places `grid`, with `current_grid` being the grid of the cursor, and returns its sort order.
+    pub fn put(&mut self, grid: u64, z_index: u64, current_grid: u64) -> SortOrder {
+        match self.layers.iter().position(|layer| layer.grid == grid) {
+            Some(index) if self.layers[index].z_index == z_index => {}
+            _ => {
+                self.remove(grid);
+                let mut insert_at = self
+                    .layers
+                    .iter()
+                    .rposition(|layer| layer.z_index <= z_index)
+                    .map_or(0, |index| index + 1);
+                if insert_at > 0
+                    && grid != current_grid
+                    && self.layers[insert_at - 1]
+                        == (Layer {
+                            grid: current_grid,
+                            z_index,
+                        })
+                {
+                    insert_at -= 1;
+                }
+                self.layers.insert(insert_at, Layer { grid, z_index });
+            }
+        }
+        self.sort_order(grid).unwrap()
+    }
+
+    /// Raises `grid` to the top of its z-index, when the cursor enters it.
+    pub fn raise(&mut self, grid: u64) {
+        let Some(index) = self.layers.iter().position(|layer| layer.grid == grid) else {
+            return;
+        };
+        let layer = self.layers.remove(index);
+        let insert_at = self
+            .layers
+            .iter()
+            .rposition(|other| other.z_index <= layer.z_index)
+            .map_or(0, |index| index + 1);
+        self.layers.insert(insert_at, layer);
+    }
+
+    pub fn remove(&mut self, grid: u64) {
+        self.layers.retain(|layer| layer.grid != grid);
+    }
+
+    pub fn sort_order(&self, grid: u64) -> Option<SortOrder> {
+        let index = self.layers.iter().position(|layer| layer.grid == grid)?;
+        Some(SortOrder {
+            z_index: self.layers[index].z_index,
+            composition_order: index as u64,
+        })
+    }
+
+    /// The grids whose place changed since the last call, with their new sort order.
+    pub fn take_changed(&mut self) -> Vec<(u64, SortOrder)> {
+        self.published
+            .retain(|grid, _| self.layers.iter().any(|layer| layer.grid == *grid));
+        let mut changed = Vec::new();
+        for grid in self.layers.iter().map(|layer| layer.grid) {
+            let sort_order = self.sort_order(grid).unwrap();
+            if self.published.get(&grid) != Some(&sort_order) {
+                self.published.insert(grid, sort_order.clone());
+                changed.push((grid, sort_order));
+            }
+        }
+        changed
+    }
+
+    /// The grids from the bottom up with their z-index, for the log.
+    pub fn dump(&self) -> String {
+        self.layers
+            .iter()
+            .map(|layer| format!("{}:z{}", layer.grid, layer.z_index))
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grids(order: &CompositionOrder) -> Vec<u64> {
+        order.layers.iter().map(|layer| layer.grid).collect()
+    }
+
+    #[test]
+    fn notifications_open_under_the_prompt() {
+        // Telescope opens its results, preview and prompt, and the cursor enters the prompt
+        let mut order = CompositionOrder::default();
+        order.put(4, 50, 2);
+        order.put(5, 50, 2);
+        order.put(6, 50, 2);
+        order.raise(6);
+        // nvim-notify shows a notification with the same z-index while typing in the prompt
+        order.put(7, 50, 6);
+        assert_eq!(grids(&order), vec![4, 5, 7, 6]);
+        assert_eq!(order.dump(), "4:z50 5:z50 7:z50 6:z50");
+
+        // Resizing the results positions them again without raising them
+        order.put(4, 50, 6);
+        assert_eq!(grids(&order), vec![4, 5, 7, 6]);
+
+        // The notification is hidden and shown again on top of the prompt once it's closed
+        order.remove(7);
+        order.remove(6);
+        order.put(7, 50, 2);
+        assert_eq!(grids(&order), vec![4, 5, 7]);
+    }
+
+    #[test]
+    fn floats_are_stacked_by_z_index_without_ties() {
+        let mut order = CompositionOrder::default();
+        // The messages and a float with the same z-index used to share a composition order
+        let messages = order.put(3, 250, 2);
+        let float = order.put(8, 250, 2);
+        assert!(messages < float);
+        assert_eq!(order.put(3, 250, 2), messages);
+
+        let completion = order.put(9, 100, 8);
+        assert!(completion < messages);
+        order.raise(9);
+        assert_eq!(grids(&order), vec![9, 3, 8]);
+
+        // A float moved to another z-index goes on top of the floats there
+        order.put(9, 300, 2);
+        assert_eq!(grids(&order), vec![3, 8, 9]);
+
+        let changed = order.take_changed();
+        assert_eq!(changed.len(), 3);
+        assert!(order.take_changed().is_empty());
+        order.remove(3);
+        assert_eq!(
+            order
+                .take_changed()
+                .into_iter()
+                .map(|(grid, _)| grid)
+                .collect::<Vec<_>>(),
+            vec![8, 9]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// How far back the flushes are counted.
+const WINDOW: Duration = Duration::from_millis(500);
+
+/// Measures how often Neovim flushes its redraws, which is much more often than anyone types when
+/// a macro is replayed or a lot of text is pasted.
+#[derive(Default)]
+pub struct FlushRate {
+    flushes: VecDeque<Instant>,
+}
+
+impl FlushRate {
+    /// Records a flush at `now`, and returns the flushes per second over the last half second.
+    pub fn record(&mut self, now: Instant) -> f32 {
+        self.flushes.push_back(now);
+        while self
+            .flushes
+            .front()
+            .is_some_and(|flush| now.duration_since(*flush) > WINDOW)
+        {
+            self.flushes.pop_front();
+        }
+        self.flushes.len() as f32 / WINDOW.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_drops_once_the_flushes_slow_down() {
+        let mut flush_rate = FlushRate::default();
+        let start = Instant::now();
+        let mut rate = 0.0;
+        for flush in 0..50 {
+            rate = flush_rate.record(start + Duration::from_millis(flush * 5));
+        }
+        assert_eq!(rate, 100.0);
+        let rate = flush_rate.record(start + Duration::from_secs(2));
+        assert_eq!(rate, 2.0);
+    }
+}
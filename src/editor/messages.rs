@@ -0,0 +1,110 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    bridge::{MessageKind, StyledContent},
+    editor::{cmdline::styled_text, style::Style, StyledText},
+};
+
+/// A message shown by Neovim through the externalized message UI (`ext_messages`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    /// Stays the same when Neovim replaces the message, so that it can be updated in place.
+    pub id: u64,
+    pub kind: MessageKind,
+    pub content: Vec<StyledText>,
+}
+
+/// The messages shown since Neovim last cleared the message area.
+#[derive(Default)]
+pub struct Messages {
+    current: Vec<Message>,
+    next_id: u64,
+}
+
+impl Messages {
+    /// Adds a message, or replaces the last one when `replace_last` is set. Returns the message to
+    /// display, or `None` when there is nothing worth showing.
+    pub fn show(
+        &mut self,
+        kind: MessageKind,
+        content: StyledContent,
+        replace_last: bool,
+        defined_styles: &HashMap<u64, Arc<Style>>,
+    ) -> Option<Message> {
+        let content = styled_text(content, defined_styles);
+        if content
+            .iter()
+            .all(|fragment| fragment.text.trim().is_empty())
+        {
+            return None;
+        }
+
+        let id = match self.current.last() {
+            Some(last) if replace_last => {
+                let id = last.id;
+                self.current.pop();
+                id
+            }
+            _ => {
+                self.next_id += 1;
+                self.next_id
+            }
+        };
+        let message = Message { id, kind, content };
+        self.current.push(message.clone());
+        Some(message)
+    }
+
+    pub fn clear(&mut self) {
+        self.current.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(text: &str) -> StyledContent {
+        vec![(0, text.to_string())]
+    }
+
+    #[test]
+    fn replace_last_keeps_the_id() {
+        let mut messages = Messages::default();
+        let first = messages
+            .show(MessageKind::Echo, content("one"), false, &HashMap::new())
+            .unwrap();
+        let second = messages
+            .show(MessageKind::Echo, content("two"), false, &HashMap::new())
+            .unwrap();
+        assert_ne!(first.id, second.id);
+
+        let replaced = messages
+            .show(MessageKind::Echo, content("three"), true, &HashMap::new())
+            .unwrap();
+        assert_eq!(replaced.id, second.id);
+        assert_eq!(replaced.content[0].text, "three");
+
+        messages.clear();
+        let after_clear = messages
+            .show(MessageKind::Echo, content("four"), true, &HashMap::new())
+            .unwrap();
+        assert_ne!(after_clear.id, second.id);
+    }
+
+    #[test]
+    fn empty_messages_are_skipped() {
+        let mut messages = Messages::default();
+        assert!(messages
+            .show(MessageKind::Echo, content("  "), false, &HashMap::new())
+            .is_none());
+        assert!(messages
+            .show(
+                MessageKind::ReturnPrompt,
+                content("Press ENTER"),
+                false,
+                &HashMap::new()
+            )
+            .is_some());
+    }
+}
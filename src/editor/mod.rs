@@ -1,12 +1,28 @@
+mod cmdline;
+mod composition_order;
 mod cursor;
 mod draw_command_batcher;
+mod flush_rate;
 mod grid;
+mod messages;
+mod multigrid_diagnostics;
+mod popupmenu;
 mod style;
+mod tabline;
+mod terminal_coalescing;
+mod title;
+mod wildmenu;
 mod window;
 
-use std::{collections::HashMap, rc::Rc, sync::Arc, thread};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
-use log::{error, trace, warn};
+use log::{debug, trace};
 use tokio::sync::mpsc::unbounded_channel;
 
 use winit::event_loop::EventLoopProxy;
@@ -20,23 +36,39 @@ use skia_safe::Color4f;
 use crate::{
     bridge::{GuiOption, NeovimHandler, RedrawEvent, WindowAnchor},
     profiling::{tracy_named_frame, tracy_zone},
-    renderer::{DrawCommand, WindowDrawCommand},
+    renderer::{
+        animation_utils::set_animations_suppressed, DrawCommand, RendererSettings,
+        WindowDrawCommand,
+    },
     running_tracker::RunningTracker,
     settings::Settings,
-    window::{UserEvent, WindowCommand},
+    window::{UserEvent, WindowCommand, WindowSettings},
 };
 
 #[cfg(target_os = "macos")]
-use crate::{cmd_line::CmdLineSettings, frame::Frame};
+use crate::{cmd_line::CmdLineSettings, frame::Frame, utils::is_light_color};
 
+pub use cmdline::{CommandLine, CommandLineLine, StyledText};
+use composition_order::CompositionOrder;
 pub use cursor::{Cursor, CursorMode, CursorShape};
 pub use draw_command_batcher::DrawCommandBatcher;
+use flush_rate::FlushRate;
+pub use messages::Message;
+use messages::Messages;
+pub use multigrid_diagnostics::MultigridAnomaly;
+use multigrid_diagnostics::MultigridDiagnostics;
+pub use popupmenu::{PopupMenu, PopupMenuAnchor, PopupMenuEntry, PopupMenuStyles};
 pub use style::{Colors, Style, UnderlineStyle};
+pub use tabline::{Tabline, TablineStyles};
+use terminal_coalescing::TerminalCoalescing;
+use title::Title;
+pub use title::TitleInfo;
+pub use wildmenu::Wildmenu;
 pub use window::*;
 
 const MODE_CMDLINE: u64 = 4;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct SortOrder {
     pub z_index: u64,
     composition_order: u64,
@@ -45,9 +77,7 @@ pub struct SortOrder {
 impl Ord for SortOrder {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         // The windows are sorted primarily by z_index, and inside the z_index by
-        // composition_order. The composition_order is the window creation order with the special
-        // case that every time a floating window is activated it gets the highest priority for
-        // its z_index.
+        // composition_order, their place in the stacking kept by CompositionOrder.
         let a = (self.z_index, (self.composition_order as i64));
         let b = (other.z_index, (other.composition_order as i64));
         a.cmp(&b)
@@ -90,14 +120,29 @@ pub struct Editor {
     pub windows: HashMap<u64, Window>,
     pub cursor: Cursor,
     pub defined_styles: HashMap<u64, Arc<Style>>,
+    /// Maps the names of the highlight groups used by the UI to their highlight ids.
+    highlight_groups: HashMap<String, u64>,
     pub mode_list: Vec<CursorMode>,
     pub draw_command_batcher: Rc<DrawCommandBatcher>,
     pub current_mode_index: Option<u64>,
+    pub command_line: CommandLine,
+    messages: Messages,
+    popup_menu: Option<PopupMenu>,
+    wildmenu: Option<Wildmenu>,
+    /// The value of 'showtabline', which decides when the tab bar is shown.
+    show_tabline: u64,
+    /// Maps grids to the handles of the Neovim windows they show.
+    window_handles: HashMap<u64, u64>,
+    terminal_windows: HashSet<u64>,
     pub ui_ready: bool,
     event_loop_proxy: EventLoopProxy<UserEvent>,
     #[allow(dead_code)]
     settings: Arc<Settings>,
-    composition_order: u64,
+    composition_order: CompositionOrder,
+    flush_rate: FlushRate,
+    multigrid_diagnostics: MultigridDiagnostics,
+    title: Title,
+    terminal_coalescing: TerminalCoalescing,
 }
 
 impl Editor {
@@ -106,26 +151,83 @@ impl Editor {
             windows: HashMap::new(),
             cursor: Cursor::new(),
             defined_styles: HashMap::new(),
+            highlight_groups: HashMap::new(),
             mode_list: Vec::new(),
             draw_command_batcher: Rc::new(DrawCommandBatcher::new()),
             current_mode_index: None,
+            command_line: CommandLine::default(),
+            messages: Messages::default(),
+            popup_menu: None,
+            wildmenu: None,
+            show_tabline: 1,
+            window_handles: HashMap::new(),
+            terminal_windows: HashSet::new(),
             ui_ready: false,
             settings,
             event_loop_proxy,
-            composition_order: 0,
+            composition_order: CompositionOrder::default(),
+            flush_rate: FlushRate::default(),
+            multigrid_diagnostics: MultigridDiagnostics::default(),
+            title: Title::default(),
+            terminal_coalescing: TerminalCoalescing::default(),
+        }
+    }
+
+    /// Skips the animations while Neovim redraws faster than they could follow, like when a macro
+    /// is replayed, and brings them back once it slows down.
+    fn suppress_animations_when_busy(&mut self) {
+        let pause_rate = self.settings.get::<RendererSettings>().animation_pause_rate;
+        let rate = self.flush_rate.record(Instant::now());
+        set_animations_suppressed(pause_rate > 0.0 && rate > pause_rate);
+    }
+
+    /// Shows the title set by Neovim, or the one built from `g:neovide_title_template`, which is
+    /// checked on every flush so that changing the template is seen right away.
+    fn update_title(&mut self) {
+        let template = self.settings.get::<WindowSettings>().title_template;
+        if let Some(title) = self.title.update(&template) {
+            let _ = self
+                .event_loop_proxy
+                .send_event(WindowCommand::TitleChanged(title).into());
+        }
+    }
+
+    /// Whether the batch of a flush is sent now, or held with the next ones while a terminal
+    /// prints faster than the frames are drawn, see `TerminalCoalescing`.
+    fn should_send_flush(&mut self) -> bool {
+        if !self
+            .settings
+            .get::<RendererSettings>()
+            .terminal_coalesce_output
+        {
+            return true;
+        }
+        let refresh_rate = self.settings.get::<WindowSettings>().refresh_rate.max(1);
+        let interval = Duration::from_secs_f64(1.0 / refresh_rate as f64);
+        self.terminal_coalescing.flush(Instant::now(), interval)
+    }
+
+    /// Sends the batch held back by `should_send_flush` once its frame is due.
+    fn send_deferred_flush(&mut self) {
+        self.terminal_coalescing.send_deferred(Instant::now());
+        self.draw_command_batcher.send_batch(&self.event_loop_proxy);
+    }
+
+    /// Shows the window positions that couldn't be honored since the last flush in one warning.
+    fn send_multigrid_warning(&mut self) {
+        let anomalies = self.multigrid_diagnostics.take_pending();
+        if !anomalies.is_empty() {
+            let _ = self
+                .event_loop_proxy
+                .send_event(WindowCommand::MultigridWarning(anomalies).into());
         }
     }
 
     pub fn handle_redraw_event(&mut self, event: RedrawEvent) {
         match event {
-            RedrawEvent::SetTitle { mut title } => {
+            RedrawEvent::SetTitle { title } => {
                 tracy_zone!("EditorSetTitle");
-                if title.is_empty() {
-                    title = "Neovide".to_string()
-                }
-                let _ = self
-                    .event_loop_proxy
-                    .send_event(WindowCommand::TitleChanged(title).into());
+                self.title.set_neovim_title(title);
             }
             RedrawEvent::ModeInfoSet { cursor_modes } => {
                 tracy_zone!("EditorModeInfoSet");
@@ -148,6 +250,7 @@ impl Editor {
                 } else {
                     self.current_mode_index = None
                 }
+                self.title.set_mode(&mode);
                 self.draw_command_batcher
                     .queue(DrawCommand::ModeChanged(mode));
             }
@@ -163,6 +266,12 @@ impl Editor {
                     .event_loop_proxy
                     .send_event(WindowCommand::SetMouseEnabled(false).into());
             }
+            RedrawEvent::Bell { visual } => {
+                tracy_zone!("EditorBell");
+                let _ = self
+                    .event_loop_proxy
+                    .send_event(WindowCommand::Bell { visual }.into());
+            }
             RedrawEvent::BusyStart => {
                 tracy_zone!("EditorBusyStart");
                 trace!("Cursor off");
@@ -177,8 +286,12 @@ impl Editor {
                 tracy_zone!("EditorFlush");
                 trace!("Image flushed");
                 tracy_named_frame!("neovim draw command flush");
+                self.suppress_animations_when_busy();
+                self.send_multigrid_warning();
+                self.queue_sort_orders();
                 self.send_cursor_info();
-                {
+                self.update_title();
+                if self.should_send_flush() {
                     trace!("send_batch");
                     self.draw_command_batcher.send_batch(&self.event_loop_proxy);
                 }
@@ -195,6 +308,12 @@ impl Editor {
                     );
                 }
 
+                // Let the title bar follow the colorscheme when requested.
+                #[cfg(target_os = "windows")]
+                let _ = self
+                    .event_loop_proxy
+                    .send_event(WindowCommand::DefaultBackgroundChanged(colors.background).into());
+
                 self.draw_command_batcher
                     .queue(DrawCommand::DefaultStyleChanged(Style::new(colors)));
                 self.redraw_screen();
@@ -231,6 +350,9 @@ impl Editor {
                 let defined_styles = &self.defined_styles;
                 let window = self.windows.get_mut(&grid);
                 if let Some(window) = window {
+                    if window.window_type == WindowType::Terminal {
+                        self.terminal_coalescing.terminal_updated();
+                    }
                     window.draw_grid_line(row, column_start, cells, defined_styles);
                 }
             }
@@ -257,21 +379,29 @@ impl Editor {
                 tracy_zone!("EditorScroll");
                 let window = self.windows.get_mut(&grid);
                 if let Some(window) = window {
+                    if window.window_type == WindowType::Terminal {
+                        self.terminal_coalescing.terminal_updated();
+                    }
                     window.scroll_region(top, bottom, left, right, rows, columns);
                 }
             }
             RedrawEvent::WindowPosition {
                 grid,
+                window,
                 start_row,
                 start_column,
                 width,
                 height,
             } => {
                 tracy_zone!("EditorWindowPosition");
-                self.set_window_position(grid, start_column, start_row, width, height)
+                trace!(grid_id = grid, window_id = window; "Window positioned");
+                self.window_handles.insert(grid, window);
+                self.set_window_position(grid, start_column, start_row, width, height);
+                self.update_window_type(grid);
             }
             RedrawEvent::WindowFloatPosition {
                 grid,
+                window,
                 anchor,
                 anchor_grid,
                 anchor_column: anchor_left,
@@ -280,18 +410,17 @@ impl Editor {
                 ..
             } => {
                 tracy_zone!("EditorWindowFloatPosition");
-                self.composition_order += 1;
+                trace!(grid_id = grid, window_id = window; "Window floated");
+                self.window_handles.insert(grid, window);
                 self.set_window_float_position(
                     grid,
                     anchor_grid,
                     anchor,
                     anchor_left,
                     anchor_top,
-                    SortOrder {
-                        z_index,
-                        composition_order: self.composition_order,
-                    },
-                )
+                    z_index,
+                );
+                self.update_window_type(grid);
             }
             RedrawEvent::WindowHide { grid } => {
                 tracy_zone!("EditorWindowHide");
@@ -300,6 +429,7 @@ impl Editor {
                     window.anchor_info = None;
                     window.hide();
                 }
+                self.composition_order.remove(grid);
             }
             RedrawEvent::WindowClose { grid } => {
                 tracy_zone!("EditorWindowClose");
@@ -316,6 +446,9 @@ impl Editor {
             }
             RedrawEvent::WindowViewport {
                 grid,
+                top_line,
+                bottom_line,
+                line_count,
                 // Don't send viewport events if they don't have a scroll delta
                 scroll_delta: Some(scroll_delta),
                 ..
@@ -324,7 +457,11 @@ impl Editor {
                 self.set_ui_ready();
                 self.draw_command_batcher.queue(DrawCommand::Window {
                     grid_id: grid,
-                    command: WindowDrawCommand::Viewport { scroll_delta },
+                    command: WindowDrawCommand::Viewport {
+                        scroll_delta,
+                        at_top: top_line < 1.0,
+                        at_bottom: line_count.is_some_and(|line_count| bottom_line >= line_count),
+                    },
                 });
             }
             RedrawEvent::WindowViewportMargins {
@@ -345,17 +482,213 @@ impl Editor {
                     },
                 });
             }
+            RedrawEvent::CommandLineShow {
+                content,
+                position,
+                first_character,
+                prompt,
+                indent,
+                level,
+            } => {
+                tracy_zone!("EditorCommandLineShow");
+                self.command_line.show(
+                    content,
+                    position,
+                    first_character,
+                    prompt,
+                    indent,
+                    level,
+                    &self.defined_styles,
+                );
+                self.queue_command_line();
+            }
+            RedrawEvent::CommandLinePosition { position, level } => {
+                tracy_zone!("EditorCommandLinePosition");
+                self.command_line.set_position(position, level);
+                self.queue_command_line();
+            }
+            RedrawEvent::CommandLineSpecialCharacter {
+                character,
+                shift,
+                level,
+            } => {
+                tracy_zone!("EditorCommandLineSpecialCharacter");
+                self.command_line
+                    .set_special_character(character, shift, level);
+                self.queue_command_line();
+            }
+            RedrawEvent::CommandLineHide { level } => {
+                tracy_zone!("EditorCommandLineHide");
+                self.command_line.hide(level);
+                self.queue_command_line();
+            }
+            RedrawEvent::CommandLineBlockShow { lines } => {
+                tracy_zone!("EditorCommandLineBlockShow");
+                self.command_line.show_block(lines, &self.defined_styles);
+                self.queue_command_line();
+            }
+            RedrawEvent::CommandLineBlockAppend { line } => {
+                tracy_zone!("EditorCommandLineBlockAppend");
+                self.command_line.append_block(line, &self.defined_styles);
+                self.queue_command_line();
+            }
+            RedrawEvent::CommandLineBlockHide => {
+                tracy_zone!("EditorCommandLineBlockHide");
+                self.command_line.hide_block();
+                self.queue_command_line();
+            }
+            RedrawEvent::HighlightGroupSet { name, id } => {
+                self.highlight_groups.insert(name, id);
+            }
+            RedrawEvent::TablineUpdate { current_tab, tabs } => {
+                tracy_zone!("EditorTablineUpdate");
+                let styles = TablineStyles::resolve(&self.highlight_groups, &self.defined_styles);
+                let tabline = Tabline::new(current_tab, tabs, self.show_tabline, styles);
+                self.draw_command_batcher
+                    .queue(DrawCommand::TablineChanged(tabline));
+            }
+            RedrawEvent::PopupMenuShow {
+                items,
+                selected,
+                row,
+                column,
+                grid,
+            } => {
+                tracy_zone!("EditorPopupMenuShow");
+                let anchor = match grid {
+                    Some(grid) => PopupMenuAnchor::Grid { grid, row, column },
+                    None => PopupMenuAnchor::CommandLine { column },
+                };
+                let query = self.popup_menu_query(anchor);
+                let styles = PopupMenuStyles::resolve(&self.highlight_groups, &self.defined_styles);
+                self.popup_menu = Some(PopupMenu::new(items, selected, anchor, &query, styles));
+                self.queue_popup_menu();
+            }
+            RedrawEvent::PopupMenuSelect { selected } => {
+                tracy_zone!("EditorPopupMenuSelect");
+                if let Some(popup_menu) = &mut self.popup_menu {
+                    popup_menu.select(selected);
+                    self.queue_popup_menu();
+                }
+            }
+            RedrawEvent::PopupMenuHide => {
+                tracy_zone!("EditorPopupMenuHide");
+                self.popup_menu = None;
+                self.queue_popup_menu();
+            }
+            RedrawEvent::WildmenuShow { items } => {
+                tracy_zone!("EditorWildmenuShow");
+                let styles = PopupMenuStyles::resolve(&self.highlight_groups, &self.defined_styles);
+                self.wildmenu = Some(Wildmenu::new(items, styles));
+                self.queue_wildmenu();
+            }
+            RedrawEvent::WildmenuSelect { selected } => {
+                tracy_zone!("EditorWildmenuSelect");
+                if let Some(wildmenu) = &mut self.wildmenu {
+                    wildmenu.select(selected);
+                    self.queue_wildmenu();
+                }
+            }
+            RedrawEvent::WildmenuHide => {
+                tracy_zone!("EditorWildmenuHide");
+                self.wildmenu = None;
+                self.queue_wildmenu();
+            }
+            RedrawEvent::MessageShow {
+                kind,
+                content,
+                replace_last,
+            } => {
+                tracy_zone!("EditorMessageShow");
+                if let Some(message) =
+                    self.messages
+                        .show(kind, content, replace_last, &self.defined_styles)
+                {
+                    self.draw_command_batcher
+                        .queue(DrawCommand::ShowMessage(message));
+                }
+            }
+            RedrawEvent::MessageClear => {
+                tracy_zone!("EditorMessageClear");
+                self.messages.clear();
+                self.draw_command_batcher.queue(DrawCommand::ClearMessages);
+            }
             // Interpreting suspend as a window minimize request
             RedrawEvent::Suspend => {
                 let _ = self
                     .event_loop_proxy
                     .send_event(WindowCommand::Minimize.into());
             }
+            RedrawEvent::TitleInfo { info } => {
+                self.title.set_info(info);
+            }
+            RedrawEvent::TerminalWindows { windows } => {
+                self.terminal_windows = windows.into_iter().collect();
+                let grids = self.windows.keys().copied().collect::<Vec<_>>();
+                for grid in grids {
+                    self.update_window_type(grid);
+                }
+            }
             _ => {}
         };
     }
 
+    /// Marks the windows showing a terminal buffer, so that they can be drawn with their own
+    /// font. Floating windows are sized by their config, so they always use the main font.
+    fn update_window_type(&mut self, grid: u64) {
+        let is_terminal = self
+            .window_handles
+            .get(&grid)
+            .is_some_and(|handle| self.terminal_windows.contains(handle));
+        if let Some(window) = self.windows.get_mut(&grid) {
+            let window_type = match window.window_type {
+                WindowType::Message { .. } => return,
+                _ if is_terminal && window.anchor_info.is_none() => WindowType::Terminal,
+                _ => WindowType::Editor,
+            };
+            window.set_window_type(window_type);
+        }
+    }
+
+    fn queue_command_line(&mut self) {
+        let lines = if self.command_line.is_visible() {
+            self.command_line.lines()
+        } else {
+            Vec::new()
+        };
+        self.draw_command_batcher
+            .queue(DrawCommand::CommandLineChanged(lines));
+    }
+
+    /// The text typed since the start of the completion, read back from the grid between the
+    /// column the popup is anchored at and the cursor.
+    fn popup_menu_query(&self, anchor: PopupMenuAnchor) -> String {
+        let PopupMenuAnchor::Grid { grid, row, column } = anchor else {
+            return String::new();
+        };
+        let (cursor_column, cursor_row) = self.cursor.grid_position;
+        if self.cursor.parent_window_id != grid || cursor_row != row || cursor_column <= column {
+            return String::new();
+        }
+        self.windows
+            .get(&grid)
+            .map(|window| window.get_row_text(row, column, cursor_column))
+            .unwrap_or_default()
+    }
+
+    fn queue_popup_menu(&mut self) {
+        self.draw_command_batcher
+            .queue(DrawCommand::PopupMenuChanged(self.popup_menu.clone()));
+    }
+
+    fn queue_wildmenu(&mut self) {
+        self.draw_command_batcher
+            .queue(DrawCommand::WildmenuChanged(self.wildmenu.clone()));
+    }
+
     fn close_window(&mut self, grid: u64) {
+        self.window_handles.remove(&grid);
+        self.composition_order.remove(grid);
         if let Some(window) = self.windows.remove(&grid) {
             window.close();
             self.draw_command_batcher
@@ -371,14 +704,14 @@ impl Editor {
                 let anchor_type = anchor_info.anchor_type.clone();
                 let anchor_left = anchor_info.anchor_left;
                 let anchor_top = anchor_info.anchor_top;
-                let sort_order = anchor_info.sort_order.clone();
+                let z_index = anchor_info.sort_order.z_index;
                 self.set_window_float_position(
                     grid,
                     anchor_grid_id,
                     anchor_type,
                     anchor_left,
                     anchor_top,
-                    sort_order,
+                    z_index,
                 )
             }
         } else {
@@ -425,12 +758,17 @@ impl Editor {
         anchor_type: WindowAnchor,
         anchor_left: f64,
         anchor_top: f64,
-        sort_order: SortOrder,
+        z_index: u64,
     ) {
         if anchor_grid == grid {
-            warn!("NeoVim requested a window to float relative to itself. This is not supported.");
+            self.multigrid_diagnostics
+                .record(MultigridAnomaly::SelfAnchored { grid });
             return;
         }
+        if !self.windows.contains_key(&anchor_grid) {
+            self.multigrid_diagnostics
+                .record(MultigridAnomaly::MissingAnchor { grid, anchor_grid });
+        }
 
         let parent_position = self.get_window_top_left(anchor_grid);
         if let Some(window) = self.windows.get_mut(&grid) {
@@ -444,17 +782,9 @@ impl Editor {
                 modified_top += parent_top;
             }
 
-            // Only update the sort order if it's the first position request (no anchor_info), or
-            // the z_index changes
-            let sort_order = if let Some(anchor_info) = &window.anchor_info {
-                if sort_order.z_index == anchor_info.sort_order.z_index {
-                    anchor_info.sort_order.clone()
-                } else {
-                    sort_order
-                }
-            } else {
-                sort_order
-            };
+            let sort_order =
+                self.composition_order
+                    .put(grid, z_index, self.cursor.parent_window_id);
 
             window.position(
                 Some(AnchorInfo {
@@ -469,7 +799,31 @@ impl Editor {
             );
             window.show();
         } else {
-            error!("Attempted to float window that does not exist.");
+            self.multigrid_diagnostics
+                .record(MultigridAnomaly::MissingGrid { grid });
+        }
+    }
+
+    /// Sends the sort orders of the windows whose place in the stacking changed, which includes the
+    /// ones under a window that was raised, put in between or removed.
+    fn queue_sort_orders(&mut self) {
+        let changed = self.composition_order.take_changed();
+        if changed.is_empty() {
+            return;
+        }
+        debug!("Composition order: {}", self.composition_order.dump());
+        for (grid, sort_order) in changed {
+            if let Some(anchor_info) = self
+                .windows
+                .get_mut(&grid)
+                .and_then(|window| window.anchor_info.as_mut())
+            {
+                anchor_info.sort_order = sort_order.clone();
+            }
+            self.draw_command_batcher.queue(DrawCommand::Window {
+                grid_id: grid,
+                command: WindowDrawCommand::SortOrder(sort_order),
+            });
         }
     }
 
@@ -486,10 +840,9 @@ impl Editor {
             anchor_type: WindowAnchor::NorthWest,
             anchor_left: 0.0,
             anchor_top: grid_top as f64,
-            sort_order: SortOrder {
-                z_index,
-                composition_order: self.composition_order,
-            },
+            sort_order: self
+                .composition_order
+                .put(grid, z_index, self.cursor.parent_window_id),
         };
 
         if let Some(window) = self.windows.get_mut(&grid) {
@@ -542,14 +895,9 @@ impl Editor {
     fn set_cursor_position(&mut self, grid: u64, grid_left: u64, grid_top: u64) {
         let mut window = self.windows.get_mut(&grid);
         if let Some(window) = &mut window {
-            if let Some(anchor) = window.anchor_info.as_mut() {
+            if window.anchor_info.is_some() {
                 // Neovim moves a window to the top of the layer each time the cursor enters it, so do the same here as well
-                self.composition_order += 1;
-                anchor.sort_order.composition_order = self.composition_order;
-                self.draw_command_batcher.queue(DrawCommand::Window {
-                    grid_id: grid,
-                    command: WindowDrawCommand::SortOrder(anchor.sort_order.clone()),
-                });
+                self.composition_order.raise(grid);
             }
         }
 
@@ -617,6 +965,10 @@ impl Editor {
                     self.redraw_screen();
                 }
             }
+            GuiOption::ShowTabLine(show_tabline) => {
+                // Neovim sends the tab line again after the option changes
+                self.show_tabline = show_tabline;
+            }
             GuiOption::LineSpace(linespace) => {
                 self.draw_command_batcher
                     .queue(DrawCommand::LineSpaceChanged(linespace as f32));
@@ -655,21 +1007,32 @@ pub fn start_editor(
     );
     thread::spawn(move || {
         let mut editor = Editor::new(event_loop_proxy, settings.clone());
+        // Only used to wait for the events with a timeout, when a flush is held back
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("Could not start the editor runtime");
 
-        while let Some(editor_command) = receiver.blocking_recv() {
-            editor.handle_redraw_event(editor_command);
+        loop {
+            let deadline = editor.terminal_coalescing.deadline();
+            let editor_command = runtime.block_on(async {
+                match deadline {
+                    Some(deadline) => {
+                        tokio::time::timeout_at(deadline.into(), receiver.recv()).await
+                    }
+                    None => Ok(receiver.recv().await),
+                }
+            });
+            match editor_command {
+                Ok(Some(editor_command)) => editor.handle_redraw_event(editor_command),
+                Ok(None) => break,
+                Err(_) => editor.send_deferred_flush(),
+            }
         }
     });
     handler
 }
 
-/// Based on formula in https://graphicdesign.stackexchange.com/questions/62368/automatically-select-a-foreground-color-based-on-a-background-color
-/// Check if the color is light or dark
-#[cfg(target_os = "macos")]
-fn is_light_color(color: &Color4f) -> bool {
-    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b > 0.5
-}
-
 /// Get the proper dark/light theme for a background_color.
 #[cfg(target_os = "macos")]
 fn window_theme_for_background(background_color: Option<Color4f>) -> Option<Theme> {
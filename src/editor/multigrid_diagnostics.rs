@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+use log::warn;
+
+/// Window positions requested by Neovim that can't be honored, which usually come from a plugin
+/// not written with `ext_multigrid` in mind and leave its floats misplaced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MultigridAnomaly {
+    /// A float anchored to its own grid.
+    SelfAnchored { grid: u64 },
+    /// A float anchored to a grid Neovim never drew or already closed.
+    MissingAnchor { grid: u64, anchor_grid: u64 },
+    /// A float position for a grid Neovim never drew.
+    MissingGrid { grid: u64 },
+}
+
+impl MultigridAnomaly {
+    pub fn describe(&self) -> String {
+        match self {
+            MultigridAnomaly::SelfAnchored { grid } => {
+                format!("Grid {grid} floats relative to itself")
+            }
+            MultigridAnomaly::MissingAnchor { grid, anchor_grid } => {
+                format!("Grid {grid} floats relative to grid {anchor_grid}, which doesn't exist")
+            }
+            MultigridAnomaly::MissingGrid { grid } => {
+                format!("Grid {grid} was positioned before it was drawn")
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            MultigridAnomaly::SelfAnchored { .. } => "self_anchored",
+            MultigridAnomaly::MissingAnchor { .. } => "missing_anchor",
+            MultigridAnomaly::MissingGrid { .. } => "missing_grid",
+        }
+    }
+}
+
+/// Collects the anomalies until the next flush, so that they are shown together in one warning.
+/// Each kind is only shown once, a plugin doing it for every float it opens would otherwise bring
+/// the warning back all the time. They are all logged still.
+#[derive(Default)]
+pub struct MultigridDiagnostics {
+    shown_kinds: HashSet<&'static str>,
+    pending: Vec<MultigridAnomaly>,
+}
+
+impl MultigridDiagnostics {
+    pub fn record(&mut self, anomaly: MultigridAnomaly) {
+        warn!("{}, floating windows may be misplaced", anomaly.describe());
+        if !self.shown_kinds.contains(anomaly.kind()) && !self.pending.contains(&anomaly) {
+            self.pending.push(anomaly);
+        }
+    }
+
+    /// Returns the anomalies to warn about since the last flush.
+    pub fn take_pending(&mut self) -> Vec<MultigridAnomaly> {
+        for anomaly in &self.pending {
+            self.shown_kinds.insert(anomaly.kind());
+        }
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_kind_is_shown_once() {
+        let mut diagnostics = MultigridDiagnostics::default();
+        diagnostics.record(MultigridAnomaly::SelfAnchored { grid: 4 });
+        diagnostics.record(MultigridAnomaly::SelfAnchored { grid: 4 });
+        diagnostics.record(MultigridAnomaly::SelfAnchored { grid: 5 });
+        diagnostics.record(MultigridAnomaly::MissingGrid { grid: 6 });
+        assert_eq!(
+            diagnostics.take_pending(),
+            vec![
+                MultigridAnomaly::SelfAnchored { grid: 4 },
+                MultigridAnomaly::SelfAnchored { grid: 5 },
+                MultigridAnomaly::MissingGrid { grid: 6 },
+            ]
+        );
+        assert!(diagnostics.take_pending().is_empty());
+
+        diagnostics.record(MultigridAnomaly::SelfAnchored { grid: 7 });
+        diagnostics.record(MultigridAnomaly::MissingAnchor {
+            grid: 8,
+            anchor_grid: 3,
+        });
+        assert_eq!(
+            diagnostics.take_pending(),
+            vec![MultigridAnomaly::MissingAnchor {
+                grid: 8,
+                anchor_grid: 3
+            }]
+        );
+    }
+}
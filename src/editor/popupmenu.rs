@@ -0,0 +1,185 @@
+use std::{collections::HashMap, sync::Arc};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    bridge::PopupMenuItem,
+    editor::style::{Colors, Style},
+};
+
+/// Where Neovim wants the completion popup to be shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PopupMenuAnchor {
+    /// Below (or above) the cell at `row` and `column` of the grid.
+    Grid { grid: u64, row: u64, column: u64 },
+    /// Below (or above) the command line, starting at `column` of its text.
+    CommandLine { column: u64 },
+}
+
+/// A completion item, ready to be drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PopupMenuEntry {
+    pub word: String,
+    pub kind: String,
+    pub menu: String,
+    /// The grapheme indices of `word` matching what was typed so far.
+    pub matches: Vec<usize>,
+}
+
+/// The highlights used to draw the popup, resolved from the `Pmenu*` highlight groups.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PopupMenuStyles {
+    pub normal: Option<Arc<Style>>,
+    pub selected: Option<Arc<Style>>,
+    pub kind: Option<Arc<Style>>,
+    pub kind_selected: Option<Arc<Style>>,
+    pub extra: Option<Arc<Style>>,
+    pub extra_selected: Option<Arc<Style>>,
+    pub matched: Option<Arc<Style>>,
+    pub matched_selected: Option<Arc<Style>>,
+    pub scrollbar: Option<Arc<Style>>,
+    pub thumb: Option<Arc<Style>>,
+}
+
+impl PopupMenuStyles {
+    pub fn resolve(
+        highlight_groups: &HashMap<String, u64>,
+        defined_styles: &HashMap<u64, Arc<Style>>,
+    ) -> Self {
+        let get = |name: &str| {
+            highlight_groups
+                .get(name)
+                .and_then(|id| defined_styles.get(id))
+                .cloned()
+        };
+        let normal = get("Pmenu");
+        let selected = get("PmenuSel");
+        // Without a match highlight, matches are shown in bold
+        let bold = |style: &Option<Arc<Style>>| {
+            let mut style = style
+                .as_deref()
+                .cloned()
+                .unwrap_or_else(|| Style::new(Colors::new(None, None, None)));
+            style.bold = true;
+            Some(Arc::new(style))
+        };
+        Self {
+            kind: get("PmenuKind").or_else(|| normal.clone()),
+            kind_selected: get("PmenuKindSel").or_else(|| selected.clone()),
+            extra: get("PmenuExtra").or_else(|| normal.clone()),
+            extra_selected: get("PmenuExtraSel").or_else(|| selected.clone()),
+            matched: get("PmenuMatch").or_else(|| bold(&normal)),
+            matched_selected: get("PmenuMatchSel").or_else(|| bold(&selected)),
+            scrollbar: get("PmenuSbar").or_else(|| normal.clone()),
+            thumb: get("PmenuThumb").or_else(|| selected.clone()),
+            normal,
+            selected,
+        }
+    }
+}
+
+/// The completion popup of Neovim's externalized popup menu (`ext_popupmenu`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PopupMenu {
+    pub entries: Vec<PopupMenuEntry>,
+    pub selected: Option<usize>,
+    pub anchor: PopupMenuAnchor,
+    pub styles: PopupMenuStyles,
+}
+
+impl PopupMenu {
+    /// Builds the menu, highlighting the characters of each item that match `query`.
+    pub fn new(
+        items: Vec<PopupMenuItem>,
+        selected: Option<u64>,
+        anchor: PopupMenuAnchor,
+        query: &str,
+        styles: PopupMenuStyles,
+    ) -> Self {
+        let entries = items
+            .into_iter()
+            .map(|item| PopupMenuEntry {
+                matches: fuzzy_match_positions(query, &item.word),
+                word: item.word,
+                kind: item.kind,
+                menu: item.menu,
+            })
+            .collect::<Vec<_>>();
+        let selected = selected
+            .map(|index| index as usize)
+            .filter(|index| *index < entries.len());
+        Self {
+            entries,
+            selected,
+            anchor,
+            styles,
+        }
+    }
+
+    pub fn select(&mut self, selected: Option<u64>) {
+        self.selected = selected
+            .map(|index| index as usize)
+            .filter(|index| *index < self.entries.len());
+    }
+}
+
+/// Returns the grapheme indices of `word` matching the characters of `query` in order, ignoring
+/// case, or nothing when `word` doesn't contain all of them.
+fn fuzzy_match_positions(query: &str, word: &str) -> Vec<usize> {
+    let query = query
+        .graphemes(true)
+        .filter(|grapheme| !grapheme.trim().is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut positions = Vec::with_capacity(query.len());
+    for (index, grapheme) in word.graphemes(true).enumerate() {
+        if positions.len() == query.len() {
+            break;
+        }
+        if grapheme.to_lowercase() == query[positions.len()] {
+            positions.push(index);
+        }
+    }
+    if positions.len() == query.len() {
+        positions
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_follow_the_query_order() {
+        assert_eq!(fuzzy_match_positions("fb", "FooBar"), vec![0, 3]);
+        assert_eq!(fuzzy_match_positions("oa", "FooBar"), vec![1, 4]);
+        assert_eq!(fuzzy_match_positions("bf", "FooBar"), Vec::<usize>::new());
+        assert_eq!(fuzzy_match_positions("", "FooBar"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn selection_outside_the_items_is_ignored() {
+        let item = PopupMenuItem {
+            word: "word".to_string(),
+            kind: String::new(),
+            menu: String::new(),
+            info: String::new(),
+        };
+        let mut menu = PopupMenu::new(
+            vec![item],
+            Some(3),
+            PopupMenuAnchor::CommandLine { column: 0 },
+            "",
+            PopupMenuStyles::default(),
+        );
+        assert_eq!(menu.selected, None);
+        menu.select(Some(0));
+        assert_eq!(menu.selected, Some(0));
+    }
+}
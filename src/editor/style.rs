@@ -31,6 +31,10 @@ pub struct Style {
     pub blend: u8,
     #[new(default)]
     pub underline: Option<UnderlineStyle>,
+    /// The names of the highlight groups combined into this style, from `ext_hlstate`, like
+    /// `Comment` or `DiagnosticUnderlineError`.
+    #[new(default)]
+    pub highlight_groups: Vec<String>,
 }
 
 impl Style {
@@ -58,11 +62,58 @@ impl Style {
         }
     }
 
+    /// Whether the style comes from the highlight group `name`, or one linked to it.
+    pub fn has_highlight_group(&self, name: &str) -> bool {
+        self.highlight_groups.iter().any(|group| group == name)
+    }
+
+    /// Whether the style is the one of virtual text, like inlay hints or diagnostics shown at the
+    /// end of the line, going by groups like `LspInlayHint` or `DiagnosticVirtualTextError`.
+    pub fn is_hint(&self) -> bool {
+        self.highlight_groups
+            .iter()
+            .any(|group| group.contains("InlayHint") || group.contains("VirtualText"))
+    }
+
+    /// Whether the style highlights the line of the cursor, with `CursorLine`, or the parts of it
+    /// `'cursorlineopt'` picks, like `CursorLineNr`. It follows the cursor instead of the text.
+    pub fn is_cursor_line(&self) -> bool {
+        self.highlight_groups
+            .iter()
+            .any(|group| group.starts_with("CursorLine"))
+    }
+
+    /// The style without the background of the cursor line, like the line looks without the
+    /// cursor on it.
+    pub fn without_cursor_line(&self) -> Style {
+        let mut style = self.clone();
+        style.colors.background = None;
+        style
+            .highlight_groups
+            .retain(|group| !group.starts_with("CursorLine"));
+        style
+    }
+
     pub fn special(&self, default_colors: &Colors) -> Color4f {
         self.colors
             .special
             .unwrap_or_else(|| self.foreground(default_colors))
     }
+
+    /// Whether text in both styles is drawn the same, so that it can be shaped together, keeping
+    /// the ligatures across highlight groups. Of the groups, only the ones changing how the text is
+    /// drawn count.
+    pub fn draws_like(&self, other: &Style) -> bool {
+        self.colors == other.colors
+            && self.reverse == other.reverse
+            && self.italic == other.italic
+            && self.bold == other.bold
+            && self.strikethrough == other.strikethrough
+            && self.blend == other.blend
+            && self.underline == other.underline
+            && self.is_hint() == other.is_hint()
+            && self.is_cursor_line() == other.is_cursor_line()
+    }
 }
 
 #[cfg(test)]
@@ -154,4 +205,47 @@ mod tests {
             style.foreground(&DEFAULT_COLORS),
         );
     }
+
+    #[test]
+    fn test_is_hint() {
+        let mut style = Style::new(COLORS);
+        assert!(!style.is_hint());
+        style.highlight_groups = vec!["Comment".to_string()];
+        assert!(!style.is_hint());
+        style.highlight_groups.push("LspInlayHint".to_string());
+        assert!(style.is_hint());
+        style.highlight_groups = vec!["DiagnosticVirtualTextWarn".to_string()];
+        assert!(style.is_hint());
+    }
+
+    #[test]
+    fn test_cursor_line() {
+        let mut style = Style::new(COLORS);
+        assert!(!style.is_cursor_line());
+        style.highlight_groups = vec!["Comment".to_string(), "CursorLine".to_string()];
+        assert!(style.is_cursor_line());
+        style.highlight_groups = vec!["CursorLineNr".to_string()];
+        assert!(style.is_cursor_line());
+
+        let style = style.without_cursor_line();
+        assert!(!style.is_cursor_line());
+        assert_eq!(style.colors.background, None);
+        assert_eq!(style.colors.foreground, COLORS.foreground);
+    }
+
+    #[test]
+    fn test_draws_like() {
+        let mut keyword = Style::new(COLORS);
+        keyword.highlight_groups = vec!["Keyword".to_string()];
+        let mut operator = keyword.clone();
+        operator.highlight_groups = vec!["Operator".to_string()];
+        assert!(keyword.draws_like(&operator));
+
+        let mut hint = keyword.clone();
+        hint.highlight_groups = vec!["LspInlayHint".to_string()];
+        assert!(!keyword.draws_like(&hint));
+
+        operator.bold = true;
+        assert!(!keyword.draws_like(&operator));
+    }
 }
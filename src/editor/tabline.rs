@@ -0,0 +1,122 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use crate::{bridge::TablineTab, editor::style::Style};
+
+/// A tab of the tab bar, with the name of the buffer shown in its current window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tab {
+    pub title: String,
+}
+
+/// The highlights used to draw the tab bar, resolved from the `TabLine*` highlight groups.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TablineStyles {
+    pub normal: Option<Arc<Style>>,
+    pub selected: Option<Arc<Style>>,
+    pub fill: Option<Arc<Style>>,
+}
+
+impl TablineStyles {
+    pub fn resolve(
+        highlight_groups: &HashMap<String, u64>,
+        defined_styles: &HashMap<u64, Arc<Style>>,
+    ) -> Self {
+        let get = |name: &str| {
+            highlight_groups
+                .get(name)
+                .and_then(|id| defined_styles.get(id))
+                .cloned()
+        };
+        Self {
+            normal: get("TabLine"),
+            selected: get("TabLineSel"),
+            fill: get("TabLineFill"),
+        }
+    }
+}
+
+/// The tab pages of Neovim's externalized tab line (`ext_tabline`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tabline {
+    pub tabs: Vec<Tab>,
+    pub current: usize,
+    pub styles: TablineStyles,
+}
+
+impl Tabline {
+    /// Returns the tab bar to show, following 'showtabline', or `None` when it should be hidden.
+    pub fn new(
+        current_tab: u64,
+        tabs: Vec<TablineTab>,
+        show_tabline: u64,
+        styles: TablineStyles,
+    ) -> Option<Self> {
+        let visible = match show_tabline {
+            0 => false,
+            1 => tabs.len() > 1,
+            _ => !tabs.is_empty(),
+        };
+        if !visible {
+            return None;
+        }
+
+        let current = tabs
+            .iter()
+            .position(|tab| tab.handle == current_tab)
+            .unwrap_or_default();
+        let tabs = tabs
+            .into_iter()
+            .map(|tab| Tab {
+                title: tab_title(&tab.name),
+            })
+            .collect();
+        Some(Self {
+            tabs,
+            current,
+            styles,
+        })
+    }
+}
+
+/// Only the file name is shown, like the tab line drawn by Neovim.
+fn tab_title(name: &str) -> String {
+    if name.is_empty() {
+        return "[No Name]".to_string();
+    }
+    Path::new(name)
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tabs(count: u64) -> Vec<TablineTab> {
+        (1..=count)
+            .map(|handle| TablineTab {
+                handle,
+                name: format!("/home/user/file{handle}.rs"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn follows_showtabline() {
+        let styles = TablineStyles::default();
+        assert!(Tabline::new(1, tabs(1), 0, styles.clone()).is_none());
+        assert!(Tabline::new(1, tabs(2), 0, styles.clone()).is_none());
+        assert!(Tabline::new(1, tabs(1), 1, styles.clone()).is_none());
+        assert!(Tabline::new(1, tabs(2), 1, styles.clone()).is_some());
+        assert!(Tabline::new(1, tabs(1), 2, styles).is_some());
+    }
+
+    #[test]
+    fn shows_the_file_names() {
+        let tabline = Tabline::new(2, tabs(2), 1, TablineStyles::default()).unwrap();
+        assert_eq!(tabline.current, 1);
+        assert_eq!(tabline.tabs[0].title, "file1.rs");
+        assert_eq!(tab_title(""), "[No Name]");
+    }
+}
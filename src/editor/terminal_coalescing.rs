@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+/// Holds back the flushes of fast terminal output, so that the grid is sent at most once per frame.
+/// A `:terminal` printing a lot scrolls and redraws its lines over many flushes, and sending each of
+/// them makes the renderer animate scrolls that the next flush already undoes, which looks like the
+/// scrollback jumping. The batch of a held flush keeps growing until the frame interval is over.
+#[derive(Default)]
+pub struct TerminalCoalescing {
+    /// Whether a terminal grid scrolled or got new lines since the last flush.
+    terminal_updated: bool,
+    last_sent: Option<Instant>,
+    deferred_until: Option<Instant>,
+}
+
+impl TerminalCoalescing {
+    pub fn terminal_updated(&mut self) {
+        self.terminal_updated = true;
+    }
+
+    /// Whether the batch of a flush at `now` should be sent, or held until `deadline`.
+    pub fn flush(&mut self, now: Instant, interval: Duration) -> bool {
+        let terminal_updated = std::mem::take(&mut self.terminal_updated);
+        if self.deferred_until.is_some() {
+            return false;
+        }
+        if terminal_updated {
+            if let Some(last_sent) = self.last_sent {
+                if now.duration_since(last_sent) < interval {
+                    self.deferred_until = Some(last_sent + interval);
+                    return false;
+                }
+            }
+        }
+        self.last_sent = Some(now);
+        true
+    }
+
+    /// When the held batch has to be sent, if there's one.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deferred_until
+    }
+
+    /// The held batch is sent at `now`.
+    pub fn send_deferred(&mut self, now: Instant) {
+        self.deferred_until = None;
+        self.last_sent = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terminal_bursts_are_sent_once_per_frame() {
+        let start = Instant::now();
+        let at = |milliseconds| start + Duration::from_millis(milliseconds);
+        let interval = Duration::from_millis(16);
+        let mut coalescing = TerminalCoalescing::default();
+
+        coalescing.terminal_updated();
+        assert!(coalescing.flush(at(0), interval));
+        coalescing.terminal_updated();
+        assert!(!coalescing.flush(at(2), interval));
+        assert_eq!(coalescing.deadline(), Some(at(16)));
+        // Everything waits for the held batch, even what isn't terminal output
+        assert!(!coalescing.flush(at(4), interval));
+        coalescing.send_deferred(at(16));
+        assert_eq!(coalescing.deadline(), None);
+
+        // Other flushes and slow output are sent right away
+        assert!(coalescing.flush(at(18), interval));
+        coalescing.terminal_updated();
+        assert!(coalescing.flush(at(40), interval));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::bridge::EditorMode;
+
+/// What `g:neovide_title_template` can show besides the title set by Neovim, sent by the runtime
+/// whenever the current buffer or directory changes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TitleInfo {
+    pub file: String,
+    pub cwd: String,
+    pub modified: bool,
+}
+
+/// Keeps the parts of the title, and builds it from `g:neovide_title_template` when one is set.
+#[derive(Default)]
+pub struct Title {
+    neovim_title: String,
+    info: TitleInfo,
+    mode: String,
+    sent: Option<String>,
+}
+
+fn mode_name(mode: &EditorMode) -> &str {
+    match mode {
+        EditorMode::Normal => "NORMAL",
+        EditorMode::Insert => "INSERT",
+        EditorMode::Visual => "VISUAL",
+        EditorMode::Replace => "REPLACE",
+        EditorMode::CmdLine => "COMMAND",
+        EditorMode::Unknown(name) => name,
+    }
+}
+
+/// Replaces the placeholders of `template`. The unknown ones are kept as they are, so that a typo
+/// shows up in the title.
+fn expand_template(template: &str, neovim_title: &str, info: &TitleInfo, mode: &str) -> String {
+    let file = if info.file.is_empty() {
+        "[No Name]"
+    } else {
+        &info.file
+    };
+    let modified = if info.modified { "[+]" } else { "" };
+    let mut title = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        title.push_str(&rest[..start]);
+        let placeholder = rest[start..]
+            .find('}')
+            .map(|end| &rest[start..=start + end]);
+        let value = placeholder.and_then(|placeholder| match placeholder {
+            "{title}" => Some(neovim_title),
+            "{file}" => Some(file),
+            "{cwd}" => Some(info.cwd.as_str()),
+            "{modified}" => Some(modified),
+            "{mode}" => Some(mode),
+            _ => None,
+        });
+        match (placeholder, value) {
+            (Some(placeholder), Some(value)) => {
+                title.push_str(value);
+                rest = &rest[start + placeholder.len()..];
+            }
+            _ => {
+                title.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    title.push_str(rest);
+    title.trim().to_string()
+}
+
+impl Title {
+    pub fn set_neovim_title(&mut self, title: String) {
+        self.neovim_title = title;
+    }
+
+    pub fn set_info(&mut self, info: TitleInfo) {
+        self.info = info;
+    }
+
+    pub fn set_mode(&mut self, mode: &EditorMode) {
+        self.mode = mode_name(mode).to_string();
+    }
+
+    /// The title to show with `template`, or `None` when it's already shown.
+    pub fn update(&mut self, template: &str) -> Option<String> {
+        let title = if template.is_empty() {
+            self.neovim_title.clone()
+        } else {
+            expand_template(template, &self.neovim_title, &self.info, &self.mode)
+        };
+        let title = if title.is_empty() {
+            "Neovide".to_string()
+        } else {
+            title
+        };
+        if self.sent.as_ref() == Some(&title) {
+            return None;
+        }
+        self.sent = Some(title.clone());
+        Some(title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_templates_are_expanded() {
+        let mut title = Title::default();
+        title.set_neovim_title("main.rs - NVIM".to_string());
+        title.set_info(TitleInfo {
+            file: "src/main.rs".to_string(),
+            cwd: "~/neovide".to_string(),
+            modified: true,
+        });
+        title.set_mode(&EditorMode::Insert);
+
+        assert_eq!(title.update(""), Some("main.rs - NVIM".to_string()));
+        assert_eq!(title.update(""), None);
+        assert_eq!(
+            title.update("{file}{modified} ({cwd}) -- {mode} --"),
+            Some("src/main.rs[+] (~/neovide) -- INSERT --".to_string())
+        );
+        assert_eq!(
+            title.update("{title} {unknown} {"),
+            Some("main.rs - NVIM {unknown} {".to_string())
+        );
+
+        title.set_info(TitleInfo::default());
+        assert_eq!(
+            title.update("{file} {modified}"),
+            Some("[No Name]".to_string())
+        );
+        title.set_neovim_title(String::new());
+        assert_eq!(title.update("{title}"), Some("Neovide".to_string()));
+    }
+}
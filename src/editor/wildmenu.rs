@@ -0,0 +1,42 @@
+use crate::editor::PopupMenuStyles;
+
+/// The command line completions of Neovim's externalized wildmenu (`ext_wildmenu`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wildmenu {
+    pub items: Vec<String>,
+    pub selected: Option<usize>,
+    /// The wildmenu is drawn like the completion popup, with the `Pmenu*` highlights.
+    pub styles: PopupMenuStyles,
+}
+
+impl Wildmenu {
+    pub fn new(items: Vec<String>, styles: PopupMenuStyles) -> Self {
+        Self {
+            items,
+            selected: None,
+            styles,
+        }
+    }
+
+    pub fn select(&mut self, selected: Option<u64>) {
+        self.selected = selected
+            .map(|index| index as usize)
+            .filter(|index| *index < self.items.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_outside_the_items_is_ignored() {
+        let items = vec!["edit".to_string(), "enew".to_string()];
+        let mut wildmenu = Wildmenu::new(items, PopupMenuStyles::default());
+        assert_eq!(wildmenu.selected, None);
+        wildmenu.select(Some(1));
+        assert_eq!(wildmenu.selected, Some(1));
+        wildmenu.select(Some(2));
+        assert_eq!(wildmenu.selected, None);
+    }
+}
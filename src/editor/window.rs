@@ -5,14 +5,40 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     bridge::GridLineCell,
-    editor::{grid::CharacterGrid, style::Style, AnchorInfo, DrawCommand, DrawCommandBatcher},
+    editor::{
+        grid::{CharacterGrid, GridCell},
+        style::Style,
+        AnchorInfo, DrawCommand, DrawCommandBatcher,
+    },
     renderer::{LineFragment, WindowDrawCommand},
 };
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum WindowType {
     Editor,
-    Message { scrolled: bool },
+    Message {
+        scrolled: bool,
+    },
+    /// A window showing a terminal buffer, which may be drawn with its own font.
+    Terminal,
+}
+
+/// The most bytes of text kept for a single cell. Even the longest emoji sequences fit easily,
+/// longer cells are malformed and would only slow down the shaper.
+const MAX_CELL_TEXT_BYTES: usize = 64;
+
+/// Cuts the text of a cell to `MAX_CELL_TEXT_BYTES`, at a character boundary.
+fn truncate_cell_text(text: &str) -> &str {
+    if text.len() <= MAX_CELL_TEXT_BYTES {
+        return text;
+    }
+    let end = text
+        .char_indices()
+        .map(|(index, character)| index + character.len_utf8())
+        .take_while(|end| *end <= MAX_CELL_TEXT_BYTES)
+        .last()
+        .unwrap_or(0);
+    &text[..end]
 }
 
 pub struct Window {
@@ -22,6 +48,9 @@ pub struct Window {
 
     pub anchor_info: Option<AnchorInfo>,
     grid_position: (f64, f64),
+    /// The size Neovim laid the window out with. Terminal windows can have a grid of a
+    /// different size when drawn with their own font.
+    layout_size: (u64, u64),
 
     draw_command_batcher: Rc<DrawCommandBatcher>,
 }
@@ -41,6 +70,7 @@ impl Window {
             window_type,
             anchor_info,
             grid_position,
+            layout_size: grid_size,
             draw_command_batcher,
         };
         window.send_updated_position();
@@ -58,6 +88,7 @@ impl Window {
         self.send_command(WindowDrawCommand::Position {
             grid_position: self.grid_position,
             grid_size: (self.grid.width as u64, self.grid.height as u64),
+            layout_size: self.layout_size,
             anchor_info: self.anchor_info.clone(),
             window_type: self.window_type,
         });
@@ -84,6 +115,17 @@ impl Window {
         (grid_cell.0, grid_cell.1, double_width)
     }
 
+    /// The text of the cells of `row` from `start` up to, but not including, `end`.
+    pub fn get_row_text(&self, row: u64, start: u64, end: u64) -> String {
+        if row >= self.get_height() {
+            return String::new();
+        }
+        (start..end)
+            .filter_map(|column| self.grid.get_cell(column as usize, row as usize))
+            .map(|(character, _)| character.as_str())
+            .collect()
+    }
+
     pub fn get_width(&self) -> u64 {
         self.grid.width as u64
     }
@@ -102,13 +144,24 @@ impl Window {
         grid_size: (u64, u64),
         grid_position: (f64, f64),
     ) {
-        self.grid
-            .resize((grid_size.0 as usize, grid_size.1 as usize));
+        // The grid of a terminal window is sized by the renderer to fit its font
+        if self.window_type != WindowType::Terminal {
+            self.grid
+                .resize((grid_size.0 as usize, grid_size.1 as usize));
+        }
+        self.layout_size = grid_size;
         self.anchor_info = anchor_info;
         self.grid_position = grid_position;
         self.send_updated_position();
     }
 
+    pub fn set_window_type(&mut self, window_type: WindowType) {
+        if self.window_type != window_type {
+            self.window_type = window_type;
+            self.send_updated_position();
+        }
+    }
+
     pub fn resize(&mut self, new_size: (u64, u64)) {
         self.grid.resize((new_size.0 as usize, new_size.1 as usize));
         self.send_updated_position();
@@ -129,29 +182,31 @@ impl Window {
             None => previous_style.clone(),
         };
 
-        // Compute text.
-        let mut text = cell.text;
-        if let Some(times) = cell.repeat {
-            // Repeats of zero times should be ignored, they are mostly useful for terminal Neovim
-            // to distinguish between empty lines and lines ending with spaces.
-            if times == 0 {
-                return;
-            }
-            text = text.repeat(times as usize);
-        }
+        // Repeats of zero times should be ignored, they are mostly useful for terminal Neovim to
+        // distinguish between empty lines and lines ending with spaces.
+        let times = match cell.repeat {
+            Some(0) => return,
+            Some(times) => times as usize,
+            None => 1,
+        };
+        // Cells past the end of the line aren't stored, so there's no need to build them
+        let times = times.min(self.grid.width.saturating_sub(*column_pos));
+        let text = truncate_cell_text(&cell.text);
 
         // Insert the contents of the cell into the grid.
-        if text.is_empty() {
-            if let Some(cell) = self.grid.get_cell_mut(*column_pos, row_index) {
-                *cell = (text, style.clone());
-            }
-            *column_pos += 1;
-        } else {
-            for character in text.graphemes(true) {
+        for _ in 0..times {
+            if text.is_empty() {
                 if let Some(cell) = self.grid.get_cell_mut(*column_pos, row_index) {
-                    *cell = (character.to_string(), style.clone());
+                    *cell = (String::new(), style.clone());
                 }
                 *column_pos += 1;
+            } else {
+                for character in text.graphemes(true) {
+                    if let Some(cell) = self.grid.get_cell_mut(*column_pos, row_index) {
+                        *cell = (character.to_string(), style.clone());
+                    }
+                    *column_pos += 1;
+                }
             }
         }
 
@@ -160,17 +215,26 @@ impl Window {
 
     // Build a line fragment for the given row starting from current_start up until the next style
     // change or double width character.
-    fn build_line_fragment(&self, row_index: usize, start: usize) -> (usize, LineFragment) {
-        let row = self.grid.row(row_index).unwrap();
-
-        let (_, style) = &row[start];
+    fn build_line_fragment(&self, row: &[GridCell], start: usize) -> (usize, LineFragment) {
+        let style = row.get(start).and_then(|(_, style)| style.clone());
 
         let mut text = String::new();
         let mut width = 0;
 
         for (character, possible_end_style) in row.iter().take(self.grid.width).skip(start) {
+            // The second half of a double width character belongs to the first whatever its style,
+            // otherwise the glyph is drawn over a cell of another fragment and clipped with it.
+            let continuation = character.is_empty() && width > 0;
+
             // Style doesn't match. Draw what we've got.
-            if style != possible_end_style {
+            let same_style = match (&style, possible_end_style) {
+                (Some(style), Some(end_style)) => {
+                    Arc::ptr_eq(style, end_style) || style.draws_like(end_style)
+                }
+                (None, None) => true,
+                _ => false,
+            };
+            if !continuation && !same_style {
                 break;
             }
 
@@ -187,21 +251,25 @@ impl Window {
         let line_fragment = LineFragment {
             text,
             window_left: start as u64,
-            width: width as u64,
-            style: style.clone(),
+            width: width.max(1) as u64,
+            style,
         };
 
-        (start + width, line_fragment)
+        (start + width.max(1), line_fragment)
     }
 
     // Redraw line by calling build_line_fragment starting at 0
     // until current_start is greater than the grid width and sending the resulting
     // fragments as a batch.
     fn redraw_line(&self, row: usize) {
+        let Some(cells) = self.grid.row(row) else {
+            warn!(grid_id = self.grid_id, row = row; "Redraw of a line out of bounds");
+            return;
+        };
         let mut current_start = 0;
         let mut line_fragments = Vec::new();
         while current_start < self.grid.width {
-            let (next_start, line_fragment) = self.build_line_fragment(row, current_start);
+            let (next_start, line_fragment) = self.build_line_fragment(cells, current_start);
             current_start = next_start;
             line_fragments.push(line_fragment);
         }
@@ -244,7 +312,7 @@ impl Window {
                 self.redraw_line(row - 1);
             }
         } else {
-            warn!("Draw command out of bounds");
+            warn!(grid_id = self.grid_id, row = row; "Draw command out of bounds");
         }
     }
 
@@ -322,3 +390,335 @@ impl Window {
         self.send_command(WindowDrawCommand::Close);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::style::Colors;
+    use rand::*;
+    use skia_safe::Color4f;
+    use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+    const PATHOLOGICAL_TEXTS: &[&str] = &[
+        "",
+        "a",
+        "\u{301}",
+        "👨\u{200d}👩\u{200d}👧\u{200d}👦",
+        "🏳\u{fe0f}\u{200d}🌈",
+        "\u{200d}\u{200d}\u{200d}",
+        "\u{fe0f}",
+        "·",
+        "→",
+    ];
+
+    fn random_text(rng: &mut impl Rng) -> String {
+        let mut text = PATHOLOGICAL_TEXTS[rng.gen_range(0..PATHOLOGICAL_TEXTS.len())].to_string();
+        // Pile combining marks and joiners on top of the base text
+        for _ in 0..rng.gen_range(0..200) {
+            text.push(match rng.gen_range(0..4) {
+                0 => '\u{301}',
+                1 => '\u{200d}',
+                2 => '\u{1f600}',
+                _ => '\u{fe0f}',
+            });
+        }
+        text
+    }
+
+    #[test]
+    fn cell_text_is_truncated_at_a_character_boundary() {
+        assert_eq!(truncate_cell_text("a\u{301}"), "a\u{301}");
+        let long = "é".repeat(MAX_CELL_TEXT_BYTES);
+        let truncated = truncate_cell_text(&long);
+        assert_eq!(truncated.len(), MAX_CELL_TEXT_BYTES);
+        let long = format!("a{}", "é".repeat(MAX_CELL_TEXT_BYTES));
+        assert_eq!(truncate_cell_text(&long).len(), MAX_CELL_TEXT_BYTES - 1);
+    }
+
+    #[test]
+    fn double_width_cells_stay_in_one_fragment() {
+        let mut window = Window::new(
+            1,
+            WindowType::Editor,
+            None,
+            (0.0, 0.0),
+            (8, 1),
+            Rc::new(DrawCommandBatcher::new()),
+        );
+        let mut styles = HashMap::new();
+        styles.insert(1, Arc::new(Style::new(Colors::new(None, None, None))));
+        let cell = |text: &str, highlight_id| GridLineCell {
+            text: text.to_string(),
+            highlight_id: Some(highlight_id),
+            repeat: None,
+        };
+        // A search match ending in the middle of the last glyph, like when it's scrolled in
+        let cells = vec![
+            cell("a", 0),
+            cell("漢", 0),
+            cell("", 1),
+            cell("字", 1),
+            cell("", 1),
+            cell("b", 0),
+            cell("語", 0),
+            cell("", 1),
+        ];
+        window.draw_grid_line(0, 0, cells, &styles);
+
+        let row = window.grid.row(0).unwrap();
+        let mut fragments = Vec::new();
+        let mut start = 0;
+        while start < window.grid.width {
+            let (next, fragment) = window.build_line_fragment(row, start);
+            fragments.push((fragment.window_left, fragment.text, fragment.width));
+            start = next;
+        }
+        assert_eq!(
+            fragments,
+            vec![
+                (0, "a漢".to_string(), 3),
+                (3, "字".to_string(), 2),
+                (5, "b語".to_string(), 3),
+            ]
+        );
+    }
+
+    const CJK_LINES: &[&str] = &[
+        "日本語の文章です",
+        "漢字とかなaカナ",
+        "中文字符測試文本",
+        "한국어텍스트입니다",
+        "全角ＡＢＣ半角abc",
+        "縦書きと横書き",
+    ];
+
+    /// The cells of a `grid_line` event showing `text`, highlighted by column so that highlights
+    /// can end in the middle of double width glyphs, like search matches and the cursorline do.
+    fn cjk_cells(text: &str, highlight: impl Fn(usize) -> u64) -> Vec<GridLineCell> {
+        let mut cells = Vec::new();
+        for character in text.chars() {
+            let column = cells.len();
+            cells.push(GridLineCell {
+                text: character.to_string(),
+                highlight_id: Some(highlight(column)),
+                repeat: None,
+            });
+            if character.width() == Some(2) {
+                cells.push(GridLineCell {
+                    text: String::new(),
+                    highlight_id: Some(highlight(column + 1)),
+                    repeat: None,
+                });
+            }
+        }
+        cells
+    }
+
+    enum ReplayEvent {
+        Line { row: u64, cells: Vec<GridLineCell> },
+        Scroll { rows: i64 },
+    }
+
+    /// Replays the `grid_line` and `grid_scroll` events on a window of `size`, and checks after
+    /// each of them that every line is split into fragments of whole glyphs.
+    fn replay(size: (u64, u64), events: Vec<ReplayEvent>) -> Window {
+        let mut window = Window::new(
+            1,
+            WindowType::Editor,
+            None,
+            (0.0, 0.0),
+            size,
+            Rc::new(DrawCommandBatcher::new()),
+        );
+        let style = |red| {
+            Arc::new(Style::new(Colors::new(
+                None,
+                Some(Color4f::new(red, 0.0, 0.0, 1.0)),
+                None,
+            )))
+        };
+        let mut styles = HashMap::new();
+        styles.insert(1, style(0.5));
+        styles.insert(2, style(1.0));
+
+        for (index, event) in events.into_iter().enumerate() {
+            match event {
+                ReplayEvent::Line { row, mut cells } => {
+                    // Neovim clears the rest of the line, so nothing of the previous one is left
+                    cells.push(GridLineCell {
+                        text: " ".to_string(),
+                        highlight_id: Some(0),
+                        repeat: Some(size.0),
+                    });
+                    window.draw_grid_line(row, 0, cells, &styles);
+                }
+                ReplayEvent::Scroll { rows } => window.scroll_region(0, size.1, 0, size.0, rows, 0),
+            }
+            for row in 0..window.grid.height {
+                let cells = window.grid.row(row).unwrap();
+                let mut start = 0;
+                while start < window.grid.width {
+                    let (next, fragment) = window.build_line_fragment(cells, start);
+                    assert_eq!(
+                        fragment.text.width(),
+                        fragment.width as usize,
+                        "{:?} in row {row} after event {index}",
+                        fragment.text,
+                    );
+                    start = next;
+                }
+                assert_eq!(start, window.grid.width);
+            }
+        }
+        window
+    }
+
+    #[test]
+    fn cjk_text_scrolls_through_a_search_match() {
+        let (width, height) = (18, 4);
+        // The match moves one column each line, so it ends in the middle of every other glyph
+        let highlight =
+            |row: usize| move |column: usize| u64::from((row..row + 5).contains(&column));
+        let mut events = (0..height)
+            .map(|row| ReplayEvent::Line {
+                row,
+                cells: cjk_cells(CJK_LINES[row as usize], highlight(row as usize)),
+            })
+            .collect::<Vec<_>>();
+        for line in height as usize..CJK_LINES.len() * 3 {
+            events.push(ReplayEvent::Scroll { rows: 1 });
+            events.push(ReplayEvent::Line {
+                row: height - 1,
+                cells: cjk_cells(
+                    CJK_LINES[line % CJK_LINES.len()],
+                    highlight(line % width as usize),
+                ),
+            });
+        }
+        // And back up, with the cursorline covering the first glyphs of the new top line
+        for line in (0..CJK_LINES.len()).rev() {
+            events.push(ReplayEvent::Scroll { rows: -1 });
+            events.push(ReplayEvent::Line {
+                row: 0,
+                cells: cjk_cells(CJK_LINES[line], |column| if column < 3 { 2 } else { 0 }),
+            });
+        }
+
+        let window = replay((width, height), events);
+        assert_eq!(window.get_row_text(0, 0, width).trim_end(), CJK_LINES[0]);
+    }
+
+    #[test]
+    fn cjk_glyphs_at_the_right_edge_stay_whole() {
+        let (width, height) = (7, 3);
+        // The last glyph ends on the last column, where the highlight of the line stops
+        let edge = |column: usize| if column == width as usize - 1 { 0 } else { 1 };
+        let line = |row| ReplayEvent::Line {
+            row,
+            cells: cjk_cells("a漢字語", edge),
+        };
+        let window = replay(
+            (width, height),
+            vec![
+                line(0),
+                line(1),
+                line(2),
+                ReplayEvent::Scroll { rows: 2 },
+                line(1),
+                line(2),
+                ReplayEvent::Scroll { rows: -1 },
+                line(0),
+            ],
+        );
+
+        for row in 0..height as usize {
+            let cells = window.grid.row(row).unwrap();
+            let mut fragments = Vec::new();
+            let mut start = 0;
+            while start < window.grid.width {
+                let (next, fragment) = window.build_line_fragment(cells, start);
+                fragments.push((fragment.window_left, fragment.text, fragment.width));
+                start = next;
+            }
+            assert_eq!(
+                fragments,
+                vec![
+                    (0, "a漢".to_string(), 3),
+                    (3, "字".to_string(), 2),
+                    (5, "語".to_string(), 2),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn highlight_groups_alone_dont_split_fragments() {
+        let mut window = Window::new(
+            1,
+            WindowType::Editor,
+            None,
+            (0.0, 0.0),
+            (4, 1),
+            Rc::new(DrawCommandBatcher::new()),
+        );
+        let style = |group: &str| {
+            let mut style = Style::new(Colors::new(None, None, None));
+            style.highlight_groups = vec![group.to_string()];
+            Arc::new(style)
+        };
+        let mut styles = HashMap::new();
+        styles.insert(1, style("Operator"));
+        styles.insert(2, style("Keyword"));
+        styles.insert(3, style("LspInlayHint"));
+        let cell = |text: &str, highlight_id| GridLineCell {
+            text: text.to_string(),
+            highlight_id: Some(highlight_id),
+            repeat: None,
+        };
+        let cells = vec![cell("-", 1), cell(">", 2), cell("=", 2), cell("x", 3)];
+        window.draw_grid_line(0, 0, cells, &styles);
+
+        let row = window.grid.row(0).unwrap();
+        let (next, fragment) = window.build_line_fragment(row, 0);
+        assert_eq!((next, fragment.text.as_str()), (3, "->="));
+        let (next, fragment) = window.build_line_fragment(row, next);
+        assert_eq!((next, fragment.text.as_str()), (4, "x"));
+    }
+
+    #[test]
+    fn pathological_cells_degrade_gracefully() {
+        // Printed so that a failure can be replayed with the same cells
+        let seed = thread_rng().gen();
+        println!("seed: {seed}");
+        let mut rng = rngs::StdRng::seed_from_u64(seed);
+        let mut window = Window::new(
+            1,
+            WindowType::Editor,
+            None,
+            (0.0, 0.0),
+            (30, 5),
+            Rc::new(DrawCommandBatcher::new()),
+        );
+        let styles = HashMap::new();
+        for _ in 0..200 {
+            let cells = (0..rng.gen_range(0..40))
+                .map(|_| GridLineCell {
+                    text: random_text(&mut rng),
+                    highlight_id: rng.gen_bool(0.5).then(|| rng.gen_range(0..3)),
+                    repeat: match rng.gen_range(0..4) {
+                        0 => None,
+                        1 => Some(0),
+                        2 => Some(rng.gen_range(1..5)),
+                        _ => Some(u64::MAX),
+                    },
+                })
+                .collect();
+            window.draw_grid_line(rng.gen_range(0..7), rng.gen_range(0..40), cells, &styles);
+        }
+        for row in 0..window.grid.height {
+            for (text, _) in window.grid.row(row).unwrap() {
+                assert!(text.len() <= MAX_CELL_TEXT_BYTES);
+            }
+        }
+    }
+}
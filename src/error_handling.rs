@@ -1,6 +1,7 @@
 use std::{
+    env,
     io::{stdout, IsTerminal},
-    process::ExitCode,
+    process::{Command, ExitCode},
     sync::Arc,
 };
 
@@ -15,8 +16,10 @@ use crate::windows_attach_to_console;
 
 use crate::{
     bridge::{send_ui, ParallelCommand},
+    cmd_line::CmdLineSettings,
+    running_tracker::RunningTracker,
     settings::Settings,
-    window::{show_error_window, UserEvent},
+    window::{show_error_window, show_recovery_window, UserEvent},
 };
 
 fn show_error(explanation: &str) -> ! {
@@ -86,3 +89,64 @@ pub fn handle_startup_errors(
         ExitCode::from(1)
     }
 }
+
+fn format_crash_message(files: &[String]) -> String {
+    let mut msg = String::from(
+        "\
+Neovim exited unexpectedly :(
+Your changes may still be in the swap files. Press r to restart Neovide and recover them with `nvim -r`.
+",
+    );
+    if !files.is_empty() {
+        msg += "\nThese files were open:\n";
+        for file in files {
+            msg += &format!("  {file}\n");
+        }
+    }
+    log::error!("{}", msg);
+    msg
+}
+
+/// Offers to recover the files that were open when Neovim crashed, by starting a new Neovide in
+/// recovery mode.
+pub fn handle_neovim_crash(
+    mut event_loop: EventLoop<UserEvent>,
+    running_tracker: &RunningTracker,
+    settings: Arc<Settings>,
+) -> ExitCode {
+    let files = running_tracker.open_files();
+    let msg = format_crash_message(&files);
+    if stdout().is_terminal() {
+        eprintln!("{msg}");
+    } else if show_recovery_window(&msg, &mut event_loop, settings.clone()) {
+        // The same options are kept, with the files replaced by the ones to recover
+        let relaunch_args = settings.get::<CmdLineSettings>().relaunch_args;
+        let result = env::current_exe().and_then(|neovide| {
+            Command::new(neovide)
+                .args(relaunch_args)
+                .arg("-r")
+                .args(&files)
+                .spawn()
+        });
+        if let Err(err) = result {
+            error!("Could not restart Neovide for recovery: {err}");
+        }
+    }
+    running_tracker.exit_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_message_lists_the_open_files() {
+        let message = format_crash_message(&["foo.rs".to_string(), "dir/bar.md".to_string()]);
+        assert!(message.starts_with("Neovim exited unexpectedly"));
+        assert!(message.contains("`nvim -r`"));
+        assert!(message.ends_with("These files were open:\n  foo.rs\n  dir/bar.md\n"));
+
+        let message = format_crash_message(&[]);
+        assert!(!message.contains("These files were open"));
+    }
+}
@@ -4,6 +4,8 @@ use serde::Deserialize;
 
 use clap::{builder::PossibleValue, ValueEnum};
 
+use crate::{cmd_line::CmdLineSettings, settings::Settings};
+
 // Options for the frame decorations
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -14,6 +16,8 @@ pub enum Frame {
     Transparent,
     #[cfg(target_os = "macos")]
     Buttonless,
+    /// Drawn by Neovide, with its own title bar.
+    Custom,
     None,
 }
 
@@ -27,6 +31,7 @@ impl From<&'_ Frame> for &'static str {
             #[cfg(target_os = "macos")]
             Frame::Buttonless => "buttonless",
 
+            Frame::Custom => "custom",
             Frame::None => "none",
         }
     }
@@ -35,9 +40,15 @@ impl From<&'_ Frame> for &'static str {
 impl ValueEnum for Frame {
     fn value_variants<'a>() -> &'a [Self] {
         #[cfg(target_os = "macos")]
-        return &[Self::Full, Self::Transparent, Self::Buttonless, Self::None];
+        return &[
+            Self::Full,
+            Self::Transparent,
+            Self::Buttonless,
+            Self::Custom,
+            Self::None,
+        ];
         #[cfg(not(target_os = "macos"))]
-        return &[Self::Full, Self::None];
+        return &[Self::Full, Self::Custom, Self::None];
     }
 
     fn to_possible_value(&self) -> Option<PossibleValue> {
@@ -50,3 +61,28 @@ impl fmt::Display for Frame {
         write!(f, "{}", <&str>::from(self))
     }
 }
+
+/// Maps `g:neovide_wayland_decorations` to a frame, none when it's left empty or unknown.
+#[cfg(target_os = "linux")]
+fn wayland_frame(decorations: &str) -> Option<Frame> {
+    match decorations {
+        "server" => Some(Frame::Full),
+        "client" => Some(Frame::Custom),
+        "none" => Some(Frame::None),
+        _ => None,
+    }
+}
+
+/// The frame of the window, which is the one of `--frame` unless `g:neovide_wayland_decorations`
+/// picks another one on Wayland.
+pub fn window_frame(settings: &Settings) -> Frame {
+    let frame = settings.get::<CmdLineSettings>().frame;
+    #[cfg(target_os = "linux")]
+    if std::env::var("WAYLAND_DISPLAY").is_ok() {
+        let decorations = settings
+            .get::<crate::window::WindowSettings>()
+            .wayland_decorations;
+        return wayland_frame(&decorations).unwrap_or(frame);
+    }
+    frame
+}
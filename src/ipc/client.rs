@@ -0,0 +1,136 @@
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Write},
+    process::ExitCode,
+};
+
+use log::debug;
+use serde_json::{json, Value};
+
+#[cfg(unix)]
+use super::check_server_directory;
+use super::{server_address, NewInstanceParams, OpenFilesParams, Request, Response};
+use crate::cmd_line::{RemoteArgs, RemoteCommand};
+
+#[cfg(unix)]
+fn connect() -> io::Result<std::os::unix::net::UnixStream> {
+    check_server_directory()?;
+    std::os::unix::net::UnixStream::connect(server_address())
+}
+
+#[cfg(windows)]
+fn connect() -> io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(server_address())
+}
+
+/// Sends a request to the running Neovide and returns the result.
+pub fn send_request(method: &str, params: Value) -> io::Result<Value> {
+    let mut stream = connect()?;
+    let mut json = serde_json::to_string(&Request::new(1, method, params))?;
+    json.push('\n');
+    stream.write_all(json.as_bytes())?;
+    stream.flush()?;
+
+    let mut line = String::new();
+    if BufReader::new(stream).read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Neovide closed the connection",
+        ));
+    }
+    let response: Response = serde_json::from_str(&line)?;
+    match response.error {
+        Some(error) => Err(io::Error::other(error.message)),
+        None => Ok(response.result.unwrap_or_default()),
+    }
+}
+
+/// Opens `files` in the running Neovide, the first one at `line`, and focuses its window. With
+/// `wait`, it only returns once they are closed. Returns false when there's no running Neovide to
+/// open them in.
+pub fn open_in_running_instance(files: &[String], line: Option<u64>, wait: bool) -> bool {
+    let params = OpenFilesParams {
+        files: files.to_vec(),
+        cwd: env::current_dir().unwrap_or_default(),
+        line,
+        wait,
+    };
+    let params = serde_json::to_value(params).unwrap();
+    match send_request("OpenFiles", params) {
+        Ok(_) => true,
+        // The running Neovide exited while the files were open
+        Err(error) if wait && error.kind() == io::ErrorKind::UnexpectedEof => true,
+        Err(error) => {
+            debug!("No running Neovide to open the files in: {error}");
+            false
+        }
+    }
+}
+
+/// Sends the request of `neovide remote` and prints the response.
+pub fn run_remote(args: RemoteArgs) -> ExitCode {
+    let result = match args.command {
+        RemoteCommand::Open { files, line, wait } => serde_json::to_value(OpenFilesParams {
+            files,
+            cwd: env::current_dir().unwrap_or_default(),
+            line,
+            wait,
+        })
+        .map_err(io::Error::from)
+        .and_then(|params| send_request("OpenFiles", params))
+        .map(|_| ()),
+        RemoteCommand::Command { command } => {
+            send_request("NvimCommand", json!({ "command": command })).map(|output| {
+                if let Some(output) = output.as_str().filter(|output| !output.is_empty()) {
+                    println!("{output}");
+                }
+            })
+        }
+        RemoteCommand::Eval { expression } => {
+            send_request("NvimEval", json!({ "expression": expression }))
+                .map(|value| println!("{value:#}"))
+        }
+        RemoteCommand::ListWindows => send_request("ListWindows", Value::Null).map(|result| {
+            for window in result["windows"].as_array().into_iter().flatten() {
+                println!("{}\t{}", window[0], window[1].as_str().unwrap_or_default());
+            }
+            // The instances come after the windows, the first one being the running Neovide
+            for instance in result["instances"].as_array().into_iter().flatten() {
+                println!(
+                    "instance\t{}\t{}\t{}",
+                    instance["pid"],
+                    instance["cwd"].as_str().unwrap_or_default(),
+                    instance["appname"].as_str().unwrap_or_default()
+                );
+            }
+        }),
+        RemoteCommand::NewInstance {
+            files,
+            cwd,
+            env: variables,
+            appname,
+        } => {
+            let current_dir = env::current_dir().unwrap_or_default();
+            let params = NewInstanceParams {
+                files,
+                cwd: Some(cwd.map_or(current_dir.clone(), |cwd| current_dir.join(cwd))),
+                env: variables.into_iter().collect(),
+                appname,
+            };
+            serde_json::to_value(params)
+                .map_err(io::Error::from)
+                .and_then(|params| send_request("NewInstance", params))
+                .map(|instance| println!("{}", instance["pid"]))
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("neovide remote: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
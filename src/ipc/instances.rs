@@ -0,0 +1,136 @@
+//! The other Neovide instances started by this one with `NewInstance` or `:NeovideNewInstance`,
+//! each with its own working directory, environment and `NVIM_APPNAME`, which its Neovim
+//! inherits. They're listed by `ListWindows`, so that tools can tell them apart.
+
+use std::{
+    collections::BTreeMap,
+    env, io,
+    path::PathBuf,
+    process::{self, Child, Command},
+    sync::OnceLock,
+};
+
+use log::error;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::to_json;
+
+/// Set for the instances started by another one, which open their own window even with
+/// `single-instance`.
+pub const NEW_INSTANCE_VARIABLE: &str = "NEOVIDE_NEW_INSTANCE";
+
+static INSTANCES: OnceLock<Mutex<Vec<(Child, InstanceInfo)>>> = OnceLock::new();
+
+/// The parameters of `NewInstance`. The files are relative to `cwd`, which defaults to the one of
+/// the running Neovide, like the rest of the environment.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NewInstanceParams {
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub appname: Option<String>,
+}
+
+/// An instance as listed by `ListWindows`, with the environment it was started with on top of the
+/// one of the running Neovide.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InstanceInfo {
+    pub pid: u32,
+    pub cwd: PathBuf,
+    pub appname: Option<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+fn instance_command(executable: PathBuf, params: &NewInstanceParams) -> Command {
+    let mut command = Command::new(executable);
+    command.args(&params.files).envs(&params.env);
+    command.env(NEW_INSTANCE_VARIABLE, "1");
+    if let Some(cwd) = &params.cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(appname) = &params.appname {
+        command.env("NVIM_APPNAME", appname);
+    }
+    command
+}
+
+/// Starts another Neovide with its own window and Neovim.
+pub fn spawn_instance(params: NewInstanceParams) -> io::Result<InstanceInfo> {
+    let mut command = instance_command(env::current_exe()?, &params);
+    let child = command.spawn()?;
+    let info = InstanceInfo {
+        pid: child.id(),
+        cwd: params
+            .cwd
+            .clone()
+            .unwrap_or_else(|| env::current_dir().unwrap_or_default()),
+        appname: params
+            .appname
+            .clone()
+            .or_else(|| params.env.get("NVIM_APPNAME").cloned())
+            .or_else(|| env::var("NVIM_APPNAME").ok()),
+        env: params.env,
+    };
+    INSTANCES
+        .get_or_init(Mutex::default)
+        .lock()
+        .push((child, info.clone()));
+    Ok(info)
+}
+
+/// Starts an instance asked for by `neovide.new_instance`, with a dictionary of the parameters.
+pub fn spawn_instance_from_neovim(params: rmpv::Value) {
+    let result = serde_json::from_value::<NewInstanceParams>(to_json(params))
+        .map_err(io::Error::from)
+        .and_then(spawn_instance);
+    if let Err(error) = result {
+        error!("Could not start a new instance: {error}");
+    }
+}
+
+/// This instance followed by the ones it started that are still running.
+pub fn list_instances() -> Vec<InstanceInfo> {
+    let current = InstanceInfo {
+        pid: process::id(),
+        cwd: env::current_dir().unwrap_or_default(),
+        appname: env::var("NVIM_APPNAME").ok(),
+        env: BTreeMap::new(),
+    };
+    let mut instances = INSTANCES.get_or_init(Mutex::default).lock();
+    instances.retain_mut(|(child, _)| matches!(child.try_wait(), Ok(None)));
+    std::iter::once(current)
+        .chain(instances.iter().map(|(_, info)| info.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::*;
+
+    #[test]
+    fn instances_get_their_own_environment() {
+        let params = NewInstanceParams {
+            files: vec!["notes.md".to_string()],
+            cwd: Some(PathBuf::from("/home/me/notes")),
+            env: BTreeMap::from([("RUST_LOG".to_string(), "debug".to_string())]),
+            appname: Some("nvim-writing".to_string()),
+        };
+        let command = instance_command(PathBuf::from("neovide"), &params);
+        assert_eq!(command.get_args().collect::<Vec<_>>(), vec!["notes.md"]);
+        assert_eq!(
+            command.get_current_dir(),
+            Some(PathBuf::from("/home/me/notes").as_path())
+        );
+        let envs = command.get_envs().collect::<Vec<_>>();
+        assert!(envs.contains(&(OsStr::new("RUST_LOG"), Some(OsStr::new("debug")))));
+        assert!(envs.contains(&(OsStr::new("NVIM_APPNAME"), Some(OsStr::new("nvim-writing")))));
+        assert!(envs.contains(&(OsStr::new(NEW_INSTANCE_VARIABLE), Some(OsStr::new("1")))));
+    }
+}
@@ -0,0 +1,232 @@
+//! A JSON-RPC server on a local socket, or a named pipe on Windows, through which other processes
+//! can control a running Neovide. Each line sent to it is a request, answered by a line with the
+//! response.
+
+mod client;
+mod instances;
+mod server;
+mod wait;
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub use client::{open_in_running_instance, run_remote};
+pub use instances::{spawn_instance_from_neovim, NewInstanceParams, NEW_INSTANCE_VARIABLE};
+pub use server::{open_file, start_server};
+pub use wait::{buffer_closed, release_waiters};
+
+const JSONRPC_VERSION: &str = "2.0";
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Neovim failed to run the command or to evaluate the expression.
+const NEOVIM_ERROR: i64 = -32000;
+/// Another Neovide could not be started.
+const INSTANCE_ERROR: i64 = -32001;
+
+/// The address of the server, shared by all the instances of the user, of which only the first one
+/// listens.
+#[cfg(unix)]
+pub fn server_address() -> PathBuf {
+    server_directory().join("neovide.sock")
+}
+
+/// The directory of the socket, which only the user can access. The runtime directory already is
+/// private, elsewhere Neovide creates one in the temporary directory.
+#[cfg(unix)]
+fn server_directory() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(|| std::env::temp_dir().join(format!("neovide-{}", current_uid())))
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: getuid always succeeds
+    unsafe { libc::getuid() }
+}
+
+/// Fails unless the directory of the socket belongs to the user and nobody else can access it, so
+/// that another user can't listen in place of Neovide.
+#[cfg(unix)]
+fn check_server_directory() -> std::io::Result<()> {
+    use std::{
+        io,
+        os::unix::fs::{MetadataExt, PermissionsExt},
+    };
+
+    let directory = server_directory();
+    let metadata = std::fs::symlink_metadata(&directory)?;
+    if !metadata.is_dir()
+        || metadata.uid() != current_uid()
+        || metadata.permissions().mode() & 0o077 != 0
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{directory:?} isn't private to the user"),
+        ));
+    }
+    Ok(())
+}
+
+/// The pipe only lets the user in, and only the first instance of it can be created, see `serve`.
+#[cfg(windows)]
+pub fn server_address() -> PathBuf {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    PathBuf::from(format!(r"\\.\pipe\neovide-{user}"))
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub id: u64,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Request {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponseError>,
+}
+
+impl Response {
+    pub fn result(id: u64, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: u64, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(ResponseError {
+                code,
+                message: message.into(),
+            }),
+        }
+    }
+}
+
+/// The parameters of `OpenFiles`, the files are relative to `cwd`. The first file is opened at
+/// `line` when it's set. With `wait`, the response is only sent once all the files are closed.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct OpenFilesParams {
+    pub files: Vec<String>,
+    pub cwd: PathBuf,
+    #[serde(default)]
+    pub line: Option<u64>,
+    #[serde(default)]
+    pub wait: bool,
+}
+
+/// The parameters of `NvimCommand`, the output of the command is returned.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct NvimCommandParams {
+    pub command: String,
+}
+
+/// The parameters of `NvimEval`, the value of the expression is returned.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct NvimEvalParams {
+    pub expression: String,
+}
+
+/// The ids of the windows with the names of their buffers.
+const LIST_WINDOWS_EXPRESSION: &str =
+    "map(nvim_list_wins(), {_, window -> [window, bufname(winbufnr(window))]})";
+
+/// Converts a value returned by Neovim to JSON. Buffers, windows and tabpages become their
+/// handles, and binary strings arrays of bytes.
+fn to_json(value: rmpv::Value) -> Value {
+    match value {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(boolean) => boolean.into(),
+        rmpv::Value::Integer(integer) => integer
+            .as_i64()
+            .map(Value::from)
+            .or_else(|| integer.as_u64().map(Value::from))
+            .unwrap_or_default(),
+        rmpv::Value::F32(float) => float.into(),
+        rmpv::Value::F64(float) => float.into(),
+        rmpv::Value::String(string) => String::from_utf8_lossy(string.as_bytes()).into(),
+        rmpv::Value::Binary(bytes) => bytes.into(),
+        rmpv::Value::Array(values) => values.into_iter().map(to_json).collect(),
+        rmpv::Value::Map(entries) => entries
+            .into_iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    rmpv::Value::String(key) => String::from_utf8_lossy(key.as_bytes()).into(),
+                    key => key.to_string(),
+                };
+                (key, to_json(value))
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        rmpv::Value::Ext(_, data) => rmpv::decode::read_value(&mut data.as_slice())
+            .map(to_json)
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn neovim_values_convert_to_json() {
+        let value = rmpv::Value::Map(vec![
+            (
+                "lines".into(),
+                rmpv::Value::Array(vec!["a".into(), 42.into()]),
+            ),
+            (1.into(), rmpv::Value::Ext(0, vec![0x05])),
+            ("nothing".into(), rmpv::Value::Nil),
+        ]);
+        assert_eq!(
+            to_json(value),
+            json!({"lines": ["a", 42], "1": 5, "nothing": null})
+        );
+    }
+
+    #[test]
+    fn responses_have_either_a_result_or_an_error() {
+        let result = serde_json::to_value(Response::result(1, json!(null))).unwrap();
+        assert_eq!(result, json!({"jsonrpc": "2.0", "id": 1, "result": null}));
+        let error = serde_json::to_value(Response::error(2, METHOD_NOT_FOUND, "Nope")).unwrap();
+        assert_eq!(
+            error,
+            json!({"jsonrpc": "2.0", "id": 2, "error": {"code": -32601, "message": "Nope"}})
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use std::{io, thread};
+
+use log::{debug, info};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::mpsc::unbounded_channel,
+};
+use winit::event_loop::EventLoopProxy;
+
+#[cfg(unix)]
+use super::{check_server_directory, current_uid, server_directory};
+use super::{
+    instances::{list_instances, spawn_instance, NewInstanceParams},
+    server_address, to_json,
+    wait::wait_for_buffer,
+    NvimCommandParams, NvimEvalParams, OpenFilesParams, Request, Response, INSTANCE_ERROR,
+    INVALID_PARAMS, LIST_WINDOWS_EXPRESSION, METHOD_NOT_FOUND, NEOVIM_ERROR, PARSE_ERROR,
+};
+use crate::{
+    bridge::{send_ui, ParallelCommand, ReplySender},
+    window::{UserEvent, WindowCommand},
+};
+
+/// The characters escaped by `fnameescape()`, backslashes are path separators on Windows.
+#[cfg(not(windows))]
+const SPECIAL_FILE_NAME_CHARACTERS: &str = " \t\n*?[{`$\\%#'\"|!<";
+#[cfg(windows)]
+const SPECIAL_FILE_NAME_CHARACTERS: &str = " \t\n*?[{`$%#'\"|!<";
+
+/// Escapes the characters that are special in file names of Ex commands.
+fn escape_file_name(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for character in path.chars() {
+        if SPECIAL_FILE_NAME_CHARACTERS.contains(character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+/// Opens the file like a dropped one, or at `line` with `:edit` when it's given.
+pub fn open_file(path: String, line: Option<u64>) {
+    match line {
+        Some(line) => send_ui(ParallelCommand::Command(format!(
+            "edit +{line} {}",
+            escape_file_name(&path)
+        ))),
+        None => send_ui(ParallelCommand::FileDrop(path)),
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(request: &Request) -> Result<T, Response> {
+    serde_json::from_value(request.params.clone())
+        .map_err(|error| Response::error(request.id, INVALID_PARAMS, error.to_string()))
+}
+
+/// Sends a command to Neovim and waits for its reply.
+async fn ask_neovim(id: u64, command: impl FnOnce(ReplySender) -> ParallelCommand) -> Response {
+    let (reply, mut receiver) = unbounded_channel();
+    send_ui(command(reply));
+    match receiver.recv().await {
+        Some(Ok(value)) => Response::result(id, to_json(value)),
+        Some(Err(error)) => Response::error(id, NEOVIM_ERROR, error),
+        None => Response::error(id, NEOVIM_ERROR, "Neovim is not running"),
+    }
+}
+
+async fn handle_request(request: Request, proxy: &EventLoopProxy<UserEvent>) -> Response {
+    let id = request.id;
+    match request.method.as_str() {
+        "OpenFiles" => match parse_params(&request) {
+            Ok(OpenFilesParams {
+                files,
+                cwd,
+                line,
+                wait,
+            }) => {
+                let mut closed = Vec::new();
+                for (index, file) in files.into_iter().enumerate() {
+                    let path = cwd.join(file).to_string_lossy().into_owned();
+                    if wait {
+                        closed.push(wait_for_buffer(path.clone()));
+                    }
+                    open_file(path, line.filter(|_| index == 0));
+                }
+                let _ = proxy.send_event(WindowCommand::FocusWindow.into());
+                for closed in closed {
+                    // Dropped by `release_waiters` when Neovim restarts, and with the process
+                    // when it exits
+                    let _ = closed.await;
+                }
+                Response::result(id, Value::Null)
+            }
+            Err(response) => response,
+        },
+        "NvimCommand" => match parse_params(&request) {
+            Ok(NvimCommandParams { command }) => {
+                ask_neovim(id, |reply| ParallelCommand::CommandOutput {
+                    command,
+                    reply,
+                })
+                .await
+            }
+            Err(response) => response,
+        },
+        "NvimEval" => match parse_params(&request) {
+            Ok(NvimEvalParams { expression }) => {
+                ask_neovim(id, |reply| ParallelCommand::Eval {
+                    expr: expression,
+                    reply,
+                })
+                .await
+            }
+            Err(response) => response,
+        },
+        "ListWindows" => {
+            let windows = ask_neovim(id, |reply| ParallelCommand::Eval {
+                expr: LIST_WINDOWS_EXPRESSION.to_string(),
+                reply,
+            })
+            .await;
+            match windows.result {
+                Some(windows) => Response::result(
+                    id,
+                    json!({ "windows": windows, "instances": list_instances() }),
+                ),
+                None => windows,
+            }
+        }
+        "NewInstance" => match parse_params::<NewInstanceParams>(&request) {
+            Ok(params) => match spawn_instance(params) {
+                Ok(instance) => Response::result(id, json!(instance)),
+                Err(error) => Response::error(
+                    id,
+                    INSTANCE_ERROR,
+                    format!("Could not start a new instance: {error}"),
+                ),
+            },
+            Err(response) => response,
+        },
+        method => Response::error(id, METHOD_NOT_FOUND, format!("Unknown method {method}")),
+    }
+}
+
+async fn handle_connection(
+    stream: impl AsyncRead + AsyncWrite,
+    proxy: EventLoopProxy<UserEvent>,
+) -> io::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle_request(request, &proxy).await,
+            Err(error) => Response::error(0, PARSE_ERROR, error.to_string()),
+        };
+        let mut json = serde_json::to_string(&response)?;
+        json.push('\n');
+        writer.write_all(json.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn serve(proxy: EventLoopProxy<UserEvent>) -> io::Result<()> {
+    use std::{
+        fs,
+        os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt},
+    };
+    use tokio::net::{UnixListener, UnixStream};
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(server_directory())?;
+    check_server_directory()?;
+
+    let address = server_address();
+    if UnixStream::connect(&address).await.is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "another Neovide is listening",
+        ));
+    }
+    // Left behind by an instance that didn't exit cleanly, anything else is left alone
+    if let Ok(metadata) = fs::symlink_metadata(&address) {
+        if !metadata.file_type().is_socket() || metadata.uid() != current_uid() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{address:?} isn't a socket of the user"),
+            ));
+        }
+        fs::remove_file(&address)?;
+    }
+    let listener = UnixListener::bind(&address)?;
+    fs::set_permissions(&address, fs::Permissions::from_mode(0o600))?;
+    info!("Listening on {address:?}");
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle_connection(stream, proxy.clone()));
+    }
+}
+
+#[cfg(windows)]
+async fn serve(proxy: EventLoopProxy<UserEvent>) -> io::Result<()> {
+    let address = server_address();
+    let security = pipe_security()?;
+    // FILE_FLAG_FIRST_PIPE_INSTANCE fails when another Neovide is listening, or when another
+    // process took the name first
+    let mut server = create_pipe(&address, &security, true)?;
+    info!("Listening on {address:?}");
+    loop {
+        server.connect().await?;
+        let connected = server;
+        server = create_pipe(&address, &security, false)?;
+        tokio::spawn(handle_connection(connected, proxy.clone()));
+    }
+}
+
+/// The security descriptor of the pipe, in SDDL, with a DACL that only lets the current user in.
+/// The default one lets everyone read from it.
+#[cfg(windows)]
+fn pipe_security() -> io::Result<String> {
+    use windows::{
+        core::PWSTR,
+        Win32::{
+            Foundation::{CloseHandle, LocalFree, HANDLE, HLOCAL},
+            Security::{
+                Authorization::ConvertSidToStringSidW, GetTokenInformation, TokenUser, TOKEN_QUERY,
+                TOKEN_USER,
+            },
+            System::Threading::{GetCurrentProcess, OpenProcessToken},
+        },
+    };
+
+    // SAFETY: The token is closed and the string of the SID freed once they are read, and the
+    // buffer is aligned for the TOKEN_USER the second call writes to it
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)?;
+        let mut length = 0;
+        // The first call only gives the size
+        let _ = GetTokenInformation(token, TokenUser, None, 0, &mut length);
+        let mut buffer = vec![0u64; (length as usize).div_ceil(8)];
+        let result = GetTokenInformation(
+            token,
+            TokenUser,
+            Some(buffer.as_mut_ptr().cast()),
+            length,
+            &mut length,
+        );
+        let _ = CloseHandle(token);
+        result?;
+        let user = &*buffer.as_ptr().cast::<TOKEN_USER>();
+
+        let mut sid = PWSTR::null();
+        ConvertSidToStringSidW(user.User.Sid, &mut sid)?;
+        let sid_string = sid.to_string();
+        LocalFree(HLOCAL(sid.0.cast()));
+        let sid_string =
+            sid_string.map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        // Protected, so that nothing is inherited, with all the access for the user alone
+        Ok(format!("D:P(A;;GA;;;{sid_string})"))
+    }
+}
+
+#[cfg(windows)]
+fn create_pipe(
+    address: &std::path::Path,
+    security: &str,
+    first: bool,
+) -> io::Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+    use windows::{
+        core::HSTRING,
+        Win32::{
+            Foundation::{LocalFree, HLOCAL},
+            Security::{
+                Authorization::{
+                    ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+                },
+                PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES,
+            },
+        },
+    };
+
+    // SAFETY: The descriptor lives until the pipe is created, which copies it
+    unsafe {
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            &HSTRING::from(security),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            None,
+        )?;
+        let mut attributes = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: descriptor.0,
+            bInheritHandle: false.into(),
+        };
+        let server = ServerOptions::new()
+            .first_pipe_instance(first)
+            .reject_remote_clients(true)
+            .create_with_security_attributes_raw(
+                address,
+                (&mut attributes as *mut SECURITY_ATTRIBUTES).cast(),
+            );
+        LocalFree(HLOCAL(descriptor.0));
+        server
+    }
+}
+
+/// Starts listening for requests on a thread of its own, unless another instance already does.
+pub fn start_server(proxy: EventLoopProxy<UserEvent>) {
+    let result = thread::Builder::new()
+        .name("ipc".to_string())
+        .spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(error) => {
+                    debug!("Could not start the IPC runtime: {error}");
+                    return;
+                }
+            };
+            if let Err(error) = runtime.block_on(serve(proxy)) {
+                debug!("No IPC server: {error}");
+            }
+        });
+    if let Err(error) = result {
+        debug!("Could not start the IPC thread: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_are_escaped() {
+        assert_eq!(
+            escape_file_name("/my files/a%b#1.rs"),
+            "/my\\ files/a\\%b\\#1.rs"
+        );
+    }
+}
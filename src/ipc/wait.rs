@@ -0,0 +1,81 @@
+//! The files opened with `--wait`, whose request is answered once Neovim closed their buffer, so
+//! that the process waiting for it returns, like an editor of git does.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+};
+
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+use crate::bridge::{send_ui, ParallelCommand};
+
+static WAITERS: OnceLock<Mutex<HashMap<u64, oneshot::Sender<()>>>> = OnceLock::new();
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn add_waiter() -> (u64, oneshot::Receiver<()>) {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = oneshot::channel();
+    WAITERS
+        .get_or_init(Mutex::default)
+        .lock()
+        .insert(id, sender);
+    (id, receiver)
+}
+
+/// Resolves once the buffer of `path` is closed, or fails when Neovim exits or restarts without
+/// closing it.
+pub fn wait_for_buffer(path: String) -> oneshot::Receiver<()> {
+    let (id, receiver) = add_waiter();
+    send_ui(ParallelCommand::WaitForBuffer { path, id });
+    receiver
+}
+
+/// Neovim closed the buffer waited for with `id`.
+pub fn buffer_closed(id: u64) {
+    if let Some(sender) = WAITERS.get_or_init(Mutex::default).lock().remove(&id) {
+        let _ = sender.send(());
+    }
+}
+
+/// Drops the waiters of the Neovim that exited, as a restarted one doesn't know their buffers. The
+/// requests waiting for them are answered then.
+pub fn release_waiters() {
+    WAITERS.get_or_init(Mutex::default).lock().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[serial_test::serial]
+    fn waiters_are_released_once() {
+        let (first, mut first_receiver) = add_waiter();
+        let (second, mut second_receiver) = add_waiter();
+        assert_ne!(first, second);
+
+        buffer_closed(first);
+        assert_eq!(first_receiver.try_recv(), Ok(()));
+        assert!(second_receiver.try_recv().is_err());
+
+        buffer_closed(first);
+        buffer_closed(second);
+        assert_eq!(second_receiver.try_recv(), Ok(()));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn waiters_are_released_on_restart() {
+        let (_, mut receiver) = add_waiter();
+        release_waiters();
+        assert_eq!(
+            receiver.try_recv(),
+            Err(oneshot::error::TryRecvError::Closed)
+        );
+    }
+}
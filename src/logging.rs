@@ -0,0 +1,171 @@
+//! Log levels that can be changed while running with `:NeovideLogLevel`, and a log format that
+//! includes the structured fields of the records, like the grid of a redraw event, and the frame
+//! being drawn.
+
+// The logger isn't started by the tests
+#![cfg_attr(test, allow(dead_code))]
+
+use std::{
+    io::{self, Write},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use flexi_logger::{DeferredNow, LogSpecification, LoggerHandle};
+use log::{
+    kv::{self, Key, Value, VisitSource},
+    LevelFilter, Record,
+};
+
+static LOGGER: OnceLock<(LoggerHandle, Mutex<LogSpecification>)> = OnceLock::new();
+static FRAME: AtomicU64 = AtomicU64::new(0);
+
+/// Keeps the handle of the started logger, to change its levels later.
+pub fn set_logger(handle: LoggerHandle, spec: LogSpecification) {
+    let _ = LOGGER.set((handle, Mutex::new(spec)));
+}
+
+/// Counts the drawn frames, to tell which frame the log lines belong to.
+pub fn next_frame() {
+    FRAME.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Formats the records like `ERROR [neovide::editor] frame=12 grid_id=1 message`.
+pub fn format(w: &mut dyn Write, _now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+    write!(
+        w,
+        "{} [{}] frame={}",
+        record.level(),
+        record.module_path().unwrap_or("<unnamed>"),
+        FRAME.load(Ordering::Relaxed)
+    )?;
+    let mut fields = FieldWriter {
+        w: &mut *w,
+        result: Ok(()),
+    };
+    let _ = record.key_values().visit(&mut fields);
+    fields.result?;
+    write!(w, " {}", record.args())
+}
+
+struct FieldWriter<'a> {
+    w: &'a mut dyn Write,
+    result: io::Result<()>,
+}
+
+impl<'kvs> VisitSource<'kvs> for FieldWriter<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.result = write!(self.w, " {key}={value}");
+        self.result
+            .as_ref()
+            .map_err(|_| kv::Error::msg("write failed"))?;
+        Ok(())
+    }
+}
+
+/// Parses the arguments of `:NeovideLogLevel`, an optional module followed by a level, into the
+/// module path and the level.
+fn parse_log_level(arguments: &str) -> Result<(String, LevelFilter), String> {
+    let arguments = arguments.split_whitespace().collect::<Vec<_>>();
+    let (module, level) = match arguments.as_slice() {
+        [level] => ("neovide", *level),
+        [module, level] => (*module, *level),
+        _ => return Err("Expected an optional module followed by a level".to_string()),
+    };
+    let level = LevelFilter::from_str(level).map_err(|_| format!("Unknown log level {level}"))?;
+    let module = module.replace('.', "::");
+    let module = if module == "neovide" || module.starts_with("neovide::") {
+        module
+    } else {
+        format!("neovide::{module}")
+    };
+    Ok((module, level))
+}
+
+/// Returns the spec with `level` for `module`, keeping the levels of the other modules.
+fn with_module_level(
+    spec: &LogSpecification,
+    module: &str,
+    level: LevelFilter,
+) -> LogSpecification {
+    let mut filters = spec
+        .module_filters()
+        .iter()
+        .filter(|filter| filter.module_name.as_deref() != Some(module))
+        .map(|filter| (filter.module_name.clone(), filter.level_filter))
+        .collect::<Vec<_>>();
+    filters.push((Some(module.to_string()), level));
+    // The most specific modules need to come first to take precedence
+    filters.sort_by_key(|(module, _)| std::cmp::Reverse(module.as_ref().map_or(0, String::len)));
+
+    let mut builder = LogSpecification::builder();
+    for (module, level) in filters {
+        match module {
+            Some(module) => builder.module(&module, level),
+            None => builder.default(level),
+        };
+    }
+    builder.build()
+}
+
+/// Handles `:NeovideLogLevel`. Without arguments it only returns the current levels, otherwise it
+/// changes the level of a module and returns the new levels.
+pub fn handle_log_level_command(arguments: &str) -> Result<String, String> {
+    let (handle, spec) = LOGGER.get().ok_or("The logger isn't running")?;
+    let mut spec = spec.lock().unwrap();
+    if !arguments.trim().is_empty() {
+        let (module, level) = parse_log_level(arguments)?;
+        *spec = with_module_level(&spec, &module, level);
+        handle.set_new_spec(spec.clone());
+        log::info!("Log levels changed to {}", *spec);
+    }
+    Ok(spec.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modules_are_relative_to_neovide() {
+        assert_eq!(
+            parse_log_level("renderer debug"),
+            Ok(("neovide::renderer".to_string(), LevelFilter::Debug))
+        );
+        assert_eq!(
+            parse_log_level("renderer.rendered_window trace"),
+            Ok((
+                "neovide::renderer::rendered_window".to_string(),
+                LevelFilter::Trace
+            ))
+        );
+        assert_eq!(
+            parse_log_level("warn"),
+            Ok(("neovide".to_string(), LevelFilter::Warn))
+        );
+        assert!(parse_log_level("renderer loud").is_err());
+        assert!(parse_log_level("").is_err());
+    }
+
+    #[test]
+    fn other_module_levels_are_kept() {
+        let spec = LogSpecification::parse("neovide = error").unwrap();
+        let spec = with_module_level(&spec, "neovide::renderer", LevelFilter::Debug);
+        let spec = with_module_level(&spec, "neovide", LevelFilter::Warn);
+        let filters = spec
+            .module_filters()
+            .iter()
+            .map(|filter| (filter.module_name.clone().unwrap(), filter.level_filter))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            filters,
+            vec![
+                ("neovide::renderer".to_string(), LevelFilter::Debug),
+                ("neovide".to_string(), LevelFilter::Warn),
+            ]
+        );
+    }
+}
@@ -22,6 +22,8 @@ mod dimensions;
 mod editor;
 mod error_handling;
 mod frame;
+mod ipc;
+mod logging;
 mod profiling;
 mod renderer;
 mod running_tracker;
@@ -53,19 +55,28 @@ use std::panic::PanicHookInfo;
 use std::path::PathBuf;
 use time::macros::format_description;
 use time::OffsetDateTime;
-use winit::{error::EventLoopError, event_loop::EventLoopProxy};
+use winit::{
+    error::EventLoopError, event_loop::EventLoopProxy,
+    platform::run_on_demand::EventLoopExtRunOnDemand,
+};
 
 #[cfg(not(test))]
-use flexi_logger::{Cleanup, Criterion, Duplicate, FileSpec, Logger, Naming};
+use flexi_logger::{Cleanup, Criterion, Duplicate, FileSpec, LogSpecification, Logger, Naming};
 
 use backtrace::Backtrace;
-use bridge::NeovimRuntime;
+use bridge::{ClipboardSettings, NeovimRuntime};
 use cmd_line::CmdLineSettings;
-use error_handling::handle_startup_errors;
-use renderer::{cursor_renderer::CursorSettings, RendererSettings};
+use error_handling::{handle_neovim_crash, handle_startup_errors};
+use renderer::{
+    bell::BellSettings, cmdline_renderer::CommandLineSettings, cursor_renderer::CursorSettings,
+    file_changed::FileChangedSettings, messages_renderer::MessagesSettings,
+    popupmenu::PopupMenuSettings, tabline::TablineSettings, wildmenu::WildmenuSettings,
+    RendererSettings,
+};
 use running_tracker::RunningTracker;
 use window::{
-    create_event_loop, determine_window_size, UpdateLoop, UserEvent, WindowSettings, WindowSize,
+    create_event_loop, determine_window_size, MouseSettings, UpdateLoop, UserEvent, WindowSettings,
+    WindowSize,
 };
 
 pub use channel_utils::*;
@@ -73,7 +84,8 @@ pub use channel_utils::*;
 pub use windows_utils::*;
 
 use crate::settings::{
-    load_last_window_settings, Config, FontSettings, PersistentWindowSettings, Settings,
+    config_settings, load_last_cell_metrics, load_last_window_settings, Config, FontSettings,
+    PersistentWindowSettings, Settings,
 };
 
 pub use profiling::startup_profiler;
@@ -101,7 +113,12 @@ fn main() -> ExitCode {
     #[cfg(target_os = "linux")]
     env::remove_var("ARGV0");
 
-    let event_loop = create_event_loop();
+    if let Some(remote_args) = cmd_line::parse_remote_arguments(&args().collect::<Vec<_>>()) {
+        return ipc::run_remote(remote_args);
+    }
+
+    window::preload_icon();
+    let mut event_loop = create_event_loop();
     clipboard::init(&event_loop);
 
     let running_tracker = RunningTracker::new();
@@ -118,10 +135,11 @@ fn main() -> ExitCode {
                 window_size,
                 font_settings,
                 event_loop.create_proxy(),
+                running_tracker.clone(),
                 settings.clone(),
             );
 
-            let result = event_loop.run_app(&mut update_loop);
+            let result = event_loop.run_app_on_demand(&mut update_loop);
 
             // Wait a little bit more and force Nevoim to exit after that.
             // This should not be required, but Neovim through libuv spawns childprocesses that inherits all the handles
@@ -130,6 +148,11 @@ fn main() -> ExitCode {
             // See https://github.com/neovide/neovide/issues/2182 (which includes links to libuv issues)
             runtime.runtime.shutdown_timeout(Duration::from_millis(500));
 
+            if running_tracker.crashed() {
+                drop(update_loop);
+                return handle_neovim_crash(event_loop, &running_tracker, settings);
+            }
+
             match result {
                 Ok(_) => running_tracker.exit_code(),
                 Err(EventLoopError::ExitFailure(code)) => ExitCode::from(code as u8),
@@ -214,10 +237,22 @@ fn setup(
     // reads `DrawCommand`, `SettingChanged`, and `WindowCommand` from the other components.
 
     settings.register::<WindowSettings>();
+    settings.register::<MouseSettings>();
     settings.register::<RendererSettings>();
     settings.register::<CursorSettings>();
+    settings.register::<CommandLineSettings>();
+    settings.register::<MessagesSettings>();
+    settings.register::<PopupMenuSettings>();
+    settings.register::<TablineSettings>();
+    settings.register::<WildmenuSettings>();
+    settings.register::<FileChangedSettings>();
+    settings.register::<ClipboardSettings>();
+    settings.register::<BellSettings>();
 
     let config = Config::init();
+    if let Some(table) = config.settings.clone() {
+        settings.apply_initial_values(config_settings(&settings, table));
+    }
     Config::watch_config_file(config.clone(), proxy.clone());
 
     set_hook(Box::new({
@@ -234,6 +269,27 @@ fn setup(
 
     //Will exit if -h or -v
     cmd_line::handle_command_line_arguments(args().collect(), settings.as_ref())?;
+    if let Some(code) = cmd_line::handle_config_dumps(args().collect(), settings.as_ref(), &config)
+    {
+        std::process::exit(code);
+    }
+    let cmdline_settings = settings.get::<CmdLineSettings>();
+    // The instances started by the running Neovide open their own window, without passing the
+    // variable on to the processes of their Neovim
+    let new_instance = env::var_os(ipc::NEW_INSTANCE_VARIABLE).is_some();
+    env::remove_var(ipc::NEW_INSTANCE_VARIABLE);
+    // nvim:// links are always opened in the running Neovide when there's one, plain files and
+    // file:// URLs only with --single-instance
+    if (cmdline_settings.single_instance || cmdline_settings.opened_url)
+        && !new_instance
+        && ipc::open_in_running_instance(
+            &cmdline_settings.files_to_open,
+            cmdline_settings.line,
+            cmdline_settings.wait,
+        )
+    {
+        std::process::exit(0);
+    }
     #[cfg(not(target_os = "windows"))]
     maybe_disown(&settings);
 
@@ -246,19 +302,27 @@ fn setup(
 
     let window_settings = load_last_window_settings().ok();
     let window_size = determine_window_size(window_settings.as_ref(), &settings);
+    let persisted_grid_size = match window_settings {
+        Some(PersistentWindowSettings::Maximized { grid_size, .. }) => grid_size,
+        Some(PersistentWindowSettings::Windowed { grid_size, .. }) => grid_size,
+        _ => None,
+    };
+    // Attach with the grid that fits the window, so that Neovim doesn't need to resize it once the
+    // fonts are loaded
     let grid_size = match window_size {
         WindowSize::Grid(grid_size) => Some(grid_size),
-        // Clippy wrongly suggests to use unwrap or default here
-        #[allow(clippy::manual_unwrap_or_default)]
-        _ => match window_settings {
-            Some(PersistentWindowSettings::Maximized { grid_size, .. }) => grid_size,
-            Some(PersistentWindowSettings::Windowed { grid_size, .. }) => grid_size,
-            _ => None,
-        },
+        WindowSize::Size(pixel_size) => load_last_cell_metrics()
+            .and_then(|cell_metrics| cell_metrics.grid_size(pixel_size))
+            .or(persisted_grid_size),
+        _ => persisted_grid_size,
     };
 
     let mut runtime = NeovimRuntime::new()?;
-    runtime.launch(proxy, grid_size, running_tracker, settings)?;
+    runtime.launch(proxy.clone(), grid_size, running_tracker, settings)?;
+    // Other processes can only control Neovide when it's asked for
+    if cmdline_settings.single_instance || cmdline_settings.remote_control {
+        ipc::start_server(proxy);
+    }
     Ok((window_size, config.font, runtime))
 }
 
@@ -266,9 +330,16 @@ fn setup(
 pub fn init_logger(settings: &Settings) {
     let cmdline_settings = settings.get::<CmdLineSettings>();
 
+    let spec = if cmdline_settings.log_to_file {
+        LogSpecification::env_or_parse("neovide")
+    } else {
+        LogSpecification::env_or_parse("neovide = error")
+    }
+    .expect("Could not init logger");
+
+    let logger = Logger::with(spec.clone()).format(logging::format);
     let logger = if cmdline_settings.log_to_file {
-        Logger::try_with_env_or_str("neovide")
-            .expect("Could not init logger")
+        logger
             .log_to_file(FileSpec::default())
             .rotate(
                 Criterion::Size(10_000_000),
@@ -277,10 +348,11 @@ pub fn init_logger(settings: &Settings) {
             )
             .duplicate_to_stderr(Duplicate::Error)
     } else {
-        Logger::try_with_env_or_str("neovide = error").expect("Could not init logger")
+        logger
     };
 
-    logger.start().expect("Could not start logger");
+    let handle = logger.start().expect("Could not start logger");
+    logging::set_logger(handle, spec);
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -1,4 +1,34 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use glamour::{Point2, Unit};
+use log::error;
+use rmpv::Value;
+
+use crate::settings::ParseFromValue;
+
+/// Set by the editor while Neovim redraws too fast for the animations to follow.
+static ANIMATIONS_SUPPRESSED: AtomicBool = AtomicBool::new(false);
+
+/// Set from `neovide_reduce_motion` or the reduced motion preference of the OS.
+static MOTION_REDUCED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_animations_suppressed(suppressed: bool) {
+    ANIMATIONS_SUPPRESSED.store(suppressed, Ordering::Relaxed);
+}
+
+pub fn set_motion_reduced(reduced: bool) {
+    MOTION_REDUCED.store(reduced, Ordering::Relaxed);
+}
+
+/// Whether the cursor trails and particles should be left out entirely.
+pub fn motion_reduced() -> bool {
+    MOTION_REDUCED.load(Ordering::Relaxed)
+}
+
+/// Whether the cursor, scroll and window animations should jump to their destination.
+pub fn animations_suppressed() -> bool {
+    ANIMATIONS_SUPPRESSED.load(Ordering::Relaxed) || motion_reduced()
+}
 
 #[allow(dead_code)]
 pub fn ease_linear(t: f32) -> f32 {
@@ -73,21 +103,125 @@ pub fn ease(ease_func: fn(f32) -> f32, start: f32, end: f32, t: f32) -> f32 {
     lerp(start, end, ease_func(t))
 }
 
-pub fn ease_point<T: Unit<Scalar = f32>>(
-    ease_func: fn(f32) -> f32,
-    start: Point2<T>,
-    end: Point2<T>,
-    t: f32,
-) -> Point2<T> {
-    Point2::new(
-        ease(ease_func, start.x, end.x, t),
-        ease(ease_func, start.y, end.y, t),
-    )
+/// How fast the step response of a spring settles, in units of the animation length, so that it's
+/// within 0.3% of the destination at the end.
+const SPRING_SETTLE_RATE: f32 = 8.0;
+
+/// The step response of a spring with the damping ratio `damping`, which ends at `t` = 1.
+/// Underdamped springs overshoot and wobble around the destination.
+fn spring_response(t: f32, damping: f32) -> f32 {
+    if t >= 1.0 {
+        return 1.0;
+    }
+    if (damping - 1.0).abs() < 1e-3 {
+        let omega = SPRING_SETTLE_RATE;
+        1.0 - (1.0 + omega * t) * (-omega * t).exp()
+    } else if damping < 1.0 {
+        let damping = damping.max(0.1);
+        let omega = SPRING_SETTLE_RATE / damping;
+        let damped_omega = omega * (1.0 - damping * damping).sqrt();
+        1.0 - (-damping * omega * t).exp()
+            * ((damped_omega * t).cos() + damping * omega / damped_omega * (damped_omega * t).sin())
+    } else {
+        let root = (damping * damping - 1.0).sqrt();
+        // The slow root is the one settling at the rate
+        let omega = SPRING_SETTLE_RATE * (damping + root);
+        let slow = -omega * (damping - root);
+        let fast = -omega * (damping + root);
+        1.0 - (fast * (slow * t).exp() - slow * (fast * t).exp()) / (fast - slow)
+    }
+}
+
+/// The curve of an animation, chosen per animation with the `*_easing` settings.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseOutQuad,
+    EaseOutCubic,
+    EaseInOutCubic,
+    EaseOutExpo,
+    /// A spring with the damping ratio, 1 is critically damped, less overshoots and more is
+    /// slower to settle.
+    Spring(f32),
+}
+
+impl Easing {
+    /// The progress of the animation at `t`, from 0 to 1.
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => ease_linear(t),
+            Easing::EaseOutQuad => ease_out_quad(t),
+            Easing::EaseOutCubic => ease_out_cubic(t),
+            Easing::EaseInOutCubic => ease_in_out_cubic(t),
+            Easing::EaseOutExpo => ease_out_expo(t),
+            Easing::Spring(damping) => spring_response(t, *damping),
+        }
+    }
+
+    pub fn ease(&self, start: f32, end: f32, t: f32) -> f32 {
+        lerp(start, end, self.apply(t))
+    }
+
+    pub fn ease_point<T: Unit<Scalar = f32>>(
+        &self,
+        start: Point2<T>,
+        end: Point2<T>,
+        t: f32,
+    ) -> Point2<T> {
+        let t = self.apply(t);
+        Point2::new(lerp(start.x, end.x, t), lerp(start.y, end.y, t))
+    }
+
+    fn parse(name: &str) -> Option<Easing> {
+        let easing = match name {
+            "linear" => Easing::Linear,
+            "ease-out-quad" => Easing::EaseOutQuad,
+            "ease-out-cubic" => Easing::EaseOutCubic,
+            "ease-in-out-cubic" => Easing::EaseInOutCubic,
+            "ease-out-expo" => Easing::EaseOutExpo,
+            "spring" => Easing::Spring(1.0),
+            name => {
+                let damping = name.strip_prefix("spring:")?.parse::<f32>().ok()?;
+                Easing::Spring(damping.max(0.0))
+            }
+        };
+        Some(easing)
+    }
+}
+
+impl ParseFromValue for Easing {
+    fn parse_from_value(&mut self, value: Value) {
+        match value.as_str().map(|name| (name, Easing::parse(name))) {
+            Some((_, Some(easing))) => *self = easing,
+            Some((name, None)) => error!(
+                "Expected linear, ease-out-quad, ease-out-cubic, ease-in-out-cubic, \
+                 ease-out-expo, spring or spring:<damping>, but received {name:?}"
+            ),
+            None => error!("Expected an easing name, but received {value:?}"),
+        }
+    }
+}
+
+impl From<Easing> for Value {
+    fn from(easing: Easing) -> Self {
+        let name = match easing {
+            Easing::Linear => "linear".to_string(),
+            Easing::EaseOutQuad => "ease-out-quad".to_string(),
+            Easing::EaseOutCubic => "ease-out-cubic".to_string(),
+            Easing::EaseInOutCubic => "ease-in-out-cubic".to_string(),
+            Easing::EaseOutExpo => "ease-out-expo".to_string(),
+            Easing::Spring(damping) if damping == 1.0 => "spring".to_string(),
+            Easing::Spring(damping) => format!("spring:{damping}"),
+        };
+        Value::from(name)
+    }
 }
 
 pub struct CriticallyDampedSpringAnimation {
     pub position: f32,
     start_position: f32,
+    // The position set by the last update, a different one means it was moved since
+    eased_position: f32,
     velocity: f32,
     scroll_t: f32,
 }
@@ -97,13 +231,20 @@ impl CriticallyDampedSpringAnimation {
         Self {
             position: 0.0,
             start_position: 0.0,
+            eased_position: 0.0,
             velocity: 0.0,
             scroll_t: 2.0,
         }
     }
 
     pub fn update(&mut self, dt: f32, animation_length: f32) -> bool {
-        if self.scroll_t == 2.0 && self.position != 0.0 {
+        self.update_with_easing(dt, animation_length, &Easing::Spring(1.0))
+    }
+
+    /// Moves the position towards 0, with a simulated spring or along the curve of `easing`.
+    pub fn update_with_easing(&mut self, dt: f32, animation_length: f32, easing: &Easing) -> bool {
+        let moved = self.position != self.eased_position;
+        if (self.scroll_t == 2.0 || moved) && self.position != 0.0 {
             self.start_position = self.position;
             self.scroll_t = 0.0;
         }
@@ -115,25 +256,30 @@ impl CriticallyDampedSpringAnimation {
             self.scroll_t = (self.scroll_t + dt / animation_length).min(1.0);
         }
 
-        // For short animations use a standard ease function
-        // This prevents precision errors, and division by zero
-        if animation_length < 0.05 {
-            self.position = ease(ease_out_expo, self.start_position, 0.0, self.scroll_t);
-        } else {
-            // Simulate critically damped spring, also known as a PD controller.
-            // For more details of why this was chosen, see this:
-            // https://gdcvault.com/play/1027059/Math-In-Game-Development-Summit
-            // < 1 underdamped,  1 critically damped, > 1 overdamped
-            let zeta = 1.0;
-            // The omega is calculated so that the destination is reached with a 2% tolerance in
-            // animation_length time.
-            let omega = 4.0 / (zeta * animation_length);
-            let k_p = omega * omega;
-            let k_d = -2.0 * zeta * omega;
-            let acc = -k_p * self.position + k_d * self.velocity;
-            self.velocity += acc * dt;
-            self.position += self.velocity * dt;
+        match easing {
+            // For short animations use a standard ease function
+            // This prevents precision errors, and division by zero
+            Easing::Spring(_) if animation_length < 0.05 => {
+                self.position = ease(ease_out_expo, self.start_position, 0.0, self.scroll_t);
+            }
+            Easing::Spring(damping) => {
+                // Simulate a damped spring, also known as a PD controller.
+                // For more details of why this was chosen, see this:
+                // https://gdcvault.com/play/1027059/Math-In-Game-Development-Summit
+                // < 1 underdamped,  1 critically damped, > 1 overdamped
+                let zeta = damping.max(0.1);
+                // The omega is calculated so that the destination is reached with a 2% tolerance in
+                // animation_length time.
+                let omega = 4.0 / (zeta * animation_length);
+                let k_p = omega * omega;
+                let k_d = -2.0 * zeta * omega;
+                let acc = -k_p * self.position + k_d * self.velocity;
+                self.velocity += acc * dt;
+                self.position += self.velocity * dt;
+            }
+            easing => self.position = easing.ease(self.start_position, 0.0, self.scroll_t),
         }
+        self.eased_position = self.position;
 
         if self.position.abs() < 0.01 {
             self.reset();
@@ -145,6 +291,7 @@ impl CriticallyDampedSpringAnimation {
 
     pub fn reset(&mut self) {
         self.position = 0.0;
+        self.eased_position = 0.0;
         self.velocity = 0.0;
         self.scroll_t = 2.0
     }
@@ -211,72 +358,76 @@ mod test {
     }
 
     #[test]
-    fn test_ease_point_linear() {
-        let start = PixelPos::new(0.0, 0.0);
-        let end = PixelPos::new(1.0, 1.0);
-        assert_eq!(ease_point(ease_linear, start, end, 1.0), end);
-    }
-
-    #[test]
-    fn test_ease_point_in_quad() {
-        let start = PixelPos::new(0.0, 0.0);
-        let end = PixelPos::new(1.0, 1.0);
-        assert_eq!(ease_point(ease_in_quad, start, end, 1.0), end);
-    }
-
-    #[test]
-    fn test_ease_point_out_quad() {
-        let start = PixelPos::new(0.0, 0.0);
-        let end = PixelPos::new(1.0, 1.0);
-        assert_eq!(ease_point(ease_out_quad, start, end, 1.0), end);
-    }
-
-    #[test]
-    fn test_ease_point_in_out_quad() {
-        let start = PixelPos::new(0.0, 0.0);
-        let end = PixelPos::new(1.0, 1.0);
-        let expected = PixelPos::new(0.68000007, 0.68000007);
-        assert_eq!(ease_point(ease_in_out_quad, start, end, 1.0), end);
-        assert_eq!(ease_point(ease_in_out_quad, start, end, 1.4), expected);
-    }
-
-    #[test]
-    fn test_ease_point_in_cubic() {
-        let start = PixelPos::new(0.0, 0.0);
-        let end = PixelPos::new(1.0, 1.0);
-        assert_eq!(ease_point(ease_in_cubic, start, end, 1.0), end);
+    fn test_spring_response() {
+        for damping in [0.3, 1.0, 2.0] {
+            assert_eq!(spring_response(0.0, damping), 0.0);
+            assert_eq!(spring_response(1.0, damping), 1.0);
+            assert!((spring_response(0.95, damping) - 1.0).abs() < 0.02);
+        }
+        // Only underdamped springs overshoot
+        assert!(spring_response(0.12, 0.3) > 1.0);
+        assert!((0..10).all(|t| spring_response(t as f32 / 10.0, 1.0) <= 1.0));
     }
 
     #[test]
-    fn test_ease_point_out_cubic() {
-        let start = PixelPos::new(0.0, 0.0);
-        let end = PixelPos::new(1.0, 1.0);
-        assert_eq!(ease_point(ease_out_cubic, start, end, 1.0), end);
+    fn test_parse_easing() {
+        assert_eq!(Easing::parse("linear"), Some(Easing::Linear));
+        assert_eq!(Easing::parse("spring"), Some(Easing::Spring(1.0)));
+        assert_eq!(Easing::parse("spring:0.5"), Some(Easing::Spring(0.5)));
+        assert_eq!(Easing::parse("spring:soft"), None);
+        assert_eq!(Easing::parse("bounce"), None);
+        assert_eq!(
+            Value::from(Easing::Spring(0.5)),
+            Value::from("spring:0.5".to_string())
+        );
     }
 
     #[test]
-    fn test_ease_point_in_out_cubic() {
+    fn test_ease_point() {
         let start = PixelPos::new(0.0, 0.0);
         let end = PixelPos::new(1.0, 1.0);
+        for easing in [
+            Easing::Linear,
+            Easing::EaseOutQuad,
+            Easing::EaseOutCubic,
+            Easing::EaseInOutCubic,
+            Easing::EaseOutExpo,
+        ] {
+            assert_eq!(easing.ease_point(start, end, 1.0), end);
+            assert_eq!(easing.ease_point(start, end, 0.0), start);
+        }
         let expected = PixelPos::new(0.0625, 0.0625);
-        assert_eq!(ease_point(ease_in_out_cubic, start, end, 1.0), end);
-        assert_eq!(ease_point(ease_in_out_cubic, start, end, 0.25), expected);
+        assert_eq!(
+            Easing::EaseInOutCubic.ease_point(start, end, 0.25),
+            expected
+        );
+        let expected = PixelPos::new(0.9995117, 0.9995117);
+        assert_eq!(Easing::EaseOutExpo.ease_point(start, end, 1.1), expected);
     }
 
     #[test]
-    fn test_ease_point_in_expo() {
-        let start = PixelPos::new(0.0, 0.0);
-        let end = PixelPos::new(1.0, 1.0);
-        assert_eq!(ease_point(ease_in_expo, start, end, 1.0), end);
-        assert_eq!(ease_point(ease_in_expo, start, end, 0.0), start);
+    fn test_ease_ends() {
+        for ease_func in [ease_in_quad, ease_in_out_quad, ease_in_cubic, ease_in_expo] {
+            assert_eq!(ease(ease_func, 0.0, 1.0, 1.0), 1.0);
+        }
+        assert_eq!(ease(ease_in_expo, 0.0, 1.0, 0.0), 0.0);
+        assert_eq!(ease(ease_in_out_quad, 0.0, 1.0, 1.4), 0.68000007);
     }
 
     #[test]
-    fn test_ease_point_out_expo() {
-        let start = PixelPos::new(0.0, 0.0);
-        let end = PixelPos::new(1.0, 1.0);
-        let expected = PixelPos::new(0.9995117, 0.9995117);
-        assert_eq!(ease_point(ease_out_expo, start, end, 1.0), end);
-        assert_eq!(ease_point(ease_out_expo, start, end, 1.1), expected);
+    #[serial_test::serial]
+    fn test_motion_reduced() {
+        set_motion_reduced(true);
+        assert!(motion_reduced());
+        assert!(animations_suppressed());
+
+        set_motion_reduced(false);
+        assert!(!motion_reduced());
+        // Suppressing the animations for fast redraws doesn't reduce the motion of the cursor vfx
+        set_animations_suppressed(true);
+        assert!(animations_suppressed());
+        assert!(!motion_reduced());
+        set_animations_suppressed(false);
+        assert!(!animations_suppressed());
     }
 }
@@ -0,0 +1,194 @@
+//! The bell of Neovim inside the window: a short flash of the grid for the visual bell of
+//! `'visualbell'`, and a beep for the audible one, both configured by `BellSettings`.
+
+use std::{
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use rodio::{source::SineWave, OutputStream, Sink, Source};
+use skia_safe::{Canvas, Color4f, Paint};
+
+use crate::{
+    settings::*,
+    units::{to_skia_rect, PixelRect},
+};
+
+/// The pitch of the beep, in Hz.
+const BEEP_FREQUENCY: f32 = 880.0;
+const BEEP_DURATION: Duration = Duration::from_millis(120);
+
+#[derive(Clone, SettingGroup)]
+#[setting_prefix = "bell"]
+pub struct BellSettings {
+    flash: bool,
+    flash_color: String,
+    flash_opacity: f32,
+    flash_duration: f32,
+    audio: bool,
+    audio_volume: f32,
+}
+
+impl Default for BellSettings {
+    fn default() -> Self {
+        Self {
+            flash: true,
+            flash_color: "".to_string(),
+            flash_opacity: 0.2,
+            flash_duration: 0.15,
+            audio: false,
+            audio_volume: 0.3,
+        }
+    }
+}
+
+/// The opacity of the flash with `remaining` seconds of the `duration` left, fading out.
+fn flash_alpha(remaining: f32, duration: f32, opacity: f32) -> f32 {
+    if duration <= 0.0 {
+        return 0.0;
+    }
+    (remaining / duration).clamp(0.0, 1.0) * opacity.clamp(0.0, 1.0)
+}
+
+/// Plays the beeps sent to it, keeping the audio output open as long as Neovide runs.
+fn play_beeps(beeps: Receiver<f32>) {
+    let (_stream, handle) = match OutputStream::try_default() {
+        Ok(output) => output,
+        Err(error) => {
+            debug!("Could not open the audio output for the bell: {error}");
+            return;
+        }
+    };
+    let sink = match Sink::try_new(&handle) {
+        Ok(sink) => sink,
+        Err(error) => {
+            debug!("Could not play the bell: {error}");
+            return;
+        }
+    };
+    for volume in beeps {
+        sink.append(
+            SineWave::new(BEEP_FREQUENCY)
+                .take_duration(BEEP_DURATION)
+                .amplify(volume.clamp(0.0, 1.0)),
+        );
+    }
+}
+
+/// Plays the beep on the thread owning the audio output, which is started by the first one.
+fn play_beep(volume: f32) {
+    static BEEPS: OnceLock<Option<Sender<f32>>> = OnceLock::new();
+    let beeps = BEEPS.get_or_init(|| {
+        let (sender, receiver) = channel();
+        let result = thread::Builder::new()
+            .name("bell".to_string())
+            .spawn(move || play_beeps(receiver));
+        match result {
+            Ok(_) => Some(sender),
+            Err(error) => {
+                debug!("Could not start the bell thread: {error}");
+                None
+            }
+        }
+    });
+    if let Some(beeps) = beeps {
+        let _ = beeps.send(volume);
+    }
+}
+
+/// Whether a beep at `now` comes after the previous one is over, so that a burst of bells, like
+/// when holding a key at the end of the buffer, doesn't queue up beeps.
+fn beep_is_due(last_beep: Option<Instant>, now: Instant) -> bool {
+    !last_beep.is_some_and(|last_beep| now.duration_since(last_beep) < BEEP_DURATION)
+}
+
+pub struct BellRenderer {
+    /// The seconds left of the flash.
+    remaining: f32,
+    last_beep: Option<Instant>,
+
+    settings: Arc<Settings>,
+}
+
+impl BellRenderer {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            remaining: 0.0,
+            last_beep: None,
+            settings,
+        }
+    }
+
+    /// Neovim rang the bell, the visual one when `'visualbell'` is set.
+    pub fn ring(&mut self, visual: bool) {
+        let settings = self.settings.get::<BellSettings>();
+        if visual {
+            if settings.flash {
+                self.remaining = settings.flash_duration;
+            }
+        } else if settings.audio {
+            let now = Instant::now();
+            if beep_is_due(self.last_beep, now) {
+                self.last_beep = Some(now);
+                play_beep(settings.audio_volume);
+            }
+        }
+    }
+
+    pub fn animate(&mut self, dt: f32) -> bool {
+        if self.remaining <= 0.0 {
+            return false;
+        }
+        self.remaining = (self.remaining - dt).max(0.0);
+        true
+    }
+
+    /// Flashes `rect` with the color of `g:neovide_bell_flash_color`, or `foreground`.
+    pub fn draw(&self, canvas: &Canvas, rect: PixelRect<f32>, foreground: Color4f) {
+        if self.remaining <= 0.0 {
+            return;
+        }
+        let settings = self.settings.get::<BellSettings>();
+        let alpha = flash_alpha(
+            self.remaining,
+            settings.flash_duration,
+            settings.flash_opacity,
+        );
+        let color = csscolorparser::parse(&settings.flash_color)
+            .map(|color| {
+                let [red, green, blue, alpha] = color.to_array();
+                Color4f::new(red, green, blue, alpha)
+            })
+            .unwrap_or(foreground);
+        let mut paint = Paint::new(color, None);
+        paint.set_alpha_f(color.a * alpha);
+        canvas.draw_rect(to_skia_rect(&rect), &paint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flash_fades_out() {
+        assert_eq!(flash_alpha(0.15, 0.15, 0.2), 0.2);
+        assert_eq!(flash_alpha(0.075, 0.15, 0.2), 0.1);
+        assert_eq!(flash_alpha(0.0, 0.15, 0.2), 0.0);
+        assert_eq!(flash_alpha(0.1, 0.0, 0.2), 0.0);
+        assert_eq!(flash_alpha(0.15, 0.15, 3.0), 1.0);
+    }
+
+    #[test]
+    fn beeps_are_debounced() {
+        let now = Instant::now();
+        assert!(beep_is_due(None, now));
+        assert!(!beep_is_due(Some(now), now + BEEP_DURATION / 2));
+        assert!(beep_is_due(Some(now), now + BEEP_DURATION));
+    }
+}
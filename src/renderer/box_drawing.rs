@@ -0,0 +1,297 @@
+//! The box drawing lines of the window separators, the fold column and the borders of the floating
+//! windows, drawn as rectangles snapped to the pixels instead of with the glyphs of the font. The
+//! glyphs rarely fill the cell exactly, which leaves gaps or overlaps between the lines, especially
+//! with fractional scale factors. The `~` filler of the lines past the end of the buffer is drawn
+//! the same way, so that it lines up with them.
+
+use std::borrow::Cow;
+
+use skia_safe::{Canvas, Paint, PaintStyle, Path, Rect};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The thickness of the line going from the center of the cell to one of its sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Weight {
+    None,
+    Light,
+    Heavy,
+}
+
+/// How the lines meet in the center of the cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Shape {
+    Straight,
+    /// An arc joining the two lines of a corner, like `╭`.
+    Rounded,
+    /// The `~` of the lines past the end of the buffer, without any lines.
+    Tilde,
+}
+
+/// The lines of a box drawing character, from the center to the top, right, bottom and left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoxLines {
+    up: Weight,
+    right: Weight,
+    down: Weight,
+    left: Weight,
+    shape: Shape,
+}
+
+const fn lines(up: u8, right: u8, down: u8, left: u8) -> BoxLines {
+    const fn weight(weight: u8) -> Weight {
+        match weight {
+            0 => Weight::None,
+            1 => Weight::Light,
+            _ => Weight::Heavy,
+        }
+    }
+    BoxLines {
+        up: weight(up),
+        right: weight(right),
+        down: weight(down),
+        left: weight(left),
+        shape: Shape::Straight,
+    }
+}
+
+const fn rounded(up: u8, right: u8, down: u8, left: u8) -> BoxLines {
+    BoxLines {
+        shape: Shape::Rounded,
+        ..lines(up, right, down, left)
+    }
+}
+
+const TILDE: BoxLines = BoxLines {
+    shape: Shape::Tilde,
+    ..lines(0, 0, 0, 0)
+};
+
+impl BoxLines {
+    pub fn of(grapheme: &str) -> Option<Self> {
+        let mut chars = grapheme.chars();
+        let (Some(character), None) = (chars.next(), chars.next()) else {
+            return None;
+        };
+        Some(match character {
+            '─' => lines(0, 1, 0, 1),
+            '━' => lines(0, 2, 0, 2),
+            '│' => lines(1, 0, 1, 0),
+            '┃' => lines(2, 0, 2, 0),
+            '┌' => lines(0, 1, 1, 0),
+            '┏' => lines(0, 2, 2, 0),
+            '┐' => lines(0, 0, 1, 1),
+            '┓' => lines(0, 0, 2, 2),
+            '└' => lines(1, 1, 0, 0),
+            '┗' => lines(2, 2, 0, 0),
+            '┘' => lines(1, 0, 0, 1),
+            '┛' => lines(2, 0, 0, 2),
+            '├' => lines(1, 1, 1, 0),
+            '┣' => lines(2, 2, 2, 0),
+            '┤' => lines(1, 0, 1, 1),
+            '┫' => lines(2, 0, 2, 2),
+            '┬' => lines(0, 1, 1, 1),
+            '┳' => lines(0, 2, 2, 2),
+            '┴' => lines(1, 1, 0, 1),
+            '┻' => lines(2, 2, 0, 2),
+            '┼' => lines(1, 1, 1, 1),
+            '╋' => lines(2, 2, 2, 2),
+            '╴' => lines(0, 0, 0, 1),
+            '╵' => lines(1, 0, 0, 0),
+            '╶' => lines(0, 1, 0, 0),
+            '╷' => lines(0, 0, 1, 0),
+            '╭' => rounded(0, 1, 1, 0),
+            '╮' => rounded(0, 0, 1, 1),
+            '╯' => rounded(1, 0, 0, 1),
+            '╰' => rounded(1, 1, 0, 0),
+            _ => return None,
+        })
+    }
+
+    /// Like [`BoxLines::of`], but also takes the `~` filler when `end_of_buffer` is set, that is
+    /// when the text has the `EndOfBuffer` highlight. Anywhere else it's just text.
+    fn of_cell(grapheme: &str, end_of_buffer: bool) -> Option<Self> {
+        if end_of_buffer && grapheme == "~" {
+            Some(TILDE)
+        } else {
+            Self::of(grapheme)
+        }
+    }
+
+    /// Draws the lines in the cell at `cell`, with `stroke` pixels for the light lines.
+    pub fn draw(&self, canvas: &Canvas, cell: Rect, stroke: f32, paint: &Paint) {
+        let thickness = |weight| match weight {
+            Weight::None => 0.0,
+            Weight::Light => stroke,
+            Weight::Heavy => stroke * 2.0,
+        };
+        let vertical = thickness(self.up).max(thickness(self.down));
+        let horizontal = thickness(self.left).max(thickness(self.right));
+        // The lines are centered, but start on a whole pixel so that they stay crisp. The ends are
+        // the exact edges of the cell, so the lines of the next cells continue without any gap.
+        let x = (cell.center_x() - vertical / 2.0).round();
+        let y = (cell.center_y() - horizontal / 2.0).round();
+        match self.shape {
+            Shape::Straight => {}
+            Shape::Rounded => {
+                return self.draw_arc(canvas, cell, (x, y), (vertical, horizontal), paint)
+            }
+            Shape::Tilde => return draw_tilde(canvas, cell, y, stroke, paint),
+        }
+        let mut draw = |left: f32, top: f32, right: f32, bottom: f32| {
+            canvas.draw_rect(Rect::new(left, top, right, bottom), paint);
+        };
+        if self.up != Weight::None {
+            let width = thickness(self.up);
+            draw(x, cell.top, x + width, y + horizontal.max(width));
+        }
+        if self.down != Weight::None {
+            let width = thickness(self.down);
+            draw(x, y, x + width, cell.bottom);
+        }
+        if self.left != Weight::None {
+            let height = thickness(self.left);
+            draw(cell.left, y, x + vertical.max(height), y + height);
+        }
+        if self.right != Weight::None {
+            let height = thickness(self.right);
+            draw(x, y, cell.right, y + height);
+        }
+    }
+
+    /// Draws a rounded corner, as a curve from the side of one line to the side of the other,
+    /// passing by the center. The ends are exactly where the straight lines would be.
+    fn draw_arc(
+        &self,
+        canvas: &Canvas,
+        cell: Rect,
+        (x, y): (f32, f32),
+        (vertical, horizontal): (f32, f32),
+        paint: &Paint,
+    ) {
+        let center = (x + vertical / 2.0, y + horizontal / 2.0);
+        let start = if self.up != Weight::None {
+            (center.0, cell.top)
+        } else {
+            (center.0, cell.bottom)
+        };
+        let end = if self.left != Weight::None {
+            (cell.left, center.1)
+        } else {
+            (cell.right, center.1)
+        };
+        let mut path = Path::default();
+        path.move_to(start);
+        path.quad_to(center, end);
+        let mut paint = paint.clone();
+        paint.set_anti_alias(true);
+        paint.set_style(PaintStyle::Stroke);
+        paint.set_stroke_width(vertical.max(horizontal));
+        canvas.draw_path(&path, &paint);
+    }
+}
+
+/// Draws the `~` filler as a wave around the height of the horizontal lines, starting at `y`.
+fn draw_tilde(canvas: &Canvas, cell: Rect, y: f32, stroke: f32, paint: &Paint) {
+    let center = y + stroke / 2.0;
+    let amplitude = (cell.height() * 0.08).max(stroke);
+    let left = cell.left + cell.width() * 0.15;
+    let right = cell.right - cell.width() * 0.15;
+    let middle = cell.center_x();
+    let mut path = Path::default();
+    path.move_to((left, center + amplitude / 2.0));
+    path.cubic_to(
+        (middle, center - amplitude * 1.5),
+        (middle, center + amplitude * 1.5),
+        (right, center - amplitude / 2.0),
+    );
+    let mut paint = paint.clone();
+    paint.set_anti_alias(true);
+    paint.set_style(PaintStyle::Stroke);
+    paint.set_stroke_width(stroke);
+    canvas.draw_path(&path, &paint);
+}
+
+/// Replaces the box drawing characters of `text` with spaces, returning them along with the
+/// cells they are in. With `end_of_buffer`, the `~` fillers are taken too.
+pub fn take_box_drawing(text: &str, end_of_buffer: bool) -> (Cow<'_, str>, Vec<(usize, BoxLines)>) {
+    let mut found = Vec::new();
+    for (cell, grapheme) in text.graphemes(true).enumerate() {
+        if let Some(lines) = BoxLines::of_cell(grapheme, end_of_buffer) {
+            found.push((cell, lines));
+        }
+    }
+    if found.is_empty() {
+        return (Cow::Borrowed(text), found);
+    }
+    let text = text
+        .graphemes(true)
+        .map(|grapheme| {
+            if BoxLines::of_cell(grapheme, end_of_buffer).is_some() {
+                " "
+            } else {
+                grapheme
+            }
+        })
+        .collect();
+    (Cow::Owned(text), found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_drawing_is_replaced_with_spaces() {
+        let (text, found) = take_box_drawing("ab│c─┼", false);
+        assert_eq!(text, "ab c  ");
+        assert_eq!(
+            found,
+            vec![
+                (2, lines(1, 0, 1, 0)),
+                (4, lines(0, 1, 0, 1)),
+                (5, lines(1, 1, 1, 1)),
+            ]
+        );
+        let (text, found) = take_box_drawing("no lines |-+~", false);
+        assert!(matches!(text, Cow::Borrowed("no lines |-+~")));
+        assert!(found.is_empty());
+        // Combined with another character it's not a line anymore
+        assert_eq!(BoxLines::of("│\u{301}"), None);
+    }
+
+    #[test]
+    fn rounded_corners_join_like_the_square_ones() {
+        for (rounded_corner, square) in [("╭", "┌"), ("╮", "┐"), ("╯", "┘"), ("╰", "└")]
+        {
+            let rounded_corner = BoxLines::of(rounded_corner).unwrap();
+            let square = BoxLines::of(square).unwrap();
+            assert_eq!(rounded_corner.shape, Shape::Rounded);
+            assert_eq!(
+                BoxLines {
+                    shape: Shape::Straight,
+                    ..rounded_corner
+                },
+                square
+            );
+        }
+        let (text, found) = take_box_drawing("╭─╮", false);
+        assert_eq!(text, "   ");
+        assert_eq!(found.len(), 3);
+    }
+
+    #[test]
+    fn tilde_is_only_taken_past_the_end_of_the_buffer() {
+        assert_eq!(BoxLines::of("~"), None);
+        let (text, found) = take_box_drawing("~/src", false);
+        assert!(matches!(text, Cow::Borrowed("~/src")));
+        assert!(found.is_empty());
+
+        let (text, found) = take_box_drawing("~", true);
+        assert_eq!(text, " ");
+        assert_eq!(found, vec![(0, TILDE)]);
+        // Other fillchars stay text, or lines when they are box drawing characters
+        let (text, found) = take_box_drawing("-│", true);
+        assert_eq!(text, "- ");
+        assert_eq!(found, vec![(1, lines(1, 0, 1, 0))]);
+    }
+}
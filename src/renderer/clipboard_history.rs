@@ -0,0 +1,306 @@
+//! `:NeovideClipboardHistory`, the texts copied through Neovide listed in a box above the grid,
+//! filtered while typing, so that an older one can be pasted again.
+
+use std::sync::Arc;
+
+use skia_safe::{Canvas, Paint, PaintStyle, RRect};
+
+use crate::{
+    editor::{Colors, Style},
+    profiling::tracy_zone,
+    renderer::{
+        cmdline_renderer::{begin_floating_box, end_floating_box, floating_box_radius},
+        popupmenu::{scroll_top, text_width, truncate},
+        GridRenderer, RendererSettings,
+    },
+    units::{to_skia_rect, GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Widest the entries get, in columns.
+const MAX_COLUMNS: usize = 80;
+const PADDING: usize = 2;
+/// The rows of the box that aren't entries: the padding, the query with a blank line, and the
+/// footer with a blank line.
+const CHROME_ROWS: usize = 6;
+const FOOTER: &str = "↑/↓ select  Enter paste  Esc close";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClipboardHistoryAction {
+    Next,
+    Previous,
+    Type(String),
+    Erase,
+    Paste,
+    Close,
+}
+
+/// Whether `entry` contains every word of the query, ignoring case.
+fn matches(entry: &str, query: &str) -> bool {
+    let entry = entry.to_lowercase();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| entry.contains(word))
+}
+
+/// An entry on a single line, with its line breaks shown as ⏎.
+fn entry_label(entry: &str) -> String {
+    entry
+        .trim_end_matches('\n')
+        .replace('\n', " ⏎ ")
+        .replace('\t', " ")
+}
+
+/// Draws `:NeovideClipboardHistory` in a box above the grid, searched and picked from with the
+/// keyboard.
+pub struct ClipboardHistoryRenderer {
+    /// The history when it's shown, the most recent entry first.
+    entries: Option<Vec<String>>,
+    query: String,
+    /// The selected entry of the ones matching the query.
+    selected: usize,
+    scroll: usize,
+}
+
+impl ClipboardHistoryRenderer {
+    pub fn new() -> Self {
+        Self {
+            entries: None,
+            query: String::new(),
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    /// Shows the entries, filtered by `query` to start with.
+    pub fn open(&mut self, entries: Vec<String>, query: &str) {
+        self.entries = Some(entries);
+        self.query = query.trim().to_string();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    /// The entries matching the query.
+    fn matching(&self) -> Vec<&String> {
+        self.entries
+            .iter()
+            .flatten()
+            .filter(|entry| matches(entry, &self.query))
+            .collect()
+    }
+
+    /// Returns the text to paste once an entry is picked.
+    pub fn navigate(&mut self, action: ClipboardHistoryAction) -> Option<String> {
+        let count = self.matching().len();
+        match action {
+            ClipboardHistoryAction::Next if count > 0 => {
+                self.selected = (self.selected + 1) % count;
+            }
+            ClipboardHistoryAction::Previous if count > 0 => {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            ClipboardHistoryAction::Type(text) => {
+                self.query.push_str(&text);
+                self.selected = 0;
+            }
+            ClipboardHistoryAction::Erase => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            ClipboardHistoryAction::Paste => {
+                let entry = self
+                    .matching()
+                    .get(self.selected)
+                    .map(|entry| entry.to_string());
+                if entry.is_some() {
+                    self.entries = None;
+                }
+                return entry;
+            }
+            ClipboardHistoryAction::Close => self.entries = None,
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("clipboard_history_draw");
+        if !self.is_active() {
+            return;
+        }
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+        let max_columns = ((region.max.x - region.min.x) / cell_width).floor() as usize;
+        let max_rows = ((region.max.y - region.min.y) / line_height).floor() as usize;
+        if max_columns <= 2 * PADDING + 1 || max_rows <= CHROME_ROWS + 2 {
+            return;
+        }
+        let columns = MAX_COLUMNS.min(max_columns - 2 * PADDING);
+
+        let labels: Vec<String> = self
+            .matching()
+            .into_iter()
+            .map(|entry| entry_label(entry))
+            .collect();
+        // A row is left above and below the box, and one is kept for the message without entries
+        let visible_rows = labels.len().clamp(1, max_rows - 2 - CHROME_ROWS);
+        let rows = visible_rows + CHROME_ROWS;
+        self.scroll = scroll_top(self.scroll, Some(self.selected), visible_rows, labels.len());
+
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let size = PixelSize::new(
+            (columns + 2 * PADDING) as f32 * cell_width,
+            rows as f32 * line_height,
+        );
+        let x = region.min.x + ((region.max.x - region.min.x - size.width) / 2.0).max(0.0);
+        let y = region.min.y + line_height;
+        let origin = PixelPos::new(x.round(), y.round());
+        let rect = PixelRect::from_origin_and_size(origin, size);
+
+        let default_style = grid_renderer.default_style.clone();
+        let blur_amount = renderer_settings.floating_blur.then_some((
+            renderer_settings.floating_blur_amount_x,
+            renderer_settings.floating_blur_amount_y,
+        ));
+        begin_floating_box(
+            root_canvas,
+            &rect,
+            radius,
+            blur_amount,
+            1.0,
+            default_style.background(&default_style.colors).to_color(),
+        );
+        root_canvas.translate((origin.x, origin.y));
+
+        let mut selected_style = Style::new(Colors::new(None, None, None));
+        selected_style.reverse = true;
+        let selected_style = Some(Arc::new(selected_style));
+
+        let query = truncate(&format!("> {}█", self.query), columns);
+        grid_renderer.draw_foreground(
+            root_canvas,
+            &query,
+            GridPos::new(PADDING as i32, 1),
+            columns as i32,
+            &None,
+        );
+        if labels.is_empty() {
+            let message = if self.entries.iter().flatten().next().is_some() {
+                "No matching entries"
+            } else {
+                "The clipboard history is empty"
+            };
+            grid_renderer.draw_foreground(
+                root_canvas,
+                &truncate(message, columns),
+                GridPos::new(PADDING as i32, 3),
+                columns as i32,
+                &None,
+            );
+        }
+        let visible = labels
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(visible_rows);
+        for (row, (index, label)) in visible.enumerate() {
+            let text = truncate(label, columns);
+            let position = GridPos::new(PADDING as i32, row as i32 + 3);
+            let style = if index == self.selected {
+                // The selection spans the whole width, even for short entries
+                grid_renderer.draw_background(
+                    root_canvas,
+                    position,
+                    columns as i32,
+                    &selected_style,
+                    1.0,
+                );
+                &selected_style
+            } else {
+                &None
+            };
+            let width = text_width(&text) as i32;
+            grid_renderer.draw_foreground(root_canvas, &text, position, width, style);
+        }
+        grid_renderer.draw_foreground(
+            root_canvas,
+            &truncate(FOOTER, columns),
+            GridPos::new(PADDING as i32, (rows - 2) as i32),
+            columns as i32,
+            &None,
+        );
+        end_floating_box(root_canvas);
+
+        let mut outline_color = default_style.foreground(&default_style.colors);
+        outline_color.a = 0.5;
+        let mut outline = Paint::new(outline_color, None);
+        outline.set_style(PaintStyle::Stroke);
+        outline.set_anti_alias(true);
+        root_canvas.draw_rrect(
+            RRect::new_rect_xy(to_skia_rect(&rect), radius, radius),
+            &outline,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> ClipboardHistoryRenderer {
+        let mut history = ClipboardHistoryRenderer::new();
+        let entries = ["let x = 1", "Hello World\n", "fn main() {\n}"];
+        history.open(entries.map(str::to_string).to_vec(), "");
+        history
+    }
+
+    #[test]
+    fn entries_are_filtered_by_every_word() {
+        let mut history = history();
+        assert_eq!(history.matching().len(), 3);
+        for text in ["world", " hello"] {
+            history.navigate(ClipboardHistoryAction::Type(text.to_string()));
+        }
+        assert_eq!(history.matching(), ["Hello World\n"]);
+        history.navigate(ClipboardHistoryAction::Type("!".to_string()));
+        assert!(history.matching().is_empty());
+        assert_eq!(history.navigate(ClipboardHistoryAction::Paste), None);
+        assert!(history.is_active());
+    }
+
+    #[test]
+    fn the_selected_entry_is_pasted() {
+        let mut history = history();
+        history.navigate(ClipboardHistoryAction::Previous);
+        assert_eq!(history.selected, 2);
+        history.navigate(ClipboardHistoryAction::Next);
+        assert_eq!(history.selected, 0);
+        history.navigate(ClipboardHistoryAction::Next);
+        // Typing starts again from the first match
+        history.navigate(ClipboardHistoryAction::Type("n".to_string()));
+        assert_eq!(history.selected, 0);
+        history.navigate(ClipboardHistoryAction::Erase);
+        history.navigate(ClipboardHistoryAction::Next);
+        assert_eq!(
+            history.navigate(ClipboardHistoryAction::Paste).as_deref(),
+            Some("Hello World\n")
+        );
+        assert!(!history.is_active());
+    }
+
+    #[test]
+    fn line_breaks_are_shown_on_one_line() {
+        assert_eq!(entry_label("fn main() {\n\t}\n"), "fn main() { ⏎  }");
+    }
+}
@@ -0,0 +1,431 @@
+use std::sync::Arc;
+
+use skia_safe::{
+    canvas::SaveLayerRec, image_filters::blur, BlendMode, Canvas, Color, Paint, RRect, Rect,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    editor::{CommandLineLine, Style, StyledText},
+    profiling::tracy_zone,
+    renderer::{
+        animation_utils::{ease, ease_out_cubic},
+        fonts::font_options::{EmojiOptions, FontOptions},
+        GridRenderer, RendererSettings,
+    },
+    settings::*,
+    units::{to_skia_rect, GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Padding around the text, in cells.
+const PADDING: f32 = 0.5;
+/// The narrowest the command line box gets, in cells.
+const MIN_COLUMNS: usize = 20;
+
+#[derive(Clone, SettingGroup)]
+#[setting_prefix = "cmdline"]
+pub struct CommandLineSettings {
+    pub enabled: bool,
+    position: String,
+    font_size: f32,
+    width: f32,
+    blur: bool,
+    animation_length: f32,
+}
+
+impl Default for CommandLineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position: "top".to_string(),
+            font_size: 0.0,
+            width: 0.6,
+            blur: true,
+            animation_length: 0.15,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CommandLineAnchor {
+    Top,
+    Center,
+    Bottom,
+}
+
+impl CommandLineAnchor {
+    fn parse(value: &str) -> Self {
+        match value {
+            "center" => CommandLineAnchor::Center,
+            "bottom" => CommandLineAnchor::Bottom,
+            _ => CommandLineAnchor::Top,
+        }
+    }
+}
+
+/// One wrapped row of the command line box.
+#[derive(Default)]
+pub(super) struct Row {
+    pub fragments: Vec<(usize, StyledText)>,
+    pub cursor_column: Option<usize>,
+}
+
+/// Wraps the command line lines into rows of at most `columns` cells.
+pub(super) fn layout_rows(lines: &[CommandLineLine], columns: usize) -> Vec<Row> {
+    let columns = columns.max(1);
+    let mut rows = Vec::new();
+    for line in lines {
+        let mut line_rows = vec![Row::default()];
+        let mut column = 0;
+        for fragment in &line.fragments {
+            for grapheme in fragment.text.graphemes(true) {
+                if column == columns {
+                    line_rows.push(Row::default());
+                    column = 0;
+                }
+                let row = line_rows.last_mut().unwrap();
+                match row.fragments.last_mut() {
+                    Some((_, last)) if last.style == fragment.style => last.text.push_str(grapheme),
+                    _ => row.fragments.push((
+                        column,
+                        StyledText {
+                            text: grapheme.to_string(),
+                            style: fragment.style.clone(),
+                        },
+                    )),
+                }
+                column += 1;
+            }
+        }
+        if let Some(cursor) = line.cursor_column {
+            let row_index = cursor / columns;
+            line_rows.resize_with(line_rows.len().max(row_index + 1), Row::default);
+            line_rows[row_index].cursor_column = Some(cursor % columns);
+        }
+        rows.extend(line_rows);
+    }
+    rows
+}
+
+pub(super) fn row_width(row: &Row) -> usize {
+    let text_width = row.fragments.last().map_or(0, |(column, fragment)| {
+        column + fragment.text.graphemes(true).count()
+    });
+    text_width.max(row.cursor_column.map_or(0, |cursor| cursor + 1))
+}
+
+/// Draws Neovim's externalized command line as a floating box above the grid.
+pub struct CommandLineRenderer {
+    grid_renderer: GridRenderer,
+    font_options: Option<FontOptions>,
+    applied_font_size: f32,
+    lines: Vec<CommandLineLine>,
+    visible: bool,
+    /// Goes from 0 when hidden to 1 when fully shown.
+    visibility: f32,
+    /// Where the box was last drawn, to place the completion popup next to it.
+    box_rect: Option<PixelRect<f32>>,
+
+    settings: Arc<Settings>,
+}
+
+impl CommandLineRenderer {
+    pub fn new(scale_factor: f64, settings: Arc<Settings>) -> Self {
+        Self {
+            grid_renderer: GridRenderer::new(scale_factor, settings.clone()),
+            font_options: None,
+            applied_font_size: 0.0,
+            lines: Vec::new(),
+            visible: false,
+            visibility: 0.0,
+            box_rect: None,
+            settings,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible || self.visibility > 0.0
+    }
+
+    /// Where the command line box was last drawn, if it's shown.
+    pub fn box_rect(&self) -> Option<PixelRect<f32>> {
+        self.box_rect
+    }
+
+    /// The area the completion popup attaches to, starting at `column` of the text.
+    pub fn popup_menu_anchor(&self, column: u64) -> Option<PixelRect<f32>> {
+        let rect = self.box_rect?;
+        let cell_width = self.grid_renderer.grid_scale.width();
+        let x = rect.min.x + (PADDING + column as f32) * cell_width;
+        Some(PixelRect::new(
+            PixelPos::new(x, rect.min.y),
+            PixelPos::new(x + cell_width, rect.max.y),
+        ))
+    }
+
+    pub fn set_lines(&mut self, lines: Vec<CommandLineLine>) {
+        self.visible = !lines.is_empty();
+        // Keep the old content around while fading out
+        if self.visible {
+            self.lines = lines;
+        }
+    }
+
+    pub fn update_default_style(&mut self, default_style: Arc<Style>) {
+        self.grid_renderer.default_style = default_style;
+    }
+
+    pub fn handle_scale_factor_update(&mut self, scale_factor: f64) {
+        self.grid_renderer.handle_scale_factor_update(scale_factor);
+    }
+
+    pub fn update_linespace(&mut self, linespace: f32) {
+        self.grid_renderer.update_linespace(linespace);
+    }
+
+    pub fn update_font(&mut self, guifont_setting: &str) {
+        // Errors are already reported by the main grid renderer
+        if let Ok(options) = FontOptions::parse(guifont_setting) {
+            self.update_font_options(options);
+        }
+    }
+
+    pub fn update_font_options(&mut self, options: FontOptions) {
+        self.font_options = Some(options);
+        self.apply_font();
+    }
+
+    pub fn update_emoji_options(&mut self, emoji: EmojiOptions) {
+        self.grid_renderer.update_emoji_options(emoji);
+    }
+
+    /// Applies the `neovide_cmdline_font_size` override on top of the current guifont.
+    fn apply_font(&mut self) {
+        let font_size = self.settings.get::<CommandLineSettings>().font_size;
+        if let Some(mut options) = self.font_options.clone() {
+            if font_size > 0.0 {
+                options.size = font_size;
+            }
+            self.grid_renderer.update_font_options(options);
+        }
+        self.applied_font_size = font_size;
+    }
+
+    pub fn animate(&mut self, dt: f32) -> bool {
+        let settings = self.settings.get::<CommandLineSettings>();
+        if settings.font_size != self.applied_font_size {
+            self.apply_font();
+        }
+
+        let target = if self.visible { 1.0 } else { 0.0 };
+        if self.visibility == target {
+            return false;
+        }
+        let step = if settings.animation_length > 0.0 {
+            dt / settings.animation_length
+        } else {
+            1.0
+        };
+        self.visibility = if self.visible {
+            (self.visibility + step).min(1.0)
+        } else {
+            (self.visibility - step).max(0.0)
+        };
+        if self.visibility == 0.0 {
+            self.lines.clear();
+        }
+        true
+    }
+
+    /// Draws the command line centered horizontally inside `region`.
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("cmdline_draw");
+        self.box_rect = None;
+        if !self.is_visible() || self.lines.is_empty() {
+            return;
+        }
+        let settings = self.settings.get::<CommandLineSettings>();
+        let grid_scale = self.grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+
+        let region_width = region.max.x - region.min.x;
+        let max_columns = ((region_width / cell_width - 2.0 * PADDING).floor() as usize).max(1);
+        let preferred_columns = (region_width * settings.width.clamp(0.1, 1.0) / cell_width
+            - 2.0 * PADDING)
+            .floor() as usize;
+        let columns = preferred_columns.clamp(MIN_COLUMNS.min(max_columns), max_columns);
+        let rows = layout_rows(&self.lines, columns);
+        let used_columns = rows
+            .iter()
+            .map(row_width)
+            .max()
+            .unwrap_or(0)
+            .clamp(MIN_COLUMNS.min(columns), columns);
+
+        let size = PixelSize::new(
+            (used_columns as f32 + 2.0 * PADDING) * cell_width,
+            (rows.len() as f32 + 2.0 * PADDING) * line_height,
+        );
+        let t = ease_out_cubic(self.visibility);
+        let position = CommandLineAnchor::parse(&settings.position);
+        let top = match position {
+            CommandLineAnchor::Top => region.min.y + line_height,
+            CommandLineAnchor::Center => region.center().y - size.height / 2.0,
+            CommandLineAnchor::Bottom => region.max.y - line_height - size.height,
+        };
+        // Slide in from the closest edge
+        let slide = match position {
+            CommandLineAnchor::Bottom => line_height,
+            _ => -line_height,
+        };
+        let origin = PixelPos::new(
+            (region.center().x - size.width / 2.0).round(),
+            (top + ease(ease_out_cubic, slide, 0.0, self.visibility)).round(),
+        );
+        let rect = PixelRect::from_origin_and_size(origin, size);
+        self.box_rect = Some(rect);
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let blur_amount = settings.blur.then_some((
+            renderer_settings.floating_blur_amount_x * t,
+            renderer_settings.floating_blur_amount_y * t,
+        ));
+        let default_style = self.grid_renderer.default_style.clone();
+        let background = default_style.background(&default_style.colors).to_color();
+        begin_floating_box(root_canvas, &rect, radius, blur_amount, t, background);
+
+        root_canvas.translate((
+            origin.x + PADDING * cell_width,
+            origin.y + PADDING * line_height,
+        ));
+        for (row_index, row) in rows.iter().enumerate() {
+            for (column, fragment) in &row.fragments {
+                let position = GridPos::new(*column as i32, row_index as i32);
+                let width = fragment.text.graphemes(true).count() as i32;
+                self.grid_renderer.draw_background(
+                    root_canvas,
+                    position,
+                    width,
+                    &fragment.style,
+                    1.0,
+                );
+                self.grid_renderer.draw_foreground(
+                    root_canvas,
+                    &fragment.text,
+                    position,
+                    width,
+                    &fragment.style,
+                );
+            }
+            if let Some(cursor) = row.cursor_column {
+                let caret = Rect::from_xywh(
+                    cursor as f32 * cell_width,
+                    row_index as f32 * line_height,
+                    (cell_width / 8.0).max(1.0).round(),
+                    line_height,
+                );
+                let paint = Paint::new(default_style.foreground(&default_style.colors), None);
+                root_canvas.draw_rect(caret, &paint);
+            }
+        }
+        end_floating_box(root_canvas);
+    }
+}
+
+/// The corner radius of floating boxes, following `neovide_floating_corner_radius`.
+pub(super) fn floating_box_radius(renderer_settings: &RendererSettings, line_height: f32) -> f32 {
+    let corner_radius = renderer_settings.floating_corner_radius;
+    if corner_radius > 0.0 && corner_radius <= 1.0 {
+        corner_radius * line_height
+    } else {
+        0.0
+    }
+}
+
+/// Starts drawing a rounded box floating above the grid, blurring what's behind it. The content
+/// drawn until [`end_floating_box`] is clipped to the box and faded by `opacity`.
+pub(super) fn begin_floating_box(
+    root_canvas: &Canvas,
+    rect: &PixelRect<f32>,
+    radius: f32,
+    blur_amount: Option<(f32, f32)>,
+    opacity: f32,
+    background: Color,
+) {
+    let skia_rect = to_skia_rect(rect);
+    let rrect = RRect::new_rect_xy(skia_rect, radius, radius);
+
+    root_canvas.save();
+    root_canvas.clip_rrect(rrect, None, Some(true));
+
+    if let Some(blur) = blur_amount.and_then(|amount| blur(amount, None, None, None)) {
+        let paint = Paint::default()
+            .set_anti_alias(false)
+            .set_blend_mode(BlendMode::Src)
+            .to_owned();
+        let save_layer_rec = SaveLayerRec::default()
+            .backdrop(&blur)
+            .bounds(&skia_rect)
+            .paint(&paint);
+        root_canvas.save_layer(&save_layer_rec);
+        root_canvas.restore();
+    }
+
+    root_canvas.save_layer_alpha(Some(skia_rect), (opacity * 255.0) as u32);
+    let mut paint = Paint::default();
+    paint.set_anti_alias(true);
+    paint.set_color(background);
+    root_canvas.draw_rrect(rrect, &paint);
+}
+
+pub(super) fn end_floating_box(root_canvas: &Canvas) {
+    root_canvas.restore();
+    root_canvas.restore();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, cursor_column: Option<usize>) -> CommandLineLine {
+        CommandLineLine {
+            fragments: vec![StyledText {
+                text: text.to_string(),
+                style: None,
+            }],
+            cursor_column,
+        }
+    }
+
+    #[test]
+    fn long_lines_wrap() {
+        let rows = layout_rows(&[line("abcdefg", Some(5))], 3);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[2].fragments[0],
+            (
+                0,
+                StyledText {
+                    text: "g".to_string(),
+                    style: None
+                }
+            )
+        );
+        assert_eq!(rows[1].cursor_column, Some(2));
+        assert_eq!(rows[0].cursor_column, None);
+    }
+
+    #[test]
+    fn cursor_after_a_full_row_starts_a_new_one() {
+        let rows = layout_rows(&[line("abc", Some(3))], 3);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].cursor_column, Some(0));
+        assert_eq!(row_width(&rows[0]), 3);
+        assert_eq!(row_width(&rows[1]), 1);
+    }
+}
@@ -1,9 +1,10 @@
 mod blink;
 mod cursor_vfx;
+mod shape;
 
 use std::{collections::HashMap, sync::Arc};
 
-use skia_safe::{op, Canvas, Paint, Path};
+use skia_safe::{Canvas, Paint, Path};
 use winit::event::WindowEvent;
 
 use crate::{
@@ -11,12 +12,13 @@ use crate::{
     editor::{Cursor, CursorShape},
     profiling::{tracy_plot, tracy_zone},
     renderer::{animation_utils::*, GridRenderer, RenderedWindow},
-    settings::{ParseFromValue, Settings},
-    units::{to_skia_point, GridPos, GridScale, PixelPos, PixelSize, PixelVec},
+    settings::{CachedSettings, ParseFromValue, Settings},
+    units::{GridPos, GridScale, GridVec, PixelPos, PixelSize},
     window::ShouldRender,
 };
 
 use blink::*;
+use shape::{add_serifs, outline_path, rounded_path};
 
 const DEFAULT_CELL_PERCENTAGE: f32 = 1.0 / 8.0;
 
@@ -28,12 +30,18 @@ const STANDARD_CORNERS: &[(f32, f32); 4] = &[(-0.5, -0.5), (0.5, -0.5), (0.5, 0.
 pub struct CursorSettings {
     antialiasing: bool,
     animation_length: f32,
+    easing: Easing,
     distance_length_adjust: bool,
     animate_in_insert_mode: bool,
     animate_command_line: bool,
     trail_size: f32,
     unfocused_outline_width: f32,
     smooth_blink: bool,
+    corner_radius: f32,
+    underline_thickness: f32,
+    outline: bool,
+    fill_opacity: f32,
+    ibeam_serifs: bool,
 
     vfx_mode: cursor_vfx::VfxMode,
     vfx_opacity: f32,
@@ -49,12 +57,18 @@ impl Default for CursorSettings {
         CursorSettings {
             antialiasing: true,
             animation_length: 0.06,
+            easing: Easing::EaseOutExpo,
             distance_length_adjust: true,
             animate_in_insert_mode: true,
             animate_command_line: true,
             trail_size: 0.7,
             unfocused_outline_width: 1.0 / 8.0,
             smooth_blink: false,
+            corner_radius: 0.0,
+            underline_thickness: 0.0,
+            outline: false,
+            fill_opacity: 0.0,
+            ibeam_serifs: false,
             vfx_mode: cursor_vfx::VfxMode::Disabled,
             vfx_opacity: 200.0,
             vfx_particle_lifetime: 1.2,
@@ -152,12 +166,10 @@ impl Corner {
                 (self.t + corner_dt / (settings.animation_length * self.length_multiplier)).min(1.0)
         }
 
-        self.current_position = ease_point(
-            ease_out_expo,
-            self.start_position,
-            corner_destination,
-            self.t,
-        );
+        self.current_position =
+            settings
+                .easing
+                .ease_point(self.start_position, corner_destination, self.t);
 
         true
     }
@@ -167,14 +179,16 @@ pub struct CursorRenderer {
     pub corners: Vec<Corner>,
     cursor: Cursor,
     destination: PixelPos<f32>,
+    /// The motion of the keys sent to a remote server that Neovim hasn't moved the cursor for yet.
+    predicted_motion: GridVec<i32>,
     blink_status: BlinkStatus,
-    previous_cursor_shape: Option<CursorShape>,
+    previous_cursor_shape: Option<(CursorShape, f32)>,
     previous_editor_mode: EditorMode,
     cursor_vfx: Option<Box<dyn cursor_vfx::CursorVfx>>,
     previous_vfx_mode: cursor_vfx::VfxMode,
     window_has_focus: bool,
 
-    settings: Arc<Settings>,
+    settings: CachedSettings<CursorSettings>,
 }
 
 impl CursorRenderer {
@@ -183,6 +197,7 @@ impl CursorRenderer {
             corners: vec![Corner::new(); 4],
             cursor: Cursor::new(),
             destination: (0.0, 0.0).into(),
+            predicted_motion: GridVec::new(0, 0),
             blink_status: BlinkStatus::new(),
             previous_cursor_shape: None,
             previous_editor_mode: EditorMode::Normal,
@@ -190,7 +205,7 @@ impl CursorRenderer {
             previous_vfx_mode: cursor_vfx::VfxMode::Disabled,
             window_has_focus: true,
 
-            settings,
+            settings: CachedSettings::new(settings),
         };
         renderer.set_cursor_shape(&CursorShape::Block, DEFAULT_CELL_PERCENTAGE);
         renderer
@@ -204,6 +219,12 @@ impl CursorRenderer {
 
     pub fn update_cursor(&mut self, new_cursor: Cursor) {
         self.cursor = new_cursor;
+        // Neovim's cursor replaces the prediction
+        self.predicted_motion = GridVec::new(0, 0);
+    }
+
+    pub fn predict_motion(&mut self, motion: GridVec<i32>) {
+        self.predicted_motion += motion;
     }
 
     fn set_cursor_shape(&mut self, cursor_shape: &CursorShape, cell_percentage: f32) {
@@ -245,7 +266,14 @@ impl CursorRenderer {
             .try_cast()
             .unwrap();
         if let Some(window) = windows.get(&self.cursor.parent_window_id) {
-            let mut grid = cursor_grid_position + window.grid_current_position.to_vector();
+            let mut grid = cursor_grid_position;
+            // The prediction stays inside the window, like the cursor of Neovim would
+            grid.x = (grid.x + self.predicted_motion.x as f32)
+                .min(window.grid_size.width as f32 - 1.0)
+                .max(0.0);
+            grid.y = (grid.y + self.predicted_motion.y as f32)
+                .min(window.grid_size.height as f32 - 1.0)
+                .max(0.0);
             grid.y -= window.scroll_animation.position;
 
             let top_border = window.viewport_margins.top as f32;
@@ -254,25 +282,31 @@ impl CursorRenderer {
             // Prevent the cursor from targeting a position outside its current window. Since only
             // the vertical direction is effected by scrolling, we only have to clamp the vertical
             // grid position.
-            grid.y = grid.y.max(window.grid_current_position.y + top_border).min(
-                window.grid_current_position.y + window.grid_size.height as f32
-                    - 1.0
-                    - bottom_border,
-            );
-
-            self.destination = grid * grid_scale;
+            grid.y = grid
+                .y
+                .max(top_border)
+                .min(window.grid_size.height as f32 - 1.0 - bottom_border);
+
+            // Windows drawn with their own font have differently sized cells
+            let content_scale = window.font_scale.unwrap_or(grid_scale);
+            self.destination =
+                window.grid_current_position * grid_scale + grid.to_vector() * content_scale;
         } else {
             self.destination = cursor_grid_position * grid_scale;
         }
     }
 
+    pub fn parent_window_id(&self) -> u64 {
+        self.cursor.parent_window_id
+    }
+
     pub fn prepare_frame(&mut self) -> ShouldRender {
         self.blink_status.update_status(&self.cursor)
     }
 
     pub fn draw(&mut self, grid_renderer: &mut GridRenderer, canvas: &Canvas) {
         tracy_zone!("cursor_draw");
-        let settings = self.settings.get::<CursorSettings>();
+        let settings = self.settings.get();
         let render = self.blink_status.should_render() || settings.smooth_blink;
         let opacity = match settings.smooth_blink {
             true => self.blink_status.opacity(),
@@ -296,11 +330,17 @@ impl CursorRenderer {
             .with_a((opacity * alpha) as u8);
         paint.set_color(background_color);
 
-        let path = if self.window_has_focus || self.cursor.shape != CursorShape::Block {
-            self.draw_rectangle(canvas, &paint)
-        } else {
+        let radius = settings.corner_radius * grid_renderer.grid_scale.width();
+        let outlined = !self.window_has_focus || settings.outline;
+        let path = if outlined && self.cursor.shape == CursorShape::Block {
             let outline_width = settings.unfocused_outline_width * grid_renderer.em_size;
-            self.draw_rectangular_outline(canvas, &paint, outline_width)
+            let fill_opacity = settings.fill_opacity.clamp(0.0, 1.0);
+            self.draw_rectangular_outline(canvas, &paint, outline_width, radius, fill_opacity)
+        } else {
+            let serif_length = (settings.ibeam_serifs
+                && self.cursor.shape == CursorShape::Vertical)
+                .then(|| grid_renderer.grid_scale.width() / 2.0);
+            self.draw_rectangle(canvas, &paint, radius, serif_length)
         };
 
         // Draw foreground
@@ -342,11 +382,16 @@ impl CursorRenderer {
         dt: f32,
     ) -> bool {
         tracy_zone!("cursor_animate");
-        let settings = self.settings.get::<CursorSettings>();
+        let settings = self.settings.get();
 
-        if settings.vfx_mode != self.previous_vfx_mode {
-            self.cursor_vfx = cursor_vfx::new_cursor_vfx(&settings.vfx_mode);
-            self.previous_vfx_mode = settings.vfx_mode.clone();
+        let vfx_mode = if motion_reduced() {
+            &cursor_vfx::VfxMode::Disabled
+        } else {
+            &settings.vfx_mode
+        };
+        if *vfx_mode != self.previous_vfx_mode {
+            self.cursor_vfx = cursor_vfx::new_cursor_vfx(vfx_mode);
+            self.previous_vfx_mode = vfx_mode.clone();
         }
 
         let mut cursor_width = grid_renderer.grid_scale.width();
@@ -363,14 +408,20 @@ impl CursorRenderer {
 
         let center_destination = self.destination + cursor_dimensions.to_vector() * 0.5;
 
-        if self.previous_cursor_shape.as_ref() != Some(&self.cursor.shape) {
-            self.previous_cursor_shape = Some(self.cursor.shape.clone());
-            self.set_cursor_shape(
-                &self.cursor.shape.clone(),
-                self.cursor
-                    .cell_percentage
-                    .unwrap_or(DEFAULT_CELL_PERCENTAGE),
-            );
+        let cell_percentage = match self.cursor.shape {
+            CursorShape::Horizontal if settings.underline_thickness > 0.0 => {
+                settings.underline_thickness * grid_renderer.shaper.scale_factor()
+                    / grid_renderer.grid_scale.height()
+            }
+            _ => self
+                .cursor
+                .cell_percentage
+                .unwrap_or(DEFAULT_CELL_PERCENTAGE),
+        };
+        let cursor_shape = (self.cursor.shape.clone(), cell_percentage);
+        if self.previous_cursor_shape.as_ref() != Some(&cursor_shape) {
+            self.set_cursor_shape(&cursor_shape.0, cell_percentage);
+            self.previous_cursor_shape = Some(cursor_shape);
 
             if let Some(vfx) = self.cursor_vfx.as_mut() {
                 vfx.restart(center_destination);
@@ -381,7 +432,8 @@ impl CursorRenderer {
 
         if center_destination != PixelPos::ZERO {
             let immediate_movement = !settings.animate_in_insert_mode && in_insert_mode
-                || !settings.animate_command_line && !changed_to_from_cmdline;
+                || !settings.animate_command_line && !changed_to_from_cmdline
+                || animations_suppressed();
             for corner in self.corners.iter_mut() {
                 let corner_animating = corner.update(
                     &settings,
@@ -420,46 +472,45 @@ impl CursorRenderer {
         animating
     }
 
-    fn draw_rectangle(&self, canvas: &Canvas, paint: &Paint) -> Path {
-        // The cursor is made up of four points, so I create a path with each of the four
-        // corners.
-        let mut path = Path::new();
+    fn corner_positions(&self) -> [PixelPos<f32>; 4] {
+        [0, 1, 2, 3].map(|index| self.corners[index].current_position)
+    }
 
-        path.move_to(to_skia_point(self.corners[0].current_position));
-        path.line_to(to_skia_point(self.corners[1].current_position));
-        path.line_to(to_skia_point(self.corners[2].current_position));
-        path.line_to(to_skia_point(self.corners[3].current_position));
-        path.close();
+    /// Draws the cursor through its four corners, rounded by `radius`, with the serifs of an
+    /// I-beam `serif_length` wide when they're given.
+    fn draw_rectangle(
+        &self,
+        canvas: &Canvas,
+        paint: &Paint,
+        radius: f32,
+        serif_length: Option<f32>,
+    ) -> Path {
+        let corners = self.corner_positions();
+        let mut path = rounded_path(&corners, radius);
+        if let Some(serif_length) = serif_length {
+            add_serifs(&mut path, &corners, serif_length);
+        }
 
         canvas.draw_path(&path, paint);
         path
     }
 
-    fn draw_rectangular_outline(&self, canvas: &Canvas, paint: &Paint, outline_width: f32) -> Path {
-        let mut rectangle = Path::new();
-        rectangle.move_to(to_skia_point(self.corners[0].current_position));
-        rectangle.line_to(to_skia_point(self.corners[1].current_position));
-        rectangle.line_to(to_skia_point(self.corners[2].current_position));
-        rectangle.line_to(to_skia_point(self.corners[3].current_position));
-        rectangle.close();
-
-        let offsets: [PixelVec<f32>; 4] = [
-            (outline_width, outline_width).into(),
-            (-outline_width, outline_width).into(),
-            (-outline_width, -outline_width).into(),
-            (outline_width, -outline_width).into(),
-        ];
-
-        let mut subtract = Path::new();
-        subtract.move_to(to_skia_point(self.corners[0].current_position + offsets[0]));
-        subtract.line_to(to_skia_point(self.corners[1].current_position + offsets[1]));
-        subtract.line_to(to_skia_point(self.corners[2].current_position + offsets[2]));
-        subtract.line_to(to_skia_point(self.corners[3].current_position + offsets[3]));
-        subtract.close();
-
-        // We have two "rectangles"; create an outline path by subtracting the smaller rectangle
-        // from the larger one. This can fail in which case we return a full "rectangle".
-        let path = op(&rectangle, &subtract, skia_safe::PathOp::Difference).unwrap_or(rectangle);
+    /// Draws the outline of the block cursor, filled with `fill_opacity` of its color.
+    fn draw_rectangular_outline(
+        &self,
+        canvas: &Canvas,
+        paint: &Paint,
+        outline_width: f32,
+        radius: f32,
+        fill_opacity: f32,
+    ) -> Path {
+        let corners = self.corner_positions();
+        if fill_opacity > 0.0 {
+            let mut fill = paint.clone();
+            fill.set_alpha_f(paint.alpha_f() * fill_opacity);
+            canvas.draw_path(&rounded_path(&corners, radius), &fill);
+        }
+        let path = outline_path(&corners, outline_width, radius);
 
         canvas.draw_path(&path, paint);
         path
@@ -0,0 +1,99 @@
+//! The paths of the cursor, from the four corners it animates. They're quadrilaterals while the
+//! cursor moves, so the corners are rounded along their edges rather than as a rectangle.
+
+use skia_safe::{op, Path, PathOp, Rect};
+
+use crate::units::{to_skia_point, PixelPos, PixelVec};
+
+fn shortest_side(corners: &[PixelPos<f32>; 4]) -> f32 {
+    (0..4)
+        .map(|index| (corners[(index + 1) % 4] - corners[index]).length())
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// The cursor with its corners rounded by `radius`, which is kept to half of its shortest side.
+pub fn rounded_path(corners: &[PixelPos<f32>; 4], radius: f32) -> Path {
+    let mut path = Path::new();
+    let radius = radius.min(shortest_side(corners) / 2.0);
+    if radius <= 0.0 {
+        path.move_to(to_skia_point(corners[0]));
+        for corner in &corners[1..] {
+            path.line_to(to_skia_point(*corner));
+        }
+        path.close();
+        return path;
+    }
+    // Start in the middle of an edge, so that every corner gets its arc
+    path.move_to(to_skia_point(corners[3].lerp(corners[0], 0.5)));
+    for index in 0..4 {
+        path.arc_to_tangent(
+            to_skia_point(corners[index]),
+            to_skia_point(corners[(index + 1) % 4]),
+            radius,
+        );
+    }
+    path.close();
+    path
+}
+
+/// The outline of the cursor, `width` thick, following the rounded corners.
+pub fn outline_path(corners: &[PixelPos<f32>; 4], width: f32, radius: f32) -> Path {
+    let offsets: [PixelVec<f32>; 4] = [
+        (width, width).into(),
+        (-width, width).into(),
+        (-width, -width).into(),
+        (width, -width).into(),
+    ];
+    let inner = [0, 1, 2, 3].map(|index| corners[index] + offsets[index]);
+    let outer = rounded_path(corners, radius);
+    let inner = rounded_path(&inner, (radius - width).max(0.0));
+    // This can fail, in which case the cursor is filled
+    op(&outer, &inner, PathOp::Difference).unwrap_or(outer)
+}
+
+/// Adds the serifs of an I-beam to a bar cursor, `length` wide and as thick as the bar, at its top
+/// and bottom.
+pub fn add_serifs(path: &mut Path, corners: &[PixelPos<f32>; 4], length: f32) {
+    let thickness = (corners[1].x - corners[0].x).abs();
+    let center = (corners[0].x + corners[1].x) / 2.0;
+    let left = center - length / 2.0;
+    let right = center + length / 2.0;
+    let top = corners[0].y;
+    let bottom = corners[3].y;
+    path.add_rect(Rect::new(left, top, right, top + thickness), None);
+    path.add_rect(Rect::new(left, bottom - thickness, right, bottom), None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(left: f32, top: f32, right: f32, bottom: f32) -> [PixelPos<f32>; 4] {
+        [
+            PixelPos::new(left, top),
+            PixelPos::new(right, top),
+            PixelPos::new(right, bottom),
+            PixelPos::new(left, bottom),
+        ]
+    }
+
+    #[test]
+    fn cursor_shapes_keep_to_the_cell() {
+        let block = rectangle(10.0, 20.0, 20.0, 40.0);
+        let square = rounded_path(&block, 0.0);
+        let rounded = rounded_path(&block, 100.0);
+        assert_eq!(*square.bounds(), Rect::new(10.0, 20.0, 20.0, 40.0));
+        assert_eq!(*rounded.bounds(), Rect::new(10.0, 20.0, 20.0, 40.0));
+        assert!(square.contains((10.5, 20.5)));
+        assert!(!rounded.contains((10.5, 20.5)));
+
+        let outline = outline_path(&block, 2.0, 0.0);
+        assert!(outline.contains((11.0, 30.0)));
+        assert!(!outline.contains((15.0, 30.0)));
+
+        let bar = rectangle(10.0, 20.0, 12.0, 40.0);
+        let mut ibeam = rounded_path(&bar, 0.0);
+        add_serifs(&mut ibeam, &bar, 6.0);
+        assert_eq!(*ibeam.bounds(), Rect::new(8.0, 20.0, 14.0, 40.0));
+    }
+}
@@ -394,6 +394,10 @@ impl SkiaRenderer for D3DSkiaRenderer {
         &self.window
     }
 
+    fn gr_context(&mut self) -> &mut DirectContext {
+        &mut self.gr_context
+    }
+
     fn flush(&mut self) {}
 
     fn swap_buffers(&mut self) {
@@ -0,0 +1,267 @@
+use std::sync::Arc;
+
+use glamour::Contains;
+use skia_safe::{Canvas, Paint, Rect};
+use winit::keyboard::{Key, NamedKey};
+
+use crate::{
+    editor::{Colors, Style},
+    profiling::tracy_zone,
+    renderer::{
+        cmdline_renderer::{begin_floating_box, end_floating_box, floating_box_radius},
+        popupmenu::{text_width, truncate},
+        GridRenderer, RendererSettings,
+    },
+    units::{GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Columns of padding around the text of the prompt.
+const PADDING: usize = 1;
+/// Columns between the buttons.
+const GAP: usize = 2;
+const MAX_COLUMNS: usize = 60;
+/// The answers, `true` restarting Neovim.
+const BUTTONS: [(bool, &str); 2] = [(true, "Restart"), (false, "Close")];
+
+/// The answer given with `key`, `true` restarting Neovim, or `None` when the key doesn't answer.
+pub fn answer_for_key(key: Key<&str>) -> Option<bool> {
+    match key {
+        Key::Named(NamedKey::Enter) | Key::Character("r") => Some(true),
+        Key::Named(NamedKey::Escape) | Key::Character("q") => Some(false),
+        _ => None,
+    }
+}
+
+fn buttons_width() -> usize {
+    BUTTONS
+        .iter()
+        .map(|(_, label)| text_width(label) + 2)
+        .sum::<usize>()
+        + GAP * (BUTTONS.len() - 1)
+}
+
+/// The answer, the first column and the width of each button, for a prompt of `columns` columns
+/// of text. They are right aligned like the buttons of a dialog.
+fn button_layout(columns: usize) -> Vec<(bool, usize, usize)> {
+    let mut column = PADDING + columns - buttons_width().min(columns);
+    BUTTONS
+        .iter()
+        .map(|(restart, label)| {
+            let width = text_width(label) + 2;
+            let button = (*restart, column, width);
+            column += width + GAP;
+            button
+        })
+        .collect()
+}
+
+/// Draws a prompt in the middle of the grid when Neovim quit with an error, like with `:cquit` or
+/// a broken config, asking whether to start it again in the same window or to close it.
+pub struct ExitPromptRenderer {
+    /// The exit code of Neovim, while waiting for an answer.
+    exit_code: Option<u8>,
+    /// Where the prompt and its buttons were last drawn, to find the clicked button.
+    box_rect: Option<PixelRect<f32>>,
+    button_rects: Vec<(bool, PixelRect<f32>)>,
+}
+
+impl ExitPromptRenderer {
+    pub fn new() -> Self {
+        Self {
+            exit_code: None,
+            box_rect: None,
+            button_rects: Vec::new(),
+        }
+    }
+
+    pub fn show(&mut self, exit_code: u8) {
+        self.exit_code = Some(exit_code);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.exit_code.is_some()
+    }
+
+    pub fn hide(&mut self) {
+        self.exit_code = None;
+        self.box_rect = None;
+        self.button_rects.clear();
+    }
+
+    /// Returns whether the button at `position` restarts Neovim.
+    pub fn hit_test(&self, position: PixelPos<f32>) -> Option<bool> {
+        self.button_rects
+            .iter()
+            .find(|(_, rect)| rect.contains(&position))
+            .map(|(restart, _)| *restart)
+    }
+
+    pub fn contains(&self, position: PixelPos<f32>) -> bool {
+        self.box_rect.is_some_and(|rect| rect.contains(&position))
+    }
+
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("exit_prompt_draw");
+        self.box_rect = None;
+        self.button_rects.clear();
+        let Some(exit_code) = self.exit_code else {
+            return;
+        };
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+        let max_columns = ((region.max.x - region.min.x) / cell_width).floor();
+        if max_columns <= (2 * PADDING) as f32 {
+            return;
+        }
+        let text_columns = (max_columns as usize - 2 * PADDING).min(MAX_COLUMNS);
+
+        let buttons_width = buttons_width();
+        let lines = [
+            truncate(
+                &format!("Neovim exited with code {exit_code}"),
+                text_columns,
+            ),
+            truncate("Restart it in this window?", text_columns),
+        ];
+        let columns = lines
+            .iter()
+            .map(|line| text_width(line))
+            .max()
+            .unwrap_or(0)
+            .max(buttons_width)
+            .min(text_columns);
+        // The lines, an empty line and the buttons
+        let rows = lines.len() + 2;
+
+        let size = PixelSize::new(
+            (columns + 2 * PADDING) as f32 * cell_width,
+            (rows + 2 * PADDING) as f32 * line_height,
+        );
+        let origin = PixelPos::new(
+            ((region.min.x + region.max.x - size.width) / 2.0).round(),
+            ((region.min.y + region.max.y - size.height) / 2.0).round(),
+        );
+        let rect = PixelRect::from_origin_and_size(origin, size);
+
+        let default_style = grid_renderer.default_style.clone();
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let blur_amount = renderer_settings.floating_blur.then_some((
+            renderer_settings.floating_blur_amount_x,
+            renderer_settings.floating_blur_amount_y,
+        ));
+        begin_floating_box(
+            root_canvas,
+            &rect,
+            radius,
+            blur_amount,
+            1.0,
+            default_style.background(&default_style.colors).to_color(),
+        );
+        root_canvas.translate((origin.x, origin.y));
+        self.box_rect = Some(rect);
+
+        let mut bold = Style::new(Colors::new(None, None, None));
+        bold.bold = true;
+        let bold = Some(Arc::new(bold));
+        for (row, line) in lines.iter().enumerate() {
+            let style = if row == 0 { &bold } else { &None };
+            grid_renderer.draw_foreground(
+                root_canvas,
+                line,
+                GridPos::new(PADDING as i32, (row + PADDING) as i32),
+                columns as i32,
+                style,
+            );
+        }
+
+        // The buttons are drawn reversed, right aligned like the buttons of a dialog
+        let mut button_style = Style::new(Colors::new(None, None, None));
+        button_style.reverse = true;
+        let button_style = Some(Arc::new(button_style));
+        let button_paint = Paint::new(default_style.foreground(&default_style.colors), None);
+        let row = rows - 1 + PADDING;
+        for ((restart, column, width), (_, label)) in
+            button_layout(columns).into_iter().zip(BUTTONS)
+        {
+            let button = Rect::from_xywh(
+                column as f32 * cell_width,
+                row as f32 * line_height,
+                width as f32 * cell_width,
+                line_height,
+            );
+            root_canvas.draw_rect(button, &button_paint);
+            grid_renderer.draw_foreground(
+                root_canvas,
+                &format!(" {label} "),
+                GridPos::new(column as i32, row as i32),
+                width as i32,
+                &button_style,
+            );
+            self.button_rects.push((
+                restart,
+                PixelRect::from_origin_and_size(
+                    PixelPos::new(origin.x + button.left, origin.y + button.top),
+                    PixelSize::new(button.width(), button.height()),
+                ),
+            ));
+        }
+
+        end_floating_box(root_canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_answer_the_prompt() {
+        assert_eq!(answer_for_key(Key::Named(NamedKey::Enter)), Some(true));
+        assert_eq!(answer_for_key(Key::Character("r")), Some(true));
+        assert_eq!(answer_for_key(Key::Named(NamedKey::Escape)), Some(false));
+        assert_eq!(answer_for_key(Key::Character("q")), Some(false));
+        assert_eq!(answer_for_key(Key::Character("x")), None);
+        assert_eq!(answer_for_key(Key::Named(NamedKey::Tab)), None);
+    }
+
+    #[test]
+    fn buttons_are_right_aligned() {
+        // " Restart " and " Close " with a gap between them
+        assert_eq!(button_layout(30), vec![(true, 13, 9), (false, 24, 7)]);
+        // A prompt narrower than the buttons starts them at the padding
+        assert_eq!(button_layout(10)[0], (true, PADDING, 9));
+    }
+
+    #[test]
+    fn clicked_button_gives_the_answer() {
+        let mut prompt = ExitPromptRenderer::new();
+        prompt.show(1);
+        assert!(prompt.is_active());
+        let rect = |x: f32| {
+            PixelRect::from_origin_and_size(PixelPos::new(x, 100.0), PixelSize::new(50.0, 20.0))
+        };
+        prompt.box_rect = Some(PixelRect::new(
+            PixelPos::new(0.0, 0.0),
+            PixelPos::new(200.0, 200.0),
+        ));
+        prompt.button_rects = vec![(true, rect(50.0)), (false, rect(120.0))];
+
+        assert_eq!(prompt.hit_test(PixelPos::new(60.0, 110.0)), Some(true));
+        assert_eq!(prompt.hit_test(PixelPos::new(130.0, 110.0)), Some(false));
+        // Between the buttons, the click is only inside the prompt
+        assert_eq!(prompt.hit_test(PixelPos::new(110.0, 110.0)), None);
+        assert!(prompt.contains(PixelPos::new(110.0, 110.0)));
+
+        prompt.hide();
+        assert!(!prompt.is_active());
+        assert_eq!(prompt.hit_test(PixelPos::new(60.0, 110.0)), None);
+        assert!(!prompt.contains(PixelPos::new(110.0, 110.0)));
+    }
+}
@@ -0,0 +1,282 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use glamour::Contains;
+use skia_safe::{Canvas, Paint, Rect};
+
+use crate::{
+    editor::{Colors, Style},
+    profiling::tracy_zone,
+    renderer::{
+        cmdline_renderer::{begin_floating_box, end_floating_box, floating_box_radius},
+        popupmenu::{text_width, truncate},
+        GridRenderer, RendererSettings,
+    },
+    settings::*,
+    units::{GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Columns of padding around the text of the prompt.
+const PADDING: usize = 1;
+/// Lines between the prompt and the edge of the grid.
+const MARGIN: f32 = 0.5;
+/// Columns between the buttons.
+const GAP: usize = 2;
+const MAX_COLUMNS: usize = 60;
+
+#[derive(Clone, Default, SettingGroup)]
+#[setting_prefix = "file_changed"]
+pub struct FileChangedSettings {
+    prompt: bool,
+}
+
+/// What Neovim reported about a file changed outside of it, from `FileChangedShell`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileChange {
+    pub buffer: u64,
+    pub file: String,
+    /// The `v:fcs_reason`, like `changed`, `conflict` or `deleted`.
+    pub reason: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileChangeAction {
+    Reload,
+    Keep,
+    Diff,
+}
+
+impl FileChangeAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            FileChangeAction::Reload => "reload",
+            FileChangeAction::Keep => "keep",
+            FileChangeAction::Diff => "diff",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            FileChangeAction::Reload => "Reload",
+            FileChangeAction::Keep => "Keep",
+            FileChangeAction::Diff => "Diff",
+        }
+    }
+}
+
+impl FileChange {
+    fn message(&self) -> &'static str {
+        match self.reason.as_str() {
+            "deleted" => "was deleted from the disk",
+            "conflict" => "changed on disk and has unsaved changes",
+            _ => "changed on disk",
+        }
+    }
+
+    /// A deleted file can't be reloaded or compared with the one on disk.
+    fn actions(&self) -> &'static [FileChangeAction] {
+        if self.reason == "deleted" {
+            &[FileChangeAction::Keep]
+        } else {
+            &[
+                FileChangeAction::Reload,
+                FileChangeAction::Keep,
+                FileChangeAction::Diff,
+            ]
+        }
+    }
+}
+
+/// Draws a prompt in the top right corner of the grid for the files changed outside of Neovim,
+/// with buttons to reload them, keep the text of the buffer or compare both.
+pub struct FileChangedRenderer {
+    /// The changes waiting for an answer, the first one is shown.
+    changes: VecDeque<FileChange>,
+    /// Where the prompt and its buttons were last drawn, to find the clicked button.
+    box_rect: Option<PixelRect<f32>>,
+    button_rects: Vec<(FileChangeAction, PixelRect<f32>)>,
+}
+
+impl FileChangedRenderer {
+    pub fn new() -> Self {
+        Self {
+            changes: VecDeque::new(),
+            box_rect: None,
+            button_rects: Vec::new(),
+        }
+    }
+
+    /// Queues the prompt for `change`, replacing the one of the same buffer.
+    pub fn push(&mut self, change: FileChange) {
+        self.changes.retain(|queued| queued.buffer != change.buffer);
+        self.changes.push_back(change);
+    }
+
+    /// Closes the shown prompt, returning the buffer it was about.
+    pub fn answer(&mut self) -> Option<u64> {
+        self.box_rect = None;
+        self.button_rects.clear();
+        self.changes.pop_front().map(|change| change.buffer)
+    }
+
+    pub fn hit_test(&self, position: PixelPos<f32>) -> Option<FileChangeAction> {
+        self.button_rects
+            .iter()
+            .find(|(_, rect)| rect.contains(&position))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn contains(&self, position: PixelPos<f32>) -> bool {
+        self.box_rect.is_some_and(|rect| rect.contains(&position))
+    }
+
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("file_changed_draw");
+        self.box_rect = None;
+        self.button_rects.clear();
+        let Some(change) = self.changes.front() else {
+            return;
+        };
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+        let max_columns = ((region.max.x - region.min.x) / cell_width - 2.0 * MARGIN).floor();
+        if max_columns <= (2 * PADDING) as f32 {
+            return;
+        }
+        let text_columns = (max_columns as usize - 2 * PADDING).min(MAX_COLUMNS);
+
+        let actions = change.actions();
+        let buttons_width = actions
+            .iter()
+            .map(|action| text_width(action.label()) + 2)
+            .sum::<usize>()
+            + GAP * (actions.len() - 1);
+        let lines = [
+            truncate(&change.file, text_columns),
+            truncate(change.message(), text_columns),
+        ];
+        let columns = lines
+            .iter()
+            .map(|line| text_width(line))
+            .max()
+            .unwrap_or(0)
+            .max(buttons_width)
+            .min(text_columns);
+        // The file, the message, an empty line and the buttons
+        let rows = lines.len() + 2;
+
+        let size = PixelSize::new(
+            (columns + 2 * PADDING) as f32 * cell_width,
+            (rows + 2 * PADDING) as f32 * line_height,
+        );
+        let origin = PixelPos::new(
+            (region.max.x - MARGIN * line_height - size.width).round(),
+            (region.min.y + MARGIN * line_height).round(),
+        );
+        let rect = PixelRect::from_origin_and_size(origin, size);
+
+        let default_style = grid_renderer.default_style.clone();
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let blur_amount = renderer_settings.floating_blur.then_some((
+            renderer_settings.floating_blur_amount_x,
+            renderer_settings.floating_blur_amount_y,
+        ));
+        begin_floating_box(
+            root_canvas,
+            &rect,
+            radius,
+            blur_amount,
+            1.0,
+            default_style.background(&default_style.colors).to_color(),
+        );
+        root_canvas.translate((origin.x, origin.y));
+        self.box_rect = Some(rect);
+
+        let mut bold = Style::new(Colors::new(None, None, None));
+        bold.bold = true;
+        let bold = Some(Arc::new(bold));
+        for (row, line) in lines.iter().enumerate() {
+            let style = if row == 0 { &bold } else { &None };
+            grid_renderer.draw_foreground(
+                root_canvas,
+                line,
+                GridPos::new(PADDING as i32, (row + PADDING) as i32),
+                columns as i32,
+                style,
+            );
+        }
+
+        // The buttons are drawn reversed, right aligned like the buttons of a dialog
+        let mut button_style = Style::new(Colors::new(None, None, None));
+        button_style.reverse = true;
+        let button_style = Some(Arc::new(button_style));
+        let button_paint = Paint::new(default_style.foreground(&default_style.colors), None);
+        let row = rows - 1 + PADDING;
+        let mut column = PADDING + columns - buttons_width.min(columns);
+        for &action in actions {
+            let width = text_width(action.label()) + 2;
+            let button = Rect::from_xywh(
+                column as f32 * cell_width,
+                row as f32 * line_height,
+                width as f32 * cell_width,
+                line_height,
+            );
+            root_canvas.draw_rect(button, &button_paint);
+            grid_renderer.draw_foreground(
+                root_canvas,
+                &format!(" {} ", action.label()),
+                GridPos::new(column as i32, row as i32),
+                width as i32,
+                &button_style,
+            );
+            self.button_rects.push((
+                action,
+                PixelRect::from_origin_and_size(
+                    PixelPos::new(origin.x + button.left, origin.y + button.top),
+                    PixelSize::new(button.width(), button.height()),
+                ),
+            ));
+            column += width + GAP;
+        }
+
+        end_floating_box(root_canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(buffer: u64, reason: &str) -> FileChange {
+        FileChange {
+            buffer,
+            file: format!("/tmp/{buffer}.txt"),
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn prompts_are_answered_in_order_once_per_buffer() {
+        let mut renderer = FileChangedRenderer::new();
+        renderer.push(change(1, "changed"));
+        renderer.push(change(2, "changed"));
+        renderer.push(change(1, "conflict"));
+        assert_eq!(renderer.changes.front(), Some(&change(2, "changed")));
+        assert_eq!(renderer.answer(), Some(2));
+        assert_eq!(renderer.changes.front(), Some(&change(1, "conflict")));
+        assert_eq!(renderer.answer(), Some(1));
+        assert_eq!(renderer.answer(), None);
+    }
+
+    #[test]
+    fn deleted_files_can_only_be_kept() {
+        assert_eq!(change(1, "deleted").actions(), &[FileChangeAction::Keep]);
+        assert_eq!(change(1, "conflict").actions().len(), 3);
+    }
+}
@@ -1,13 +1,12 @@
-use std::{num::NonZeroUsize, sync::Arc};
+use std::sync::Arc;
 
 use itertools::Itertools;
-use log::{debug, error, info, trace};
-use lru::LruCache;
-use skia_safe::{graphics::set_font_cache_limit, TextBlob, TextBlobBuilder};
+use log::{debug, error, info, trace, warn};
+use skia_safe::{graphics::set_font_cache_limit, Point, TextBlob, TextBlobBuilder};
 use swash::{
     shape::ShapeContext,
     text::{
-        cluster::{CharCluster, Parser, Status, Token},
+        cluster::{CharCluster, ClusterInfo, Emoji, Parser, Status, Token},
         Script,
     },
     Metrics,
@@ -17,22 +16,70 @@ use unicode_segmentation::UnicodeSegmentation;
 use crate::{
     error_msg,
     profiling::tracy_zone,
-    renderer::fonts::{font_loader::*, font_options::*},
+    renderer::fonts::{
+        font_cache::{FontCache, ShapeConfiguration, ShapeKey},
+        font_loader::*,
+        font_options::*,
+    },
     units::PixelSize,
 };
 
-#[derive(new, Clone, Hash, PartialEq, Eq, Debug)]
-struct ShapeKey {
-    pub text: String,
-    pub style: CoarseStyle,
+const FONT_CACHE_SIZE: usize = 8 * 1024 * 1024;
+
+/// Neovim gives the emoji two cells.
+const EMOJI_CELLS: f32 = 2.0;
+
+/// Where an emoji too large for its cells is drawn instead, relative to its place on the grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct EmojiFit {
+    scale: f32,
+    x: f32,
+    y: f32,
 }
 
-const FONT_CACHE_SIZE: usize = 8 * 1024 * 1024;
+/// Whether a cluster is drawn as an emoji, which isn't the case when it asks for the text
+/// presentation, or isn't a pictograph at all like the digits.
+fn is_emoji(info: ClusterInfo) -> bool {
+    matches!(info.emoji(), Emoji::Default | Emoji::Color)
+}
+
+/// Shrinks an emoji `advance` wide, in a font with the `ascent` and `descent`, into the cells of
+/// the size given, centering it in them. Returns None when it fits already.
+fn fit_emoji(
+    advance: f32,
+    ascent: f32,
+    descent: f32,
+    cell_width: f32,
+    cell_height: f32,
+    baseline: f32,
+) -> Option<EmojiFit> {
+    let width = EMOJI_CELLS * cell_width;
+    let height = ascent + descent;
+    let horizontal = if advance > 0.0 { width / advance } else { 1.0 };
+    let vertical = if height > 0.0 {
+        cell_height / height
+    } else {
+        1.0
+    };
+    let scale = horizontal.min(vertical);
+    if scale >= 1.0 {
+        return None;
+    }
+    Some(EmojiFit {
+        scale,
+        x: (width - advance * scale) / 2.0,
+        // Moves the middle of the shrunk glyph to the middle of the cell, both from the baseline
+        y: (cell_height / 2.0 - baseline) - (descent - ascent) * scale / 2.0,
+    })
+}
 
 pub struct CachingShaper {
     options: FontOptions,
+    emoji: EmojiOptions,
     font_loader: FontLoader,
-    blob_cache: LruCache<ShapeKey, Vec<TextBlob>>,
+    /// The blobs of the text are shared with the other shapers using the same font and size. 0
+    /// until the font is first loaded.
+    configuration: usize,
     shape_context: ShapeContext,
     scale_factor: f32,
     linespace: f32,
@@ -45,8 +92,9 @@ impl CachingShaper {
         let font_size = options.size * scale_factor;
         let mut shaper = CachingShaper {
             options,
+            emoji: EmojiOptions::default(),
             font_loader: FontLoader::new(font_size),
-            blob_cache: LruCache::new(NonZeroUsize::new(10000).unwrap()),
+            configuration: 0,
             shape_context: ShapeContext::new(),
             scale_factor,
             linespace: 0.0,
@@ -75,6 +123,10 @@ impl CachingShaper {
         (self.options.size * self.scale_factor).max(min_font_size)
     }
 
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
     pub fn update_scale_factor(&mut self, scale_factor: f32) {
         debug!("scale_factor changed: {:.2}", scale_factor);
         self.scale_factor = scale_factor;
@@ -130,6 +182,25 @@ impl CachingShaper {
         }
     }
 
+    pub fn update_emoji_options(&mut self, emoji: EmojiOptions) {
+        if emoji == self.emoji {
+            return;
+        }
+        debug!("Updating emoji options: {:?}", emoji);
+        if let Some(font_desc) = &emoji.font {
+            let key = FontKey {
+                font_desc: Some(font_desc.clone()),
+                hinting: self.options.hinting.clone(),
+                edging: self.options.edging.clone(),
+            };
+            if self.font_loader.get_or_load(&key).is_none() {
+                error_msg!("The emoji font {} couldn't be loaded", font_desc.family);
+            }
+        }
+        self.emoji = emoji;
+        self.update_configuration();
+    }
+
     pub fn update_linespace(&mut self, linespace: f32) {
         debug!("Updating linespace: {}", linespace);
 
@@ -162,7 +233,24 @@ impl CachingShaper {
             font_size, font_width
         );
 
-        self.blob_cache.clear();
+        self.update_configuration();
+    }
+
+    /// Looks up the id of the shaped text shared with the other shapers, which changes with the
+    /// font, its size and the metrics of the cells.
+    fn update_configuration(&mut self) {
+        let configuration = ShapeConfiguration {
+            options: self.options.clone(),
+            emoji: self.emoji.clone(),
+            size: self.current_size(),
+            linespace: self.linespace,
+            cell: self.font_base_dimensions(),
+            baseline: self.baseline_offset(),
+        };
+        let font_cache = FontCache::shared();
+        let previous = self.configuration;
+        self.configuration = font_cache.acquire_configuration(&configuration);
+        font_cache.release_configuration(previous);
     }
 
     pub fn font_names(&self) -> Vec<String> {
@@ -259,6 +347,12 @@ impl CachingShaper {
 
         let mut results = Vec::new();
         'cluster: while parser.next(&mut cluster) {
+            let emoji = is_emoji(cluster.info());
+            // The fonts with colors of their own are only used for monochrome emoji when none of
+            // the others has them
+            let monochrome = emoji && self.emoji.monochrome;
+            let mut color = None;
+
             // TODO: Don't redo this work for every cluster. Save it some how
             // Create font fallback list
             let mut font_fallback_keys = Vec::new();
@@ -276,6 +370,21 @@ impl CachingShaper {
                     .unique(),
             );
 
+            // The emoji font comes before the default and system ones, or even before the
+            // configured fonts when the emoji asks for the color presentation
+            if let Some(font_desc) = self.emoji.font.as_ref().filter(|_| emoji) {
+                let emoji_key = FontKey {
+                    font_desc: Some(font_desc.clone()),
+                    hinting: self.options.hinting.clone(),
+                    edging: self.options.edging.clone(),
+                };
+                if cluster.info().emoji() == Emoji::Color {
+                    font_fallback_keys.insert(0, emoji_key);
+                } else {
+                    font_fallback_keys.push(emoji_key);
+                }
+            }
+
             // Add default font
             font_fallback_keys.push(FontKey {
                 font_desc: None,
@@ -291,6 +400,9 @@ impl CachingShaper {
                 if let Some(font_pair) = self.font_loader.get_or_load(fallback_key) {
                     let charmap = font_pair.swash_font.as_ref().charmap();
                     match cluster.map(|ch| charmap.map(ch)) {
+                        Status::Complete if monochrome && font_pair.color => {
+                            color.get_or_insert(font_pair);
+                        }
                         Status::Complete => {
                             results.push((cluster.to_owned(), font_pair.clone()));
                             continue 'cluster;
@@ -305,6 +417,9 @@ impl CachingShaper {
             for loaded_font in self.font_loader.loaded_fonts() {
                 let charmap = loaded_font.swash_font.as_ref().charmap();
                 match cluster.map(|ch| charmap.map(ch)) {
+                    Status::Complete if monochrome && loaded_font.color => {
+                        color.get_or_insert(loaded_font);
+                    }
                     Status::Complete => {
                         results.push((cluster.to_owned(), loaded_font.clone()));
                         self.font_loader.refresh(loaded_font.as_ref());
@@ -317,20 +432,25 @@ impl CachingShaper {
 
             if let Some(best) = best {
                 results.push((cluster.to_owned(), best.clone()));
+                continue;
+            }
+
+            let system_font = cluster.chars().first().and_then(|character| {
+                self.font_loader
+                    .load_font_for_character(style, character.ch)
+            });
+            // Of two fonts with colors, a monochrome emoji keeps the one configured
+            let fallback_font = match (system_font, color) {
+                (Some(system_font), Some(color)) if system_font.color => Some(color),
+                (system_font, color) => system_font.or(color),
+            };
+            if let Some(fallback_font) = fallback_font {
+                results.push((cluster.to_owned(), fallback_font));
+            } else if let Some(last_resort) = self.font_loader.get_or_load_last_resort() {
+                // Last Resort covers all of the unicode space so we will always have a fallback
+                results.push((cluster.to_owned(), last_resort));
             } else {
-                let fallback_character = cluster.chars()[0].ch;
-                if let Some(fallback_font) = self
-                    .font_loader
-                    .load_font_for_character(style, fallback_character)
-                {
-                    results.push((cluster.to_owned(), fallback_font));
-                } else {
-                    // Last Resort covers all of the unicode space so we will always have a fallback
-                    results.push((
-                        cluster.to_owned(),
-                        self.font_loader.get_or_load_last_resort().unwrap(),
-                    ));
-                }
+                warn!("No font found for a cluster of {:?}", text);
             }
         }
 
@@ -354,8 +474,8 @@ impl CachingShaper {
             }
         }
 
-        if !current_group.is_empty() {
-            grouped_results.push((current_group, current_font_option.unwrap()));
+        if let Some(current_font) = current_font_option {
+            grouped_results.push((current_group, current_font));
         }
 
         grouped_results
@@ -369,7 +489,10 @@ impl CachingShaper {
 
     pub fn shape(&mut self, text: String, style: CoarseStyle) -> Vec<TextBlob> {
         let current_size = self.current_size();
-        let glyph_width = self.font_base_dimensions().width;
+        let cell = self.font_base_dimensions();
+        let glyph_width = cell.width;
+        let baseline = self.baseline_offset();
+        let fit_emoji_cell = self.emoji.fit_cell;
 
         let mut resulting_blobs = Vec::new();
 
@@ -398,44 +521,80 @@ impl CachingShaper {
                 shaper.add_cluster(&cluster);
             }
 
+            let font_metrics = shaper.metrics();
             let mut glyph_data = Vec::new();
+            let mut emoji_data = Vec::new();
 
             shaper.shape_with(|glyph_cluster| {
+                let emoji = fit_emoji_cell && is_emoji(glyph_cluster.info);
                 for glyph in glyph_cluster.glyphs {
                     let position = (glyph.data as f32 * glyph_width, glyph.y);
-                    glyph_data.push((glyph.id, position));
+                    let fit = if emoji {
+                        fit_emoji(
+                            glyph.advance,
+                            font_metrics.ascent,
+                            font_metrics.descent,
+                            glyph_width,
+                            cell.height,
+                            baseline,
+                        )
+                    } else {
+                        None
+                    };
+                    if let Some(fit) = fit {
+                        emoji_data.push((glyph.id, position, fit));
+                    } else {
+                        glyph_data.push((glyph.id, position));
+                    }
                 }
             });
 
-            if glyph_data.is_empty() {
+            if glyph_data.is_empty() && emoji_data.is_empty() {
                 continue;
             }
 
             let mut blob_builder = TextBlobBuilder::new();
-            let (glyphs, positions) =
-                blob_builder.alloc_run_pos(&font_pair.skia_font, glyph_data.len(), None);
-            for (i, (glyph_id, glyph_position)) in glyph_data.iter().enumerate() {
-                glyphs[i] = *glyph_id;
-                positions[i] = (*glyph_position).into();
+            if !glyph_data.is_empty() {
+                let (glyphs, positions) =
+                    blob_builder.alloc_run_pos(&font_pair.skia_font, glyph_data.len(), None);
+                for (i, (glyph_id, glyph_position)) in glyph_data.iter().enumerate() {
+                    glyphs[i] = *glyph_id;
+                    positions[i] = (*glyph_position).into();
+                }
+            }
+            // The shrunk emoji each get a run of their own, with the font at their size
+            for (glyph_id, (x, y), fit) in emoji_data {
+                let mut emoji_font = font_pair.skia_font.clone();
+                emoji_font.set_size(font_pair.skia_font.size() * fit.scale);
+                let (glyphs, positions) = blob_builder.alloc_run_pos(&emoji_font, 1, None);
+                glyphs[0] = glyph_id;
+                positions[0] = Point::new(x + fit.x, y + fit.y);
             }
 
-            let blob = blob_builder.make();
-            resulting_blobs.push(blob.expect("Could not create textblob"));
+            if let Some(blob) = blob_builder.make() {
+                resulting_blobs.push(blob);
+            } else {
+                warn!("Could not create a text blob for {:?}", text);
+            }
         }
 
         resulting_blobs
     }
 
-    pub fn shape_cached(&mut self, text: String, style: CoarseStyle) -> &Vec<TextBlob> {
+    pub fn shape_cached(&mut self, text: String, style: CoarseStyle) -> Arc<Vec<TextBlob>> {
         tracy_zone!("shape_cached");
-        let key = ShapeKey::new(text.clone(), style);
-
-        if !self.blob_cache.contains(&key) {
-            let blobs = self.shape(text, style);
-            self.blob_cache.put(key.clone(), blobs);
+        let shared_cache = FontCache::shared();
+        let key = ShapeKey {
+            configuration: self.configuration,
+            text,
+            style,
+        };
+        if let Some(blobs) = shared_cache.blobs(&key) {
+            return blobs;
         }
 
-        self.blob_cache.get(&key).unwrap()
+        let blobs = self.shape(key.text.clone(), style);
+        shared_cache.insert_blobs(key, blobs)
     }
 
     fn get_font_features(&self, name: Option<&str>) -> Vec<(String, u16)> {
@@ -455,3 +614,32 @@ impl CachingShaper {
         }
     }
 }
+
+impl Drop for CachingShaper {
+    fn drop(&mut self) {
+        FontCache::shared().release_configuration(self.configuration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emoji_are_shrunk_into_their_cells() {
+        // Fits in two cells of 10x20 with the baseline at 16 already
+        assert_eq!(fit_emoji(18.0, 14.0, 4.0, 10.0, 20.0, 16.0), None);
+
+        // Twice as wide as the cells
+        let fit = fit_emoji(40.0, 14.0, 4.0, 10.0, 20.0, 16.0).unwrap();
+        assert_eq!(fit.scale, 0.5);
+        assert_eq!(fit.x, 0.0);
+        assert_eq!(fit.y, -6.0 + 2.5);
+
+        // Taller than the cell, so centered horizontally
+        let fit = fit_emoji(20.0, 32.0, 8.0, 10.0, 20.0, 16.0).unwrap();
+        assert_eq!(fit.scale, 0.5);
+        assert_eq!(fit.x, 5.0);
+        assert_eq!(fit.y, -6.0 + 6.0);
+    }
+}
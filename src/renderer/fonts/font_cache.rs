@@ -0,0 +1,189 @@
+//! Fonts and shaped text shared by all the grid renderers. The main grid, the command line and the
+//! terminal font each have their own shaper, which would otherwise load the same fonts and shape
+//! the same text again. The entries are evicted here, least recently used first, whichever shaper
+//! used them.
+
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, OnceLock},
+};
+
+use lru::LruCache;
+use parking_lot::Mutex;
+use skia_safe::TextBlob;
+
+use crate::{
+    renderer::fonts::{
+        font_loader::{FontKey, FontPair},
+        font_options::{CoarseStyle, EmojiOptions, FontOptions},
+    },
+    units::PixelSize,
+};
+
+const FONT_CACHE_SIZE: usize = 64;
+const BLOB_CACHE_SIZE: usize = 20000;
+
+static SHARED_FONT_CACHE: OnceLock<FontCache> = OnceLock::new();
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub struct ShapeKey {
+    /// The font options and size the text was shaped with, see
+    /// [`FontCache::acquire_configuration`].
+    pub configuration: usize,
+    pub text: String,
+    pub style: CoarseStyle,
+}
+
+/// Everything the shaped text depends on, besides the text and its style.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ShapeConfiguration {
+    pub options: FontOptions,
+    pub emoji: EmojiOptions,
+    pub size: f32,
+    pub linespace: f32,
+    /// The size of the cells and the baseline in them, which place the glyphs and fit the emoji.
+    /// The cell width also covers the width of the options, which they don't compare.
+    pub cell: PixelSize<f32>,
+    pub baseline: f32,
+}
+
+pub struct FontCache {
+    fonts: Mutex<LruCache<(FontKey, u32), Arc<FontPair>>>,
+    blobs: Mutex<LruCache<ShapeKey, Arc<Vec<TextBlob>>>>,
+    /// The configurations of the shapers, with their ids and how many shapers use them.
+    configurations: Mutex<Vec<ConfigurationEntry>>,
+    /// Ids aren't reused, the blobs of a released configuration may still be cached. They start at
+    /// 1, so 0 is never a configuration.
+    next_configuration: Mutex<usize>,
+}
+
+struct ConfigurationEntry {
+    id: usize,
+    configuration: ShapeConfiguration,
+    users: usize,
+}
+
+impl FontCache {
+    fn new() -> Self {
+        Self {
+            fonts: Mutex::new(LruCache::new(NonZeroUsize::new(FONT_CACHE_SIZE).unwrap())),
+            blobs: Mutex::new(LruCache::new(NonZeroUsize::new(BLOB_CACHE_SIZE).unwrap())),
+            configurations: Mutex::new(Vec::new()),
+            next_configuration: Mutex::new(1),
+        }
+    }
+
+    pub fn shared() -> &'static FontCache {
+        SHARED_FONT_CACHE.get_or_init(FontCache::new)
+    }
+
+    pub fn font(&self, key: &FontKey, size: f32) -> Option<Arc<FontPair>> {
+        self.fonts
+            .lock()
+            .get(&(key.clone(), size.to_bits()))
+            .cloned()
+    }
+
+    pub fn insert_font(&self, key: FontKey, size: f32, font_pair: Arc<FontPair>) {
+        self.fonts.lock().put((key, size.to_bits()), font_pair);
+    }
+
+    /// Returns the same id to the shapers using the same font options, size and cell metrics, so
+    /// that they can share the shaped text. The shaper releases it with
+    /// [`FontCache::release_configuration`] once it changes or goes away.
+    pub fn acquire_configuration(&self, configuration: &ShapeConfiguration) -> usize {
+        let mut configurations = self.configurations.lock();
+        if let Some(entry) = configurations
+            .iter_mut()
+            .find(|entry| entry.configuration == *configuration)
+        {
+            entry.users += 1;
+            return entry.id;
+        }
+        let mut next_configuration = self.next_configuration.lock();
+        let id = *next_configuration;
+        *next_configuration += 1;
+        configurations.push(ConfigurationEntry {
+            id,
+            configuration: configuration.clone(),
+            users: 1,
+        });
+        id
+    }
+
+    /// Forgets the configuration once no shaper uses it, like the sizes passed while zooming.
+    pub fn release_configuration(&self, id: usize) {
+        let mut configurations = self.configurations.lock();
+        if let Some(index) = configurations.iter().position(|entry| entry.id == id) {
+            configurations[index].users -= 1;
+            if configurations[index].users == 0 {
+                configurations.swap_remove(index);
+            }
+        }
+    }
+
+    pub fn blobs(&self, key: &ShapeKey) -> Option<Arc<Vec<TextBlob>>> {
+        self.blobs.lock().get(key).cloned()
+    }
+
+    pub fn insert_blobs(&self, key: ShapeKey, blobs: Vec<TextBlob>) -> Arc<Vec<TextBlob>> {
+        let blobs = Arc::new(blobs);
+        self.blobs.lock().put(key, blobs.clone());
+        blobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configuration() -> ShapeConfiguration {
+        ShapeConfiguration {
+            options: FontOptions::parse("Fira Code:h14").unwrap(),
+            emoji: EmojiOptions::default(),
+            size: 28.0,
+            linespace: 0.0,
+            cell: PixelSize::new(17.0, 36.0),
+            baseline: 27.0,
+        }
+    }
+
+    #[test]
+    fn shapers_with_the_same_font_share_a_configuration() {
+        let cache = FontCache::new();
+        let id = cache.acquire_configuration(&configuration());
+        assert_eq!(cache.acquire_configuration(&configuration()), id);
+
+        let changes: [fn(&mut ShapeConfiguration); 5] = [
+            |c| c.size = 14.0,
+            |c| c.options = FontOptions::parse("Fira Code:h14:b").unwrap(),
+            |c| c.emoji = EmojiOptions::new(true, true, "Noto Emoji"),
+            |c| c.linespace = 4.0,
+            |c| c.cell.height = 40.0,
+        ];
+        for change in changes {
+            let mut other = configuration();
+            change(&mut other);
+            assert_ne!(cache.acquire_configuration(&other), id);
+        }
+        assert_eq!(cache.acquire_configuration(&configuration()), id);
+    }
+
+    #[test]
+    fn unused_configurations_are_evicted() {
+        let cache = FontCache::new();
+        let id = cache.acquire_configuration(&configuration());
+        assert_eq!(cache.acquire_configuration(&configuration()), id);
+
+        cache.release_configuration(id);
+        assert_eq!(cache.configurations.lock().len(), 1);
+        cache.release_configuration(id);
+        assert!(cache.configurations.lock().is_empty());
+
+        // Releasing before the first lookup does nothing
+        cache.release_configuration(0);
+        let new_id = cache.acquire_configuration(&configuration());
+        assert_ne!(new_id, id);
+        assert_eq!(cache.configurations.lock().len(), 1);
+    }
+}
@@ -7,10 +7,12 @@ use std::{
 use log::trace;
 use lru::LruCache;
 use skia_safe::{font::Edging as SkiaEdging, Data, Font, FontHinting as SkiaHinting, FontMgr};
+use swash::tag_from_bytes;
 
 use crate::{
     profiling::tracy_zone,
     renderer::fonts::{
+        font_cache::FontCache,
         font_options::{CoarseStyle, FontDescription, FontEdging, FontHinting},
         swash_font::SwashFont,
     },
@@ -19,10 +21,15 @@ use crate::{
 static DEFAULT_FONT: &[u8] = include_bytes!("../../../assets/fonts/FiraCodeNerdFont-Regular.ttf");
 static LAST_RESORT_FONT: &[u8] = include_bytes!("../../../assets/fonts/LastResort-Regular.ttf");
 
+/// The tables of the bitmap, layered and SVG glyphs, which are drawn in their own colors.
+const COLOR_TABLES: [&[u8; 4]; 4] = [b"COLR", b"CBDT", b"sbix", b"SVG "];
+
 pub struct FontPair {
     pub key: FontKey,
     pub skia_font: Font,
     pub swash_font: SwashFont,
+    /// Whether the glyphs have colors of their own, like most emoji fonts.
+    pub color: bool,
 }
 
 impl FontPair {
@@ -38,11 +45,15 @@ impl FontPair {
         // don't care about those here, since we are just loading the font, so ignore them
         let index = index & 0xFFFF;
         let swash_font = SwashFont::from_data(font_data, index)?;
+        let color = COLOR_TABLES
+            .iter()
+            .any(|table| swash_font.as_ref().table(tag_from_bytes(table)).is_some());
 
         Some(Self {
             key,
             skia_font,
             swash_font,
+            color,
         })
     }
 }
@@ -108,8 +119,16 @@ impl FontLoader {
             return Some(cached.clone());
         }
 
-        let loaded_font = self.load(font_key.clone())?;
-        let font_arc = Arc::new(loaded_font);
+        // Another shaper could have loaded it already
+        let shared_cache = FontCache::shared();
+        let font_arc = match shared_cache.font(font_key, self.font_size) {
+            Some(shared) => shared,
+            None => {
+                let font_arc = Arc::new(self.load(font_key.clone())?);
+                shared_cache.insert_font(font_key.clone(), self.font_size, font_arc.clone());
+                font_arc
+            }
+        };
         self.cache.put(font_key.clone(), font_arc.clone());
 
         Some(font_arc)
@@ -134,10 +153,18 @@ impl FontLoader {
             edging: FontEdging::default(),
         };
 
-        let font_pair = Arc::new(FontPair::new(
-            font_key.clone(),
-            Font::from_typeface(typeface, self.font_size),
-        )?);
+        let shared_cache = FontCache::shared();
+        let font_pair = match shared_cache.font(&font_key, self.font_size) {
+            Some(shared) => shared,
+            None => {
+                let font_pair = Arc::new(FontPair::new(
+                    font_key.clone(),
+                    Font::from_typeface(typeface, self.font_size),
+                )?);
+                shared_cache.insert_font(font_key.clone(), self.font_size, font_pair.clone());
+                font_pair
+            }
+        };
 
         self.cache.put(font_key, font_pair.clone());
 
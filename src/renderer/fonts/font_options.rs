@@ -19,9 +19,13 @@ const FONT_HEIGHT_PREFIX: char = 'h';
 const FONT_WIDTH_PREFIX: char = 'w';
 const FONT_BOLD_OPT: &str = "b";
 const FONT_ITALIC_OPT: &str = "i";
+const FONT_FEATURE_ENABLE_PREFIX: char = '+';
+const FONT_FEATURE_DISABLE_PREFIX: char = '-';
+const FONT_FEATURE_VALUE_SEPARATOR: char = '=';
 
 const INVALID_SIZE_ERR: &str = "Invalid size";
 const INVALID_WIDTH_ERR: &str = "Invalid width";
+const MISSING_FEATURE_FONT_ERR: &str = "Font features need a font to follow";
 
 /// Description of the normal font.
 #[derive(Clone, Debug, Deserialize, PartialEq, Hash, Eq, Default)]
@@ -60,6 +64,10 @@ impl CoarseStyle {
         Some(name)
     }
 
+    pub fn with_italic(self, italic: bool) -> Self {
+        Self { italic, ..self }
+    }
+
     /// Iterates through all possible style permutations.
     pub fn permutations() -> impl Iterator<Item = CoarseStyle> {
         iter::repeat([true, false])
@@ -99,6 +107,33 @@ impl From<&Arc<editor::Style>> for CoarseStyle {
     }
 }
 
+/// How the emoji are drawn, whichever font they come from.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EmojiOptions {
+    /// Prefers the fonts drawing the emoji in the color of the text.
+    pub monochrome: bool,
+    /// Shrinks the emoji larger than the two cells they take.
+    pub fit_cell: bool,
+    /// The font tried for the emoji before the ones of the system.
+    pub font: Option<FontDescription>,
+}
+
+impl EmojiOptions {
+    pub fn new(monochrome: bool, fit_cell: bool, font: &str) -> Self {
+        let font = Some(font.trim())
+            .filter(|font| !font.is_empty())
+            .map(|font| FontDescription {
+                family: parse_font_name(font),
+                style: None,
+            });
+        Self {
+            monochrome,
+            fit_cell,
+            font,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FontOptions {
     pub normal: Vec<FontDescription>,
@@ -137,44 +172,50 @@ impl FontOptions {
     pub fn parse(guifont_setting: &str) -> Result<FontOptions, &str> {
         let mut font_options = FontOptions::default();
 
-        let mut parts = guifont_setting
-            .split(FONT_OPTS_SEPARATOR)
-            .filter(|part| !part.is_empty());
-
-        if let Some(parts) = parts.next() {
-            let parsed_font_list = parts
-                .split(FONT_LIST_SEPARATOR)
-                .filter(|fallback| !fallback.is_empty())
-                .map(parse_font_name)
-                .collect_vec();
-
-            if !parsed_font_list.is_empty() {
-                font_options.normal = parsed_font_list
-                    .into_iter()
-                    .map(|family| FontDescription {
-                        family,
-                        style: None,
-                    })
-                    .collect();
+        // Every font can be followed by options. The features only apply to the font they follow,
+        // the other options apply to all of them.
+        let mut style: Vec<String> = vec![];
+        for entry in guifont_setting.split(FONT_LIST_SEPARATOR) {
+            let mut parts = entry.split(FONT_OPTS_SEPARATOR);
+            let family = parts
+                .next()
+                .filter(|name| !name.is_empty())
+                .map(parse_font_name);
+
+            for part in parts.filter(|part| !part.is_empty()) {
+                if let Some(hinting_string) = part.strip_prefix(FONT_HINTING_PREFIX) {
+                    font_options.hinting = FontHinting::parse(hinting_string)?;
+                } else if let Some(edging_string) = part.strip_prefix(FONT_EDGING_PREFIX) {
+                    font_options.edging = FontEdging::parse(edging_string)?;
+                } else if is_font_feature(part) {
+                    let Some(family) = &family else {
+                        return Err(MISSING_FEATURE_FONT_ERR);
+                    };
+                    let feature = FontFeature::parse(part)?;
+                    font_options
+                        .features
+                        .entry(family.clone())
+                        .or_default()
+                        .push(feature);
+                } else if part.starts_with(FONT_HEIGHT_PREFIX) && part.len() > 1 {
+                    font_options.size = parse_pixels(part).map_err(|_| INVALID_SIZE_ERR)?;
+                } else if part.starts_with(FONT_WIDTH_PREFIX) && part.len() > 1 {
+                    font_options.width = parse_pixels(part).map_err(|_| INVALID_WIDTH_ERR)?;
+                } else if part == FONT_BOLD_OPT {
+                    style.push("Bold".to_string());
+                } else if part == FONT_ITALIC_OPT {
+                    style.push("Italic".to_string());
+                }
             }
-        }
 
-        let mut style: Vec<String> = vec![];
-        for part in parts {
-            if let Some(hinting_string) = part.strip_prefix(FONT_HINTING_PREFIX) {
-                font_options.hinting = FontHinting::parse(hinting_string)?;
-            } else if let Some(edging_string) = part.strip_prefix(FONT_EDGING_PREFIX) {
-                font_options.edging = FontEdging::parse(edging_string)?;
-            } else if part.starts_with(FONT_HEIGHT_PREFIX) && part.len() > 1 {
-                font_options.size = parse_pixels(part).map_err(|_| INVALID_SIZE_ERR)?;
-            } else if part.starts_with(FONT_WIDTH_PREFIX) && part.len() > 1 {
-                font_options.width = parse_pixels(part).map_err(|_| INVALID_WIDTH_ERR)?;
-            } else if part == FONT_BOLD_OPT {
-                style.push("Bold".to_string());
-            } else if part == FONT_ITALIC_OPT {
-                style.push("Italic".to_string());
+            if let Some(family) = family {
+                font_options.normal.push(FontDescription {
+                    family,
+                    style: None,
+                });
             }
         }
+
         let style = if style.is_empty() {
             None
         } else {
@@ -278,6 +319,13 @@ impl PartialEq for FontOptions {
     }
 }
 
+/// Whether an option is an OpenType feature like `+ss01`, `-liga` or `cv02=3`.
+fn is_font_feature(part: &str) -> bool {
+    part.starts_with(FONT_FEATURE_ENABLE_PREFIX)
+        || part.starts_with(FONT_FEATURE_DISABLE_PREFIX)
+        || part.contains(FONT_FEATURE_VALUE_SEPARATOR)
+}
+
 fn parse_pixels(part: &str) -> Result<f32, ParseFloatError> {
     Ok(points_to_pixels(part[1..].parse::<f32>()?))
 }
@@ -450,6 +498,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_features_per_font_from_guifont_setting() {
+        let guifont_setting = "FiraCode:+cv02:+ss03,Noto_Sans:-liga:calt=0:h14:b";
+        let font_options = FontOptions::parse(guifont_setting).unwrap();
+
+        let families = font_options
+            .normal
+            .iter()
+            .map(|font| font.family.as_str())
+            .collect_vec();
+        assert_eq!(families, ["FiraCode", "Noto Sans"]);
+        assert_eq!(
+            font_options.features.get("FiraCode"),
+            Some(&vec![
+                FontFeature("cv02".to_string(), 1),
+                FontFeature("ss03".to_string(), 1),
+            ])
+        );
+        assert_eq!(
+            font_options.features.get("Noto Sans"),
+            Some(&vec![
+                FontFeature("liga".to_string(), 0),
+                FontFeature("calt".to_string(), 0),
+            ])
+        );
+        // The other options still apply to every font
+        assert_eq!(font_options.size, points_to_pixels(14.0));
+        assert!(font_options
+            .normal
+            .iter()
+            .all(|font| font.style.as_deref() == Some("Bold")));
+
+        assert_eq!(
+            FontOptions::parse(":+liga").unwrap_err(),
+            MISSING_FEATURE_FONT_ERR
+        );
+        assert!(FontOptions::parse("FiraCode:ss01=on").is_err());
+    }
+
     #[test]
     fn test_parse_edging_from_guifont_setting() {
         let guifont_setting = "Fira Code Mono:#e-subpixelantialias";
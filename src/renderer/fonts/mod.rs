@@ -1,4 +1,5 @@
 pub mod caching_shaper;
+pub mod font_cache;
 pub mod font_loader;
 pub mod font_options;
 mod swash_font;
@@ -0,0 +1,135 @@
+//! Compares two screenshots of Neovide with `--compare-frames`, for the bug reports about the
+//! rendering changing between backends or versions, like the gamma of the fonts. The differences
+//! are drawn as a heatmap over a faded copy of the first frame, so they're easy to spot.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use skia_safe::{
+    gpu::DirectContext, images, AlphaType, ColorType, Data, EncodedImageFormat, ISize, Image,
+    ImageInfo,
+};
+
+/// The first frame shows under the heatmap this many times darker.
+const BACKGROUND_DIMMING: u32 = 4;
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct FrameDiff {
+    pub width: i32,
+    pub height: i32,
+    pub different_pixels: u64,
+    /// The largest difference of a channel, from 0 to 255.
+    pub max_difference: u8,
+    /// The average difference of the channels over all the pixels, from 0 to 255.
+    pub mean_difference: f64,
+}
+
+/// Goes from yellow for the smallest differences to red for the largest ones.
+fn heat(difference: u8) -> [u8; 3] {
+    [255, 255 - difference.saturating_mul(2), 0]
+}
+
+/// Compares two RGBA buffers of the same size, and returns the heatmap with the statistics.
+fn diff_pixels(first: &[u8], second: &[u8]) -> (Vec<u8>, FrameDiff) {
+    let mut heatmap = Vec::with_capacity(first.len());
+    let mut diff = FrameDiff::default();
+    let mut total = 0u64;
+    for (a, b) in first.chunks_exact(4).zip(second.chunks_exact(4)) {
+        let difference = a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+        total += a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| a.abs_diff(*b) as u64)
+            .sum::<u64>();
+        if difference > 0 {
+            diff.different_pixels += 1;
+            diff.max_difference = diff.max_difference.max(difference);
+            heatmap.extend(heat(difference));
+        } else {
+            let luma = (a[0] as u32 * 77 + a[1] as u32 * 150 + a[2] as u32 * 29) >> 8;
+            heatmap.extend([(luma / BACKGROUND_DIMMING) as u8; 3]);
+        }
+        heatmap.push(255);
+    }
+    let channels = first.len().min(second.len());
+    if channels > 0 {
+        diff.mean_difference = total as f64 / channels as f64;
+    }
+    (heatmap, diff)
+}
+
+fn image_info(size: ISize) -> ImageInfo {
+    ImageInfo::new(size, ColorType::RGBA8888, AlphaType::Unpremul, None)
+}
+
+fn load_pixels(path: &Path) -> Result<(ISize, Vec<u8>)> {
+    let bytes = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let image = Image::from_encoded(Data::new_copy(&bytes))
+        .with_context(|| format!("{} isn't a PNG or JPEG image", path.display()))?;
+    let info = image_info(image.dimensions());
+    let row_bytes = info.min_row_bytes();
+    let mut pixels = vec![0; row_bytes * info.height() as usize];
+    if !image.read_pixels(
+        &info,
+        &mut pixels,
+        row_bytes,
+        (0, 0),
+        skia_safe::image::CachingHint::Disallow,
+    ) {
+        bail!("Could not decode {}", path.display());
+    }
+    Ok((image.dimensions(), pixels))
+}
+
+/// Compares the frames saved at `first` and `second`, and writes the heatmap of their differences
+/// to `output` as a PNG.
+pub fn compare_frames(first: &Path, second: &Path, output: &Path) -> Result<FrameDiff> {
+    let (size, first_pixels) = load_pixels(first)?;
+    let (second_size, second_pixels) = load_pixels(second)?;
+    if size != second_size {
+        bail!(
+            "The frames have different sizes, {}x{} and {}x{}",
+            size.width,
+            size.height,
+            second_size.width,
+            second_size.height
+        );
+    }
+    let (heatmap, mut diff) = diff_pixels(&first_pixels, &second_pixels);
+    diff.width = size.width;
+    diff.height = size.height;
+
+    let info = image_info(size);
+    let row_bytes = info.min_row_bytes();
+    let png = images::raster_from_data(&info, Data::new_copy(&heatmap), row_bytes)
+        .and_then(|image| image.encode(None::<&mut DirectContext>, EncodedImageFormat::PNG, None))
+        .context("Could not encode the heatmap")?;
+    fs::write(output, png.as_bytes())
+        .with_context(|| format!("Could not write {}", output.display()))?;
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn differences_are_heated() {
+        let first = [100, 100, 100, 255, 10, 20, 30, 255];
+        let second = [100, 100, 100, 255, 10, 20, 130, 255];
+        let (heatmap, diff) = diff_pixels(&first, &second);
+
+        assert_eq!(diff.different_pixels, 1);
+        assert_eq!(diff.max_difference, 100);
+        assert_eq!(diff.mean_difference, 100.0 / 8.0);
+        // The same pixel is a faded gray, the other one is heated
+        assert_eq!(&heatmap[..4], &[25, 25, 25, 255]);
+        assert_eq!(&heatmap[4..], &[255, 55, 0, 255]);
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "profiling")]
+use crate::profiling::tracy_plot;
+use crate::renderer::SkiaRenderer;
+
+const MEGABYTE: usize = 1024 * 1024;
+/// The budget Skia gives its resource cache by default.
+const DEFAULT_LIMIT: usize = 256 * MEGABYTE;
+
+/// How much of the GPU memory the resource cache of Skia holds, the textures of the glyphs, the
+/// layers of the floats and their blur among others.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuCacheUsage {
+    pub used_bytes: usize,
+    pub limit_bytes: usize,
+    pub resources: usize,
+}
+
+impl Display for GpuCacheUsage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.1} MB of {:.0} MB in {} resources",
+            self.used_bytes as f64 / MEGABYTE as f64,
+            self.limit_bytes as f64 / MEGABYTE as f64,
+            self.resources
+        )
+    }
+}
+
+fn limit_bytes(megabytes: u32) -> usize {
+    if megabytes == 0 {
+        DEFAULT_LIMIT
+    } else {
+        megabytes as usize * MEGABYTE
+    }
+}
+
+/// Caps the resource cache at `neovide_gpu_cache_mb`, or the default of Skia with 0. Skia frees
+/// the resources over the new budget on its own, the next time it flushes.
+pub fn set_gpu_cache_limit(skia_renderer: &mut dyn SkiaRenderer, megabytes: u32) {
+    let limit = limit_bytes(megabytes);
+    log::debug!("Limiting the GPU cache to {} MB", limit / MEGABYTE);
+    skia_renderer.gr_context().set_resource_cache_limit(limit);
+}
+
+pub fn gpu_cache_usage(skia_renderer: &mut dyn SkiaRenderer) -> GpuCacheUsage {
+    let context = skia_renderer.gr_context();
+    let usage = context.resource_cache_usage();
+    GpuCacheUsage {
+        used_bytes: usage.resource_bytes,
+        limit_bytes: context.resource_cache_limit(),
+        resources: usage.resource_count,
+    }
+}
+
+#[cfg(feature = "profiling")]
+pub fn plot_gpu_cache(skia_renderer: &mut dyn SkiaRenderer) {
+    let usage = gpu_cache_usage(skia_renderer);
+    tracy_plot!("gpu_cache_bytes_used", usage.used_bytes as f64);
+    tracy_plot!("gpu_cache_bytes_limit", usage.limit_bytes as f64);
+    tracy_plot!("gpu_cache_resources", usage.resources as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_keeps_the_default_limit() {
+        assert_eq!(limit_bytes(0), DEFAULT_LIMIT);
+        assert_eq!(limit_bytes(64), 64 * MEGABYTE);
+        let usage = GpuCacheUsage {
+            used_bytes: 12 * MEGABYTE + MEGABYTE / 2,
+            limit_bytes: limit_bytes(64),
+            resources: 120,
+        };
+        assert_eq!(usage.to_string(), "12.5 MB of 64 MB in 120 resources");
+    }
+}
@@ -1,20 +1,24 @@
 use std::sync::Arc;
 
 use log::trace;
-use skia_safe::{colors, dash_path_effect, BlendMode, Canvas, Color, Paint, Path, HSV};
+use skia_safe::{
+    colors, dash_path_effect, BlendMode, Canvas, Color, Color4f, Paint, Path, Point, Rect, HSV,
+};
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     editor::{Colors, Style, UnderlineStyle},
     profiling::tracy_zone,
-    renderer::{CachingShaper, RendererSettings},
+    renderer::{box_drawing::take_box_drawing, CachingShaper, RendererSettings},
     settings::*,
     units::{
         to_skia_point, to_skia_rect, GridPos, GridScale, GridSize, PixelPos, PixelRect, PixelVec,
     },
+    utils::luminance,
     window::WindowSettings,
 };
 
-use super::fonts::font_options::FontOptions;
+use super::fonts::font_options::{CoarseStyle, EmojiOptions, FontOptions};
 
 pub struct GridRenderer {
     pub shaper: CachingShaper,
@@ -23,7 +27,8 @@ pub struct GridRenderer {
     pub grid_scale: GridScale,
     pub is_ready: bool,
 
-    settings: Arc<Settings>,
+    renderer_settings: CachedSettings<RendererSettings>,
+    window_settings: CachedSettings<WindowSettings>,
 }
 
 /// Struct with named fields to be returned from draw_background
@@ -37,6 +42,7 @@ pub struct BackgroundInfo {
 impl GridRenderer {
     pub fn new(scale_factor: f64, settings: Arc<Settings>) -> Self {
         let mut shaper = CachingShaper::new(scale_factor as f32);
+        shaper.update_emoji_options(settings.get::<RendererSettings>().emoji_options());
         let default_style = Arc::new(Style::new(Colors::new(
             Some(colors::WHITE),
             Some(colors::BLACK),
@@ -52,7 +58,8 @@ impl GridRenderer {
             grid_scale: GridScale::new(font_dimensions),
             is_ready: false,
 
-            settings,
+            renderer_settings: CachedSettings::new(settings.clone()),
+            window_settings: CachedSettings::new(settings),
         }
     }
 
@@ -75,6 +82,10 @@ impl GridRenderer {
         self.update_font_dimensions();
     }
 
+    pub fn update_emoji_options(&mut self, emoji: EmojiOptions) {
+        self.shaper.update_emoji_options(emoji);
+    }
+
     pub fn update_linespace(&mut self, linespace_setting: f32) {
         self.shaper.update_linespace(linespace_setting);
         self.update_font_dimensions();
@@ -114,7 +125,7 @@ impl GridRenderer {
         opacity: f32,
     ) -> BackgroundInfo {
         tracy_zone!("draw_background");
-        let debug = self.settings.get::<RendererSettings>().debug_renderer;
+        let debug = self.renderer_settings.get().debug_renderer;
         if style.is_none() && !debug {
             return BackgroundInfo {
                 custom_color: false,
@@ -138,16 +149,7 @@ impl GridRenderer {
             paint.set_color(style_background);
         }
 
-        let is_default_background = style_background == self.get_default_background_color();
-        let normal_opacity = self.settings.get::<WindowSettings>().normal_opacity;
-
-        let alpha = if normal_opacity < 1.0 && is_default_background {
-            normal_opacity
-        } else if style.blend > 0 {
-            ((100 - style.blend) as f32 / 100.0) * opacity
-        } else {
-            opacity
-        };
+        let alpha = self.background_alpha(style, opacity);
         paint.set_alpha_f(alpha);
 
         let custom_color = paint.color4f() != self.default_style.colors.background.unwrap();
@@ -161,6 +163,57 @@ impl GridRenderer {
         }
     }
 
+    /// The opacity of the background of the cells with `style`.
+    fn background_alpha(&self, style: &Style, opacity: f32) -> f32 {
+        let style_background = style.background(&self.default_style.colors).to_color();
+        let is_default_background = style_background == self.get_default_background_color();
+        let normal_opacity = self.window_settings.get().normal_opacity;
+
+        if normal_opacity < 1.0 && is_default_background {
+            normal_opacity
+        } else if style.blend > 0 {
+            ((100 - style.blend) as f32 / 100.0) * opacity
+        } else {
+            opacity
+        }
+    }
+
+    /// Draws a scrim behind the text of transparent cells, so that it stays readable over bright
+    /// or busy content behind the window. The luminance of the backdrop of each cell is sampled
+    /// before the scrim goes over it, so the scrim pushes it away from the text, and it gets
+    /// stronger the more transparent the cells are. Returns true if a scrim was drawn.
+    pub fn draw_scrim(
+        &mut self,
+        canvas: &Canvas,
+        text: &str,
+        grid_position: GridPos<i32>,
+        style: &Option<Arc<Style>>,
+        opacity: f32,
+    ) -> bool {
+        let strength = self.renderer_settings.get().text_scrim.clamp(0.0, 1.0);
+        if strength <= 0.0 {
+            return false;
+        }
+        let style = style.as_ref().unwrap_or(&self.default_style);
+        let alpha = self.background_alpha(style, opacity);
+        if alpha >= 1.0 {
+            return false;
+        }
+
+        let foreground = style.foreground(&self.default_style.colors);
+        let backdrop = style.background(&self.default_style.colors);
+        let mut paint = Paint::new(scrim_color(&foreground, &backdrop), None);
+        paint.set_alpha_f(strength * (1.0 - alpha));
+        paint.set_anti_alias(false);
+        let runs = text_runs(text);
+        for &(start, end) in &runs {
+            let position = GridPos::new(grid_position.x + start as i32, grid_position.y);
+            let region = self.compute_text_region(position, (end - start) as i32);
+            canvas.draw_rect(to_skia_rect(&region), &paint);
+        }
+        !runs.is_empty()
+    }
+
     /// Draws some foreground text.
     /// Returns true if any text was actually drawn.
     pub fn draw_foreground(
@@ -177,6 +230,11 @@ impl GridRenderer {
         let width = size.width;
 
         let style = style.as_ref().unwrap_or(&self.default_style);
+        let settings = self.renderer_settings.get();
+        let hint = style.is_hint();
+        if hint && settings.hide_hints {
+            return false;
+        }
         let mut drawn = false;
 
         // We don't want to clip text in the x position, only the y so we add a buffer of 1
@@ -201,17 +259,43 @@ impl GridRenderer {
         paint.set_anti_alias(false);
         paint.set_blend_mode(BlendMode::SrcOver);
 
-        if self.settings.get::<RendererSettings>().debug_renderer {
+        if self.renderer_settings.get().debug_renderer {
             let random_hsv: HSV = (rand::random::<f32>() * 360.0, 1.0, 1.0).into();
             let random_color = random_hsv.to_color(255);
             paint.set_color(random_color);
         } else {
-            paint.set_color(style.foreground(&self.default_style.colors).to_color());
+            let mut foreground = style.foreground(&self.default_style.colors);
+            if hint {
+                let background = style.background(&self.default_style.colors);
+                foreground = mix_colors(foreground, background, settings.hint_dim.clamp(0.0, 1.0));
+            }
+            paint.set_color(foreground.to_color());
         }
         paint.set_anti_alias(false);
 
+        // Box drawing characters are drawn as lines, so that they join without gaps
+        let (text, box_lines) = if self.renderer_settings.get().box_drawing_glyphs {
+            (text.into(), Vec::new())
+        } else {
+            take_box_drawing(text, style.has_highlight_group("EndOfBuffer"))
+        };
+        if !box_lines.is_empty() {
+            let stroke = self.shaper.stroke_size().round().max(1.0);
+            for (cell, lines) in box_lines {
+                let cell = Rect::from_xywh(
+                    pos.x + cell as f32 * self.grid_scale.width(),
+                    pos.y,
+                    self.grid_scale.width(),
+                    self.grid_scale.height(),
+                );
+                lines.draw(canvas, cell, stroke, &paint);
+            }
+            drawn = true;
+        }
+
         // There's a lot of overhead for empty blobs in Skia, for some reason they never hit the
         // cache, so trim all the spaces
+        let text: &str = &text;
         let trimmed = text.trim_start();
         let leading_space_bytes = text.len() - trimmed.len();
         let leading_spaces = text[..leading_space_bytes].chars().count();
@@ -222,15 +306,27 @@ impl GridRenderer {
         );
 
         if !trimmed.is_empty() {
+            let mut coarse_style = CoarseStyle::from(style);
+            let mut origin = to_skia_point(pos + adjustment);
+            canvas.save();
+            if hint {
+                coarse_style = coarse_style.with_italic(style.italic || settings.hint_italic);
+                // The hints are shrunk towards the start of their baseline
+                let scale = settings.hint_scale.clamp(0.5, 1.0);
+                canvas.translate(origin);
+                canvas.scale((scale, scale));
+                origin = Point::new(0.0, 0.0);
+            }
             for blob in self
                 .shaper
-                .shape_cached(trimmed.to_string(), style.into())
+                .shape_cached(trimmed.to_string(), coarse_style)
                 .iter()
             {
                 tracy_zone!("draw_text_blob");
-                canvas.draw_text_blob(blob, to_skia_point(pos + adjustment), &paint);
+                canvas.draw_text_blob(blob, origin, &paint);
                 drawn = true;
             }
+            canvas.restore();
         }
 
         if style.strikethrough {
@@ -263,10 +359,7 @@ impl GridRenderer {
         let mut underline_paint = Paint::default();
         underline_paint.set_anti_alias(false);
         underline_paint.set_blend_mode(BlendMode::SrcOver);
-        let underline_stroke_scale = self
-            .settings
-            .get::<RendererSettings>()
-            .underline_stroke_scale;
+        let underline_stroke_scale = self.renderer_settings.get().underline_stroke_scale;
         // clamp to 1 and round to avoid aliasing issues
         let stroke_width = (stroke_size * underline_stroke_scale).max(1.).round();
 
@@ -329,3 +422,62 @@ impl GridRenderer {
         canvas.restore();
     }
 }
+
+/// The cells of the runs of visible text, as start and end columns.
+fn text_runs(text: &str) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (cell, grapheme) in text.graphemes(true).enumerate() {
+        if grapheme.trim().is_empty() {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, end)) if *end == cell => *end = cell + 1,
+            _ => runs.push((cell, cell + 1)),
+        }
+    }
+    runs
+}
+
+/// The color of the scrim going over `backdrop`, the one making the text more distinct from it.
+/// Text lighter than its backdrop gets a dark scrim and text darker than it a light one.
+fn scrim_color(foreground: &Color4f, backdrop: &Color4f) -> Color4f {
+    if luminance(foreground) >= luminance(backdrop) {
+        colors::BLACK
+    } else {
+        colors::WHITE
+    }
+}
+
+/// Blends `amount` of `to` into `from`.
+fn mix_colors(from: Color4f, to: Color4f, amount: f32) -> Color4f {
+    Color4f::new(
+        from.r + (to.r - from.r) * amount,
+        from.g + (to.g - from.g) * amount,
+        from.b + (to.b - from.b) * amount,
+        from.a,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_runs_skip_spaces() {
+        assert_eq!(text_runs("  ab c   de "), vec![(2, 4), (5, 6), (9, 11)]);
+        assert!(text_runs("    ").is_empty());
+    }
+
+    #[test]
+    fn scrim_goes_away_from_the_text() {
+        let gray = Color4f::new(0.5, 0.5, 0.5, 1.0);
+        let light_gray = Color4f::new(0.7, 0.7, 0.7, 1.0);
+        let dark_gray = Color4f::new(0.3, 0.3, 0.3, 1.0);
+        // Light text on a lighter backdrop still wants it lighter, not darker
+        assert_eq!(scrim_color(&light_gray, &colors::WHITE), colors::WHITE);
+        assert_eq!(scrim_color(&light_gray, &gray), colors::BLACK);
+        // And dark text on a darker backdrop wants it darker
+        assert_eq!(scrim_color(&dark_gray, &colors::BLACK), colors::BLACK);
+        assert_eq!(scrim_color(&dark_gray, &gray), colors::WHITE);
+    }
+}
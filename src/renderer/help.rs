@@ -0,0 +1,879 @@
+//! `:NeovideHelp`, the documentation of the website bundled at build time and shown above the
+//! grid, so that the settings can be read about without a network connection or leaving Neovim.
+//! Each heading of the pages is a topic, and the links between them can be followed.
+
+use std::sync::{Arc, OnceLock};
+
+use skia_safe::{Canvas, Paint, PaintStyle, RRect};
+
+use crate::{
+    editor::{Colors, Style, UnderlineStyle},
+    profiling::tracy_zone,
+    renderer::{
+        cmdline_renderer::{begin_floating_box, end_floating_box, floating_box_radius},
+        popupmenu::{text_width, truncate},
+        GridRenderer, RendererSettings,
+    },
+    units::{to_skia_rect, GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// The pages of `website/docs` as `(file name, markdown)`, see `build.rs`.
+const PAGES: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/help_pages.rs"));
+
+/// Widest the text of the help gets, in columns.
+const MAX_COLUMNS: usize = 80;
+const PADDING: usize = 2;
+/// The rows of the box that aren't the text: the padding, and the footer with a blank line.
+const CHROME_ROWS: usize = 4;
+const CODE_INDENT: usize = 4;
+
+type Spans = Vec<(String, Option<usize>)>;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Block {
+    Paragraph {
+        spans: Spans,
+        indent: usize,
+    },
+    /// Code and tables, shown as they are.
+    Verbatim(Vec<String>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct HelpTopic {
+    /// The file name of the page.
+    page: String,
+    anchor: String,
+    title: String,
+    level: usize,
+    blocks: Vec<Block>,
+    /// The targets of the links, in the order of the text.
+    links: Vec<String>,
+    /// The topics the links go to, `None` for the links out of the documentation.
+    targets: Vec<Option<usize>>,
+    /// The `g:neovide_*` settings mentioned, normalized.
+    settings: Vec<String>,
+    /// The lowercase text, for searching.
+    text: String,
+}
+
+impl HelpTopic {
+    fn new(page: &str, title: &str, level: usize) -> Self {
+        let title = title.replace(['`', '*'], "");
+        Self {
+            page: page.to_string(),
+            anchor: anchor(&title),
+            title,
+            level,
+            blocks: Vec::new(),
+            links: Vec::new(),
+            targets: Vec::new(),
+            settings: Vec::new(),
+            text: String::new(),
+        }
+    }
+
+    fn add_text(&mut self, line: &str) {
+        let mut rest = line;
+        while let Some(start) = rest.find("neovide_") {
+            let name = &rest[start..];
+            let end = name
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(name.len());
+            let setting = normalize(&name[..end]);
+            if !setting.is_empty() && !self.settings.contains(&setting) {
+                self.settings.push(setting);
+            }
+            rest = &name[end..];
+        }
+        self.text.push_str(&line.to_lowercase());
+        self.text.push('\n');
+    }
+
+    fn add_paragraph(&mut self, text: &str, indent: usize) {
+        self.add_text(text);
+        let spans = parse_inline(text, &mut self.links);
+        self.blocks.push(Block::Paragraph { spans, indent });
+    }
+
+    fn add_verbatim(&mut self, line: &str) {
+        self.add_text(line);
+        let line = line.replace('\t', "    ");
+        match self.blocks.last_mut() {
+            Some(Block::Verbatim(lines)) => lines.push(line),
+            _ => self.blocks.push(Block::Verbatim(vec![line])),
+        }
+    }
+}
+
+/// The id of a heading on the website, like `scale-factor` for `Scale Factor`.
+fn anchor(title: &str) -> String {
+    title
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Makes `g:neovide_scale_factor`, `scale_factor` and `Scale Factor` the same, and
+/// `:NeovideTour` the same as `Tour`.
+fn normalize(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    let name = name.trim_start_matches(':');
+    let name = name.strip_prefix("g:").unwrap_or(name);
+    let name = name.strip_prefix("neovide").unwrap_or(name);
+    name.chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+/// Removes the markup of a line of markdown, and returns the runs of text with the index of the
+/// link they are, which is added to `links`.
+fn parse_inline(text: &str, links: &mut Vec<String>) -> Spans {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['[', '`']) {
+        let (before, after) = rest.split_at(start);
+        plain.push_str(before);
+        if let Some(code) = after.strip_prefix('`') {
+            let end = code.find('`').unwrap_or(code.len());
+            plain.push_str(&code[..end]);
+            rest = code.get(end + 1..).unwrap_or_default();
+            continue;
+        }
+        let link = after[1..].find("](").and_then(|middle| {
+            let target = 1 + middle + 2;
+            after[target..].find(')').map(|end| {
+                (
+                    &after[1..1 + middle],
+                    &after[target..target + end],
+                    target + end,
+                )
+            })
+        });
+        let Some((link_text, target, end)) = link else {
+            plain.push('[');
+            rest = &after[1..];
+            continue;
+        };
+        // Images can't be shown
+        if !plain.ends_with('!') {
+            if !plain.is_empty() {
+                spans.push((std::mem::take(&mut plain), None));
+            }
+            links.push(target.to_string());
+            spans.push((link_text.replace('`', ""), Some(links.len() - 1)));
+        } else {
+            plain.pop();
+        }
+        rest = &after[end + 1..];
+    }
+    plain.push_str(rest);
+    if !plain.is_empty() {
+        spans.push((plain, None));
+    }
+    spans
+        .into_iter()
+        .map(|(text, link)| (text.replace("**", ""), link))
+        .collect()
+}
+
+/// The level and the title of a heading.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let title = line.trim_start_matches('#');
+    let level = line.len() - title.len();
+    (level > 0 && title.starts_with(' ')).then_some((level, title.trim()))
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("- ")
+        || line.starts_with("* ")
+        || line.split_once(". ").is_some_and(|(number, _)| {
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+/// Splits a page into its topics, one per heading.
+fn parse_page(page: &str, markdown: &str) -> Vec<HelpTopic> {
+    let name = page.trim_end_matches(".md");
+    let mut topics = vec![HelpTopic::new(page, name, 1)];
+    let mut paragraph: Option<(String, usize)> = None;
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let topic = topics.last_mut().unwrap();
+        if line.trim_start().starts_with("```") {
+            if let Some((text, indent)) = paragraph.take() {
+                topic.add_paragraph(&text, indent);
+            }
+            in_code = !in_code;
+            // Separates the blocks of code that follow each other
+            if in_code && matches!(topic.blocks.last(), Some(Block::Verbatim(_))) {
+                topic.add_verbatim("");
+            }
+            continue;
+        }
+        if in_code {
+            topic.add_verbatim(line);
+            continue;
+        }
+        let trimmed = line.trim();
+        let heading = heading(trimmed);
+        if trimmed.is_empty() || heading.is_some() || trimmed.starts_with('|') {
+            if let Some((text, indent)) = paragraph.take() {
+                topic.add_paragraph(&text, indent);
+            }
+        }
+        if let Some((level, title)) = heading {
+            if topic.blocks.is_empty() && topic.level == 1 && topics.len() == 1 {
+                // The title of the page
+                topics[0] = HelpTopic::new(page, title, level);
+            } else {
+                topics.push(HelpTopic::new(page, title, level));
+            }
+        } else if trimmed.starts_with('|') {
+            topic.add_verbatim(trimmed);
+        } else if !trimmed.is_empty() {
+            match &mut paragraph {
+                Some((text, _)) if !is_list_item(line) => {
+                    text.push(' ');
+                    text.push_str(trimmed);
+                }
+                _ => {
+                    if let Some((text, indent)) = paragraph.take() {
+                        topic.add_paragraph(&text, indent);
+                    }
+                    let indent = line.len() - line.trim_start().len();
+                    paragraph = Some((trimmed.to_string(), indent));
+                }
+            }
+        }
+    }
+    if let Some((text, indent)) = paragraph.take() {
+        topics.last_mut().unwrap().add_paragraph(&text, indent);
+    }
+    topics
+}
+
+/// The topic a link of `page` goes to.
+fn resolve(topics: &[HelpTopic], page: &str, target: &str) -> Option<usize> {
+    if target.contains("://") || target.starts_with("mailto:") {
+        return None;
+    }
+    let (file, anchor) = target.split_once('#').unwrap_or((target, ""));
+    let file = match file.rsplit('/').next() {
+        Some("") | None => page,
+        Some(file) => file,
+    };
+    topics
+        .iter()
+        .position(|topic| topic.page == file && (anchor.is_empty() || topic.anchor == anchor))
+}
+
+fn parse_pages(pages: &[(&str, &str)]) -> Vec<HelpTopic> {
+    let mut topics: Vec<HelpTopic> = pages
+        .iter()
+        .flat_map(|(page, markdown)| parse_page(page, markdown))
+        .collect();
+    let targets: Vec<Vec<Option<usize>>> = topics
+        .iter()
+        .map(|topic| {
+            topic
+                .links
+                .iter()
+                .map(|link| resolve(&topics, &topic.page, link))
+                .collect()
+        })
+        .collect();
+    for (topic, targets) in topics.iter_mut().zip(targets) {
+        topic.targets = targets;
+    }
+    topics
+}
+
+fn topics() -> &'static [HelpTopic] {
+    static TOPICS: OnceLock<Vec<HelpTopic>> = OnceLock::new();
+    TOPICS.get_or_init(|| parse_pages(PAGES))
+}
+
+/// The topic `query` names, by its title, or by a setting it's about.
+fn find_topic(topics: &[HelpTopic], query: &str) -> Option<usize> {
+    let query = normalize(query);
+    if query.is_empty() {
+        return None;
+    }
+    topics
+        .iter()
+        .position(|topic| normalize(&topic.title) == query || normalize(&topic.anchor) == query)
+        .or_else(|| {
+            topics
+                .iter()
+                .position(|topic| topic.settings.contains(&query))
+        })
+}
+
+/// The topics with `query` in their title, then the ones with it in their text.
+fn search_topics(topics: &[HelpTopic], query: &str) -> Vec<usize> {
+    let query = query.trim().to_lowercase();
+    let (mut titles, mut texts) = (Vec::new(), Vec::new());
+    for (index, topic) in topics.iter().enumerate() {
+        if topic.title.to_lowercase().contains(&query) {
+            titles.push(index);
+        } else if topic.text.contains(&query) {
+            texts.push(index);
+        }
+    }
+    titles.extend(texts);
+    titles
+}
+
+/// A run of text on a line of the layout, starting at `column`.
+#[derive(Clone, Debug, PartialEq)]
+struct Segment {
+    text: String,
+    column: usize,
+    link: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct HelpLine {
+    segments: Vec<Segment>,
+    heading: bool,
+}
+
+impl HelpLine {
+    fn text(text: String, column: usize, heading: bool) -> Self {
+        Self {
+            segments: vec![Segment {
+                text,
+                column,
+                link: None,
+            }],
+            heading,
+        }
+    }
+}
+
+/// Breaks the spans into lines of at most `columns`, between words. The words glued to the
+/// previous span, like the punctuation after a link, stay with it.
+fn wrap_spans(spans: &Spans, indent: usize, columns: usize) -> Vec<HelpLine> {
+    let mut lines = Vec::new();
+    let mut line = HelpLine::default();
+    let mut column = indent;
+    let mut previous_space = true;
+    for (text, link) in spans {
+        for (index, word) in text.split_whitespace().enumerate() {
+            let glued = index == 0
+                && !previous_space
+                && !text.starts_with(char::is_whitespace)
+                && !line.segments.is_empty();
+            let width = text_width(word);
+            if !glued && !line.segments.is_empty() && column + 1 + width > columns {
+                lines.push(std::mem::take(&mut line));
+                column = indent;
+            }
+            let space = usize::from(!glued && !line.segments.is_empty());
+            match line.segments.last_mut() {
+                Some(segment) if segment.link == *link => {
+                    if space == 1 {
+                        segment.text.push(' ');
+                    }
+                    segment.text.push_str(word);
+                }
+                _ => line.segments.push(Segment {
+                    text: word.to_string(),
+                    column: column + space,
+                    link: *link,
+                }),
+            }
+            column += space + width;
+        }
+        previous_space = text.ends_with(char::is_whitespace);
+    }
+    if !line.segments.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn layout_topic(topic: &HelpTopic, columns: usize) -> Vec<HelpLine> {
+    let mut lines = vec![HelpLine::text(topic.title.clone(), 0, true)];
+    for block in &topic.blocks {
+        lines.push(HelpLine::default());
+        match block {
+            Block::Paragraph { spans, indent } => {
+                lines.extend(wrap_spans(spans, *indent, columns));
+            }
+            Block::Verbatim(verbatim) => lines.extend(
+                verbatim
+                    .iter()
+                    .map(|line| HelpLine::text(line.clone(), CODE_INDENT, false)),
+            ),
+        }
+    }
+    lines
+}
+
+/// The list of the topics found, each a link to the topic. Returns the lines with the topic of
+/// each link.
+fn layout_results(
+    topics: &[HelpTopic],
+    query: &str,
+    results: &[usize],
+) -> (Vec<HelpLine>, Vec<Option<usize>>) {
+    let title = if query.is_empty() {
+        "Neovide help".to_string()
+    } else {
+        format!("Help for \"{query}\"")
+    };
+    let mut lines = vec![HelpLine::text(title, 0, true), HelpLine::default()];
+    if results.is_empty() {
+        lines.push(HelpLine::text("Nothing found".to_string(), 0, false));
+    }
+    for (link, topic) in results.iter().enumerate() {
+        let title = &topics[*topic].title;
+        // The whole documentation is shown as a tree
+        let column = if query.is_empty() {
+            2 * topics[*topic].level.saturating_sub(1)
+        } else {
+            0
+        };
+        let mut line = HelpLine {
+            segments: vec![Segment {
+                text: title.clone(),
+                column,
+                link: Some(link),
+            }],
+            heading: false,
+        };
+        if !query.is_empty() {
+            line.segments.push(Segment {
+                text: format!("({})", topics[*topic].page),
+                column: column + text_width(title) + 1,
+                link: None,
+            });
+        }
+        lines.push(line);
+    }
+    (lines, results.iter().copied().map(Some).collect())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum HelpPage {
+    Topic(usize),
+    /// The topics found for a query, or all of them for an empty one.
+    Results(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HelpAction {
+    Scroll(isize),
+    ScrollPage(isize),
+    NextLink,
+    PreviousLink,
+    /// Opens the selected link, or searches for the query being typed.
+    Follow,
+    Back,
+    Search,
+    Type(String),
+    Erase,
+    /// Closes the search being typed, or the help.
+    Close,
+}
+
+/// Draws `:NeovideHelp` in a box above the grid, scrolled and browsed with the keyboard.
+pub struct HelpRenderer {
+    page: Option<HelpPage>,
+    history: Vec<(HelpPage, usize)>,
+    scroll: usize,
+    selected_link: Option<usize>,
+    /// The query being typed after `/`.
+    search: Option<String>,
+    // The layout of the last frame, which the keys move through
+    line_count: usize,
+    visible_rows: usize,
+    /// The links shown, with their row.
+    links: Vec<(usize, usize)>,
+}
+
+impl HelpRenderer {
+    pub fn new() -> Self {
+        Self {
+            page: None,
+            history: Vec::new(),
+            scroll: 0,
+            selected_link: None,
+            search: None,
+            line_count: 0,
+            visible_rows: 1,
+            links: Vec::new(),
+        }
+    }
+
+    /// Shows the topic `query` names, or the topics mentioning it, or all of them when it's empty.
+    pub fn open(&mut self, query: &str) {
+        let page = match find_topic(topics(), query) {
+            Some(topic) => HelpPage::Topic(topic),
+            None => HelpPage::Results(query.trim().to_string()),
+        };
+        self.history.clear();
+        self.show(page);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.page.is_some()
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    fn show(&mut self, page: HelpPage) {
+        self.page = Some(page);
+        self.scroll = 0;
+        self.selected_link = None;
+        self.search = None;
+    }
+
+    fn go_to(&mut self, page: HelpPage) {
+        if let Some(current) = self.page.take() {
+            self.history.push((current, self.scroll));
+        }
+        self.show(page);
+    }
+
+    /// Selects the link at `index` of the ones shown, scrolling to it.
+    fn select_link(&mut self, index: usize) {
+        let Some((link, row)) = self.links.get(index).copied() else {
+            return;
+        };
+        self.selected_link = Some(link);
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + self.visible_rows {
+            self.scroll = row + 1 - self.visible_rows;
+        }
+    }
+
+    fn scroll_by(&mut self, rows: isize) {
+        let max_scroll = self.line_count.saturating_sub(self.visible_rows);
+        self.scroll = self.scroll.saturating_add_signed(rows).min(max_scroll);
+    }
+
+    /// The lines of the page shown, and the topic each of their links goes to.
+    fn layout(&self, columns: usize) -> (Vec<HelpLine>, Vec<Option<usize>>) {
+        let topics = topics();
+        match &self.page {
+            Some(HelpPage::Topic(topic)) => {
+                let topic = &topics[*topic];
+                (layout_topic(topic, columns), topic.targets.clone())
+            }
+            Some(HelpPage::Results(query)) if query.is_empty() => {
+                let all: Vec<usize> = (0..topics.len()).collect();
+                layout_results(topics, query, &all)
+            }
+            Some(HelpPage::Results(query)) => {
+                layout_results(topics, query, &search_topics(topics, query))
+            }
+            None => (Vec::new(), Vec::new()),
+        }
+    }
+
+    fn selected_target(&self) -> Option<Option<usize>> {
+        let link = self.selected_link?;
+        let (_, targets) = self.layout(MAX_COLUMNS);
+        targets.get(link).copied()
+    }
+
+    pub fn navigate(&mut self, action: HelpAction) {
+        if let Some(query) = &mut self.search {
+            match action {
+                HelpAction::Type(text) => query.push_str(&text),
+                HelpAction::Erase => {
+                    query.pop();
+                }
+                HelpAction::Follow => {
+                    let query = query.clone();
+                    self.go_to(HelpPage::Results(query));
+                }
+                HelpAction::Close => self.search = None,
+                _ => {}
+            }
+            return;
+        }
+        let count = self.links.len();
+        let selected = self
+            .selected_link
+            .and_then(|selected| self.links.iter().position(|(link, _)| *link == selected));
+        match action {
+            HelpAction::Scroll(rows) => self.scroll_by(rows),
+            HelpAction::ScrollPage(pages) => self.scroll_by(pages * self.visible_rows as isize),
+            HelpAction::NextLink if count > 0 => {
+                self.select_link(selected.map_or(0, |index| (index + 1) % count));
+            }
+            HelpAction::PreviousLink if count > 0 => {
+                self.select_link(selected.map_or(count - 1, |index| (index + count - 1) % count));
+            }
+            HelpAction::Follow => {
+                if let Some(Some(topic)) = self.selected_target() {
+                    self.go_to(HelpPage::Topic(topic));
+                }
+            }
+            HelpAction::Back => {
+                if let Some((page, scroll)) = self.history.pop() {
+                    self.show(page);
+                    self.scroll = scroll;
+                }
+            }
+            HelpAction::Search => self.search = Some(String::new()),
+            HelpAction::Close => {
+                self.page = None;
+                self.history.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// The last line of the box: the query being typed, the address of a link out of the
+    /// documentation, or the keys.
+    fn footer(&self, links: &[String]) -> String {
+        if let Some(query) = &self.search {
+            return format!("/{query}█");
+        }
+        if let (Some(HelpPage::Topic(_)), Some(link)) = (&self.page, self.selected_link) {
+            if let Some(url) = links.get(link).filter(|url| url.contains("://")) {
+                return url.clone();
+            }
+        }
+        "j/k scroll  Tab link  Enter follow  ← back  / search  Esc close".to_string()
+    }
+
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("help_draw");
+        let Some(page) = &self.page else {
+            return;
+        };
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+        let max_columns = ((region.max.x - region.min.x) / cell_width).floor() as usize;
+        let max_rows = ((region.max.y - region.min.y) / line_height).floor() as usize;
+        if max_columns <= 2 * PADDING + 1 || max_rows <= CHROME_ROWS + 2 {
+            return;
+        }
+        let columns = MAX_COLUMNS.min(max_columns - 2 * PADDING);
+        // A row is left above and below the box
+        let rows = max_rows - 2;
+
+        let (lines, _) = self.layout(columns);
+        let links = match page {
+            HelpPage::Topic(topic) => topics()[*topic].links.clone(),
+            HelpPage::Results(_) => Vec::new(),
+        };
+        self.line_count = lines.len();
+        self.visible_rows = rows - CHROME_ROWS;
+        self.links.clear();
+        for (row, line) in lines.iter().enumerate() {
+            for link in line.segments.iter().filter_map(|segment| segment.link) {
+                if !self.links.iter().any(|(shown, _)| *shown == link) {
+                    self.links.push((link, row));
+                }
+            }
+        }
+        self.scroll_by(0);
+
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let size = PixelSize::new(
+            (columns + 2 * PADDING) as f32 * cell_width,
+            rows as f32 * line_height,
+        );
+        let x = region.min.x + ((region.max.x - region.min.x - size.width) / 2.0).max(0.0);
+        let y = region.min.y + line_height;
+        let origin = PixelPos::new(x.round(), y.round());
+        let rect = PixelRect::from_origin_and_size(origin, size);
+
+        let default_style = grid_renderer.default_style.clone();
+        let blur_amount = renderer_settings.floating_blur.then_some((
+            renderer_settings.floating_blur_amount_x,
+            renderer_settings.floating_blur_amount_y,
+        ));
+        begin_floating_box(
+            root_canvas,
+            &rect,
+            radius,
+            blur_amount,
+            1.0,
+            default_style.background(&default_style.colors).to_color(),
+        );
+        root_canvas.translate((origin.x, origin.y));
+
+        let style = |configure: fn(&mut Style)| {
+            let mut style = Style::new(Colors::new(None, None, None));
+            configure(&mut style);
+            Some(Arc::new(style))
+        };
+        let heading_style = style(|style| style.bold = true);
+        let link_style = style(|style| style.underline = Some(UnderlineStyle::Underline));
+        let selected_style = style(|style| {
+            style.reverse = true;
+            style.underline = Some(UnderlineStyle::Underline);
+        });
+
+        let visible = lines.iter().skip(self.scroll).take(self.visible_rows);
+        for (row, line) in visible.enumerate() {
+            for segment in &line.segments {
+                let column = segment.column.min(columns);
+                let text = truncate(&segment.text, columns - column);
+                if text.is_empty() {
+                    continue;
+                }
+                let position = GridPos::new((PADDING + column) as i32, row as i32 + 1);
+                let width = text_width(&text) as i32;
+                let style = match segment.link {
+                    Some(link) if Some(link) == self.selected_link => {
+                        grid_renderer.draw_background(
+                            root_canvas,
+                            position,
+                            width,
+                            &selected_style,
+                            1.0,
+                        );
+                        &selected_style
+                    }
+                    Some(_) => &link_style,
+                    None if line.heading => &heading_style,
+                    None => &None,
+                };
+                grid_renderer.draw_foreground(root_canvas, &text, position, width, style);
+            }
+        }
+        let footer = truncate(&self.footer(&links), columns);
+        grid_renderer.draw_foreground(
+            root_canvas,
+            &footer,
+            GridPos::new(PADDING as i32, (rows - 2) as i32),
+            columns as i32,
+            &None,
+        );
+        end_floating_box(root_canvas);
+
+        let mut outline_color = default_style.foreground(&default_style.colors);
+        outline_color.a = 0.5;
+        let mut outline = Paint::new(outline_color, None);
+        outline.set_style(PaintStyle::Stroke);
+        outline.set_anti_alias(true);
+        root_canvas.draw_rrect(
+            RRect::new_rect_xy(to_skia_rect(&rect), radius, radius),
+            &outline,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "# Configuration
+
+Intro with a [link](features.md#zoom).
+
+## Scale Factor
+
+```lua
+vim.g.neovide_scale_factor = 1.0
+```
+
+Changes the **size** of `everything`, see [the zoom](#zoom).
+
+- first item
+- second
+  item
+
+### Zoom
+
+Pinch to zoom, [docs](https://neovide.dev).
+";
+
+    #[test]
+    fn pages_are_split_by_headings() {
+        let topics = parse_pages(&[("configuration.md", PAGE)]);
+        let titles: Vec<_> = topics.iter().map(|topic| topic.title.as_str()).collect();
+        assert_eq!(titles, vec!["Configuration", "Scale Factor", "Zoom"]);
+        assert_eq!(topics[1].anchor, "scale-factor");
+        assert_eq!(topics[1].level, 2);
+        assert_eq!(topics[1].settings, vec!["scalefactor"]);
+        assert_eq!(
+            topics[1].blocks,
+            vec![
+                Block::Verbatim(vec!["vim.g.neovide_scale_factor = 1.0".to_string()]),
+                Block::Paragraph {
+                    spans: vec![
+                        ("Changes the size of everything, see ".to_string(), None),
+                        ("the zoom".to_string(), Some(0)),
+                        (".".to_string(), None),
+                    ],
+                    indent: 0,
+                },
+                Block::Paragraph {
+                    spans: vec![("- first item".to_string(), None)],
+                    indent: 0,
+                },
+                Block::Paragraph {
+                    spans: vec![("- second item".to_string(), None)],
+                    indent: 0,
+                },
+            ]
+        );
+        // The links go to the topics, but not out of the documentation
+        assert_eq!(topics[0].targets, vec![None]);
+        assert_eq!(topics[1].targets, vec![Some(2)]);
+        assert_eq!(topics[2].targets, vec![None]);
+    }
+
+    #[test]
+    fn topics_are_found_by_title_and_setting() {
+        let topics = parse_pages(&[("configuration.md", PAGE)]);
+        assert_eq!(find_topic(&topics, "scale factor"), Some(1));
+        assert_eq!(find_topic(&topics, "g:neovide_scale_factor"), Some(1));
+        assert_eq!(find_topic(&topics, ":NeovideZoom"), Some(2));
+        assert_eq!(find_topic(&topics, "pinch"), None);
+        assert_eq!(find_topic(&topics, ""), None);
+        assert_eq!(search_topics(&topics, "Zoom"), vec![2, 0, 1]);
+        assert_eq!(search_topics(&topics, "pinch"), vec![2]);
+    }
+
+    #[test]
+    fn links_stay_on_their_line() {
+        let spans = vec![
+            ("See the ".to_string(), None),
+            ("scale factor".to_string(), Some(0)),
+            (".".to_string(), None),
+        ];
+        let segments = |line: &HelpLine| {
+            line.segments
+                .iter()
+                .map(|segment| (segment.text.as_str(), segment.column, segment.link))
+                .collect::<Vec<_>>()
+        };
+        let lines = wrap_spans(&spans, 0, 14);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            segments(&lines[0]),
+            vec![("See the", 0, None), ("scale", 8, Some(0))]
+        );
+        assert_eq!(
+            segments(&lines[1]),
+            vec![("factor", 0, Some(0)), (".", 6, None)]
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use std::sync::Arc;
+
+use skia_safe::Canvas;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    bridge::MessageKind,
+    editor::{CommandLineLine, Message, StyledText},
+    profiling::tracy_zone,
+    renderer::{
+        animation_utils::ease_out_cubic,
+        cmdline_renderer::{
+            begin_floating_box, end_floating_box, floating_box_radius, layout_rows, row_width,
+        },
+        text_selection::SelectableText,
+        GridRenderer, RendererSettings,
+    },
+    settings::*,
+    units::{GridPos, PixelPos, PixelRect, PixelSize, PixelVec},
+};
+
+/// Padding around the text of a toast, in cells.
+const PADDING: f32 = 0.5;
+/// Space between the toasts and the edge of the window, in cells.
+const MARGIN: f32 = 1.0;
+/// The narrowest a toast is allowed to wrap at, in cells.
+const MIN_COLUMNS: usize = 20;
+/// How much of the window width a toast can take.
+const MAX_WIDTH: f32 = 0.4;
+const FADE_LENGTH: f32 = 0.2;
+
+#[derive(Clone, SettingGroup)]
+#[setting_prefix = "messages"]
+pub struct MessagesSettings {
+    pub enabled: bool,
+    timeout: f32,
+    max_count: u32,
+    position: String,
+}
+
+impl Default for MessagesSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: 4.0,
+            max_count: 5,
+            position: "bottom_right".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ToastCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ToastCorner {
+    fn parse(value: &str) -> Self {
+        match value {
+            "top_left" => ToastCorner::TopLeft,
+            "top_right" => ToastCorner::TopRight,
+            "bottom_left" => ToastCorner::BottomLeft,
+            _ => ToastCorner::BottomRight,
+        }
+    }
+
+    fn is_top(self) -> bool {
+        matches!(self, ToastCorner::TopLeft | ToastCorner::TopRight)
+    }
+
+    fn is_left(self) -> bool {
+        matches!(self, ToastCorner::TopLeft | ToastCorner::BottomLeft)
+    }
+}
+
+/// Splits the message content into lines, dropping the empty lines around it.
+fn message_lines(message: &Message) -> Vec<CommandLineLine> {
+    let mut lines = vec![CommandLineLine::default()];
+    for fragment in &message.content {
+        for (index, text) in fragment.text.split('\n').enumerate() {
+            if index > 0 {
+                lines.push(CommandLineLine::default());
+            }
+            if !text.is_empty() {
+                lines.last_mut().unwrap().fragments.push(StyledText {
+                    text: text.to_string(),
+                    style: fragment.style.clone(),
+                });
+            }
+        }
+    }
+
+    while lines.last().is_some_and(|line| line.fragments.is_empty()) {
+        lines.pop();
+    }
+    let leading_empty = lines
+        .iter()
+        .take_while(|line| line.fragments.is_empty())
+        .count();
+    lines.drain(..leading_empty);
+    lines
+}
+
+struct Toast {
+    message: Message,
+    /// Seconds since the toast was shown.
+    age: f32,
+}
+
+impl Toast {
+    /// Whether Neovim waits for an answer to the message, which keeps it shown until Neovim
+    /// clears the messages or shows the next one.
+    fn is_prompt(&self) -> bool {
+        matches!(
+            self.message.kind,
+            MessageKind::Confirm | MessageKind::ConfirmSubstitute | MessageKind::ReturnPrompt
+        )
+    }
+
+    fn opacity(&self, timeout: f32) -> f32 {
+        let fade_in = self.age / FADE_LENGTH;
+        let fade_out = if timeout > 0.0 && !self.is_prompt() {
+            (timeout + FADE_LENGTH - self.age) / FADE_LENGTH
+        } else {
+            1.0
+        };
+        ease_out_cubic(fade_in.min(fade_out).clamp(0.0, 1.0))
+    }
+}
+
+/// Draws the messages of Neovim's externalized message UI as toasts stacked in a corner.
+pub struct MessagesRenderer {
+    toasts: Vec<Toast>,
+
+    settings: Arc<Settings>,
+}
+
+impl MessagesRenderer {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            toasts: Vec::new(),
+            settings,
+        }
+    }
+
+    pub fn show(&mut self, message: Message) {
+        if let Some(toast) = self
+            .toasts
+            .iter_mut()
+            .find(|toast| toast.message.id == message.id)
+        {
+            // Replaced messages stay visible without fading in again
+            toast.message = message;
+            toast.age = toast.age.min(FADE_LENGTH);
+        } else {
+            self.clear_prompts();
+            self.toasts.push(Toast { message, age: 0.0 });
+        }
+
+        let max_count = self.settings.get::<MessagesSettings>().max_count.max(1) as usize;
+        if self.toasts.len() > max_count {
+            self.toasts.drain(..self.toasts.len() - max_count);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.toasts.clear();
+    }
+
+    /// Removes the messages Neovim was waiting for an answer to, when it clears the messages.
+    pub fn clear_prompts(&mut self) {
+        self.toasts.retain(|toast| !toast.is_prompt());
+    }
+
+    pub fn animate(&mut self, dt: f32) -> bool {
+        if self.toasts.is_empty() {
+            return false;
+        }
+        let timeout = self.settings.get::<MessagesSettings>().timeout;
+        for toast in &mut self.toasts {
+            toast.age += dt;
+        }
+        if timeout > 0.0 {
+            self.toasts
+                .retain(|toast| toast.is_prompt() || toast.age < timeout + FADE_LENGTH);
+            // Keep animating to expire the remaining toasts
+            self.toasts
+                .iter()
+                .any(|toast| !toast.is_prompt() || toast.age < FADE_LENGTH)
+        } else {
+            self.toasts.iter().any(|toast| toast.age < FADE_LENGTH)
+        }
+    }
+
+    /// Draws the toasts inside `region`, the newest one closest to the configured corner.
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+        selectable_text: &mut SelectableText,
+    ) {
+        tracy_zone!("messages_draw");
+        if self.toasts.is_empty() {
+            return;
+        }
+        let settings = self.settings.get::<MessagesSettings>();
+        let corner = ToastCorner::parse(&settings.position);
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+        let radius = floating_box_radius(renderer_settings, line_height);
+
+        let region_width = region.max.x - region.min.x;
+        let region_height = region.max.y - region.min.y;
+        let available_columns =
+            ((region_width / cell_width - 2.0 * (PADDING + MARGIN)).floor() as usize).max(1);
+        let columns = ((region_width * MAX_WIDTH / cell_width - 2.0 * PADDING).floor() as usize)
+            .max(MIN_COLUMNS)
+            .min(available_columns);
+
+        let default_style = grid_renderer.default_style.clone();
+        let background = default_style.background(&default_style.colors).to_color();
+
+        let mut offset = MARGIN * line_height;
+        for toast in self.toasts.iter().rev() {
+            let rows = layout_rows(&message_lines(&toast.message), columns);
+            if rows.is_empty() {
+                continue;
+            }
+            let used_columns = rows.iter().map(row_width).max().unwrap_or(0);
+            let size = PixelSize::new(
+                (used_columns as f32 + 2.0 * PADDING) * cell_width,
+                (rows.len() as f32 + 2.0 * PADDING) * line_height,
+            );
+            if offset + size.height > region_height {
+                break;
+            }
+
+            let x = if corner.is_left() {
+                region.min.x + MARGIN * cell_width
+            } else {
+                region.max.x - MARGIN * cell_width - size.width
+            };
+            let y = if corner.is_top() {
+                region.min.y + offset
+            } else {
+                region.max.y - offset - size.height
+            };
+            offset += size.height + PADDING * line_height;
+
+            let origin = PixelPos::new(x.round(), y.round());
+            let rect = PixelRect::from_origin_and_size(origin, size);
+            let opacity = toast.opacity(settings.timeout);
+            let blur_amount = renderer_settings.floating_blur.then_some((
+                renderer_settings.floating_blur_amount_x * opacity,
+                renderer_settings.floating_blur_amount_y * opacity,
+            ));
+            begin_floating_box(root_canvas, &rect, radius, blur_amount, opacity, background);
+
+            let text_origin = PixelPos::new(
+                origin.x + PADDING * cell_width,
+                origin.y + PADDING * line_height,
+            );
+            root_canvas.translate((text_origin.x, text_origin.y));
+            selectable_text.begin_block();
+            for (row_index, row) in rows.iter().enumerate() {
+                let row_text = row
+                    .fragments
+                    .iter()
+                    .map(|(_, fragment)| fragment.text.as_str())
+                    .collect::<String>();
+                selectable_text.add_line(
+                    text_origin + PixelVec::new(0.0, row_index as f32 * line_height),
+                    PixelSize::new(cell_width, line_height),
+                    &row_text,
+                );
+                for (column, fragment) in &row.fragments {
+                    let position = GridPos::new(*column as i32, row_index as i32);
+                    let width = fragment.text.graphemes(true).count() as i32;
+                    grid_renderer.draw_background(
+                        root_canvas,
+                        position,
+                        width,
+                        &fragment.style,
+                        1.0,
+                    );
+                    grid_renderer.draw_foreground(
+                        root_canvas,
+                        &fragment.text,
+                        position,
+                        width,
+                        &fragment.style,
+                    );
+                }
+            }
+            end_floating_box(root_canvas);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(texts: &[&str]) -> Message {
+        Message {
+            id: 1,
+            kind: MessageKind::Echo,
+            content: texts
+                .iter()
+                .map(|text| StyledText {
+                    text: text.to_string(),
+                    style: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn message_lines_split_on_newlines() {
+        let lines = message_lines(&message(&["\nfirst", " line\nsecond\n\nlast\n"]));
+        let texts = lines
+            .iter()
+            .map(|line| {
+                line.fragments
+                    .iter()
+                    .map(|fragment| fragment.text.as_str())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["first line", "second", "", "last"]);
+    }
+
+    #[test]
+    fn toasts_fade_in_and_out() {
+        let toast = Toast {
+            message: message(&["hello"]),
+            age: 0.0,
+        };
+        assert_eq!(toast.opacity(4.0), 0.0);
+
+        let toast = Toast { age: 1.0, ..toast };
+        assert_eq!(toast.opacity(4.0), 1.0);
+
+        let toast = Toast { age: 4.5, ..toast };
+        assert_eq!(toast.opacity(4.0), 0.0);
+        assert_eq!(toast.opacity(0.0), 1.0);
+    }
+
+    #[test]
+    fn prompts_stay_until_cleared_or_replaced() {
+        let settings = Arc::new(Settings::new());
+        settings.register::<MessagesSettings>();
+        let mut renderer = MessagesRenderer::new(settings);
+        renderer.show(Message {
+            kind: MessageKind::Confirm,
+            ..message(&["Save changes?"])
+        });
+        renderer.show(Message {
+            id: 2,
+            kind: MessageKind::ReturnPrompt,
+            ..message(&["Press ENTER"])
+        });
+        assert_eq!(renderer.toasts.len(), 1);
+        assert_eq!(renderer.toasts[0].message.id, 2);
+
+        renderer.animate(10.0);
+        assert_eq!(renderer.toasts.len(), 1);
+        assert_eq!(renderer.toasts[0].opacity(4.0), 1.0);
+
+        renderer.show(Message {
+            id: 3,
+            ..message(&["written"])
+        });
+        assert_eq!(renderer.toasts.len(), 1);
+        assert_eq!(renderer.toasts[0].message.id, 3);
+
+        renderer.show(Message {
+            id: 4,
+            kind: MessageKind::Confirm,
+            ..message(&["Save changes?"])
+        });
+        renderer.clear_prompts();
+        assert_eq!(renderer.toasts.len(), 1);
+        renderer.animate(10.0);
+        assert!(renderer.toasts.is_empty());
+    }
+}
@@ -179,6 +179,10 @@ impl SkiaRenderer for MetalSkiaRenderer {
         &self.window
     }
 
+    fn gr_context(&mut self) -> &mut DirectContext {
+        &mut self.context
+    }
+
     fn flush(&mut self) {
         tracy_gpu_zone!("flush");
 
@@ -1,12 +1,33 @@
 pub mod animation_utils;
+pub mod bell;
+mod box_drawing;
+pub mod clipboard_history;
+pub mod cmdline_renderer;
 pub mod cursor_renderer;
+pub mod exit_prompt;
+pub mod file_changed;
 pub mod fonts;
+pub mod frame_diff;
+pub mod gpu_cache;
 pub mod grid_renderer;
+pub mod help;
+pub mod messages_renderer;
+pub mod mouse_selection;
+pub mod multigrid_warning;
 pub mod opengl;
+pub mod overlay;
+pub mod popupmenu;
 pub mod profiler;
 mod rendered_layer;
 mod rendered_window;
+pub mod tabline;
+pub mod text_selection;
+pub mod titlebar;
+pub mod tour;
 mod vsync;
+pub mod wildmenu;
+#[cfg(target_os = "linux")]
+mod window_shape;
 
 #[cfg(target_os = "windows")]
 pub mod d3d;
@@ -22,7 +43,7 @@ use std::{
 
 use itertools::Itertools;
 use log::error;
-use skia_safe::Canvas;
+use skia_safe::{gpu::DirectContext, Canvas, Paint, PaintStyle};
 
 use winit::{
     event::WindowEvent,
@@ -33,11 +54,14 @@ use winit::{
 use crate::{
     bridge::EditorMode,
     cmd_line::CmdLineSettings,
-    editor::{Cursor, Style},
+    editor::{
+        CommandLineLine, Cursor, Message, PopupMenu, PopupMenuAnchor, Style, Tabline, Wildmenu,
+    },
+    frame::window_frame,
     profiling::{tracy_create_gpu_context, tracy_named_frame, tracy_zone},
     renderer::rendered_layer::{group_windows, FloatingLayer},
     settings::*,
-    units::{to_skia_rect, GridRect, GridSize, PixelPos},
+    units::{to_skia_rect, GridPos, GridRect, GridSize, GridVec, PixelPos, PixelRect, PixelSize},
     window::{ShouldRender, UserEvent},
     WindowSettings,
 };
@@ -54,14 +78,31 @@ use skia_safe::graphics::{
 #[cfg(feature = "gpu_profiling")]
 use crate::profiling::GpuCtx;
 
+use bell::BellRenderer;
+use clipboard_history::ClipboardHistoryRenderer;
+use cmdline_renderer::CommandLineRenderer;
 use cursor_renderer::CursorRenderer;
+use exit_prompt::ExitPromptRenderer;
+use file_changed::FileChangedRenderer;
 pub use fonts::caching_shaper::CachingShaper;
 pub use grid_renderer::GridRenderer;
+use help::HelpRenderer;
+use messages_renderer::MessagesRenderer;
+use mouse_selection::{draw_selection, GridSelection};
+use multigrid_warning::MultigridWarningRenderer;
+use overlay::OverlayRenderer;
+use popupmenu::PopupMenuRenderer;
 pub use rendered_window::{LineFragment, RenderedWindow, WindowDrawCommand, WindowDrawDetails};
+use tabline::{TablineRenderer, TablineSettings};
+use text_selection::SelectableText;
+use titlebar::TitlebarRenderer;
+use tour::TourRenderer;
+use wildmenu::WildmenuRenderer;
 
 pub use vsync::VSync;
 
-use self::fonts::font_options::FontOptions;
+use self::fonts::font_options::{EmojiOptions, FontOptions};
+use animation_utils::Easing;
 
 #[cfg(feature = "profiling")]
 fn plot_skia_cache() {
@@ -86,48 +127,92 @@ fn plot_skia_cache() {
 #[derive(SettingGroup, Clone)]
 pub struct RendererSettings {
     position_animation_length: f32,
+    position_easing: Easing,
     scroll_animation_length: f32,
+    scroll_easing: Easing,
     scroll_animation_far_lines: u32,
+    scroll_overscroll_bounce: bool,
     floating_blur: bool,
     floating_blur_amount_x: f32,
     floating_blur_amount_y: f32,
     floating_shadow: bool,
     floating_z_height: f32,
     floating_corner_radius: f32,
+    floating_size_animation: bool,
     light_angle_degrees: f32,
     light_radius: f32,
     debug_renderer: bool,
     profiler: bool,
+    profiler_dropped_frames: bool,
     underline_stroke_scale: f32,
     text_gamma: f32,
     text_contrast: f32,
     experimental_layer_grouping: bool,
+    terminal_font: String,
+    pub terminal_coalesce_output: bool,
+    box_drawing_glyphs: bool,
+    text_scrim: f32,
+    hint_italic: bool,
+    hint_dim: f32,
+    hint_scale: f32,
+    hide_hints: bool,
+    pub animation_pause_rate: f32,
+    pub reduce_motion: bool,
+    pub gpu_cache_mb: u32,
+    emoji_monochrome: bool,
+    emoji_fit_cell: bool,
+    emoji_font: String,
 }
 
 impl Default for RendererSettings {
     fn default() -> Self {
         Self {
             position_animation_length: 0.15,
+            position_easing: Easing::EaseOutExpo,
             scroll_animation_length: 0.3,
+            scroll_easing: Easing::Spring(1.0),
             scroll_animation_far_lines: 1,
+            scroll_overscroll_bounce: false,
             floating_blur: true,
             floating_blur_amount_x: 2.0,
             floating_blur_amount_y: 2.0,
             floating_shadow: true,
             floating_z_height: 10.,
             floating_corner_radius: 0.0,
+            floating_size_animation: true,
             light_angle_degrees: 45.,
             light_radius: 5.,
             debug_renderer: false,
             profiler: false,
+            profiler_dropped_frames: false,
             underline_stroke_scale: 1.,
             text_gamma: 0.0,
             text_contrast: 0.5,
             experimental_layer_grouping: false,
+            terminal_font: "".to_string(),
+            terminal_coalesce_output: true,
+            box_drawing_glyphs: false,
+            text_scrim: 0.0,
+            hint_italic: false,
+            hint_dim: 0.0,
+            hint_scale: 1.0,
+            hide_hints: false,
+            animation_pause_rate: 100.0,
+            reduce_motion: false,
+            gpu_cache_mb: 0,
+            emoji_monochrome: false,
+            emoji_fit_cell: true,
+            emoji_font: "".to_string(),
         }
     }
 }
 
+impl RendererSettings {
+    pub fn emoji_options(&self) -> EmojiOptions {
+        EmojiOptions::new(self.emoji_monochrome, self.emoji_fit_cell, &self.emoji_font)
+    }
+}
+
 // Since draw commmands are inserted into a heap, we need to implement Ord such that
 // the commands that should be processed first (such as window draw commands or close
 // window) are sorted as larger than the ones that should be handled later
@@ -140,6 +225,12 @@ pub enum DrawCommand {
     LineSpaceChanged(f32),
     DefaultStyleChanged(Style),
     ModeChanged(EditorMode),
+    CommandLineChanged(Vec<CommandLineLine>),
+    ShowMessage(Message),
+    ClearMessages,
+    PopupMenuChanged(Option<PopupMenu>),
+    TablineChanged(Option<Tabline>),
+    WildmenuChanged(Option<Wildmenu>),
     UIReady,
     Window {
         grid_id: u64,
@@ -151,15 +242,46 @@ pub enum DrawCommand {
 pub struct Renderer {
     cursor_renderer: CursorRenderer,
     pub grid_renderer: GridRenderer,
+    pub cmdline_renderer: CommandLineRenderer,
+    messages_renderer: MessagesRenderer,
+    popupmenu_renderer: PopupMenuRenderer,
+    pub tabline_renderer: TablineRenderer,
+    pub titlebar_renderer: TitlebarRenderer,
+    pub wildmenu_renderer: WildmenuRenderer,
+    pub tour_renderer: TourRenderer,
+    pub help_renderer: HelpRenderer,
+    pub clipboard_history_renderer: ClipboardHistoryRenderer,
+    pub file_changed_renderer: FileChangedRenderer,
+    pub exit_prompt_renderer: ExitPromptRenderer,
+    pub multigrid_warning_renderer: MultigridWarningRenderer,
+    /// Flashes the grid for the visual bell.
+    pub bell_renderer: BellRenderer,
+    /// The drawings of the plugins, see `neovide.draw`.
+    pub overlay_renderer: OverlayRenderer,
+    /// Draws terminal windows when `neovide_terminal_font` is set.
+    terminal_grid_renderer: Option<GridRenderer>,
     current_mode: EditorMode,
 
     rendered_windows: HashMap<u64, RenderedWindow>,
     pub window_regions: Vec<WindowDrawDetails>,
+    /// Whether files, text or links are dragged over the window, to outline where they can be
+    /// dropped.
+    pub drop_preview: bool,
+    /// Stretches the grid to the size of the window while Neovim catches up with a resize.
+    pub resize_preview_scale: Option<(f32, f32)>,
+    /// The cells selected with the mouse, shaded over their window.
+    pub mouse_selection: Option<GridSelection>,
+    /// The text the overlays drew in the last frame, and the part of it selected with the mouse.
+    pub selectable_text: SelectableText,
 
     profiler: profiler::Profiler,
     pub os_scale_factor: f64,
     pub user_scale_factor: f64,
+    /// The name of the monitor showing the window, for `g:neovide_font_size_per_monitor`.
+    monitor: Option<String>,
 
+    window_settings: CachedSettings<WindowSettings>,
+    renderer_settings: CachedSettings<RendererSettings>,
     settings: Arc<Settings>,
 }
 
@@ -181,7 +303,14 @@ impl Renderer {
         let scale_factor = user_scale_factor * os_scale_factor;
         let cursor_renderer = CursorRenderer::new(settings.clone());
         let mut grid_renderer = GridRenderer::new(scale_factor, settings.clone());
-        grid_renderer.update_font_options(init_font_settings.map(|x| x.into()).unwrap_or_default());
+        let font_options: FontOptions = init_font_settings.map(|x| x.into()).unwrap_or_default();
+        grid_renderer.update_font_options(font_options.clone());
+        let mut cmdline_renderer = CommandLineRenderer::new(scale_factor, settings.clone());
+        cmdline_renderer.update_font_options(font_options);
+        let messages_renderer = MessagesRenderer::new(settings.clone());
+        let popupmenu_renderer = PopupMenuRenderer::new(settings.clone());
+        let tabline_renderer = TablineRenderer::new(settings.clone());
+        let wildmenu_renderer = WildmenuRenderer::new(settings.clone());
         let current_mode = EditorMode::Unknown(String::from(""));
 
         let rendered_windows = HashMap::new();
@@ -189,17 +318,41 @@ impl Renderer {
 
         let profiler = profiler::Profiler::new(12.0, settings.clone());
 
-        Renderer {
+        let mut renderer = Renderer {
             rendered_windows,
             cursor_renderer,
             grid_renderer,
+            cmdline_renderer,
+            messages_renderer,
+            popupmenu_renderer,
+            tabline_renderer,
+            titlebar_renderer: TitlebarRenderer::new(settings.clone()),
+            wildmenu_renderer,
+            tour_renderer: TourRenderer::new(),
+            help_renderer: HelpRenderer::new(),
+            clipboard_history_renderer: ClipboardHistoryRenderer::new(),
+            file_changed_renderer: FileChangedRenderer::new(),
+            exit_prompt_renderer: ExitPromptRenderer::new(),
+            multigrid_warning_renderer: MultigridWarningRenderer::new(),
+            bell_renderer: BellRenderer::new(settings.clone()),
+            overlay_renderer: OverlayRenderer::new(),
+            terminal_grid_renderer: None,
             current_mode,
             window_regions,
+            drop_preview: false,
+            resize_preview_scale: None,
+            mouse_selection: None,
+            selectable_text: SelectableText::default(),
             profiler,
             os_scale_factor,
             user_scale_factor,
+            monitor: None,
+            window_settings: CachedSettings::new(settings.clone()),
+            renderer_settings: CachedSettings::new(settings.clone()),
             settings,
-        }
+        };
+        renderer.update_terminal_font();
+        renderer
     }
 
     pub fn handle_event(&mut self, event: &WindowEvent) {
@@ -214,24 +367,95 @@ impl Renderer {
         self.cursor_renderer.prepare_frame()
     }
 
-    pub fn draw_frame(&mut self, root_canvas: &Canvas, dt: f32) {
+    pub fn draw_frame(&mut self, root_canvas: &Canvas, dt: f32, expected_dt: Option<f32>) {
         tracy_zone!("renderer_draw_frame");
-        let window_settings = self.settings.get::<WindowSettings>();
+        let window_settings = self.window_settings.get();
         let opacity = if window_settings.normal_opacity < 1.0 {
             window_settings.normal_opacity
         } else {
             window_settings.transparency
         };
         let default_background = self.grid_renderer.get_default_background(opacity);
+        // The system backdrop only shows through where nothing is drawn, the cells still cover it
+        // as much as the opacity asks for
+        #[cfg(target_os = "windows")]
+        let default_background =
+            if window_settings.system_backdrop() != winit::platform::windows::BackdropType::None {
+                default_background.with_a(0)
+            } else {
+                default_background
+            };
         let grid_scale = self.grid_renderer.grid_scale;
 
-        let layer_grouping = self
-            .settings
-            .get::<RendererSettings>()
-            .experimental_layer_grouping;
-        root_canvas.clear(default_background);
+        let layer_grouping = self.renderer_settings.get().experimental_layer_grouping;
+        #[cfg(target_os = "linux")]
+        let window_shape = self.window_shape(root_canvas);
+        #[cfg(target_os = "linux")]
+        let shaped = window_shape.is_some();
+        #[cfg(not(target_os = "linux"))]
+        let shaped = false;
+        if !shaped {
+            root_canvas.clear(default_background);
+        }
         root_canvas.save();
         root_canvas.reset_matrix();
+        #[cfg(target_os = "linux")]
+        if let Some(window_shape) = &window_shape {
+            window_shape.clip(root_canvas, default_background);
+        }
+
+        // The custom title bar takes the top of the window, with the tabs in it when asked for
+        let titlebar_height = self.titlebar_height();
+        if titlebar_height > 0.0 {
+            let width = root_canvas.base_layer_size().width as f32;
+            let rect = PixelRect::new(
+                PixelPos::new(0.0, 0.0),
+                PixelPos::new(width, titlebar_height),
+            );
+            let show_tabs =
+                self.tabs_in_titlebar() && self.tabline_renderer.height(grid_scale) > 0.0;
+            let tabs_rect =
+                self.titlebar_renderer
+                    .draw(root_canvas, rect, &mut self.grid_renderer, !show_tabs);
+            if show_tabs {
+                let settings = self.renderer_settings.get();
+                self.tabline_renderer.draw(
+                    root_canvas,
+                    tabs_rect,
+                    &mut self.grid_renderer,
+                    &settings,
+                );
+            }
+        }
+
+        // The tab bar sits right above the grid, outside of the area the windows are clipped to
+        if let Some(root_window) = self
+            .rendered_windows
+            .get(&1)
+            .filter(|_| !self.tabs_in_titlebar())
+        {
+            let region = root_window.pixel_region(grid_scale);
+            let tabline_rect = PixelRect::new(
+                PixelPos::new(region.min.x, region.min.y - self.tabline_height()),
+                PixelPos::new(region.max.x, region.min.y),
+            );
+            let settings = self.renderer_settings.get();
+            self.tabline_renderer.draw(
+                root_canvas,
+                tabline_rect,
+                &mut self.grid_renderer,
+                &settings,
+            );
+        }
+
+        if let (Some(root_window), Some(scale)) =
+            (self.rendered_windows.get(&1), self.resize_preview_scale)
+        {
+            let origin = root_window.pixel_region(grid_scale).min;
+            root_canvas.translate((origin.x, origin.y));
+            root_canvas.scale(scale);
+            root_canvas.translate((-origin.x, -origin.y));
+        }
 
         if let Some(root_window) = self.rendered_windows.get(&1) {
             let clip_rect = to_skia_rect(&root_window.pixel_region(grid_scale));
@@ -300,7 +524,7 @@ impl Renderer {
             (root_windows, floating_layers)
         };
 
-        let settings = self.settings.get::<RendererSettings>();
+        let settings = self.renderer_settings.get();
         let root_window_regions = root_windows
             .into_iter()
             .map(|window| window.draw(root_canvas, default_background, grid_scale))
@@ -317,10 +541,118 @@ impl Renderer {
             .into_iter()
             .chain(floating_window_regions)
             .collect();
-        self.cursor_renderer
-            .draw(&mut self.grid_renderer, root_canvas);
+        self.draw_mouse_selection(root_canvas);
+        // The command line has its own caret, so hide the grid cursor while it's shown
+        if !self.cmdline_renderer.is_visible() {
+            let cursor_in_terminal = self.cursor_in_terminal();
+            let cursor_grid_renderer = match &mut self.terminal_grid_renderer {
+                Some(terminal_grid_renderer) if cursor_in_terminal => terminal_grid_renderer,
+                _ => &mut self.grid_renderer,
+            };
+            self.cursor_renderer.draw(cursor_grid_renderer, root_canvas);
+        }
+
+        self.selectable_text.begin_frame();
+        if let Some(root_window) = self.rendered_windows.get(&1) {
+            let region = root_window.pixel_region(grid_scale);
+            let default_style = &self.grid_renderer.default_style;
+            self.bell_renderer.draw(
+                root_canvas,
+                region,
+                default_style.foreground(&default_style.colors),
+            );
+            self.overlay_renderer.draw(
+                root_canvas,
+                region,
+                self.scale_factor() as f32,
+                &mut self.grid_renderer,
+                &mut self.selectable_text,
+            );
+            self.messages_renderer.draw(
+                root_canvas,
+                region,
+                &mut self.grid_renderer,
+                &settings,
+                &mut self.selectable_text,
+            );
+            let default_style = &self.grid_renderer.default_style;
+            self.selectable_text
+                .draw_selection(root_canvas, default_style.foreground(&default_style.colors));
+            self.file_changed_renderer.draw(
+                root_canvas,
+                region,
+                &mut self.grid_renderer,
+                &settings,
+            );
+            self.multigrid_warning_renderer.draw(
+                root_canvas,
+                region,
+                &mut self.grid_renderer,
+                &settings,
+            );
+            self.exit_prompt_renderer
+                .draw(root_canvas, region, &mut self.grid_renderer, &settings);
+            self.cmdline_renderer.draw(root_canvas, region, &settings);
+            // Without the native command line, the completions go above the last line
+            let wildmenu_anchor = self.cmdline_renderer.box_rect().unwrap_or_else(|| {
+                PixelRect::new(
+                    PixelPos::new(region.min.x, region.max.y - grid_scale.height()),
+                    region.max,
+                )
+            });
+            self.wildmenu_renderer.draw(
+                root_canvas,
+                wildmenu_anchor,
+                region,
+                &mut self.grid_renderer,
+                &settings,
+            );
+            if let Some(anchor) = self.popup_menu_anchor(region) {
+                self.popupmenu_renderer.draw(
+                    root_canvas,
+                    anchor,
+                    region,
+                    &mut self.grid_renderer,
+                    &settings,
+                );
+            }
+        }
+
+        if let Some(root_window) = self.rendered_windows.get(&1) {
+            let region = root_window.pixel_region(grid_scale);
+            let cursor = PixelRect::from_origin_and_size(
+                self.cursor_renderer.get_destination(),
+                PixelSize::new(grid_scale.width(), grid_scale.height()),
+            );
+            self.tour_renderer.draw(
+                root_canvas,
+                region,
+                cursor,
+                &mut self.grid_renderer,
+                &settings,
+            );
+            self.help_renderer
+                .draw(root_canvas, region, &mut self.grid_renderer, &settings);
+            self.clipboard_history_renderer.draw(
+                root_canvas,
+                region,
+                &mut self.grid_renderer,
+                &settings,
+            );
+        }
 
-        self.profiler.draw(root_canvas, dt);
+        self.selectable_text.end_frame();
+
+        if self.drop_preview {
+            self.draw_drop_preview(root_canvas);
+        }
+
+        self.profiler.draw(root_canvas, dt, expected_dt);
+
+        #[cfg(target_os = "linux")]
+        if let Some(window_shape) = &window_shape {
+            window_shape.draw_border(root_canvas);
+        }
 
         root_canvas.restore();
 
@@ -328,6 +660,81 @@ impl Renderer {
         plot_skia_cache();
     }
 
+    /// The rounded corners and the border of a frameless window, following the size of the
+    /// surface.
+    #[cfg(target_os = "linux")]
+    fn window_shape(&self, root_canvas: &Canvas) -> Option<window_shape::WindowShape> {
+        let window_settings = self.window_settings.get();
+        let size = root_canvas.base_layer_size();
+        window_shape::window_shape(
+            size.width as f32,
+            size.height as f32,
+            self.os_scale_factor as f32,
+            window_frame(&self.settings),
+            window_settings.window_corner_radius,
+            &window_settings.window_border_color,
+        )
+    }
+
+    /// Outlines the grid, where the dragged files are dropped.
+    fn draw_drop_preview(&self, root_canvas: &Canvas) {
+        let Some(root_window) = self.rendered_windows.get(&1) else {
+            return;
+        };
+        let default_style = &self.grid_renderer.default_style;
+        let mut color = default_style.foreground(&default_style.colors);
+        color.a = 0.5;
+        let width = (2.0 * self.os_scale_factor as f32).round();
+        let mut paint = Paint::new(color, None);
+        paint.set_style(PaintStyle::Stroke);
+        paint.set_stroke_width(width);
+        let rect = to_skia_rect(&root_window.pixel_region(self.grid_renderer.grid_scale));
+        root_canvas.draw_rect(rect.with_inset((width / 2.0, width / 2.0)), &paint);
+    }
+
+    fn draw_mouse_selection(&self, root_canvas: &Canvas) {
+        let Some(selection) = &self.mouse_selection else {
+            return;
+        };
+        let Some(details) = self
+            .window_regions
+            .iter()
+            .find(|details| details.id == selection.grid_id)
+        else {
+            return;
+        };
+        let default_style = &self.grid_renderer.default_style;
+        draw_selection(
+            root_canvas,
+            selection,
+            details.region,
+            self.grid_renderer.grid_scale,
+            default_style.foreground(&default_style.colors),
+        );
+    }
+
+    /// The text of the cells selected with the mouse, one line per row.
+    pub fn selection_text(&self, selection: &GridSelection) -> Option<String> {
+        self.rendered_windows
+            .get(&selection.grid_id)
+            .map(|window| window.text_in(selection.cells()))
+    }
+
+    /// The text of a line of a window, and the index of the character at the cell `position` in
+    /// it.
+    pub fn line_at(&self, grid_id: u64, position: GridPos<u32>) -> Option<(String, usize)> {
+        self.rendered_windows
+            .get(&grid_id)?
+            .line_at(position.y, position.x)
+    }
+
+    /// Where the window is on the screen grid, in cells.
+    pub fn window_grid_position(&self, grid_id: u64) -> Option<GridPos<f32>> {
+        self.rendered_windows
+            .get(&grid_id)
+            .map(|window| window.grid_current_position)
+    }
+
     pub fn animate_frame(&mut self, grid_rect: &GridRect<f32>, dt: f32) -> bool {
         let windows = {
             let (mut root_windows, mut floating_windows): (
@@ -347,7 +754,7 @@ impl Renderer {
             root_windows.into_iter().chain(floating_windows)
         };
 
-        let settings = self.settings.get::<RendererSettings>();
+        let settings = self.renderer_settings.get();
         // Clippy recommends short-circuiting with any which is not what we want
         #[allow(clippy::unnecessary_fold)]
         let mut animating = windows.fold(false, |acc, window| {
@@ -359,24 +766,121 @@ impl Renderer {
         self.cursor_renderer
             .update_cursor_destination(grid_scale, windows);
 
+        let cursor_in_terminal = self.cursor_in_terminal();
+        let cursor_grid_renderer = match &self.terminal_grid_renderer {
+            Some(terminal_grid_renderer) if cursor_in_terminal => terminal_grid_renderer,
+            _ => &self.grid_renderer,
+        };
         animating |= self
             .cursor_renderer
-            .animate(&self.current_mode, &self.grid_renderer, dt);
+            .animate(&self.current_mode, cursor_grid_renderer, dt);
+
+        animating |= self.cmdline_renderer.animate(dt);
+        animating |= self.messages_renderer.animate(dt);
+        animating |= self.popupmenu_renderer.animate(dt);
+        animating |= self.tabline_renderer.animate(dt);
+        animating |= self.wildmenu_renderer.animate(dt);
+        animating |= self.bell_renderer.animate(dt);
 
         animating
     }
 
+    /// Moves the cursor ahead of a slow server for a key that moves it, until Neovim's own cursor
+    /// arrives.
+    pub fn predict_cursor_motion(&mut self, motion: GridVec<i32>) {
+        if matches!(self.current_mode, EditorMode::Normal | EditorMode::Visual) {
+            self.cursor_renderer.predict_motion(motion);
+        }
+    }
+
+    /// The height of the tab bar shown above the grid, in pixels, none when it's in the title bar.
+    pub fn tabline_height(&self) -> f32 {
+        if self.tabs_in_titlebar() {
+            return 0.0;
+        }
+        self.tabline_renderer.height(self.grid_renderer.grid_scale)
+    }
+
+    /// The height of the title bar drawn with `--frame custom`, in pixels.
+    pub fn titlebar_height(&self) -> f32 {
+        self.titlebar_renderer.height(self.grid_renderer.grid_scale)
+    }
+
+    fn tabs_in_titlebar(&self) -> bool {
+        self.titlebar_renderer.enabled() && self.settings.get::<TablineSettings>().in_titlebar
+    }
+
+    /// The cell, or the command line, the completion popup is attached to. Without the native
+    /// command line, that's the cell of the last line of `region`.
+    fn popup_menu_anchor(&self, region: PixelRect<f32>) -> Option<PixelRect<f32>> {
+        match self.popupmenu_renderer.menu()?.anchor {
+            PopupMenuAnchor::Grid { grid, row, column } => {
+                let grid_scale = self.grid_renderer.grid_scale;
+                let window = self.rendered_windows.get(&grid)?;
+                let cell_scale = window.font_scale.unwrap_or(grid_scale);
+                let origin = window.grid_current_position * grid_scale
+                    + GridVec::new(column as f32, row as f32) * cell_scale;
+                Some(PixelRect::from_origin_and_size(
+                    origin,
+                    PixelSize::new(cell_scale.width(), cell_scale.height()),
+                ))
+            }
+            PopupMenuAnchor::CommandLine { column } => {
+                let anchor = self.cmdline_renderer.popup_menu_anchor(column);
+                Some(anchor.unwrap_or_else(|| {
+                    let grid_scale = self.grid_renderer.grid_scale;
+                    let x = region.min.x + column as f32 * grid_scale.width();
+                    PixelRect::new(
+                        PixelPos::new(x, region.max.y - grid_scale.height()),
+                        PixelPos::new(x + grid_scale.width(), region.max.y),
+                    )
+                }))
+            }
+        }
+    }
+
+    /// Pulls the window past the buffer boundary when `lines` are scrolled while the viewport is
+    /// already at the top or the bottom.
+    pub fn handle_overscroll(&mut self, window_id: u64, lines: f32) {
+        if !self
+            .settings
+            .get::<RendererSettings>()
+            .scroll_overscroll_bounce
+        {
+            return;
+        }
+        if let Some(window) = self.rendered_windows.get_mut(&window_id) {
+            window.overscroll(lines);
+        }
+    }
+
+    pub fn clear_windows(&mut self) {
+        self.rendered_windows.clear();
+        self.window_regions.clear();
+        self.messages_renderer.clear();
+        self.popupmenu_renderer.set_menu(None);
+        self.tabline_renderer.set_tabline(None);
+        self.wildmenu_renderer.set_wildmenu(None);
+        self.exit_prompt_renderer.hide();
+    }
+
     pub fn handle_config_changed(&mut self, config: HotReloadConfigs) {
         match config {
             HotReloadConfigs::Font(font) => match font {
                 Some(font) => {
-                    self.grid_renderer.update_font_options(font.into());
+                    let options: FontOptions = font.into();
+                    self.grid_renderer.update_font_options(options.clone());
+                    self.cmdline_renderer.update_font_options(options);
                 }
                 None => {
                     self.grid_renderer
                         .update_font_options(FontOptions::default());
+                    self.cmdline_renderer
+                        .update_font_options(FontOptions::default());
                 }
             },
+            // Sent to Neovim by the window, they come back as settings changes
+            HotReloadConfigs::Settings(_) => {}
         }
     }
 
@@ -396,17 +900,111 @@ impl Renderer {
         result
     }
 
-    pub fn handle_os_scale_factor_change(&mut self, os_scale_factor: f64) {
+    pub fn handle_os_scale_factor_change(&mut self, os_scale_factor: f64, monitor: Option<String>) {
         self.os_scale_factor = os_scale_factor;
-        self.grid_renderer
-            .handle_scale_factor_update(self.os_scale_factor * self.user_scale_factor);
+        self.monitor = monitor;
+        self.handle_scale_factor_update();
     }
 
-    pub fn prepare_lines(&mut self, force: bool) {
-        let transparency = self.settings.get::<WindowSettings>().transparency;
+    /// The window moved to another monitor, which can have another font size. Returns whether
+    /// the font size changed.
+    pub fn handle_monitor_change(&mut self, monitor: Option<String>) -> bool {
+        if monitor == self.monitor {
+            return false;
+        }
+        let old_scale_factor = self.scale_factor();
+        self.monitor = monitor;
+        if self.scale_factor() == old_scale_factor {
+            return false;
+        }
+        self.handle_scale_factor_update();
+        true
+    }
+
+    /// The scale factor of the OS, times the zoom of the user and the font size of the monitor.
+    pub fn scale_factor(&self) -> f64 {
+        let window_settings = self.window_settings.get();
+        let monitor_multiplier = window_settings
+            .font_size_per_monitor
+            .multiplier(self.monitor.as_deref());
+        self.os_scale_factor * self.user_scale_factor * monitor_multiplier as f64
+    }
+
+    pub fn handle_scale_factor_update(&mut self) {
+        let scale_factor = self.scale_factor();
+        self.grid_renderer.handle_scale_factor_update(scale_factor);
+        self.cmdline_renderer
+            .handle_scale_factor_update(scale_factor);
+        if let Some(terminal_grid_renderer) = &mut self.terminal_grid_renderer {
+            terminal_grid_renderer.handle_scale_factor_update(scale_factor);
+        }
+    }
+
+    /// Creates, updates or removes the grid renderer used for terminal windows, according to
+    /// `neovide_terminal_font`.
+    pub fn update_terminal_font(&mut self) {
+        let terminal_font = self.settings.get::<RendererSettings>().terminal_font;
+        if terminal_font.is_empty() {
+            self.terminal_grid_renderer = None;
+            return;
+        }
+        let options = match FontOptions::parse(&terminal_font) {
+            Ok(options) => options,
+            Err(error) => {
+                error!("Invalid neovide_terminal_font: {error}");
+                return;
+            }
+        };
+        let terminal_grid_renderer = self.terminal_grid_renderer.get_or_insert_with(|| {
+            let scale_factor = self.scale_factor();
+            GridRenderer::new(scale_factor, self.settings.clone())
+        });
+        terminal_grid_renderer.default_style = self.grid_renderer.default_style.clone();
+        terminal_grid_renderer.update_font_options(options);
+    }
+
+    pub fn update_emoji_options(&mut self) {
+        let emoji = self.settings.get::<RendererSettings>().emoji_options();
+        self.grid_renderer.update_emoji_options(emoji.clone());
+        self.cmdline_renderer.update_emoji_options(emoji.clone());
+        if let Some(terminal_grid_renderer) = &mut self.terminal_grid_renderer {
+            terminal_grid_renderer.update_emoji_options(emoji);
+        }
+    }
+
+    fn cursor_in_terminal(&self) -> bool {
         self.rendered_windows
-            .iter_mut()
-            .for_each(|(_, w)| w.prepare_lines(&mut self.grid_renderer, transparency, force));
+            .get(&self.cursor_renderer.parent_window_id())
+            .is_some_and(|window| window.font_scale.is_some())
+    }
+
+    /// Returns the grids that should be resized to fit the font they are drawn with.
+    pub fn take_grid_size_requests(&mut self) -> Vec<(u64, GridSize<u32>)> {
+        let grid_scale = self.grid_renderer.grid_scale;
+        let terminal_scale = self
+            .terminal_grid_renderer
+            .as_ref()
+            .map(|terminal_grid_renderer| terminal_grid_renderer.grid_scale);
+        self.rendered_windows
+            .values_mut()
+            .filter_map(|window| {
+                window.font_scale = terminal_scale.filter(|_| window.is_terminal());
+                window
+                    .take_grid_size_request(grid_scale)
+                    .map(|size| (window.id, size))
+            })
+            .collect()
+    }
+
+    pub fn prepare_lines(&mut self, force: bool) {
+        let transparency = self.window_settings.get().transparency;
+        for window in self.rendered_windows.values_mut() {
+            let grid_renderer = match &mut self.terminal_grid_renderer {
+                Some(terminal_grid_renderer) if window.is_terminal() => terminal_grid_renderer,
+                _ => &mut self.grid_renderer,
+            };
+            window.prepare_lines(grid_renderer, transparency, force);
+        }
     }
 
     fn handle_draw_command(&mut self, draw_command: DrawCommand, result: &mut DrawCommandResult) {
@@ -435,8 +1033,9 @@ impl Renderer {
                             // Ignore the errors when not using multigrid, since Neovim wrongly sends some of these
                             if !settings.no_multi_grid {
                                 error!(
-                                    "WindowDrawCommand: {:?} sent for uninitialized grid {}",
-                                    command, grid_id
+                                    grid_id = grid_id;
+                                    "WindowDrawCommand: {:?} sent for uninitialized grid",
+                                    command
                                 );
                             }
                         }
@@ -448,18 +1047,47 @@ impl Renderer {
             }
             DrawCommand::FontChanged(new_font) => {
                 self.grid_renderer.update_font(&new_font);
+                self.cmdline_renderer.update_font(&new_font);
                 result.font_changed = true;
             }
             DrawCommand::LineSpaceChanged(new_linespace) => {
                 self.grid_renderer.update_linespace(new_linespace);
+                self.cmdline_renderer.update_linespace(new_linespace);
+                if let Some(terminal_grid_renderer) = &mut self.terminal_grid_renderer {
+                    terminal_grid_renderer.update_linespace(new_linespace);
+                }
                 result.font_changed = true;
             }
             DrawCommand::DefaultStyleChanged(new_style) => {
-                self.grid_renderer.default_style = Arc::new(new_style);
+                let new_style = Arc::new(new_style);
+                self.cmdline_renderer
+                    .update_default_style(new_style.clone());
+                if let Some(terminal_grid_renderer) = &mut self.terminal_grid_renderer {
+                    terminal_grid_renderer.default_style = new_style.clone();
+                }
+                self.grid_renderer.default_style = new_style;
             }
             DrawCommand::ModeChanged(new_mode) => {
                 self.current_mode = new_mode;
             }
+            DrawCommand::CommandLineChanged(lines) => {
+                self.cmdline_renderer.set_lines(lines);
+            }
+            DrawCommand::ShowMessage(message) => {
+                self.messages_renderer.show(message);
+            }
+            DrawCommand::ClearMessages => {
+                self.messages_renderer.clear_prompts();
+            }
+            DrawCommand::PopupMenuChanged(menu) => {
+                self.popupmenu_renderer.set_menu(menu);
+            }
+            DrawCommand::TablineChanged(tabline) => {
+                self.tabline_renderer.set_tabline(tabline);
+            }
+            DrawCommand::WildmenuChanged(wildmenu) => {
+                self.wildmenu_renderer.set_wildmenu(wildmenu);
+            }
             DrawCommand::UIReady => {
                 result.should_show = true;
             }
@@ -473,6 +1101,10 @@ impl Renderer {
             .for_each(|(_, w)| w.flush(renderer_settings));
     }
 
+    pub fn current_mode(&self) -> &EditorMode {
+        &self.current_mode
+    }
+
     pub fn get_cursor_destination(&self) -> PixelPos<f32> {
         self.cursor_renderer.get_destination()
     }
@@ -553,6 +1185,9 @@ pub trait SkiaRenderer {
     fn swap_buffers(&mut self);
     fn canvas(&mut self) -> &Canvas;
     fn resize(&mut self);
+    /// Applies the swap interval again, after the window moved to another monitor
+    fn refresh_swap_interval(&mut self) {}
+    fn gr_context(&mut self) -> &mut DirectContext;
     fn create_vsync(&self, proxy: EventLoopProxy<UserEvent>) -> VSync;
     #[cfg(feature = "gpu_profiling")]
     fn tracy_create_gpu_context(&self, name: &str) -> Box<dyn GpuCtx>;
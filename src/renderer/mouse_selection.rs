@@ -0,0 +1,144 @@
+use std::ops::Range;
+
+use skia_safe::{Canvas, Color4f, Paint};
+
+use crate::{
+    renderer::LineFragment,
+    units::{to_skia_rect, GridPos, GridRect, GridScale, GridVec, PixelRect},
+};
+
+/// A rectangle of cells selected with `neovide_mouse_selection`, which Neovide copies on its own
+/// instead of going through the visual mode of Neovim.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridSelection {
+    pub grid_id: u64,
+    pub start: GridPos<u32>,
+    pub end: GridPos<u32>,
+}
+
+impl GridSelection {
+    pub fn new(grid_id: u64, position: GridPos<u32>) -> Self {
+        Self {
+            grid_id,
+            start: position,
+            end: position,
+        }
+    }
+
+    /// The selected cells, whichever way the pointer was dragged.
+    pub fn cells(&self) -> GridRect<u32> {
+        GridRect::new(
+            self.start.min(self.end),
+            self.start.max(self.end) + GridVec::new(1, 1),
+        )
+    }
+}
+
+/// The text of the fragments of a line inside `columns`. Fragments whose text doesn't have one
+/// character per cell, like the double width ones, are taken whole.
+pub fn fragments_text(fragments: &[LineFragment], columns: Range<u64>) -> String {
+    let mut text = String::new();
+    for fragment in fragments {
+        let fragment_columns = fragment.window_left..fragment.window_left + fragment.width;
+        let start = fragment_columns.start.max(columns.start);
+        let end = fragment_columns.end.min(columns.end);
+        if start >= end {
+            continue;
+        }
+        if fragment.text.chars().count() as u64 == fragment.width {
+            text.extend(
+                fragment
+                    .text
+                    .chars()
+                    .skip((start - fragment.window_left) as usize)
+                    .take((end - start) as usize),
+            );
+        } else {
+            text.push_str(&fragment.text);
+        }
+    }
+    text.trim_end().to_string()
+}
+
+/// The text of a whole line, and the index of the character at `column` in it, or its length past
+/// the end. A column inside a fragment taken whole, like a double width one, is at its start.
+pub fn fragments_line_at(fragments: &[LineFragment], column: u64) -> (String, usize) {
+    let mut text = String::new();
+    let mut length = 0;
+    let mut index = None;
+    for fragment in fragments {
+        let characters = fragment.text.chars().count();
+        let fragment_columns = fragment.window_left..fragment.window_left + fragment.width;
+        if index.is_none() && fragment_columns.contains(&column) {
+            let offset = if characters as u64 == fragment.width {
+                (column - fragment.window_left) as usize
+            } else {
+                0
+            };
+            index = Some(length + offset);
+        }
+        text.push_str(&fragment.text);
+        length += characters;
+    }
+    (text, index.unwrap_or(length))
+}
+
+/// Shades the selected cells of the window drawn at `region`.
+pub fn draw_selection(
+    canvas: &Canvas,
+    selection: &GridSelection,
+    region: PixelRect<f32>,
+    grid_scale: GridScale,
+    mut color: Color4f,
+) {
+    let cells = selection.cells() * grid_scale;
+    let rect = PixelRect::new(
+        (region.min + *cells.min.as_vector()).min(region.max),
+        (region.min + *cells.max.as_vector()).min(region.max),
+    );
+    color.a = 0.3;
+    canvas.draw_rect(to_skia_rect(&rect), &Paint::new(color, None));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(window_left: u64, text: &str, width: u64) -> LineFragment {
+        LineFragment {
+            text: text.to_string(),
+            window_left,
+            width,
+            style: None,
+        }
+    }
+
+    #[test]
+    fn selection_takes_the_selected_columns() {
+        let selection = GridSelection {
+            grid_id: 2,
+            start: GridPos::new(6, 3),
+            end: GridPos::new(2, 1),
+        };
+        assert_eq!(
+            selection.cells(),
+            GridRect::new(GridPos::new(2, 1), GridPos::new(7, 4))
+        );
+
+        let fragments = [
+            fragment(0, "let ", 4),
+            fragment(4, "漢", 2),
+            fragment(6, " = 1   ", 7),
+        ];
+        assert_eq!(fragments_text(&fragments, 2..8), "t 漢 =");
+        assert_eq!(fragments_text(&fragments, 5..13), "漢 = 1");
+        assert_eq!(fragments_text(&fragments, 20..30), "");
+
+        assert_eq!(
+            fragments_line_at(&fragments, 9),
+            ("let 漢 = 1   ".to_string(), 8)
+        );
+        assert_eq!(fragments_line_at(&fragments, 5).1, 4);
+        assert_eq!(fragments_line_at(&fragments, 20).1, 12);
+    }
+}
@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use glamour::Contains;
+use skia_safe::{Canvas, Paint, Rect};
+
+use crate::{
+    editor::{Colors, MultigridAnomaly, Style},
+    profiling::tracy_zone,
+    renderer::{
+        cmdline_renderer::{begin_floating_box, end_floating_box, floating_box_radius},
+        popupmenu::{text_width, truncate},
+        GridRenderer, RendererSettings,
+    },
+    units::{GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Columns of padding around the text of the warning.
+const PADDING: usize = 1;
+/// Lines between the warning and the edge of the grid.
+const MARGIN: f32 = 0.5;
+const MAX_COLUMNS: usize = 72;
+/// The anomalies listed before summing up the rest.
+const MAX_LISTED: usize = 5;
+const TITLE: &str = "Floating windows may be misplaced";
+const SUGGESTION: [&str; 2] = [
+    "A plugin may not support ext_multigrid. Try updating it, or",
+    "check whether :NeovideToggleMultigrid fixes it.",
+];
+const DISMISS: &str = "Dismiss";
+
+/// Draws a warning in the top left corner of the grid about the floats Neovim asked for that
+/// can't be placed, with the grids involved. Clicking it dismisses it.
+pub struct MultigridWarningRenderer {
+    anomalies: Vec<MultigridAnomaly>,
+    box_rect: Option<PixelRect<f32>>,
+}
+
+impl MultigridWarningRenderer {
+    pub fn new() -> Self {
+        Self {
+            anomalies: Vec::new(),
+            box_rect: None,
+        }
+    }
+
+    pub fn push(&mut self, anomalies: Vec<MultigridAnomaly>) {
+        self.anomalies.extend(anomalies);
+    }
+
+    pub fn dismiss(&mut self) {
+        self.anomalies.clear();
+        self.box_rect = None;
+    }
+
+    pub fn contains(&self, position: PixelPos<f32>) -> bool {
+        self.box_rect.is_some_and(|rect| rect.contains(&position))
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = self
+            .anomalies
+            .iter()
+            .take(MAX_LISTED)
+            .map(MultigridAnomaly::describe)
+            .collect::<Vec<_>>();
+        if self.anomalies.len() > MAX_LISTED {
+            lines.push(format!(
+                "and {} more, see the log",
+                self.anomalies.len() - MAX_LISTED
+            ));
+        }
+        lines.push(String::new());
+        lines.extend(SUGGESTION.iter().map(|line| line.to_string()));
+        lines
+    }
+
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("multigrid_warning_draw");
+        self.box_rect = None;
+        if self.anomalies.is_empty() {
+            return;
+        }
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+        let max_columns = ((region.max.x - region.min.x) / cell_width - 2.0 * MARGIN).floor();
+        if max_columns <= (2 * PADDING) as f32 {
+            return;
+        }
+        let text_columns = (max_columns as usize - 2 * PADDING).min(MAX_COLUMNS);
+
+        let lines = std::iter::once(TITLE.to_string())
+            .chain(self.lines())
+            .map(|line| truncate(&line, text_columns))
+            .collect::<Vec<_>>();
+        let button_width = text_width(DISMISS) + 2;
+        let columns = lines
+            .iter()
+            .map(|line| text_width(line))
+            .max()
+            .unwrap_or(0)
+            .max(button_width)
+            .min(text_columns);
+        // The lines, an empty line and the button
+        let rows = lines.len() + 2;
+
+        let size = PixelSize::new(
+            (columns + 2 * PADDING) as f32 * cell_width,
+            (rows + 2 * PADDING) as f32 * line_height,
+        );
+        let origin = PixelPos::new(
+            (region.min.x + MARGIN * line_height).round(),
+            (region.min.y + MARGIN * line_height).round(),
+        );
+        let rect = PixelRect::from_origin_and_size(origin, size);
+
+        let default_style = grid_renderer.default_style.clone();
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let blur_amount = renderer_settings.floating_blur.then_some((
+            renderer_settings.floating_blur_amount_x,
+            renderer_settings.floating_blur_amount_y,
+        ));
+        begin_floating_box(
+            root_canvas,
+            &rect,
+            radius,
+            blur_amount,
+            1.0,
+            default_style.background(&default_style.colors).to_color(),
+        );
+        root_canvas.translate((origin.x, origin.y));
+        self.box_rect = Some(rect);
+
+        let mut bold = Style::new(Colors::new(None, None, None));
+        bold.bold = true;
+        let bold = Some(Arc::new(bold));
+        for (row, line) in lines.iter().enumerate() {
+            let style = if row == 0 { &bold } else { &None };
+            grid_renderer.draw_foreground(
+                root_canvas,
+                line,
+                GridPos::new(PADDING as i32, (row + PADDING) as i32),
+                columns as i32,
+                style,
+            );
+        }
+
+        let mut button_style = Style::new(Colors::new(None, None, None));
+        button_style.reverse = true;
+        let button_style = Some(Arc::new(button_style));
+        let button_paint = Paint::new(default_style.foreground(&default_style.colors), None);
+        let row = rows - 1 + PADDING;
+        let column = PADDING + columns - button_width.min(columns);
+        root_canvas.draw_rect(
+            Rect::from_xywh(
+                column as f32 * cell_width,
+                row as f32 * line_height,
+                button_width as f32 * cell_width,
+                line_height,
+            ),
+            &button_paint,
+        );
+        grid_renderer.draw_foreground(
+            root_canvas,
+            &format!(" {DISMISS} "),
+            GridPos::new(column as i32, row as i32),
+            button_width as i32,
+            &button_style,
+        );
+
+        end_floating_box(root_canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_lists_are_summed_up() {
+        let mut renderer = MultigridWarningRenderer::new();
+        renderer.push(
+            (2..10)
+                .map(|grid| MultigridAnomaly::MissingGrid { grid })
+                .collect(),
+        );
+        let lines = renderer.lines();
+        assert_eq!(lines[0], "Grid 2 was positioned before it was drawn");
+        assert_eq!(lines[MAX_LISTED], "and 3 more, see the log");
+        assert_eq!(lines.len(), MAX_LISTED + 2 + SUGGESTION.len());
+
+        renderer.dismiss();
+        assert!(renderer.anomalies.is_empty());
+    }
+}
@@ -55,6 +55,7 @@ pub struct OpenGLSkiaRenderer {
     window_surface: Surface<WindowSurface>,
     config: Config,
     window: Option<Window>,
+    vsync: bool,
 
     settings: Arc<Settings>,
 }
@@ -66,6 +67,21 @@ fn clamp_render_buffer_size(size: &PhysicalSize<u32>) -> PhysicalSize<u32> {
     )
 }
 
+fn set_swap_interval(
+    window_surface: &Surface<WindowSurface>,
+    context: &PossiblyCurrentContext,
+    vsync: bool,
+) {
+    // NOTE: We don't care if these fails, the driver can override the SwapInterval in any case, so it needs to work in all cases
+    // The OpenGL VSync is always disabled on Wayland and Windows, since they have their own
+    // implementation
+    let _ = if vsync && env::var("WAYLAND_DISPLAY").is_err() && OS != "windows" && OS != "macos" {
+        window_surface.set_swap_interval(context, SwapInterval::Wait(NonZeroU32::new(1).unwrap()))
+    } else {
+        window_surface.set_swap_interval(context, SwapInterval::DontWait)
+    };
+}
+
 fn get_proc_address(surface: &Surface<WindowSurface>, addr: &CStr) -> *const c_void {
     GlDisplay::get_proc_address(&surface.display(), addr)
 }
@@ -103,16 +119,7 @@ impl OpenGLSkiaRenderer {
             .make_current(&window_surface)
             .unwrap();
 
-        // NOTE: We don't care if these fails, the driver can override the SwapInterval in any case, so it needs to work in all cases
-        // The OpenGL VSync is always disabled on Wayland and Windows, since they have their own
-        // implementation
-        let _ = if vsync && env::var("WAYLAND_DISPLAY").is_err() && OS != "windows" && OS != "macos"
-        {
-            window_surface
-                .set_swap_interval(&context, SwapInterval::Wait(NonZeroU32::new(1).unwrap()))
-        } else {
-            window_surface.set_swap_interval(&context, SwapInterval::DontWait)
-        };
+        set_swap_interval(&window_surface, &context, vsync);
 
         gl::load_with(|s| get_proc_address(&window_surface, CString::new(s).unwrap().as_c_str()));
 
@@ -150,6 +157,7 @@ impl OpenGLSkiaRenderer {
             window_surface,
             context,
             window: Some(window),
+            vsync,
             config,
             gr_context,
             fb_info,
@@ -165,6 +173,10 @@ impl SkiaRenderer for OpenGLSkiaRenderer {
         self.window.as_ref().unwrap()
     }
 
+    fn gr_context(&mut self) -> &mut DirectContext {
+        &mut self.gr_context
+    }
+
     fn flush(&mut self) {
         {
             tracy_gpu_zone!("skia flush");
@@ -196,18 +208,23 @@ impl SkiaRenderer for OpenGLSkiaRenderer {
         );
     }
 
+    fn refresh_swap_interval(&mut self) {
+        // GLX syncs the swaps to the monitor the window was on when the interval was set
+        set_swap_interval(&self.window_surface, &self.context, self.vsync);
+    }
+
     #[allow(unused_variables)]
     fn create_vsync(&self, proxy: EventLoopProxy<UserEvent>) -> VSync {
         #[cfg(target_os = "linux")]
         if env::var("WAYLAND_DISPLAY").is_ok() {
             VSync::WinitThrottling()
         } else {
-            VSync::Opengl()
+            VSync::Opengl(self.window().current_monitor())
         }
 
         #[cfg(target_os = "windows")]
         {
-            VSync::WindowsDwm(VSyncWinDwm::new(proxy))
+            VSync::WindowsDwm(VSyncWinDwm::new(self.window(), proxy))
         }
 
         #[cfg(target_os = "macos")]
@@ -0,0 +1,301 @@
+//! Simple vector drawings of plugins, sent with `neovide.draw(layer, primitives)`. Each call
+//! replaces what the layer showed, and an empty list removes it. The layers are drawn over the
+//! grid, under the command line and the popups, in the order of their names.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, OnceLock},
+};
+
+use log::warn;
+use parking_lot::Mutex;
+use rmpv::Value;
+use skia_safe::{Canvas, Color, Color4f, Paint, PaintStyle};
+
+use crate::{
+    editor::{Colors, Style},
+    profiling::tracy_zone,
+    renderer::{text_selection::SelectableText, GridRenderer},
+    units::{GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Plugins drawing more than this get their layers cut short, the frames would slow down first.
+const MAX_PRIMITIVES: usize = 4096;
+const MAX_LAYERS: usize = 32;
+const MAX_TEXT_LENGTH: usize = 256;
+
+/// The layers drawn since the window last took them. Only the last drawing of a layer is kept, so
+/// that a plugin drawing faster than the frames doesn't pile up events.
+static PENDING_LAYERS: OnceLock<Mutex<HashMap<String, Vec<OverlayPrimitive>>>> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverlayUnits {
+    /// Columns and lines of the screen grid, and radiuses in lines.
+    Grid,
+    /// Logical pixels from the top left corner of the grid.
+    Pixel,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OverlayShape {
+    Line {
+        from: (f32, f32),
+        to: (f32, f32),
+    },
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    Circle {
+        x: f32,
+        y: f32,
+        radius: f32,
+    },
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverlayPrimitive {
+    pub shape: OverlayShape,
+    pub units: OverlayUnits,
+    /// The foreground color of the grid when not given.
+    pub color: Option<Color>,
+    pub fill: bool,
+    /// In logical pixels.
+    pub stroke_width: f32,
+}
+
+/// Reads `#rrggbb` or `#rrggbbaa`.
+fn parse_color(color: &str) -> Option<Color> {
+    let hex = color.strip_prefix('#')?;
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some(Color::new(0xff000000 | value)),
+        8 => Some(Color::new(value.rotate_right(8))),
+        _ => None,
+    }
+}
+
+impl OverlayPrimitive {
+    /// Reads a `{type, x, y, ...}` dictionary sent by a plugin, `None` when it isn't one of the
+    /// known primitives.
+    pub fn parse(value: &Value) -> Option<Self> {
+        let map = value.as_map()?;
+        let get = |name: &str| {
+            map.iter()
+                .find(|(key, _)| key.as_str() == Some(name))
+                .map(|(_, value)| value)
+        };
+        let number = |name: &str| get(name).and_then(Value::as_f64).map(|value| value as f32);
+
+        let shape = match get("type")?.as_str()? {
+            "line" => OverlayShape::Line {
+                from: (number("x1")?, number("y1")?),
+                to: (number("x2")?, number("y2")?),
+            },
+            "rect" => OverlayShape::Rect {
+                x: number("x")?,
+                y: number("y")?,
+                width: number("width")?,
+                height: number("height")?,
+            },
+            "circle" => OverlayShape::Circle {
+                x: number("x")?,
+                y: number("y")?,
+                radius: number("radius")?,
+            },
+            "text" => OverlayShape::Text {
+                x: number("x")?,
+                y: number("y")?,
+                text: get("text")?
+                    .as_str()?
+                    .chars()
+                    .take(MAX_TEXT_LENGTH)
+                    .collect(),
+            },
+            _ => return None,
+        };
+        let units = match get("units").and_then(Value::as_str) {
+            Some("pixel") => OverlayUnits::Pixel,
+            _ => OverlayUnits::Grid,
+        };
+        Some(Self {
+            shape,
+            units,
+            color: get("color").and_then(Value::as_str).and_then(parse_color),
+            fill: get("fill").and_then(Value::as_bool).unwrap_or(false),
+            stroke_width: number("stroke_width").unwrap_or(1.0),
+        })
+    }
+}
+
+/// Queues the drawing of a layer, and returns whether the window has to be told. It's only told
+/// once until it takes the layers.
+pub fn queue_layer(layer: String, mut primitives: Vec<OverlayPrimitive>) -> bool {
+    if primitives.len() > MAX_PRIMITIVES {
+        warn!(
+            "Overlay layer {layer} has more than {MAX_PRIMITIVES} primitives, the rest are dropped"
+        );
+        primitives.truncate(MAX_PRIMITIVES);
+    }
+    let mut pending = PENDING_LAYERS.get_or_init(Mutex::default).lock();
+    let notify = pending.is_empty();
+    pending.insert(layer, primitives);
+    notify
+}
+
+pub fn take_pending_layers() -> HashMap<String, Vec<OverlayPrimitive>> {
+    PENDING_LAYERS
+        .get()
+        .map(|pending| std::mem::take(&mut *pending.lock()))
+        .unwrap_or_default()
+}
+
+pub struct OverlayRenderer {
+    layers: BTreeMap<String, Vec<OverlayPrimitive>>,
+}
+
+impl OverlayRenderer {
+    pub fn new() -> Self {
+        Self {
+            layers: BTreeMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, layers: HashMap<String, Vec<OverlayPrimitive>>) {
+        for (name, primitives) in layers {
+            if primitives.is_empty() {
+                self.layers.remove(&name);
+            } else if self.layers.len() < MAX_LAYERS || self.layers.contains_key(&name) {
+                self.layers.insert(name, primitives);
+            } else {
+                warn!("Overlay layer {name} ignored, there are already {MAX_LAYERS} layers");
+            }
+        }
+    }
+
+    pub fn draw(
+        &self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        scale_factor: f32,
+        grid_renderer: &mut GridRenderer,
+        selectable_text: &mut SelectableText,
+    ) {
+        if self.layers.is_empty() {
+            return;
+        }
+        tracy_zone!("overlay_draw");
+        let grid_scale = grid_renderer.grid_scale;
+        let default_style = grid_renderer.default_style.clone();
+        let default_color = default_style.foreground(&default_style.colors).to_color();
+
+        root_canvas.save();
+        root_canvas.translate((region.min.x, region.min.y));
+        for primitive in self.layers.values().flatten() {
+            let (scale_x, scale_y) = match primitive.units {
+                OverlayUnits::Grid => (grid_scale.width(), grid_scale.height()),
+                OverlayUnits::Pixel => (scale_factor, scale_factor),
+            };
+            let point = |x: f32, y: f32| (x * scale_x, y * scale_y);
+            let color = primitive.color.unwrap_or(default_color);
+            let mut paint = Paint::new(Color4f::from(color), None);
+            paint.set_anti_alias(true);
+            paint.set_stroke_width(primitive.stroke_width * scale_factor);
+            paint.set_style(if primitive.fill {
+                PaintStyle::Fill
+            } else {
+                PaintStyle::Stroke
+            });
+
+            match &primitive.shape {
+                OverlayShape::Line { from, to } => {
+                    root_canvas.draw_line(point(from.0, from.1), point(to.0, to.1), &paint);
+                }
+                OverlayShape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    let (left, top) = point(*x, *y);
+                    let (width, height) = point(*width, *height);
+                    root_canvas
+                        .draw_rect(skia_safe::Rect::from_xywh(left, top, width, height), &paint);
+                }
+                OverlayShape::Circle { x, y, radius } => {
+                    root_canvas.draw_circle(point(*x, *y), radius * scale_y, &paint);
+                }
+                OverlayShape::Text { x, y, text } => {
+                    let style = Some(Arc::new(Style::new(Colors::new(
+                        Some(Color4f::from(color)),
+                        None,
+                        None,
+                    ))));
+                    let (left, top) = point(*x, *y);
+                    selectable_text.begin_block();
+                    selectable_text.add_line(
+                        PixelPos::new(region.min.x + left, region.min.y + top),
+                        PixelSize::new(grid_scale.width(), grid_scale.height()),
+                        text,
+                    );
+                    root_canvas.save();
+                    root_canvas.translate((left, top));
+                    grid_renderer.draw_foreground(
+                        root_canvas,
+                        text,
+                        GridPos::new(0, 0),
+                        text.chars().count() as i32,
+                        &style,
+                    );
+                    root_canvas.restore();
+                }
+            }
+        }
+        root_canvas.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitives_are_read_from_dictionaries() {
+        let value = Value::Map(vec![
+            ("type".into(), "circle".into()),
+            ("x".into(), 10.into()),
+            ("y".into(), Value::F64(2.5)),
+            ("radius".into(), 3.into()),
+            ("units".into(), "pixel".into()),
+            ("color".into(), "#ff800040".into()),
+            ("fill".into(), true.into()),
+        ]);
+        assert_eq!(
+            OverlayPrimitive::parse(&value),
+            Some(OverlayPrimitive {
+                shape: OverlayShape::Circle {
+                    x: 10.0,
+                    y: 2.5,
+                    radius: 3.0,
+                },
+                units: OverlayUnits::Pixel,
+                color: Some(Color::from_argb(0x40, 0xff, 0x80, 0x00)),
+                fill: true,
+                stroke_width: 1.0,
+            })
+        );
+        let unknown = Value::Map(vec![("type".into(), "polygon".into())]);
+        assert_eq!(OverlayPrimitive::parse(&unknown), None);
+        assert_eq!(
+            parse_color("#102030"),
+            Some(Color::from_rgb(0x10, 0x20, 0x30))
+        );
+    }
+}
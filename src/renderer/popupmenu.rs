@@ -0,0 +1,514 @@
+use std::sync::Arc;
+
+use skia_safe::{Canvas, Paint, Rect};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    editor::{Colors, PopupMenu, PopupMenuEntry, Style},
+    profiling::tracy_zone,
+    renderer::{
+        animation_utils::CriticallyDampedSpringAnimation,
+        cmdline_renderer::{begin_floating_box, end_floating_box, floating_box_radius},
+        GridRenderer, RendererSettings,
+    },
+    settings::*,
+    units::{GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Columns of padding on each side of the items.
+const PADDING: usize = 1;
+const ELLIPSIS: &str = "…";
+
+#[derive(Clone, SettingGroup)]
+#[setting_prefix = "popupmenu"]
+pub struct PopupMenuSettings {
+    pub enabled: bool,
+    max_items: u32,
+    kind_icons: bool,
+    animation_length: f32,
+}
+
+impl Default for PopupMenuSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_items: 12,
+            kind_icons: true,
+            animation_length: 0.1,
+        }
+    }
+}
+
+/// Nerd Font codicons for the LSP completion kinds, and the single letter kinds of Vim.
+fn kind_icon(kind: &str) -> Option<&'static str> {
+    let icon = match kind {
+        "Text" => "\u{ea93}",
+        "Method" | "Function" | "Constructor" | "f" => "\u{ea8c}",
+        "Field" | "m" => "\u{eb5f}",
+        "Variable" | "v" => "\u{ea88}",
+        "Class" => "\u{eb5b}",
+        "Interface" => "\u{eb61}",
+        "Module" => "\u{ea8b}",
+        "Property" => "\u{eb65}",
+        "Unit" => "\u{ea96}",
+        "Value" | "Enum" | "EnumMember" => "\u{ea95}",
+        "Keyword" => "\u{eb62}",
+        "Snippet" => "\u{eb66}",
+        "Color" => "\u{eb5c}",
+        "File" => "\u{eb60}",
+        "Reference" => "\u{ea94}",
+        "Folder" => "\u{ea83}",
+        "Constant" | "d" => "\u{eb5d}",
+        "Struct" | "t" => "\u{ea91}",
+        "Event" => "\u{ea86}",
+        "Operator" => "\u{eb64}",
+        "TypeParameter" => "\u{ea92}",
+        _ => return None,
+    };
+    Some(icon)
+}
+
+/// Nerd Font devicons for file name completions.
+fn file_icon(word: &str) -> Option<&'static str> {
+    if word.ends_with('/') || word.ends_with('\\') {
+        return Some("\u{e5ff}");
+    }
+    let (_, extension) = word.rsplit_once('.')?;
+    let icon = match extension.to_lowercase().as_str() {
+        "rs" => "\u{e7a8}",
+        "py" => "\u{e606}",
+        "js" | "mjs" | "cjs" => "\u{e74e}",
+        "ts" | "tsx" => "\u{e628}",
+        "lua" => "\u{e620}",
+        "vim" => "\u{e62b}",
+        "md" => "\u{e73e}",
+        "json" => "\u{e60b}",
+        "toml" | "yaml" | "yml" | "ini" | "conf" => "\u{e615}",
+        "c" | "h" => "\u{e61e}",
+        "cpp" | "cc" | "hpp" => "\u{e61d}",
+        "go" => "\u{e626}",
+        "html" => "\u{e736}",
+        "css" | "scss" => "\u{e749}",
+        "sh" | "bash" | "zsh" | "fish" => "\u{e795}",
+        _ => "\u{f15b}",
+    };
+    Some(icon)
+}
+
+/// What is drawn for an item, in cells.
+#[derive(Debug, PartialEq)]
+struct ItemColumns {
+    icon: Option<String>,
+    kind: String,
+}
+
+fn item_columns(entry: &PopupMenuEntry, kind_icons: bool) -> ItemColumns {
+    if !kind_icons {
+        return ItemColumns {
+            icon: None,
+            kind: entry.kind.clone(),
+        };
+    }
+    // Kinds that are already decorated by a plugin, like " Function", keep their glyph
+    if let Some(first) = entry.kind.graphemes(true).next() {
+        if !first.is_ascii() {
+            return ItemColumns {
+                icon: Some(first.to_string()),
+                kind: entry.kind[first.len()..].trim().to_string(),
+            };
+        }
+    }
+    let icon = kind_icon(&entry.kind).or_else(|| match entry.kind.as_str() {
+        "" => file_icon(&entry.word),
+        _ => None,
+    });
+    ItemColumns {
+        icon: icon.map(str::to_string),
+        // The icon replaces the kind name
+        kind: if icon.is_some() {
+            String::new()
+        } else {
+            entry.kind.clone()
+        },
+    }
+}
+
+pub(super) fn text_width(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Cuts `text` to `width` cells, ending it with an ellipsis when it doesn't fit.
+pub(super) fn truncate(text: &str, width: usize) -> String {
+    if text_width(text) <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut truncated = text.graphemes(true).take(width - 1).collect::<String>();
+    truncated.push_str(ELLIPSIS);
+    truncated
+}
+
+/// The first visible item, so that the selected one stays in view.
+pub(super) fn scroll_top(
+    current: usize,
+    selected: Option<usize>,
+    visible: usize,
+    count: usize,
+) -> usize {
+    let mut top = current.min(count.saturating_sub(visible));
+    if let Some(selected) = selected {
+        if selected < top {
+            top = selected;
+        } else if selected >= top + visible {
+            top = selected + 1 - visible;
+        }
+    }
+    top
+}
+
+/// Draws the completion menu of Neovim's externalized popup menu next to the text being
+/// completed.
+pub struct PopupMenuRenderer {
+    menu: Option<PopupMenu>,
+    scroll_top: usize,
+    /// Offset of the selection highlight from the selected item, in rows, animated towards 0.
+    selection_animation: CriticallyDampedSpringAnimation,
+
+    settings: Arc<Settings>,
+}
+
+impl PopupMenuRenderer {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            menu: None,
+            scroll_top: 0,
+            selection_animation: CriticallyDampedSpringAnimation::new(),
+            settings,
+        }
+    }
+
+    pub fn menu(&self) -> Option<&PopupMenu> {
+        self.menu.as_ref()
+    }
+
+    pub fn set_menu(&mut self, menu: Option<PopupMenu>) {
+        let previous_selection = self
+            .menu
+            .as_ref()
+            .filter(|previous| Some(previous.anchor) == menu.as_ref().map(|menu| menu.anchor))
+            .and_then(|previous| previous.selected);
+        match (
+            previous_selection,
+            menu.as_ref().and_then(|menu| menu.selected),
+        ) {
+            (Some(previous), Some(selected)) => {
+                self.selection_animation.position += previous as f32 - selected as f32;
+            }
+            _ => self.selection_animation.reset(),
+        }
+        if menu.is_none() {
+            self.scroll_top = 0;
+        }
+        self.menu = menu;
+    }
+
+    pub fn animate(&mut self, dt: f32) -> bool {
+        if self.menu.is_none() {
+            return false;
+        }
+        let animation_length = self.settings.get::<PopupMenuSettings>().animation_length;
+        self.selection_animation.update(dt, animation_length)
+    }
+
+    /// Draws the menu below `anchor`, or above it when there isn't enough room, keeping it
+    /// inside `region`.
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        anchor: PixelRect<f32>,
+        region: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("popupmenu_draw");
+        let Some(menu) = &self.menu else {
+            return;
+        };
+        if menu.entries.is_empty() {
+            return;
+        }
+        let settings = self.settings.get::<PopupMenuSettings>();
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+
+        let space_below = ((region.max.y - anchor.max.y) / line_height).floor() as usize;
+        let space_above = ((anchor.min.y - region.min.y) / line_height).floor() as usize;
+        let wanted_rows = menu.entries.len().min(settings.max_items.max(1) as usize);
+        let below = space_below >= wanted_rows || space_below >= space_above;
+        let visible_rows = wanted_rows.min(if below { space_below } else { space_above });
+        if visible_rows == 0 {
+            return;
+        }
+        self.scroll_top = scroll_top(
+            self.scroll_top,
+            menu.selected,
+            visible_rows,
+            menu.entries.len(),
+        );
+        let has_scrollbar = visible_rows < menu.entries.len();
+
+        let columns = menu
+            .entries
+            .iter()
+            .map(|entry| item_columns(entry, settings.kind_icons))
+            .collect::<Vec<_>>();
+        let icon_width = if columns.iter().any(|column| column.icon.is_some()) {
+            2
+        } else {
+            0
+        };
+        let kind_width = columns
+            .iter()
+            .map(|column| text_width(&column.kind))
+            .max()
+            .unwrap_or(0);
+        let mut menu_width = menu
+            .entries
+            .iter()
+            .map(|entry| text_width(&entry.menu))
+            .max()
+            .unwrap_or(0);
+        let mut word_width = menu
+            .entries
+            .iter()
+            .map(|entry| text_width(&entry.word))
+            .max()
+            .unwrap_or(0);
+        let gap = |width: usize| if width > 0 { width + 1 } else { 0 };
+        let max_columns = ((region.max.x - region.min.x) / cell_width).floor() as usize;
+        let fixed_width = 2 * PADDING + icon_width + gap(kind_width) + usize::from(has_scrollbar);
+        let overflow = (fixed_width + word_width + gap(menu_width)).saturating_sub(max_columns);
+        // Shrink the extra text first, then the words
+        let menu_shrink = overflow.min(menu_width);
+        menu_width -= menu_shrink;
+        word_width = word_width.saturating_sub(overflow - menu_shrink).max(1);
+        let total_columns = fixed_width + word_width + gap(menu_width);
+
+        let size = PixelSize::new(
+            total_columns as f32 * cell_width,
+            visible_rows as f32 * line_height,
+        );
+        // Line the words up with the completed text
+        let x = (anchor.min.x - (PADDING + icon_width) as f32 * cell_width)
+            .min(region.max.x - size.width)
+            .max(region.min.x);
+        let y = if below {
+            anchor.max.y
+        } else {
+            anchor.min.y - size.height
+        };
+        let origin = PixelPos::new(x.round(), y.round());
+        let rect = PixelRect::from_origin_and_size(origin, size);
+
+        let default_style = grid_renderer.default_style.clone();
+        let default_colors = &default_style.colors;
+        let normal_style = menu.styles.normal.clone();
+        let selected_style = menu.styles.selected.clone().or_else(|| {
+            let mut style = Style::new(Colors::new(None, None, None));
+            style.reverse = true;
+            Some(Arc::new(style))
+        });
+        let background_of = |style: &Option<Arc<Style>>| {
+            style
+                .as_deref()
+                .unwrap_or(&default_style)
+                .background(default_colors)
+        };
+
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let blur_amount = renderer_settings.floating_blur.then_some((
+            renderer_settings.floating_blur_amount_x,
+            renderer_settings.floating_blur_amount_y,
+        ));
+        begin_floating_box(
+            root_canvas,
+            &rect,
+            radius,
+            blur_amount,
+            1.0,
+            background_of(&normal_style).to_color(),
+        );
+        root_canvas.translate((origin.x, origin.y));
+
+        let list_width = (total_columns - usize::from(has_scrollbar)) as f32 * cell_width;
+        if let Some(selected) = menu.selected {
+            let row = selected as f32 - self.scroll_top as f32 + self.selection_animation.position;
+            let paint = Paint::new(background_of(&selected_style), None);
+            root_canvas.draw_rect(
+                Rect::from_xywh(0.0, row * line_height, list_width, line_height),
+                &paint,
+            );
+        }
+
+        let styles = &menu.styles;
+        let visible_entries = menu
+            .entries
+            .iter()
+            .zip(&columns)
+            .enumerate()
+            .skip(self.scroll_top)
+            .take(visible_rows);
+        for (row, (index, (entry, column))) in visible_entries.enumerate() {
+            let is_selected = menu.selected == Some(index);
+            let (item_style, matched_style, kind_style, extra_style) = if is_selected {
+                (
+                    &selected_style,
+                    &styles.matched_selected,
+                    &styles.kind_selected,
+                    &styles.extra_selected,
+                )
+            } else {
+                (&normal_style, &styles.matched, &styles.kind, &styles.extra)
+            };
+            let row = row as i32;
+            let mut x = PADDING as i32;
+
+            if let Some(icon) = &column.icon {
+                grid_renderer.draw_foreground(
+                    root_canvas,
+                    icon,
+                    GridPos::new(x, row),
+                    1,
+                    kind_style,
+                );
+            }
+            x += icon_width as i32;
+
+            // Draw the word in runs of matched and unmatched characters
+            let word = truncate(&entry.word, word_width);
+            let mut run = String::new();
+            let mut run_start = x;
+            let mut run_matched = false;
+            for (grapheme_index, grapheme) in word.graphemes(true).enumerate() {
+                let matched = entry.matches.contains(&grapheme_index);
+                if matched != run_matched && !run.is_empty() {
+                    let style = if run_matched {
+                        matched_style
+                    } else {
+                        item_style
+                    };
+                    let width = text_width(&run) as i32;
+                    grid_renderer.draw_foreground(
+                        root_canvas,
+                        &run,
+                        GridPos::new(run_start, row),
+                        width,
+                        style,
+                    );
+                    run_start += width;
+                    run.clear();
+                }
+                run_matched = matched;
+                run.push_str(grapheme);
+            }
+            if !run.is_empty() {
+                let style = if run_matched {
+                    matched_style
+                } else {
+                    item_style
+                };
+                grid_renderer.draw_foreground(
+                    root_canvas,
+                    &run,
+                    GridPos::new(run_start, row),
+                    text_width(&run) as i32,
+                    style,
+                );
+            }
+            x += word_width as i32 + 1;
+
+            if kind_width > 0 {
+                grid_renderer.draw_foreground(
+                    root_canvas,
+                    &column.kind,
+                    GridPos::new(x, row),
+                    kind_width as i32,
+                    kind_style,
+                );
+                x += kind_width as i32 + 1;
+            }
+            if menu_width > 0 {
+                grid_renderer.draw_foreground(
+                    root_canvas,
+                    &truncate(&entry.menu, menu_width),
+                    GridPos::new(x, row),
+                    menu_width as i32,
+                    extra_style,
+                );
+            }
+        }
+
+        if has_scrollbar {
+            let count = menu.entries.len() as f32;
+            let track = Rect::from_xywh(list_width, 0.0, cell_width, size.height);
+            root_canvas.draw_rect(track, &Paint::new(background_of(&styles.scrollbar), None));
+            let thumb_height = (size.height * visible_rows as f32 / count).max(line_height / 2.0);
+            let thumb_top = (size.height - thumb_height) * self.scroll_top as f32
+                / (count - visible_rows as f32);
+            let thumb = Rect::from_xywh(list_width, thumb_top, cell_width, thumb_height);
+            root_canvas.draw_rect(thumb, &Paint::new(background_of(&styles.thumb), None));
+        }
+
+        end_floating_box(root_canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(word: &str, kind: &str) -> PopupMenuEntry {
+        PopupMenuEntry {
+            word: word.to_string(),
+            kind: kind.to_string(),
+            menu: String::new(),
+            matches: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn kinds_are_replaced_by_icons() {
+        let columns = item_columns(&entry("main", "Function"), true);
+        assert_eq!(columns.icon.as_deref(), Some("\u{ea8c}"));
+        assert_eq!(columns.kind, "");
+
+        let columns = item_columns(&entry("main.rs", ""), true);
+        assert_eq!(columns.icon.as_deref(), Some("\u{e7a8}"));
+
+        let columns = item_columns(&entry("main", "\u{ea8c} Function"), true);
+        assert_eq!(columns.icon.as_deref(), Some("\u{ea8c}"));
+        assert_eq!(columns.kind, "Function");
+
+        let columns = item_columns(&entry("main", "Function"), false);
+        assert_eq!(columns.icon, None);
+        assert_eq!(columns.kind, "Function");
+    }
+
+    #[test]
+    fn selection_stays_visible() {
+        assert_eq!(scroll_top(0, Some(3), 5, 10), 0);
+        assert_eq!(scroll_top(0, Some(7), 5, 10), 3);
+        assert_eq!(scroll_top(3, Some(1), 5, 10), 1);
+        assert_eq!(scroll_top(8, None, 5, 6), 1);
+    }
+
+    #[test]
+    fn long_text_is_truncated() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("much too long", 5), "much…");
+        assert_eq!(truncate("long", 0), "");
+    }
+}
@@ -1,44 +1,67 @@
+use std::cell::OnceCell;
 use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::{
     profiling::tracy_zone,
     renderer::{animation_utils::lerp, fonts::font_loader::*, RendererSettings},
-    settings::Settings,
+    settings::{CachedSettings, Settings},
 };
 use skia_safe::{Canvas, Color, Paint, Point, Rect, Size};
 
 const FRAMETIMES_COUNT: usize = 48;
+// A frame taking this many times the vsync interval missed at least one vsync
+const LATE_FRAME_FACTOR: f32 = 1.5;
+const TIMELINE_DURATION: Duration = Duration::from_secs(10);
+const TIMELINE_HEIGHT: f32 = 16.0;
 
 pub struct Profiler {
-    pub font: Arc<FontPair>,
+    /// Loaded the first time the profiler is shown, the system fonts are slow to look up.
+    font: OnceCell<Arc<FontPair>>,
+    font_size: f32,
     pub position: Point,
     pub size: Size,
     pub frametimes: VecDeque<f32>,
+    late_frames: VecDeque<Instant>,
+    late_frame_count: u64,
 
-    settings: Arc<Settings>,
+    settings: CachedSettings<RendererSettings>,
 }
 
 impl Profiler {
     pub fn new(font_size: f32, settings: Arc<Settings>) -> Self {
-        let font_key = FontKey::default();
-        let mut font_loader = FontLoader::new(font_size);
-        let font = font_loader.get_or_load(&font_key).unwrap();
         Self {
-            font,
+            font: OnceCell::new(),
+            font_size,
             position: Point::new(32.0, 32.0),
             size: Size::new(200.0, 120.0),
             frametimes: VecDeque::with_capacity(FRAMETIMES_COUNT),
+            late_frames: VecDeque::new(),
+            late_frame_count: 0,
 
-            settings,
+            settings: CachedSettings::new(settings),
         }
     }
 
-    pub fn draw(&mut self, root_canvas: &Canvas, dt: f32) {
+    fn font(&self) -> &FontPair {
+        self.font.get_or_init(|| {
+            let mut font_loader = FontLoader::new(self.font_size);
+            font_loader.get_or_load(&FontKey::default()).unwrap()
+        })
+    }
+
+    /// Draws the HUD, `expected_dt` is the vsync interval when the frame before was drawn right
+    /// after another one, and `dt` can be compared to it.
+    pub fn draw(&mut self, root_canvas: &Canvas, dt: f32, expected_dt: Option<f32>) {
         tracy_zone!("profiler_draw");
-        if !self.settings.get::<RendererSettings>().profiler {
+        let settings = self.settings.get();
+        if !settings.profiler && !settings.profiler_dropped_frames {
             return;
         }
+        if settings.profiler_dropped_frames {
+            self.record_late_frame(dt, expected_dt);
+        }
 
         root_canvas.save();
         let rect = self.get_rect();
@@ -55,11 +78,11 @@ impl Profiler {
         let color = Color::from_argb(255, 0, 255, 0);
         paint.set_color(color);
         let mut text_position = self.position;
-        text_position.y += self.font.skia_font.size();
+        text_position.y += self.font().skia_font.size();
         root_canvas.draw_str(
             format!("{:.0}FPS", 1.0 / dt.max(f32::EPSILON)),
             text_position,
-            &self.font.skia_font,
+            &self.font().skia_font,
             &paint,
         );
 
@@ -69,10 +92,69 @@ impl Profiler {
         }
 
         self.draw_graph(root_canvas);
+        if settings.profiler_dropped_frames {
+            self.draw_timeline(root_canvas);
+        }
 
         root_canvas.restore();
     }
 
+    fn record_late_frame(&mut self, dt: f32, expected_dt: Option<f32>) {
+        let now = Instant::now();
+        while self
+            .late_frames
+            .front()
+            .is_some_and(|time| now - *time > TIMELINE_DURATION)
+        {
+            self.late_frames.pop_front();
+        }
+
+        let Some(expected_dt) = expected_dt else {
+            return;
+        };
+        if is_late_frame(dt, expected_dt) {
+            self.late_frames.push_back(now);
+            self.late_frame_count += 1;
+            log::warn!(
+                "Late frame: {:.1}ms instead of {:.1}ms",
+                dt * 1000.0,
+                expected_dt * 1000.0
+            );
+        }
+    }
+
+    fn draw_timeline(&self, root_canvas: &Canvas) {
+        let rect = self.get_rect();
+        let strip = Rect::new(
+            rect.left,
+            rect.bottom - TIMELINE_HEIGHT,
+            rect.right,
+            rect.bottom,
+        );
+
+        let mut paint = Paint::default();
+        paint.set_color(Color::from_argb(255, 60, 60, 60));
+        root_canvas.draw_rect(strip, &paint);
+
+        // The newest frames are on the right
+        let now = Instant::now();
+        paint.set_color(Color::from_argb(255, 255, 40, 40));
+        paint.set_stroke_width(2.0);
+        for time in &self.late_frames {
+            let age = (now - *time).as_secs_f32() / TIMELINE_DURATION.as_secs_f32();
+            let x = lerp(strip.right, strip.left, age.min(1.0));
+            root_canvas.draw_line((x, strip.top), (x, strip.bottom), &paint);
+        }
+
+        paint.set_color(Color::from_argb(255, 0, 255, 0));
+        root_canvas.draw_str(
+            format!("late: {}", self.late_frame_count),
+            (strip.right - 64.0, strip.top - 4.0),
+            &self.font().skia_font,
+            &paint,
+        );
+    }
+
     fn draw_graph(&self, root_canvas: &Canvas) {
         let mut paint = Paint::default();
         let color = Color::from_argb(255, 0, 100, 200);
@@ -94,6 +176,9 @@ impl Profiler {
 
         let mut rect = self.get_rect();
         rect.bottom -= 8.0; // bottom margin
+        if self.settings.get().profiler_dropped_frames {
+            rect.bottom -= TIMELINE_HEIGHT;
+        }
 
         let graph_height = 80.0;
 
@@ -120,29 +205,50 @@ impl Profiler {
         root_canvas.draw_str(
             format!("min: {min_ft:.1}ms"),
             (rect.left, rect.bottom),
-            &self.font.skia_font,
+            &self.font().skia_font,
             &paint,
         );
         root_canvas.draw_str(
             format!("avg: {avg:.1}ms"),
             (rect.left, rect.bottom - graph_height * 0.5),
-            &self.font.skia_font,
+            &self.font().skia_font,
             &paint,
         );
         root_canvas.draw_str(
             format!("max: {max_ft:.1}ms"),
             (rect.left, rect.bottom - graph_height),
-            &self.font.skia_font,
+            &self.font().skia_font,
             &paint,
         );
     }
 
     fn get_rect(&self) -> Rect {
+        let mut height = self.size.height;
+        if self.settings.get().profiler_dropped_frames {
+            height += TIMELINE_HEIGHT;
+        }
         Rect::new(
             self.position.x,
             self.position.y,
             self.position.x + self.size.width,
-            self.position.y + self.size.height,
+            self.position.y + height,
         )
     }
 }
+
+fn is_late_frame(dt: f32, expected_dt: f32) -> bool {
+    dt > expected_dt * LATE_FRAME_FACTOR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_late_frame() {
+        let expected_dt = 1.0 / 60.0;
+        assert!(!is_late_frame(expected_dt, expected_dt));
+        assert!(!is_late_frame(expected_dt * 1.2, expected_dt));
+        assert!(is_late_frame(expected_dt * 2.0, expected_dt));
+    }
+}
@@ -1,17 +1,27 @@
 use std::{cell::RefCell, rc::Rc, sync::Arc};
 
-use skia_safe::{Canvas, Color, Matrix, Picture, PictureRecorder, Rect};
+use skia_safe::{Canvas, ClipOp, Color, Matrix, Picture, PictureRecorder, Rect};
 
 use crate::{
     cmd_line::CmdLineSettings,
     editor::{AnchorInfo, SortOrder, Style, WindowType},
     profiling::{tracy_plot, tracy_zone},
-    renderer::{animation_utils::*, GridRenderer, RendererSettings},
+    renderer::{
+        animation_utils::*,
+        mouse_selection::{fragments_line_at, fragments_text},
+        GridRenderer, RendererSettings,
+    },
     settings::Settings,
     units::{to_skia_rect, GridPos, GridRect, GridScale, GridSize, PixelRect, PixelVec},
     utils::RingBuffer,
 };
 
+// The overscroll only follows a fraction of the scrolled distance, and is limited, so that it
+// feels like pulling against a spring
+const OVERSCROLL_RESISTANCE: f32 = 0.3;
+const MAX_OVERSCROLL_LINES: f32 = 3.0;
+const OVERSCROLL_ANIMATION_LENGTH: f32 = 0.3;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct LineFragment {
     pub text: String,
@@ -24,6 +34,14 @@ pub struct LineFragment {
 pub struct ViewportMargins {
     pub top: u64,
     pub bottom: u64,
+    pub left: u64,
+    pub right: u64,
+}
+
+impl ViewportMargins {
+    fn has_sides(&self) -> bool {
+        self.left > 0 || self.right > 0
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -31,6 +49,7 @@ pub enum WindowDrawCommand {
     Position {
         grid_position: (f64, f64),
         grid_size: (u64, u64),
+        layout_size: (u64, u64),
         anchor_info: Option<AnchorInfo>,
         window_type: WindowType,
     },
@@ -52,6 +71,8 @@ pub enum WindowDrawCommand {
     Close,
     Viewport {
         scroll_delta: f64,
+        at_top: bool,
+        at_bottom: bool,
     },
     ViewportMargins {
         top: u64,
@@ -69,6 +90,11 @@ struct Line {
     foreground_picture: Option<Picture>,
     has_transparency: bool,
     is_valid: bool,
+    /// Whether the line had the cursor on it, and is highlighted by `'cursorline'`.
+    has_cursor_line: bool,
+    /// The line scrolled out of the window, so that the cursor isn't on it anymore, even though
+    /// the scroll animation still shows it.
+    scrolled_out: bool,
 }
 
 pub struct RenderedWindow {
@@ -79,18 +105,31 @@ pub struct RenderedWindow {
     window_type: WindowType,
 
     pub grid_size: GridSize<u32>,
+    /// The size Neovim laid the window out with, in cells of the main font.
+    layout_size: GridSize<u32>,
+    /// The cell size of the font the window content is drawn with, when it differs from the
+    /// main font.
+    pub font_scale: Option<GridScale>,
+    requested_grid_size: Option<GridSize<u32>>,
 
     scrollback_lines: RingBuffer<Option<Rc<RefCell<Line>>>>,
     actual_lines: RingBuffer<Option<Rc<RefCell<Line>>>>,
     scroll_delta: isize,
     pub viewport_margins: ViewportMargins,
+    viewport_at_top: bool,
+    viewport_at_bottom: bool,
 
     grid_start_position: GridPos<f32>,
     pub grid_current_position: GridPos<f32>,
     grid_destination: GridPos<f32>,
     position_t: f32,
 
+    grid_start_size: GridSize<f32>,
+    grid_current_size: GridSize<f32>,
+    size_t: f32,
+
     pub scroll_animation: CriticallyDampedSpringAnimation,
+    overscroll_animation: CriticallyDampedSpringAnimation,
 }
 
 #[derive(Clone, Debug)]
@@ -121,26 +160,70 @@ impl RenderedWindow {
             window_type: WindowType::Editor,
 
             grid_size,
+            layout_size: grid_size,
+            font_scale: None,
+            requested_grid_size: None,
 
             actual_lines: RingBuffer::new(grid_size.height as usize, None),
             scrollback_lines: RingBuffer::new(2 * grid_size.height as usize, None),
             scroll_delta: 0,
-            viewport_margins: ViewportMargins { top: 0, bottom: 0 },
+            viewport_margins: ViewportMargins {
+                top: 0,
+                bottom: 0,
+                left: 0,
+                right: 0,
+            },
+            viewport_at_top: false,
+            viewport_at_bottom: false,
 
             grid_start_position: grid_position,
             grid_current_position: grid_position,
             grid_destination: grid_position,
             position_t: 2.0, // 2.0 is out of the 0.0 to 1.0 range and stops animation.
 
+            grid_start_size: GridSize::ZERO,
+            grid_current_size: GridSize::ZERO,
+            size_t: 2.0,
+
             scroll_animation: CriticallyDampedSpringAnimation::new(),
+            overscroll_animation: CriticallyDampedSpringAnimation::new(),
         }
     }
 
     pub fn pixel_region(&self, grid_scale: GridScale) -> PixelRect<f32> {
-        GridRect::<f32>::from_origin_and_size(
-            self.grid_current_position,
-            self.grid_size.try_cast().unwrap(),
-        ) * grid_scale
+        // Windows with their own font still occupy the space Neovim laid them out in
+        let size = if self.font_scale.is_some() {
+            self.layout_size.try_cast().unwrap()
+        } else {
+            self.grid_current_size
+        };
+        GridRect::<f32>::from_origin_and_size(self.grid_current_position, size) * grid_scale
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        self.window_type == WindowType::Terminal
+    }
+
+    /// Returns the grid size to request from Neovim when it differs from the last request. Terminal
+    /// windows drawn with their own font fit as many cells as possible into their layout, other
+    /// windows go back to the layout size.
+    pub fn take_grid_size_request(&mut self, grid_scale: GridScale) -> Option<GridSize<u32>> {
+        let desired = match self.font_scale {
+            Some(font_scale) if self.valid => {
+                let pixel_size = self.layout_size * grid_scale;
+                Some(GridSize::new(
+                    ((pixel_size.width / font_scale.width()).floor() as u32).max(1),
+                    ((pixel_size.height / font_scale.height()).floor() as u32).max(1),
+                ))
+            }
+            _ => None,
+        };
+        if desired == self.requested_grid_size {
+            return None;
+        }
+        self.requested_grid_size = desired;
+        let size = desired.unwrap_or(self.layout_size);
+        (size != self.grid_size).then_some(size)
     }
 
     fn get_target_position(&self, grid_rect: &GridRect<f32>) -> GridPos<f32> {
@@ -181,27 +264,53 @@ impl RenderedWindow {
         dt: f32,
     ) -> bool {
         let mut animating = false;
+        let (position_animation_length, scroll_animation_length) = if animations_suppressed() {
+            (0.0, 0.0)
+        } else {
+            (
+                settings.position_animation_length,
+                settings.scroll_animation_length,
+            )
+        };
 
         if self.position_t > 1.0 - f32::EPSILON {
             // We are at destination, move t out of 0-1 range to stop the animation.
             self.position_t = 2.0;
         } else {
             animating = true;
-            self.position_t = (self.position_t + dt / settings.position_animation_length).min(1.0);
+            self.position_t = (self.position_t + dt / position_animation_length).min(1.0);
         }
 
         let prev_position = self.grid_current_position;
-        self.grid_current_position = ease_point(
-            ease_out_expo,
+        self.grid_current_position = settings.position_easing.ease_point(
             self.grid_start_position,
             self.get_target_position(grid_rect),
             self.position_t,
         );
         animating |= self.grid_current_position != prev_position;
 
-        let scrolling = self
-            .scroll_animation
-            .update(dt, settings.scroll_animation_length);
+        if self.size_t > 1.0 - f32::EPSILON || !settings.floating_size_animation {
+            self.size_t = 2.0;
+        } else {
+            animating = true;
+            self.size_t = (self.size_t + dt / position_animation_length).min(1.0);
+        }
+        let target_size: GridSize<f32> = self.grid_size.try_cast().unwrap();
+        self.grid_current_size = if self.size_t > 1.0 {
+            target_size
+        } else {
+            let easing = &settings.position_easing;
+            GridSize::new(
+                easing.ease(self.grid_start_size.width, target_size.width, self.size_t),
+                easing.ease(self.grid_start_size.height, target_size.height, self.size_t),
+            )
+        };
+
+        let scrolling = self.scroll_animation.update_with_easing(
+            dt,
+            scroll_animation_length,
+            &settings.scroll_easing,
+        );
 
         animating |= scrolling;
 
@@ -209,6 +318,10 @@ impl RenderedWindow {
             tracy_plot!("Scroll position {}", self.scroll_animation.position.into());
         }
 
+        animating |= self
+            .overscroll_animation
+            .update(dt, OVERSCROLL_ANIMATION_LENGTH);
+
         animating
     }
 
@@ -228,6 +341,17 @@ impl RenderedWindow {
                 canvas.draw_picture(background_picture, Some(&matrix), None);
             }
         }
+        if self.viewport_margins.has_sides() {
+            canvas.clip_rect(inner_region, ClipOp::Difference, false);
+            for (matrix, line) in
+                self.iter_side_border_lines_with_transform(pixel_region, grid_scale)
+            {
+                let line = line.borrow();
+                if let Some(background_picture) = &line.background_picture {
+                    canvas.draw_picture(background_picture, Some(&matrix), None);
+                }
+            }
+        }
         canvas.restore();
 
         canvas.save();
@@ -261,8 +385,22 @@ impl RenderedWindow {
                 canvas.draw_picture(foreground_picture, Some(&matrix), None);
             }
         }
+        let inner_region = self.inner_region(pixel_region, grid_scale);
+        if self.viewport_margins.has_sides() {
+            canvas.save();
+            canvas.clip_rect(inner_region, ClipOp::Difference, false);
+            for (matrix, line) in
+                self.iter_side_border_lines_with_transform(pixel_region, grid_scale)
+            {
+                let line = line.borrow();
+                if let Some(foreground_picture) = &line.foreground_picture {
+                    canvas.draw_picture(foreground_picture, Some(&matrix), None);
+                }
+            }
+            canvas.restore();
+        }
         canvas.save();
-        canvas.clip_rect(self.inner_region(pixel_region, grid_scale), None, false);
+        canvas.clip_rect(inner_region, None, false);
         for (matrix, line) in self.iter_scrollable_lines_with_transform(pixel_region, grid_scale) {
             let line = line.borrow();
             if let Some(foreground_picture) = &line.foreground_picture {
@@ -272,6 +410,33 @@ impl RenderedWindow {
         canvas.restore();
     }
 
+    /// The text of the cells in `cells`, one line per row, as Neovim last drew them.
+    pub fn text_in(&self, cells: GridRect<u32>) -> String {
+        let rows = cells.min.y as usize..(cells.max.y as usize).min(self.actual_lines.len());
+        let columns = u64::from(cells.min.x)..u64::from(cells.max.x);
+        rows.map(|row| {
+            self.actual_lines[row]
+                .as_ref()
+                .map(|line| fragments_text(&line.borrow().line_fragments, columns.clone()))
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+
+    /// The text of the line at `row`, and the index of the character at `column` in it.
+    pub fn line_at(&self, row: u32, column: u32) -> Option<(String, usize)> {
+        let row = row as usize;
+        if row >= self.actual_lines.len() {
+            return None;
+        }
+        let line = self.actual_lines[row].as_ref()?;
+        Some(fragments_line_at(
+            &line.borrow().line_fragments,
+            u64::from(column),
+        ))
+    }
+
     pub fn has_transparency(&self) -> bool {
         let scroll_offset_lines = self.scroll_animation.position.floor() as isize;
         if self.scrollback_lines.is_empty() {
@@ -321,6 +486,7 @@ impl RenderedWindow {
             WindowDrawCommand::Position {
                 grid_position,
                 grid_size,
+                layout_size,
                 anchor_info,
                 window_type,
             } => {
@@ -348,9 +514,11 @@ impl RenderedWindow {
                     self.grid_destination = grid_position;
                 }
 
+                let size_changed = new_grid_size != self.grid_size;
                 let height = new_grid_size.height as usize;
                 self.actual_lines.resize(height, None);
                 self.grid_size = new_grid_size;
+                self.layout_size = GridSize::<u64>::from(layout_size).try_cast().unwrap();
 
                 self.scrollback_lines.resize(2 * height, None);
                 self.scrollback_lines.clone_from_iter(&self.actual_lines);
@@ -363,6 +531,20 @@ impl RenderedWindow {
                 self.anchor_info = anchor_info;
                 self.window_type = window_type;
 
+                // Floating windows like completion popups grow and shrink smoothly, the content is
+                // drawn at its final size and only the visible area is animated.
+                let animate_size = self.anchor_info.is_some()
+                    && !matches!(self.window_type, WindowType::Message { .. })
+                    && !self.hidden
+                    && self.grid_current_size.area() > 0.0;
+                if !animate_size {
+                    self.size_t = 2.0;
+                    self.grid_current_size = new_grid_size.try_cast().unwrap();
+                } else if size_changed {
+                    self.size_t = 0.0;
+                    self.grid_start_size = self.grid_current_size;
+                }
+
                 if self.hidden {
                     self.hidden = false;
                     self.position_t = 2.0; // We don't want to animate since the window is becoming visible,
@@ -377,12 +559,20 @@ impl RenderedWindow {
             } => {
                 tracy_zone!("draw_line_cmd", 0);
 
+                let has_cursor_line = line_fragments.iter().any(|fragment| {
+                    fragment
+                        .style
+                        .as_ref()
+                        .is_some_and(|style| style.is_cursor_line())
+                });
                 let line = Line {
                     line_fragments,
                     background_picture: None,
                     foreground_picture: None,
                     has_transparency: false,
                     is_valid: false,
+                    has_cursor_line,
+                    scrolled_out: false,
                 };
 
                 self.actual_lines[row] = Some(Rc::new(RefCell::new(line)));
@@ -427,12 +617,28 @@ impl RenderedWindow {
                 tracy_zone!("hide_cmd", 0);
                 self.hidden = true;
             }
-            WindowDrawCommand::Viewport { scroll_delta } => {
-                log::trace!("Handling Viewport {}", self.id);
+            WindowDrawCommand::Viewport {
+                scroll_delta,
+                at_top,
+                at_bottom,
+            } => {
+                log::trace!(grid_id = self.id; "Handling viewport");
                 self.scroll_delta = scroll_delta.round() as isize;
+                self.viewport_at_top = at_top;
+                self.viewport_at_bottom = at_bottom;
             }
-            WindowDrawCommand::ViewportMargins { top, bottom, .. } => {
-                self.viewport_margins = ViewportMargins { top, bottom }
+            WindowDrawCommand::ViewportMargins {
+                top,
+                bottom,
+                left,
+                right,
+            } => {
+                self.viewport_margins = ViewportMargins {
+                    top,
+                    bottom,
+                    left,
+                    right,
+                }
             }
             WindowDrawCommand::SortOrder(sort_order) => {
                 if let Some(anchor_info) = self.anchor_info.as_mut() {
@@ -443,6 +649,23 @@ impl RenderedWindow {
         };
     }
 
+    /// Visually pulls the content past the buffer boundary, it snaps back when animating.
+    /// Positive `lines` scroll towards the top of the buffer.
+    pub fn overscroll(&mut self, lines: f32) {
+        let at_boundary = if lines > 0.0 {
+            self.viewport_at_top
+        } else {
+            self.viewport_at_bottom
+        };
+        if !at_boundary {
+            return;
+        }
+
+        let position = self.overscroll_animation.position + lines * OVERSCROLL_RESISTANCE;
+        self.overscroll_animation.position =
+            position.clamp(-MAX_OVERSCROLL_LINES, MAX_OVERSCROLL_LINES);
+    }
+
     pub fn flush(&mut self, renderer_settings: &RendererSettings) {
         if !self.valid {
             return;
@@ -495,10 +718,33 @@ impl RenderedWindow {
             }
             self.scroll_animation.position = scroll_offset;
             log::trace!("Current scroll {scroll_offset}");
+            self.clear_scrolled_out_cursor_lines(inner_size);
         }
         self.scroll_delta = 0;
     }
 
+    /// Neovim moved the highlight of the cursor line to the lines now in the window, but the ones
+    /// that scrolled out keep it while they're animated away, showing two cursor lines that jump
+    /// with each scroll. They're drawn again without it.
+    fn clear_scrolled_out_cursor_lines(&mut self, inner_size: usize) {
+        let inner_size = inner_size as isize;
+        for line in self
+            .scrollback_lines
+            .iter_range(inner_size..2 * inner_size)
+            .flatten()
+        {
+            // The lines still in the grid are drawn as they are
+            if Rc::strong_count(line) > 1 {
+                continue;
+            }
+            let mut line = line.borrow_mut();
+            if line.has_cursor_line && !line.scrolled_out {
+                line.scrolled_out = true;
+                line.is_valid = false;
+            }
+        }
+    }
+
     fn iter_border_lines(&self) -> impl Iterator<Item = (isize, &Rc<RefCell<Line>>)> {
         let top_border_indices = 0..self.viewport_margins.top as isize;
         let actual_line_count = self.actual_lines.len() as isize;
@@ -510,6 +756,32 @@ impl RenderedWindow {
             .filter_map(move |i| self.actual_lines[i].as_ref().map(|line| (i, line)))
     }
 
+    // Iterates over the lines between the top and bottom margins, as they are in the grid, to draw
+    // the columns of the left and right margins that stay in place while scrolling
+    fn iter_side_border_lines_with_transform(
+        &self,
+        pixel_region: PixelRect<f32>,
+        grid_scale: GridScale,
+    ) -> impl Iterator<Item = (Matrix, &Rc<RefCell<Line>>)> {
+        let grid_scale = self.font_scale.unwrap_or(grid_scale);
+        let inner_indices = if self.viewport_margins.has_sides() {
+            self.viewport_margins.top as isize
+                ..self.actual_lines.len() as isize - self.viewport_margins.bottom as isize
+        } else {
+            0..0
+        };
+        inner_indices
+            .filter_map(move |i| self.actual_lines[i].as_ref().map(|line| (i, line)))
+            .map(move |(i, line)| {
+                let mut matrix = Matrix::new_identity();
+                matrix.set_translate((
+                    pixel_region.min.x,
+                    pixel_region.min.y + (i as f32 * grid_scale.height()),
+                ));
+                (matrix, line)
+            })
+    }
+
     // Iterates over the scrollable lines (excluding the viewport margins). Includes the index for
     // the given line being scrolled
     fn iter_scrollable_lines(&self) -> impl Iterator<Item = (isize, &Rc<RefCell<Line>>)> {
@@ -537,9 +809,11 @@ impl RenderedWindow {
         pixel_region: PixelRect<f32>,
         grid_scale: GridScale,
     ) -> impl Iterator<Item = (Matrix, &Rc<RefCell<Line>>)> {
+        let grid_scale = self.font_scale.unwrap_or(grid_scale);
         let scroll_offset_lines = self.scroll_animation.position.floor();
         let scroll_offset = scroll_offset_lines - self.scroll_animation.position;
-        let scroll_offset_pixels = (scroll_offset * grid_scale.height()).round();
+        let scroll_offset_pixels =
+            ((scroll_offset + self.overscroll_animation.position) * grid_scale.height()).round();
 
         self.iter_scrollable_lines().map(move |(i, line)| {
             let mut matrix = Matrix::new_identity();
@@ -558,6 +832,7 @@ impl RenderedWindow {
         pixel_region: PixelRect<f32>,
         grid_scale: GridScale,
     ) -> impl Iterator<Item = (Matrix, &Rc<RefCell<Line>>)> {
+        let grid_scale = self.font_scale.unwrap_or(grid_scale);
         self.iter_border_lines().map(move |(i, line)| {
             let mut matrix = Matrix::new_identity();
             matrix.set_translate((
@@ -568,15 +843,23 @@ impl RenderedWindow {
         })
     }
 
-    /// Returns the rect containing the region of the window that does not have borders above and
-    /// below it. Note: This does not take into account the borders on the left and the right of
-    /// the window.
+    /// Returns the rect containing the region of the window inside the viewport margins, like the
+    /// winbar and the borders, which is the part that scrolls.
     pub fn inner_region(&self, pixel_region: PixelRect<f32>, grid_scale: GridScale) -> Rect {
-        let line_height = grid_scale.height();
+        let grid_scale = self.font_scale.unwrap_or(grid_scale);
+        let margins = &self.viewport_margins;
 
         let adjusted_region = PixelRect::new(
-            pixel_region.min + PixelVec::new(0., self.viewport_margins.top as f32 * line_height),
-            pixel_region.max - PixelVec::new(0., self.viewport_margins.bottom as f32 * line_height),
+            pixel_region.min
+                + PixelVec::new(
+                    margins.left as f32 * grid_scale.width(),
+                    margins.top as f32 * grid_scale.height(),
+                ),
+            pixel_region.max
+                - PixelVec::new(
+                    margins.right as f32 * grid_scale.width(),
+                    margins.bottom as f32 * grid_scale.height(),
+                ),
         );
 
         to_skia_rect(&adjusted_region)
@@ -605,13 +888,21 @@ impl RenderedWindow {
             let mut has_transparency = false;
             let mut custom_background = false;
 
+            let scrolled_out = line.scrolled_out;
+            let line_style = |style: &Option<Arc<Style>>| match style {
+                Some(style) if scrolled_out && style.is_cursor_line() => {
+                    Some(Arc::new(style.without_cursor_line()))
+                }
+                style => style.clone(),
+            };
             for line_fragment in line.line_fragments.iter() {
                 let LineFragment {
+                    text,
                     window_left,
                     width,
                     style,
-                    ..
                 } = line_fragment;
+                let style = &line_style(style);
                 let grid_position = (i32::try_from(*window_left).unwrap(), 0).into();
                 let background_info = grid_renderer.draw_background(
                     canvas,
@@ -622,6 +913,10 @@ impl RenderedWindow {
                 );
                 custom_background |= background_info.custom_color;
                 has_transparency |= background_info.transparent;
+                if background_info.transparent {
+                    custom_background |=
+                        grid_renderer.draw_scrim(canvas, text, grid_position, style, opacity);
+                }
             }
             let background_picture =
                 custom_background.then_some(recorder.finish_recording_as_picture(None).unwrap());
@@ -635,6 +930,7 @@ impl RenderedWindow {
                     width,
                     style,
                 } = line_fragment;
+                let style = &line_style(style);
                 let grid_position = (i32::try_from(*window_left).unwrap(), 0).into();
 
                 foreground_drawn |= grid_renderer.draw_foreground(
@@ -683,3 +979,268 @@ impl RenderedWindow {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor::WindowAnchor,
+        units::{PixelPos, PixelSize},
+    };
+
+    fn grid_scale() -> GridScale {
+        GridScale::new(PixelSize::new(10.0, 20.0))
+    }
+
+    /// A window of 10x5 cells with a line on each row, and the margins given.
+    fn window(margins: ViewportMargins) -> RenderedWindow {
+        let mut window = RenderedWindow::new(2);
+        window.handle_window_draw_command(WindowDrawCommand::Position {
+            grid_position: (0.0, 0.0),
+            grid_size: (10, 5),
+            layout_size: (10, 5),
+            anchor_info: None,
+            window_type: WindowType::Editor,
+        });
+        for row in 0..5 {
+            window.handle_window_draw_command(WindowDrawCommand::DrawLine {
+                row,
+                line_fragments: Vec::new(),
+            });
+        }
+        window.handle_window_draw_command(WindowDrawCommand::ViewportMargins {
+            top: margins.top,
+            bottom: margins.bottom,
+            left: margins.left,
+            right: margins.right,
+        });
+        window
+            .scrollback_lines
+            .clone_from_iter(&window.actual_lines);
+        window
+    }
+
+    fn region() -> PixelRect<f32> {
+        PixelRect::new(PixelPos::new(0.0, 0.0), PixelPos::new(100.0, 100.0))
+    }
+
+    #[test]
+    fn inner_region_leaves_out_the_margins() {
+        let window = window(ViewportMargins {
+            top: 1,
+            bottom: 1,
+            left: 2,
+            right: 1,
+        });
+        let inner = window.inner_region(region(), grid_scale());
+        assert_eq!(
+            (inner.left, inner.top, inner.right, inner.bottom),
+            (20.0, 20.0, 90.0, 80.0)
+        );
+    }
+
+    #[test]
+    fn side_margins_stay_in_place_while_scrolling() {
+        let mut window = window(ViewportMargins {
+            top: 1,
+            bottom: 1,
+            left: 2,
+            right: 1,
+        });
+        window.scroll_animation.position = 0.5;
+        let grid_scale = grid_scale();
+
+        let side_offsets = window
+            .iter_side_border_lines_with_transform(region(), grid_scale)
+            .map(|(matrix, _)| matrix.translate_y())
+            .collect::<Vec<_>>();
+        assert_eq!(side_offsets, vec![20.0, 40.0, 60.0]);
+
+        // The scrolled lines are half a line further up
+        let scrolled_offsets = window
+            .iter_scrollable_lines_with_transform(region(), grid_scale)
+            .map(|(matrix, _)| matrix.translate_y())
+            .collect::<Vec<_>>();
+        assert_eq!(scrolled_offsets.first(), Some(&10.0));
+    }
+
+    #[test]
+    fn no_side_lines_without_side_margins() {
+        let window = window(ViewportMargins {
+            top: 1,
+            bottom: 1,
+            left: 0,
+            right: 0,
+        });
+        assert!(!window.viewport_margins.has_sides());
+        assert_eq!(
+            window
+                .iter_side_border_lines_with_transform(region(), grid_scale())
+                .count(),
+            0
+        );
+        let border_rows = window
+            .iter_border_lines()
+            .map(|(row, _)| row)
+            .collect::<Vec<_>>();
+        assert_eq!(border_rows, vec![0, 4]);
+    }
+
+    #[test]
+    fn overscroll_only_pulls_past_the_boundaries() {
+        let mut window = window(ViewportMargins {
+            top: 0,
+            bottom: 0,
+            left: 0,
+            right: 0,
+        });
+        let viewport = |at_top, at_bottom| WindowDrawCommand::Viewport {
+            scroll_delta: 0.0,
+            at_top,
+            at_bottom,
+        };
+        // In the middle of the buffer it scrolls normally
+        window.overscroll(2.0);
+        assert_eq!(window.overscroll_animation.position, 0.0);
+
+        window.handle_window_draw_command(viewport(true, false));
+        window.overscroll(-2.0);
+        assert_eq!(window.overscroll_animation.position, 0.0);
+        window.overscroll(2.0);
+        assert_eq!(
+            window.overscroll_animation.position,
+            2.0 * OVERSCROLL_RESISTANCE
+        );
+        // It's held back and limited, like pulling a spring
+        window.overscroll(100.0);
+        assert_eq!(window.overscroll_animation.position, MAX_OVERSCROLL_LINES);
+
+        window.handle_window_draw_command(viewport(false, true));
+        window.overscroll(-100.0);
+        assert_eq!(window.overscroll_animation.position, -MAX_OVERSCROLL_LINES);
+    }
+
+    fn floating_position(grid_size: (u64, u64)) -> WindowDrawCommand {
+        WindowDrawCommand::Position {
+            grid_position: (0.0, 0.0),
+            grid_size,
+            layout_size: grid_size,
+            anchor_info: Some(AnchorInfo {
+                anchor_grid_id: 1,
+                anchor_type: WindowAnchor::NorthWest,
+                anchor_left: 0.0,
+                anchor_top: 0.0,
+                sort_order: SortOrder::default(),
+            }),
+            window_type: WindowType::Editor,
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn floating_windows_grow_smoothly() {
+        let settings = RendererSettings::default();
+        let grid_rect = GridRect::new(GridPos::new(0.0, 0.0), GridPos::new(10.0, 8.0));
+        let mut window = RenderedWindow::new(2);
+        // A window that just appeared starts at its full size
+        window.handle_window_draw_command(floating_position((10, 5)));
+        window.animate(&settings, &grid_rect, 0.0);
+        assert_eq!(window.grid_current_size, GridSize::new(10.0, 5.0));
+
+        window.handle_window_draw_command(floating_position((10, 8)));
+        assert!(window.animate(
+            &settings,
+            &grid_rect,
+            settings.position_animation_length / 2.0
+        ));
+        let height = window.grid_current_size.height;
+        assert!(height > 5.0 && height < 8.0, "{height}");
+        let region = window.pixel_region(grid_scale());
+        assert_eq!(region.max.y - region.min.y, height * grid_scale().height());
+
+        window.animate(&settings, &grid_rect, settings.position_animation_length);
+        assert_eq!(window.grid_current_size, GridSize::new(10.0, 8.0));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn only_floating_windows_animate_their_size() {
+        let grid_rect = GridRect::new(GridPos::new(0.0, 0.0), GridPos::new(10.0, 8.0));
+        let mut window = window(ViewportMargins {
+            top: 0,
+            bottom: 0,
+            left: 0,
+            right: 0,
+        });
+        window.handle_window_draw_command(WindowDrawCommand::Position {
+            grid_position: (0.0, 0.0),
+            grid_size: (10, 8),
+            layout_size: (10, 8),
+            anchor_info: None,
+            window_type: WindowType::Editor,
+        });
+        assert_eq!(window.grid_current_size, GridSize::new(10.0, 8.0));
+
+        // Nor do floating windows when the animation is turned off
+        let settings = RendererSettings {
+            floating_size_animation: false,
+            ..Default::default()
+        };
+        let mut window = RenderedWindow::new(3);
+        window.handle_window_draw_command(floating_position((10, 5)));
+        window.animate(&settings, &grid_rect, 0.0);
+        window.handle_window_draw_command(floating_position((10, 8)));
+        window.animate(&settings, &grid_rect, 0.01);
+        assert_eq!(window.grid_current_size, GridSize::new(10.0, 8.0));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn reduced_motion_jumps_to_the_destination() {
+        let settings = RendererSettings::default();
+        let grid_rect = GridRect::new(GridPos::new(0.0, 0.0), GridPos::new(10.0, 8.0));
+        let mut window = RenderedWindow::new(2);
+        window.handle_window_draw_command(floating_position((10, 5)));
+        window.animate(&settings, &grid_rect, 0.0);
+
+        set_motion_reduced(true);
+        window.handle_window_draw_command(floating_position((10, 8)));
+        window.scroll_animation.position = 5.0;
+        window.animate(&settings, &grid_rect, 0.001);
+        set_motion_reduced(false);
+
+        assert_eq!(window.grid_current_size, GridSize::new(10.0, 8.0));
+        assert_eq!(window.scroll_animation.position, 0.0);
+    }
+
+    #[test]
+    fn terminal_font_fits_into_the_layout() {
+        let mut window = window(ViewportMargins {
+            top: 0,
+            bottom: 0,
+            left: 0,
+            right: 0,
+        });
+        // Without its own font the grid already has the layout size
+        assert_eq!(window.take_grid_size_request(grid_scale()), None);
+
+        // A font with cells of 7x15 pixels fits 14x6 cells into the 100x100 pixels of the layout
+        window.font_scale = Some(GridScale::new(PixelSize::new(7.0, 15.0)));
+        assert_eq!(
+            window.take_grid_size_request(grid_scale()),
+            Some(GridSize::new(14, 6))
+        );
+        // The same size isn't requested twice
+        assert_eq!(window.take_grid_size_request(grid_scale()), None);
+        // The window still covers the space of its layout
+        assert_eq!(window.pixel_region(grid_scale()), region());
+
+        // Removing the font goes back to the layout size
+        window.font_scale = None;
+        window.grid_size = GridSize::new(14, 6);
+        assert_eq!(
+            window.take_grid_size_request(grid_scale()),
+            Some(GridSize::new(10, 5))
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use std::sync::Arc;
+
+use skia_safe::{Canvas, Paint, RRect, Rect};
+
+use crate::{
+    editor::{Style, Tabline},
+    profiling::tracy_zone,
+    renderer::{
+        animation_utils::CriticallyDampedSpringAnimation, cmdline_renderer::floating_box_radius,
+        popupmenu::truncate, GridRenderer, RendererSettings,
+    },
+    settings::*,
+    units::{to_skia_rect, GridPos, GridScale, PixelPos, PixelRect},
+};
+
+/// Height of the tab bar, in lines.
+const HEIGHT: f32 = 1.5;
+const CLOSE_BUTTON: &str = "×";
+/// Cells used around the title: the left padding, the close button and the space before it.
+const TAB_DECORATION_COLUMNS: usize = 3;
+
+#[derive(Clone, SettingGroup)]
+#[setting_prefix = "tabline"]
+pub struct TablineSettings {
+    pub enabled: bool,
+    max_tab_width: u32,
+    animation_length: f32,
+    /// Shows the tabs in the title bar of `--frame custom` instead of above the grid.
+    pub in_titlebar: bool,
+}
+
+impl Default for TablineSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_tab_width: 25,
+            animation_length: 0.15,
+            in_titlebar: false,
+        }
+    }
+}
+
+/// What a click on the tab bar does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TablineHit {
+    pub index: usize,
+    pub close_button: bool,
+}
+
+/// Finds the tab at `x`, measured from the left of the tab bar.
+fn hit_tab(x: f32, tab_width: f32, cell_width: f32, count: usize) -> Option<TablineHit> {
+    if x < 0.0 || tab_width <= 0.0 {
+        return None;
+    }
+    let index = (x / tab_width).floor() as usize;
+    if index >= count {
+        return None;
+    }
+    let x_in_tab = x - index as f32 * tab_width;
+    Some(TablineHit {
+        index,
+        close_button: x_in_tab >= tab_width - 2.0 * cell_width,
+    })
+}
+
+/// Draws the tab pages of Neovim's externalized tab line as a bar above the grid.
+pub struct TablineRenderer {
+    tabline: Option<Tabline>,
+    /// Offset of the selected tab highlight from the selected tab, in tabs, animated towards 0.
+    selection_animation: CriticallyDampedSpringAnimation,
+    /// Where the bar was last drawn, and the width of its tabs, to find the clicked tab.
+    bar_rect: Option<PixelRect<f32>>,
+    tab_width: f32,
+    cell_width: f32,
+
+    settings: Arc<Settings>,
+}
+
+impl TablineRenderer {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            tabline: None,
+            selection_animation: CriticallyDampedSpringAnimation::new(),
+            bar_rect: None,
+            tab_width: 0.0,
+            cell_width: 0.0,
+            settings,
+        }
+    }
+
+    pub fn set_tabline(&mut self, tabline: Option<Tabline>) {
+        match (&self.tabline, &tabline) {
+            (Some(previous), Some(tabline)) if previous.tabs.len() == tabline.tabs.len() => {
+                self.selection_animation.position +=
+                    previous.current as f32 - tabline.current as f32;
+            }
+            _ => self.selection_animation.reset(),
+        }
+        self.tabline = tabline;
+    }
+
+    /// The height taken by the tab bar above the grid, in pixels.
+    pub fn height(&self, grid_scale: GridScale) -> f32 {
+        if self.tabline.is_some() && self.settings.get::<TablineSettings>().enabled {
+            (HEIGHT * grid_scale.height()).round()
+        } else {
+            0.0
+        }
+    }
+
+    pub fn animate(&mut self, dt: f32) -> bool {
+        if self.tabline.is_none() {
+            return false;
+        }
+        let animation_length = self.settings.get::<TablineSettings>().animation_length;
+        self.selection_animation.update(dt, animation_length)
+    }
+
+    pub fn hit_test(&self, position: PixelPos<f32>) -> Option<TablineHit> {
+        let rect = self.bar_rect?;
+        if position.y < rect.min.y || position.y >= rect.max.y {
+            return None;
+        }
+        let count = self.tabline.as_ref()?.tabs.len();
+        hit_tab(
+            position.x - rect.min.x,
+            self.tab_width,
+            self.cell_width,
+            count,
+        )
+    }
+
+    pub fn contains(&self, position: PixelPos<f32>) -> bool {
+        self.bar_rect.is_some_and(|rect| {
+            position.x >= rect.min.x
+                && position.x < rect.max.x
+                && position.y >= rect.min.y
+                && position.y < rect.max.y
+        })
+    }
+
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        rect: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("tabline_draw");
+        self.bar_rect = None;
+        let Some(tabline) = &self.tabline else {
+            return;
+        };
+        let bar_width = rect.max.x - rect.min.x;
+        let bar_height = rect.max.y - rect.min.y;
+        if tabline.tabs.is_empty() || bar_width <= 0.0 || bar_height <= 0.0 {
+            return;
+        }
+        let settings = self.settings.get::<TablineSettings>();
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+
+        let max_tab_width = settings
+            .max_tab_width
+            .max(TAB_DECORATION_COLUMNS as u32 + 1) as f32;
+        let tab_width = (max_tab_width * cell_width)
+            .min(bar_width / tabline.tabs.len() as f32)
+            .floor();
+        self.bar_rect = Some(rect);
+        self.tab_width = tab_width;
+        self.cell_width = cell_width;
+
+        let default_style = grid_renderer.default_style.clone();
+        let background_of = |style: &Option<Arc<Style>>| {
+            style
+                .as_deref()
+                .unwrap_or(&default_style)
+                .background(&default_style.colors)
+        };
+
+        root_canvas.save();
+        root_canvas.clip_rect(to_skia_rect(&rect), None, Some(false));
+        let fill = Paint::new(background_of(&tabline.styles.fill), None);
+        root_canvas.draw_rect(to_skia_rect(&rect), &fill);
+
+        let tab_paint = Paint::new(background_of(&tabline.styles.normal), None);
+        for index in 0..tabline.tabs.len() {
+            let x = rect.min.x + index as f32 * tab_width;
+            root_canvas.draw_rect(
+                Rect::from_xywh(x + 1.0, rect.min.y, tab_width - 2.0, bar_height),
+                &tab_paint,
+            );
+        }
+
+        // The highlight slides over to the newly selected tab
+        let selection_x =
+            rect.min.x + (tabline.current as f32 + self.selection_animation.position) * tab_width;
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let mut selection_paint = Paint::new(background_of(&tabline.styles.selected), None);
+        selection_paint.set_anti_alias(true);
+        root_canvas.draw_rrect(
+            RRect::new_rect_xy(
+                Rect::from_xywh(selection_x + 1.0, rect.min.y, tab_width - 2.0, bar_height),
+                radius,
+                radius,
+            ),
+            &selection_paint,
+        );
+
+        let tab_columns = (tab_width / cell_width).floor() as usize;
+        let title_columns = tab_columns.saturating_sub(TAB_DECORATION_COLUMNS);
+        let text_top = rect.min.y + ((bar_height - line_height) / 2.0).round();
+        for (index, tab) in tabline.tabs.iter().enumerate() {
+            let style = if index == tabline.current {
+                &tabline.styles.selected
+            } else {
+                &tabline.styles.normal
+            };
+            root_canvas.save();
+            root_canvas.translate((rect.min.x + index as f32 * tab_width, text_top));
+            let title = truncate(&tab.title, title_columns);
+            grid_renderer.draw_foreground(
+                root_canvas,
+                &title,
+                GridPos::new(1, 0),
+                title_columns as i32,
+                style,
+            );
+            if tab_columns > 1 {
+                grid_renderer.draw_foreground(
+                    root_canvas,
+                    CLOSE_BUTTON,
+                    GridPos::new(tab_columns as i32 - 2, 0),
+                    1,
+                    style,
+                );
+            }
+            root_canvas.restore();
+        }
+        root_canvas.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_clicked_tab() {
+        assert_eq!(
+            hit_tab(15.0, 100.0, 10.0, 3),
+            Some(TablineHit {
+                index: 0,
+                close_button: false
+            })
+        );
+        assert_eq!(
+            hit_tab(195.0, 100.0, 10.0, 3),
+            Some(TablineHit {
+                index: 1,
+                close_button: true
+            })
+        );
+        assert_eq!(hit_tab(350.0, 100.0, 10.0, 3), None);
+        assert_eq!(hit_tab(-1.0, 100.0, 10.0, 3), None);
+    }
+}
@@ -0,0 +1,308 @@
+//! Selecting with the mouse the text Neovide draws on its own, like the messages or the text of
+//! the overlays, which Neovim doesn't know about and can't copy.
+
+use skia_safe::{Canvas, Color4f, Paint, Rect};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::units::{PixelPos, PixelSize};
+
+/// A selection between two positions of some text, `anchor` being where the pointer was pressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextSelection<T> {
+    pub anchor: T,
+    pub focus: T,
+}
+
+impl<T: Copy + Ord> TextSelection<T> {
+    pub fn new(position: T) -> Self {
+        Self {
+            anchor: position,
+            focus: position,
+        }
+    }
+
+    /// The start and the end of the selection, whichever way the pointer was dragged.
+    pub fn range(&self) -> (T, T) {
+        (self.anchor.min(self.focus), self.anchor.max(self.focus))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.focus
+    }
+}
+
+/// A caret position of `SelectableText`, before the grapheme at `column` of `line`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextPoint {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SelectableLine {
+    /// The text drawn together, a selection doesn't go from one block to another.
+    block: usize,
+    origin: PixelPos<f32>,
+    cell_size: PixelSize<f32>,
+    graphemes: Vec<String>,
+    /// The cell each grapheme starts at, wide characters taking two, followed by the width of
+    /// the line.
+    columns: Vec<usize>,
+}
+
+impl SelectableLine {
+    fn width(&self) -> f32 {
+        self.x_of(self.graphemes.len())
+    }
+
+    /// The left side of the grapheme at `index`, or the end of the line past its last one.
+    fn x_of(&self, index: usize) -> f32 {
+        self.columns[index] as f32 * self.cell_size.width
+    }
+
+    fn contains_y(&self, y: f32) -> bool {
+        y >= self.origin.y && y < self.origin.y + self.cell_size.height
+    }
+
+    /// The grapheme boundary closest to `x`, the later one when it's right between two.
+    fn column_at(&self, x: f32) -> usize {
+        let cell = (x - self.origin.x) / self.cell_size.width;
+        let distance = |index: &usize| (self.columns[*index] as f32 - cell).abs();
+        (0..self.columns.len())
+            .rev()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .unwrap_or(0)
+    }
+}
+
+/// The lines of text the overlays drew in cells during the last frame, to hit test the pointer
+/// and shade the selection. A selection only applies to the lines it was made on, the
+/// generation changes whenever they do.
+#[derive(Default)]
+pub struct SelectableText {
+    lines: Vec<SelectableLine>,
+    previous_lines: Vec<SelectableLine>,
+    blocks: usize,
+    generation: u64,
+    selection: Option<TextSelection<TextPoint>>,
+}
+
+impl SelectableText {
+    pub fn begin_frame(&mut self) {
+        self.previous_lines = std::mem::take(&mut self.lines);
+        self.blocks = 0;
+    }
+
+    pub fn end_frame(&mut self) {
+        if self.lines != self.previous_lines {
+            self.generation += 1;
+            self.selection = None;
+        }
+    }
+
+    /// Starts a new block of lines, like a toast.
+    pub fn begin_block(&mut self) {
+        self.blocks += 1;
+    }
+
+    pub fn add_line(&mut self, origin: PixelPos<f32>, cell_size: PixelSize<f32>, text: &str) {
+        if cell_size.width <= 0.0 || cell_size.height <= 0.0 {
+            return;
+        }
+        let graphemes: Vec<String> = text.graphemes(true).map(str::to_string).collect();
+        let columns = std::iter::once(0)
+            .chain(graphemes.iter().scan(0, |column, grapheme| {
+                *column += grapheme.width();
+                Some(*column)
+            }))
+            .collect();
+        self.lines.push(SelectableLine {
+            block: self.blocks,
+            origin,
+            cell_size,
+            graphemes,
+            columns,
+        });
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The position under `point`, when it's over a line of text.
+    pub fn hit(&self, point: PixelPos<f32>) -> Option<TextPoint> {
+        self.lines
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, line)| {
+                let inside = line.contains_y(point.y)
+                    && point.x >= line.origin.x
+                    && point.x < line.origin.x + line.width();
+                inside.then(|| TextPoint {
+                    line: index,
+                    column: line.column_at(point.x),
+                })
+            })
+    }
+
+    /// The position closest to `point` among the lines of the block of `anchor`, to extend a
+    /// selection while dragging.
+    pub fn nearest(&self, anchor: TextPoint, point: PixelPos<f32>) -> Option<TextPoint> {
+        let block = self.lines.get(anchor.line)?.block;
+        let lines = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.block == block);
+        let (index, line) = lines
+            .clone()
+            .find(|(_, line)| line.contains_y(point.y))
+            .or_else(|| {
+                if point.y < self.lines[anchor.line].origin.y {
+                    lines.clone().next()
+                } else {
+                    lines.clone().last()
+                }
+            })?;
+        // Above or below the block, the selection goes to its start or its end
+        let column = if line.contains_y(point.y) {
+            line.column_at(point.x)
+        } else if point.y < line.origin.y {
+            0
+        } else {
+            line.graphemes.len()
+        };
+        Some(TextPoint {
+            line: index,
+            column,
+        })
+    }
+
+    /// Shows the selection made on the lines of `generation`, the others are outdated.
+    pub fn set_selection(&mut self, selection: Option<(u64, TextSelection<TextPoint>)>) {
+        self.selection = selection
+            .filter(|(generation, selection)| {
+                *generation == self.generation && !selection.is_empty()
+            })
+            .map(|(_, selection)| selection);
+    }
+
+    /// Calls `f` with the line and the selected columns of each selected line.
+    fn for_each_selected(&self, mut f: impl FnMut(&SelectableLine, usize, usize)) {
+        let Some(selection) = &self.selection else {
+            return;
+        };
+        let (start, end) = selection.range();
+        for index in start.line..=end.line {
+            let Some(line) = self.lines.get(index) else {
+                break;
+            };
+            let first = if index == start.line { start.column } else { 0 };
+            let last = if index == end.line {
+                end.column
+            } else {
+                line.graphemes.len()
+            };
+            f(line, first.min(last), last);
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let mut lines = Vec::new();
+        self.for_each_selected(|line, first, last| {
+            lines.push(line.graphemes[first..last].concat().trim_end().to_string());
+        });
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    pub fn draw_selection(&self, canvas: &Canvas, mut color: Color4f) {
+        color.a = 0.3;
+        let paint = Paint::new(color, None);
+        self.for_each_selected(|line, first, last| {
+            let rect = Rect::from_xywh(
+                line.origin.x + line.x_of(first),
+                line.origin.y,
+                line.x_of(last) - line.x_of(first),
+                line.cell_size.height,
+            );
+            canvas.draw_rect(rect, &paint);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_follows_the_lines_of_a_block() {
+        let cell = PixelSize::new(10.0, 20.0);
+        let mut text = SelectableText::default();
+        text.begin_frame();
+        text.begin_block();
+        text.add_line(PixelPos::new(100.0, 0.0), cell, "E5108: Error");
+        text.add_line(PixelPos::new(100.0, 20.0), cell, "stack traceback:");
+        text.begin_block();
+        text.add_line(PixelPos::new(100.0, 60.0), cell, "written");
+        text.end_frame();
+
+        let anchor = text.hit(PixelPos::new(171.0, 5.0)).unwrap();
+        assert_eq!(anchor, TextPoint { line: 0, column: 7 });
+        assert_eq!(text.hit(PixelPos::new(50.0, 5.0)), None);
+
+        // Dragging over the next toast stays at the end of the block
+        let mut selection = TextSelection::new(anchor);
+        selection.focus = text.nearest(anchor, PixelPos::new(150.0, 65.0)).unwrap();
+        assert_eq!(
+            selection.focus,
+            TextPoint {
+                line: 1,
+                column: 16
+            }
+        );
+        text.set_selection(Some((text.generation(), selection)));
+        assert_eq!(
+            text.selected_text().as_deref(),
+            Some("Error\nstack traceback:")
+        );
+
+        // The selection is dropped once the toasts change
+        let generation = text.generation();
+        text.begin_frame();
+        text.begin_block();
+        text.add_line(PixelPos::new(100.0, 60.0), cell, "written");
+        text.end_frame();
+        assert_eq!(text.selected_text(), None);
+        text.set_selection(Some((generation, selection)));
+        assert_eq!(text.selected_text(), None);
+    }
+
+    #[test]
+    fn wide_characters_take_two_cells() {
+        let cell = PixelSize::new(10.0, 20.0);
+        let mut text = SelectableText::default();
+        text.begin_frame();
+        text.begin_block();
+        text.add_line(PixelPos::new(0.0, 0.0), cell, "a日本b");
+        text.end_frame();
+
+        let hit = |x| text.hit(PixelPos::new(x, 5.0)).map(|point| point.column);
+        // The line is six cells wide, not four
+        assert_eq!(hit(55.0), Some(4));
+        assert_eq!(hit(60.0), None);
+        // The boundaries of 日 are at the cells 1 and 3
+        assert_eq!(hit(14.0), Some(1));
+        assert_eq!(hit(21.0), Some(2));
+        assert_eq!(hit(26.0), Some(2));
+        assert_eq!(hit(34.0), Some(2));
+        assert_eq!(hit(41.0), Some(3));
+
+        let anchor = TextPoint { line: 0, column: 1 };
+        let mut selection = TextSelection::new(anchor);
+        selection.focus = text.hit(PixelPos::new(49.0, 5.0)).unwrap();
+        text.set_selection(Some((text.generation(), selection)));
+        assert_eq!(text.selected_text().as_deref(), Some("日本"));
+    }
+}
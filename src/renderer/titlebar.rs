@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use skia_safe::{Canvas, Paint};
+use winit::window::ResizeDirection;
+
+use crate::{
+    frame::{window_frame, Frame},
+    profiling::tracy_zone,
+    renderer::{
+        popupmenu::{text_width, truncate},
+        GridRenderer,
+    },
+    settings::*,
+    units::{to_skia_rect, GridPos, GridScale, PixelPos, PixelRect, PixelSize},
+};
+
+/// Height of the title bar, in lines.
+const HEIGHT: f32 = 1.75;
+/// Width of each button, in heights of the title bar.
+const BUTTON_WIDTH: f32 = 1.5;
+const MINIMIZE_BUTTON: &str = "─";
+const MAXIMIZE_BUTTON: &str = "□";
+const CLOSE_BUTTON: &str = "×";
+/// How close to the edges of the window a press resizes it, in pixels.
+const RESIZE_BORDER: f32 = 6.0;
+
+/// What a click on the title bar does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TitlebarHit {
+    Minimize,
+    Maximize,
+    Close,
+    /// Anywhere else, which drags the window.
+    Title,
+}
+
+/// Finds the button at `x`, measured from the left of a title bar `width` wide, the buttons being
+/// on the right.
+fn hit_titlebar(x: f32, width: f32, button_width: f32) -> TitlebarHit {
+    let from_right = width - x;
+    if from_right < 0.0 || button_width <= 0.0 {
+        return TitlebarHit::Title;
+    }
+    match (from_right / button_width).floor() as usize {
+        0 => TitlebarHit::Close,
+        1 => TitlebarHit::Maximize,
+        2 => TitlebarHit::Minimize,
+        _ => TitlebarHit::Title,
+    }
+}
+
+/// The edge of a window of `size` that a press at `position` resizes, since the custom frame has
+/// no borders of the OS either.
+pub fn resize_direction(position: PixelPos<f32>, size: PixelSize<f32>) -> Option<ResizeDirection> {
+    let west = position.x < RESIZE_BORDER;
+    let east = position.x >= size.width - RESIZE_BORDER;
+    let north = position.y < RESIZE_BORDER;
+    let south = position.y >= size.height - RESIZE_BORDER;
+    match (north, south, west, east) {
+        (true, _, true, _) => Some(ResizeDirection::NorthWest),
+        (true, _, _, true) => Some(ResizeDirection::NorthEast),
+        (_, true, true, _) => Some(ResizeDirection::SouthWest),
+        (_, true, _, true) => Some(ResizeDirection::SouthEast),
+        (true, ..) => Some(ResizeDirection::North),
+        (_, true, ..) => Some(ResizeDirection::South),
+        (_, _, true, _) => Some(ResizeDirection::West),
+        (_, _, _, true) => Some(ResizeDirection::East),
+        _ => None,
+    }
+}
+
+/// Draws the title bar of `--frame custom`, with the title, or the tabs, and the window buttons,
+/// the same way on every platform.
+pub struct TitlebarRenderer {
+    title: String,
+    /// Where the bar was last drawn, and the width of its buttons, to find what was clicked.
+    bar_rect: Option<PixelRect<f32>>,
+    button_width: f32,
+
+    settings: Arc<Settings>,
+}
+
+impl TitlebarRenderer {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            title: String::new(),
+            bar_rect: None,
+            button_width: 0.0,
+            settings,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        window_frame(&self.settings) == Frame::Custom
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    /// The height taken by the title bar above everything else, in pixels.
+    pub fn height(&self, grid_scale: GridScale) -> f32 {
+        if self.enabled() {
+            (HEIGHT * grid_scale.height()).round()
+        } else {
+            0.0
+        }
+    }
+
+    pub fn hit_test(&self, position: PixelPos<f32>) -> Option<TitlebarHit> {
+        let rect = self.bar_rect?;
+        if !self.contains(position) {
+            return None;
+        }
+        Some(hit_titlebar(
+            position.x - rect.min.x,
+            rect.max.x - rect.min.x,
+            self.button_width,
+        ))
+    }
+
+    pub fn contains(&self, position: PixelPos<f32>) -> bool {
+        self.bar_rect.is_some_and(|rect| {
+            position.x >= rect.min.x
+                && position.x < rect.max.x
+                && position.y >= rect.min.y
+                && position.y < rect.max.y
+        })
+    }
+
+    /// Draws the bar in `rect`, with the title unless `show_title` is false, and returns the part
+    /// left of the buttons, where the tabs can go instead.
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        rect: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        show_title: bool,
+    ) -> PixelRect<f32> {
+        tracy_zone!("titlebar_draw");
+        let bar_width = rect.max.x - rect.min.x;
+        let bar_height = rect.max.y - rect.min.y;
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+        self.bar_rect = Some(rect);
+        self.button_width = (BUTTON_WIDTH * bar_height).round();
+
+        let default_style = grid_renderer.default_style.clone();
+        let colors = &default_style.colors;
+        root_canvas.save();
+        root_canvas.clip_rect(to_skia_rect(&rect), None, Some(false));
+        let fill = Paint::new(default_style.background(colors), None);
+        root_canvas.draw_rect(to_skia_rect(&rect), &fill);
+
+        let text_top = rect.min.y + ((bar_height - line_height) / 2.0).round();
+        let buttons = [CLOSE_BUTTON, MAXIMIZE_BUTTON, MINIMIZE_BUTTON];
+        for (index, button) in buttons.into_iter().enumerate() {
+            let center = rect.max.x - (index as f32 + 0.5) * self.button_width;
+            root_canvas.save();
+            root_canvas.translate(((center - cell_width / 2.0).round(), text_top));
+            grid_renderer.draw_foreground(root_canvas, button, GridPos::new(0, 0), 1, &None);
+            root_canvas.restore();
+        }
+
+        let free_rect = PixelRect::new(
+            rect.min,
+            PixelPos::new(
+                (rect.max.x - buttons.len() as f32 * self.button_width).max(rect.min.x),
+                rect.max.y,
+            ),
+        );
+        if show_title && cell_width > 0.0 {
+            // Centered in the window, as long as it doesn't run into the buttons
+            let columns = ((free_rect.max.x - free_rect.min.x) / cell_width).floor() as usize;
+            let title = truncate(&self.title, columns.saturating_sub(2));
+            let title_width = text_width(&title) as f32 * cell_width;
+            let left = (rect.min.x + (bar_width - title_width) / 2.0)
+                .min(free_rect.max.x - cell_width - title_width)
+                .max(rect.min.x + cell_width);
+            root_canvas.save();
+            root_canvas.translate((left.round(), text_top));
+            grid_renderer.draw_foreground(
+                root_canvas,
+                &title,
+                GridPos::new(0, 0),
+                text_width(&title) as i32,
+                &None,
+            );
+            root_canvas.restore();
+        }
+        root_canvas.restore();
+        free_rect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_clicked_button() {
+        assert_eq!(hit_titlebar(990.0, 1000.0, 50.0), TitlebarHit::Close);
+        assert_eq!(hit_titlebar(940.0, 1000.0, 50.0), TitlebarHit::Maximize);
+        assert_eq!(hit_titlebar(880.0, 1000.0, 50.0), TitlebarHit::Minimize);
+        assert_eq!(hit_titlebar(500.0, 1000.0, 50.0), TitlebarHit::Title);
+
+        let size = PixelSize::new(800.0, 600.0);
+        assert_eq!(
+            resize_direction(PixelPos::new(2.0, 2.0), size),
+            Some(ResizeDirection::NorthWest)
+        );
+        assert_eq!(
+            resize_direction(PixelPos::new(400.0, 598.0), size),
+            Some(ResizeDirection::South)
+        );
+        assert_eq!(
+            resize_direction(PixelPos::new(797.0, 300.0), size),
+            Some(ResizeDirection::East)
+        );
+        assert_eq!(resize_direction(PixelPos::new(400.0, 300.0), size), None);
+    }
+}
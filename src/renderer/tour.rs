@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use skia_safe::{Canvas, ClipOp, Color4f, Paint, PaintStyle, RRect};
+
+use crate::{
+    editor::{Colors, Style},
+    profiling::tracy_zone,
+    renderer::{
+        cmdline_renderer::{begin_floating_box, end_floating_box, floating_box_radius},
+        popupmenu::{text_width, truncate},
+        GridRenderer, RendererSettings,
+    },
+    settings::config_path,
+    units::{to_skia_rect, GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Widest the text of the tour gets, in columns.
+const MAX_COLUMNS: usize = 60;
+const PADDING: usize = 2;
+const SCRIM_ALPHA: f32 = 0.5;
+
+/// The part of the window a step of the tour points at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TourHighlight {
+    Nothing,
+    Grid,
+    Cursor,
+}
+
+struct TourStep {
+    title: &'static str,
+    text: &'static str,
+    highlight: TourHighlight,
+}
+
+const STEPS: &[TourStep] = &[
+    TourStep {
+        title: "Welcome to Neovide",
+        text: "This tour shows a few things Neovide adds to Neovim. Press Enter or → for the next \
+               step, ← for the previous one, and Esc to close it.",
+        highlight: TourHighlight::Nothing,
+    },
+    TourStep {
+        title: "Zoom",
+        text: "Set g:neovide_scale_factor to zoom in and out, or pinch on a trackpad or touch \
+               screen. Map it to keys like <C-=> and <C--> to zoom from the keyboard, the FAQ has \
+               an example.",
+        highlight: TourHighlight::Grid,
+    },
+    TourStep {
+        title: "Fullscreen",
+        text:
+            "Set g:neovide_fullscreen to fill the screen, or bind \"fullscreen\" to a hot corner \
+               or a mouse button to toggle it with the mouse.",
+        highlight: TourHighlight::Grid,
+    },
+    TourStep {
+        title: "Opacity",
+        text: "Set g:neovide_transparency below 1.0 to see through the window, and \
+               g:neovide_normal_opacity to only fade the background of the text.",
+        highlight: TourHighlight::Grid,
+    },
+    TourStep {
+        title: "Animations",
+        text: "The cursor, scrolling and windows are animated. Tune them with \
+               g:neovide_cursor_animation_length, g:neovide_scroll_animation_length and \
+               g:neovide_position_animation_length, or set them to 0 to turn them off.",
+        highlight: TourHighlight::Cursor,
+    },
+    TourStep {
+        title: "Configuration",
+        text: "Options that are needed before Neovim starts, like the font and the window frame, \
+               are read from the config file at {config}.",
+        highlight: TourHighlight::Nothing,
+    },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TourAction {
+    Next,
+    Previous,
+    Close,
+}
+
+/// The step shown after `action`, or `None` once the tour is over.
+fn navigate(step: usize, count: usize, action: TourAction) -> Option<usize> {
+    match action {
+        TourAction::Next => Some(step + 1).filter(|step| *step < count),
+        TourAction::Previous => Some(step.saturating_sub(1)),
+        TourAction::Close => None,
+    }
+}
+
+/// Breaks the text into lines of at most `columns`, between words where possible.
+fn wrap_text(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let width = text_width(&line);
+        if !line.is_empty() && width + 1 + text_width(word) > columns {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+        // Words longer than a line are cut
+        if text_width(&line) > columns {
+            lines.push(truncate(&line, columns));
+            line.clear();
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Draws `:NeovideTour`, a few steps explaining the features of Neovide above the grid, which
+/// point at the part of the window they are about.
+pub struct TourRenderer {
+    step: Option<usize>,
+}
+
+impl TourRenderer {
+    pub fn new() -> Self {
+        Self { step: None }
+    }
+
+    pub fn start(&mut self) {
+        self.step = Some(0);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.step.is_some()
+    }
+
+    pub fn navigate(&mut self, action: TourAction) {
+        self.step = self
+            .step
+            .and_then(|step| navigate(step, STEPS.len(), action));
+    }
+
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        region: PixelRect<f32>,
+        cursor: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("tour_draw");
+        let Some(step) = self.step.and_then(|step| STEPS.get(step)) else {
+            return;
+        };
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+        let max_columns = ((region.max.x - region.min.x) / cell_width).floor() as usize;
+        if max_columns <= 2 * PADDING + 1 {
+            return;
+        }
+        let radius = floating_box_radius(renderer_settings, line_height);
+
+        let highlight = match step.highlight {
+            TourHighlight::Nothing => None,
+            TourHighlight::Grid => Some(region),
+            TourHighlight::Cursor => Some(cursor),
+        };
+
+        // Dim everything but the highlighted part, and outline it
+        let default_style = grid_renderer.default_style.clone();
+        let mut outline_color = default_style.foreground(&default_style.colors);
+        let highlight_rrect = highlight.map(|highlight| {
+            let rect = to_skia_rect(&highlight).with_outset((cell_width / 2.0, line_height / 4.0));
+            RRect::new_rect_xy(rect, radius, radius)
+        });
+        root_canvas.save();
+        if let Some(rrect) = &highlight_rrect {
+            root_canvas.clip_rrect(rrect, ClipOp::Difference, Some(true));
+        }
+        root_canvas.draw_rect(
+            to_skia_rect(&region),
+            &Paint::new(Color4f::new(0.0, 0.0, 0.0, SCRIM_ALPHA), None),
+        );
+        root_canvas.restore();
+        if let Some(rrect) = &highlight_rrect {
+            let mut outline = Paint::new(outline_color, None);
+            outline.set_style(PaintStyle::Stroke);
+            outline.set_stroke_width(2.0);
+            outline.set_anti_alias(true);
+            root_canvas.draw_rrect(rrect, &outline);
+        }
+
+        let columns = MAX_COLUMNS.min(max_columns - 2 * PADDING);
+        let text = step
+            .text
+            .replace("{config}", &config_path().to_string_lossy());
+        let mut lines = vec![step.title.to_string(), String::new()];
+        lines.extend(wrap_text(&text, columns));
+        lines.push(String::new());
+        lines.push(format!(
+            "{}/{}  ← previous  → next  Esc close",
+            self.step.unwrap_or_default() + 1,
+            STEPS.len()
+        ));
+
+        let size = PixelSize::new(
+            (columns + 2 * PADDING) as f32 * cell_width,
+            (lines.len() + 2) as f32 * line_height,
+        );
+        let x = region.min.x + ((region.max.x - region.min.x - size.width) / 2.0).max(0.0);
+        let mut y = region.min.y + ((region.max.y - region.min.y - size.height) / 2.0).max(0.0);
+        // Keep the box off the highlighted cursor
+        if let Some(highlight) = highlight.filter(|_| step.highlight == TourHighlight::Cursor) {
+            if y < highlight.max.y + line_height && y + size.height > highlight.min.y - line_height
+            {
+                y = if highlight.max.y + line_height + size.height <= region.max.y {
+                    highlight.max.y + line_height
+                } else {
+                    (highlight.min.y - line_height - size.height).max(region.min.y)
+                };
+            }
+        }
+        let origin = PixelPos::new(x.round(), y.round());
+        let rect = PixelRect::from_origin_and_size(origin, size);
+
+        let blur_amount = renderer_settings.floating_blur.then_some((
+            renderer_settings.floating_blur_amount_x,
+            renderer_settings.floating_blur_amount_y,
+        ));
+        begin_floating_box(
+            root_canvas,
+            &rect,
+            radius,
+            blur_amount,
+            1.0,
+            default_style.background(&default_style.colors).to_color(),
+        );
+        root_canvas.translate((origin.x, origin.y));
+
+        let mut title_style = Style::new(Colors::new(None, None, None));
+        title_style.bold = true;
+        let title_style = Some(Arc::new(title_style));
+        for (row, line) in lines.iter().enumerate() {
+            let style = if row == 0 { &title_style } else { &None };
+            grid_renderer.draw_foreground(
+                root_canvas,
+                line,
+                GridPos::new(PADDING as i32, row as i32 + 1),
+                columns as i32,
+                style,
+            );
+        }
+        end_floating_box(root_canvas);
+
+        // The outline is drawn around the box too, so it stands out from the dimmed window
+        outline_color.a = 0.5;
+        let mut outline = Paint::new(outline_color, None);
+        outline.set_style(PaintStyle::Stroke);
+        outline.set_anti_alias(true);
+        root_canvas.draw_rrect(
+            RRect::new_rect_xy(to_skia_rect(&rect), radius, radius),
+            &outline,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigating_the_steps() {
+        assert_eq!(navigate(0, 3, TourAction::Next), Some(1));
+        assert_eq!(navigate(2, 3, TourAction::Next), None);
+        assert_eq!(navigate(0, 3, TourAction::Previous), Some(0));
+        assert_eq!(navigate(2, 3, TourAction::Previous), Some(1));
+        assert_eq!(navigate(1, 3, TourAction::Close), None);
+    }
+
+    #[test]
+    fn text_is_wrapped_between_words() {
+        assert_eq!(
+            wrap_text("Set the scale factor to zoom", 12),
+            vec!["Set the", "scale factor", "to zoom"]
+        );
+        assert_eq!(
+            wrap_text("g:neovide_scale_factor zooms", 10),
+            vec!["g:neovide…", "zooms"]
+        );
+        assert!(wrap_text("", 10).is_empty());
+    }
+}
@@ -10,7 +10,7 @@ mod vsync_win_swap_chain;
 
 use std::sync::Arc;
 
-use winit::{event_loop::EventLoopProxy, window::Window};
+use winit::{event_loop::EventLoopProxy, monitor::MonitorHandle, window::Window};
 
 use crate::{
     renderer::SkiaRenderer, settings::Settings, window::UserEvent, window::WindowSettings,
@@ -27,7 +27,8 @@ pub use vsync_macos_display_link::VSyncMacosDisplayLink;
 
 #[allow(dead_code)]
 pub enum VSync {
-    Opengl(),
+    /// Remembers the monitor the swap interval was last applied for
+    Opengl(Option<MonitorHandle>),
     WinitThrottling(),
     Timer(VSyncTimer),
     #[cfg(target_os = "windows")]
@@ -81,10 +82,22 @@ impl VSync {
         return matches!(self, VSync::WinitThrottling());
     }
 
-    pub fn update(&mut self, #[allow(unused_variables)] window: &Window) {
+    /// Follows the window to the monitor it's on, which can have another refresh rate than the
+    /// one it was on before
+    pub fn update(&mut self, renderer: &mut dyn SkiaRenderer) {
         match self {
+            VSync::Opengl(monitor) => {
+                let current_monitor = renderer.window().current_monitor();
+                if *monitor != current_monitor {
+                    log::trace!("Window moved to another monitor, refreshing the swap interval");
+                    renderer.refresh_swap_interval();
+                    *monitor = current_monitor;
+                }
+            }
+            #[cfg(target_os = "windows")]
+            VSync::WindowsDwm(vsync) => vsync.update(renderer.window()),
             #[cfg(target_os = "macos")]
-            VSync::MacosDisplayLink(vsync) => vsync.update(window),
+            VSync::MacosDisplayLink(vsync) => vsync.update(renderer.window()),
             _ => {}
         }
     }
@@ -1,6 +1,6 @@
 use std::{
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU32, Ordering},
         Arc,
     },
     thread::{spawn, JoinHandle},
@@ -11,7 +11,7 @@ use spin_sleep::SpinSleeper;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::Graphics::Dwm::{DwmGetCompositionTimingInfo, DWM_TIMING_INFO};
 use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
-use winit::event_loop::EventLoopProxy;
+use winit::{event_loop::EventLoopProxy, window::Window};
 
 use crate::{
     profiling::{tracy_plot, tracy_zone},
@@ -22,8 +22,14 @@ pub struct VSyncWinDwm {
     should_exit: Arc<AtomicBool>,
     vsync_thread: Option<JoinHandle<()>>,
     redraw_requested: Arc<AtomicBool>,
+    // The refresh rate of the monitor the window is on in millihertz, 0 when unknown
+    monitor_refresh_rate: Arc<AtomicU32>,
 }
 
+// How far the refresh period of the monitor can be from the one of the compositor before the
+// compositor timing is considered to be for another monitor
+const PERIOD_TOLERANCE: f64 = 0.1;
+
 /// Calculates the time until the vblank, taking into account that the vblank is cyclic, so this
 /// always finds the next vblank forward
 fn time_until_vblank_forward(delay: f64, period: f64) -> f64 {
@@ -42,12 +48,22 @@ fn vblank_wait_time(delay: f64, period: f64, offset: f64) -> f64 {
     time_until_vblank
 }
 
+/// The compositor reports the timing of the primary monitor, which is off when the window is on a
+/// monitor with another refresh rate. Then there's no vblank to align to, and the period of the
+/// monitor is used instead.
+fn monitor_period(dwm_period: f64, monitor_period: Option<f64>) -> Option<f64> {
+    monitor_period.filter(|monitor_period| {
+        (monitor_period - dwm_period).abs() > dwm_period * PERIOD_TOLERANCE
+    })
+}
+
 impl VSyncWinDwm {
     // On Windows the fake vsync is always enabled
     // Everything else is very jerky
-    pub fn new(proxy: EventLoopProxy<UserEvent>) -> Self {
+    pub fn new(window: &Window, proxy: EventLoopProxy<UserEvent>) -> Self {
         let should_exit = Arc::new(AtomicBool::new(false));
         let redraw_requested = Arc::new(AtomicBool::new(false));
+        let monitor_refresh_rate = Arc::new(AtomicU32::new(0));
 
         // When using OpenGL on Windows in windowed mode, swap_buffers does not seem to be
         // synchronized with the Desktop Window Manager. So work around that by manually waiting
@@ -56,6 +72,7 @@ impl VSyncWinDwm {
         let vsync_thread = {
             let should_exit = Arc::clone(&should_exit);
             let redraw_requested = Arc::clone(&redraw_requested);
+            let monitor_refresh_rate = Arc::clone(&monitor_refresh_rate);
             Some(spawn(move || {
                 let performance_frequency = unsafe {
                     let mut performance_frequency = 0;
@@ -80,8 +97,16 @@ impl VSyncWinDwm {
                             / performance_frequency)
                             .max(0.001);
 
-                        // Target the middle of the vblank, which gives maximum time for both us and the compositor
-                        let sleep_time = vblank_wait_time(vblank_delay, period, 0.5 * period);
+                        let monitor_refresh_rate = monitor_refresh_rate.load(Ordering::Relaxed);
+                        let sleep_time = match monitor_period(
+                            period,
+                            (monitor_refresh_rate > 0)
+                                .then(|| 1000.0 / monitor_refresh_rate as f64),
+                        ) {
+                            Some(monitor_period) => monitor_period,
+                            // Target the middle of the vblank, which gives maximum time for both us and the compositor
+                            None => vblank_wait_time(vblank_delay, period, 0.5 * period),
+                        };
                         sleeper.sleep(Duration::from_secs_f64(sleep_time));
 
                         (vblank_delay, sleep_time)
@@ -96,15 +121,27 @@ impl VSyncWinDwm {
             }))
         };
 
-        Self {
+        let mut vsync = Self {
             should_exit,
             vsync_thread,
             redraw_requested,
-        }
+            monitor_refresh_rate,
+        };
+        vsync.update(window);
+        vsync
     }
 
     pub fn wait_for_vsync(&mut self) {}
 
+    pub fn update(&mut self, window: &Window) {
+        let refresh_rate = window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .unwrap_or(0);
+        self.monitor_refresh_rate
+            .store(refresh_rate, Ordering::Relaxed);
+    }
+
     pub fn request_redraw(&mut self) {
         self.redraw_requested.store(true, Ordering::Relaxed);
     }
@@ -155,6 +192,20 @@ mod tests {
         assert_abs_diff_eq!(vblank_wait_time(0.006739, 1.0 / 144.0, 0.0), 0.006739);
     }
 
+    #[test]
+    fn test_monitor_period() {
+        assert_eq!(monitor_period(1.0 / 60.0, None), None);
+        assert_eq!(monitor_period(1.0 / 60.0, Some(1.0 / 59.94)), None);
+        assert_eq!(
+            monitor_period(1.0 / 60.0, Some(1.0 / 120.0)),
+            Some(1.0 / 120.0)
+        );
+        assert_eq!(
+            monitor_period(1.0 / 144.0, Some(1.0 / 60.0)),
+            Some(1.0 / 60.0)
+        );
+    }
+
     #[test]
     fn test_vblank_wait_time_with_half_offset() {
         assert_abs_diff_eq!(vblank_wait_time(0.3, 1.0, 0.5), 0.8);
@@ -0,0 +1,361 @@
+use std::sync::Arc;
+
+use glamour::Contains;
+use skia_safe::{Canvas, Paint, Rect};
+
+use crate::{
+    editor::{Colors, Style, Wildmenu},
+    profiling::tracy_zone,
+    renderer::{
+        animation_utils::CriticallyDampedSpringAnimation,
+        cmdline_renderer::{begin_floating_box, end_floating_box, floating_box_radius},
+        popupmenu::{scroll_top, text_width, truncate},
+        GridRenderer, RendererSettings,
+    },
+    settings::*,
+    units::{GridPos, PixelPos, PixelRect, PixelSize},
+};
+
+/// Columns of padding on each side of the items.
+const PADDING: usize = 1;
+/// Columns between the items of the horizontal layout.
+const GAP: usize = 2;
+
+#[derive(Clone, SettingGroup)]
+#[setting_prefix = "wildmenu"]
+pub struct WildmenuSettings {
+    pub enabled: bool,
+    layout: String,
+    max_items: u32,
+    animation_length: f32,
+}
+
+impl Default for WildmenuSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            layout: "vertical".to_string(),
+            max_items: 10,
+            animation_length: 0.1,
+        }
+    }
+}
+
+impl WildmenuSettings {
+    fn is_horizontal(&self) -> bool {
+        self.layout == "horizontal"
+    }
+}
+
+/// The keys moving the selection of the wildmenu from `selected` to `target`.
+pub fn selection_keys(selected: Option<usize>, target: usize) -> String {
+    // Without a selection, the first <C-n> selects the first item
+    let from = selected.map_or(-1, |selected| selected as isize);
+    let steps = target as isize - from;
+    let key = if steps > 0 { "<C-n>" } else { "<C-p>" };
+    key.repeat(steps.unsigned_abs())
+}
+
+/// The first item shown by the horizontal layout, so that the selected one stays in view.
+fn first_visible_item(
+    widths: &[usize],
+    current: usize,
+    selected: Option<usize>,
+    columns: usize,
+) -> usize {
+    let mut first = current.min(widths.len().saturating_sub(1));
+    if let Some(selected) = selected {
+        if selected < first {
+            first = selected;
+        } else {
+            let span = |first: usize| {
+                widths[first..=selected].iter().sum::<usize>() + GAP * (selected - first)
+            };
+            while first < selected && span(first) > columns {
+                first += 1;
+            }
+        }
+    }
+    first
+}
+
+/// Draws the completions of Neovim's externalized wildmenu above the command line.
+pub struct WildmenuRenderer {
+    wildmenu: Option<Wildmenu>,
+    /// The first visible item, of the list or of the row.
+    first_item: usize,
+    /// Offset of the selection highlight from the selected item, in rows, animated towards 0.
+    selection_animation: CriticallyDampedSpringAnimation,
+    /// Where the box and each of the visible items were last drawn, to find the clicked item.
+    box_rect: Option<PixelRect<f32>>,
+    item_rects: Vec<(usize, PixelRect<f32>)>,
+
+    settings: Arc<Settings>,
+}
+
+impl WildmenuRenderer {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            wildmenu: None,
+            first_item: 0,
+            selection_animation: CriticallyDampedSpringAnimation::new(),
+            box_rect: None,
+            item_rects: Vec::new(),
+            settings,
+        }
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.wildmenu.as_ref()?.selected
+    }
+
+    /// Whether the items are shown as a list, which is browsed with the up and down keys.
+    pub fn is_vertical(&self) -> bool {
+        self.wildmenu.is_some() && !self.settings.get::<WildmenuSettings>().is_horizontal()
+    }
+
+    pub fn set_wildmenu(&mut self, wildmenu: Option<Wildmenu>) {
+        let previous_selection = self
+            .wildmenu
+            .as_ref()
+            .filter(|previous| Some(&previous.items) == wildmenu.as_ref().map(|menu| &menu.items))
+            .and_then(|previous| previous.selected);
+        match (
+            previous_selection,
+            wildmenu.as_ref().and_then(|menu| menu.selected),
+        ) {
+            (Some(previous), Some(selected)) => {
+                self.selection_animation.position += previous as f32 - selected as f32;
+            }
+            _ => self.selection_animation.reset(),
+        }
+        if wildmenu.is_none() {
+            self.first_item = 0;
+            self.box_rect = None;
+            self.item_rects.clear();
+        }
+        self.wildmenu = wildmenu;
+    }
+
+    pub fn animate(&mut self, dt: f32) -> bool {
+        if self.wildmenu.is_none() {
+            return false;
+        }
+        let animation_length = self.settings.get::<WildmenuSettings>().animation_length;
+        self.selection_animation.update(dt, animation_length)
+    }
+
+    pub fn hit_test(&self, position: PixelPos<f32>) -> Option<usize> {
+        self.item_rects
+            .iter()
+            .find(|(_, rect)| rect.contains(&position))
+            .map(|(index, _)| *index)
+    }
+
+    pub fn contains(&self, position: PixelPos<f32>) -> bool {
+        self.box_rect.is_some_and(|rect| rect.contains(&position))
+    }
+
+    /// Draws the items above `anchor`, or below it when there isn't enough room, keeping them
+    /// inside `region`.
+    pub fn draw(
+        &mut self,
+        root_canvas: &Canvas,
+        anchor: PixelRect<f32>,
+        region: PixelRect<f32>,
+        grid_renderer: &mut GridRenderer,
+        renderer_settings: &RendererSettings,
+    ) {
+        tracy_zone!("wildmenu_draw");
+        self.box_rect = None;
+        self.item_rects.clear();
+        let Some(wildmenu) = &self.wildmenu else {
+            return;
+        };
+        if wildmenu.items.is_empty() {
+            return;
+        }
+        let settings = self.settings.get::<WildmenuSettings>();
+        let grid_scale = grid_renderer.grid_scale;
+        let cell_width = grid_scale.width();
+        let line_height = grid_scale.height();
+
+        let max_columns = ((region.max.x - region.min.x) / cell_width).floor() as usize;
+        if max_columns <= 2 * PADDING {
+            return;
+        }
+        let widths = wildmenu
+            .items
+            .iter()
+            .map(|item| text_width(item))
+            .collect::<Vec<_>>();
+
+        let space_above = ((anchor.min.y - region.min.y) / line_height).floor() as usize;
+        let space_below = ((region.max.y - anchor.max.y) / line_height).floor() as usize;
+        let horizontal = settings.is_horizontal();
+        let (columns, rows) = if horizontal {
+            let anchor_columns = ((anchor.max.x - anchor.min.x) / cell_width).floor() as usize;
+            (anchor_columns.clamp(2 * PADDING + 1, max_columns), 1)
+        } else {
+            let item_columns = widths.iter().copied().max().unwrap_or(0) + 2 * PADDING;
+            let wanted_rows = wildmenu.items.len().min(settings.max_items.max(1) as usize);
+            (
+                item_columns.min(max_columns),
+                wanted_rows.min(space_above.max(space_below)),
+            )
+        };
+        if rows == 0 {
+            return;
+        }
+        let above = space_above >= rows || space_above >= space_below;
+        let text_columns = columns - 2 * PADDING;
+
+        let size = PixelSize::new(columns as f32 * cell_width, rows as f32 * line_height);
+        let x = anchor
+            .min
+            .x
+            .min(region.max.x - size.width)
+            .max(region.min.x);
+        let y = if above {
+            anchor.min.y - size.height
+        } else {
+            anchor.max.y
+        };
+        let origin = PixelPos::new(x.round(), y.round());
+        let rect = PixelRect::from_origin_and_size(origin, size);
+
+        let default_style = grid_renderer.default_style.clone();
+        let default_colors = &default_style.colors;
+        let normal_style = wildmenu.styles.normal.clone();
+        let selected_style = wildmenu.styles.selected.clone().or_else(|| {
+            let mut style = Style::new(Colors::new(None, None, None));
+            style.reverse = true;
+            Some(Arc::new(style))
+        });
+        let background_of = |style: &Option<Arc<Style>>| {
+            style
+                .as_deref()
+                .unwrap_or(&default_style)
+                .background(default_colors)
+        };
+
+        let radius = floating_box_radius(renderer_settings, line_height);
+        let blur_amount = renderer_settings.floating_blur.then_some((
+            renderer_settings.floating_blur_amount_x,
+            renderer_settings.floating_blur_amount_y,
+        ));
+        begin_floating_box(
+            root_canvas,
+            &rect,
+            radius,
+            blur_amount,
+            1.0,
+            background_of(&normal_style).to_color(),
+        );
+        root_canvas.translate((origin.x, origin.y));
+        self.box_rect = Some(rect);
+
+        // The cells taken by each visible item, from the left of the box and the top row
+        let mut visible_items = Vec::new();
+        if horizontal {
+            self.first_item =
+                first_visible_item(&widths, self.first_item, wildmenu.selected, text_columns);
+            let mut column = PADDING;
+            for (index, width) in widths.iter().enumerate().skip(self.first_item) {
+                if column >= columns - PADDING {
+                    break;
+                }
+                let width = (*width).min(columns - PADDING - column);
+                visible_items.push((index, column, 0, width));
+                column += width + GAP;
+            }
+        } else {
+            self.first_item = scroll_top(
+                self.first_item,
+                wildmenu.selected,
+                rows,
+                wildmenu.items.len(),
+            );
+            for row in 0..rows.min(wildmenu.items.len() - self.first_item) {
+                visible_items.push((self.first_item + row, PADDING, row, text_columns));
+            }
+        }
+
+        if let Some(selected) = wildmenu.selected {
+            let paint = Paint::new(background_of(&selected_style), None);
+            if horizontal {
+                if let Some((_, column, _, width)) =
+                    visible_items.iter().find(|(index, ..)| *index == selected)
+                {
+                    root_canvas.draw_rect(
+                        Rect::from_xywh(
+                            (*column as f32 - 1.0) * cell_width,
+                            0.0,
+                            (*width as f32 + 2.0) * cell_width,
+                            line_height,
+                        ),
+                        &paint,
+                    );
+                }
+            } else {
+                let row =
+                    selected as f32 - self.first_item as f32 + self.selection_animation.position;
+                root_canvas.draw_rect(
+                    Rect::from_xywh(0.0, row * line_height, size.width, line_height),
+                    &paint,
+                );
+            }
+        }
+
+        for &(index, column, row, width) in &visible_items {
+            let style = if wildmenu.selected == Some(index) {
+                &selected_style
+            } else {
+                &normal_style
+            };
+            grid_renderer.draw_foreground(
+                root_canvas,
+                &truncate(&wildmenu.items[index], width),
+                GridPos::new(column as i32, row as i32),
+                width as i32,
+                style,
+            );
+            let item_rect = if horizontal {
+                PixelRect::from_origin_and_size(
+                    PixelPos::new(origin.x + column as f32 * cell_width, origin.y),
+                    PixelSize::new(width as f32 * cell_width, line_height),
+                )
+            } else {
+                PixelRect::from_origin_and_size(
+                    PixelPos::new(origin.x, origin.y + row as f32 * line_height),
+                    PixelSize::new(size.width, line_height),
+                )
+            };
+            self.item_rects.push((index, item_rect));
+        }
+
+        end_floating_box(root_canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clicks_move_the_selection_with_keys() {
+        assert_eq!(selection_keys(None, 0), "<C-n>");
+        assert_eq!(selection_keys(Some(1), 3), "<C-n><C-n>");
+        assert_eq!(selection_keys(Some(3), 1), "<C-p><C-p>");
+        assert_eq!(selection_keys(Some(2), 2), "");
+    }
+
+    #[test]
+    fn horizontal_selection_stays_visible() {
+        let widths = [4, 4, 4, 4];
+        assert_eq!(first_visible_item(&widths, 0, Some(1), 10), 0);
+        assert_eq!(first_visible_item(&widths, 0, Some(3), 10), 2);
+        assert_eq!(first_visible_item(&widths, 2, Some(0), 10), 0);
+        assert_eq!(first_visible_item(&widths, 2, None, 10), 2);
+    }
+}
@@ -0,0 +1,86 @@
+//! The rounded corners and the border of the frameless windows on Linux, where the compositors
+//! leave the windows without decorations square, unlike macOS and Windows 11.
+
+use skia_safe::{Canvas, Color4f, Paint, PaintStyle, RRect, Rect};
+
+use crate::frame::Frame;
+
+/// Width of the border, in logical pixels.
+const BORDER_WIDTH: f32 = 1.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowShape {
+    pub rrect: RRect,
+    border: Option<(Color4f, f32)>,
+}
+
+fn parse_color(color: &str) -> Option<Color4f> {
+    let [red, green, blue, alpha] = csscolorparser::parse(color).ok()?.to_array();
+    Some(Color4f::new(red, green, blue, alpha))
+}
+
+/// The shape of a window of `width` by `height` pixels, none for the decorated windows and when
+/// there's neither a radius nor a border. `corner_radius` is in logical pixels.
+pub fn window_shape(
+    width: f32,
+    height: f32,
+    scale_factor: f32,
+    frame: Frame,
+    corner_radius: f32,
+    border_color: &str,
+) -> Option<WindowShape> {
+    let border = parse_color(border_color).map(|color| (color, BORDER_WIDTH * scale_factor));
+    if frame == Frame::Full || (corner_radius <= 0.0 && border.is_none()) {
+        return None;
+    }
+    let radius = (corner_radius * scale_factor).clamp(0.0, width.min(height) / 2.0);
+    Some(WindowShape {
+        rrect: RRect::new_rect_xy(Rect::from_wh(width, height), radius, radius),
+        border,
+    })
+}
+
+impl WindowShape {
+    /// Clips everything drawn after it to the shape, and fills it with `background`, the rest of
+    /// the window staying transparent.
+    pub fn clip(&self, canvas: &Canvas, background: Color4f) {
+        canvas.clear(Color4f::new(0.0, 0.0, 0.0, 0.0));
+        canvas.clip_rrect(self.rrect, None, Some(true));
+        canvas.draw_paint(&Paint::new(background, None));
+    }
+
+    pub fn draw_border(&self, canvas: &Canvas) {
+        let Some((color, width)) = self.border else {
+            return;
+        };
+        let mut paint = Paint::new(color, None);
+        paint.set_anti_alias(true);
+        paint.set_style(PaintStyle::Stroke);
+        paint.set_stroke_width(width);
+        canvas.draw_rrect(self.rrect.with_inset((width / 2.0, width / 2.0)), &paint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_frameless_windows_are_shaped() {
+        assert_eq!(
+            window_shape(800.0, 600.0, 2.0, Frame::Full, 8.0, "red"),
+            None
+        );
+        assert_eq!(window_shape(800.0, 600.0, 2.0, Frame::None, 0.0, ""), None);
+
+        let shape = window_shape(800.0, 600.0, 2.0, Frame::None, 8.0, "#ff0000").unwrap();
+        assert_eq!(
+            shape.rrect.radii(skia_safe::rrect::Corner::UpperLeft).x,
+            16.0
+        );
+        assert_eq!(shape.border, Some((Color4f::new(1.0, 0.0, 0.0, 1.0), 2.0)));
+
+        let shape = window_shape(800.0, 600.0, 1.0, Frame::Custom, 8.0, "not a color").unwrap();
+        assert_eq!(shape.border, None);
+    }
+}
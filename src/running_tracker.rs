@@ -1,31 +1,159 @@
 use std::{
     process::ExitCode,
     sync::{
-        atomic::{AtomicU8, Ordering},
+        atomic::{AtomicBool, AtomicU8, Ordering},
         Arc,
     },
 };
 
 use log::info;
+use parking_lot::Mutex;
+use tokio::sync::Notify;
 
 #[derive(Clone)]
 pub struct RunningTracker {
     exit_code: Arc<AtomicU8>,
+    restart_requested: Arc<AtomicBool>,
+    multigrid_toggle_requested: Arc<AtomicBool>,
+    quit_requested: Arc<AtomicBool>,
+    crashed: Arc<AtomicBool>,
+    // The files open in Neovim, so that they can be recovered after a crash
+    open_files: Arc<Mutex<Vec<String>>>,
+    // The working directory of Neovim when it quit, where a restarted instance continues
+    cwd: Arc<Mutex<Option<String>>>,
+    exit_prompt_answered: Arc<Notify>,
 }
 
 impl RunningTracker {
     pub fn new() -> Self {
         Self {
             exit_code: Arc::new(AtomicU8::new(0)),
+            restart_requested: Arc::new(AtomicBool::new(false)),
+            multigrid_toggle_requested: Arc::new(AtomicBool::new(false)),
+            quit_requested: Arc::new(AtomicBool::new(false)),
+            crashed: Arc::new(AtomicBool::new(false)),
+            open_files: Arc::new(Mutex::new(Vec::new())),
+            cwd: Arc::new(Mutex::new(None)),
+            exit_prompt_answered: Arc::new(Notify::new()),
         }
     }
 
     pub fn quit_with_code(&self, code: u8, reason: &str) {
         self.exit_code.store(code, Ordering::Release);
+        self.quit_requested.store(true, Ordering::Release);
         info!("Quit with code {}: {}", code, reason);
     }
 
+    /// Returns `true` if Neovim asked to quit, and resets the state for a restarted instance.
+    pub fn take_quit_request(&self) -> bool {
+        self.quit_requested.swap(false, Ordering::AcqRel)
+    }
+
+    pub fn mark_crashed(&self) {
+        self.crashed.store(true, Ordering::Release);
+        self.exit_code.store(1, Ordering::Release);
+        info!("Neovim exited without quitting");
+    }
+
+    pub fn crashed(&self) -> bool {
+        self.crashed.load(Ordering::Acquire)
+    }
+
+    pub fn set_open_files(&self, files: Vec<String>) {
+        *self.open_files.lock() = files;
+    }
+
+    pub fn open_files(&self) -> Vec<String> {
+        self.open_files.lock().clone()
+    }
+
+    /// Marks that Neovim should be started again when it quits, instead of closing the window.
+    pub fn set_restart_requested(&self, restart: bool) {
+        self.restart_requested.store(restart, Ordering::Release);
+    }
+
+    pub fn take_restart_request(&self) -> bool {
+        self.restart_requested.swap(false, Ordering::AcqRel)
+    }
+
+    /// Marks that the restarted Neovim should switch between multigrid and a single grid.
+    pub fn set_multigrid_toggle_requested(&self, toggle: bool) {
+        self.set_restart_requested(toggle);
+        self.multigrid_toggle_requested
+            .store(toggle, Ordering::Release);
+    }
+
+    pub fn take_multigrid_toggle_request(&self) -> bool {
+        self.multigrid_toggle_requested
+            .swap(false, Ordering::AcqRel)
+    }
+
+    pub fn set_cwd(&self, cwd: Option<String>) {
+        *self.cwd.lock() = cwd;
+    }
+
+    pub fn cwd(&self) -> Option<String> {
+        self.cwd.lock().clone()
+    }
+
+    /// The exit code Neovim quit with, when it isn't zero.
+    pub fn failed_exit_code(&self) -> Option<u8> {
+        Some(self.exit_code.load(Ordering::Acquire)).filter(|code| *code != 0)
+    }
+
+    /// Answers the prompt shown when Neovim quit with an error, by restarting it or closing the
+    /// window.
+    pub fn answer_exit_prompt(&self, restart: bool) {
+        if restart {
+            self.exit_code.store(0, Ordering::Release);
+        }
+        self.set_restart_requested(restart);
+        self.exit_prompt_answered.notify_one();
+    }
+
+    /// Waits for `answer_exit_prompt`, returning `true` when Neovim should be restarted.
+    pub async fn wait_for_exit_prompt(&self) -> bool {
+        self.exit_prompt_answered.notified().await;
+        self.take_restart_request()
+    }
+
     pub fn exit_code(&self) -> ExitCode {
         ExitCode::from(self.exit_code.load(Ordering::Acquire))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_keeps_the_open_files() {
+        let tracker = RunningTracker::new();
+        // The bridge and the window share the state through clones
+        let bridge = tracker.clone();
+        bridge.set_open_files(vec!["foo.rs".to_string(), "bar.md".to_string()]);
+        assert!(!tracker.crashed());
+        assert_eq!(tracker.failed_exit_code(), None);
+
+        bridge.mark_crashed();
+        assert!(tracker.crashed());
+        assert_eq!(tracker.failed_exit_code(), Some(1));
+        assert_eq!(tracker.open_files(), vec!["foo.rs", "bar.md"]);
+        // A crash isn't a quit asked by Neovim
+        assert!(!tracker.take_quit_request());
+    }
+
+    #[test]
+    fn multigrid_toggle_restarts_once() {
+        let tracker = RunningTracker::new();
+        tracker.set_multigrid_toggle_requested(true);
+        assert!(tracker.take_restart_request());
+        assert!(tracker.take_multigrid_toggle_request());
+        assert!(!tracker.take_multigrid_toggle_request());
+
+        // A plain restart keeps the grid as it is
+        tracker.set_restart_requested(true);
+        assert!(tracker.take_restart_request());
+        assert!(!tracker.take_multigrid_toggle_request());
+    }
+}
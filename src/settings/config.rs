@@ -43,6 +43,8 @@ pub struct Config {
     pub maximized: Option<bool>,
     pub neovim_bin: Option<PathBuf>,
     pub no_multigrid: Option<bool>,
+    pub remote_control: Option<bool>,
+    pub single_instance: Option<bool>,
     pub srgb: Option<bool>,
     pub tabs: Option<bool>,
     pub theme: Option<String>,
@@ -51,11 +53,14 @@ pub struct Config {
     pub vsync: Option<bool>,
     pub wsl: Option<bool>,
     pub backtraces_path: Option<PathBuf>,
+    /// Values of the `g:neovide_*` settings, named without the prefix.
+    pub settings: Option<toml::Table>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum HotReloadConfigs {
     Font(Option<FontSettings>),
+    Settings(toml::Table),
 }
 
 impl Config {
@@ -81,6 +86,12 @@ impl Config {
         if let Some(no_multigrid) = self.no_multigrid {
             env::set_var("NEOVIDE_NO_MULTIGRID", no_multigrid.to_string());
         }
+        if let Some(remote_control) = self.remote_control {
+            env::set_var("NEOVIDE_REMOTE_CONTROL", remote_control.to_string());
+        }
+        if let Some(single_instance) = self.single_instance {
+            env::set_var("NEOVIDE_SINGLE_INSTANCE", single_instance.to_string());
+        }
         if let Some(maximized) = self.maximized {
             env::set_var("NEOVIDE_MAXIMIZED", maximized.to_string());
         }
@@ -128,17 +139,89 @@ impl Config {
                 e
             )
         })?;
-        let config = toml::from_str(&toml).map_err(|e| {
+        let parse_error = |e: &dyn std::fmt::Display| {
             format!(
                 "Error while parsing config file {}:\n{}\nContinuing with default config.",
                 path.to_string_lossy(),
                 e
             )
-        })?;
+        };
+        let table = toml.parse::<toml::Table>().map_err(|e| parse_error(&e))?;
+        let config = toml::Value::Table(merge_platform_tables(table))
+            .try_into()
+            .map_err(|e| parse_error(&e))?;
         Ok(config)
     }
 }
 
+/// The table applied when Neovim runs in WSL. It can't be named `wsl`, which is the option.
+pub const WSL_TABLE: &str = "wsl-overrides";
+/// The tables of the config file overriding the rest on a platform, in the order they're applied.
+pub const PLATFORM_TABLES: &[&str] = &["windows", "macos", "linux", WSL_TABLE];
+
+/// Whether Neovim runs in WSL, with `wsl = true` in the config file, `$NEOVIDE_WSL` or `--wsl`,
+/// or Neovide itself runs in a WSL distribution.
+fn uses_wsl(table: &toml::Table) -> bool {
+    table.get("wsl").and_then(toml::Value::as_bool) == Some(true)
+        || env::var("NEOVIDE_WSL").is_ok_and(|wsl| wsl == "true" || wsl == "1")
+        || env::args().any(|arg| arg == "--wsl")
+        || env::var_os("WSL_DISTRO_NAME").is_some()
+}
+
+/// Merges `overrides` into `base`, table by table, so that `[linux.font]` can only change the size
+/// of the font for example.
+fn merge_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overrides)) => {
+                merge_tables(base, overrides)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Applies the `[windows]`, `[macos]`, `[linux]` and `[wsl-overrides]` tables of the config file
+/// over the rest when Neovide runs there, and drops the other ones.
+pub fn merge_platform_tables(mut table: toml::Table) -> toml::Table {
+    let mut platform_tables = PLATFORM_TABLES
+        .iter()
+        .filter_map(|name| match table.remove(*name) {
+            Some(toml::Value::Table(overrides)) => Some((*name, overrides)),
+            // Not a table, left for the parsing to report
+            Some(value) => {
+                table.insert(name.to_string(), value);
+                None
+            }
+            None => None,
+        })
+        .collect::<Vec<_>>();
+
+    let os = if cfg!(target_os = "windows") {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "macos"
+    } else {
+        "linux"
+    };
+    if let Some(index) = platform_tables.iter().position(|(name, _)| *name == os) {
+        let (_, overrides) = platform_tables.remove(index);
+        merge_tables(&mut table, overrides);
+    }
+    if let Some(index) = platform_tables
+        .iter()
+        .position(|(name, _)| *name == WSL_TABLE)
+    {
+        let (_, overrides) = platform_tables.remove(index);
+        if uses_wsl(&table) {
+            merge_tables(&mut table, overrides);
+        }
+    }
+    table
+}
+
 fn watcher_thread(init_config: Config, event_loop_proxy: EventLoopProxy<UserEvent>) {
     let (tx, rx) = mpsc::channel();
     let mut debouncer = new_debouncer(Duration::from_millis(500), None, tx).unwrap();
@@ -184,6 +267,89 @@ fn watcher_thread(init_config: Config, event_loop_proxy: EventLoopProxy<UserEven
                 ))))
                 .unwrap();
         }
+        if config.settings != previous_config.settings {
+            event_loop_proxy
+                .send_event(UserEvent::ConfigsChanged(Box::new(
+                    HotReloadConfigs::Settings(config.settings.clone().unwrap_or_default()),
+                )))
+                .unwrap();
+        }
         previous_config = config;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::fonts::font_options::FontDescription;
+
+    #[test]
+    fn platform_tables_override_the_base_config() {
+        let table = r#"
+            frame = "full"
+
+            [font]
+            normal = "Fira Code"
+            size = 14
+
+            [windows]
+            frame = "none"
+            wsl = true
+            font.size = 11
+
+            [macos]
+            frame = "transparent"
+            wsl = true
+            font.size = 12
+
+            [linux]
+            frame = "none"
+            wsl = true
+            font.size = 13
+
+            [wsl-overrides]
+            idle = false
+        "#
+        .parse::<toml::Table>()
+        .unwrap();
+        let config: Config = toml::Value::Table(merge_platform_tables(table))
+            .try_into()
+            .unwrap();
+
+        // The frames of macOS don't even parse elsewhere, the other tables are dropped
+        #[cfg(target_os = "windows")]
+        let (frame, size) = (Frame::None, 11.0);
+        #[cfg(target_os = "macos")]
+        let (frame, size) = (Frame::Transparent, 12.0);
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        let (frame, size) = (Frame::None, 13.0);
+        assert_eq!(config.frame, Some(frame));
+        let font = config.font.unwrap();
+        assert_eq!(font.size, size);
+        let normal = Vec::<FontDescription>::from(font.normal);
+        assert_eq!(normal[0].family, "Fira Code");
+        assert_eq!(config.wsl, Some(true));
+        assert_eq!(config.idle, Some(false));
+    }
+
+    #[test]
+    fn wsl_table_and_option_coexist() {
+        let table = r#"
+            wsl = true
+            idle = true
+
+            [wsl-overrides]
+            idle = false
+            font.size = 10
+        "#
+        .parse::<toml::Table>()
+        .unwrap();
+        let config: Config = toml::Value::Table(merge_platform_tables(table))
+            .try_into()
+            .unwrap();
+
+        assert_eq!(config.wsl, Some(true));
+        assert_eq!(config.idle, Some(false));
+        assert_eq!(config.font.unwrap().size, 10.0);
+    }
+}
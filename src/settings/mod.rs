@@ -1,29 +1,40 @@
 mod font;
 mod from_value;
+mod schema;
+mod snapshot;
+mod verify;
 mod window_size;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use log::trace;
 use nvim_rs::Neovim;
 use parking_lot::RwLock;
 use rmpv::Value;
 use std::{
     any::{Any, TypeId},
+    cell::RefCell,
     collections::HashMap,
     convert::TryInto,
     fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 use winit::event_loop::EventLoopProxy;
 
 use crate::{bridge::NeovimWriter, window::UserEvent};
 pub use from_value::ParseFromValue;
+pub use schema::config_schema;
+pub use snapshot::{config_settings, export_settings, import_settings, resolved_settings};
+pub use verify::verify_config;
 pub use window_size::{
-    clamped_grid_size, load_last_window_settings, neovide_std_datapath, save_window_size,
-    PersistentWindowSettings, DEFAULT_GRID_SIZE, MIN_GRID_SIZE,
+    clamped_grid_size, load_last_cell_metrics, load_last_window_settings, neovide_std_datapath,
+    save_window_size, PersistentWindowSettings, DEFAULT_GRID_SIZE, MIN_GRID_SIZE,
 };
 
 mod config;
-pub use config::{Config, HotReloadConfigs};
+pub use config::{config_path, merge_platform_tables, Config, HotReloadConfigs};
 pub use font::FontSettings;
 
 pub trait SettingGroup {
@@ -45,6 +56,8 @@ type ReaderHandlerFunc = fn(&Settings) -> Option<Value>;
 #[derive(Default, Debug)]
 pub struct Settings {
     settings: RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    /// Bumped by every change of the settings, to tell the cached settings to reload.
+    generation: AtomicU64,
     updaters: RwLock<HashMap<SettingLocation, UpdateHandlerFunc>>,
     readers: RwLock<HashMap<SettingLocation, ReaderHandlerFunc>>,
 }
@@ -82,9 +95,17 @@ impl Settings {
         let t: T = (*t).clone();
         let mut write_lock = self.settings.write();
         write_lock.insert(type_id, Box::new(t));
+        // The updaters set the new values before sending `SettingsChanged`, so the cached
+        // settings are already stale when the event is handled
+        self.generation.fetch_add(1, Ordering::Release);
     }
 
     pub fn get<T: Clone + Send + Sync + 'static>(&'_ self) -> T {
+        self.get_with_generation().1
+    }
+
+    /// Returns the settings together with the generation they belong to.
+    fn get_with_generation<T: Clone + Send + Sync + 'static>(&self) -> (u64, T) {
         let read_lock = self.settings.read();
         let boxed = &read_lock
             .get(&TypeId::of::<T>())
@@ -92,43 +113,72 @@ impl Settings {
         let value: &T = boxed
             .downcast_ref::<T>()
             .expect("Attempted to extract a settings object of the wrong type");
-        (*value).clone()
+        // Changes only happen with the write lock held, so this matches the value
+        let generation = self.generation.load(Ordering::Acquire);
+        (generation, (*value).clone())
     }
 
     pub fn setting_locations(&self) -> Vec<SettingLocation> {
         self.updaters.read().keys().cloned().collect()
     }
 
+    /// Reads the values of all the settings from Neovim in one request, and gives Neovim the
+    /// values of the `g:neovide_*` variables it didn't set itself in a second one.
     pub async fn read_initial_values(&self, nvim: &Neovim<NeovimWriter>) -> Result<()> {
-        let keys: Vec<SettingLocation> = self.updaters.read().keys().cloned().collect();
-
-        for location in keys {
+        let mut names = Vec::new();
+        let mut defaults = HashMap::new();
+        let mut options = Vec::new();
+        for location in self.setting_locations() {
             match &location {
                 SettingLocation::NeovideGlobal(name) => {
                     let variable_name = format!("neovide_{name}");
-                    match nvim.get_var(&variable_name).await {
-                        Ok(value) => {
-                            self.updaters.read().get(&location).unwrap()(self, value);
-                        }
-                        Err(error) => {
-                            trace!("Initial value load failed for {}: {}", name, error);
-                            let value = self.readers.read().get(&location).unwrap()(self);
-                            if let Some(value) = value {
-                                nvim.set_var(&variable_name, value).await.with_context(|| {
-                                    format!("Could not set initial value for {name}")
-                                })?;
-                            }
-                        }
+                    if let Some(value) = self.readers.read().get(&location).unwrap()(self) {
+                        defaults.insert(variable_name.clone(), value);
                     }
+                    names.push(Value::from(variable_name));
                 }
-                SettingLocation::NeovimOption(name) => match nvim.get_option(name).await {
-                    Ok(value) => {
-                        self.updaters.read().get(&location).unwrap()(self, value);
-                    }
-                    Err(error) => {
-                        trace!("Initial value load failed for {}: {}", name, error);
-                    }
-                },
+                SettingLocation::NeovimOption(name) => options.push(Value::from(name.as_str())),
+            }
+        }
+
+        let values = nvim
+            .exec_lua(
+                include_str!("read_initial_values.lua"),
+                vec![Value::Array(names), Value::Array(options)],
+            )
+            .await
+            .context("Could not read the initial values of the settings")?;
+
+        let (globals, options, missing) = initial_values(values);
+        let calls = set_default_calls(&missing, &mut defaults);
+        if !calls.is_empty() {
+            let result = nvim
+                .call_atomic(calls)
+                .await
+                .context("Could not set the initial values of the settings")?;
+            if let Some(error) = result.get(1).filter(|error| !error.is_nil()) {
+                bail!("Could not set the initial values of the settings: {error}");
+            }
+        }
+
+        let updaters = self.updaters.read();
+        let locations = globals
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let name = name.strip_prefix("neovide_")?.to_owned();
+                Some((SettingLocation::NeovideGlobal(name), value))
+            })
+            .chain(
+                options
+                    .into_iter()
+                    .map(|(name, value)| (SettingLocation::NeovimOption(name), value)),
+            );
+        for (location, value) in locations {
+            match updaters.get(&location) {
+                Some(update) => {
+                    update(self, value);
+                }
+                None => trace!("Initial value of unknown setting {:?}", location),
             }
         }
         Ok(())
@@ -173,16 +223,120 @@ impl Settings {
         let _ = event_loop_proxy.send_event(event.into());
     }
 
+    /// Applies values of `g:neovide_*` settings before Neovim is attached, so that they're sent to
+    /// Neovim by `read_initial_values` unless it already set the variables itself.
+    pub fn apply_initial_values(&self, values: Vec<(String, Value)>) {
+        let updaters = self.updaters.read();
+        for (name, value) in values {
+            if let Some(update) = updaters.get(&SettingLocation::NeovideGlobal(name)) {
+                update(self, value);
+            }
+        }
+    }
+
     pub fn register<T: SettingGroup>(&self) {
         T::register(self);
     }
 }
 
+/// A handle for reading a settings group every frame. It keeps a copy of the group, so reading it
+/// costs an atomic load instead of locking the settings and cloning the group, and only clones the
+/// group again once the settings changed.
+pub struct CachedSettings<T> {
+    settings: Arc<Settings>,
+    cache: RefCell<Option<(u64, Arc<T>)>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> CachedSettings<T> {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            settings,
+            cache: RefCell::new(None),
+        }
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        let generation = self.settings.generation.load(Ordering::Acquire);
+        let mut cache = self.cache.borrow_mut();
+        match &*cache {
+            Some((cached_generation, value)) if *cached_generation == generation => value.clone(),
+            _ => {
+                let (generation, value) = self.settings.get_with_generation::<T>();
+                let value = Arc::new(value);
+                *cache = Some((generation, value.clone()));
+                value
+            }
+        }
+    }
+}
+
+/// Splits the result of `read_initial_values.lua` into the values of the global variables and the
+/// ones of the options, by name.
+fn initial_values(values: Value) -> (Vec<(String, Value)>, Vec<(String, Value)>, Vec<String>) {
+    let mut globals = Vec::new();
+    let mut options = Vec::new();
+    let mut missing = Vec::new();
+    let Value::Map(values) = values else {
+        return (globals, options, missing);
+    };
+    for (key, value) in values {
+        if key.as_str() == Some("missing") {
+            if let Value::Array(names) = value {
+                missing.extend(
+                    names
+                        .iter()
+                        .filter_map(|name| name.as_str().map(str::to_owned)),
+                );
+            }
+            continue;
+        }
+        let target = match key.as_str() {
+            Some("globals") => &mut globals,
+            Some("options") => &mut options,
+            _ => continue,
+        };
+        // Neovim turns empty tables without a type into arrays
+        let Value::Map(entries) = value else {
+            continue;
+        };
+        target.extend(entries.into_iter().filter_map(|(name, value)| {
+            let name = name.as_str()?.to_owned();
+            Some((name, value))
+        }));
+    }
+    (globals, options, missing)
+}
+
+/// The `nvim_set_var` calls giving the `missing` variables their `defaults`, for
+/// `nvim_call_atomic`. They're not set from Lua, which turns the integral floats into Vim Numbers,
+/// `g:neovide_scale_factor` becoming 1 instead of 1.0.
+fn set_default_calls(missing: &[String], defaults: &mut HashMap<String, Value>) -> Vec<Value> {
+    missing
+        .iter()
+        .filter_map(|name| {
+            let value = defaults.remove(name)?;
+            Some(Value::Array(vec![
+                Value::from("nvim_set_var"),
+                Value::Array(vec![Value::from(name.as_str()), value]),
+            ]))
+        })
+        .collect()
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum SettingsChanged {
     Window(crate::window::WindowSettingsChanged),
+    Mouse(crate::window::MouseSettingsChanged),
     Cursor(crate::renderer::cursor_renderer::CursorSettingsChanged),
     Renderer(crate::renderer::RendererSettingsChanged),
+    CommandLine(crate::renderer::cmdline_renderer::CommandLineSettingsChanged),
+    Messages(crate::renderer::messages_renderer::MessagesSettingsChanged),
+    PopupMenu(crate::renderer::popupmenu::PopupMenuSettingsChanged),
+    Tabline(crate::renderer::tabline::TablineSettingsChanged),
+    Wildmenu(crate::renderer::wildmenu::WildmenuSettingsChanged),
+    FileChanged(crate::renderer::file_changed::FileChangedSettingsChanged),
+    Clipboard(crate::bridge::ClipboardSettingsChanged),
+    Bell(crate::renderer::bell::BellSettingsChanged),
     #[cfg(test)]
     Test(tests::TestSettingsChanged),
 }
@@ -194,6 +348,7 @@ mod tests {
         foo: String,
         bar: String,
         baz: String,
+        scale: f32,
         #[option = "mousemoveevent"]
         mousemoveevent_option: Option<bool>,
     }
@@ -204,6 +359,7 @@ mod tests {
                 foo: "foo".to_string(),
                 bar: "bar".to_string(),
                 baz: "baz".to_string(),
+                scale: 1.0,
                 mousemoveevent_option: None,
             }
         }
@@ -312,6 +468,46 @@ mod tests {
         assert_eq!(v2, r2);
     }
 
+    #[test]
+    fn cached_settings_reload_after_changes() {
+        let settings = Arc::new(Settings::new());
+        settings.set(&1u32);
+        let cached = CachedSettings::<u32>::new(settings.clone());
+        let first = cached.get();
+        assert_eq!(*first, 1);
+        assert!(Arc::ptr_eq(&first, &cached.get()));
+
+        settings.set(&2u32);
+        assert_eq!(*cached.get(), 2);
+        // Other groups changing reloads the value too, but it stays the same
+        settings.set(&1.0f32);
+        assert_eq!(*cached.get(), 2);
+    }
+
+    #[test]
+    fn cached_settings_follow_the_generation() {
+        let settings = Arc::new(Settings::new());
+        settings.set(&1u32);
+        let (generation, value) = settings.get_with_generation::<u32>();
+        assert_eq!(value, 1);
+        // Every change bumps the generation once, whichever group it is
+        settings.set(&2u32);
+        settings.set(&1.0f32);
+        assert_eq!(settings.get_with_generation::<u32>(), (generation + 2, 2));
+
+        let cached = CachedSettings::<u32>::new(settings.clone());
+        let other = CachedSettings::<u32>::new(settings.clone());
+        let first = cached.get();
+        assert!(Arc::ptr_eq(&first, &cached.get()));
+        // A change invalidates every cache, each one reloading on its own
+        settings.set(&3u32);
+        let second = cached.get();
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(*second, 3);
+        assert!(Arc::ptr_eq(&second, &cached.get()));
+        assert_eq!(*other.get(), 3);
+    }
+
     #[tokio::test]
     async fn test_read_initial_values() {
         let settings = Settings::new();
@@ -343,5 +539,50 @@ mod tests {
         assert_eq!(test_settings.bar, "bar_set");
         assert_eq!(test_settings.baz, "baz");
         assert_eq!(test_settings.mousemoveevent_option, Some(true));
+        // The defaults are given to Neovim
+        assert_eq!(
+            nvim.get_var("neovide_foo").await.unwrap(),
+            Value::from("foo")
+        );
+        // Integral floats stay floats, and not Vim Numbers
+        assert!(nvim.get_var("neovide_scale").await.unwrap().is_f64());
+    }
+
+    #[test]
+    fn initial_values_are_split_by_kind() {
+        let values = Value::Map(vec![
+            (
+                Value::from("globals"),
+                Value::Map(vec![(Value::from("neovide_bar"), Value::from("bar_set"))]),
+            ),
+            (Value::from("options"), Value::Array(Vec::new())),
+        ]);
+        let (globals, options, missing) = initial_values(values);
+        assert_eq!(
+            globals,
+            vec![("neovide_bar".to_owned(), Value::from("bar_set"))]
+        );
+        assert!(options.is_empty());
+        assert!(missing.is_empty());
+        assert_eq!(
+            initial_values(Value::Nil),
+            (Vec::new(), Vec::new(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn float_defaults_stay_floats() {
+        let mut defaults = HashMap::from([
+            ("neovide_scale_factor".to_owned(), Value::from(1.0f32)),
+            ("neovide_foo".to_owned(), Value::from("foo")),
+        ]);
+        let calls = set_default_calls(&["neovide_scale_factor".to_owned()], &mut defaults);
+        assert_eq!(
+            calls,
+            vec![Value::Array(vec![
+                Value::from("nvim_set_var"),
+                Value::Array(vec![Value::from("neovide_scale_factor"), Value::F32(1.0)]),
+            ])]
+        );
     }
 }
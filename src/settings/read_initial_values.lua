@@ -0,0 +1,25 @@
+-- Reads every setting Neovide observes in a single request, which saves a round trip per setting
+-- on the remote connections. The variables that aren't set yet are returned as missing, for Neovide
+-- to set them to its defaults.
+local names, options = ...
+
+local globals = vim.empty_dict()
+local missing = {}
+for _, name in ipairs(names) do
+    local value = vim.g[name]
+    if value == nil then
+        table.insert(missing, name)
+    else
+        globals[name] = value
+    end
+end
+
+local option_values = vim.empty_dict()
+for _, name in ipairs(options) do
+    local ok, value = pcall(vim.api.nvim_get_option_value, name, {})
+    if ok then
+        option_values[name] = value
+    end
+end
+
+return { globals = globals, options = option_values, missing = missing }
@@ -0,0 +1,151 @@
+//! The JSON schema of the config file, printed by `--config-schema` for the editors validating
+//! and completing `config.toml`.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use super::{
+    config::{PLATFORM_TABLES, WSL_TABLE},
+    snapshot::{global_values, to_toml},
+    Settings,
+};
+
+/// The keys of the config file outside of its tables, with their JSON type.
+pub(super) const CONFIG_KEYS: &[(&str, &str)] = &[
+    ("backtraces-path", "string"),
+    ("fork", "boolean"),
+    ("frame", "string"),
+    ("idle", "boolean"),
+    ("maximized", "boolean"),
+    ("mouse-cursor-icon", "string"),
+    ("neovim-bin", "string"),
+    ("no-multigrid", "boolean"),
+    ("remote-control", "boolean"),
+    ("single-instance", "boolean"),
+    ("srgb", "boolean"),
+    ("tabs", "boolean"),
+    ("theme", "string"),
+    ("title-hidden", "boolean"),
+    ("vsync", "boolean"),
+    ("wsl", "boolean"),
+];
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+        Value::Null => "null",
+    }
+}
+
+/// The type of a value of the `[settings]` table.
+fn setting_type(value: &Value) -> &'static str {
+    match json_type(value) {
+        // Whole numbers are accepted for the floats too
+        "integer" | "number" => "number",
+        json_type => json_type,
+    }
+}
+
+/// The expected and the found types when `value` doesn't have the type of the setting `name`,
+/// among the `types` of `setting_types`.
+pub(super) fn type_mismatch(
+    types: &HashMap<String, &'static str>,
+    name: &str,
+    value: &toml::Value,
+) -> Option<(&'static str, &'static str)> {
+    let expected = *types.get(name)?;
+    let found = serde_json::to_value(value).map_or("datetime", |value| setting_type(&value));
+    (found != expected).then_some((expected, found))
+}
+
+/// The default of a setting as JSON, `None` for the settings without one.
+fn json_default(value: &rmpv::Value) -> Option<Value> {
+    to_toml(value).and_then(|value| serde_json::to_value(value).ok())
+}
+
+/// The types of the settings with a default, by name. The others, like the optional ones, take
+/// any type.
+pub(super) fn setting_types(settings: &Settings) -> HashMap<String, &'static str> {
+    global_values(settings)
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, setting_type(&json_default(&value)?))))
+        .collect()
+}
+
+/// The properties of the `[settings]` table, one per `g:neovide_*` setting of the registered
+/// groups with its default.
+fn settings_properties(settings: &Settings) -> Map<String, Value> {
+    global_values(settings)
+        .into_iter()
+        .map(|(name, value)| {
+            let schema = json_default(&value).map_or_else(
+                || json!({}),
+                |default| json!({ "type": setting_type(&default), "default": default }),
+            );
+            (name, schema)
+        })
+        .collect()
+}
+
+pub fn config_schema(settings: &Settings) -> Value {
+    let mut properties = CONFIG_KEYS
+        .iter()
+        .map(|(name, json_type)| (name.to_string(), json!({ "type": json_type })))
+        .collect::<Map<_, _>>();
+    properties.insert("font".to_string(), json!({ "type": "object" }));
+    // The platform tables take the same keys
+    for name in PLATFORM_TABLES {
+        let description = match *name {
+            WSL_TABLE => {
+                "Overrides the rest of the config file when Neovim runs in WSL".to_string()
+            }
+            name => format!("Overrides the rest of the config file on {name}"),
+        };
+        properties.insert(
+            name.to_string(),
+            json!({ "type": "object", "description": description }),
+        );
+    }
+    properties.insert(
+        "settings".to_string(),
+        json!({
+            "type": "object",
+            "description": "The g:neovide_* settings, named without the prefix",
+            "properties": settings_properties(settings),
+            "additionalProperties": false,
+        }),
+    );
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Neovide config.toml",
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::cursor_renderer::CursorSettings;
+
+    #[test]
+    fn schema_lists_the_registered_settings() {
+        let settings = Settings::new();
+        settings.register::<CursorSettings>();
+        let schema = config_schema(&settings);
+        let cursor_settings = &schema["properties"]["settings"]["properties"];
+        assert_eq!(
+            cursor_settings["cursor_vfx_mode"],
+            json!({ "type": "string", "default": "" })
+        );
+        assert_eq!(cursor_settings["cursor_antialiasing"]["type"], "boolean");
+        assert_eq!(schema["properties"]["vsync"]["type"], "boolean");
+    }
+}
@@ -0,0 +1,186 @@
+//! Snapshots of the `g:neovide_*` settings in a TOML file, written by `:NeovideExportSettings` and
+//! applied by `:NeovideImportSettings`, to share the same look and feel between machines.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use log::warn;
+use rmpv::Value;
+
+use super::{
+    schema::{setting_types, type_mismatch},
+    SettingLocation, Settings,
+};
+
+/// The current values of the global variable settings, without the `neovide_` prefix.
+pub(super) fn global_values(settings: &Settings) -> Vec<(String, Value)> {
+    let readers = settings.readers.read();
+    let mut values = readers
+        .iter()
+        .filter_map(|(location, reader)| match location {
+            SettingLocation::NeovideGlobal(name) => Some((name.clone(), reader(settings)?)),
+            SettingLocation::NeovimOption(_) => None,
+        })
+        .collect::<Vec<_>>();
+    values.sort_by(|(a, _), (b, _)| a.cmp(b));
+    values
+}
+
+pub(super) fn to_toml(value: &Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Boolean(value) => toml::Value::Boolean(*value),
+        Value::Integer(value) => toml::Value::Integer(value.as_i64()?),
+        // Going through the shortest text of the f32 keeps 0.8 from becoming 0.800000011920929
+        Value::F32(value) => toml::Value::Float(value.to_string().parse().ok()?),
+        Value::F64(value) => toml::Value::Float(*value),
+        Value::String(value) => toml::Value::String(value.as_str()?.to_string()),
+        Value::Array(values) => toml::Value::Array(values.iter().filter_map(to_toml).collect()),
+        Value::Map(entries) => toml::Value::Table(
+            entries
+                .iter()
+                .filter_map(|(key, value)| Some((key.as_str()?.to_string(), to_toml(value)?)))
+                .collect(),
+        ),
+        _ => return None,
+    })
+}
+
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::Boolean(value) => Value::from(value),
+        toml::Value::Integer(value) => Value::from(value),
+        toml::Value::Float(value) => Value::from(value),
+        toml::Value::String(value) => Value::from(value),
+        toml::Value::Datetime(value) => Value::from(value.to_string()),
+        toml::Value::Array(values) => Value::Array(values.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Map(
+            table
+                .into_iter()
+                .map(|(key, value)| (Value::from(key), from_toml(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn snapshot_text(settings: &Settings) -> Result<String> {
+    let table = global_values(settings)
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), to_toml(value)?)))
+        .collect::<toml::Table>();
+    let text = toml::to_string(&table).context("Could not serialize the settings")?;
+    Ok(format!(
+        "# Neovide settings, apply them with :NeovideImportSettings\n{text}"
+    ))
+}
+
+/// The values of the known settings in `table`, skipping the unknown ones with a warning that
+/// mentions `source`.
+fn known_values(settings: &Settings, table: toml::Table, source: &str) -> Vec<(String, Value)> {
+    let updaters = settings.updaters.read();
+    table
+        .into_iter()
+        .filter_map(|(name, value)| {
+            if updaters.contains_key(&SettingLocation::NeovideGlobal(name.clone())) {
+                Some((name, from_toml(value)))
+            } else {
+                warn!("Skipping the unknown setting {name} of {source}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parses a snapshot into the values of the known settings, skipping the unknown ones.
+fn parse_snapshot(settings: &Settings, text: &str) -> Result<Vec<(String, Value)>> {
+    let table = text.parse::<toml::Table>()?;
+    Ok(known_values(settings, table, "the snapshot"))
+}
+
+/// The values of the `[settings]` table of the config file, named like the `g:neovide_*`
+/// variables without their prefix. The values of the wrong type are skipped, the settings would
+/// fail to parse them.
+pub fn config_settings(settings: &Settings, mut table: toml::Table) -> Vec<(String, Value)> {
+    let types = setting_types(settings);
+    table.retain(|name, value| match type_mismatch(&types, name, value) {
+        Some((expected, _)) => {
+            warn!("Skipping the setting {name} of the config file, expected a {expected}");
+            false
+        }
+        None => true,
+    });
+    known_values(settings, table, "the config file")
+}
+
+/// The lines of the `[settings]` table of the resolved configuration, each with a comment telling
+/// whether it comes from the config file or is the default.
+pub fn resolved_settings(
+    settings: &Settings,
+    config_settings: Option<&toml::Table>,
+) -> Vec<String> {
+    global_values(settings)
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = to_toml(value)?;
+            let source = if config_settings.is_some_and(|table| table.contains_key(name)) {
+                "config file"
+            } else {
+                "default"
+            };
+            Some(format!("{name} = {value} # {source}"))
+        })
+        .collect()
+}
+
+/// Writes the current settings to `path`.
+pub fn export_settings(settings: &Settings, path: &Path) -> Result<()> {
+    let text = snapshot_text(settings)?;
+    fs::write(path, text).with_context(|| format!("Could not write {}", path.display()))
+}
+
+/// Reads the settings of the snapshot at `path`. They aren't applied here, Neovim sets the
+/// variables instead, which notifies Neovide of the changes like any other and keeps both sides
+/// in sync.
+pub fn import_settings(settings: &Settings, path: &Path) -> Result<Vec<(String, Value)>> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let values = parse_snapshot(settings, &text)
+        .with_context(|| format!("Could not parse {}", path.display()))?;
+    if values.is_empty() {
+        bail!("{} doesn't contain any Neovide setting", path.display());
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::RendererSettings;
+
+    #[test]
+    fn snapshots_round_trip() {
+        let settings = Settings::new();
+        settings.register::<RendererSettings>();
+        let text = snapshot_text(&settings).unwrap();
+        assert!(text.contains("\nposition_animation_length = 0.15\n"));
+
+        let values = parse_snapshot(&settings, &text).unwrap();
+        assert_eq!(values.len(), global_values(&settings).len());
+        assert!(values.contains(&("debug_renderer".to_string(), Value::from(false))));
+
+        let values = parse_snapshot(&settings, "text_gamma = 0.5\nunknown = 1").unwrap();
+        assert_eq!(values, vec![("text_gamma".to_string(), Value::from(0.5))]);
+        assert!(parse_snapshot(&settings, "text_gamma = ").is_err());
+    }
+
+    #[test]
+    fn config_settings_are_applied_initially() {
+        let settings = Settings::new();
+        settings.register::<RendererSettings>();
+        let config = "[settings]\ntext_gamma = 0.5\nunknown = true\n"
+            .parse::<toml::Table>()
+            .unwrap();
+        let table = config["settings"].as_table().unwrap().clone();
+        settings.apply_initial_values(config_settings(&settings, table));
+        assert!(global_values(&settings).contains(&("text_gamma".to_string(), Value::from(0.5f32))));
+    }
+}
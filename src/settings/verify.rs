@@ -0,0 +1,230 @@
+//! `--verify-config`, which checks the config file without opening a window, for the dotfile
+//! repositories testing their Neovide config in CI.
+
+use std::{fs, path::Path};
+
+use serde::Serialize;
+use skia_safe::FontMgr;
+
+use super::{
+    config::{merge_platform_tables, Config, PLATFORM_TABLES},
+    font::FontSettings,
+    schema::{setting_types, type_mismatch, CONFIG_KEYS},
+    SettingLocation, Settings,
+};
+use crate::renderer::fonts::font_options::{
+    FontDescription, FontEdging, FontFeature, FontHinting, SecondaryFontDescription,
+};
+
+/// Something wrong with the config file, `key` being the dotted path of the value, or empty when
+/// it's about the whole file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigProblem {
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks the keys and the types of the config file, and returns the config when it could be
+/// read.
+fn check_config_text(settings: &Settings, text: &str) -> (Option<Config>, Vec<ConfigProblem>) {
+    let table = match text.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(error) => return (None, vec![ConfigProblem::new("", error.message())]),
+    };
+
+    let mut problems = check_keys(settings, &table, "");
+    for name in PLATFORM_TABLES {
+        if let Some(toml::Value::Table(overrides)) = table.get(*name) {
+            problems.extend(check_keys(settings, overrides, &format!("{name}.")));
+        }
+    }
+
+    // Whatever the keys above didn't catch, like the types of the font table, with the tables of
+    // the current platform applied
+    let config = match toml::Value::Table(merge_platform_tables(table)).try_into::<Config>() {
+        Ok(config) => Some(config),
+        Err(error) => {
+            problems.push(ConfigProblem::new("", error.message()));
+            None
+        }
+    };
+    if let Some(font) = config.as_ref().and_then(|config| config.font.as_ref()) {
+        problems.extend(check_font_options(font));
+    }
+    (config, problems)
+}
+
+/// Checks the keys of the base config or of a platform table, whose keys start with `prefix`.
+fn check_keys(settings: &Settings, table: &toml::Table, prefix: &str) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    for (key, value) in table {
+        let known = key == "font"
+            || key == "settings"
+            || CONFIG_KEYS.iter().any(|(name, _)| name == key)
+            || (prefix.is_empty() && value.is_table() && PLATFORM_TABLES.contains(&key.as_str()));
+        if !known {
+            problems.push(ConfigProblem::new(format!("{prefix}{key}"), "unknown key"));
+        }
+    }
+
+    if let Some(values) = table.get("settings") {
+        match values.as_table() {
+            Some(values) => problems.extend(check_settings(settings, values, prefix)),
+            None => problems.push(ConfigProblem::new(
+                format!("{prefix}settings"),
+                "expected a table",
+            )),
+        }
+    }
+    problems
+}
+
+fn check_settings(settings: &Settings, values: &toml::Table, prefix: &str) -> Vec<ConfigProblem> {
+    let updaters = settings.updaters.read();
+    let types = setting_types(settings);
+    values
+        .iter()
+        .filter_map(|(name, value)| {
+            let key = format!("{prefix}settings.{name}");
+            if !updaters.contains_key(&SettingLocation::NeovideGlobal(name.clone())) {
+                return Some(ConfigProblem::new(key, "unknown setting"));
+            }
+            let (expected, found) = type_mismatch(&types, name, value)?;
+            Some(ConfigProblem::new(
+                key,
+                format!("expected a {expected}, found a {found}"),
+            ))
+        })
+        .collect()
+}
+
+fn check_font_options(font: &FontSettings) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    for (family, features) in font.features.iter().flatten() {
+        for feature in features {
+            if FontFeature::parse(feature).is_err() {
+                problems.push(ConfigProblem::new(
+                    format!("font.features.{family}"),
+                    format!("invalid feature {feature}, expected +name, -name or name=value"),
+                ));
+            }
+        }
+    }
+    if let Some(Err(error)) = font.hinting.as_deref().map(FontHinting::parse) {
+        problems.push(ConfigProblem::new("font.hinting", error));
+    }
+    if let Some(Err(error)) = font.edging.as_deref().map(FontEdging::parse) {
+        problems.push(ConfigProblem::new("font.edging", error));
+    }
+    problems
+}
+
+/// The fonts of the config file that aren't installed, each font being looked up with its style
+/// like the font loader does.
+fn missing_fonts(font: &FontSettings) -> Vec<ConfigProblem> {
+    let mut fonts: Vec<(&str, FontDescription)> = Vec::<FontDescription>::from(font.normal.clone())
+        .into_iter()
+        .map(|description| ("font.normal", description))
+        .collect();
+    let secondary_fonts = [
+        ("font.bold", &font.bold),
+        ("font.italic", &font.italic),
+        ("font.bold_italic", &font.bold_italic),
+    ];
+    for (key, descriptions) in secondary_fonts {
+        let descriptions = descriptions
+            .clone()
+            .map(Vec::<SecondaryFontDescription>::from);
+        for description in descriptions.into_iter().flatten() {
+            // Without a family, the normal font is used with the style
+            if let Some(family) = description.family {
+                let style = description.style;
+                fonts.push((key, FontDescription { family, style }));
+            }
+        }
+    }
+
+    let font_mgr = FontMgr::new();
+    fonts
+        .into_iter()
+        .filter_map(|(key, description)| {
+            let (family, style) = description.as_family_and_font_style();
+            font_mgr
+                .match_family_style(family, style)
+                .is_none()
+                .then(|| ConfigProblem::new(key, format!("the font {family} isn't installed")))
+        })
+        .collect()
+}
+
+/// Verifies the config file at `path`, and returns the problems found.
+pub fn verify_config(settings: &Settings, path: &Path) -> Vec<ConfigProblem> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            return vec![ConfigProblem::new(
+                "",
+                format!("could not read {}: {error}", path.display()),
+            )]
+        }
+    };
+    let (config, mut problems) = check_config_text(settings, &text);
+    if let Some(font) = config.and_then(|config| config.font) {
+        problems.extend(missing_fonts(&font));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::cursor_renderer::CursorSettings;
+
+    #[test]
+    fn config_problems_are_reported_by_key() {
+        let settings = Settings::new();
+        settings.register::<CursorSettings>();
+        let text = r#"
+            vsync = true
+            fullscreen = true
+
+            [font]
+            normal = "Fira Code"
+            size = 14
+            hinting = "strong"
+
+            [settings]
+            cursor_antialiasing = "yes"
+            cursor_vfx_mode = "railgun"
+            cursor_unknown = 1
+        "#;
+        let (config, problems) = check_config_text(&settings, text);
+        assert!(config.is_some());
+        assert_eq!(
+            problems,
+            vec![
+                ConfigProblem::new("fullscreen", "unknown key"),
+                ConfigProblem::new(
+                    "settings.cursor_antialiasing",
+                    "expected a boolean, found a string"
+                ),
+                ConfigProblem::new("settings.cursor_unknown", "unknown setting"),
+                ConfigProblem::new("font.hinting", "Invalid hinting"),
+            ]
+        );
+
+        let (config, problems) = check_config_text(&settings, "vsync = 1");
+        assert!(config.is_none());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("invalid type"));
+    }
+}
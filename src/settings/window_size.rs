@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use winit::dpi::{PhysicalPosition, PhysicalSize};
@@ -8,6 +8,8 @@ use crate::{
 };
 
 const SETTINGS_FILE: &str = "neovide-settings.json";
+/// How many working directories the window is remembered for.
+const MAX_PROJECTS: usize = 50;
 
 pub const DEFAULT_GRID_SIZE: GridSize<u32> = GridSize {
     width: 100,
@@ -22,7 +24,7 @@ pub const MAX_GRID_SIZE: GridSize<u32> = GridSize {
     height: 1000,
 };
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum PersistentWindowSettings {
     Maximized {
         #[serde(default)]
@@ -38,9 +40,100 @@ pub enum PersistentWindowSettings {
     },
 }
 
+/// The size of the cells and the space around the grid from the last session, to start Neovim with
+/// the grid that fits the window before the fonts are loaded.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CellMetrics {
+    cell_size: PhysicalSize<f32>,
+    /// The part of the window not covered by the grid, like the padding and the tab bar.
+    padding: PhysicalSize<u32>,
+}
+
+impl CellMetrics {
+    fn new(
+        cell_size: PhysicalSize<f32>,
+        pixel_size: PhysicalSize<u32>,
+        grid_size: GridSize<u32>,
+    ) -> Self {
+        let grid_pixels = |cells: u32, cell_size: f32| (cells as f32 * cell_size).round() as u32;
+        Self {
+            cell_size,
+            padding: PhysicalSize::new(
+                pixel_size
+                    .width
+                    .saturating_sub(grid_pixels(grid_size.width, cell_size.width)),
+                pixel_size
+                    .height
+                    .saturating_sub(grid_pixels(grid_size.height, cell_size.height)),
+            ),
+        }
+    }
+
+    /// The grid that fits a window of `pixel_size`, if the metrics are usable.
+    pub fn grid_size(&self, pixel_size: PhysicalSize<u32>) -> Option<GridSize<u32>> {
+        if self.cell_size.width <= 0.0 || self.cell_size.height <= 0.0 {
+            return None;
+        }
+        // Allow for the rounding of the padding
+        let cells = |pixels: u32, padding: u32, cell_size: f32| {
+            (pixels.saturating_sub(padding) as f32 / cell_size + 0.01).floor() as u32
+        };
+        Some(clamped_grid_size(&GridSize::new(
+            cells(pixel_size.width, self.padding.width, self.cell_size.width),
+            cells(
+                pixel_size.height,
+                self.padding.height,
+                self.cell_size.height,
+            ),
+        )))
+    }
+}
+
+/// The window of the last session started in a working directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct ProjectWindowSettings {
+    directory: PathBuf,
+    window: PersistentWindowSettings,
+}
+
 #[derive(Serialize, Deserialize)]
 struct PersistentSettings {
+    /// The window of the last session, used for the directories without one of their own.
+    window: PersistentWindowSettings,
+    #[serde(default)]
+    cell_metrics: Option<CellMetrics>,
+    /// The window of each working directory, the last used first.
+    #[serde(default)]
+    projects: Vec<ProjectWindowSettings>,
+}
+
+impl PersistentSettings {
+    fn window_for(self, directory: Option<&Path>) -> PersistentWindowSettings {
+        directory
+            .and_then(|directory| {
+                self.projects
+                    .into_iter()
+                    .find(|project| project.directory == directory)
+            })
+            .map_or(self.window, |project| project.window)
+    }
+}
+
+/// Moves the window of `directory` to the front of the projects, forgetting the least recently
+/// used ones.
+fn remember_project(
+    projects: &mut Vec<ProjectWindowSettings>,
+    directory: PathBuf,
     window: PersistentWindowSettings,
+) {
+    projects.retain(|project| project.directory != directory);
+    projects.insert(0, ProjectWindowSettings { directory, window });
+    projects.truncate(MAX_PROJECTS);
+}
+
+/// The directory Neovide was started in, which the window is remembered for.
+fn project_directory() -> Option<PathBuf> {
+    std::env::current_dir().ok()
 }
 
 fn settings_path() -> PathBuf {
@@ -61,16 +154,26 @@ pub fn neovide_std_datapath() -> PathBuf {
 
 pub fn load_last_window_settings() -> Result<PersistentWindowSettings, String> {
     let settings = load_settings()?;
-    let loaded_settings = settings.window;
+    let loaded_settings = settings.window_for(project_directory().as_deref());
     log::debug!("Loaded window settings: {:?}", loaded_settings);
 
     Ok(loaded_settings)
 }
 
+pub fn load_last_cell_metrics() -> Option<CellMetrics> {
+    let cell_metrics = load_settings().ok()?.cell_metrics;
+    log::debug!("Loaded cell metrics: {:?}", cell_metrics);
+    cell_metrics
+}
+
 pub fn save_window_size(window_wrapper: &WinitWindowWrapper, settings: &Settings) {
     if window_wrapper.skia_renderer.is_none() {
         return;
     }
+    // The picture in picture window isn't the size to open the next time with
+    if window_wrapper.is_picture_in_picture() {
+        return;
+    }
     let window = window_wrapper.skia_renderer.as_ref().unwrap().window();
     // Don't save the window size when the window is minimized, since the size can be 0
     // Note wayland can't determine this
@@ -82,35 +185,177 @@ pub fn save_window_size(window_wrapper: &WinitWindowWrapper, settings: &Settings
     let grid_size = window_wrapper.get_grid_size();
     let position = window.outer_position().ok();
     let window_settings = settings.get::<WindowSettings>();
+    let grid_scale = window_wrapper.renderer.grid_renderer.grid_scale;
+    // Saved even when the window size isn't remembered, since a window of the default size needs
+    // them too
+    let cell_metrics = CellMetrics::new(
+        PhysicalSize::new(grid_scale.width(), grid_scale.height()),
+        pixel_size,
+        grid_size,
+    );
+
+    let window = if maximized && window_settings.remember_window_size {
+        PersistentWindowSettings::Maximized {
+            grid_size: { window_settings.remember_window_size.then_some(grid_size) },
+        }
+    } else {
+        PersistentWindowSettings::Windowed {
+            pixel_size: { window_settings.remember_window_size.then_some(pixel_size) },
+            grid_size: { window_settings.remember_window_size.then_some(grid_size) },
+            position: {
+                window_settings
+                    .remember_window_position
+                    .then_some(position)
+                    .flatten()
+                    .unwrap_or_default()
+            },
+        }
+    };
+    let mut projects = load_settings()
+        .map(|settings| settings.projects)
+        .unwrap_or_default();
+    if let Some(directory) = project_directory() {
+        remember_project(&mut projects, directory, window.clone());
+    }
 
     let settings = PersistentSettings {
-        window: if maximized && window_settings.remember_window_size {
-            PersistentWindowSettings::Maximized {
-                grid_size: { window_settings.remember_window_size.then_some(grid_size) },
-            }
-        } else {
-            PersistentWindowSettings::Windowed {
-                pixel_size: { window_settings.remember_window_size.then_some(pixel_size) },
-                grid_size: { window_settings.remember_window_size.then_some(grid_size) },
-                position: {
-                    window_settings
-                        .remember_window_position
-                        .then_some(position)
-                        .flatten()
-                        .unwrap_or_default()
-                },
-            }
-        },
+        window,
+        cell_metrics: Some(cell_metrics),
+        projects,
     };
 
     let settings_path = settings_path();
-    std::fs::create_dir_all(neovide_std_datapath()).unwrap();
     let json = serde_json::to_string(&settings).unwrap();
-    log::debug!("Saved Window Settings: {}", json);
-    std::fs::write(&settings_path, json)
-        .unwrap_or_else(|_| panic!("Can't write to {settings_path:?}"));
+    match std::fs::create_dir_all(neovide_std_datapath())
+        .and_then(|_| write_atomically(&settings_path, &json))
+    {
+        Ok(()) => log::debug!("Saved Window Settings: {}", json),
+        Err(error) => log::error!("Can't write to {settings_path:?}: {error}"),
+    }
+}
+
+/// Writes to a temporary file first and renames it, so that the settings are never left half
+/// written when Neovide is killed while saving them.
+fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let temporary_path = temporary_path(path);
+    std::fs::write(&temporary_path, contents)?;
+    std::fs::rename(&temporary_path, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary_path);
+    })
+}
+
+/// The temporary file `write_atomically` writes to, named after the process so that several
+/// Neovide instances saving at the same time don't write over each other's file.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(format!(".{}.tmp", std::process::id()));
+    PathBuf::from(temporary_path)
 }
 
 pub fn clamped_grid_size(grid_size: &GridSize<u32>) -> GridSize<u32> {
     grid_size.clamp(MIN_GRID_SIZE, MAX_GRID_SIZE)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_size_from_cell_metrics() {
+        let cell_metrics = CellMetrics::new(
+            PhysicalSize::new(9.5, 20.0),
+            PhysicalSize::new(1000, 830),
+            GridSize::new(104, 40),
+        );
+        assert_eq!(cell_metrics.padding, PhysicalSize::new(12, 30));
+        assert_eq!(
+            cell_metrics.grid_size(PhysicalSize::new(1000, 830)),
+            Some(GridSize::new(104, 40))
+        );
+        assert_eq!(
+            cell_metrics.grid_size(PhysicalSize::new(1500, 1030)),
+            Some(GridSize::new(156, 50))
+        );
+        assert_eq!(
+            cell_metrics.grid_size(PhysicalSize::new(10, 10)),
+            Some(MIN_GRID_SIZE)
+        );
+        let unusable = CellMetrics::new(
+            PhysicalSize::new(0.0, 0.0),
+            PhysicalSize::new(1000, 830),
+            GridSize::new(104, 40),
+        );
+        assert_eq!(unusable.grid_size(PhysicalSize::new(1000, 830)), None);
+    }
+
+    #[test]
+    fn settings_are_replaced_atomically() {
+        let path =
+            std::env::temp_dir().join(format!("neovide-settings-test-{}.json", std::process::id()));
+        write_atomically(&path, "first").unwrap();
+        write_atomically(&path, "second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        assert!(!temporary_path(&path).exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn temporary_file_is_per_process() {
+        let temporary_path = temporary_path(Path::new("settings.json"));
+        assert_eq!(
+            temporary_path,
+            PathBuf::from(format!("settings.json.{}.tmp", std::process::id()))
+        );
+    }
+
+    fn windowed(width: u32) -> PersistentWindowSettings {
+        PersistentWindowSettings::Windowed {
+            position: PhysicalPosition::default(),
+            pixel_size: Some(PhysicalSize::new(width, 600)),
+            grid_size: None,
+        }
+    }
+
+    #[test]
+    fn window_is_remembered_per_directory() {
+        let mut projects = Vec::new();
+        remember_project(&mut projects, PathBuf::from("/a"), windowed(1));
+        remember_project(&mut projects, PathBuf::from("/b"), windowed(2));
+        remember_project(&mut projects, PathBuf::from("/a"), windowed(3));
+        assert_eq!(
+            projects,
+            vec![
+                ProjectWindowSettings {
+                    directory: PathBuf::from("/a"),
+                    window: windowed(3),
+                },
+                ProjectWindowSettings {
+                    directory: PathBuf::from("/b"),
+                    window: windowed(2),
+                },
+            ]
+        );
+
+        let settings = || PersistentSettings {
+            window: windowed(4),
+            cell_metrics: None,
+            projects: projects.clone(),
+        };
+        assert_eq!(settings().window_for(Some(Path::new("/b"))), windowed(2));
+        assert_eq!(settings().window_for(Some(Path::new("/c"))), windowed(4));
+        assert_eq!(settings().window_for(None), windowed(4));
+
+        for index in 0..MAX_PROJECTS + 5 {
+            remember_project(
+                &mut projects,
+                PathBuf::from(format!("/{index}")),
+                windowed(5),
+            );
+        }
+        assert_eq!(projects.len(), MAX_PROJECTS);
+        assert_eq!(
+            projects[0].directory,
+            PathBuf::from(format!("/{}", MAX_PROJECTS + 4))
+        );
+    }
+}
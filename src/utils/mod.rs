@@ -4,6 +4,19 @@ mod test;
 
 pub use ring_buffer::*;
 
+use skia_safe::Color4f;
+
+/// Based on formula in https://graphicdesign.stackexchange.com/questions/62368/automatically-select-a-foreground-color-based-on-a-background-color
+/// Check if the color is light or dark
+pub fn is_light_color(color: &Color4f) -> bool {
+    luminance(color) > 0.5
+}
+
+/// The relative luminance of the color, from 0.0 for black to 1.0 for white.
+pub fn luminance(color: &Color4f) -> f32 {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
+
 #[cfg(not(target_os = "windows"))]
 pub fn is_tty() -> bool {
     use std::io::IsTerminal;
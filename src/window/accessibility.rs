@@ -0,0 +1,339 @@
+//! Exposes the text of the grid with the cursor, the position of the cursor and the mode to the
+//! screen readers through AccessKit, so that VoiceOver, NVDA and Orca can read what's on screen.
+//! The tree is built from the same draw commands as the rendered windows, and only when a screen
+//! reader asks for it.
+
+use std::collections::HashMap;
+
+use accesskit::{Action, Live, Node, NodeId, Role, TextPosition, TextSelection, Tree, TreeUpdate};
+use accesskit_winit::Adapter;
+use winit::{
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoopProxy},
+    window::Window,
+};
+
+use super::UserEvent;
+use crate::{
+    bridge::EditorMode,
+    renderer::{DrawCommand, LineFragment, WindowDrawCommand},
+};
+
+const ROOT_ID: NodeId = NodeId(0);
+const DOCUMENT_ID: NodeId = NodeId(1);
+const MODE_ID: NodeId = NodeId(2);
+/// The lines of the grid get the ids from here on.
+const FIRST_LINE_ID: u64 = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AccessibilityEvent {
+    InitialTreeRequested,
+    ActionRequested(Action),
+    Deactivated,
+}
+
+impl From<accesskit_winit::Event> for UserEvent {
+    fn from(event: accesskit_winit::Event) -> Self {
+        let event = match event.window_event {
+            accesskit_winit::WindowEvent::InitialTreeRequested => {
+                AccessibilityEvent::InitialTreeRequested
+            }
+            accesskit_winit::WindowEvent::ActionRequested(request) => {
+                AccessibilityEvent::ActionRequested(request.action)
+            }
+            accesskit_winit::WindowEvent::AccessibilityDeactivated => {
+                AccessibilityEvent::Deactivated
+            }
+        };
+        UserEvent::Accessibility(event)
+    }
+}
+
+/// A line of a grid, as the fragments of text covering its columns.
+#[derive(Clone, Debug, Default)]
+struct GridLine {
+    fragments: Vec<(u64, String)>,
+}
+
+impl GridLine {
+    fn new(line_fragments: &[LineFragment]) -> Self {
+        Self {
+            fragments: line_fragments
+                .iter()
+                .map(|fragment| (fragment.window_left, fragment.text.clone()))
+                .collect(),
+        }
+    }
+
+    fn text(&self) -> String {
+        let text = self
+            .fragments
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect::<String>();
+        text.trim_end().to_string()
+    }
+
+    /// The index of the character at `column`, clamped to the end of the trimmed text.
+    fn character_index(&self, column: u64) -> usize {
+        let mut index = 0;
+        for (left, text) in &self.fragments {
+            if *left > column {
+                break;
+            }
+            index += text.chars().count().min((column - left) as usize + 1);
+        }
+        // The index of the column itself, not the one after it
+        index.saturating_sub(1).min(self.text().chars().count())
+    }
+}
+
+/// What the screen readers are told about, kept up to date with the draw commands.
+#[derive(Default)]
+pub struct AccessibilityModel {
+    grids: HashMap<u64, Vec<GridLine>>,
+    cursor_grid: u64,
+    cursor_position: (u64, u64),
+    mode: Option<EditorMode>,
+}
+
+impl AccessibilityModel {
+    pub fn handle_draw_command(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::UpdateCursor(cursor) => {
+                self.cursor_grid = cursor.parent_window_id;
+                self.cursor_position = cursor.grid_position;
+            }
+            DrawCommand::ModeChanged(mode) => self.mode = Some(mode.clone()),
+            DrawCommand::CloseWindow(grid_id) => {
+                self.grids.remove(grid_id);
+            }
+            DrawCommand::Window { grid_id, command } => {
+                self.handle_window_command(*grid_id, command)
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_window_command(&mut self, grid_id: u64, command: &WindowDrawCommand) {
+        match command {
+            WindowDrawCommand::Position {
+                grid_size: (_, height),
+                ..
+            } => {
+                self.grids
+                    .entry(grid_id)
+                    .or_default()
+                    .resize(*height as usize, GridLine::default());
+            }
+            WindowDrawCommand::DrawLine {
+                row,
+                line_fragments,
+            } => {
+                let lines = self.grids.entry(grid_id).or_default();
+                if *row >= lines.len() {
+                    lines.resize(*row + 1, GridLine::default());
+                }
+                lines[*row] = GridLine::new(line_fragments);
+            }
+            WindowDrawCommand::Scroll {
+                top,
+                bottom,
+                rows,
+                cols: 0,
+                ..
+            } => {
+                let Some(lines) = self.grids.get_mut(&grid_id) else {
+                    return;
+                };
+                let bottom = (*bottom as usize).min(lines.len());
+                let top = (*top as usize).min(bottom);
+                let region = &mut lines[top..bottom];
+                let rows_up = *rows > 0;
+                let count = (rows.unsigned_abs() as usize).min(region.len());
+                // The lines scrolled in are drawn afterwards
+                if rows_up {
+                    region.rotate_left(count);
+                } else {
+                    region.rotate_right(count);
+                }
+            }
+            WindowDrawCommand::Clear => {
+                if let Some(lines) = self.grids.get_mut(&grid_id) {
+                    lines.fill(GridLine::default());
+                }
+            }
+            WindowDrawCommand::Close => {
+                self.grids.remove(&grid_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn mode_name(&self) -> &str {
+        match &self.mode {
+            Some(EditorMode::Normal) | None => "Normal",
+            Some(EditorMode::Insert) => "Insert",
+            Some(EditorMode::Visual) => "Visual",
+            Some(EditorMode::Replace) => "Replace",
+            Some(EditorMode::CmdLine) => "Command line",
+            Some(EditorMode::Unknown(name)) => name,
+        }
+    }
+
+    pub fn tree_update(&self, initial: bool) -> TreeUpdate {
+        let empty = Vec::new();
+        let lines = self.grids.get(&self.cursor_grid).unwrap_or(&empty);
+        let line_id = |row: usize| NodeId(FIRST_LINE_ID + row as u64);
+
+        let mut nodes = Vec::with_capacity(lines.len() + 3);
+        for (row, line) in lines.iter().enumerate() {
+            let text = line.text();
+            let mut node = Node::new(Role::TextRun);
+            node.set_character_lengths(
+                text.chars()
+                    .map(|character| character.len_utf8() as u8)
+                    .collect::<Vec<_>>(),
+            );
+            node.set_value(text);
+            nodes.push((line_id(row), node));
+        }
+
+        let mut document = Node::new(Role::MultilineTextInput);
+        document.set_label("Neovim");
+        document.set_children((0..lines.len()).map(line_id).collect::<Vec<_>>());
+        let (column, row) = self.cursor_position;
+        if let Some(line) = lines.get(row as usize) {
+            let position = TextPosition {
+                node: line_id(row as usize),
+                character_index: line.character_index(column),
+            };
+            document.set_text_selection(TextSelection {
+                anchor: position,
+                focus: position,
+            });
+        }
+        nodes.push((DOCUMENT_ID, document));
+
+        // Announced by the screen readers whenever it changes
+        let mut mode = Node::new(Role::Label);
+        mode.set_value(format!("{} mode", self.mode_name()));
+        mode.set_live(Live::Polite);
+        nodes.push((MODE_ID, mode));
+
+        let mut root = Node::new(Role::Window);
+        root.set_label("Neovide");
+        root.set_children(vec![DOCUMENT_ID, MODE_ID]);
+        nodes.push((ROOT_ID, root));
+
+        TreeUpdate {
+            nodes,
+            tree: initial.then(|| Tree::new(ROOT_ID)),
+            focus: DOCUMENT_ID,
+        }
+    }
+}
+
+/// The draw commands are followed from the start, the adapter is only attached once the window is
+/// created.
+#[derive(Default)]
+pub struct Accessibility {
+    adapter: Option<Adapter>,
+    model: AccessibilityModel,
+}
+
+impl Accessibility {
+    /// Has to be called before the window is shown.
+    pub fn attach(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window: &Window,
+        proxy: EventLoopProxy<UserEvent>,
+    ) {
+        self.adapter = Some(Adapter::with_event_loop_proxy(event_loop, window, proxy));
+    }
+
+    pub fn process_event(&mut self, window: &Window, event: &WindowEvent) {
+        if let Some(adapter) = &mut self.adapter {
+            adapter.process_event(window, event);
+        }
+    }
+
+    pub fn handle_draw_commands(&mut self, batch: &[DrawCommand]) {
+        for command in batch {
+            self.model.handle_draw_command(command);
+        }
+        self.update(false);
+    }
+
+    pub fn handle_event(&mut self, event: AccessibilityEvent) {
+        match event {
+            AccessibilityEvent::InitialTreeRequested => self.update(true),
+            AccessibilityEvent::ActionRequested(action) => {
+                log::trace!("Unsupported accessibility action {action:?}");
+            }
+            AccessibilityEvent::Deactivated => {}
+        }
+    }
+
+    /// Sends the tree to the screen reader, unless there's none.
+    fn update(&mut self, initial: bool) {
+        let model = &self.model;
+        if let Some(adapter) = &mut self.adapter {
+            adapter.update_if_active(|| model.tree_update(initial));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw_line(row: usize, text: &str) -> DrawCommand {
+        DrawCommand::Window {
+            grid_id: 2,
+            command: WindowDrawCommand::DrawLine {
+                row,
+                line_fragments: vec![LineFragment {
+                    text: text.to_string(),
+                    window_left: 0,
+                    width: text.chars().count() as u64,
+                    style: None,
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn scrolled_lines_follow_the_grid() {
+        let mut model = AccessibilityModel::default();
+        for (row, text) in ["one", "two", "three"].iter().enumerate() {
+            model.handle_draw_command(&draw_line(row, text));
+        }
+        model.handle_draw_command(&DrawCommand::Window {
+            grid_id: 2,
+            command: WindowDrawCommand::Scroll {
+                top: 0,
+                bottom: 3,
+                left: 0,
+                right: 5,
+                rows: 1,
+                cols: 0,
+            },
+        });
+        let lines = &model.grids[&2];
+        assert_eq!(lines[0].text(), "two");
+        assert_eq!(lines[1].text(), "three");
+    }
+
+    #[test]
+    fn cursor_column_maps_to_a_character() {
+        let line = GridLine {
+            fragments: vec![(0, "let ".to_string()), (4, "x  ".to_string())],
+        };
+        assert_eq!(line.text(), "let x");
+        assert_eq!(line.character_index(0), 0);
+        assert_eq!(line.character_index(4), 4);
+        assert_eq!(line.character_index(6), 5);
+    }
+}
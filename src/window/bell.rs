@@ -0,0 +1,69 @@
+//! What the bell of Neovim does while the window isn't focused, where it would go unnoticed
+//! otherwise: flash the button in the taskbar, or bounce the icon in the dock, and play the sound
+//! of the system, as `g:neovide_unfocused_bell` chooses.
+
+use winit::window::{UserAttentionType, Window};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BellAttention {
+    pub flash: bool,
+    pub sound: bool,
+}
+
+impl BellAttention {
+    /// Reads `flash`, `sound`, `both` or `none`, flashing for anything else.
+    pub fn parse(setting: &str) -> Self {
+        let (flash, sound) = match setting {
+            "sound" => (false, true),
+            "both" => (true, true),
+            "none" => (false, false),
+            _ => (true, false),
+        };
+        BellAttention { flash, sound }
+    }
+}
+
+pub fn ring_unfocused_bell(window: &Window, attention: BellAttention) {
+    if attention.flash {
+        window.request_user_attention(Some(UserAttentionType::Informational));
+    }
+    if attention.sound {
+        play_system_sound(window);
+    }
+}
+
+fn play_system_sound(window: &Window) {
+    #[cfg(target_os = "linux")]
+    if super::x11::x11_window(window).is_some() {
+        // There's no bell on Wayland yet
+        super::x11::ring_bell();
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = window;
+        unsafe { objc2_app_kit::NSBeep() };
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let _ = window;
+        crate::windows_utils::play_system_sound();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bell_attention() {
+        let parse = |setting| {
+            let attention = BellAttention::parse(setting);
+            (attention.flash, attention.sound)
+        };
+        assert_eq!(parse("flash"), (true, false));
+        assert_eq!(parse("sound"), (false, true));
+        assert_eq!(parse("both"), (true, true));
+        assert_eq!(parse("none"), (false, false));
+        assert_eq!(parse("bounce"), (true, false));
+    }
+}
@@ -0,0 +1,59 @@
+//! Blurs what's behind the transparent window. Winit does it on macOS and Windows, and on Wayland
+//! through the blur protocol of KWin, but not on X11, where KWin reads a property of the window
+//! instead.
+
+use winit::window::Window;
+
+#[cfg(target_os = "linux")]
+use super::{
+    opaque_region::opaque_region,
+    x11::{set_cardinal_property, x11_window},
+};
+
+#[cfg(target_os = "linux")]
+const BLUR_BEHIND_REGION: &str = "_KDE_NET_WM_BLUR_BEHIND_REGION";
+
+/// The blurred rectangles of a window of `width` by `height` pixels, flattened. It's empty for the
+/// whole window, but leaves out the rounded corners, which stay transparent.
+#[cfg(target_os = "linux")]
+fn blur_region(width: u32, height: u32, corner_radius: u32) -> Vec<u32> {
+    if corner_radius == 0 {
+        return Vec::new();
+    }
+    opaque_region(width, height, corner_radius)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Updates the blur after `g:neovide_window_blurred`, the opacity or the size of the window
+/// changed.
+pub fn update_blur(window: &Window, blurred: bool, corner_radius: u32) {
+    window.set_blur(blurred);
+    #[cfg(target_os = "linux")]
+    if let Some(x11_window) = x11_window(window) {
+        let size = window.inner_size();
+        let region = blur_region(size.width, size.height, corner_radius);
+        set_cardinal_property(
+            x11_window,
+            BLUR_BEHIND_REGION,
+            blurred.then_some(region.as_slice()),
+        );
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = corner_radius;
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_windows_are_blurred_whole() {
+        assert!(blur_region(800, 600, 0).is_empty());
+        assert_eq!(
+            blur_region(800, 600, 10),
+            vec![10, 0, 780, 10, 0, 10, 800, 580, 10, 590, 780, 10]
+        );
+    }
+}
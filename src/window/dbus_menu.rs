@@ -0,0 +1,281 @@
+//! Exports the menu of the window over D-Bus with the `com.canonical.dbusmenu` interface, and
+//! registers it with the `com.canonical.AppMenu.Registrar`, so the global menu bars of KDE and
+//! Unity show it. Only X11 windows can be registered, since the registrar identifies them by their
+//! X11 id.
+
+use std::collections::HashMap;
+
+use log::info;
+use serde::Serialize;
+use zbus::{
+    interface,
+    zvariant::{ObjectPath, OwnedValue, StructureBuilder, Type, Value},
+    Connection,
+};
+
+use super::session_bus::{self, with_timeout};
+use crate::bridge::{send_ui, ParallelCommand};
+
+const MENU_PATH: &str = "/MenuBar";
+const REGISTRAR: &str = "com.canonical.AppMenu.Registrar";
+const REGISTRAR_PATH: &str = "/com/canonical/AppMenu/Registrar";
+/// The version of the dbusmenu interface implemented.
+const VERSION: u32 = 3;
+
+struct MenuItem {
+    label: &'static str,
+    command: &'static str,
+}
+
+const fn item(label: &'static str, command: &'static str) -> MenuItem {
+    MenuItem { label, command }
+}
+
+/// The labels use `_` before the mnemonic, like GTK.
+const MENUS: &[(&str, &[MenuItem])] = &[
+    (
+        "_File",
+        &[
+            item("_New", "confirm enew"),
+            item("_Save", "write"),
+            item("Save _All", "wall"),
+            item("_Close", "confirm quit"),
+            item("_Quit", "confirm qall"),
+        ],
+    ),
+    (
+        "_Edit",
+        &[
+            item("_Undo", "undo"),
+            item("_Redo", "redo"),
+            item("_Paste", "normal! \"+gP"),
+            item("Select _All", "normal! ggVG"),
+        ],
+    ),
+    (
+        "_View",
+        &[
+            item(
+                "_Fullscreen",
+                "let g:neovide_fullscreen = !get(g:, 'neovide_fullscreen', v:false)",
+            ),
+            item(
+                "Zoom _In",
+                "let g:neovide_scale_factor = get(g:, 'neovide_scale_factor', 1.0) * 1.1",
+            ),
+            item(
+                "Zoom _Out",
+                "let g:neovide_scale_factor = get(g:, 'neovide_scale_factor', 1.0) / 1.1",
+            ),
+            item("_Reset Zoom", "let g:neovide_scale_factor = 1.0"),
+            item("_Tour", "NeovideTour"),
+        ],
+    ),
+];
+
+/// The id of the root is 0, the menus are numbered from 1, and their items from 100 times the
+/// number of the menu.
+fn item_id(menu: usize, item: usize) -> i32 {
+    ((menu + 1) * 100 + item + 1) as i32
+}
+
+fn item_command(id: i32) -> Option<&'static str> {
+    let (menu, item) = (id / 100 - 1, id % 100 - 1);
+    let (_, items) = MENUS.get(usize::try_from(menu).ok()?)?;
+    Some(items.get(usize::try_from(item).ok()?)?.command)
+}
+
+type Properties = HashMap<&'static str, Value<'static>>;
+
+/// A menu item and its children, the `(ia{sv}av)` of the dbusmenu layouts.
+#[derive(Serialize, Type)]
+struct Layout {
+    id: i32,
+    properties: Properties,
+    children: Vec<Value<'static>>,
+}
+
+impl Layout {
+    fn into_value(self) -> Value<'static> {
+        Value::from(
+            StructureBuilder::new()
+                .add_field(self.id)
+                .add_field(self.properties)
+                .add_field(self.children)
+                .build(),
+        )
+    }
+}
+
+fn properties(id: i32) -> Properties {
+    let label = match id {
+        0 => None,
+        1..=99 => MENUS.get(id as usize - 1).map(|(label, _)| *label),
+        _ => {
+            let (menu, item) = ((id / 100 - 1) as usize, (id % 100 - 1) as usize);
+            MENUS
+                .get(menu)
+                .and_then(|(_, items)| items.get(item))
+                .map(|item| item.label)
+        }
+    };
+    let mut properties = Properties::new();
+    if let Some(label) = label {
+        properties.insert("label", Value::from(label));
+    }
+    if (1..=99).contains(&id) || id == 0 {
+        properties.insert("children-display", Value::from("submenu"));
+    }
+    properties
+}
+
+fn layout(id: i32, depth: i32) -> Layout {
+    let children: Vec<i32> = match id {
+        0 => (1..=MENUS.len() as i32).collect(),
+        1..=99 => MENUS
+            .get(id as usize - 1)
+            .map(|(_, items)| (0..items.len()).map(|item| item_id(id as usize - 1, item)))
+            .into_iter()
+            .flatten()
+            .collect(),
+        _ => Vec::new(),
+    };
+    let children = if depth == 0 {
+        Vec::new()
+    } else {
+        children
+            .into_iter()
+            .map(|child| layout(child, depth - 1).into_value())
+            .collect()
+    };
+    Layout {
+        id,
+        properties: properties(id),
+        children,
+    }
+}
+
+struct DbusMenu {
+    revision: u32,
+}
+
+#[interface(name = "com.canonical.dbusmenu")]
+impl DbusMenu {
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> (u32, Layout) {
+        (self.revision, layout(parent_id, recursion_depth))
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, Properties)> {
+        ids.into_iter().map(|id| (id, properties(id))).collect()
+    }
+
+    fn get_property(&self, id: i32, name: String) -> Value<'static> {
+        properties(id)
+            .remove(name.as_str())
+            .unwrap_or_else(|| Value::from(""))
+    }
+
+    fn event(&self, id: i32, event_id: String, _data: OwnedValue, _timestamp: u32) {
+        if event_id != "clicked" {
+            return;
+        }
+        if let Some(command) = item_command(id) {
+            send_ui(ParallelCommand::Command(command.to_string()));
+        }
+    }
+
+    fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        for (id, event_id, data, timestamp) in events {
+            self.event(id, event_id, data, timestamp);
+        }
+        Vec::new()
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        VERSION
+    }
+
+    #[zbus(property)]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+async fn serve_menu(connection: Connection, window_id: u32) -> zbus::Result<()> {
+    // The menu is served as long as the shared connection is open
+    connection
+        .object_server()
+        .at(MENU_PATH, DbusMenu { revision: 1 })
+        .await?;
+    with_timeout(connection.call_method(
+        Some(REGISTRAR),
+        REGISTRAR_PATH,
+        Some(REGISTRAR),
+        "RegisterWindow",
+        &(window_id, ObjectPath::from_static_str_unchecked(MENU_PATH)),
+    ))
+    .await?;
+    info!("Registered the global menu of window {window_id}");
+    Ok(())
+}
+
+/// Serves the menu of the X11 window `window_id` on the connection to the session bus. Nothing
+/// happens when there's no global menu bar to show it.
+pub fn export_menu(window_id: u32) {
+    session_bus::spawn("global menu", move |connection| {
+        serve_menu(connection, window_id)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_ids_map_to_their_commands() {
+        assert_eq!(item_command(item_id(0, 1)), Some("write"));
+        assert_eq!(item_command(item_id(2, 4)), Some("NeovideTour"));
+        assert_eq!(item_command(0), None);
+        assert_eq!(item_command(1), None);
+        assert_eq!(item_command(item_id(0, 50)), None);
+        assert_eq!(item_command(item_id(10, 0)), None);
+    }
+
+    #[test]
+    fn layout_follows_the_depth() {
+        let root = layout(0, -1);
+        assert_eq!(root.children.len(), MENUS.len());
+        assert!(layout(0, 0).children.is_empty());
+        let file = layout(1, 1);
+        assert_eq!(file.properties.get("label"), Some(&Value::from("_File")));
+        assert_eq!(file.children.len(), MENUS[0].1.len());
+    }
+}
@@ -0,0 +1,76 @@
+//! Dropping onto the window. winit only tells which files are dropped, and not where, so the drag
+//! and drop APIs of macOS and Windows are also used, to insert dragged text and links at the cell
+//! they are dropped on.
+// Elsewhere only winit's files are dropped
+#![cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+
+use std::path::PathBuf;
+
+use winit::{event_loop::EventLoopProxy, window::Window};
+
+use super::UserEvent;
+use crate::units::PixelPos;
+
+/// What's dragged onto the window, as told by the drag and drop API of the platform.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragEvent {
+    /// Files, text or a link are dragged over the window.
+    Hovered,
+    /// The drag left the window without dropping anything.
+    Left,
+    /// Files were dropped. Only Windows sends them, since its drop target replaces winit's, which
+    /// sends `WindowEvent::DroppedFile` everywhere else.
+    Files(Vec<PathBuf>),
+    /// Text or a link was dropped, at a position in physical pixels of the window.
+    Text {
+        text: String,
+        position: PixelPos<f32>,
+    },
+}
+
+/// Receives the text and the links dragged onto the window, on top of the files winit handles.
+/// Neither X11 nor Wayland drags are available outside of winit, so only files can be dropped
+/// there.
+pub fn register_drop_target(window: &Window, proxy: EventLoopProxy<UserEvent>) {
+    #[cfg(target_os = "macos")]
+    super::macos::register_drop_target(window, proxy);
+    #[cfg(target_os = "windows")]
+    crate::windows_utils::register_drop_target(window, proxy);
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let _ = (window, proxy);
+}
+
+/// The path of a dropped file, to open it.
+pub fn dropped_file_path(path: PathBuf) -> Option<String> {
+    match path.into_os_string().into_string() {
+        Ok(path) => Some(path),
+        Err(path) => {
+            log::warn!("Can't open the dropped file {path:?}, its path isn't valid UTF-8");
+            None
+        }
+    }
+}
+
+/// The dropped text as Neovim puts it, with Unix line breaks. Nothing is inserted for empty text.
+pub fn dropped_text(text: &str) -> Option<String> {
+    let text = text.trim_end_matches('\0').replace("\r\n", "\n");
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_text_has_unix_line_breaks() {
+        assert_eq!(
+            dropped_text("first\r\nsecond\r\n\0"),
+            Some("first\nsecond\n".to_string())
+        );
+        assert_eq!(
+            dropped_text("https://neovide.dev/"),
+            Some("https://neovide.dev/".to_string())
+        );
+        assert_eq!(dropped_text("\0"), None);
+    }
+}
@@ -4,8 +4,8 @@ use skia_safe::{
     canvas::{Canvas, SaveLayerRec},
     colors::{BLACK, WHITE},
     textlayout::{
-        FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, TextHeightBehavior, TextIndex,
-        TextStyle,
+        FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, RectHeightStyle,
+        RectWidthStyle, TextHeightBehavior, TextIndex, TextStyle,
     },
     Color4f, FontMgr, Paint, Point, Rect, Size,
 };
@@ -14,16 +14,20 @@ use strum::{EnumCount, EnumIter};
 use winit::{
     application::ApplicationHandler,
     dpi::PhysicalSize,
-    event::{ElementState, KeyEvent, Modifiers, MouseScrollDelta, WindowEvent},
+    event::{ElementState, KeyEvent, Modifiers, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::{ActiveEventLoop, EventLoop},
     keyboard::{Key, NamedKey},
+    platform::run_on_demand::EventLoopExtRunOnDemand,
     window::Window,
 };
 
 use crate::{
     clipboard,
     cmd_line::SRGB_DEFAULT,
-    renderer::{build_window_config, create_skia_renderer, SkiaRenderer, WindowConfig},
+    renderer::{
+        build_window_config, create_skia_renderer, text_selection::TextSelection, SkiaRenderer,
+        WindowConfig,
+    },
     settings::Settings,
     window::{load_icon, UserEvent},
 };
@@ -37,10 +41,22 @@ const MIN_SIZE: PhysicalSize<u32> = PhysicalSize::new(500, 500);
 const DEFAULT_SIZE: PhysicalSize<u32> = PhysicalSize::new(800, 600);
 
 pub fn show_error_window(message: &str, event_loop: EventLoop<UserEvent>, settings: Arc<Settings>) {
-    let mut error_window = ErrorWindow::new(message, settings);
+    let mut error_window = ErrorWindow::new(message, false, settings);
     event_loop.run_app(&mut error_window).ok();
 }
 
+/// Shows the error window after the main window has been closed, with an additional option to
+/// recover. Returns `true` if the user chose to recover.
+pub fn show_recovery_window(
+    message: &str,
+    event_loop: &mut EventLoop<UserEvent>,
+    settings: Arc<Settings>,
+) -> bool {
+    let mut error_window = ErrorWindow::new(message, true, settings);
+    event_loop.run_app_on_demand(&mut error_window).ok();
+    error_window.recover_requested
+}
+
 #[derive(Debug)]
 enum Scroll {
     None,
@@ -73,19 +89,30 @@ struct State {
     current_position: TextIndex,
     modifiers: Modifiers,
     mouse_scroll_accumulator: f32,
+    /// Where the message was last painted, to hit test the pointer.
+    message_rect: Rect,
+    message_origin: Point,
+    pointer_position: Point,
+    /// The part of the message selected with the mouse, in UTF-16 positions like the paragraph.
+    selection: Option<TextSelection<usize>>,
+    selecting: bool,
 }
 
 struct ErrorWindow<'a> {
     state: Option<State>,
     message: &'a str,
+    recoverable: bool,
+    recover_requested: bool,
     settings: Arc<Settings>,
 }
 
 impl<'a> ErrorWindow<'a> {
-    fn new(message: &'a str, settings: Arc<Settings>) -> Self {
+    fn new(message: &'a str, recoverable: bool, settings: Arc<Settings>) -> Self {
         Self {
             state: None,
             message,
+            recoverable,
+            recover_requested: false,
             settings,
         }
     }
@@ -99,18 +126,36 @@ impl ApplicationHandler<UserEvent> for ErrorWindow<'_> {
         event: WindowEvent,
     ) {
         let state = self.state.as_mut().unwrap();
-        state.handle_window_event(event, event_loop, self.message);
+        if state.handle_window_event(event, event_loop, self.message, self.recoverable) {
+            self.recover_requested = true;
+            event_loop.exit();
+        }
     }
 
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.state.is_none() {
-            self.state = Some(State::new(self.message, event_loop, self.settings.clone()));
+            self.state = Some(State::new(
+                self.message,
+                self.recoverable,
+                event_loop,
+                self.settings.clone(),
+            ));
         }
     }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        // Close the window, the event loop may be run again
+        self.state = None;
+    }
 }
 
 impl State {
-    fn new(message: &str, event_loop: &ActiveEventLoop, settings: Arc<Settings>) -> Self {
+    fn new(
+        message: &str,
+        recoverable: bool,
+        event_loop: &ActiveEventLoop,
+        settings: Arc<Settings>,
+    ) -> Self {
         let message = message.trim_end();
 
         let font_manager = FontMgr::new();
@@ -124,7 +169,8 @@ impl State {
         skia_renderer.window().set_visible(true);
         let scale_factor = skia_renderer.window().scale_factor();
         let size = skia_renderer.window().inner_size();
-        let paragraphs = create_paragraphs(message, scale_factor as f32, &font_collection);
+        let paragraphs =
+            create_paragraphs(message, recoverable, scale_factor as f32, &font_collection);
         let scroll = Scroll::None;
         let current_position = 0;
         let modifiers = Modifiers::default();
@@ -140,15 +186,22 @@ impl State {
             current_position,
             modifiers,
             mouse_scroll_accumulator,
+            message_rect: Rect::default(),
+            message_origin: Point::default(),
+            pointer_position: Point::default(),
+            selection: None,
+            selecting: false,
         }
     }
 
+    /// Returns `true` when recovering was requested.
     fn handle_window_event(
         &mut self,
         event: WindowEvent,
         event_loop: &ActiveEventLoop,
         message: &str,
-    ) {
+        recoverable: bool,
+    ) -> bool {
         match event {
             WindowEvent::CloseRequested => {
                 event_loop.exit();
@@ -162,14 +215,22 @@ impl State {
             }
             WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                 self.scale_factor = scale_factor;
-                self.paragraphs =
-                    create_paragraphs(message, scale_factor as f32, &self.font_collection);
+                self.paragraphs = create_paragraphs(
+                    message,
+                    recoverable,
+                    scale_factor as f32,
+                    &self.font_collection,
+                );
             }
             WindowEvent::KeyboardInput {
                 event,
                 is_synthetic: false,
                 ..
             } => {
+                let recover = matches!(&event.logical_key, Key::Character(c) if c.as_str() == "r");
+                if recoverable && recover && event.state == ElementState::Pressed {
+                    return true;
+                }
                 if self.handle_keyboard_input(event, event_loop, message) {
                     self.skia_renderer.window().request_redraw();
                 }
@@ -192,8 +253,32 @@ impl State {
                 }
             }
             WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer_position = Point::new(position.x as f32, position.y as f32);
+                if self.selecting {
+                    if let Some(selection) = &mut self.selection {
+                        selection.focus = self.message_position_at(self.pointer_position);
+                    }
+                    self.skia_renderer.window().request_redraw();
+                }
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.selecting = state == ElementState::Pressed
+                    && self.message_rect.contains(self.pointer_position);
+                if state == ElementState::Pressed {
+                    self.selection = self.selecting.then(|| {
+                        TextSelection::new(self.message_position_at(self.pointer_position))
+                    });
+                    self.skia_renderer.window().request_redraw();
+                }
+            }
             _ => {}
         }
+        false
     }
 
     fn render(&mut self) {
@@ -202,10 +287,19 @@ impl State {
         let (offset, possible_scroll_direction) =
             self.handle_scrolling(message_rect.height() as f64);
 
+        self.message_rect = message_rect;
+        self.message_origin = Point::new(PADDING, PADDING - offset as f32);
+
         let canvas = self.skia_renderer.canvas();
         canvas.save();
 
-        render_main_message(&self.paragraphs.message, canvas, &message_rect, offset);
+        render_main_message(
+            &self.paragraphs.message,
+            canvas,
+            &message_rect,
+            self.message_origin,
+            self.selection,
+        );
         render_help_message(
             &self.paragraphs.help_messages[possible_scroll_direction as usize],
             canvas,
@@ -218,6 +312,26 @@ impl State {
         self.skia_renderer.swap_buffers();
     }
 
+    /// The position of the message under `point`.
+    fn message_position_at(&self, point: Point) -> usize {
+        let position = self
+            .paragraphs
+            .message
+            .get_glyph_position_at_coordinate(point - self.message_origin);
+        position.position.max(0) as usize
+    }
+
+    /// The selected text of the message, or all of it without a selection.
+    fn copied_text(&self, message: &str) -> String {
+        match self.selection.filter(|selection| !selection.is_empty()) {
+            Some(selection) => {
+                let (start, end) = selection.range();
+                utf16_slice(message.trim_end(), start, end)
+            }
+            None => message.to_string(),
+        }
+    }
+
     fn handle_keyboard_input(
         &mut self,
         event: KeyEvent,
@@ -227,6 +341,14 @@ impl State {
         if event.state != ElementState::Pressed {
             return false;
         }
+        #[cfg(target_os = "macos")]
+        let copy_modifier = self.modifiers.state().super_key();
+        #[cfg(not(target_os = "macos"))]
+        let copy_modifier = self.modifiers.state().control_key();
+        if copy_modifier && matches!(event.logical_key.as_ref(), Key::Character("c" | "C")) {
+            let _ = clipboard::set_contents(self.copied_text(message), "+");
+            return true;
+        }
         let handled = if self.modifiers.state().control_key() {
             // Ctrl is pressed
             // Require e and y to be combined with ctrl, since y is copy
@@ -257,7 +379,7 @@ impl State {
                         true
                     }
                     "y" => {
-                        let _ = clipboard::set_contents(message.to_string(), "+");
+                        let _ = clipboard::set_contents(self.copied_text(message), "+");
                         true
                     }
                     _ => false,
@@ -426,15 +548,44 @@ impl State {
     }
 }
 
-fn render_main_message(message: &Paragraph, canvas: &Canvas, rect: &Rect, offset: f64) {
+fn render_main_message(
+    message: &Paragraph,
+    canvas: &Canvas,
+    rect: &Rect,
+    origin: Point,
+    selection: Option<TextSelection<usize>>,
+) {
     canvas.clear(BACKGROUND_COLOR);
 
     let save_layer_rec = SaveLayerRec::default().bounds(rect);
     canvas.save_layer(&save_layer_rec);
-    message.paint(canvas, Point::new(PADDING, PADDING - offset as f32));
+    if let Some(selection) = selection.filter(|selection| !selection.is_empty()) {
+        let (start, end) = selection.range();
+        let mut color = TEXT_COLOR;
+        color.a = 0.3;
+        let paint = Paint::new(color, None);
+        for text_box in
+            message.get_rects_for_range(start..end, RectHeightStyle::Max, RectWidthStyle::Tight)
+        {
+            canvas.draw_rect(text_box.rect.with_offset(origin), &paint);
+        }
+    }
+    message.paint(canvas, origin);
     canvas.restore();
 }
 
+/// The part of `text` between two UTF-16 positions, which the paragraphs of Skia use.
+fn utf16_slice(text: &str, start: usize, end: usize) -> String {
+    let mut position = 0;
+    text.chars()
+        .filter(|character| {
+            let inside = position >= start && position < end;
+            position += character.len_utf16();
+            inside
+        })
+        .collect()
+}
+
 fn render_help_message(message: &Paragraph, canvas: &Canvas, help_message_rect: &Rect) {
     let help_message_text_point =
         Point::new(help_message_rect.left + PADDING, help_message_rect.top);
@@ -444,6 +595,7 @@ fn render_help_message(message: &Paragraph, canvas: &Canvas, help_message_rect:
 
 fn create_paragraphs(
     message: &str,
+    recoverable: bool,
     scale_factor: f32,
     font_collection: &FontCollection,
 ) -> Paragraphs {
@@ -469,7 +621,11 @@ fn create_paragraphs(
         paragraph_builder.build()
     };
 
-    let message_line = "quit (q), copy (y)";
+    let message_line = if recoverable {
+        "quit (q), copy (y), recover (r)"
+    } else {
+        "quit (q), copy (y)"
+    };
 
     let help_messages = PossibleScrollDirection::iter()
         .map(|dir| match dir {
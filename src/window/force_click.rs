@@ -0,0 +1,196 @@
+//! Force clicks of the macOS trackpads, which press harder after a click. The text under the
+//! pointer is classified so that a word, a URL and a file path can each do something else.
+
+/// What a force click landed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForceClickKind {
+    Word,
+    Url,
+    File,
+}
+
+impl ForceClickKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ForceClickKind::Word => "word",
+            ForceClickKind::Url => "url",
+            ForceClickKind::File => "file",
+        }
+    }
+}
+
+/// Characters around the URLs and the paths in prose and code, which never end them.
+fn is_delimiter(character: char) -> bool {
+    character.is_whitespace() || "()[]{}<>\"'`,;|".contains(character)
+}
+
+fn is_word_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+/// The characters of `characters` around `index` that `accept` takes, as a range.
+fn expand(
+    characters: &[char],
+    index: usize,
+    accept: impl Fn(char) -> bool,
+) -> Option<(usize, usize)> {
+    if !characters
+        .get(index)
+        .is_some_and(|character| accept(*character))
+    {
+        return None;
+    }
+    let start = characters[..index]
+        .iter()
+        .rposition(|character| !accept(*character))
+        .map_or(0, |position| position + 1);
+    let end = characters[index..]
+        .iter()
+        .position(|character| !accept(*character))
+        .map_or(characters.len(), |position| index + position);
+    Some((start, end))
+}
+
+fn is_url(token: &str) -> bool {
+    let Some((scheme, rest)) = token.split_once("://") else {
+        return token.starts_with("mailto:") && token.len() > "mailto:".len();
+    };
+    let mut scheme_characters = scheme.chars();
+    !rest.is_empty()
+        && scheme_characters
+            .next()
+            .is_some_and(|character| character.is_ascii_alphabetic())
+        && scheme_characters
+            .all(|character| character.is_ascii_alphanumeric() || "+.-".contains(character))
+}
+
+fn is_file(token: &str) -> bool {
+    token.contains('/') || token.contains('\\') || token.starts_with('~')
+}
+
+/// Classifies the text at the character `index` of `line`, and returns it. A URL or a path keeps
+/// everything up to the delimiters around it, like `src/main.rs:42`, without the punctuation ending
+/// a sentence. Anything else is the word under the pointer, and blanks are nothing.
+pub fn force_click_target(line: &str, index: usize) -> Option<(ForceClickKind, String)> {
+    let characters = line.chars().collect::<Vec<_>>();
+    let (start, end) = expand(&characters, index, |character| !is_delimiter(character))?;
+    let token = characters[start..end]
+        .iter()
+        .collect::<String>()
+        .trim_end_matches(['.', ':', '!', '?'])
+        .to_string();
+    if is_url(&token) {
+        return Some((ForceClickKind::Url, token));
+    }
+    if is_file(&token) {
+        return Some((ForceClickKind::File, token));
+    }
+    let (start, end) = expand(&characters, index, is_word_character)?;
+    Some((
+        ForceClickKind::Word,
+        characters[start..end].iter().collect(),
+    ))
+}
+
+/// How hard the trackpad has to be pressed for a force click, from the `input_macos_force_click`
+/// settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PressureThresholds {
+    pub enabled: bool,
+    /// How far into the force stage the pressure goes first, from 0 to 1.
+    pub threshold: f32,
+    /// How far below the threshold the pressure falls before another force click can fire.
+    pub dead_zone: f32,
+}
+
+impl Default for PressureThresholds {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 0.0,
+            dead_zone: 0.0,
+        }
+    }
+}
+
+/// Follows the stage and the pressure reported by the trackpad, and tells when they make a force
+/// click. After one fired, the pressure has to leave the force stage or fall through the dead zone
+/// before the next one, so that a wavering finger doesn't repeat it.
+#[derive(Debug, Default)]
+pub struct PressureTracker {
+    fired: bool,
+}
+
+impl PressureTracker {
+    /// Returns whether the new `stage` and `pressure` make a force click.
+    pub fn update(&mut self, stage: i64, pressure: f32, thresholds: PressureThresholds) -> bool {
+        if stage < 2 || pressure < thresholds.threshold - thresholds.dead_zone {
+            self.fired = false;
+            return false;
+        }
+        if self.fired || !thresholds.enabled || pressure < thresholds.threshold {
+            return false;
+        }
+        self.fired = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn force_clicks_follow_the_thresholds() {
+        let mut tracker = PressureTracker::default();
+        let thresholds = PressureThresholds::default();
+        assert!(!tracker.update(1, 0.9, thresholds));
+        assert!(tracker.update(2, 0.0, thresholds));
+        assert!(!tracker.update(2, 0.5, thresholds));
+        assert!(!tracker.update(1, 0.5, thresholds));
+        assert!(tracker.update(2, 0.1, thresholds));
+
+        let mut tracker = PressureTracker::default();
+        let thresholds = PressureThresholds {
+            enabled: true,
+            threshold: 0.5,
+            dead_zone: 0.2,
+        };
+        assert!(!tracker.update(2, 0.4, thresholds));
+        assert!(tracker.update(2, 0.6, thresholds));
+        // Wavering inside the dead zone doesn't fire again
+        assert!(!tracker.update(2, 0.35, thresholds));
+        assert!(!tracker.update(2, 0.6, thresholds));
+        assert!(!tracker.update(2, 0.2, thresholds));
+        assert!(tracker.update(2, 0.6, thresholds));
+
+        let mut tracker = PressureTracker::default();
+        let disabled = PressureThresholds {
+            enabled: false,
+            ..PressureThresholds::default()
+        };
+        assert!(!tracker.update(2, 1.0, disabled));
+    }
+
+    #[test]
+    fn force_clicks_are_classified() {
+        let line = "See https://neovide.dev/faq.html. (src/main.rs:42) for vim.lsp.buf.hover()";
+        let at = |text: &str| force_click_target(line, line.find(text).unwrap());
+        assert_eq!(
+            at("neovide.dev"),
+            Some((
+                ForceClickKind::Url,
+                "https://neovide.dev/faq.html".to_string()
+            ))
+        );
+        assert_eq!(
+            at("main"),
+            Some((ForceClickKind::File, "src/main.rs:42".to_string()))
+        );
+        assert_eq!(at("buf"), Some((ForceClickKind::Word, "buf".to_string())));
+        assert_eq!(at("See"), Some((ForceClickKind::Word, "See".to_string())));
+        assert_eq!(at(" for"), None);
+        assert_eq!(at("()"), None);
+        assert_eq!(force_click_target(line, 1000), None);
+    }
+}
@@ -1,21 +1,21 @@
 use std::sync::Arc;
 
 use crate::{
-    bridge::{send_ui, SerialCommand},
+    bridge::{input_latency, send_ui, SerialCommand},
+    cmd_line::CmdLineSettings,
     settings::Settings,
+    units::GridVec,
+    window::WindowSettings,
 };
 
 #[allow(unused_imports)]
 use winit::platform::modifier_supplement::KeyEventExtModifierSupplement;
 use winit::{
     event::{ElementState, Ime, KeyEvent, Modifiers, WindowEvent},
-    keyboard::{Key, KeyCode, KeyLocation, NamedKey, PhysicalKey},
+    keyboard::{Key, KeyCode, KeyLocation, ModifiersState, NamedKey, PhysicalKey},
 };
 #[cfg(target_os = "macos")]
-use {
-    crate::{window::settings::OptionAsMeta, window::WindowSettings},
-    winit::keyboard::ModifiersKeyState,
-};
+use {crate::window::settings::OptionAsMeta, winit::keyboard::ModifiersKeyState};
 
 use crate::profiling::tracy_named_frame;
 
@@ -23,11 +23,60 @@ fn is_ascii_alphabetic_char(text: &str) -> bool {
     text.len() == 1 && text.chars().next().unwrap().is_ascii_alphabetic()
 }
 
+/// Where the cursor goes in normal mode for the keys that move it by one cell.
+fn key_motion(key: &str) -> Option<GridVec<i32>> {
+    match key {
+        "h" | "<Left>" => Some(GridVec::new(-1, 0)),
+        "l" | "<Right>" => Some(GridVec::new(1, 0)),
+        "k" | "<Up>" => Some(GridVec::new(0, -1)),
+        "j" | "<Down>" => Some(GridVec::new(0, 1)),
+        _ => None,
+    }
+}
+
+/// What the keys sent since the last complete command still wait for, which changes what a motion
+/// key does, like the `j` of `dj`, `5j`, `rj` or `fj`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum PendingCommand {
+    #[default]
+    None,
+    /// A count, like the `5` of `5j`.
+    Count,
+    /// An operator or a prefix waiting for a motion, like `d` or `g`.
+    Operator,
+    /// A command taking the next key as a character, like `r`, `f` or the `"` of a register.
+    Character,
+}
+
+impl PendingCommand {
+    /// Follows `key` and returns its motion when it moves the cursor on its own.
+    fn after_key(&mut self, key: &str) -> Option<GridVec<i32>> {
+        let pending = *self;
+        *self = match (pending, key) {
+            (PendingCommand::Character, _) => PendingCommand::None,
+            (_, "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9")
+            | (PendingCommand::Count, "0") => PendingCommand::Count,
+            (PendingCommand::None, "d" | "c" | "y" | "<" | ">" | "=" | "!" | "g" | "z") => {
+                PendingCommand::Operator
+            }
+            (_, "r" | "f" | "F" | "t" | "T" | "m" | "'" | "`" | "\"" | "q" | "@") => {
+                PendingCommand::Character
+            }
+            _ => PendingCommand::None,
+        };
+        key_motion(key).filter(|_| pending == PendingCommand::None)
+    }
+}
+
 pub struct KeyboardManager {
     modifiers: Modifiers,
     ime_preedit: (String, Option<(usize, usize)>),
     meta_is_pressed: bool, // see note on 'meta' below
-    #[allow(dead_code)]
+    /// The up and down keys browse the wildmenu when it's drawn as a list.
+    wildmenu_vertical: bool,
+    /// The cursor motion of the last key sent to a remote server, to show it before Neovim does.
+    predicted_motion: Option<GridVec<i32>>,
+    pending_command: PendingCommand,
     settings: Arc<Settings>,
 }
 
@@ -37,10 +86,41 @@ impl KeyboardManager {
             modifiers: Modifiers::default(),
             ime_preedit: ("".to_string(), None),
             meta_is_pressed: false,
+            wildmenu_vertical: false,
+            predicted_motion: None,
+            pending_command: PendingCommand::None,
             settings,
         }
     }
 
+    pub fn set_wildmenu_vertical(&mut self, wildmenu_vertical: bool) {
+        self.wildmenu_vertical = wildmenu_vertical;
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers.state()
+    }
+
+    pub fn take_predicted_motion(&mut self) -> Option<GridVec<i32>> {
+        self.predicted_motion.take()
+    }
+
+    fn is_remote(&self) -> bool {
+        self.settings.get::<CmdLineSettings>().server.is_some()
+    }
+
+    /// Drops the repeats of a held key while a slow server hasn't caught up with the previous
+    /// ones.
+    fn drops_repeat(&self, key_event: &KeyEvent) -> bool {
+        key_event.repeat
+            && self.is_remote()
+            && self
+                .settings
+                .get::<WindowSettings>()
+                .input_remote_repeat_throttle
+            && !input_latency::should_send_repeat()
+    }
+
     pub fn handle_event(&mut self, event: &WindowEvent) {
         match event {
             WindowEvent::KeyboardInput {
@@ -49,10 +129,27 @@ impl KeyboardManager {
                 ..
             } if self.ime_preedit.0.is_empty() => {
                 log::trace!("{:#?}", key_event);
-                if key_event.state == ElementState::Pressed {
-                    if let Some(text) = self.format_key(key_event) {
+                if key_event.state == ElementState::Pressed && self.drops_repeat(key_event) {
+                    log::trace!("Key repeat dropped until the server catches up");
+                } else if key_event.state == ElementState::Pressed {
+                    if let Some(mut text) = self.format_key(key_event) {
                         log::trace!("Key pressed {} {:?}", text, self.modifiers.state());
+                        if self.wildmenu_vertical {
+                            text = match text.as_str() {
+                                "<Down>" => "<C-n>".to_string(),
+                                "<Up>" => "<C-p>".to_string(),
+                                _ => text,
+                            };
+                        }
                         tracy_named_frame!("keyboard input");
+                        if self.is_remote()
+                            && self
+                                .settings
+                                .get::<WindowSettings>()
+                                .input_remote_cursor_prediction
+                        {
+                            self.predicted_motion = self.pending_command.after_key(&text);
+                        }
                         send_ui(SerialCommand::Keyboard(text));
                     }
                 }
@@ -374,3 +471,35 @@ fn get_special_key(key_event: &KeyEvent) -> Option<&str> {
 // avoid confusing users who have a post-2017 keyboard and are not aware of this
 // history, it is probably best to refer to this physical key as the 'option'
 // key, and not as the 'alt' key.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn motions(keys: &[&str]) -> Vec<Option<GridVec<i32>>> {
+        let mut pending = PendingCommand::None;
+        keys.iter().map(|key| pending.after_key(key)).collect()
+    }
+
+    #[test]
+    fn motions_are_only_predicted_without_a_pending_command() {
+        let down = Some(GridVec::new(0, 1));
+        let right = Some(GridVec::new(1, 0));
+        assert_eq!(motions(&["j", "l"]), vec![down, right]);
+        assert_eq!(motions(&["d", "j", "j"]), vec![None, None, down]);
+        assert_eq!(motions(&["5", "j", "j"]), vec![None, None, down]);
+        assert_eq!(motions(&["1", "0", "j"]), vec![None, None, None]);
+        assert_eq!(motions(&["r", "l", "l"]), vec![None, None, right]);
+        assert_eq!(motions(&["f", "l", "l"]), vec![None, None, right]);
+        assert_eq!(
+            motions(&["d", "f", "l", "l"]),
+            vec![None, None, None, right]
+        );
+        assert_eq!(
+            motions(&["\"", "a", "y", "j", "j"]),
+            vec![None, None, None, None, down]
+        );
+        // Escape cancels the operator
+        assert_eq!(motions(&["d", "<Esc>", "j"]), vec![None, None, down]);
+    }
+}
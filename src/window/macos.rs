@@ -1,30 +1,51 @@
 use std::sync::Arc;
-use std::{os::raw::c_void, str};
+use std::{
+    cell::OnceCell,
+    env,
+    os::raw::c_void,
+    path::{Path, PathBuf},
+    process::Command,
+    str,
+};
 
 use objc2::{
     declare_class, msg_send, msg_send_id, mutability,
     rc::{autoreleasepool, Retained},
-    runtime::{AnyClass, AnyObject, ClassBuilder},
+    runtime::{AnyClass, AnyObject, ClassBuilder, NSObjectProtocol, ProtocolObject, Sel},
     sel, ClassType, DeclaredClass,
 };
 use objc2_app_kit::{
-    NSApplication, NSAutoresizingMaskOptions, NSColor, NSEvent, NSEventModifierFlags, NSImage,
-    NSMenu, NSMenuItem, NSView, NSWindow, NSWindowStyleMask, NSWindowTabbingMode,
+    NSApplication, NSAutoresizingMaskOptions, NSColor, NSDocumentController, NSEvent,
+    NSEventModifierFlags, NSFilenamesPboardType, NSImage, NSMenu, NSMenuItem, NSPasteboard,
+    NSPasteboardTypeString, NSPasteboardTypeURL, NSView, NSWindow, NSWindowStyleMask,
+    NSWindowTabbingMode, NSWorkspace,
 };
 use objc2_foundation::{
     ns_string, MainThreadMarker, NSArray, NSData, NSDictionary, NSObject, NSPoint, NSProcessInfo,
-    NSRect, NSSize, NSString, NSUserDefaults,
+    NSRect, NSSize, NSString, NSUserDefaults, NSUserNotification, NSUserNotificationCenter,
+    NSUserNotificationCenterDelegate, NSUserNotificationDefaultSoundName, NSURL,
 };
 
 use csscolorparser::Color;
+use log::error;
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
-use winit::window::Window;
+use winit::{event_loop::EventLoopProxy, window::Window};
 
 use crate::{
     bridge::{send_ui, ParallelCommand},
     settings::Settings,
 };
-use crate::{cmd_line::CmdLineSettings, error_msg, frame::Frame};
+use crate::{
+    cmd_line::{parse_url, CmdLineSettings},
+    error_msg,
+    frame::Frame,
+    ipc,
+    units::PixelPos,
+};
+
+use super::{
+    dropped_text, os_notify::OsNotification, DragEvent, TaskbarProgress, UserEvent, WindowCommand,
+};
 
 use super::{WindowSettings, WindowSettingsChanged};
 
@@ -226,6 +247,21 @@ impl MacosWindowFeature {
         }
     }
 
+    /// Shows the proxy icon of `file` in the title bar, which can be dragged to other apps like
+    /// Finder and Mail, or hides it.
+    pub fn set_represented_file(&self, file: Option<&Path>) {
+        let file = file
+            .map(|file| file.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        unsafe {
+            self.ns_window
+                .setRepresentedFilename(&NSString::from_str(&file));
+        }
+        if !file.is_empty() {
+            note_recent_document(&file);
+        }
+    }
+
     /// Print a deprecation warning for `neovide_background_color`
     fn display_deprecation_warning(&self) {
         error_msg!(concat!(
@@ -483,6 +519,365 @@ impl Menu {
     }
 }
 
+/// Adds `file` to the recent documents, shown in the dock menu and by Spotlight.
+fn note_recent_document(file: &str) {
+    let Some(mtm) = MainThreadMarker::new() else {
+        return;
+    };
+    unsafe {
+        let url = NSURL::fileURLWithPath(&NSString::from_str(file));
+        NSDocumentController::sharedDocumentController(mtm).noteNewRecentDocumentURL(&url);
+    }
+}
+
+#[derive(Clone)]
+struct DockMenuHandlerIvars {}
+
+declare_class!(
+    #[derive(Debug)]
+    struct DockMenuHandler;
+
+    unsafe impl ClassType for DockMenuHandler {
+        type Super = NSObject;
+        type Mutability = mutability::MainThreadOnly;
+        const NAME: &'static str = "DockMenuHandler";
+    }
+
+    impl DeclaredClass for DockMenuHandler {
+        type Ivars = DockMenuHandlerIvars;
+    }
+
+    unsafe impl DockMenuHandler {
+        #[method(newWindow:)]
+        unsafe fn new_window(&self, _sender: &NSMenuItem) {
+            // Every window is its own process
+            if let Err(error) = env::current_exe().and_then(|neovide| Command::new(neovide).spawn())
+            {
+                error!("Could not open a new window: {error}");
+            }
+        }
+
+        #[method(openRecent:)]
+        unsafe fn open_recent(&self, sender: &NSMenuItem) {
+            let Some(url) = sender.representedObject() else {
+                return;
+            };
+            let url = Retained::cast::<NSURL>(url);
+            if let Some(path) = url.path() {
+                send_ui(ParallelCommand::FileDrop(path.to_string()));
+            }
+        }
+    }
+);
+
+impl DockMenuHandler {
+    fn new(mtm: MainThreadMarker) -> Retained<DockMenuHandler> {
+        unsafe { msg_send_id![mtm.alloc(), init] }
+    }
+}
+
+thread_local! {
+    // The menu items only keep a weak reference to their target
+    static DOCK_MENU_HANDLER: OnceCell<Retained<DockMenuHandler>> = const { OnceCell::new() };
+}
+
+struct NotificationHandlerIvars {
+    proxy: EventLoopProxy<UserEvent>,
+}
+
+declare_class!(
+    struct NotificationHandler;
+
+    unsafe impl ClassType for NotificationHandler {
+        type Super = NSObject;
+        type Mutability = mutability::MainThreadOnly;
+        const NAME: &'static str = "NotificationHandler";
+    }
+
+    impl DeclaredClass for NotificationHandler {
+        type Ivars = NotificationHandlerIvars;
+    }
+
+    unsafe impl NSObjectProtocol for NotificationHandler {}
+
+    #[allow(deprecated)]
+    unsafe impl NSUserNotificationCenterDelegate for NotificationHandler {
+        #[method(userNotificationCenter:didActivateNotification:)]
+        unsafe fn did_activate_notification(
+            &self,
+            _center: &NSUserNotificationCenter,
+            _notification: &NSUserNotification,
+        ) {
+            let _ = self.ivars().proxy.send_event(WindowCommand::FocusWindow.into());
+        }
+    }
+);
+
+impl NotificationHandler {
+    fn new(mtm: MainThreadMarker, proxy: EventLoopProxy<UserEvent>) -> Retained<Self> {
+        let this = mtm.alloc().set_ivars(NotificationHandlerIvars { proxy });
+        unsafe { msg_send_id![super(this), init] }
+    }
+}
+
+thread_local! {
+    // The notification center only keeps a weak reference to its delegate
+    static NOTIFICATION_HANDLER: OnceCell<Retained<NotificationHandler>> = const { OnceCell::new() };
+}
+
+/// Posts the notification to the notification center, clicking it focuses the window.
+#[allow(deprecated)] // The UserNotifications framework needs a signed app
+pub fn show_notification(notification: &OsNotification, proxy: EventLoopProxy<UserEvent>) {
+    let Some(mtm) = MainThreadMarker::new() else {
+        return;
+    };
+    unsafe {
+        let center = NSUserNotificationCenter::defaultUserNotificationCenter();
+        NOTIFICATION_HANDLER.with(|handler| {
+            handler.get_or_init(|| {
+                let handler = NotificationHandler::new(mtm, proxy);
+                center.setDelegate(Some(ProtocolObject::from_ref(&*handler)));
+                handler
+            });
+        });
+
+        let user_notification = NSUserNotification::new();
+        user_notification.setTitle(Some(&NSString::from_str(&notification.title)));
+        user_notification.setInformativeText(Some(&NSString::from_str(&notification.body)));
+        if notification.sound {
+            user_notification.setSoundName(Some(NSUserNotificationDefaultSoundName));
+        }
+        center.deliverNotification(&user_notification);
+    }
+}
+
+/// Shows the progress as the badge of the icon in the dock, since the dock has no progress bars.
+pub fn show_dock_progress(progress: TaskbarProgress) {
+    let Some(mtm) = MainThreadMarker::new() else {
+        return;
+    };
+    let label = match progress {
+        TaskbarProgress::Hidden => None,
+        TaskbarProgress::Indeterminate => Some("…".to_string()),
+        TaskbarProgress::Error(_) => Some("!".to_string()),
+        TaskbarProgress::Normal(fraction) | TaskbarProgress::Paused(fraction) => {
+            Some(format!("{}%", (fraction * 100.0).round()))
+        }
+    };
+    let label = label.map(|label| NSString::from_str(&label));
+    unsafe {
+        let dock_tile = NSApplication::sharedApplication(mtm).dockTile();
+        dock_tile.setBadgeLabel(label.as_deref());
+    }
+}
+
+/// Titles the recent documents with their file names, and adds the folder to the names shared by
+/// several of them, like the Open Recent menu of other apps.
+fn recent_document_titles(paths: &[PathBuf]) -> Vec<(String, &PathBuf)> {
+    let file_name = |path: &PathBuf| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    };
+    paths
+        .iter()
+        .filter_map(|path| {
+            let name = file_name(path)?;
+            let shared = paths
+                .iter()
+                .filter(|other| file_name(other).as_ref() == Some(&name))
+                .count()
+                > 1;
+            let folder = path.parent().and_then(Path::file_name).filter(|_| shared);
+            let title = match folder {
+                Some(folder) => format!("{name} — {}", folder.to_string_lossy()),
+                None => name,
+            };
+            Some((title, path))
+        })
+        .collect()
+}
+
+fn dock_menu(mtm: MainThreadMarker) -> Retained<NSMenu> {
+    let handler =
+        DOCK_MENU_HANDLER.with(|handler| handler.get_or_init(|| DockMenuHandler::new(mtm)).clone());
+    unsafe {
+        let menu = NSMenu::new(mtm);
+
+        let new_window_item = NSMenuItem::new(mtm);
+        new_window_item.setTitle(ns_string!("New Window"));
+        new_window_item.setAction(Some(sel!(newWindow:)));
+        new_window_item.setTarget(Some(&handler));
+        menu.addItem(&new_window_item);
+
+        let recent_paths = NSDocumentController::sharedDocumentController(mtm)
+            .recentDocumentURLs()
+            .iter()
+            .filter_map(|url| url.path())
+            .map(|path| PathBuf::from(path.to_string()))
+            .collect::<Vec<_>>();
+        let recent_documents = recent_document_titles(&recent_paths);
+        if !recent_documents.is_empty() {
+            let recent_menu = NSMenu::new(mtm);
+            for (title, path) in recent_documents {
+                let url = NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy()));
+                let item = NSMenuItem::new(mtm);
+                item.setTitle(&NSString::from_str(&title));
+                item.setAction(Some(sel!(openRecent:)));
+                item.setTarget(Some(&handler));
+                item.setRepresentedObject(Some(&url));
+                recent_menu.addItem(&item);
+            }
+            let recent_item = NSMenuItem::new(mtm);
+            recent_item.setTitle(ns_string!("Open Recent"));
+            recent_item.setSubmenu(Some(&recent_menu));
+            menu.addItem(&recent_item);
+        }
+        menu
+    }
+}
+
+thread_local! {
+    // The class of winit's window delegates, which handles the dragged files
+    static WINDOW_DELEGATE_CLASS: OnceCell<&'static AnyClass> = const { OnceCell::new() };
+    static DRAG_PROXY: OnceCell<EventLoopProxy<UserEvent>> = const { OnceCell::new() };
+}
+
+fn send_drag_event(event: DragEvent) {
+    DRAG_PROXY.with(|proxy| {
+        if let Some(proxy) = proxy.get() {
+            let _ = proxy.send_event(WindowCommand::Drag(event).into());
+        }
+    });
+}
+
+/// Whether files are dragged, which are left to winit.
+unsafe fn drags_files(sender: &NSObject) -> bool {
+    let pasteboard: Retained<NSPasteboard> = msg_send_id![sender, draggingPasteboard];
+    let types = NSArray::from_slice(&[NSFilenamesPboardType]);
+    pasteboard.availableTypeFromArray(&types).is_some()
+}
+
+/// The dragged link, or else the dragged text.
+unsafe fn dragged_text(sender: &NSObject) -> Option<String> {
+    let pasteboard: Retained<NSPasteboard> = msg_send_id![sender, draggingPasteboard];
+    [NSPasteboardTypeURL, NSPasteboardTypeString]
+        .into_iter()
+        .find_map(|pasteboard_type| pasteboard.stringForType(pasteboard_type))
+        .and_then(|text| dropped_text(&text.to_string()))
+}
+
+/// Where the pointer is, in physical pixels from the top left corner of the window, like the
+/// positions of winit.
+unsafe fn drag_position(sender: &NSObject) -> Option<PixelPos<f32>> {
+    let window: Option<Retained<NSWindow>> = msg_send_id![sender, draggingDestinationWindow];
+    let window = window?;
+    let view = window.contentView()?;
+    let location: NSPoint = msg_send![sender, draggingLocation];
+    let mut point = view.convertPoint_fromView(location, None);
+    if !view.isFlipped() {
+        point.y = view.bounds().size.height - point.y;
+    }
+    let scale_factor = window.backingScaleFactor();
+    Some(PixelPos::new(
+        (point.x * scale_factor) as f32,
+        (point.y * scale_factor) as f32,
+    ))
+}
+
+/// Calls the drag and drop method of winit's window delegate, which handles the files.
+unsafe fn super_dragging(this: &AnyObject, selector: Sel, sender: &NSObject) -> bool {
+    let Some(class) = WINDOW_DELEGATE_CLASS.with(|class| class.get().copied()) else {
+        return false;
+    };
+    if selector == sel!(draggingEntered:) {
+        msg_send![super(this, class), draggingEntered: sender]
+    } else {
+        msg_send![super(this, class), performDragOperation: sender]
+    }
+}
+
+/// Accepts text and links dragged onto the window, on top of the files winit accepts, by
+/// extending the class of its window delegate.
+pub fn register_drop_target(window: &Window, proxy: EventLoopProxy<UserEvent>) {
+    // Returns the drag operation like winit's delegate, where true is the copy operation, which
+    // AppKit keeps using while the pointer moves
+    unsafe extern "C" fn dragging_entered(
+        this: &AnyObject,
+        selector: Sel,
+        sender: &NSObject,
+    ) -> bool {
+        if drags_files(sender) {
+            return super_dragging(this, selector, sender);
+        }
+        let accepted = dragged_text(sender).is_some();
+        if accepted {
+            send_drag_event(DragEvent::Hovered);
+        }
+        accepted
+    }
+
+    // winit expects file names in every drop, and fails without them
+    unsafe extern "C" fn perform_drag_operation(
+        this: &AnyObject,
+        selector: Sel,
+        sender: &NSObject,
+    ) -> bool {
+        if drags_files(sender) {
+            return super_dragging(this, selector, sender);
+        }
+        let (Some(text), Some(position)) = (dragged_text(sender), drag_position(sender)) else {
+            return false;
+        };
+        send_drag_event(DragEvent::Text { text, position });
+        true
+    }
+
+    DRAG_PROXY.with(|cell| {
+        let _ = cell.set(proxy);
+    });
+    let ns_window = get_ns_window(window);
+    unsafe {
+        let Some(delegate) = ns_window.delegate() else {
+            return;
+        };
+        let class: &'static AnyClass = msg_send![&delegate, class];
+        let class = match ClassBuilder::new("NeovideWindowDelegate", class) {
+            Some(mut subclass) => {
+                WINDOW_DELEGATE_CLASS.with(|cell| {
+                    let _ = cell.set(class);
+                });
+                subclass.add_method(
+                    sel!(draggingEntered:),
+                    dragging_entered as unsafe extern "C" fn(_, _, _) -> _,
+                );
+                subclass.add_method(
+                    sel!(performDragOperation:),
+                    perform_drag_operation as unsafe extern "C" fn(_, _, _) -> _,
+                );
+                subclass.register()
+            }
+            // Already registered for an earlier window
+            None => AnyClass::get("NeovideWindowDelegate").unwrap(),
+        };
+        // This is safe for the same reasons as the subclass of the application delegate: no new
+        // ivars, and the methods are compatible with the ones they override
+        let delegate = Retained::cast::<AnyObject>(delegate);
+        AnyObject::set_class(&delegate, class);
+
+        let types = NSArray::from_slice(&[
+            NSFilenamesPboardType,
+            NSPasteboardTypeURL,
+            NSPasteboardTypeString,
+        ]);
+        ns_window.registerForDraggedTypes(&types);
+    }
+}
+
+/// The "Reduce motion" option of the accessibility settings.
+pub fn prefers_reduced_motion() -> bool {
+    unsafe { NSWorkspace::sharedWorkspace().accessibilityDisplayShouldReduceMotion() }
+}
+
 pub fn register_file_handler() {
     unsafe extern "C" fn handle_open_files(
         _this: &mut AnyObject,
@@ -498,6 +893,35 @@ pub fn register_file_handler() {
         });
     }
 
+    // The links and file URLs opened with Neovide, when it's the handler of the nvim scheme
+    unsafe extern "C" fn handle_open_urls(
+        _this: &mut AnyObject,
+        _sel: objc2::runtime::Sel,
+        _sender: &objc2::runtime::AnyObject,
+        urls: &mut NSArray<NSURL>,
+    ) {
+        autoreleasepool(|pool| {
+            for url in urls.iter() {
+                let Some(url) = url.absoluteString() else {
+                    continue;
+                };
+                match parse_url(url.as_str(pool)) {
+                    Some(target) => ipc::open_file(target.file, target.line),
+                    None => error!("Can't open the URL {}", url.as_str(pool)),
+                }
+            }
+        });
+    }
+
+    unsafe extern "C" fn application_dock_menu(
+        _this: &mut AnyObject,
+        _sel: objc2::runtime::Sel,
+        _sender: &objc2::runtime::AnyObject,
+    ) -> *mut NSMenu {
+        let mtm = MainThreadMarker::new().expect("The dock menu is requested on the main thread");
+        Retained::autorelease_return(dock_menu(mtm))
+    }
+
     let mtm = MainThreadMarker::new().expect("File handler must be registered on main thread.");
 
     unsafe {
@@ -513,6 +937,14 @@ pub fn register_file_handler() {
             sel!(application:openFiles:),
             handle_open_files as unsafe extern "C" fn(_, _, _, _) -> _,
         );
+        my_class.add_method(
+            sel!(application:openURLs:),
+            handle_open_urls as unsafe extern "C" fn(_, _, _, _) -> _,
+        );
+        my_class.add_method(
+            sel!(applicationDockMenu:),
+            application_dock_menu as unsafe extern "C" fn(_, _, _) -> _,
+        );
         let class = my_class.register();
 
         // this should be safe as:
@@ -530,3 +962,28 @@ pub fn register_file_handler() {
         NSUserDefaults::standardUserDefaults().registerDefaults(dict.as_ref());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_documents_are_titled_by_name() {
+        let paths = [
+            "/Users/me/notes.md",
+            "/Users/me/.config/nvim/init.lua",
+            "/Users/me/project/init.lua",
+            "/",
+        ]
+        .map(PathBuf::from);
+        let titles = recent_document_titles(&paths)
+            .into_iter()
+            .map(|(title, _)| title)
+            .collect::<Vec<_>>();
+        // The root has no name to show
+        assert_eq!(
+            titles,
+            vec!["notes.md", "init.lua — nvim", "init.lua — project"]
+        );
+    }
+}
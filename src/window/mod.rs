@@ -1,15 +1,34 @@
+mod accessibility;
+mod bell;
+mod blur;
+#[cfg(target_os = "linux")]
+mod dbus_menu;
+mod drag_and_drop;
 mod error_window;
+mod force_click;
 mod keyboard_manager;
 mod mouse_manager;
+mod opaque_region;
+mod os_notify;
+mod pip;
+mod resize_throttle;
+#[cfg(target_os = "linux")]
+mod session_bus;
 mod settings;
+mod suspend;
+mod taskbar_progress;
 mod update_loop;
 mod window_wrapper;
+#[cfg(target_os = "linux")]
+mod x11;
+mod zoom;
 
 #[cfg(target_os = "macos")]
 pub mod macos;
 
 #[cfg(target_os = "linux")]
 use std::env;
+use std::{path::PathBuf, sync::OnceLock, thread};
 
 use winit::{
     dpi::{PhysicalSize, Size},
@@ -32,27 +51,42 @@ use winit::platform::macos::EventLoopBuilderExtMacOS;
 #[cfg(target_os = "macos")]
 use macos::register_file_handler;
 
+#[cfg(target_os = "windows")]
+use skia_safe::Color4f;
+
+use accessibility::AccessibilityEvent;
+pub use drag_and_drop::{dropped_text, DragEvent};
+pub use force_click::ForceClickKind;
 use image::{load_from_memory, GenericImageView, Pixel};
 use keyboard_manager::KeyboardManager;
 use mouse_manager::MouseManager;
+pub use mouse_manager::{MouseSettings, MouseSettingsChanged};
+pub use os_notify::{NotificationLevel, OsNotification};
+pub use taskbar_progress::TaskbarProgress;
 
 use crate::{
     cmd_line::{CmdLineSettings, GeometryArgs},
-    frame::Frame,
-    renderer::{build_window_config, DrawCommand, WindowConfig},
+    editor::MultigridAnomaly,
+    frame::{window_frame, Frame},
+    renderer::{
+        build_window_config,
+        file_changed::{FileChange, FileChangeAction},
+        DrawCommand, WindowConfig,
+    },
     settings::{
         clamped_grid_size, load_last_window_settings, save_window_size, HotReloadConfigs,
         PersistentWindowSettings, Settings, SettingsChanged,
     },
     units::GridSize,
 };
-pub use error_window::show_error_window;
+pub use error_window::{show_error_window, show_recovery_window};
 pub use settings::{WindowSettings, WindowSettingsChanged};
 pub use update_loop::ShouldRender;
 pub use update_loop::UpdateLoop;
 pub use window_wrapper::WinitWindowWrapper;
 
 static ICON: &[u8] = include_bytes!("../../assets/neovide.ico");
+static DECODED_ICON: OnceLock<Icon> = OnceLock::new();
 
 const DEFAULT_WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize {
     width: 500,
@@ -70,16 +104,61 @@ const MAX_PERSISTENT_WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize {
 #[derive(Clone, Debug, PartialEq)]
 pub enum WindowCommand {
     TitleChanged(String),
+    /// The file shown by the current buffer, which can be dragged from the title bar.
+    RepresentedFileChanged(Option<PathBuf>),
     SetMouseEnabled(bool),
+    /// Whether the current buffer is a help file, for the 'h' flag of 'mouse'.
+    HelpBufferChanged(bool),
     ListAvailableFonts,
     FocusWindow,
+    /// Shows a notification of the OS, unless the window is focused.
+    OsNotify(OsNotification),
+    /// Shows the progress of a job on the icon in the taskbar or the dock.
+    ShowProgressBar(TaskbarProgress),
+    /// Neovim rang the bell, or flashed the screen with `visual` set.
+    Bell {
+        visual: bool,
+    },
     Minimize,
+    ToggleFullscreen,
+    TogglePictureInPicture,
+    RunCommand(String),
+    ShowTour,
+    /// Shows the documentation about a topic, see `:NeovideHelp`.
+    ShowHelp(String),
+    /// Shows the clipboard history to paste from, filtered by a query, see
+    /// `:NeovideClipboardHistory`.
+    ShowClipboardHistory {
+        entries: Vec<String>,
+        query: String,
+    },
+    /// Echoes the version, the size of the window and the GPU memory used.
+    ShowInfo,
+    FileChanged(FileChange),
+    ResolveFileChange(FileChangeAction),
+    /// Warns about the floats Neovim asked for that can't be placed.
+    MultigridWarning(Vec<MultigridAnomaly>),
+    DismissMultigridWarning,
+    /// Restarts Neovim after it quit with an error, or closes the window.
+    AnswerExitPrompt(bool),
+    /// Something was dragged onto the window, see `drag_and_drop`.
+    Drag(DragEvent),
+    /// Plugins drew on the overlay layers, which are waiting in `overlay::take_pending_layers`.
+    DrawOverlays,
+    /// Pastes the primary selection at the cursor, or the clipboard where there's none.
+    PastePrimarySelection,
     #[allow(dead_code)] // Theme change is only used on macOS right now
     ThemeChanged(Option<Theme>),
     #[cfg(windows)]
+    DefaultBackgroundChanged(Option<Color4f>),
+    #[cfg(windows)]
     RegisterRightClick,
     #[cfg(windows)]
     UnregisterRightClick,
+    #[cfg(windows)]
+    RegisterUrlHandler,
+    #[cfg(windows)]
+    UnregisterUrlHandler,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -90,7 +169,11 @@ pub enum UserEvent {
     ConfigsChanged(Box<HotReloadConfigs>),
     #[allow(dead_code)]
     RedrawRequested,
+    NeovimRestarted,
+    /// Neovim quit with the exit code given, which isn't zero.
+    NeovimFailed(u8),
     NeovimExited,
+    Accessibility(AccessibilityEvent),
 }
 
 impl From<Vec<DrawCommand>> for UserEvent {
@@ -169,19 +252,20 @@ pub fn create_window(
         window_attributes
     };
 
-    let frame_decoration = cmd_line_settings.frame;
+    let frame_decoration = window_frame(settings);
 
     #[cfg(target_os = "macos")]
     let title_hidden = cmd_line_settings.title_hidden;
 
-    // There is only two options for windows & linux, no need to match more options.
+    // Only the full frame has the decorations of the OS on windows & linux, the custom one is
+    // drawn by the renderer.
     #[cfg(not(target_os = "macos"))]
     let mut window_attributes = window_attributes.with_decorations(frame_decoration == Frame::Full);
 
     #[cfg(target_os = "macos")]
     let mut window_attributes = match frame_decoration {
         Frame::Full => window_attributes,
-        Frame::None => window_attributes.with_decorations(false),
+        Frame::None | Frame::Custom => window_attributes.with_decorations(false),
         Frame::Buttonless => window_attributes
             .with_title_hidden(title_hidden)
             .with_titlebar_buttons_hidden(true)
@@ -278,7 +362,20 @@ pub fn determine_window_size(
     }
 }
 
+/// Decodes the icon on another thread while Neovim starts, it's only needed once the window is
+/// created.
+pub fn preload_icon() {
+    thread::spawn(|| {
+        DECODED_ICON.get_or_init(decode_icon);
+    });
+}
+
+/// Waits for the icon when it's still being decoded.
 pub fn load_icon() -> Icon {
+    DECODED_ICON.get_or_init(decode_icon).clone()
+}
+
+fn decode_icon() -> Icon {
     let icon = load_from_memory(ICON).expect("Failed to parse icon data");
     let (width, height) = icon.dimensions();
     let mut rgba = Vec::with_capacity((width * height) as usize * 4);
@@ -1,6 +1,5 @@
 use std::{
-    cmp::Ordering,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -8,29 +7,217 @@ use std::{
 use winit::{
     event::WindowEvent,
     event::{DeviceId, ElementState, MouseButton, MouseScrollDelta, Touch, TouchPhase},
-    window::Window,
+    keyboard::ModifiersState,
+    window::{CursorIcon, ResizeDirection, Window},
 };
 
 use glamour::Contains;
 
 use crate::{
-    bridge::{send_ui, SerialCommand},
-    renderer::{Renderer, WindowDrawDetails},
-    settings::Settings,
+    bridge::{send_ui, EditorMode, ParallelCommand, SerialCommand},
+    cmd_line::CmdLineSettings,
+    renderer::{
+        exit_prompt::ExitPromptRenderer,
+        file_changed::FileChangedRenderer,
+        mouse_selection::GridSelection,
+        multigrid_warning::MultigridWarningRenderer,
+        tabline::TablineRenderer,
+        text_selection::{SelectableText, TextPoint, TextSelection},
+        titlebar::{resize_direction, TitlebarHit, TitlebarRenderer},
+        wildmenu::{selection_keys, WildmenuRenderer},
+        Renderer, WindowDrawDetails,
+    },
+    settings::*,
     units::{GridPos, GridScale, GridVec, PixelPos, PixelRect, PixelSize, PixelVec},
+    window::force_click::{
+        force_click_target, ForceClickKind, PressureThresholds, PressureTracker,
+    },
     window::keyboard_manager::KeyboardManager,
-    window::WindowSettings,
+    window::{WindowCommand, WindowSettings},
 };
 
+// A lifted finger only starts a kinetic scroll when it was still moving this recently
+const KINETIC_SCROLL_RELEASE_TIMEOUT: Duration = Duration::from_millis(50);
+
+// Two fingers zoom instead of scrolling once their distance changed by this ratio
+const PINCH_THRESHOLD: f32 = 0.1;
+const MIN_PINCH_SCALE_FACTOR: f32 = 0.25;
+const MAX_PINCH_SCALE_FACTOR: f32 = 4.0;
+
+// Two presses on the custom title bar this close together maximize the window
+const TITLEBAR_DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The scale factor after pinching from `start_distance` to `distance`, in steps of 5%.
+fn pinch_scale_factor(start_scale_factor: f32, start_distance: f32, distance: f32) -> f32 {
+    let scale_factor = start_scale_factor * distance / start_distance.max(1.0);
+    ((scale_factor * 20.0).round() / 20.0).clamp(MIN_PINCH_SCALE_FACTOR, MAX_PINCH_SCALE_FACTOR)
+}
+
+/// Adds up the fractional deltas of high resolution wheels and touchpads, and hands out whole
+/// lines once they add up to one.
+#[derive(Debug, Default)]
+struct ScrollAccumulator {
+    remainder: GridVec<f32>,
+}
+
+impl ScrollAccumulator {
+    /// Returns the whole lines and columns to scroll after adding `amount`.
+    fn accumulate(&mut self, amount: GridVec<f32>) -> GridVec<i32> {
+        fn accumulate_axis(remainder: &mut f32, amount: f32) -> i32 {
+            if amount == 0.0 {
+                return 0;
+            }
+            // What's left from the other direction shouldn't delay a reversal
+            if *remainder != 0.0 && remainder.signum() != amount.signum() {
+                *remainder = 0.0;
+            }
+            *remainder += amount;
+            // Allow for rounding errors, so that ten deltas of 0.1 scroll a line
+            let lines = (*remainder + remainder.signum() * 0.001).trunc();
+            *remainder -= lines;
+            lines as i32
+        }
+        GridVec::new(
+            accumulate_axis(&mut self.remainder.x, amount.x),
+            accumulate_axis(&mut self.remainder.y, amount.y),
+        )
+    }
+
+    fn reset(&mut self) {
+        self.remainder = GridVec::default();
+    }
+}
+
+/// Averages the pixel deltas of the trackpad over the last few events, which evens out the jumps of
+/// a light touch. What the average holds back is handed out when the gesture ends, so that the
+/// distance scrolled stays the same.
+#[derive(Debug, Default)]
+struct ScrollSmoothing {
+    deltas: VecDeque<PixelVec<f32>>,
+    owed: PixelVec<f32>,
+}
+
+impl ScrollSmoothing {
+    /// The delta to scroll by for `delta`, averaged over `window` events.
+    fn smooth(&mut self, delta: PixelVec<f32>, window: usize) -> PixelVec<f32> {
+        if window <= 1 {
+            return delta + self.finish();
+        }
+        self.deltas.push_back(delta);
+        while self.deltas.len() > window {
+            self.deltas.pop_front();
+        }
+        let sum = self
+            .deltas
+            .iter()
+            .fold(PixelVec::default(), |sum, delta| sum + *delta);
+        // Dividing by the whole window eases the start of a gesture in
+        let average = sum / window as f32;
+        self.owed = self.owed + delta - average;
+        average
+    }
+
+    /// Returns what's left to scroll once the gesture ended.
+    fn finish(&mut self) -> PixelVec<f32> {
+        self.deltas.clear();
+        std::mem::take(&mut self.owed)
+    }
+}
+
+/// The thresholds of the force clicks, and the events the scrolling of the trackpad is averaged
+/// over. Only macOS reports the pressure and has settings for them.
+#[cfg(target_os = "macos")]
+fn touchpad_settings(settings: &WindowSettings) -> (PressureThresholds, usize) {
+    let thresholds = PressureThresholds {
+        enabled: settings.input_macos_force_click,
+        threshold: settings.input_macos_force_click_threshold.clamp(0.0, 1.0),
+        dead_zone: settings.input_macos_force_click_dead_zone.max(0.0),
+    };
+    (thresholds, settings.input_macos_scroll_smoothing as usize)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn touchpad_settings(_settings: &WindowSettings) -> (PressureThresholds, usize) {
+    (PressureThresholds::default(), 1)
+}
+
 fn clamp_position(
     position: PixelPos<f32>,
     region: PixelRect<f32>,
     grid_scale: GridScale,
 ) -> PixelPos<f32> {
     let min = region.min;
-    let max = region.max - GridPos::new(1.0, 1.0) * grid_scale;
+    let max: PixelPos<f32> = (region.max - GridPos::new(1.0, 1.0) * grid_scale).into();
 
-    position.clamp(min, max.into())
+    // Regions smaller than a cell would otherwise end before they start
+    position.clamp(min, max.max(min))
+}
+
+/// The cell at `position`, relative to `region` and clamped to it. `None` when the cells have no
+/// size, like before the fonts are loaded.
+fn relative_grid_position(
+    position: PixelPos<f32>,
+    region: PixelRect<f32>,
+    grid_scale: GridScale,
+) -> Option<GridPos<u32>> {
+    if !(grid_scale.width() > 0.0 && grid_scale.height() > 0.0) {
+        return None;
+    }
+    let clamped_position = clamp_position(position, region, grid_scale);
+    let relative_position = (clamped_position - region.min).to_point();
+
+    (relative_position / grid_scale)
+        .floor()
+        .max((0.0, 0.0).into())
+        .try_cast()
+}
+
+/// The window under the pointer, found in the regions the windows were last drawn at.
+#[derive(Debug)]
+enum HitTest<'a> {
+    /// A window, and the cell under the pointer relative to it.
+    Window {
+        details: &'a WindowDrawDetails,
+        position: GridPos<u32>,
+    },
+    /// The window a drag started on isn't drawn anymore, because it was closed mid-drag.
+    Closed,
+    /// No window, like outside of the grid, before the first frame or while the cells have no
+    /// size.
+    Empty,
+}
+
+/// Finds the window under `position`, or the window being dragged on wherever the pointer is.
+fn hit_test(
+    window_regions: &[WindowDrawDetails],
+    position: PixelPos<f32>,
+    grid_scale: GridScale,
+    drag_window_id: Option<u64>,
+) -> HitTest<'_> {
+    let details = if let Some(drag_window_id) = drag_window_id {
+        let Some(details) = window_regions
+            .iter()
+            .find(|details| details.id == drag_window_id)
+        else {
+            return HitTest::Closed;
+        };
+        details
+    } else {
+        // the rendered window regions are sorted by draw order, so the earlier windows in the
+        // list are drawn under the later ones
+        let Some(details) = window_regions
+            .iter()
+            .filter(|details| details.region.contains(&position))
+            .last()
+        else {
+            return HitTest::Empty;
+        };
+        details
+    };
+    match relative_grid_position(position, details.region, grid_scale) {
+        Some(position) => HitTest::Window { details, position },
+        None => HitTest::Empty,
+    }
 }
 
 fn mouse_button_to_button_text(mouse_button: MouseButton) -> Option<String> {
@@ -49,11 +236,246 @@ struct DragDetails {
     button: MouseButton,
 }
 
+// Neovim only recognizes up to quadruple clicks
+const MAX_CLICK_COUNT: u8 = 4;
+
+#[derive(Clone, Debug)]
+struct ClickDetails {
+    button: MouseButton,
+    window_id: u64,
+    position: GridPos<u32>,
+    time: Instant,
+    count: u8,
+}
+
+impl ClickDetails {
+    /// Counts a new press as a multi-click, when it's done with the same button on the same cell
+    /// within `interval` after the previous one.
+    fn next(
+        previous: Option<&ClickDetails>,
+        button: MouseButton,
+        window_id: u64,
+        position: GridPos<u32>,
+        time: Instant,
+        interval: Duration,
+    ) -> ClickDetails {
+        let count = match previous {
+            Some(previous)
+                if previous.button == button
+                    && previous.window_id == window_id
+                    && previous.position == position
+                    && time.saturating_duration_since(previous.time) <= interval =>
+            {
+                previous.count % MAX_CLICK_COUNT + 1
+            }
+            _ => 1,
+        };
+        ClickDetails {
+            button,
+            window_id,
+            position,
+            time,
+            count,
+        }
+    }
+}
+
+/// Size of the hot corner areas, in logical pixels.
+const HOT_CORNER_SIZE: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HotCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl HotCorner {
+    fn at(position: PixelPos<f32>, window_size: PixelSize<f32>, corner_size: f32) -> Option<Self> {
+        let left = position.x < corner_size;
+        let right = position.x >= window_size.width - corner_size;
+        let top = position.y < corner_size;
+        let bottom = position.y >= window_size.height - corner_size;
+        match (left, right, top, bottom) {
+            (true, _, true, _) => Some(HotCorner::TopLeft),
+            (_, true, true, _) => Some(HotCorner::TopRight),
+            (true, _, _, true) => Some(HotCorner::BottomLeft),
+            (_, true, _, true) => Some(HotCorner::BottomRight),
+            _ => None,
+        }
+    }
+
+    fn action(self, settings: &WindowSettings) -> &str {
+        match self {
+            HotCorner::TopLeft => &settings.hot_corner_top_left,
+            HotCorner::TopRight => &settings.hot_corner_top_right,
+            HotCorner::BottomLeft => &settings.hot_corner_bottom_left,
+            HotCorner::BottomRight => &settings.hot_corner_bottom_right,
+        }
+    }
+}
+
+/// Translates a hot corner action into the window command that performs it. Anything that isn't
+/// a built-in action is run as an Ex command.
+fn hot_corner_command(action: &str) -> Option<WindowCommand> {
+    match action.trim() {
+        "" => None,
+        "fullscreen" => Some(WindowCommand::ToggleFullscreen),
+        "minimize" => Some(WindowCommand::Minimize),
+        "fonts" => Some(WindowCommand::ListAvailableFonts),
+        command => Some(WindowCommand::RunCommand(
+            command.strip_prefix(':').unwrap_or(command).to_string(),
+        )),
+    }
+}
+
+#[derive(Clone, SettingGroup)]
+#[setting_prefix = "mouse"]
+pub struct MouseSettings {
+    middle_click: String,
+    back_button: String,
+    forward_button: String,
+    ctrl_click: String,
+    alt_click: String,
+    super_click: String,
+    selection: bool,
+    force_click_word: String,
+    force_click_url: String,
+    force_click_file: String,
+    #[option = "mouse"]
+    mouse_modes: String,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            middle_click: "".to_string(),
+            back_button: "<C-o>".to_string(),
+            forward_button: "<C-i>".to_string(),
+            ctrl_click: "".to_string(),
+            alt_click: "".to_string(),
+            super_click: "".to_string(),
+            selection: false,
+            force_click_word: "hover".to_string(),
+            force_click_url: "open".to_string(),
+            force_click_file: "open".to_string(),
+            mouse_modes: "nvi".to_string(),
+        }
+    }
+}
+
+/// The `(row, column)` cell of the screen grid at a position of the window, where dropped text is
+/// inserted.
+pub fn drop_position(renderer: &Renderer, position: PixelPos<f32>) -> Option<(u64, u64)> {
+    let HitTest::Window { details, position } = hit_test(
+        &renderer.window_regions,
+        position,
+        renderer.grid_renderer.grid_scale,
+        None,
+    ) else {
+        return None;
+    };
+    let origin = renderer.window_grid_position(details.id)?;
+    Some((
+        origin.y.round().max(0.0) as u64 + position.y as u64,
+        origin.x.round().max(0.0) as u64 + position.x as u64,
+    ))
+}
+
+/// Whether the 'mouse' option `modes` enables the mouse in `mode`, the way `mouse_has()` of
+/// Neovim does. `help_buffer` tells whether the current buffer is a help file, for the 'h' flag.
+fn mouse_enabled_in(modes: &str, mode: &EditorMode, help_buffer: bool) -> bool {
+    let flag = match mode {
+        EditorMode::Normal => 'n',
+        EditorMode::Visual => 'v',
+        EditorMode::Insert | EditorMode::Replace => 'i',
+        EditorMode::CmdLine => 'c',
+        EditorMode::Unknown(name) => match name.as_str() {
+            "operator" => 'n',
+            "visual_select" => 'v',
+            "showmatch" => 'i',
+            "cmdline_insert" | "cmdline_replace" => 'c',
+            // The hit-enter and the more prompts
+            "more" | "more_lastline" => 'r',
+            // Neovim decides in terminal mode, and in modes it doesn't report yet
+            _ => return true,
+        },
+    };
+    modes.chars().any(|enabled| match enabled {
+        'a' => "nvic".contains(flag),
+        'h' => help_buffer && flag != 'r',
+        enabled => enabled == flag,
+    })
+}
+
+impl MouseSettings {
+    /// The binding of a button pressed with the given modifiers, empty when it goes to Neovim.
+    fn binding(&self, button: MouseButton, modifiers: ModifiersState) -> &str {
+        match button {
+            MouseButton::Left if modifiers.control_key() => &self.ctrl_click,
+            MouseButton::Left if modifiers.alt_key() => &self.alt_click,
+            MouseButton::Left if modifiers.super_key() => &self.super_click,
+            MouseButton::Middle => &self.middle_click,
+            MouseButton::Back => &self.back_button,
+            MouseButton::Forward => &self.forward_button,
+            _ => "",
+        }
+    }
+
+    /// What a force click on the text of `kind` does.
+    pub fn force_click_action(&self, kind: ForceClickKind) -> &str {
+        match kind {
+            ForceClickKind::Word => &self.force_click_word,
+            ForceClickKind::Url => &self.force_click_url,
+            ForceClickKind::File => &self.force_click_file,
+        }
+    }
+}
+
+/// What a bound mouse button does instead of clicking in Neovim.
+#[derive(Debug, PartialEq)]
+enum MouseBinding {
+    Keys(String),
+    Window(WindowCommand),
+}
+
+/// Translates a mouse binding: the Neovide actions of the hot corners, `paste_selection`, an Ex
+/// command starting with `:`, or otherwise keys sent to Neovim as if typed.
+fn parse_mouse_binding(binding: &str) -> Option<MouseBinding> {
+    match binding.trim() {
+        "" => None,
+        "paste_selection" => Some(MouseBinding::Window(WindowCommand::PastePrimarySelection)),
+        action @ ("fullscreen" | "minimize" | "fonts") => {
+            hot_corner_command(action).map(MouseBinding::Window)
+        }
+        command if command.starts_with(':') => {
+            hot_corner_command(command).map(MouseBinding::Window)
+        }
+        keys => Some(MouseBinding::Keys(keys.to_string())),
+    }
+}
+
+#[derive(Debug)]
+struct HotCornerDwell {
+    corner: HotCorner,
+    entered: Instant,
+    triggered: bool,
+}
+
 struct EditorState<'a> {
     grid_scale: &'a GridScale,
     window_regions: &'a Vec<WindowDrawDetails>,
+    tabline: &'a TablineRenderer,
+    titlebar: &'a TitlebarRenderer,
+    wildmenu: &'a WildmenuRenderer,
+    file_changed: &'a FileChangedRenderer,
+    exit_prompt: &'a ExitPromptRenderer,
+    multigrid_warning: &'a MultigridWarningRenderer,
+    selectable_text: &'a SelectableText,
     window: &'a Window,
     keyboard_manager: &'a KeyboardManager,
+    mode: &'a EditorMode,
 }
 
 #[derive(Debug)]
@@ -64,20 +486,78 @@ struct TouchTrace {
     left_deadzone_once: bool,
 }
 
+/// Two fingers on the screen, which scroll when moved together and zoom when pinched.
+#[derive(Debug)]
+struct TouchGesture {
+    start_distance: f32,
+    start_scale_factor: f32,
+    last_center: PixelPos<f32>,
+    last_scale_factor: f32,
+    zooming: bool,
+}
+
 pub struct MouseManager {
     drag_details: Option<DragDetails>,
+    last_click: Option<ClickDetails>,
     grid_position: GridPos<u32>,
 
     has_moved: bool,
     window_position: PixelPos<f32>,
 
-    scroll_position: GridPos<f32>,
+    // The parts of a line scrolled so far, kept apart for wheels and precise devices, and the grid
+    // they were scrolled on
+    line_scroll: ScrollAccumulator,
+    pixel_scroll: ScrollAccumulator,
+    scroll_grid_id: u64,
 
     // the tuple allows to keep track of different fingers per device
     touch_position: HashMap<(DeviceId, u64), TouchTrace>,
+    touch_gesture: Option<TouchGesture>,
+
+    // Velocity of the last touch scroll in pixels per second, and when it was measured. Used to
+    // keep scrolling after the finger has been lifted.
+    touch_scroll_velocity: PixelVec<f32>,
+    last_touch_scroll: Option<Instant>,
+    kinetic_scroll_velocity: Option<PixelVec<f32>>,
+
+    // Lines scrolled on the given window by precise devices, consumed by the renderer for the
+    // overscroll bounce
+    pending_overscroll: Option<(u64, f32)>,
+
+    // The hot corner the pointer is resting in, and the command to run once it has dwelled there
+    // long enough
+    hot_corner: Option<HotCornerDwell>,
+    pending_window_command: Option<WindowCommand>,
+
+    // A button whose press ran its binding, so that its release isn't sent to Neovim either
+    bound_button: Option<MouseButton>,
+
+    // The cells selected with Alt and the left button, whether the button is still held, and the
+    // selection to copy once it's released
+    selection: Option<GridSelection>,
+    selecting: bool,
+    copied_selection: Option<GridSelection>,
+
+    // The text of the overlays selected with the left button, with the generation of the lines
+    // it was made on, and whether the button is still held
+    text_selection: Option<(u64, TextSelection<TextPoint>)>,
+    selecting_text: bool,
+
+    // The pressure on the trackpad, to tell the force clicks, and the averaging of its scrolling
+    touchpad_pressure: PressureTracker,
+    scroll_smoothing: ScrollSmoothing,
+
+    // With the custom frame, the edge the pointer would resize, shown with its cursor, and the
+    // last press on the title bar, to tell double clicks
+    resize_edge: Option<ResizeDirection>,
+    last_titlebar_press: Option<Instant>,
+    /// The last press past the tabs of the tab bar, to open a new tab on double clicks.
+    last_tabline_press: Option<Instant>,
 
     mouse_hidden: bool,
     pub enabled: bool,
+    /// Whether the current buffer is a help file, which the 'h' flag of 'mouse' enables it in.
+    pub help_buffer: bool,
 
     settings: Arc<Settings>,
 }
@@ -86,50 +566,72 @@ impl MouseManager {
     pub fn new(settings: Arc<Settings>) -> MouseManager {
         MouseManager {
             drag_details: None,
+            last_click: None,
             has_moved: false,
             window_position: PixelPos::default(),
             grid_position: GridPos::default(),
-            scroll_position: GridPos::default(),
+            line_scroll: ScrollAccumulator::default(),
+            pixel_scroll: ScrollAccumulator::default(),
+            scroll_grid_id: 0,
             touch_position: HashMap::new(),
+            touch_gesture: None,
+            touch_scroll_velocity: PixelVec::default(),
+            last_touch_scroll: None,
+            kinetic_scroll_velocity: None,
+            pending_overscroll: None,
+            hot_corner: None,
+            pending_window_command: None,
+            bound_button: None,
+            selection: None,
+            selecting: false,
+            copied_selection: None,
+            text_selection: None,
+            selecting_text: false,
+            touchpad_pressure: PressureTracker::default(),
+            scroll_smoothing: ScrollSmoothing::default(),
+            resize_edge: None,
+            last_titlebar_press: None,
+            last_tabline_press: None,
             mouse_hidden: false,
             enabled: true,
+            help_buffer: false,
             settings,
         }
     }
 
-    fn get_window_details_under_mouse<'b>(
-        &self,
-        editor_state: &'b EditorState<'b>,
-    ) -> Option<&'b WindowDrawDetails> {
-        let position = self.window_position;
-
-        // the rendered window regions are sorted by draw order, so the earlier windows in the
-        // list are drawn under the later ones
-        editor_state
-            .window_regions
-            .iter()
-            .filter(|details| details.region.contains(&position))
-            .last()
+    /// Whether the mouse goes to Neovim, following both the mouse_on and mouse_off events and
+    /// the modes of the 'mouse' option.
+    fn is_enabled(&self, editor_state: &EditorState) -> bool {
+        self.enabled
+            && mouse_enabled_in(
+                &self.settings.get::<MouseSettings>().mouse_modes,
+                editor_state.mode,
+                self.help_buffer,
+            )
     }
 
-    fn get_relative_position(
-        &self,
-        window_details: &WindowDrawDetails,
-        editor_state: &EditorState,
-    ) -> GridPos<u32> {
-        let global_bounds = window_details.region;
-        let clamped_position = clamp_position(
+    fn hit_test<'b>(&self, editor_state: &'b EditorState<'b>, dragging: bool) -> HitTest<'b> {
+        let drag_window_id = self
+            .drag_details
+            .as_ref()
+            .filter(|_| dragging)
+            .map(|drag_details| drag_details.draw_details.id);
+        hit_test(
+            editor_state.window_regions,
             self.window_position,
-            global_bounds,
             *editor_state.grid_scale,
-        );
-        let relative_position = (clamped_position - window_details.region.min).to_point();
+            drag_window_id,
+        )
+    }
 
-        (relative_position / *editor_state.grid_scale)
-            .floor()
-            .max((0.0, 0.0).into())
-            .try_cast()
-            .unwrap()
+    fn get_window_details_under_mouse<'b>(
+        &self,
+        editor_state: &'b EditorState<'b>,
+    ) -> Option<&'b WindowDrawDetails> {
+        match self.hit_test(editor_state, false) {
+            HitTest::Window { details, .. } => Some(details),
+            HitTest::Closed | HitTest::Empty => None,
+        }
     }
 
     fn handle_pointer_motion(&mut self, position: PixelPos<f32>, editor_state: &EditorState) {
@@ -141,51 +643,65 @@ impl MouseManager {
         }
 
         self.window_position = position;
+        self.update_hot_corner(position, window_size, editor_state);
+        self.update_resize_cursor(window_size, editor_state);
+
+        if self.selecting {
+            self.update_selection(editor_state);
+            return;
+        }
+        if self.selecting_text {
+            self.update_text_selection(editor_state);
+            return;
+        }
 
         // If dragging, the relevant window (the one which we send all commands to) is the one
         // which the mouse drag started on. Otherwise its the top rendered window
-        let window_details = if let Some(drag_details) = &self.drag_details {
-            editor_state
-                .window_regions
-                .iter()
-                .find(|details| details.id == drag_details.draw_details.id)
-        } else {
-            self.get_window_details_under_mouse(editor_state)
+        let (window_details, relative_position) = match self.hit_test(editor_state, true) {
+            HitTest::Window { details, position } => (details, position),
+            HitTest::Closed => {
+                // There's nothing left to drag on
+                self.drag_details = None;
+                self.has_moved = false;
+                return;
+            }
+            HitTest::Empty => return,
         };
 
-        if let Some(window_details) = window_details {
-            let relative_position = self.get_relative_position(window_details, editor_state);
-            let previous_position = self.grid_position;
-            self.grid_position = relative_position;
+        let previous_position = self.grid_position;
+        self.grid_position = relative_position;
 
-            let has_moved = self.grid_position != previous_position;
+        let has_moved = self.grid_position != previous_position;
 
-            if has_moved {
-                if let Some(drag_details) = &self.drag_details {
+        if has_moved {
+            if let Some(drag_details) = &self.drag_details {
+                if let Some(button) = mouse_button_to_button_text(drag_details.button) {
                     send_ui(SerialCommand::Drag {
-                        button: mouse_button_to_button_text(drag_details.button).unwrap(),
+                        button,
                         grid_id: window_details.event_grid_id(&self.settings),
                         position: self.grid_position.to_tuple(),
                         modifier_string: editor_state
                             .keyboard_manager
                             .format_modifier_string("", true),
                     });
-                } else if self.settings.get::<WindowSettings>().mouse_move_event {
-                    // Send a mouse move command
-                    send_ui(SerialCommand::MouseButton {
-                        button: "move".into(),
-                        action: "".into(), // this is ignored by nvim
-                        grid_id: window_details.event_grid_id(&self.settings),
-                        position: relative_position.to_tuple(),
-                        modifier_string: editor_state
-                            .keyboard_manager
-                            .format_modifier_string("", true),
-                    })
                 }
+            } else if self.settings.get::<WindowSettings>().mouse_move_event
+                && self.is_enabled(editor_state)
+            {
+                // Send a mouse move command
+                send_ui(SerialCommand::MouseButton {
+                    button: "move".into(),
+                    action: "".into(), // this is ignored by nvim
+                    grid_id: window_details.event_grid_id(&self.settings),
+                    position: relative_position.to_tuple(),
+                    modifier_string: editor_state
+                        .keyboard_manager
+                        .format_modifier_string("", true),
+                })
             }
-
-            self.has_moved = self.drag_details.is_some() && (self.has_moved || has_moved);
         }
+
+        self.has_moved = self.drag_details.is_some() && (self.has_moved || has_moved);
     }
 
     fn handle_pointer_transition(
@@ -194,12 +710,74 @@ impl MouseManager {
         down: bool,
         editor_state: &EditorState,
     ) {
+        if self.drag_details.is_none()
+            && self.handle_custom_frame_click(mouse_button, down, editor_state)
+        {
+            return;
+        }
+        if self.drag_details.is_none() && editor_state.file_changed.contains(self.window_position) {
+            if down && mouse_button == MouseButton::Left {
+                self.pending_window_command = editor_state
+                    .file_changed
+                    .hit_test(self.window_position)
+                    .map(WindowCommand::ResolveFileChange);
+            }
+            return;
+        }
+        if self.drag_details.is_none() && editor_state.exit_prompt.contains(self.window_position) {
+            if down && mouse_button == MouseButton::Left {
+                self.pending_window_command = editor_state
+                    .exit_prompt
+                    .hit_test(self.window_position)
+                    .map(WindowCommand::AnswerExitPrompt);
+            }
+            return;
+        }
+        if self.drag_details.is_none()
+            && editor_state
+                .multigrid_warning
+                .contains(self.window_position)
+        {
+            if down && mouse_button == MouseButton::Left {
+                self.pending_window_command = Some(WindowCommand::DismissMultigridWarning);
+            }
+            return;
+        }
+        if self.drag_details.is_none() && editor_state.wildmenu.contains(self.window_position) {
+            if down && mouse_button == MouseButton::Left {
+                self.handle_wildmenu_click(editor_state);
+            }
+            return;
+        }
+        if self.drag_details.is_none() && editor_state.tabline.contains(self.window_position) {
+            if down {
+                self.handle_tabline_click(mouse_button, editor_state);
+            }
+            return;
+        }
+
+        if self.handle_text_selection(mouse_button, down, editor_state) {
+            return;
+        }
+
+        if self.handle_selection(mouse_button, down, editor_state) {
+            return;
+        }
+
+        if self.handle_mouse_binding(mouse_button, down, editor_state) {
+            return;
+        }
+
         // For some reason pointer down is handled differently from pointer up and drag.
         // Floating windows: relative coordinates are great.
         // Non floating windows: rather than global coordinates, relative are needed
-        if self.enabled {
+        if self.is_enabled(editor_state) {
             if let Some(button_text) = mouse_button_to_button_text(mouse_button) {
-                if let &Some(details) = &self.get_window_details_under_mouse(editor_state) {
+                if let HitTest::Window {
+                    details,
+                    position: clicked_position,
+                } = self.hit_test(editor_state, false)
+                {
                     let action = if down {
                         "press".to_owned()
                     } else {
@@ -209,17 +787,34 @@ impl MouseManager {
                     let position = if !down && self.has_moved {
                         self.grid_position
                     } else {
-                        self.get_relative_position(details, editor_state)
+                        clicked_position
                     };
 
+                    let mut modifier_string = editor_state
+                        .keyboard_manager
+                        .format_modifier_string("", true);
+                    let click_interval = self.settings.get::<WindowSettings>().mouse_click_interval;
+                    if down && click_interval > 0.0 {
+                        let click = ClickDetails::next(
+                            self.last_click.as_ref(),
+                            mouse_button,
+                            details.id,
+                            position,
+                            Instant::now(),
+                            Duration::from_secs_f32(click_interval),
+                        );
+                        if click.count > 1 {
+                            modifier_string = format!("{}-{modifier_string}", click.count);
+                        }
+                        self.last_click = Some(click);
+                    }
+
                     send_ui(SerialCommand::MouseButton {
                         button: button_text.clone(),
                         action,
                         grid_id: details.event_grid_id(&self.settings),
                         position: position.to_tuple(),
-                        modifier_string: editor_state
-                            .keyboard_manager
-                            .format_modifier_string("", true),
+                        modifier_string,
                     });
 
                     if down {
@@ -241,64 +836,491 @@ impl MouseManager {
         }
     }
 
-    fn handle_line_scroll(&mut self, amount: GridVec<f32>, editor_state: &EditorState) {
-        if !self.enabled {
+    /// Starts selecting cells on Alt and the left button when `neovide_mouse_selection` is set,
+    /// whether the mouse is enabled in Neovim or not, and copies them once it's released. Any other
+    /// click clears the selection. Returns `true` when the button was handled here.
+    fn handle_selection(
+        &mut self,
+        mouse_button: MouseButton,
+        down: bool,
+        editor_state: &EditorState,
+    ) -> bool {
+        if mouse_button != MouseButton::Left {
+            return false;
+        }
+        if !down {
+            if !self.selecting {
+                return false;
+            }
+            self.selecting = false;
+            self.copied_selection = self.selection.clone();
+            return true;
+        }
+
+        self.selection = None;
+        if !self.settings.get::<MouseSettings>().selection
+            || !editor_state.keyboard_manager.modifiers().alt_key()
+            || self.drag_details.is_some()
+        {
+            return false;
+        }
+        let HitTest::Window { details, position } = self.hit_test(editor_state, false) else {
+            return false;
+        };
+        self.selection = Some(GridSelection::new(details.id, position));
+        self.selecting = true;
+        true
+    }
+
+    /// Selects the text of the overlays, like the messages, when the left button is pressed over
+    /// it. A press anywhere else drops the selection.
+    fn handle_text_selection(
+        &mut self,
+        mouse_button: MouseButton,
+        down: bool,
+        editor_state: &EditorState,
+    ) -> bool {
+        if mouse_button != MouseButton::Left {
+            return false;
+        }
+        if !down {
+            let selecting = self.selecting_text;
+            self.selecting_text = false;
+            return selecting;
+        }
+
+        self.text_selection = None;
+        if self.drag_details.is_some() {
+            return false;
+        }
+        let Some(point) = editor_state.selectable_text.hit(self.window_position) else {
+            return false;
+        };
+        let generation = editor_state.selectable_text.generation();
+        self.text_selection = Some((generation, TextSelection::new(point)));
+        self.selecting_text = true;
+        true
+    }
+
+    fn update_text_selection(&mut self, editor_state: &EditorState) {
+        let Some((generation, selection)) = &mut self.text_selection else {
+            return;
+        };
+        if *generation != editor_state.selectable_text.generation() {
+            // The overlays changed under the pointer
+            self.text_selection = None;
+            self.selecting_text = false;
             return;
         }
+        if let Some(point) = editor_state
+            .selectable_text
+            .nearest(selection.anchor, self.window_position)
+        {
+            selection.focus = point;
+        }
+    }
 
-        let draw_details = self.get_window_details_under_mouse(editor_state);
-        let grid_id = draw_details
-            .map(|details| details.event_grid_id(&self.settings))
-            .unwrap_or(0);
+    /// Moves the end of the selection to the cell under the pointer, clamped to the window it was
+    /// started on.
+    fn update_selection(&mut self, editor_state: &EditorState) {
+        let Some(selection) = &mut self.selection else {
+            return;
+        };
+        let Some(details) = editor_state
+            .window_regions
+            .iter()
+            .find(|details| details.id == selection.grid_id)
+        else {
+            // The window was closed mid-selection
+            self.selection = None;
+            self.selecting = false;
+            return;
+        };
+        if let Some(position) = relative_grid_position(
+            self.window_position,
+            details.region,
+            *editor_state.grid_scale,
+        ) {
+            selection.end = position;
+        }
+    }
 
-        let previous: GridPos<i32> = self.scroll_position.floor().try_cast().unwrap();
-        self.scroll_position += amount;
-        let new: GridPos<i32> = self.scroll_position.floor().try_cast().unwrap();
+    /// Runs the binding of the button instead of sending it to Neovim. Returns `true` when the
+    /// button was bound.
+    fn handle_mouse_binding(
+        &mut self,
+        mouse_button: MouseButton,
+        down: bool,
+        editor_state: &EditorState,
+    ) -> bool {
+        if !down {
+            if self.bound_button == Some(mouse_button) {
+                self.bound_button = None;
+                return true;
+            }
+            return false;
+        }
+        if self.drag_details.is_some() {
+            return false;
+        }
+        let settings = self.settings.get::<MouseSettings>();
+        let binding = settings.binding(mouse_button, editor_state.keyboard_manager.modifiers());
+        self.run_mouse_binding(mouse_button, binding, self.is_enabled(editor_state))
+    }
 
-        let vertical_input_type = match new.y.partial_cmp(&previous.y) {
-            Some(Ordering::Greater) => Some("up"),
-            Some(Ordering::Less) => Some("down"),
-            _ => None,
+    /// Runs `binding` for the pressed button, `enabled` telling whether the mouse goes to Neovim.
+    fn run_mouse_binding(
+        &mut self,
+        mouse_button: MouseButton,
+        binding: &str,
+        enabled: bool,
+    ) -> bool {
+        let Some(binding) = parse_mouse_binding(binding) else {
+            return false;
         };
+        // Like in a terminal, pasting the selection works with the mouse disabled too
+        if !enabled && binding != MouseBinding::Window(WindowCommand::PastePrimarySelection) {
+            return false;
+        }
+        match binding {
+            MouseBinding::Keys(keys) => send_ui(SerialCommand::Keyboard(keys)),
+            MouseBinding::Window(command) => self.pending_window_command = Some(command),
+        }
+        self.bound_button = Some(mouse_button);
+        true
+    }
 
-        if let Some(input_type) = vertical_input_type {
-            let scroll_command = SerialCommand::Scroll {
-                direction: input_type.to_string(),
-                grid_id,
-                position: self.grid_position.to_tuple(),
-                modifier_string: editor_state
-                    .keyboard_manager
-                    .format_modifier_string("", true),
-            };
-            for _ in 0..(new.y - previous.y).abs() {
-                send_ui(scroll_command.clone());
+    /// Selects the clicked completion, with the same keys that move the selection in Neovim.
+    fn handle_wildmenu_click(&self, editor_state: &EditorState) {
+        let Some(index) = editor_state.wildmenu.hit_test(self.window_position) else {
+            return;
+        };
+        let keys = selection_keys(editor_state.wildmenu.selected(), index);
+        if !keys.is_empty() {
+            send_ui(SerialCommand::Keyboard(keys));
+        }
+    }
+
+    /// The edge of the window under the pointer when it can be resized by dragging it, which the
+    /// custom frame has to do itself.
+    fn resize_edge(
+        &self,
+        window_size: PixelSize<f32>,
+        editor_state: &EditorState,
+    ) -> Option<ResizeDirection> {
+        if !editor_state.titlebar.enabled() || editor_state.window.is_maximized() {
+            return None;
+        }
+        resize_direction(self.window_position, window_size)
+    }
+
+    fn update_resize_cursor(&mut self, window_size: PixelSize<f32>, editor_state: &EditorState) {
+        let resize_edge = self.resize_edge(window_size, editor_state);
+        if resize_edge == self.resize_edge {
+            return;
+        }
+        self.resize_edge = resize_edge;
+        match resize_edge {
+            Some(direction) => editor_state.window.set_cursor(CursorIcon::from(direction)),
+            None => editor_state.window.set_cursor(
+                self.settings
+                    .get::<CmdLineSettings>()
+                    .mouse_cursor_icon
+                    .parse(),
+            ),
+        }
+    }
+
+    /// Resizes the window from its edges, and runs the buttons of the custom title bar or drags
+    /// the window by it. Returns `false` when the click is for something else, like the tabs shown
+    /// in the title bar.
+    fn handle_custom_frame_click(
+        &mut self,
+        mouse_button: MouseButton,
+        down: bool,
+        editor_state: &EditorState,
+    ) -> bool {
+        let window = editor_state.window;
+        let window_size = window.inner_size();
+        let window_size = PixelSize::new(window_size.width as f32, window_size.height as f32);
+        if let Some(direction) = self.resize_edge(window_size, editor_state) {
+            if down && mouse_button == MouseButton::Left {
+                let _ = window.drag_resize_window(direction);
+            }
+            return true;
+        }
+        if editor_state
+            .tabline
+            .hit_test(self.window_position)
+            .is_some()
+        {
+            return false;
+        }
+        let Some(hit) = editor_state.titlebar.hit_test(self.window_position) else {
+            return false;
+        };
+        if !down || mouse_button != MouseButton::Left {
+            return true;
+        }
+        match hit {
+            TitlebarHit::Minimize => window.set_minimized(true),
+            TitlebarHit::Maximize => window.set_maximized(!window.is_maximized()),
+            TitlebarHit::Close => send_ui(ParallelCommand::Quit),
+            TitlebarHit::Title => {
+                let double_click = self
+                    .last_titlebar_press
+                    .is_some_and(|press| press.elapsed() < TITLEBAR_DOUBLE_CLICK_INTERVAL);
+                if double_click {
+                    self.last_titlebar_press = None;
+                    window.set_maximized(!window.is_maximized());
+                } else {
+                    self.last_titlebar_press = Some(Instant::now());
+                    let _ = window.drag_window();
+                }
             }
         }
+        true
+    }
 
-        let horizontal_input_type = match new.x.partial_cmp(&previous.x) {
-            Some(Ordering::Greater) => Some("left"),
-            Some(Ordering::Less) => Some("right"),
-            _ => None,
+    /// Switches to the clicked tab, closes it with the close button or the middle button, and
+    /// opens a new tab with a double click past the tabs, like the tab line of Neovim.
+    ///
+    /// The clicks can't go through `nvim_input_mouse`. With ext_tabline Neovim doesn't draw its tab
+    /// line, so it has neither a row nor click regions for it, and a click on the first row lands
+    /// in the window below. The commands do what its default click handlers do instead.
+    fn handle_tabline_click(&mut self, mouse_button: MouseButton, editor_state: &EditorState) {
+        let hit = editor_state.tabline.hit_test(self.window_position);
+        let command = match (mouse_button, hit) {
+            (MouseButton::Left, Some(hit)) if hit.close_button => {
+                format!("{}tabclose", hit.index + 1)
+            }
+            (MouseButton::Left, Some(hit)) => format!("{}tabnext", hit.index + 1),
+            (MouseButton::Middle, Some(hit)) => format!("{}tabclose", hit.index + 1),
+            (MouseButton::Left, None) => {
+                let click_interval = self.settings.get::<WindowSettings>().mouse_click_interval;
+                let double_click = self.last_tabline_press.take().is_some_and(|press| {
+                    press.elapsed() < Duration::from_secs_f32(click_interval.max(0.0))
+                });
+                if !double_click {
+                    self.last_tabline_press = Some(Instant::now());
+                    return;
+                }
+                "$tabnew".to_string()
+            }
+            _ => return,
         };
+        send_ui(ParallelCommand::Command(command));
+    }
+
+    /// The grid to scroll, forgetting the partly scrolled lines when it changed.
+    fn scroll_grid_id(&mut self, editor_state: &EditorState) -> u64 {
+        let grid_id = self
+            .get_window_details_under_mouse(editor_state)
+            .map(|details| details.event_grid_id(&self.settings))
+            .unwrap_or(0);
+        if grid_id != self.scroll_grid_id {
+            self.line_scroll.reset();
+            self.pixel_scroll.reset();
+            self.scroll_grid_id = grid_id;
+        }
+        grid_id
+    }
 
-        if let Some(input_type) = horizontal_input_type {
+    /// Sends one scroll event per line and column, up and left for positive amounts.
+    fn send_scroll(&self, lines: GridVec<i32>, grid_id: u64, editor_state: &EditorState) {
+        let scrolls = [
+            (lines.y, if lines.y > 0 { "up" } else { "down" }),
+            (lines.x, if lines.x > 0 { "left" } else { "right" }),
+        ];
+        for (amount, direction) in scrolls {
+            if amount == 0 {
+                continue;
+            }
             let scroll_command = SerialCommand::Scroll {
-                direction: input_type.to_string(),
+                direction: direction.to_string(),
                 grid_id,
                 position: self.grid_position.to_tuple(),
                 modifier_string: editor_state
                     .keyboard_manager
                     .format_modifier_string("", true),
             };
-            for _ in 0..(new.x - previous.x).abs() {
+            for _ in 0..amount.unsigned_abs() {
                 send_ui(scroll_command.clone());
             }
         }
     }
 
+    /// Scrolls by lines, which can be fractional with high resolution wheels.
+    fn handle_line_scroll(&mut self, amount: GridVec<f32>, editor_state: &EditorState) {
+        if !self.is_enabled(editor_state) {
+            return;
+        }
+        let grid_id = self.scroll_grid_id(editor_state);
+        let lines = self.line_scroll.accumulate(amount);
+        self.send_scroll(lines, grid_id, editor_state);
+    }
+
     fn handle_pixel_scroll(&mut self, amount: PixelVec<f32>, editor_state: &EditorState) {
+        if !self.is_enabled(editor_state) {
+            return;
+        }
         let amount = amount / *editor_state.grid_scale;
-        self.handle_line_scroll(amount, editor_state);
+        let grid_id = self.scroll_grid_id(editor_state);
+        let lines = self.pixel_scroll.accumulate(amount);
+        self.send_scroll(lines, grid_id, editor_state);
+
+        if amount.y != 0.0 {
+            if let Some(details) = self.get_window_details_under_mouse(editor_state) {
+                let id = details.id;
+                self.pending_overscroll = match self.pending_overscroll {
+                    Some((pending_id, lines)) if pending_id == id => Some((id, lines + amount.y)),
+                    _ => Some((id, amount.y)),
+                };
+            }
+        }
+    }
+
+    /// Returns the lines scrolled with a precise device since the last call, along with the id of
+    /// the window they were scrolled on.
+    pub fn take_overscroll(&mut self) -> Option<(u64, f32)> {
+        self.pending_overscroll.take()
+    }
+
+    pub fn take_window_command(&mut self) -> Option<WindowCommand> {
+        self.pending_window_command.take()
+    }
+
+    pub fn selection(&self) -> Option<&GridSelection> {
+        self.selection.as_ref()
+    }
+
+    pub fn text_selection(&self) -> Option<(u64, TextSelection<TextPoint>)> {
+        self.text_selection
+    }
+
+    /// Returns the selection to copy, once the button selecting it was released.
+    pub fn take_copied_selection(&mut self) -> Option<GridSelection> {
+        self.copied_selection.take()
+    }
+
+    /// Sends a force click to Neovim once the trackpad is pressed hard enough, with the text under
+    /// the pointer. The click before it already moved the cursor there.
+    fn handle_touchpad_pressure(
+        &mut self,
+        stage: i64,
+        pressure: f32,
+        editor_state: &EditorState,
+        renderer: &Renderer,
+    ) {
+        let (thresholds, _) = touchpad_settings(&self.settings.get::<WindowSettings>());
+        let force_clicked = self.touchpad_pressure.update(stage, pressure, thresholds);
+        if !force_clicked || self.drag_details.is_some() {
+            return;
+        }
+        let HitTest::Window { details, position } = self.hit_test(editor_state, false) else {
+            return;
+        };
+        let Some((kind, text)) = renderer
+            .line_at(details.id, position)
+            .and_then(|(line, index)| force_click_target(&line, index))
+        else {
+            return;
+        };
+        send_ui(ParallelCommand::ForceClick { kind, text });
+    }
+
+    fn update_hot_corner(
+        &mut self,
+        position: PixelPos<f32>,
+        window_size: PixelSize<f32>,
+        editor_state: &EditorState,
+    ) {
+        let settings = self.settings.get::<WindowSettings>();
+        let corner_size = HOT_CORNER_SIZE * editor_state.window.scale_factor() as f32;
+        let corner = HotCorner::at(position, window_size, corner_size)
+            .filter(|corner| self.drag_details.is_none() && !corner.action(&settings).is_empty());
+        let unchanged = matches!(
+            (corner, &self.hot_corner),
+            (Some(corner), Some(dwell)) if dwell.corner == corner
+        );
+        if !unchanged {
+            self.hot_corner = corner.map(|corner| HotCornerDwell {
+                corner,
+                entered: Instant::now(),
+                triggered: false,
+            });
+        }
+    }
+
+    /// Triggers the hot corner action once the pointer has rested in the corner for the
+    /// configured delay. Returns `true` while still waiting.
+    fn check_hot_corner_dwell(&mut self) -> bool {
+        let Some(dwell) = &mut self.hot_corner else {
+            return false;
+        };
+        if dwell.triggered {
+            return false;
+        }
+        let settings = self.settings.get::<WindowSettings>();
+        if dwell.entered.elapsed().as_secs_f32() < settings.hot_corner_delay {
+            return true;
+        }
+        dwell.triggered = true;
+        self.pending_window_command = hot_corner_command(dwell.corner.action(&settings));
+        false
+    }
+
+    fn start_kinetic_scroll(&mut self) {
+        let inertia = self.settings.get::<WindowSettings>().touch_scroll_inertia;
+        // Only keep scrolling if the finger was still moving when it was lifted
+        let recently_scrolled = self
+            .last_touch_scroll
+            .is_some_and(|last| last.elapsed() < KINETIC_SCROLL_RELEASE_TIMEOUT);
+        if inertia > 0.0 && recently_scrolled {
+            self.kinetic_scroll_velocity = Some(self.touch_scroll_velocity);
+        }
+    }
+
+    /// Continues a touch scroll after the finger has been lifted, decaying its velocity with the
+    /// configured inertia, and waits for the hot corners. Returns `true` while either is active.
+    pub fn animate_frame(
+        &mut self,
+        dt: f32,
+        keyboard_manager: &KeyboardManager,
+        renderer: &Renderer,
+        window: &Window,
+    ) -> bool {
+        let waiting_for_hot_corner = self.check_hot_corner_dwell();
+        let Some(velocity) = self.kinetic_scroll_velocity else {
+            return waiting_for_hot_corner;
+        };
+
+        let editor_state = EditorState {
+            grid_scale: &renderer.grid_renderer.grid_scale,
+            window_regions: &renderer.window_regions,
+            tabline: &renderer.tabline_renderer,
+            titlebar: &renderer.titlebar_renderer,
+            wildmenu: &renderer.wildmenu_renderer,
+            file_changed: &renderer.file_changed_renderer,
+            exit_prompt: &renderer.exit_prompt_renderer,
+            multigrid_warning: &renderer.multigrid_warning_renderer,
+            selectable_text: &renderer.selectable_text,
+            window,
+            keyboard_manager,
+            mode: renderer.current_mode(),
+        };
+
+        let inertia = self.settings.get::<WindowSettings>().touch_scroll_inertia;
+        // Stop once the scroll is slower than one line per second
+        let min_velocity = editor_state.grid_scale.height();
+        if inertia <= 0.0 || velocity.length() < min_velocity {
+            self.kinetic_scroll_velocity = None;
+            return waiting_for_hot_corner;
+        }
+
+        self.handle_pixel_scroll(velocity * dt, &editor_state);
+        self.kinetic_scroll_velocity = Some(velocity * (-dt / inertia).exp());
+        true
     }
 
     fn handle_touch(
@@ -310,6 +1332,10 @@ impl MouseManager {
     ) {
         match phase {
             TouchPhase::Started => {
+                self.kinetic_scroll_velocity = None;
+                self.touch_scroll_velocity = PixelVec::default();
+                self.last_touch_scroll = None;
+
                 let settings = self.settings.get::<WindowSettings>();
                 let enable_deadzone = settings.touch_deadzone >= 0.0;
 
@@ -322,6 +1348,17 @@ impl MouseManager {
                         left_deadzone_once: !enable_deadzone,
                     },
                 );
+                if self.touch_position.len() == 2 {
+                    self.start_touch_gesture(editor_state);
+                } else {
+                    self.touch_gesture = None;
+                }
+            }
+            TouchPhase::Moved if self.touch_position.len() > 1 => {
+                if let Some(trace) = self.touch_position.get_mut(&finger_id) {
+                    trace.last = location;
+                }
+                self.update_touch_gesture(editor_state);
             }
             TouchPhase::Moved => {
                 let mut dragging_just_now = false;
@@ -353,12 +1390,22 @@ impl MouseManager {
                     // the double check might seem useless, but the if branch above might set
                     // trace.left_deadzone_once - which urges to check again
                     else if trace.left_deadzone_once {
-                        let delta = (trace.last.x - location.x, location.y - trace.last.y).into();
+                        let delta: PixelVec<f32> =
+                            (trace.last.x - location.x, location.y - trace.last.y).into();
 
                         // not updating the position would cause the movement to "escalate" from the
                         // starting point
                         trace.last = location;
 
+                        let now = Instant::now();
+                        if let Some(last_touch_scroll) = self.last_touch_scroll {
+                            let dt = (now - last_touch_scroll).as_secs_f32();
+                            if dt > 0.0 {
+                                self.touch_scroll_velocity = delta / dt;
+                            }
+                        }
+                        self.last_touch_scroll = Some(now);
+
                         self.handle_pixel_scroll(delta, editor_state);
                     }
                 }
@@ -369,8 +1416,13 @@ impl MouseManager {
                 }
             }
             TouchPhase::Ended | TouchPhase::Cancelled => {
+                if self.touch_gesture.take().is_some() {
+                    self.touch_position.remove(&finger_id);
+                    return;
+                }
                 if let Some(trace) = self.touch_position.remove(&finger_id) {
-                    if self.drag_details.is_some() {
+                    let dragging = self.drag_details.is_some();
+                    if dragging {
                         self.handle_pointer_transition(MouseButton::Left, false, editor_state);
                     }
                     if !trace.left_deadzone_once {
@@ -380,12 +1432,77 @@ impl MouseManager {
                         );
                         self.handle_pointer_transition(MouseButton::Left, true, editor_state);
                         self.handle_pointer_transition(MouseButton::Left, false, editor_state);
+                    } else if !dragging && *phase == TouchPhase::Ended {
+                        self.start_kinetic_scroll();
                     }
                 }
             }
         }
     }
 
+    /// The center of the two fingers, and the distance between them.
+    fn touch_gesture_points(&self) -> Option<(PixelPos<f32>, f32)> {
+        let mut traces = self.touch_position.values();
+        let (first, second) = (traces.next()?.last, traces.next()?.last);
+        let center = PixelPos::new((first.x + second.x) / 2.0, (first.y + second.y) / 2.0);
+        Some((center, (first - second).length()))
+    }
+
+    fn start_touch_gesture(&mut self, editor_state: &EditorState) {
+        // The second finger turns a drag or a tap of the first one into a gesture
+        if self.drag_details.is_some() {
+            self.handle_pointer_transition(MouseButton::Left, false, editor_state);
+        }
+        for trace in self.touch_position.values_mut() {
+            trace.left_deadzone_once = true;
+        }
+        self.kinetic_scroll_velocity = None;
+        self.last_touch_scroll = None;
+
+        let Some((center, distance)) = self.touch_gesture_points() else {
+            return;
+        };
+        let scale_factor = self.settings.get::<WindowSettings>().scale_factor;
+        self.touch_gesture = Some(TouchGesture {
+            start_distance: distance,
+            start_scale_factor: scale_factor,
+            last_center: center,
+            last_scale_factor: scale_factor,
+            zooming: false,
+        });
+    }
+
+    fn update_touch_gesture(&mut self, editor_state: &EditorState) {
+        let Some((center, distance)) = self.touch_gesture_points() else {
+            return;
+        };
+        let Some(gesture) = &mut self.touch_gesture else {
+            return;
+        };
+
+        let ratio = distance / gesture.start_distance.max(1.0);
+        gesture.zooming |= (ratio - 1.0).abs() >= PINCH_THRESHOLD;
+        if gesture.zooming {
+            let scale_factor =
+                pinch_scale_factor(gesture.start_scale_factor, gesture.start_distance, distance);
+            if scale_factor != gesture.last_scale_factor {
+                gesture.last_scale_factor = scale_factor;
+                send_ui(ParallelCommand::Command(format!(
+                    "let g:neovide_scale_factor = {scale_factor:.2}"
+                )));
+            }
+            return;
+        }
+
+        let delta: PixelVec<f32> = (
+            gesture.last_center.x - center.x,
+            center.y - gesture.last_center.y,
+        )
+            .into();
+        gesture.last_center = center;
+        self.handle_pixel_scroll(delta, editor_state);
+    }
+
     pub fn handle_event(
         &mut self,
         event: &WindowEvent,
@@ -396,8 +1513,16 @@ impl MouseManager {
         let editor_state = EditorState {
             grid_scale: &renderer.grid_renderer.grid_scale,
             window_regions: &renderer.window_regions,
+            tabline: &renderer.tabline_renderer,
+            titlebar: &renderer.titlebar_renderer,
+            wildmenu: &renderer.wildmenu_renderer,
+            file_changed: &renderer.file_changed_renderer,
+            exit_prompt: &renderer.exit_prompt_renderer,
+            multigrid_warning: &renderer.multigrid_warning_renderer,
+            selectable_text: &renderer.selectable_text,
             window,
             keyboard_manager,
+            mode: renderer.current_mode(),
         };
         match event {
             WindowEvent::CursorMoved { position, .. } => {
@@ -410,14 +1535,26 @@ impl MouseManager {
                     self.mouse_hidden = false;
                 }
             }
+            WindowEvent::CursorLeft { .. } => {
+                self.hot_corner = None;
+            }
             WindowEvent::MouseWheel {
                 delta: MouseScrollDelta::LineDelta(x, y),
                 ..
             } => self.handle_line_scroll((*x, *y).into(), &editor_state),
             WindowEvent::MouseWheel {
                 delta: MouseScrollDelta::PixelDelta(delta),
+                phase,
                 ..
-            } => self.handle_pixel_scroll((delta.x as f32, delta.y as f32).into(), &editor_state),
+            } => {
+                let (_, smoothing) = touchpad_settings(&self.settings.get::<WindowSettings>());
+                let delta = PixelVec::new(delta.x as f32, delta.y as f32);
+                let mut delta = self.scroll_smoothing.smooth(delta, smoothing);
+                if matches!(phase, TouchPhase::Ended | TouchPhase::Cancelled) {
+                    delta = delta + self.scroll_smoothing.finish();
+                }
+                self.handle_pixel_scroll(delta, &editor_state);
+            }
             WindowEvent::Touch(Touch {
                 device_id,
                 id,
@@ -435,6 +1572,9 @@ impl MouseManager {
                 state == &ElementState::Pressed,
                 &editor_state,
             ),
+            WindowEvent::TouchpadPressure {
+                stage, pressure, ..
+            } => self.handle_touchpad_pressure(*stage, *pressure, &editor_state, renderer),
 
             WindowEvent::KeyboardInput {
                 event: key_event, ..
@@ -451,3 +1591,308 @@ impl MouseManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::*;
+
+    fn click(previous: Option<&ClickDetails>, position: (u32, u32), time: Instant) -> ClickDetails {
+        ClickDetails::next(
+            previous,
+            MouseButton::Left,
+            1,
+            position.into(),
+            time,
+            Duration::from_millis(500),
+        )
+    }
+
+    fn random_regions(rng: &mut impl Rng) -> Vec<WindowDrawDetails> {
+        (0..rng.gen_range(0..5))
+            .map(|id| {
+                let origin = PixelPos::new(rng.gen_range(0.0..500.0), rng.gen_range(0.0..500.0));
+                // Include regions without any size, like windows being laid out
+                let size = match rng.gen_range(0..3) {
+                    0 => PixelSize::new(0.0, 0.0),
+                    1 => PixelSize::new(rng.gen_range(0.0..5.0), rng.gen_range(0.0..5.0)),
+                    _ => PixelSize::new(rng.gen_range(0.0..500.0), rng.gen_range(0.0..500.0)),
+                };
+                WindowDrawDetails {
+                    id,
+                    region: PixelRect::from_origin_and_size(origin, size),
+                }
+            })
+            .collect()
+    }
+
+    fn random_grid_scale(rng: &mut impl Rng) -> GridScale {
+        // Scale factor changes and fonts still loading give cells of any size, including none
+        match rng.gen_range(0..4) {
+            0 => GridScale::new(PixelSize::new(0.0, 0.0)),
+            1 => GridScale::new(PixelSize::new(0.01, 0.01)),
+            2 => GridScale::new(PixelSize::new(1000.0, 2000.0)),
+            _ => GridScale::new(PixelSize::new(
+                rng.gen_range(1.0..40.0),
+                rng.gen_range(1.0..80.0),
+            )),
+        }
+    }
+
+    #[test]
+    fn hit_test_stays_inside_the_window() {
+        // Printed so that a failure can be replayed with the same regions
+        let seed = thread_rng().gen();
+        println!("seed: {seed}");
+        let mut rng = rngs::StdRng::seed_from_u64(seed);
+        for _ in 0..10000 {
+            let regions = random_regions(&mut rng);
+            let grid_scale = random_grid_scale(&mut rng);
+            let position =
+                PixelPos::new(rng.gen_range(-100.0..1100.0), rng.gen_range(-100.0..1100.0));
+            let drag_window_id = rng.gen_bool(0.3).then(|| rng.gen_range(0..5));
+            match hit_test(&regions, position, grid_scale, drag_window_id) {
+                HitTest::Window { details, position } => {
+                    assert!(regions.iter().any(|region| region.id == details.id));
+                    let size = details.region.size();
+                    let columns = (size.width / grid_scale.width()).ceil().max(1.0);
+                    let lines = (size.height / grid_scale.height()).ceil().max(1.0);
+                    assert!((position.x as f32) < columns, "{position:?} {details:?}");
+                    assert!((position.y as f32) < lines, "{position:?} {details:?}");
+                }
+                HitTest::Closed => {
+                    let id = drag_window_id.unwrap();
+                    assert!(regions.iter().all(|region| region.id != id));
+                }
+                HitTest::Empty => {}
+            }
+        }
+    }
+
+    #[test]
+    fn smoothing_keeps_the_distance() {
+        let mut smoothing = ScrollSmoothing::default();
+        let deltas = [0.0, 30.0, 0.0, 30.0, 0.0, 30.0];
+        let smoothed = deltas
+            .iter()
+            .map(|delta| smoothing.smooth(PixelVec::new(0.0, *delta), 3).y)
+            .collect::<Vec<_>>();
+        assert_eq!(smoothed, vec![0.0, 10.0, 10.0, 20.0, 10.0, 20.0]);
+        // The rest comes once the gesture ends
+        let total = smoothed.iter().sum::<f32>() + smoothing.finish().y;
+        assert_eq!(total, 90.0);
+        assert_eq!(
+            smoothing.smooth(PixelVec::new(0.0, 5.0), 1),
+            PixelVec::new(0.0, 5.0)
+        );
+    }
+
+    #[test]
+    fn dragging_on_a_closed_window() {
+        let regions = vec![WindowDrawDetails {
+            id: 1,
+            region: PixelRect::from_origin_and_size(
+                PixelPos::new(0.0, 0.0),
+                PixelSize::new(100.0, 100.0),
+            ),
+        }];
+        let grid_scale = GridScale::new(PixelSize::new(10.0, 20.0));
+        let position = PixelPos::new(250.0, 50.0);
+        assert!(matches!(
+            hit_test(&regions, position, grid_scale, Some(1)),
+            HitTest::Window { position, .. } if position == GridPos::new(9, 2)
+        ));
+        assert!(matches!(
+            hit_test(&regions, position, grid_scale, Some(2)),
+            HitTest::Closed
+        ));
+        assert!(matches!(
+            hit_test(&regions, position, grid_scale, None),
+            HitTest::Empty
+        ));
+    }
+
+    #[test]
+    fn mouse_bindings() {
+        let settings = MouseSettings::default();
+        assert_eq!(
+            settings.binding(MouseButton::Back, ModifiersState::empty()),
+            "<C-o>"
+        );
+        assert_eq!(
+            settings.binding(MouseButton::Left, ModifiersState::CONTROL),
+            ""
+        );
+        assert_eq!(parse_mouse_binding(" "), None);
+        assert_eq!(
+            parse_mouse_binding("<C-o>"),
+            Some(MouseBinding::Keys("<C-o>".to_string()))
+        );
+        assert_eq!(
+            parse_mouse_binding(":bnext"),
+            Some(MouseBinding::Window(WindowCommand::RunCommand(
+                "bnext".to_string()
+            )))
+        );
+        assert_eq!(
+            parse_mouse_binding("fullscreen"),
+            Some(MouseBinding::Window(WindowCommand::ToggleFullscreen))
+        );
+        assert_eq!(
+            parse_mouse_binding("paste_selection"),
+            Some(MouseBinding::Window(WindowCommand::PastePrimarySelection))
+        );
+    }
+
+    #[test]
+    fn paste_selection_works_with_the_mouse_disabled() {
+        let mut mouse_manager = MouseManager::new(Arc::new(Settings::new()));
+        // Other bindings leave the click alone while the mouse is disabled
+        assert!(!mouse_manager.run_mouse_binding(MouseButton::Middle, ":bnext", false));
+        assert_eq!(mouse_manager.take_window_command(), None);
+
+        assert!(mouse_manager.run_mouse_binding(MouseButton::Middle, "paste_selection", false));
+        assert_eq!(
+            mouse_manager.take_window_command(),
+            Some(WindowCommand::PastePrimarySelection)
+        );
+        // The release of the button doesn't reach Neovim either
+        assert_eq!(mouse_manager.bound_button, Some(MouseButton::Middle));
+
+        assert!(mouse_manager.run_mouse_binding(MouseButton::Middle, "paste_selection", true));
+        assert_eq!(
+            mouse_manager.take_window_command(),
+            Some(WindowCommand::PastePrimarySelection)
+        );
+    }
+
+    #[test]
+    fn fractional_scrolls_add_up_to_lines() {
+        let mut accumulator = ScrollAccumulator::default();
+        let mut lines = GridVec::default();
+        for _ in 0..10 {
+            lines += accumulator.accumulate(GridVec::new(0.0, 0.1));
+        }
+        assert_eq!(lines, GridVec::new(0, 1));
+        assert_eq!(
+            accumulator.accumulate(GridVec::new(2.5, -3.0)),
+            GridVec::new(2, -3)
+        );
+        // Reversing starts from scratch instead of first paying back the remainder
+        assert_eq!(
+            accumulator.accumulate(GridVec::new(-1.0, 0.0)),
+            GridVec::new(-1, 0)
+        );
+    }
+
+    #[test]
+    fn pinching_scales_in_steps() {
+        assert_eq!(pinch_scale_factor(1.0, 100.0, 150.0), 1.5);
+        assert_eq!(pinch_scale_factor(1.0, 100.0, 123.0), 1.25);
+        assert_eq!(pinch_scale_factor(1.0, 100.0, 1.0), MIN_PINCH_SCALE_FACTOR);
+        assert_eq!(
+            pinch_scale_factor(2.0, 100.0, 1000.0),
+            MAX_PINCH_SCALE_FACTOR
+        );
+    }
+
+    #[test]
+    fn counts_clicks_within_interval() {
+        let start = Instant::now();
+        let first = click(None, (3, 4), start);
+        let second = click(Some(&first), (3, 4), start + Duration::from_millis(200));
+        let third = click(Some(&second), (3, 4), start + Duration::from_millis(400));
+        assert_eq!(first.count, 1);
+        assert_eq!(second.count, 2);
+        assert_eq!(third.count, 3);
+    }
+
+    #[test]
+    fn resets_after_interval() {
+        let start = Instant::now();
+        let first = click(None, (3, 4), start);
+        let second = click(Some(&first), (3, 4), start + Duration::from_millis(600));
+        assert_eq!(second.count, 1);
+    }
+
+    #[test]
+    fn resets_on_other_cell_or_button() {
+        let start = Instant::now();
+        let first = click(None, (3, 4), start);
+        let moved = click(Some(&first), (4, 4), start + Duration::from_millis(100));
+        assert_eq!(moved.count, 1);
+
+        let right = ClickDetails::next(
+            Some(&first),
+            MouseButton::Right,
+            1,
+            (3, 4).into(),
+            start + Duration::from_millis(100),
+            Duration::from_millis(500),
+        );
+        assert_eq!(right.count, 1);
+    }
+
+    #[test]
+    fn wraps_after_quadruple_click() {
+        let start = Instant::now();
+        let mut last = click(None, (0, 0), start);
+        for _ in 0..MAX_CLICK_COUNT {
+            last = click(Some(&last), (0, 0), start);
+        }
+        assert_eq!(last.count, 1);
+    }
+
+    #[test]
+    fn detects_hot_corners() {
+        let size = PixelSize::new(800.0, 600.0);
+        let at = |x, y| HotCorner::at(PixelPos::new(x, y), size, 4.0);
+        assert_eq!(at(0.0, 0.0), Some(HotCorner::TopLeft));
+        assert_eq!(at(799.0, 2.0), Some(HotCorner::TopRight));
+        assert_eq!(at(3.0, 599.0), Some(HotCorner::BottomLeft));
+        assert_eq!(at(797.0, 597.0), Some(HotCorner::BottomRight));
+        assert_eq!(at(400.0, 0.0), None);
+        assert_eq!(at(0.0, 300.0), None);
+    }
+
+    #[test]
+    fn parses_hot_corner_actions() {
+        assert_eq!(hot_corner_command(""), None);
+        assert_eq!(
+            hot_corner_command("fullscreen"),
+            Some(WindowCommand::ToggleFullscreen)
+        );
+        assert_eq!(
+            hot_corner_command(":ZenMode"),
+            Some(WindowCommand::RunCommand("ZenMode".to_string()))
+        );
+        assert_eq!(
+            hot_corner_command("Telescope commands"),
+            Some(WindowCommand::RunCommand("Telescope commands".to_string()))
+        );
+    }
+
+    #[test]
+    fn mouse_follows_the_modes_of_the_option() {
+        let unknown = |name: &str| EditorMode::Unknown(name.to_string());
+        assert!(mouse_enabled_in("nvi", &EditorMode::Normal, false));
+        assert!(!mouse_enabled_in("n", &EditorMode::Insert, false));
+        assert!(mouse_enabled_in("n", &unknown("terminal"), false));
+        assert!(mouse_enabled_in("n", &unknown("operator"), false));
+        assert!(!mouse_enabled_in("i", &unknown("operator"), false));
+        assert!(mouse_enabled_in("a", &EditorMode::CmdLine, false));
+        assert!(!mouse_enabled_in("", &EditorMode::Visual, false));
+    }
+
+    #[test]
+    fn mouse_follows_the_help_and_prompt_flags() {
+        let more = EditorMode::Unknown("more".to_string());
+        assert!(mouse_enabled_in("r", &more, false));
+        assert!(!mouse_enabled_in("a", &more, false));
+        assert!(!mouse_enabled_in("h", &more, true));
+
+        assert!(mouse_enabled_in("h", &EditorMode::Normal, true));
+        assert!(mouse_enabled_in("h", &EditorMode::Insert, true));
+        assert!(!mouse_enabled_in("h", &EditorMode::Normal, false));
+    }
+}
@@ -0,0 +1,73 @@
+//! Tells the compositor which parts of the window are opaque, so that it doesn't have to blend
+//! what's behind them. That's all of it when the window isn't transparent, except for the corners
+//! when they are rounded.
+
+use winit::window::Window;
+
+#[cfg(target_os = "linux")]
+use {
+    super::x11::{set_cardinal_property, x11_window},
+    raw_window_handle::{HasWindowHandle, RawWindowHandle},
+};
+
+/// The opaque rectangles of a window of `width` by `height` pixels, as x, y, width and height.
+/// The squares of the rounded corners are left out, which keeps the region to three rectangles.
+pub fn opaque_region(width: u32, height: u32, corner_radius: u32) -> Vec<[u32; 4]> {
+    let radius = corner_radius.min(width / 2).min(height / 2);
+    if radius == 0 {
+        return vec![[0, 0, width, height]];
+    }
+    vec![
+        [radius, 0, width - 2 * radius, radius],
+        [0, radius, width, height - 2 * radius],
+        [radius, height - radius, width - 2 * radius, radius],
+    ]
+    .into_iter()
+    .filter(|[_, _, width, height]| *width > 0 && *height > 0)
+    .collect()
+}
+
+/// Updates the opaque region after the window changed its size or its opacity. macOS already
+/// marks the window opaque along with its background, see `MacosWindowFeature`.
+pub fn update_opaque_region(window: &Window, opaque: bool, corner_radius: u32) {
+    #[cfg(target_os = "linux")]
+    {
+        let size = window.inner_size();
+        let region = if opaque {
+            opaque_region(size.width, size.height, corner_radius)
+        } else {
+            Vec::new()
+        };
+        if let Some(x11_window) = x11_window(window) {
+            let region = region.into_iter().flatten().collect::<Vec<_>>();
+            set_cardinal_property(
+                x11_window,
+                "_NET_WM_OPAQUE_REGION",
+                (!region.is_empty()).then_some(region.as_slice()),
+            );
+        } else if let Ok(RawWindowHandle::Wayland(_)) =
+            window.window_handle().map(|handle| handle.as_raw())
+        {
+            // Winit marks the whole surface opaque when it isn't transparent, and follows its
+            // size, but it can't leave out the corners
+            window.set_transparent(!opaque || corner_radius > 0);
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (window, opaque, corner_radius);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounded_corners_are_left_out() {
+        assert_eq!(opaque_region(800, 600, 0), vec![[0, 0, 800, 600]]);
+        assert_eq!(
+            opaque_region(800, 600, 10),
+            vec![[10, 0, 780, 10], [0, 10, 800, 580], [10, 590, 780, 10]]
+        );
+        assert!(opaque_region(20, 20, 50).is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+//! Native notifications of the OS, posted by `neovide.os_notify` or `neovide.notify` while the
+//! window isn't focused, so that plugins can tell when a long build or test run finishes. Clicking
+//! them focuses the window again.
+
+use rmpv::Value;
+use winit::event_loop::EventLoopProxy;
+
+use super::UserEvent;
+
+/// How urgent a notification is, errors stay on screen until they're dismissed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NotificationLevel {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+impl NotificationLevel {
+    /// Reads `info`, `warn` or `error`, or one of the levels of `vim.log.levels`.
+    fn parse(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(name) => match name.as_str()? {
+                "info" => Some(NotificationLevel::Info),
+                "warn" | "warning" => Some(NotificationLevel::Warning),
+                "error" => Some(NotificationLevel::Error),
+                _ => None,
+            },
+            Value::Integer(level) => match level.as_u64()? {
+                0..=2 => Some(NotificationLevel::Info),
+                3 => Some(NotificationLevel::Warning),
+                _ => Some(NotificationLevel::Error),
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OsNotification {
+    pub title: String,
+    pub body: String,
+    pub sound: bool,
+    pub level: NotificationLevel,
+}
+
+impl OsNotification {
+    /// Reads the `{title, body, sound}` dictionary sent by Neovim.
+    pub fn parse(value: &Value) -> Option<Self> {
+        let mut notification = OsNotification {
+            title: "Neovide".to_string(),
+            body: String::new(),
+            sound: false,
+            level: NotificationLevel::Info,
+        };
+        for (key, value) in value.as_map()? {
+            match (key.as_str()?, value) {
+                ("title", Value::String(title)) => {
+                    notification.title = title.as_str()?.to_string();
+                }
+                ("body", Value::String(body)) => notification.body = body.as_str()?.to_string(),
+                ("sound", Value::Boolean(sound)) => notification.sound = *sound,
+                ("level", level) => {
+                    notification.level = NotificationLevel::parse(level).unwrap_or_default();
+                }
+                _ => {}
+            }
+        }
+        Some(notification)
+    }
+
+    /// Reads the `title, body, level` arguments of `neovide.notify`, the level being optional.
+    pub fn from_arguments(arguments: &[Value]) -> Option<Self> {
+        let [title, body, rest @ ..] = arguments else {
+            return None;
+        };
+        Some(OsNotification {
+            title: title.as_str()?.to_string(),
+            body: body.as_str()?.to_string(),
+            sound: false,
+            level: rest
+                .first()
+                .and_then(NotificationLevel::parse)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+pub fn show_notification(notification: OsNotification, proxy: EventLoopProxy<UserEvent>) {
+    #[cfg(target_os = "linux")]
+    linux::show_notification(notification, proxy);
+    #[cfg(target_os = "macos")]
+    super::macos::show_notification(&notification, proxy);
+    #[cfg(target_os = "windows")]
+    crate::windows_utils::show_notification(&notification, proxy);
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{collections::HashMap, time::Duration};
+
+    use futures::StreamExt;
+    use tokio::time::timeout;
+    use winit::event_loop::EventLoopProxy;
+    use zbus::{zvariant::Value, Connection, Proxy};
+
+    use super::{NotificationLevel, OsNotification};
+    use crate::window::{
+        session_bus::{self, with_timeout},
+        UserEvent, WindowCommand,
+    };
+
+    const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+    const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+    /// The action of clicking the notification itself.
+    const DEFAULT_ACTION: &str = "default";
+    /// How long a click on the notification still focuses the window. The servers keep the
+    /// critical ones until they're dismissed, which may be never.
+    const CLICK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+    async fn notify(
+        connection: Connection,
+        notification: OsNotification,
+        proxy: EventLoopProxy<UserEvent>,
+    ) -> zbus::Result<()> {
+        let notifications = with_timeout(Proxy::new(
+            &connection,
+            NOTIFICATIONS,
+            NOTIFICATIONS_PATH,
+            NOTIFICATIONS,
+        ))
+        .await?;
+        // Listen before posting, the click could come before we're done otherwise
+        let mut signals = with_timeout(notifications.receive_all_signals()).await?;
+
+        let mut hints = HashMap::new();
+        // Critical notifications stay until they're dismissed
+        let urgency: u8 = match notification.level {
+            NotificationLevel::Info | NotificationLevel::Warning => 1,
+            NotificationLevel::Error => 2,
+        };
+        hints.insert("urgency", Value::from(urgency));
+        if notification.sound {
+            hints.insert("sound-name", Value::from("message-new-instant"));
+        } else {
+            hints.insert("suppress-sound", Value::from(true));
+        }
+        let id: u32 = with_timeout(notifications.call(
+            "Notify",
+            &(
+                "Neovide",
+                0u32,
+                "neovide",
+                notification.title.as_str(),
+                notification.body.as_str(),
+                vec![DEFAULT_ACTION, "Open"],
+                hints,
+                -1i32,
+            ),
+        ))
+        .await?;
+
+        let clicked = async {
+            while let Some(message) = signals.next().await {
+                let header = message.header();
+                match header.member().map(|member| member.as_str()) {
+                    Some("ActionInvoked") => {
+                        let (signal_id, action): (u32, String) = message.body().deserialize()?;
+                        if signal_id == id {
+                            if action == DEFAULT_ACTION {
+                                let _ = proxy.send_event(WindowCommand::FocusWindow.into());
+                            }
+                            break;
+                        }
+                    }
+                    Some("NotificationClosed") => {
+                        let (signal_id, _reason): (u32, u32) = message.body().deserialize()?;
+                        if signal_id == id {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok::<_, zbus::Error>(())
+        };
+        // Stop listening once it's too late for a click to matter
+        timeout(CLICK_TIMEOUT, clicked).await.unwrap_or(Ok(()))
+    }
+
+    /// Posts the notification and waits for its click, on the connection to the session bus.
+    pub fn show_notification(notification: OsNotification, proxy: EventLoopProxy<UserEvent>) {
+        session_bus::spawn("notification", move |connection| {
+            notify(connection, notification, proxy)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_notification() {
+        let value = Value::Map(vec![
+            (Value::from("title"), Value::from("Build")),
+            (Value::from("body"), Value::from("Finished in 2m")),
+            (Value::from("sound"), Value::from(true)),
+        ]);
+        assert_eq!(
+            OsNotification::parse(&value),
+            Some(OsNotification {
+                title: "Build".to_string(),
+                body: "Finished in 2m".to_string(),
+                sound: true,
+                level: NotificationLevel::Info,
+            })
+        );
+
+        let value = Value::Map(vec![(Value::from("body"), Value::from("Done"))]);
+        assert_eq!(
+            OsNotification::parse(&value).map(|notification| notification.title),
+            Some("Neovide".to_string())
+        );
+        assert_eq!(OsNotification::parse(&Value::from("Done")), None);
+    }
+
+    #[test]
+    fn notification_from_arguments() {
+        let notification = OsNotification::from_arguments(&[
+            Value::from("LSP"),
+            Value::from("3 errors"),
+            Value::from("error"),
+        ])
+        .unwrap();
+        assert_eq!(notification.title, "LSP");
+        assert_eq!(notification.level, NotificationLevel::Error);
+
+        let level = |arguments: &[Value]| {
+            OsNotification::from_arguments(arguments).map(|notification| notification.level)
+        };
+        // The levels of vim.log.levels
+        assert_eq!(
+            level(&[Value::from("Build"), Value::from("Done"), Value::from(3)]),
+            Some(NotificationLevel::Warning)
+        );
+        assert_eq!(
+            level(&[Value::from("Build"), Value::from("Done")]),
+            Some(NotificationLevel::Info)
+        );
+        assert_eq!(level(&[Value::from("Build")]), None);
+    }
+}
@@ -0,0 +1,144 @@
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    window::{Fullscreen, Window, WindowLevel},
+};
+
+use super::WindowSettings;
+
+/// Pixels between the picture in picture window and the edges of the monitor.
+const MARGIN: i32 = 16;
+const MIN_SIZE: PhysicalSize<u32> = PhysicalSize {
+    width: 160,
+    height: 90,
+};
+
+/// What picture in picture changes about the window, to restore it afterwards.
+struct SavedWindow {
+    inner_size: PhysicalSize<u32>,
+    position: Option<PhysicalPosition<i32>>,
+    decorations: bool,
+    maximized: bool,
+    fullscreen: bool,
+}
+
+/// `:NeovideTogglePip` shrinks the window to a small undecorated window, above the other ones in
+/// the bottom right corner of the monitor, with a smaller font. Toggling it again restores the
+/// window.
+pub struct PictureInPicture {
+    saved: Option<SavedWindow>,
+}
+
+/// The size and position of the picture in picture window, in the bottom right corner of the
+/// monitor at `monitor_position` and of `monitor_size`.
+fn pip_frame(
+    monitor_position: PhysicalPosition<i32>,
+    monitor_size: PhysicalSize<u32>,
+    requested: PhysicalSize<u32>,
+) -> (PhysicalPosition<i32>, PhysicalSize<u32>) {
+    let max_width = (monitor_size.width as i32 - 2 * MARGIN).max(MIN_SIZE.width as i32) as u32;
+    let max_height = (monitor_size.height as i32 - 2 * MARGIN).max(MIN_SIZE.height as i32) as u32;
+    let size = PhysicalSize::new(
+        requested.width.clamp(MIN_SIZE.width, max_width),
+        requested.height.clamp(MIN_SIZE.height, max_height),
+    );
+    let position = PhysicalPosition::new(
+        monitor_position.x + monitor_size.width as i32 - size.width as i32 - MARGIN,
+        monitor_position.y + monitor_size.height as i32 - size.height as i32 - MARGIN,
+    );
+    (position, size)
+}
+
+impl PictureInPicture {
+    pub fn new() -> Self {
+        Self { saved: None }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// The scale factor of the font, `g:neovide_scale_factor` scaled down while active.
+    pub fn user_scale_factor(&self, settings: &WindowSettings) -> f64 {
+        let scale_factor = settings.scale_factor as f64;
+        if self.is_active() {
+            scale_factor * settings.pip_scale_factor as f64
+        } else {
+            scale_factor
+        }
+    }
+
+    pub fn enter(&mut self, window: &Window, settings: &WindowSettings) {
+        if self.is_active() {
+            return;
+        }
+        self.saved = Some(SavedWindow {
+            inner_size: window.inner_size(),
+            position: window.outer_position().ok(),
+            decorations: window.is_decorated(),
+            maximized: window.is_maximized(),
+            fullscreen: window.fullscreen().is_some(),
+        });
+        // Fullscreen windows can't be resized, so picture in picture starts from the window
+        window.set_fullscreen(None);
+
+        let scale_factor = window.scale_factor();
+        let requested = PhysicalSize::new(
+            (settings.pip_width as f64 * scale_factor) as u32,
+            (settings.pip_height as f64 * scale_factor) as u32,
+        );
+        window.set_maximized(false);
+        window.set_decorations(false);
+        window.set_window_level(WindowLevel::AlwaysOnTop);
+        match window.current_monitor() {
+            Some(monitor) => {
+                let (position, size) = pip_frame(monitor.position(), monitor.size(), requested);
+                let _ = window.request_inner_size(size);
+                window.set_outer_position(position);
+            }
+            None => {
+                let _ = window.request_inner_size(requested);
+            }
+        }
+    }
+
+    /// Restores the window as it was before, with the level of `g:neovide_window_level`.
+    pub fn leave(&mut self, window: &Window, window_level: WindowLevel) {
+        let Some(saved) = self.saved.take() else {
+            return;
+        };
+        window.set_window_level(window_level);
+        window.set_decorations(saved.decorations);
+        let _ = window.request_inner_size(saved.inner_size);
+        if let Some(position) = saved.position {
+            window.set_outer_position(position);
+        }
+        window.set_maximized(saved.maximized);
+        if saved.fullscreen {
+            window.set_fullscreen(Some(Fullscreen::Borderless(window.current_monitor())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pip_is_in_the_bottom_right_corner_of_the_monitor() {
+        let monitor_position = PhysicalPosition::new(1920, 0);
+        let monitor_size = PhysicalSize::new(1920, 1080);
+        assert_eq!(
+            pip_frame(monitor_position, monitor_size, PhysicalSize::new(480, 270)),
+            (
+                PhysicalPosition::new(3840 - 480 - MARGIN, 1080 - 270 - MARGIN),
+                PhysicalSize::new(480, 270)
+            )
+        );
+        // Too big or too small sizes are clamped
+        let (_, size) = pip_frame(monitor_position, monitor_size, PhysicalSize::new(4000, 10));
+        assert_eq!(
+            size,
+            PhysicalSize::new(1920 - 2 * MARGIN as u32, MIN_SIZE.height)
+        );
+    }
+}
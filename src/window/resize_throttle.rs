@@ -0,0 +1,107 @@
+//! Sends the grid sizes of an interactive resize at a limited rate, so that the text reflows while
+//! the window is dragged without flooding Neovim with a resize per frame.
+
+use std::time::{Duration, Instant};
+
+use crate::units::GridSize;
+
+#[derive(Debug, Default)]
+pub struct ResizeThrottle {
+    last_sent: Option<Instant>,
+    pending: Option<GridSize<u32>>,
+}
+
+fn interval(rate: f32) -> Duration {
+    if rate > 0.0 {
+        Duration::from_secs_f32(1.0 / rate)
+    } else {
+        Duration::ZERO
+    }
+}
+
+impl ResizeThrottle {
+    /// Asks for the grid to be resized to `size`, and returns it when it can be sent right away.
+    /// Otherwise it waits for `poll`, replacing the size waiting before it. A `rate` of 0 doesn't
+    /// throttle anything.
+    pub fn request(
+        &mut self,
+        size: GridSize<u32>,
+        now: Instant,
+        rate: f32,
+    ) -> Option<GridSize<u32>> {
+        self.pending = Some(size);
+        self.poll(now, rate)
+    }
+
+    /// The size waiting to be sent, once `rate` allows it.
+    pub fn poll(&mut self, now: Instant, rate: f32) -> Option<GridSize<u32>> {
+        if let Some(last_sent) = self.last_sent {
+            if now.duration_since(last_sent) < interval(rate) {
+                return None;
+            }
+        }
+        let size = self.pending.take()?;
+        self.last_sent = Some(now);
+        Some(size)
+    }
+
+    /// When the size waiting can be sent.
+    pub fn deadline(&self, rate: f32) -> Option<Instant> {
+        self.pending?;
+        Some(self.last_sent? + interval(rate))
+    }
+}
+
+/// How much the `rendered` grid has to be stretched to fill the `target` one, unless they already
+/// match.
+pub fn preview_scale(target: GridSize<u32>, rendered: GridSize<u32>) -> Option<(f32, f32)> {
+    if target == rendered || rendered.width == 0 || rendered.height == 0 {
+        return None;
+    }
+    Some((
+        target.width as f32 / rendered.width as f32,
+        target.height as f32 / rendered.height as f32,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resizes_are_sent_at_the_rate() {
+        let start = Instant::now();
+        let at = |milliseconds| start + Duration::from_millis(milliseconds);
+        let size = |columns| GridSize::new(columns, 30);
+        let mut throttle = ResizeThrottle::default();
+
+        assert_eq!(throttle.request(size(80), at(0), 10.0), Some(size(80)));
+        assert_eq!(throttle.request(size(81), at(20), 10.0), None);
+        assert_eq!(throttle.request(size(82), at(40), 10.0), None);
+        assert_eq!(throttle.deadline(10.0), Some(at(100)));
+        assert_eq!(throttle.poll(at(90), 10.0), None);
+        // Only the last size is sent
+        assert_eq!(throttle.poll(at(100), 10.0), Some(size(82)));
+        assert_eq!(throttle.poll(at(300), 10.0), None);
+        assert_eq!(throttle.deadline(10.0), None);
+
+        assert_eq!(throttle.request(size(83), at(310), 0.0), Some(size(83)));
+        assert_eq!(throttle.request(size(84), at(311), 0.0), Some(size(84)));
+    }
+
+    #[test]
+    fn preview_stretches_the_rendered_grid() {
+        let size = GridSize::new;
+        assert_eq!(preview_scale(size(80, 30), size(80, 30)), None);
+        // Growing and shrinking, in one direction or both
+        assert_eq!(
+            preview_scale(size(100, 30), size(80, 30)),
+            Some((1.25, 1.0))
+        );
+        assert_eq!(preview_scale(size(80, 15), size(80, 30)), Some((1.0, 0.5)));
+        assert_eq!(preview_scale(size(40, 60), size(80, 30)), Some((0.5, 2.0)));
+        // Nothing was rendered yet to stretch
+        assert_eq!(preview_scale(size(80, 30), size(0, 30)), None);
+        assert_eq!(preview_scale(size(80, 30), size(80, 0)), None);
+    }
+}
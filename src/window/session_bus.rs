@@ -0,0 +1,66 @@
+//! The connection to the D-Bus session bus shared by the notifications, the global menu and the
+//! taskbar progress, with the thread running their requests.
+
+use std::{future::Future, io, sync::OnceLock, thread, time::Duration};
+
+use log::debug;
+use tokio::{runtime::Handle, sync::OnceCell, time::timeout};
+use zbus::Connection;
+
+/// How long a request to the session bus may take, so that a stuck bus doesn't pile them up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+static RUNTIME: OnceLock<Option<Handle>> = OnceLock::new();
+static CONNECTION: OnceCell<Connection> = OnceCell::const_new();
+
+/// Starts the thread running the requests, which lives as long as Neovide.
+fn start() -> Option<Handle> {
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(error) => {
+            debug!("Could not start the session bus runtime: {error}");
+            return None;
+        }
+    };
+    let handle = runtime.handle().clone();
+    let result = thread::Builder::new()
+        .name("session_bus".to_string())
+        .spawn(move || runtime.block_on(std::future::pending::<()>()));
+    if let Err(error) = result {
+        debug!("Could not start the session bus thread: {error}");
+        return None;
+    }
+    Some(handle)
+}
+
+/// Fails when the request didn't finish in time.
+pub async fn with_timeout<T>(request: impl Future<Output = zbus::Result<T>>) -> zbus::Result<T> {
+    timeout(REQUEST_TIMEOUT, request)
+        .await
+        .map_err(io::Error::from)?
+}
+
+/// Runs the task with the connection, which is opened the first time it's needed. `name`
+/// describes the task in the logs when it fails.
+pub fn spawn<Task, Output>(name: &'static str, task: Task)
+where
+    Task: FnOnce(Connection) -> Output + Send + 'static,
+    Output: Future<Output = zbus::Result<()>> + Send + 'static,
+{
+    let Some(handle) = RUNTIME.get_or_init(start) else {
+        return;
+    };
+    handle.spawn(async move {
+        let connection = with_timeout(CONNECTION.get_or_try_init(Connection::session)).await;
+        let result = match connection {
+            Ok(connection) => task(connection.clone()).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            debug!("No {name}: {error}");
+        }
+    });
+}
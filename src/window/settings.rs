@@ -1,12 +1,18 @@
-#[cfg(target_os = "macos")]
-use {log::error, rmpv::Value};
+use std::collections::HashMap;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use log::error;
+use rmpv::Value;
 
 use crate::settings::*;
+#[cfg(target_os = "windows")]
+use winit::platform::windows::BackdropType;
 
 #[derive(Clone, SettingGroup, PartialEq)]
 pub struct WindowSettings {
     pub refresh_rate: u64,
     pub refresh_rate_idle: u64,
+    pub refresh_rate_idle_delay: f32,
     pub transparency: f32,
     pub normal_opacity: f32,
     pub window_blurred: bool,
@@ -15,27 +21,62 @@ pub struct WindowSettings {
     pub iso_layout: bool,
     pub remember_window_size: bool,
     pub remember_window_position: bool,
+    pub resize_rate: f32,
+    pub resize_preview: bool,
     pub hide_mouse_when_typing: bool,
     pub touch_deadzone: f32,
     pub touch_drag_timeout: f32,
+    pub touch_scroll_inertia: f32,
+    pub mouse_click_interval: f32,
+    pub hot_corner_top_left: String,
+    pub hot_corner_top_right: String,
+    pub hot_corner_bottom_left: String,
+    pub hot_corner_bottom_right: String,
+    pub hot_corner_delay: f32,
     pub background_color: String,
     pub confirm_quit: bool,
+    pub unfocused_bell: String,
     pub padding_top: u32,
     pub padding_left: u32,
     pub padding_right: u32,
     pub padding_bottom: u32,
     pub theme: String,
+    pub window_level: String,
+    pub pip_width: u32,
+    pub pip_height: u32,
+    pub pip_scale_factor: f32,
+    pub font_size_per_monitor: FontSizePerMonitor,
     #[cfg(target_os = "macos")]
     pub input_macos_alt_is_meta: bool,
     #[cfg(target_os = "macos")]
     pub input_macos_option_key_is_meta: OptionAsMeta,
+    #[cfg(target_os = "macos")]
+    pub input_macos_force_click: bool,
+    #[cfg(target_os = "macos")]
+    pub input_macos_force_click_threshold: f32,
+    #[cfg(target_os = "macos")]
+    pub input_macos_force_click_dead_zone: f32,
+    #[cfg(target_os = "macos")]
+    pub input_macos_scroll_smoothing: u32,
     pub input_ime: bool,
+    pub input_remote_repeat_throttle: bool,
+    pub input_remote_cursor_prediction: bool,
     pub show_border: bool,
+    pub title_template: String,
+
+    #[cfg(target_os = "linux")]
+    pub window_corner_radius: f32,
+    #[cfg(target_os = "linux")]
+    pub window_border_color: String,
+    #[cfg(target_os = "linux")]
+    pub wayland_decorations: String,
 
     #[cfg(target_os = "windows")]
     pub title_background_color: String,
     #[cfg(target_os = "windows")]
     pub title_text_color: String,
+    #[cfg(target_os = "windows")]
+    pub windows_backdrop: String,
 
     #[option = "mousemoveevent"]
     pub mouse_move_event: bool,
@@ -56,36 +97,121 @@ impl Default for WindowSettings {
             iso_layout: false,
             refresh_rate: 60,
             refresh_rate_idle: 5,
+            refresh_rate_idle_delay: 0.0,
             remember_window_size: true,
             remember_window_position: true,
+            resize_rate: 10.0,
+            resize_preview: true,
             hide_mouse_when_typing: false,
             touch_deadzone: 6.0,
             touch_drag_timeout: 0.17,
+            touch_scroll_inertia: 0.0,
+            mouse_click_interval: 0.0,
+            hot_corner_top_left: "".to_string(),
+            hot_corner_top_right: "".to_string(),
+            hot_corner_bottom_left: "".to_string(),
+            hot_corner_bottom_right: "".to_string(),
+            hot_corner_delay: 0.5,
             background_color: "".to_string(),
             confirm_quit: true,
+            unfocused_bell: "flash".to_string(),
             padding_top: 0,
             padding_left: 0,
             padding_right: 0,
             padding_bottom: 0,
             theme: "".to_string(),
+            window_level: "normal".to_string(),
+            pip_width: 480,
+            pip_height: 270,
+            pip_scale_factor: 0.75,
+            font_size_per_monitor: FontSizePerMonitor::default(),
             #[cfg(target_os = "macos")]
             input_macos_alt_is_meta: false,
             #[cfg(target_os = "macos")]
             input_macos_option_key_is_meta: OptionAsMeta::None,
+            #[cfg(target_os = "macos")]
+            input_macos_force_click: true,
+            #[cfg(target_os = "macos")]
+            input_macos_force_click_threshold: 0.0,
+            #[cfg(target_os = "macos")]
+            input_macos_force_click_dead_zone: 0.0,
+            #[cfg(target_os = "macos")]
+            input_macos_scroll_smoothing: 1,
             input_ime: true,
+            input_remote_repeat_throttle: true,
+            input_remote_cursor_prediction: false,
             mouse_move_event: false,
             observed_lines: None,
             observed_columns: None,
             show_border: false,
+            title_template: "".to_string(),
+
+            #[cfg(target_os = "linux")]
+            window_corner_radius: 0.0,
+            #[cfg(target_os = "linux")]
+            window_border_color: "".to_string(),
+            #[cfg(target_os = "linux")]
+            wayland_decorations: "".to_string(),
 
             #[cfg(target_os = "windows")]
             title_background_color: "".to_string(),
             #[cfg(target_os = "windows")]
             title_text_color: "".to_string(),
+            #[cfg(target_os = "windows")]
+            windows_backdrop: "none".to_string(),
+        }
+    }
+}
+
+/// `g:neovide_font_size_per_monitor`, the multipliers of the font size on the monitors, by their
+/// names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontSizePerMonitor(HashMap<String, f32>);
+
+impl FontSizePerMonitor {
+    /// The multiplier on `monitor`, 1 for the monitors that aren't listed.
+    pub fn multiplier(&self, monitor: Option<&str>) -> f32 {
+        monitor
+            .and_then(|monitor| self.0.get(monitor))
+            .copied()
+            .filter(|multiplier| *multiplier > 0.0)
+            .unwrap_or(1.0)
+    }
+}
+
+impl ParseFromValue for FontSizePerMonitor {
+    fn parse_from_value(&mut self, value: Value) {
+        let Some(entries) = value.as_map() else {
+            log::error!(
+                "Setting font_size_per_monitor expected a dictionary, but received {:?}",
+                value
+            );
+            return;
+        };
+        self.0.clear();
+        for (monitor, multiplier) in entries {
+            let mut size: f32 = 1.0;
+            size.parse_from_value(multiplier.clone());
+            if let Some(monitor) = monitor.as_str() {
+                self.0.insert(monitor.to_string(), size);
+            }
         }
     }
 }
 
+impl From<FontSizePerMonitor> for Value {
+    fn from(sizes: FontSizePerMonitor) -> Self {
+        let mut entries: Vec<_> = sizes.0.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(monitor, size)| (Value::from(monitor), Value::from(size)))
+                .collect(),
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg(target_os = "macos")]
 pub enum OptionAsMeta {
@@ -95,6 +221,25 @@ pub enum OptionAsMeta {
     None,
 }
 
+#[cfg(target_os = "windows")]
+impl WindowSettings {
+    /// The material of Windows 11 shown behind the window, falling back to none.
+    pub fn system_backdrop(&self) -> BackdropType {
+        match self.windows_backdrop.as_str() {
+            "mica" => BackdropType::MainWindow,
+            "acrylic" => BackdropType::TransientWindow,
+            "tabbed" => BackdropType::TabbedWindow,
+            "none" => BackdropType::None,
+            backdrop => {
+                error!(
+                    "Unknown windows backdrop {backdrop}, expected mica, acrylic, tabbed or none"
+                );
+                BackdropType::None
+            }
+        }
+    }
+}
+
 #[cfg(target_os = "macos")]
 impl ParseFromValue for OptionAsMeta {
     fn parse_from_value(&mut self, value: Value) {
@@ -0,0 +1,75 @@
+use winit::dpi::PhysicalSize;
+
+/// Rendering is suspended while the window is minimized or has no area, like when a tiling window
+/// manager shrinks it to nothing. The surface can't be resized to that, and resizing the grid to it
+/// would only reflow everything in Neovim until the window comes back.
+#[derive(Debug, Default)]
+pub struct SuspendState {
+    suspended: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SuspendTransition {
+    Suspend,
+    Resume,
+}
+
+impl SuspendState {
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Updates the state from the current window, returning the transition when it changed.
+    pub fn update(
+        &mut self,
+        minimized: bool,
+        size: PhysicalSize<u32>,
+    ) -> Option<SuspendTransition> {
+        let suspended = minimized || size.width == 0 || size.height == 0;
+        if suspended == self.suspended {
+            return None;
+        }
+        self.suspended = suspended;
+        if suspended {
+            log::debug!("Suspending the rendering, the window is minimized or empty");
+            Some(SuspendTransition::Suspend)
+        } else {
+            log::debug!("Resuming the rendering at {}x{}", size.width, size.height);
+            Some(SuspendTransition::Resume)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suspends_while_minimized_or_empty() {
+        let size = PhysicalSize::new(800, 600);
+        let mut state = SuspendState::default();
+        assert_eq!(state.update(false, size), None);
+        assert!(!state.is_suspended());
+
+        assert_eq!(state.update(true, size), Some(SuspendTransition::Suspend));
+        assert!(state.is_suspended());
+        // Some platforms also report an empty size once minimized
+        assert_eq!(state.update(true, PhysicalSize::new(0, 0)), None);
+        assert_eq!(state.update(false, size), Some(SuspendTransition::Resume));
+        assert!(!state.is_suspended());
+    }
+
+    #[test]
+    fn suspends_at_zero_height() {
+        let mut state = SuspendState::default();
+        assert_eq!(
+            state.update(false, PhysicalSize::new(800, 0)),
+            Some(SuspendTransition::Suspend)
+        );
+        assert_eq!(state.update(false, PhysicalSize::new(800, 0)), None);
+        assert_eq!(
+            state.update(false, PhysicalSize::new(800, 20)),
+            Some(SuspendTransition::Resume)
+        );
+    }
+}
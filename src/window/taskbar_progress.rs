@@ -0,0 +1,172 @@
+//! The progress of long jobs, posted by plugins with `neovide.progress`, shown on the icon of
+//! Neovide in the taskbar or the dock, so that it stays visible while the window is minimized or
+//! behind others.
+
+use rmpv::Value;
+use winit::window::Window;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskbarProgress {
+    Hidden,
+    /// A job is running, without knowing how far it is.
+    Indeterminate,
+    /// The fraction of the job that's done, from 0 to 1.
+    Normal(f32),
+    Paused(f32),
+    Error(f32),
+}
+
+impl TaskbarProgress {
+    /// Reads the `{percent, state}` dictionary sent by Neovim. Nil and the `none` state hide the
+    /// progress, and a missing percent makes it indeterminate.
+    pub fn parse(value: &Value) -> Option<Self> {
+        if value.is_nil() {
+            return Some(TaskbarProgress::Hidden);
+        }
+        let mut percent = None;
+        let mut state = "normal";
+        for (key, value) in value.as_map()? {
+            match (key.as_str()?, value) {
+                ("percent", Value::Integer(integer)) => percent = integer.as_f64(),
+                ("percent", Value::F32(float)) => percent = Some(*float as f64),
+                ("percent", Value::F64(float)) => percent = Some(*float),
+                ("state", Value::String(name)) => state = name.as_str()?,
+                _ => {}
+            }
+        }
+        let fraction = percent.map(|percent| (percent / 100.0).clamp(0.0, 1.0) as f32);
+        Some(match (state, fraction) {
+            ("none", _) => TaskbarProgress::Hidden,
+            ("indeterminate", _) | (_, None) => TaskbarProgress::Indeterminate,
+            ("paused", Some(fraction)) => TaskbarProgress::Paused(fraction),
+            ("error", Some(fraction)) => TaskbarProgress::Error(fraction),
+            (_, Some(fraction)) => TaskbarProgress::Normal(fraction),
+        })
+    }
+
+    /// The fraction that's done, when there's one to show.
+    pub fn fraction(self) -> Option<f32> {
+        match self {
+            TaskbarProgress::Normal(fraction)
+            | TaskbarProgress::Paused(fraction)
+            | TaskbarProgress::Error(fraction) => Some(fraction),
+            TaskbarProgress::Hidden | TaskbarProgress::Indeterminate => None,
+        }
+    }
+}
+
+pub fn show_taskbar_progress(window: &Window, progress: TaskbarProgress) {
+    #[cfg(target_os = "linux")]
+    {
+        let _ = window;
+        linux::show_taskbar_progress(progress);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = window;
+        super::macos::show_dock_progress(progress);
+    }
+    #[cfg(target_os = "windows")]
+    crate::windows_utils::show_taskbar_progress(window, progress);
+}
+
+/// The `com.canonical.Unity.LauncherEntry` signals, which the docks and task managers of KDE,
+/// Ubuntu and others read the progress of the applications from.
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{collections::HashMap, sync::OnceLock};
+
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+    use zbus::{zvariant::Value, Connection};
+
+    use super::TaskbarProgress;
+    use crate::window::session_bus::{self, with_timeout};
+
+    const LAUNCHER_ENTRY: &str = "com.canonical.Unity.LauncherEntry";
+    const LAUNCHER_ENTRY_PATH: &str = "/com/canonical/unity/launcherentry/neovide";
+    const APP_URI: &str = "application://neovide.desktop";
+
+    static SENDER: OnceLock<UnboundedSender<TaskbarProgress>> = OnceLock::new();
+
+    async fn send_updates(
+        connection: Connection,
+        mut receiver: UnboundedReceiver<TaskbarProgress>,
+    ) -> zbus::Result<()> {
+        while let Some(progress) = receiver.recv().await {
+            let mut properties = HashMap::new();
+            properties.insert(
+                "progress",
+                Value::from(progress.fraction().unwrap_or_default() as f64),
+            );
+            // The launchers can't show an indeterminate progress, so they show none
+            properties.insert(
+                "progress-visible",
+                Value::from(progress.fraction().is_some()),
+            );
+            properties.insert(
+                "urgent",
+                Value::from(matches!(progress, TaskbarProgress::Error(_))),
+            );
+            with_timeout(connection.emit_signal(
+                None::<&str>,
+                LAUNCHER_ENTRY_PATH,
+                LAUNCHER_ENTRY,
+                "Update",
+                &(APP_URI, properties),
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Starts sending the updates in order, on the connection to the session bus.
+    fn start() -> UnboundedSender<TaskbarProgress> {
+        let (sender, receiver) = unbounded_channel();
+        session_bus::spawn("taskbar progress", move |connection| {
+            send_updates(connection, receiver)
+        });
+        sender
+    }
+
+    pub fn show_taskbar_progress(progress: TaskbarProgress) {
+        let _ = SENDER.get_or_init(start).send(progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_progress() {
+        let progress = |entries: Vec<(&str, Value)>| {
+            TaskbarProgress::parse(&Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (Value::from(key), value))
+                    .collect(),
+            ))
+        };
+        assert_eq!(
+            progress(vec![("percent", Value::from(42))]),
+            Some(TaskbarProgress::Normal(0.42))
+        );
+        assert_eq!(
+            progress(vec![
+                ("percent", Value::from(150.0)),
+                ("state", Value::from("error"))
+            ]),
+            Some(TaskbarProgress::Error(1.0))
+        );
+        assert_eq!(progress(vec![]), Some(TaskbarProgress::Indeterminate));
+        assert_eq!(
+            progress(vec![("state", Value::from("none"))]),
+            Some(TaskbarProgress::Hidden)
+        );
+        assert_eq!(
+            TaskbarProgress::parse(&Value::Nil),
+            Some(TaskbarProgress::Hidden)
+        );
+        assert_eq!(TaskbarProgress::parse(&Value::from("42")), None);
+    }
+}
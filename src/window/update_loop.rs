@@ -13,7 +13,8 @@ use super::{save_window_size, CmdLineSettings, UserEvent, WindowSettings, WinitW
 use crate::{
     profiling::{tracy_plot, tracy_zone},
     renderer::DrawCommand,
-    settings::Settings,
+    running_tracker::RunningTracker,
+    settings::{CachedSettings, Settings},
     FontSettings, WindowSize,
 };
 
@@ -73,6 +74,8 @@ impl ShouldRender {
 }
 
 const MAX_ANIMATION_DT: f64 = 1.0 / 120.0;
+/// How long the window has to stay still before its size and position are saved.
+const SAVE_WINDOW_DELAY: Duration = Duration::from_secs(1);
 
 pub struct UpdateLoop {
     idle: bool,
@@ -85,11 +88,17 @@ pub struct UpdateLoop {
     pending_draw_commands: Vec<Vec<DrawCommand>>,
     animation_start: Instant, // When the last animation started (went from idle to animating)
     animation_time: Duration, // How long the current animation has been simulated, will usually be in the future
+    // When to save the window after it was moved or resized, so that it's not lost if Neovide is
+    // killed
+    save_window_deadline: Option<Instant>,
+    // The last input or draw command, after which the frame rate drops to `refresh_rate_idle`
+    last_activity: Instant,
 
     window_wrapper: WinitWindowWrapper,
     create_window_allowed: bool,
     proxy: EventLoopProxy<UserEvent>,
 
+    window_settings: CachedSettings<WindowSettings>,
     settings: Arc<Settings>,
 }
 
@@ -98,6 +107,7 @@ impl UpdateLoop {
         initial_window_size: WindowSize,
         initial_font_settings: Option<FontSettings>,
         proxy: EventLoopProxy<UserEvent>,
+        running_tracker: RunningTracker,
         settings: Arc<Settings>,
     ) -> Self {
         let previous_frame_start = Instant::now();
@@ -113,8 +123,12 @@ impl UpdateLoop {
         let cmd_line_settings = settings.get::<CmdLineSettings>();
         let idle = cmd_line_settings.idle;
 
-        let window_wrapper =
-            WinitWindowWrapper::new(initial_window_size, initial_font_settings, settings.clone());
+        let window_wrapper = WinitWindowWrapper::new(
+            initial_window_size,
+            initial_font_settings,
+            running_tracker,
+            settings.clone(),
+        );
 
         Self {
             idle,
@@ -127,11 +141,14 @@ impl UpdateLoop {
             pending_draw_commands,
             animation_start,
             animation_time,
+            save_window_deadline: None,
+            last_activity: Instant::now(),
 
             window_wrapper,
             create_window_allowed: false,
             proxy,
 
+            window_settings: CachedSettings::new(settings.clone()),
             settings,
         }
     }
@@ -140,14 +157,37 @@ impl UpdateLoop {
         match self.focused {
             // NOTE: Always wait for the idle refresh rate when winit throttling is used to avoid waking up too early
             // The winit redraw request will likely happen much before that and wake it up anyway
-            FocusedState::Focused | FocusedState::UnfocusedNotDrawn => {
-                self.settings.get::<WindowSettings>().refresh_rate as f32
+            FocusedState::Focused | FocusedState::UnfocusedNotDrawn if !self.is_inactive() => {
+                self.window_settings.get().refresh_rate as f32
             }
-            _ => self.settings.get::<WindowSettings>().refresh_rate_idle as f32,
+            _ => self.window_settings.get().refresh_rate_idle as f32,
         }
         .max(1.0)
     }
 
+    /// If there was no input and nothing to draw for `refresh_rate_idle_delay` seconds, which
+    /// drops the refresh rate to `refresh_rate_idle` even while the window is focused.
+    fn is_inactive(&self) -> bool {
+        let delay = self.window_settings.get().refresh_rate_idle_delay;
+        delay > 0.0 && self.last_activity.elapsed().as_secs_f32() >= delay
+    }
+
+    /// The shortest time between frames once inactive, which also holds with VSync, as that wakes
+    /// the GPU for every frame of the monitor otherwise.
+    ///
+    /// While active there's deliberately no cap with VSync. `refresh_rate` defaults to 60 and has
+    /// always applied only without VSync, so capping it there would halve the frame rate of every
+    /// faster monitor. Users wanting fewer frames can use `--no-vsync` with `refresh_rate`.
+    fn min_frame_time(&self) -> Option<Duration> {
+        self.is_inactive()
+            .then(|| Duration::from_secs_f32(1.0 / self.get_refresh_rate()))
+    }
+
+    fn is_frame_rate_limited(&self) -> bool {
+        self.min_frame_time()
+            .is_some_and(|min_frame_time| self.previous_frame_start.elapsed() < min_frame_time)
+    }
+
     fn get_frame_deadline(&self) -> Instant {
         let refresh_rate = self.get_refresh_rate();
         let expected_frame_duration = Duration::from_secs_f32(1.0 / refresh_rate);
@@ -160,10 +200,25 @@ impl UpdateLoop {
             return self.animation_start + self.animation_time;
         }
 
-        match self.should_render {
+        let mut deadline = match self.should_render {
             ShouldRender::Immediately => Instant::now(),
             ShouldRender::Deadline(old_deadline) => old_deadline.min(self.get_frame_deadline()),
             _ => self.get_frame_deadline(),
+        };
+        if let Some(min_frame_time) = self.min_frame_time() {
+            deadline = deadline.max(self.previous_frame_start + min_frame_time);
+        }
+        self.save_window_deadline
+            .map_or(deadline, |save_deadline| save_deadline.min(deadline))
+    }
+
+    fn save_window_when_still(&mut self) {
+        if self
+            .save_window_deadline
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            self.save_window_deadline = None;
+            save_window_size(&self.window_wrapper, &self.settings);
         }
     }
 
@@ -220,7 +275,9 @@ impl UpdateLoop {
     fn render(&mut self) {
         self.pending_render = false;
         tracy_plot!("pending_render", self.pending_render as u8 as f64);
-        self.window_wrapper.draw_frame(self.last_dt);
+        // The last two frames were both part of the current run of frames
+        let continuous = self.num_consecutive_rendered >= 2;
+        self.window_wrapper.draw_frame(self.last_dt, continuous);
 
         if let FocusedState::UnfocusedNotDrawn = self.focused {
             self.focused = FocusedState::Unfocused;
@@ -291,6 +348,10 @@ impl UpdateLoop {
 
         let res = self.window_wrapper.prepare_frame();
         self.should_render.update(res);
+        // Wait for the next frame allowed by the frame rate limits, without losing the render
+        if self.is_frame_rate_limited() {
+            return;
+        }
 
         let should_animate =
             self.should_render == ShouldRender::Immediately || !self.idle || skipped_frame;
@@ -336,6 +397,19 @@ impl ApplicationHandler<UserEvent> for UpdateLoop {
             WindowEvent::RedrawRequested => {
                 self.redraw_requested();
             }
+            WindowEvent::KeyboardInput { .. }
+            | WindowEvent::Ime(_)
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::Touch(_) => {
+                self.last_activity = Instant::now();
+            }
+            WindowEvent::Moved(_) | WindowEvent::Resized(_)
+                if self.window_wrapper.skia_renderer.is_some() =>
+            {
+                self.save_window_deadline = Some(Instant::now() + SAVE_WINDOW_DELAY);
+            }
             WindowEvent::Focused(focused_event) => {
                 self.focused = if focused_event {
                     FocusedState::Focused
@@ -360,8 +434,12 @@ impl ApplicationHandler<UserEvent> for UpdateLoop {
 
     fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
         tracy_zone!("user_event");
+        if matches!(event, UserEvent::DrawCommandBatch(_)) {
+            self.last_activity = Instant::now();
+        }
         match event {
             UserEvent::NeovimExited => {
+                self.save_window_deadline = None;
                 save_window_size(&self.window_wrapper, &self.settings);
                 event_loop.exit();
             }
@@ -384,6 +462,7 @@ impl ApplicationHandler<UserEvent> for UpdateLoop {
 
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         tracy_zone!("about_to_wait");
+        self.save_window_when_still();
         self.prepare_and_animate();
         self.schedule_next_event(event_loop);
     }
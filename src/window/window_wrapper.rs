@@ -1,15 +1,24 @@
-use std::sync::Arc;
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use log::trace;
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
 use winit::{
     dpi,
-    event::{Ime, WindowEvent},
+    event::{ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent},
     event_loop::{ActiveEventLoop, EventLoopProxy},
-    window::{Fullscreen, Theme},
+    keyboard::{Key, NamedKey},
+    window::{Fullscreen, Theme, WindowLevel},
 };
 
 use super::{
+    accessibility::Accessibility,
+    drag_and_drop::{dropped_file_path, register_drop_target, DragEvent},
+    pip::PictureInPicture,
+    zoom::ZoomAnimation,
     KeyboardManager, MouseManager, UserEvent, WindowCommand, WindowSettings, WindowSettingsChanged,
 };
 
@@ -19,30 +28,114 @@ use {
     winit::platform::macos::{self, WindowExtMacOS},
 };
 
+#[cfg(feature = "profiling")]
+use crate::renderer::gpu_cache::plot_gpu_cache;
 use crate::{
     bridge::{send_ui, ParallelCommand, SerialCommand},
+    clipboard, ipc, logging,
     profiling::{tracy_frame, tracy_gpu_collect, tracy_gpu_zone, tracy_plot, tracy_zone},
     renderer::{
-        create_skia_renderer, DrawCommand, Renderer, RendererSettingsChanged, SkiaRenderer, VSync,
+        animation_utils::set_motion_reduced,
+        clipboard_history::ClipboardHistoryAction,
+        cmdline_renderer::CommandLineSettingsChanged,
+        create_skia_renderer,
+        exit_prompt::answer_for_key,
+        file_changed::FileChangeAction,
+        gpu_cache::{gpu_cache_usage, set_gpu_cache_limit},
+        help::HelpAction,
+        messages_renderer::MessagesSettingsChanged,
+        overlay::take_pending_layers,
+        popupmenu::PopupMenuSettingsChanged,
+        tabline::TablineSettingsChanged,
+        tour::TourAction,
+        wildmenu::WildmenuSettingsChanged,
+        DrawCommand, Renderer, RendererSettings, RendererSettingsChanged, SkiaRenderer, VSync,
     },
+    running_tracker::RunningTracker,
     settings::{
-        clamped_grid_size, FontSettings, HotReloadConfigs, Settings, SettingsChanged,
-        DEFAULT_GRID_SIZE, MIN_GRID_SIZE,
+        clamped_grid_size, config_settings, FontSettings, HotReloadConfigs, Settings,
+        SettingsChanged, DEFAULT_GRID_SIZE, MIN_GRID_SIZE,
     },
-    units::{GridRect, GridSize, PixelPos, PixelSize},
+    units::{GridRect, GridScale, GridSize, PixelPos, PixelSize},
     window::{create_window, PhysicalSize, ShouldRender, WindowSize},
     CmdLineSettings,
 };
 #[cfg(windows)]
 use {
-    crate::windows_utils::{register_right_click, unregister_right_click},
+    crate::{
+        utils::is_light_color,
+        windows_utils::{
+            register_right_click, register_url_handler, set_proxy_file, unregister_right_click,
+            unregister_url_handler,
+        },
+    },
+    skia_safe::Color4f,
     winit::platform::windows::{Color, WindowExtWindows},
 };
 
 #[cfg(target_os = "macos")]
 use super::macos::MacosWindowFeature;
+use super::{
+    bell::{ring_unfocused_bell, BellAttention},
+    mouse_manager::drop_position,
+    os_notify::show_notification,
+    resize_throttle::{preview_scale, ResizeThrottle},
+    suspend::{SuspendState, SuspendTransition},
+    taskbar_progress::show_taskbar_progress,
+};
+
+use super::{blur::update_blur, opaque_region::update_opaque_region};
+#[cfg(target_os = "linux")]
+use {
+    super::dbus_menu::export_menu,
+    crate::frame::{window_frame, Frame},
+};
 
 const GRID_TOLERANCE: f32 = 1e-3;
+/// How long after the last change of the window size the grid is stretched to fill it.
+const RESIZE_PREVIEW_DURATION: Duration = Duration::from_secs(1);
+
+fn send_resize(grid_size: GridSize<u32>) {
+    send_ui(ParallelCommand::Resize {
+        width: grid_size.width.into(),
+        height: grid_size.height.into(),
+    });
+}
+
+/// Parses `g:neovide_window_level`, falling back to a normal window.
+fn parse_window_level(level: &str) -> WindowLevel {
+    match level {
+        "always_on_top" => WindowLevel::AlwaysOnTop,
+        "always_on_bottom" => WindowLevel::AlwaysOnBottom,
+        "normal" => WindowLevel::Normal,
+        _ => {
+            log::warn!(
+                "Unknown window level {level}, expected normal, always_on_top or always_on_bottom"
+            );
+            WindowLevel::Normal
+        }
+    }
+}
+
+/// The area the IME popup is kept clear of, in physical pixels, for a cursor at `position`. The
+/// cursor can be above or left of the window while it animates, and with fractional scaling its
+/// position is fractional, so it's rounded down and kept inside the window.
+fn ime_cursor_area(
+    position: PixelPos<f32>,
+    grid_scale: GridScale,
+) -> (dpi::PhysicalPosition<u32>, dpi::PhysicalSize<u32>) {
+    let font_dimensions = GridSize::new(1.0, 1.0) * grid_scale;
+    let position = dpi::PhysicalPosition::new(
+        position.x.max(0.0).floor() as u32,
+        position.y.max(0.0).floor() as u32,
+    );
+    // NOTE: some compositors don't like excluding too much and try to render popup at the
+    // bottom right corner of the provided area, so exclude just the full-width char to not
+    // obscure the cursor and not render popup at the end of the window.
+    let width = (font_dimensions.width * 2.0).ceil() as u32;
+    let height = font_dimensions.height.ceil() as u32;
+    (position, dpi::PhysicalSize::new(width, height))
+}
 
 fn round_or_op<Op: FnOnce(f32) -> f32>(v: f32, op: Op) -> f32 {
     let rounded = v.round();
@@ -83,8 +176,13 @@ pub struct WinitWindowWrapper {
     keyboard_manager: KeyboardManager,
     mouse_manager: MouseManager,
     title: String,
+    represented_file: Option<PathBuf>,
     font_changed_last_frame: bool,
     saved_inner_size: dpi::PhysicalSize<u32>,
+    /// Limits the resizes sent while the window is dragged, and when it was last resized, to
+    /// stretch the grid until Neovim catches up.
+    resize_throttle: ResizeThrottle,
+    last_window_resize: Option<Instant>,
     saved_grid_size: Option<GridSize<u32>>,
     requested_columns: Option<u32>,
     requested_lines: Option<u32>,
@@ -92,12 +190,20 @@ pub struct WinitWindowWrapper {
     window_padding: WindowPadding,
     initial_window_size: WindowSize,
     is_minimized: bool,
+    suspend_state: SuspendState,
     ime_enabled: bool,
     ime_area: (dpi::PhysicalPosition<u32>, dpi::PhysicalSize<u32>),
+    zoom: Option<ZoomAnimation>,
+    pip: PictureInPicture,
     pub vsync: Option<VSync>,
+    proxy: Option<EventLoopProxy<UserEvent>>,
+    accessibility: Accessibility,
     #[cfg(target_os = "macos")]
     pub macos_feature: Option<MacosWindowFeature>,
+    #[cfg(windows)]
+    default_background: Option<Color4f>,
 
+    running_tracker: RunningTracker,
     settings: Arc<Settings>,
 }
 
@@ -105,6 +211,7 @@ impl WinitWindowWrapper {
     pub fn new(
         initial_window_size: WindowSize,
         initial_font_settings: Option<FontSettings>,
+        running_tracker: RunningTracker,
         settings: Arc<Settings>,
     ) -> Self {
         let saved_inner_size = Default::default();
@@ -116,8 +223,11 @@ impl WinitWindowWrapper {
             keyboard_manager: KeyboardManager::new(settings.clone()),
             mouse_manager: MouseManager::new(settings.clone()),
             title: String::from("Neovide"),
+            represented_file: None,
             font_changed_last_frame: false,
             saved_inner_size,
+            resize_throttle: ResizeThrottle::default(),
+            last_window_resize: None,
             saved_grid_size: None,
             requested_columns: None,
             requested_lines: None,
@@ -130,11 +240,19 @@ impl WinitWindowWrapper {
             },
             initial_window_size,
             is_minimized: false,
+            suspend_state: SuspendState::default(),
             vsync: None,
+            proxy: None,
+            accessibility: Accessibility::default(),
             ime_enabled: false,
             ime_area: Default::default(),
+            zoom: None,
+            pip: PictureInPicture::new(),
             #[cfg(target_os = "macos")]
             macos_feature: None,
+            #[cfg(windows)]
+            default_background: None,
+            running_tracker,
             settings,
         }
     }
@@ -191,27 +309,323 @@ impl WinitWindowWrapper {
         tracy_zone!("handle_window_commands", 0);
         match command {
             WindowCommand::TitleChanged(new_title) => self.handle_title_changed(new_title),
+            WindowCommand::RepresentedFileChanged(file) => {
+                self.represented_file = file;
+                self.apply_represented_file();
+            }
             WindowCommand::SetMouseEnabled(mouse_enabled) => {
                 self.mouse_manager.enabled = mouse_enabled
             }
+            WindowCommand::HelpBufferChanged(help_buffer) => {
+                self.mouse_manager.help_buffer = help_buffer
+            }
             WindowCommand::ListAvailableFonts => self.send_font_names(),
             WindowCommand::FocusWindow => {
                 if let Some(skia_renderer) = &self.skia_renderer {
                     skia_renderer.window().focus_window();
                 }
             }
+            WindowCommand::OsNotify(notification) => {
+                if let (Some(skia_renderer), Some(proxy)) = (&self.skia_renderer, &self.proxy) {
+                    if !skia_renderer.window().has_focus() {
+                        show_notification(notification, proxy.clone());
+                    }
+                }
+            }
+            WindowCommand::Bell { visual } => {
+                self.renderer.bell_renderer.ring(visual);
+                if let Some(skia_renderer) = &self.skia_renderer {
+                    let window = skia_renderer.window();
+                    if !window.has_focus() {
+                        let setting = self.settings.get::<WindowSettings>().unfocused_bell;
+                        ring_unfocused_bell(window, BellAttention::parse(&setting));
+                    }
+                }
+            }
+            WindowCommand::ShowProgressBar(progress) => {
+                if let Some(skia_renderer) = &self.skia_renderer {
+                    show_taskbar_progress(skia_renderer.window(), progress);
+                }
+            }
             WindowCommand::Minimize => {
                 self.minimize_window();
                 self.is_minimized = true;
             }
+            WindowCommand::ToggleFullscreen => {
+                if let Some(skia_renderer) = &self.skia_renderer {
+                    let fullscreen = skia_renderer.window().fullscreen().is_some();
+                    self.set_fullscreen(!fullscreen);
+                }
+            }
+            WindowCommand::TogglePictureInPicture => self.toggle_picture_in_picture(),
+            WindowCommand::RunCommand(command) => send_ui(ParallelCommand::Command(command)),
+            WindowCommand::ShowTour => self.renderer.tour_renderer.start(),
+            WindowCommand::ShowHelp(topic) => self.renderer.help_renderer.open(&topic),
+            WindowCommand::ShowClipboardHistory { entries, query } => self
+                .renderer
+                .clipboard_history_renderer
+                .open(entries, &query),
+            WindowCommand::ShowInfo => self.show_info(),
+            WindowCommand::FileChanged(change) => self.renderer.file_changed_renderer.push(change),
+            WindowCommand::ResolveFileChange(action) => {
+                let buffer = self.renderer.file_changed_renderer.answer();
+                if let Some(buffer) = buffer.filter(|_| action != FileChangeAction::Keep) {
+                    send_ui(ParallelCommand::ResolveFileChange {
+                        buffer,
+                        action: action.name().to_string(),
+                    });
+                }
+            }
+            WindowCommand::MultigridWarning(anomalies) => {
+                self.renderer.multigrid_warning_renderer.push(anomalies)
+            }
+            WindowCommand::DismissMultigridWarning => {
+                self.renderer.multigrid_warning_renderer.dismiss()
+            }
+            WindowCommand::AnswerExitPrompt(restart) => self.answer_exit_prompt(restart),
+            WindowCommand::Drag(event) => self.handle_drag_event(event),
+            WindowCommand::PastePrimarySelection => match clipboard::get_contents("*") {
+                Ok(text) => send_ui(ParallelCommand::Paste(text)),
+                Err(error) => log::error!("Could not get the primary selection: {error}"),
+            },
+            WindowCommand::DrawOverlays => {
+                self.renderer.overlay_renderer.update(take_pending_layers())
+            }
             WindowCommand::ThemeChanged(new_theme) => {
                 self.handle_theme_changed(new_theme);
             }
             #[cfg(windows)]
+            WindowCommand::DefaultBackgroundChanged(background) => {
+                self.default_background = background;
+                self.handle_title_colors();
+            }
+            #[cfg(windows)]
             WindowCommand::RegisterRightClick => register_right_click(),
             #[cfg(windows)]
             WindowCommand::UnregisterRightClick => unregister_right_click(),
+            #[cfg(windows)]
+            WindowCommand::RegisterUrlHandler => register_url_handler(),
+            #[cfg(windows)]
+            WindowCommand::UnregisterUrlHandler => unregister_url_handler(),
+        }
+    }
+
+    pub fn is_picture_in_picture(&self) -> bool {
+        self.pip.is_active()
+    }
+
+    fn toggle_picture_in_picture(&mut self) {
+        let Some(skia_renderer) = &self.skia_renderer else {
+            return;
+        };
+        let window = skia_renderer.window();
+        let window_settings = self.settings.get::<WindowSettings>();
+        if self.pip.is_active() {
+            self.pip
+                .leave(window, parse_window_level(&window_settings.window_level));
+        } else {
+            self.pip.enter(window, &window_settings);
+        }
+        self.renderer.user_scale_factor = self.pip.user_scale_factor(&window_settings);
+        self.renderer.handle_scale_factor_update();
+        self.font_changed_last_frame = true;
+    }
+
+    /// Browses the tour with the keyboard and the mouse. Returns `true` when the event was used.
+    fn handle_tour_event(&mut self, event: &WindowEvent) -> bool {
+        let action = match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key,
+                        ..
+                    },
+                ..
+            } => match logical_key.as_ref() {
+                Key::Named(NamedKey::Enter | NamedKey::ArrowRight | NamedKey::Space)
+                | Key::Character("l" | "n") => Some(TourAction::Next),
+                Key::Named(NamedKey::ArrowLeft | NamedKey::Backspace)
+                | Key::Character("h" | "p") => Some(TourAction::Previous),
+                Key::Named(NamedKey::Escape) | Key::Character("q") => Some(TourAction::Close),
+                _ => None,
+            },
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => Some(TourAction::Next),
+            WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. } => None,
+            _ => return false,
+        };
+        if let Some(action) = action {
+            self.renderer.tour_renderer.navigate(action);
+        }
+        true
+    }
+
+    /// Scrolls and browses the help with the keyboard and the mouse wheel. Returns `true` when the
+    /// event was used.
+    fn handle_help_event(&mut self, event: &WindowEvent) -> bool {
+        let searching = self.renderer.help_renderer.is_searching();
+        let action = match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key,
+                        text,
+                        ..
+                    },
+                ..
+            } if searching => match logical_key.as_ref() {
+                Key::Named(NamedKey::Enter) => Some(HelpAction::Follow),
+                Key::Named(NamedKey::Backspace) => Some(HelpAction::Erase),
+                Key::Named(NamedKey::Escape) => Some(HelpAction::Close),
+                _ => text
+                    .as_ref()
+                    .filter(|text| !text.chars().any(char::is_control))
+                    .map(|text| HelpAction::Type(text.to_string())),
+            },
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key,
+                        ..
+                    },
+                ..
+            } => match logical_key.as_ref() {
+                Key::Named(NamedKey::ArrowDown) | Key::Character("j") => {
+                    Some(HelpAction::Scroll(1))
+                }
+                Key::Named(NamedKey::ArrowUp) | Key::Character("k") => Some(HelpAction::Scroll(-1)),
+                Key::Named(NamedKey::PageDown | NamedKey::Space) => Some(HelpAction::ScrollPage(1)),
+                Key::Named(NamedKey::PageUp) => Some(HelpAction::ScrollPage(-1)),
+                Key::Named(NamedKey::Tab) | Key::Character("n") => Some(HelpAction::NextLink),
+                Key::Character("N" | "p") => Some(HelpAction::PreviousLink),
+                Key::Named(NamedKey::Enter) => Some(HelpAction::Follow),
+                Key::Named(NamedKey::ArrowLeft | NamedKey::Backspace) | Key::Character("h") => {
+                    Some(HelpAction::Back)
+                }
+                Key::Character("/") => Some(HelpAction::Search),
+                Key::Named(NamedKey::Escape) | Key::Character("q") => Some(HelpAction::Close),
+                _ => None,
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => *lines,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        position.y as f32 / self.renderer.grid_renderer.grid_scale.height()
+                    }
+                };
+                Some(HelpAction::Scroll(-lines.round() as isize))
+            }
+            WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. } => None,
+            _ => return false,
+        };
+        if let Some(action) = action {
+            self.renderer.help_renderer.navigate(action);
+        }
+        true
+    }
+
+    /// Handles what the drag and drop API of the platform tells about drags, on top of winit.
+    fn handle_drag_event(&mut self, event: DragEvent) {
+        match event {
+            DragEvent::Hovered => self.renderer.drop_preview = true,
+            DragEvent::Left => self.renderer.drop_preview = false,
+            DragEvent::Files(paths) => {
+                self.renderer.drop_preview = false;
+                for path in paths.into_iter().filter_map(dropped_file_path) {
+                    send_ui(ParallelCommand::FileDrop(path));
+                }
+            }
+            DragEvent::Text { text, position } => {
+                self.renderer.drop_preview = false;
+                let position = drop_position(&self.renderer, position);
+                send_ui(ParallelCommand::TextDrop(text, position));
+            }
+        }
+    }
+
+    /// Searches the clipboard history and picks the entry to paste with the keyboard and the mouse
+    /// wheel. Returns `true` when the event was used.
+    fn handle_clipboard_history_event(&mut self, event: &WindowEvent) -> bool {
+        let control = self.keyboard_manager.modifiers().control_key();
+        let action = match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key,
+                        text,
+                        ..
+                    },
+                ..
+            } => match logical_key.as_ref() {
+                Key::Named(NamedKey::ArrowDown | NamedKey::Tab) => {
+                    Some(ClipboardHistoryAction::Next)
+                }
+                Key::Named(NamedKey::ArrowUp) => Some(ClipboardHistoryAction::Previous),
+                Key::Character("n") if control => Some(ClipboardHistoryAction::Next),
+                Key::Character("p") if control => Some(ClipboardHistoryAction::Previous),
+                Key::Named(NamedKey::Enter) => Some(ClipboardHistoryAction::Paste),
+                Key::Named(NamedKey::Backspace) => Some(ClipboardHistoryAction::Erase),
+                Key::Named(NamedKey::Escape) => Some(ClipboardHistoryAction::Close),
+                _ => text
+                    .as_ref()
+                    .filter(|text| !control && !text.chars().any(char::is_control))
+                    .map(|text| ClipboardHistoryAction::Type(text.to_string())),
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => *lines,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32,
+                };
+                match lines {
+                    lines if lines > 0.0 => Some(ClipboardHistoryAction::Previous),
+                    lines if lines < 0.0 => Some(ClipboardHistoryAction::Next),
+                    _ => None,
+                }
+            }
+            WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. } => None,
+            _ => return false,
+        };
+        let entry =
+            action.and_then(|action| self.renderer.clipboard_history_renderer.navigate(action));
+        if let Some(entry) = entry {
+            send_ui(ParallelCommand::Put(entry));
         }
+        true
+    }
+
+    /// Restarts Neovim or closes the window with the keyboard, after Neovim quit with an error.
+    /// The clicks are left to the mouse manager. Returns `true` when the event was used.
+    fn handle_exit_prompt_event(&mut self, event: &WindowEvent) -> bool {
+        let restart = match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key,
+                        ..
+                    },
+                ..
+            } => answer_for_key(logical_key.as_ref()),
+            WindowEvent::CloseRequested => Some(false),
+            WindowEvent::KeyboardInput { .. } => None,
+            _ => return false,
+        };
+        if let Some(restart) = restart {
+            self.answer_exit_prompt(restart);
+        }
+        true
+    }
+
+    fn answer_exit_prompt(&mut self, restart: bool) {
+        self.renderer.exit_prompt_renderer.hide();
+        self.running_tracker.answer_exit_prompt(restart);
     }
 
     pub fn handle_window_settings_changed(&mut self, changed_setting: WindowSettingsChanged) {
@@ -231,31 +645,64 @@ impl WinitWindowWrapper {
             WindowSettingsChanged::InputIme(ime_enabled) => {
                 self.set_ime(ime_enabled);
             }
-            WindowSettingsChanged::ScaleFactor(user_scale_factor) => {
+            WindowSettingsChanged::FontSizePerMonitor(..) => {
+                self.renderer.handle_scale_factor_update();
+                self.font_changed_last_frame = true;
+            }
+            WindowSettingsChanged::ScaleFactor(..) | WindowSettingsChanged::PipScaleFactor(..) => {
+                let window_settings = self.settings.get::<WindowSettings>();
                 let renderer = &mut self.renderer;
-                renderer.user_scale_factor = user_scale_factor.into();
-                renderer.grid_renderer.handle_scale_factor_update(
-                    renderer.os_scale_factor * renderer.user_scale_factor,
-                );
+                renderer.user_scale_factor = self.pip.user_scale_factor(&window_settings);
+                renderer.handle_scale_factor_update();
                 self.font_changed_last_frame = true;
             }
-            WindowSettingsChanged::WindowBlurred(blur) => {
-                if let Some(skia_renderer) = &self.skia_renderer {
-                    let WindowSettings { transparency, .. } = self.settings.get::<WindowSettings>();
-                    let transparent = transparency < 1.0;
-                    skia_renderer.window().set_blur(blur && transparent);
+            WindowSettingsChanged::WindowBlurred(..) => {
+                self.update_blur();
+            }
+            WindowSettingsChanged::WindowLevel(level) => {
+                // Picture in picture stays on top, the level is applied once it's left
+                if let Some(skia_renderer) = self
+                    .skia_renderer
+                    .as_ref()
+                    .filter(|_| !self.pip.is_active())
+                {
+                    skia_renderer
+                        .window()
+                        .set_window_level(parse_window_level(&level));
                 }
             }
             WindowSettingsChanged::Transparency(..) | WindowSettingsChanged::NormalOpacity(..) => {
                 self.renderer.prepare_lines(true);
+                self.update_opaque_region();
+                self.update_blur();
+            }
+            #[cfg(target_os = "linux")]
+            WindowSettingsChanged::WindowCornerRadius(..) => {
+                self.update_opaque_region();
+                self.update_blur();
+            }
+            #[cfg(target_os = "linux")]
+            WindowSettingsChanged::WaylandDecorations(..) => {
+                if let Some(skia_renderer) = &self.skia_renderer {
+                    let frame = window_frame(&self.settings);
+                    skia_renderer.window().set_decorations(frame == Frame::Full);
+                }
+                // The custom title bar takes some lines
+                self.font_changed_last_frame = true;
+                self.update_opaque_region();
+                self.update_blur();
             }
             #[cfg(target_os = "windows")]
-            WindowSettingsChanged::TitleBackgroundColor(color) => {
-                self.handle_title_background_color(&color);
+            WindowSettingsChanged::TitleBackgroundColor(..)
+            | WindowSettingsChanged::TitleTextColor(..) => {
+                self.handle_title_colors();
             }
             #[cfg(target_os = "windows")]
-            WindowSettingsChanged::TitleTextColor(color) => {
-                self.handle_title_text_color(&color);
+            WindowSettingsChanged::WindowsBackdrop(..) => {
+                if let Some(skia_renderer) = &self.skia_renderer {
+                    let backdrop = self.settings.get::<WindowSettings>().system_backdrop();
+                    skia_renderer.window().set_system_backdrop(backdrop);
+                }
             }
 
             #[cfg(target_os = "macos")]
@@ -288,17 +735,174 @@ impl WinitWindowWrapper {
                 }
                 self.font_changed_last_frame = true;
             }
+            RendererSettingsChanged::TerminalFont(..) => {
+                self.renderer.update_terminal_font();
+                self.font_changed_last_frame = true;
+            }
+            RendererSettingsChanged::EmojiMonochrome(..)
+            | RendererSettingsChanged::EmojiFitCell(..)
+            | RendererSettingsChanged::EmojiFont(..) => {
+                self.renderer.update_emoji_options();
+                self.font_changed_last_frame = true;
+            }
+            RendererSettingsChanged::HintItalic(..)
+            | RendererSettingsChanged::HintDim(..)
+            | RendererSettingsChanged::HintScale(..)
+            | RendererSettingsChanged::HideHints(..) => {
+                // Redraw the lines, so that the hints already drawn change too
+                self.font_changed_last_frame = true;
+            }
+            RendererSettingsChanged::ReduceMotion(..) => self.update_reduce_motion(),
+            RendererSettingsChanged::GpuCacheMb(gpu_cache_mb) => {
+                if let Some(skia_renderer) = &mut self.skia_renderer {
+                    set_gpu_cache_limit(skia_renderer.as_mut(), gpu_cache_mb);
+                }
+            }
             _ => {}
         }
     }
 
+    /// Echoes what `:NeovideInfo` shows about the window and the GPU.
+    fn show_info(&mut self) {
+        let mut lines = vec![format!("Neovide v{}", env!("CARGO_PKG_VERSION"))];
+        let grid_size = self.renderer.get_grid_size();
+        lines.push(format!(
+            "Grid: {}x{}, scale factor {:.2}",
+            grid_size.width,
+            grid_size.height,
+            self.renderer.scale_factor()
+        ));
+        if let Some(skia_renderer) = &mut self.skia_renderer {
+            let size = skia_renderer.window().inner_size();
+            lines.push(format!("Window: {}x{} pixels", size.width, size.height));
+            if let Some(monitor) = skia_renderer.window().current_monitor() {
+                let name = monitor.name().unwrap_or_else(|| "unknown".to_string());
+                lines.push(format!("Monitor: {name}"));
+            }
+            lines.push(format!(
+                "GPU cache: {}",
+                gpu_cache_usage(skia_renderer.as_mut())
+            ));
+        }
+        send_ui(ParallelCommand::ShowInfo { lines });
+    }
+
+    /// Copies the cells selected with the mouse to the system clipboard, and to the primary
+    /// selection on Linux, like a terminal does.
+    fn copy_text(&self, text: String) {
+        #[cfg(target_os = "linux")]
+        if let Err(error) = clipboard::set_contents(text.clone(), "*") {
+            log::error!("Could not set the primary selection: {error}");
+        }
+        if let Err(error) = clipboard::set_contents(text, "+") {
+            log::error!("Could not copy the selection: {error}");
+        }
+    }
+
+    /// Copies the text selected in the overlays with Ctrl+C, or Cmd+C on macOS, instead of sending
+    /// the keys to Neovim.
+    fn handle_copy_shortcut(&self, event: &WindowEvent) -> bool {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    state: ElementState::Pressed,
+                    logical_key,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+        let modifiers = self.keyboard_manager.modifiers();
+        #[cfg(target_os = "macos")]
+        let copy_modifier = modifiers.super_key();
+        #[cfg(not(target_os = "macos"))]
+        let copy_modifier = modifiers.control_key();
+        if !copy_modifier || !matches!(logical_key.as_ref(), Key::Character("c" | "C")) {
+            return false;
+        }
+        let Some(text) = self.renderer.selectable_text.selected_text() else {
+            return false;
+        };
+        self.copy_text(text);
+        true
+    }
+
+    /// Turns the animations off with `neovide_reduce_motion` or when the OS prefers reduced motion,
+    /// which winit doesn't tell.
+    fn update_reduce_motion(&self) {
+        let setting = self.settings.get::<RendererSettings>().reduce_motion;
+        #[cfg(target_os = "macos")]
+        let os_preference = super::macos::prefers_reduced_motion();
+        #[cfg(windows)]
+        let os_preference = crate::windows_utils::prefers_reduced_motion();
+        #[cfg(not(any(target_os = "macos", windows)))]
+        let os_preference = false;
+        set_motion_reduced(setting || os_preference);
+    }
+
     pub fn handle_title_changed(&mut self, new_title: String) {
         self.title = new_title;
+        self.renderer
+            .titlebar_renderer
+            .set_title(self.title.clone());
         if let Some(skia_renderer) = &self.skia_renderer {
             skia_renderer.window().set_title(&self.title);
         }
     }
 
+    /// The radius of the rounded corners of the frameless windows, in pixels, which are
+    /// transparent.
+    fn corner_radius(&self, window: &winit::window::Window) -> u32 {
+        #[cfg(target_os = "linux")]
+        if window_frame(&self.settings) != Frame::Full {
+            let radius = self.settings.get::<WindowSettings>().window_corner_radius;
+            return (radius * window.scale_factor() as f32).ceil() as u32;
+        }
+        let _ = window;
+        0
+    }
+
+    /// Lets the compositor skip blending what's behind the window when it's fully opaque.
+    fn update_opaque_region(&self) {
+        let window_settings = self.settings.get::<WindowSettings>();
+        if let Some(skia_renderer) = &self.skia_renderer {
+            let window = skia_renderer.window();
+            let opaque = window_settings
+                .transparency
+                .min(window_settings.normal_opacity)
+                >= 1.0;
+            update_opaque_region(window, opaque, self.corner_radius(window));
+        }
+    }
+
+    fn update_blur(&self) {
+        let WindowSettings {
+            transparency,
+            window_blurred,
+            ..
+        } = self.settings.get::<WindowSettings>();
+        if let Some(skia_renderer) = &self.skia_renderer {
+            let window = skia_renderer.window();
+            let blurred = window_blurred && transparency < 1.0;
+            update_blur(window, blurred, self.corner_radius(window));
+        }
+    }
+
+    /// Shows the file of the current buffer in the title bar, from where it can be dragged to
+    /// other apps.
+    fn apply_represented_file(&self) {
+        #[cfg(target_os = "macos")]
+        if let Some(macos_feature) = &self.macos_feature {
+            macos_feature.set_represented_file(self.represented_file.as_deref());
+        }
+        #[cfg(windows)]
+        if let Some(skia_renderer) = &self.skia_renderer {
+            set_proxy_file(skia_renderer.window(), self.represented_file.clone());
+        }
+    }
+
     pub fn handle_theme_changed(&mut self, new_theme: Option<Theme>) {
         if let Some(skia_renderer) = &self.skia_renderer {
             skia_renderer.window().set_theme(new_theme);
@@ -320,6 +924,12 @@ impl WinitWindowWrapper {
 
     pub fn handle_focus_gained(&mut self) {
         send_ui(ParallelCommand::FocusGained);
+        // X11 keeps the urgency of a bell until it's cleared
+        if let Some(skia_renderer) = &self.skia_renderer {
+            skia_renderer.window().request_user_attention(None);
+        }
+        // The preference of the OS could have changed while in another app
+        self.update_reduce_motion();
         // Got focus back after being minimized previously
         if self.is_minimized {
             // Sending <NOP> after suspend triggers the `VimResume` AutoCmd
@@ -330,6 +940,25 @@ impl WinitWindowWrapper {
     }
 
     pub fn handle_window_event(&mut self, event: WindowEvent) -> bool {
+        // The tour takes the keys and clicks while it's shown
+        if self.renderer.tour_renderer.is_active() && self.handle_tour_event(&event) {
+            return true;
+        }
+        // And so does the help
+        if self.renderer.help_renderer.is_active() && self.handle_help_event(&event) {
+            return true;
+        }
+        // And the clipboard history
+        if self.renderer.clipboard_history_renderer.is_active()
+            && self.handle_clipboard_history_event(&event)
+        {
+            return true;
+        }
+        // Neovim isn't running while it asks what to do after an error
+        if self.renderer.exit_prompt_renderer.is_active() && self.handle_exit_prompt_event(&event) {
+            return true;
+        }
+
         // The renderer and vsync should always be created when a window event is received
         let skia_renderer = self.skia_renderer.as_mut().unwrap();
         let vsync = self.vsync.as_mut().unwrap();
@@ -340,8 +969,30 @@ impl WinitWindowWrapper {
             &self.renderer,
             skia_renderer.window(),
         );
+        if let Some((window_id, lines)) = self.mouse_manager.take_overscroll() {
+            self.renderer.handle_overscroll(window_id, lines);
+        }
+        self.renderer.mouse_selection = self.mouse_manager.selection().cloned();
+        if let Some(selection) = self.mouse_manager.take_copied_selection() {
+            if let Some(text) = self.renderer.selection_text(&selection) {
+                self.copy_text(text);
+            }
+        }
+        self.renderer
+            .selectable_text
+            .set_selection(self.mouse_manager.text_selection());
+        if self.handle_copy_shortcut(&event) {
+            return true;
+        }
+        self.keyboard_manager
+            .set_wildmenu_vertical(self.renderer.wildmenu_renderer.is_vertical());
         self.keyboard_manager.handle_event(&event);
+        if let Some(motion) = self.keyboard_manager.take_predicted_motion() {
+            self.renderer.predict_cursor_motion(motion);
+        }
         self.renderer.handle_event(&event);
+        self.accessibility
+            .process_event(skia_renderer.window(), &event);
         let mut should_render = true;
 
         match event {
@@ -353,15 +1004,30 @@ impl WinitWindowWrapper {
                 tracy_zone!("ScaleFactorChanged");
                 self.handle_scale_factor_update(scale_factor);
             }
-            WindowEvent::Resized { .. } => {
-                skia_renderer.resize();
-                #[cfg(target_os = "macos")]
-                self.macos_feature.as_mut().unwrap().handle_size_changed();
+            WindowEvent::Resized(size) => {
+                let minimized = skia_renderer.window().is_minimized() == Some(true);
+                self.suspend_state.update(minimized, size);
+                // The surface keeps its size until there's something to show again
+                if !self.suspend_state.is_suspended() {
+                    skia_renderer.resize();
+                    #[cfg(target_os = "macos")]
+                    self.macos_feature.as_mut().unwrap().handle_size_changed();
+                    self.update_opaque_region();
+                    self.update_blur();
+                }
+            }
+            WindowEvent::HoveredFile(_) => {
+                self.renderer.drop_preview = true;
+            }
+            WindowEvent::HoveredFileCancelled => {
+                self.renderer.drop_preview = false;
             }
             WindowEvent::DroppedFile(path) => {
                 tracy_zone!("DroppedFile");
-                let file_path = path.into_os_string().into_string().unwrap();
-                send_ui(ParallelCommand::FileDrop(file_path));
+                self.renderer.drop_preview = false;
+                if let Some(path) = dropped_file_path(path) {
+                    send_ui(ParallelCommand::FileDrop(path));
+                }
             }
             WindowEvent::Focused(focus) => {
                 tracy_zone!("Focused");
@@ -384,7 +1050,14 @@ impl WinitWindowWrapper {
             }
             WindowEvent::Moved(_) => {
                 tracy_zone!("Moved");
-                vsync.update(skia_renderer.window());
+                vsync.update(skia_renderer.as_mut());
+                let monitor = skia_renderer
+                    .window()
+                    .current_monitor()
+                    .and_then(|monitor| monitor.name());
+                if self.renderer.handle_monitor_change(monitor) {
+                    self.font_changed_last_frame = true;
+                }
             }
             WindowEvent::Ime(Ime::Enabled) => {
                 log::info!("Ime enabled");
@@ -395,6 +1068,16 @@ impl WinitWindowWrapper {
                 log::info!("Ime disabled");
                 self.ime_enabled = false;
             }
+            WindowEvent::PinchGesture { delta, phase, .. } => {
+                tracy_zone!("PinchGesture");
+                self.handle_pinch_gesture(delta as f32, phase);
+            }
+            WindowEvent::DoubleTapGesture { .. } => {
+                tracy_zone!("DoubleTapGesture");
+                let zoom = self.zoom_animation();
+                zoom.set_target(1.0);
+                zoom.end_gesture();
+            }
             _ => {
                 tracy_zone!("Unknown WindowEvent");
                 should_render = false;
@@ -417,23 +1100,83 @@ impl WinitWindowWrapper {
             UserEvent::SettingsChanged(SettingsChanged::Renderer(e)) => {
                 self.handle_render_settings_changed(e);
             }
+            UserEvent::SettingsChanged(SettingsChanged::CommandLine(
+                CommandLineSettingsChanged::Enabled(enabled),
+            )) => {
+                send_ui(ParallelCommand::SetUiOption {
+                    name: "ext_cmdline".to_string(),
+                    value: enabled,
+                });
+            }
+            UserEvent::SettingsChanged(SettingsChanged::Messages(
+                MessagesSettingsChanged::Enabled(enabled),
+            )) => {
+                send_ui(ParallelCommand::SetUiOption {
+                    name: "ext_messages".to_string(),
+                    value: enabled,
+                });
+            }
+            UserEvent::SettingsChanged(SettingsChanged::PopupMenu(
+                PopupMenuSettingsChanged::Enabled(enabled),
+            )) => {
+                send_ui(ParallelCommand::SetUiOption {
+                    name: "ext_popupmenu".to_string(),
+                    value: enabled,
+                });
+            }
+            UserEvent::SettingsChanged(SettingsChanged::Tabline(
+                TablineSettingsChanged::Enabled(enabled),
+            )) => {
+                send_ui(ParallelCommand::SetUiOption {
+                    name: "ext_tabline".to_string(),
+                    value: enabled,
+                });
+            }
+            UserEvent::SettingsChanged(SettingsChanged::Wildmenu(
+                WildmenuSettingsChanged::Enabled(enabled),
+            )) => {
+                send_ui(ParallelCommand::SetUiOption {
+                    name: "ext_wildmenu".to_string(),
+                    value: enabled,
+                });
+            }
             UserEvent::ConfigsChanged(config) => {
                 self.handle_config_changed(*config);
             }
+            UserEvent::Accessibility(event) => self.accessibility.handle_event(event),
+            UserEvent::NeovimFailed(exit_code) => {
+                self.renderer.exit_prompt_renderer.show(exit_code);
+            }
+            UserEvent::NeovimRestarted => {
+                // The new instance starts from scratch, so forget the old windows and send it the
+                // grid size once it's ready.
+                self.renderer.clear_windows();
+                self.saved_grid_size = None;
+                // Nothing will close the buffers waited for with `--wait`
+                ipc::release_waiters();
+            }
             _ => {}
         }
     }
 
-    pub fn draw_frame(&mut self, dt: f32) {
+    /// Draws a frame, `continuous` tells if `dt` was measured between two frames drawn one after
+    /// the other, which should be a vsync interval apart.
+    pub fn draw_frame(&mut self, dt: f32, continuous: bool) {
         tracy_zone!("draw_frame");
-        if self.skia_renderer.is_none() {
+        if self.skia_renderer.is_none() || self.suspend_state.is_suspended() {
             return;
         }
+        logging::next_frame();
         let skia_renderer = self.skia_renderer.as_mut().unwrap();
         let vsync = self.vsync.as_mut().unwrap();
 
-        self.renderer.draw_frame(skia_renderer.canvas(), dt);
+        let expected_dt =
+            continuous.then(|| vsync.get_refresh_rate(skia_renderer.window(), &self.settings));
+        self.renderer
+            .draw_frame(skia_renderer.canvas(), dt, expected_dt);
         skia_renderer.flush();
+        #[cfg(feature = "profiling")]
+        plot_gpu_cache(skia_renderer.as_mut());
         {
             tracy_gpu_zone!("wait for vsync");
             vsync.wait_for_vsync();
@@ -450,7 +1193,24 @@ impl WinitWindowWrapper {
     pub fn animate_frame(&mut self, dt: f32) -> bool {
         tracy_zone!("animate_frame", 0);
 
-        let res = self
+        let mut res = false;
+        if let Some(skia_renderer) = &self.skia_renderer {
+            res |= self.mouse_manager.animate_frame(
+                dt,
+                &self.keyboard_manager,
+                &self.renderer,
+                skia_renderer.window(),
+            );
+            if let Some((window_id, lines)) = self.mouse_manager.take_overscroll() {
+                self.renderer.handle_overscroll(window_id, lines);
+            }
+        }
+        if let Some(command) = self.mouse_manager.take_window_command() {
+            self.handle_window_command(command);
+        }
+
+        res |= self.animate_zoom(dt);
+        res |= self
             .renderer
             .animate_frame(&self.get_grid_rect_from_window(GridSize::default()), dt);
         tracy_plot!("animate_frame", res as u8 as f64);
@@ -477,20 +1237,16 @@ impl WinitWindowWrapper {
         let WindowSettings {
             input_ime,
             theme,
-            transparency,
-            window_blurred,
             fullscreen,
+            window_level,
             #[cfg(target_os = "macos")]
             input_macos_option_key_is_meta,
-
-            #[cfg(target_os = "windows")]
-            title_background_color,
-            #[cfg(target_os = "windows")]
-            title_text_color,
             ..
         } = self.settings.get::<WindowSettings>();
 
         window.set_ime_allowed(input_ime);
+        window.set_window_level(parse_window_level(&window_level));
+        self.update_reduce_motion();
 
         // It's important that this is created before the window is resized, since it can change the padding and affect the size
         #[cfg(target_os = "macos")]
@@ -502,7 +1258,9 @@ impl WinitWindowWrapper {
         }
 
         let scale_factor = window.scale_factor();
-        self.renderer.handle_os_scale_factor_change(scale_factor);
+        let monitor = window.current_monitor().and_then(|monitor| monitor.name());
+        self.renderer
+            .handle_os_scale_factor_change(scale_factor, monitor);
 
         let mut size = PhysicalSize::default();
         match self.initial_window_size {
@@ -553,16 +1311,25 @@ impl WinitWindowWrapper {
             window.window_handle().unwrap().as_raw(),
             RawWindowHandle::Wayland(_)
         );
+        // The screen readers only find the tree of windows that weren't shown yet
+        self.accessibility.attach(event_loop, window, proxy.clone());
         // On Wayland we can show the window now, since internally it's only shown after the first rendering
         // On the other platforms the window is shown after rendering to avoid flickering
         if is_wayland {
             window.set_visible(true);
         }
 
+        #[cfg(target_os = "linux")]
+        match window.window_handle().unwrap().as_raw() {
+            RawWindowHandle::Xlib(handle) => export_menu(handle.window as u32),
+            RawWindowHandle::Xcb(handle) => export_menu(handle.window.get()),
+            _ => {}
+        }
+
         let cmd_line_settings = self.settings.get::<CmdLineSettings>();
         let srgb = cmd_line_settings.srgb;
         let vsync_enabled = cmd_line_settings.vsync;
-        let skia_renderer =
+        let mut skia_renderer =
             create_skia_renderer(window_config, srgb, vsync_enabled, self.settings.clone());
         let window = skia_renderer.window();
 
@@ -574,7 +1341,6 @@ impl WinitWindowWrapper {
             self.renderer.grid_renderer.grid_scale
         );
 
-        window.set_blur(window_blurred && transparency < 1.0);
         if fullscreen {
             let handle = window.current_monitor();
             window.set_fullscreen(Some(Fullscreen::Borderless(handle)));
@@ -593,15 +1359,13 @@ impl WinitWindowWrapper {
 
         #[cfg(target_os = "windows")]
         {
-            if let Some(winit_color) = Self::parse_winit_color(&title_background_color) {
-                window.set_title_background_color(Some(winit_color));
-            }
-
-            if let Some(winit_color) = Self::parse_winit_color(&title_text_color) {
-                window.set_title_text_color(winit_color);
-            }
+            self.apply_title_colors(window);
+            let backdrop = self.settings.get::<WindowSettings>().system_backdrop();
+            window.set_system_backdrop(backdrop);
         }
 
+        self.proxy = Some(proxy.clone());
+        register_drop_target(window, proxy.clone());
         self.vsync = Some(VSync::new(
             vsync_enabled,
             skia_renderer.as_ref(),
@@ -614,14 +1378,21 @@ impl WinitWindowWrapper {
             window.request_redraw();
         }
 
+        let gpu_cache_mb = self.settings.get::<RendererSettings>().gpu_cache_mb;
+        set_gpu_cache_limit(skia_renderer.as_mut(), gpu_cache_mb);
+
         self.ui_state = UIState::FirstFrame;
         self.skia_renderer = Some(skia_renderer);
         #[cfg(target_os = "macos")]
         self.set_macos_option_as_meta(input_macos_option_key_is_meta);
+        self.apply_represented_file();
+        self.update_opaque_region();
+        self.update_blur();
     }
 
     pub fn handle_draw_commands(&mut self, batch: Vec<DrawCommand>) {
         tracy_zone!("handle_draw_commands");
+        self.accessibility.handle_draw_commands(&batch);
         let handle_draw_commands_result = self.renderer.handle_draw_commands(batch);
 
         self.font_changed_last_frame |= handle_draw_commands_result.font_changed;
@@ -629,11 +1400,24 @@ impl WinitWindowWrapper {
         if self.ui_state == UIState::Initing && handle_draw_commands_result.should_show {
             log::info!("Showing the Window");
             self.ui_state = UIState::WaitingForWindowCreate;
+        } else if self.ui_state >= UIState::FirstFrame
+            && handle_draw_commands_result.should_show
+            && self.saved_grid_size.is_none()
+        {
+            // A restarted Neovim is attached with the default size
+            self.update_grid_size_from_window();
         };
     }
 
     fn handle_config_changed(&mut self, config: HotReloadConfigs) {
         tracy_zone!("handle_config_changed");
+        if let HotReloadConfigs::Settings(table) = config {
+            let values = config_settings(&self.settings, table);
+            if !values.is_empty() {
+                send_ui(ParallelCommand::SetSettings(values));
+            }
+            return;
+        }
         self.renderer.handle_config_changed(config);
         self.font_changed_last_frame = true;
     }
@@ -652,6 +1436,11 @@ impl WinitWindowWrapper {
             padding_top
         };
 
+        // The title bar and the tab bar take the space above the grid
+        let window_padding_top = window_padding_top
+            + self.renderer.titlebar_height() as u32
+            + self.renderer.tabline_height() as u32;
+
         WindowPadding {
             top: window_padding_top,
             left: window_settings.padding_left,
@@ -675,7 +1464,26 @@ impl WinitWindowWrapper {
         }
 
         // The skia renderer shuld always be created when this point is reached, since the < UIState::FirstFrame check will return true
-        let skia_renderer = self.skia_renderer.as_ref().unwrap();
+        let skia_renderer = self.skia_renderer.as_mut().unwrap();
+
+        // Not every platform sends a resize when minimizing, so check it every frame
+        let minimized = skia_renderer.window().is_minimized() == Some(true);
+        match self
+            .suspend_state
+            .update(minimized, skia_renderer.window().inner_size())
+        {
+            Some(SuspendTransition::Resume) => {
+                // The resizes were skipped while suspended
+                skia_renderer.resize();
+                #[cfg(target_os = "macos")]
+                self.macos_feature.as_mut().unwrap().handle_size_changed();
+                should_render = ShouldRender::Immediately;
+            }
+            // Nothing to draw, and the grid keeps its size until the window is back
+            _ if self.suspend_state.is_suspended() => return ShouldRender::Wait,
+            _ => {}
+        }
+        let window = skia_renderer.window();
 
         let resize_requested = self.requested_columns.is_some() || self.requested_lines.is_some();
         if resize_requested {
@@ -683,12 +1491,13 @@ impl WinitWindowWrapper {
             // So, deal with them first and resize the window programmatically.
             // The new window size will then be processed in the following frame.
             self.update_window_size_from_grid();
-        } else if skia_renderer.window().is_minimized() != Some(true) {
-            // NOTE: Only actually resize the grid when the window is not minimized
-            // Some platforms return a zero size when that is the case, so we should not try to resize to that.
-            let new_size = skia_renderer.window().inner_size();
+        } else {
+            let new_size = window.inner_size();
             if self.saved_inner_size != new_size || self.font_changed_last_frame || padding_changed
             {
+                if self.saved_inner_size != new_size {
+                    self.last_window_resize = Some(Instant::now());
+                }
                 self.window_padding = window_padding;
                 self.saved_inner_size = new_size;
 
@@ -699,6 +1508,15 @@ impl WinitWindowWrapper {
 
         self.update_ime_position(false);
 
+        let resize_rate = self.settings.get::<WindowSettings>().resize_rate;
+        if let Some(grid_size) = self.resize_throttle.poll(Instant::now(), resize_rate) {
+            send_resize(grid_size);
+        }
+        if let Some(deadline) = self.resize_throttle.deadline(resize_rate) {
+            should_render.update(ShouldRender::Deadline(deadline));
+        }
+        self.renderer.resize_preview_scale = self.resize_preview_scale();
+
         should_render.update(self.renderer.prepare_frame());
 
         if self.font_changed_last_frame {
@@ -706,6 +1524,14 @@ impl WinitWindowWrapper {
             self.font_changed_last_frame = false;
         }
 
+        for (grid, size) in self.renderer.take_grid_size_requests() {
+            send_ui(ParallelCommand::ResizeGrid {
+                grid,
+                width: size.width.into(),
+                height: size.height.into(),
+            });
+        }
+
         should_render
     }
 
@@ -808,10 +1634,25 @@ impl WinitWindowWrapper {
             grid_size,
             self.saved_inner_size
         );
-        send_ui(ParallelCommand::Resize {
-            width: grid_size.width.into(),
-            height: grid_size.height.into(),
-        });
+        let resize_rate = self.settings.get::<WindowSettings>().resize_rate;
+        if let Some(grid_size) =
+            self.resize_throttle
+                .request(grid_size, Instant::now(), resize_rate)
+        {
+            send_resize(grid_size);
+        }
+    }
+
+    /// How much the grid has to be stretched to fill the window, while Neovim hasn't caught up
+    /// with an interactive resize yet.
+    fn resize_preview_scale(&self) -> Option<(f32, f32)> {
+        let resizing = self
+            .last_window_resize
+            .is_some_and(|last_resize| last_resize.elapsed() < RESIZE_PREVIEW_DURATION);
+        if !resizing || !self.settings.get::<WindowSettings>().resize_preview {
+            return None;
+        }
+        preview_scale(self.saved_grid_size?, self.renderer.get_grid_size())
     }
 
     fn update_ime_position(&mut self, force: bool) {
@@ -819,27 +1660,57 @@ impl WinitWindowWrapper {
             return;
         }
         let skia_renderer = self.skia_renderer.as_ref().unwrap();
-        let grid_scale = self.renderer.grid_renderer.grid_scale;
-        let font_dimensions = GridSize::new(1.0, 1.0) * grid_scale;
-        let position = self.renderer.get_cursor_destination();
-        let position = position.try_cast::<u32>().unwrap();
-        let position = dpi::PhysicalPosition {
-            x: position.x,
-            y: position.y,
-        };
-        // NOTE: some compositors don't like excluding too much and try to render popup at the
-        // bottom right corner of the provided area, so exclude just the full-width char to not
-        // obscure the cursor and not render popup at the end of the window.
-        let width = (font_dimensions.width * 2.0).ceil() as u32;
-        let height = font_dimensions.height.ceil() as u32;
-        let size = dpi::PhysicalSize::new(width, height);
-        let area = (position, size);
+        let area = ime_cursor_area(
+            self.renderer.get_cursor_destination(),
+            self.renderer.grid_renderer.grid_scale,
+        );
         if force || self.ime_area != area {
-            self.ime_area = (position, size);
+            self.ime_area = area;
+            let (position, size) = area;
             skia_renderer.window().set_ime_cursor_area(position, size);
         }
     }
 
+    fn zoom_animation(&mut self) -> &mut ZoomAnimation {
+        let scale_factor = self.renderer.user_scale_factor as f32;
+        self.zoom
+            .get_or_insert_with(|| ZoomAnimation::new(scale_factor))
+    }
+
+    fn handle_pinch_gesture(&mut self, delta: f32, phase: TouchPhase) {
+        // Some platforms report a NaN delta
+        if delta.is_nan() {
+            return;
+        }
+        let zoom = self.zoom_animation();
+        zoom.pinch(delta);
+        if matches!(phase, TouchPhase::Ended | TouchPhase::Cancelled) {
+            zoom.end_gesture();
+        }
+    }
+
+    /// Applies the scale factor of a zoom gesture, and saves it once the zoom has settled.
+    fn animate_zoom(&mut self, dt: f32) -> bool {
+        let Some(zoom) = &mut self.zoom else {
+            return false;
+        };
+        let animating = zoom.update(dt);
+        let scale_factor = zoom.scale_factor() as f64;
+        if (scale_factor - self.renderer.user_scale_factor).abs() > 1e-3 {
+            self.renderer.user_scale_factor = scale_factor;
+            self.renderer.handle_scale_factor_update();
+            self.font_changed_last_frame = true;
+        }
+        if zoom.is_finished() {
+            send_ui(ParallelCommand::Command(format!(
+                "let g:neovide_scale_factor = {:.2}",
+                zoom.target()
+            )));
+            self.zoom = None;
+        }
+        animating
+    }
+
     fn handle_scale_factor_update(&mut self, scale_factor: f64) {
         if self.skia_renderer.is_none() {
             return;
@@ -850,8 +1721,20 @@ impl WinitWindowWrapper {
             .as_mut()
             .unwrap()
             .handle_scale_factor_update(scale_factor);
-        self.renderer.handle_os_scale_factor_change(scale_factor);
+        let monitor = skia_renderer
+            .window()
+            .current_monitor()
+            .and_then(|monitor| monitor.name());
+        self.renderer
+            .handle_os_scale_factor_change(scale_factor, monitor);
         skia_renderer.resize();
+        // The cells change size with a fractional scale too, even when the window keeps its
+        // physical size, so the grid size has to be computed again
+        self.font_changed_last_frame = true;
+        self.update_opaque_region();
+        self.update_blur();
+        // The IME area is in physical pixels, which the compositor converts with the new scale
+        self.update_ime_position(true);
     }
 
     #[cfg(windows)]
@@ -866,21 +1749,108 @@ impl WinitWindowWrapper {
     }
 
     #[cfg(windows)]
-    fn handle_title_background_color(&self, color: &str) {
+    fn handle_title_colors(&self) {
         if let Some(skia_renderer) = &self.skia_renderer {
-            let winit_color = Self::parse_winit_color(color);
-            skia_renderer
-                .window()
-                .set_title_background_color(winit_color);
+            self.apply_title_colors(skia_renderer.window());
         }
     }
 
+    /// Applies the title bar colors. When `title_background_color` is set to `auto`, the title bar
+    /// and the window border follow the background color of the current colorscheme.
     #[cfg(windows)]
-    fn handle_title_text_color(&self, color: &str) {
-        if let Some(skia_renderer) = &self.skia_renderer {
-            if let Some(winit_color) = Self::parse_winit_color(color) {
-                skia_renderer.window().set_title_text_color(winit_color);
-            }
+    fn apply_title_colors(&self, window: &winit::window::Window) {
+        let WindowSettings {
+            title_background_color,
+            title_text_color,
+            ..
+        } = self.settings.get::<WindowSettings>();
+
+        let auto = title_background_color == "auto";
+        if auto {
+            let background = self.default_background.map(|color| {
+                let color = color.to_color();
+                Color::from_rgb(color.r(), color.g(), color.b())
+            });
+            window.set_title_background_color(background);
+            window.set_border_color(background);
+        } else {
+            window.set_title_background_color(Self::parse_winit_color(&title_background_color));
+            window.set_border_color(None);
         }
+
+        let auto_background = self.default_background.filter(|_| auto);
+        window.set_title_text_color(Self::title_text_color(&title_text_color, auto_background));
+    }
+
+    /// The color of the title text, the configured one or else one that stays readable over the
+    /// background chosen for `auto`. Leaving `auto` gives the text color back to the system.
+    #[cfg(windows)]
+    fn title_text_color(title_text_color: &str, auto_background: Option<Color4f>) -> Color {
+        Self::parse_winit_color(title_text_color).unwrap_or_else(|| match auto_background {
+            Some(background) if is_light_color(&background) => Color::from_rgb(0, 0, 0),
+            Some(_) => Color::from_rgb(255, 255, 255),
+            None => Color::SYSTEM_DEFAULT,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_levels() {
+        assert_eq!(
+            parse_window_level("always_on_top"),
+            WindowLevel::AlwaysOnTop
+        );
+        assert_eq!(
+            parse_window_level("always_on_bottom"),
+            WindowLevel::AlwaysOnBottom
+        );
+        assert_eq!(parse_window_level("normal"), WindowLevel::Normal);
+        assert_eq!(parse_window_level("floating"), WindowLevel::Normal);
+    }
+
+    #[test]
+    fn ime_area_stays_in_the_window() {
+        let grid_scale = GridScale::new(PixelSize::new(10.5, 21.0));
+        let (position, size) = ime_cursor_area(PixelPos::new(-3.0, 52.5), grid_scale);
+        assert_eq!(position, dpi::PhysicalPosition::new(0, 52));
+        assert_eq!(size, dpi::PhysicalSize::new(21, 21));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_backdrops() {
+        use winit::platform::windows::BackdropType;
+
+        let backdrop = |name: &str| {
+            WindowSettings {
+                windows_backdrop: name.to_string(),
+                ..Default::default()
+            }
+            .system_backdrop()
+        };
+        assert_eq!(backdrop("mica"), BackdropType::MainWindow);
+        assert_eq!(backdrop("acrylic"), BackdropType::TransientWindow);
+        assert_eq!(backdrop("tabbed"), BackdropType::TabbedWindow);
+        assert_eq!(backdrop("none"), BackdropType::None);
+        assert_eq!(backdrop("glass"), BackdropType::None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn title_text_stays_readable() {
+        let light = Some(Color4f::new(0.9, 0.9, 0.8, 1.0));
+        let dark = Some(Color4f::new(0.1, 0.1, 0.2, 1.0));
+        let text_color = WinitWindowWrapper::title_text_color;
+        assert_eq!(text_color("", light), Color::from_rgb(0, 0, 0));
+        assert_eq!(text_color("", dark), Color::from_rgb(255, 255, 255));
+        // The configured color wins, even with auto
+        assert_eq!(text_color("#ff0000", dark), Color::from_rgb(255, 0, 0));
+        // Without auto the system picks it again
+        assert_eq!(text_color("", None), Color::SYSTEM_DEFAULT);
+        assert_eq!(text_color("not a color", None), Color::SYSTEM_DEFAULT);
     }
 }
@@ -0,0 +1,81 @@
+//! A connection of Neovide's own to the X server, to set the properties of the window that winit
+//! doesn't know about.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use log::warn;
+use parking_lot::Mutex;
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use winit::window::Window;
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{Atom, AtomEnum, ConnectionExt as _, PropMode},
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+};
+
+static CONNECTION: OnceLock<Option<RustConnection>> = OnceLock::new();
+static ATOMS: OnceLock<Mutex<HashMap<&'static str, Atom>>> = OnceLock::new();
+
+/// The id of the window when it's an X11 one.
+pub fn x11_window(window: &Window) -> Option<u32> {
+    match window.window_handle().ok()?.as_raw() {
+        RawWindowHandle::Xlib(handle) => Some(handle.window as u32),
+        RawWindowHandle::Xcb(handle) => Some(handle.window.get()),
+        _ => None,
+    }
+}
+
+fn atom(connection: &RustConnection, name: &'static str) -> Option<Atom> {
+    let mut atoms = ATOMS.get_or_init(Mutex::default).lock();
+    if let Some(atom) = atoms.get(name) {
+        return Some(*atom);
+    }
+    let atom = connection
+        .intern_atom(false, name.as_bytes())
+        .ok()?
+        .reply()
+        .ok()?
+        .atom;
+    atoms.insert(name, atom);
+    Some(atom)
+}
+
+fn connection() -> Option<&'static RustConnection> {
+    CONNECTION
+        .get_or_init(|| Some(x11rb::connect(None).ok()?.0))
+        .as_ref()
+}
+
+/// Sets the property `name` of `window` to a list of cardinals, or deletes it without a `value`.
+pub fn set_cardinal_property(window: u32, name: &'static str, value: Option<&[u32]>) {
+    let Some(connection) = connection() else {
+        return;
+    };
+    let Some(atom) = atom(connection, name) else {
+        return;
+    };
+    let result = match value {
+        Some(value) => connection
+            .change_property32(PropMode::REPLACE, window, atom, AtomEnum::CARDINAL, value)
+            .map(drop),
+        None => connection.delete_property(window, atom).map(drop),
+    };
+    if let Err(error) = result.and_then(|_| connection.flush()) {
+        warn!("Could not set {name} on the window: {error}");
+    }
+}
+
+/// Rings the bell of the X server, at its default volume.
+pub fn ring_bell() {
+    let Some(connection) = connection() else {
+        return;
+    };
+    if let Err(error) = connection
+        .bell(0)
+        .map(drop)
+        .and_then(|_| connection.flush())
+    {
+        warn!("Could not ring the bell: {error}");
+    }
+}
@@ -0,0 +1,90 @@
+use crate::renderer::animation_utils::CriticallyDampedSpringAnimation;
+
+const MIN_SCALE_FACTOR: f32 = 0.25;
+const MAX_SCALE_FACTOR: f32 = 4.0;
+const ANIMATION_LENGTH: f32 = 0.15;
+
+/// Smoothly changes the scale factor while zooming with trackpad gestures. The scale factor is
+/// only saved to `g:neovide_scale_factor` once the gesture has ended and the animation settled.
+pub struct ZoomAnimation {
+    target: f32,
+    /// Offset of the shown scale factor from the target, animated towards 0.
+    offset: CriticallyDampedSpringAnimation,
+    gesture_ended: bool,
+}
+
+impl ZoomAnimation {
+    pub fn new(scale_factor: f32) -> Self {
+        Self {
+            target: scale_factor,
+            offset: CriticallyDampedSpringAnimation::new(),
+            gesture_ended: false,
+        }
+    }
+
+    pub fn set_target(&mut self, target: f32) {
+        let target = target.clamp(MIN_SCALE_FACTOR, MAX_SCALE_FACTOR);
+        self.offset.position += self.target - target;
+        self.target = target;
+        self.gesture_ended = false;
+    }
+
+    /// Zooms in for positive deltas and out for negative ones, relative to the current target.
+    pub fn pinch(&mut self, delta: f32) {
+        self.set_target(self.target * (1.0 + delta));
+    }
+
+    pub fn end_gesture(&mut self) {
+        let target = (self.target * 100.0).round() / 100.0;
+        self.offset.position += self.target - target;
+        self.target = target;
+        self.gesture_ended = true;
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        self.target + self.offset.position
+    }
+
+    /// Returns `true` while the scale factor is still moving towards the target.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.offset.update(dt, ANIMATION_LENGTH)
+    }
+
+    /// Whether the gesture is over and the scale factor reached its target.
+    pub fn is_finished(&self) -> bool {
+        self.gesture_ended && self.offset.position == 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinching_is_relative_and_clamped() {
+        let mut zoom = ZoomAnimation::new(1.0);
+        zoom.pinch(0.5);
+        assert_eq!(zoom.target(), 1.5);
+        assert_eq!(zoom.scale_factor(), 1.0);
+        zoom.pinch(10.0);
+        assert_eq!(zoom.target(), MAX_SCALE_FACTOR);
+        zoom.pinch(-1.0);
+        assert_eq!(zoom.target(), MIN_SCALE_FACTOR);
+    }
+
+    #[test]
+    fn finishes_at_the_target_after_the_gesture() {
+        let mut zoom = ZoomAnimation::new(1.0);
+        zoom.pinch(0.333);
+        while zoom.update(1.0 / 60.0) {}
+        assert!(!zoom.is_finished());
+        zoom.end_gesture();
+        while zoom.update(1.0 / 60.0) {}
+        assert!(zoom.is_finished());
+        assert_eq!(zoom.scale_factor(), 1.33);
+    }
+}
@@ -1,16 +1,75 @@
-use windows::Win32::{
-    System::Console::{AttachConsole, ATTACH_PARENT_PROCESS},
-    UI::HiDpi::{SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2},
+use std::{
+    cell::Cell,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use windows::{
+    core::{implement, HSTRING},
+    Data::Xml::Dom::XmlDocument,
+    Foundation::TypedEventHandler,
+    Win32::{
+        Foundation::{BOOL, HWND, LPARAM, LRESULT, POINT, POINTL, WPARAM},
+        Graphics::Gdi::ScreenToClient,
+        System::{
+            Com::{
+                CoCreateInstance, IBindCtx, IDataObject, CLSCTX_INPROC_SERVER, DVASPECT_CONTENT,
+                FORMATETC, TYMED_HGLOBAL,
+            },
+            Console::{AttachConsole, ATTACH_PARENT_PROCESS},
+            Memory::{GlobalLock, GlobalUnlock},
+            Ole::{
+                IDropSource, IDropTarget, IDropTarget_Impl, RegisterDragDrop, ReleaseStgMedium,
+                RevokeDragDrop, CF_HDROP, CF_UNICODETEXT, DROPEFFECT, DROPEFFECT_COPY,
+                DROPEFFECT_LINK, DROPEFFECT_NONE,
+            },
+            SystemServices::MODIFIERKEYS_FLAGS,
+        },
+        UI::{
+            HiDpi::{SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2},
+            Input::KeyboardAndMouse::DragDetect,
+            Shell::{
+                BHID_DataObject, DefSubclassProc, DragQueryFileW, IShellItem, ITaskbarList3,
+                SHCreateItemFromParsingName, SHDoDragDrop, SetWindowSubclass, TaskbarList, HDROP,
+                TBPFLAG, TBPF_ERROR, TBPF_INDETERMINATE, TBPF_NOPROGRESS, TBPF_NORMAL, TBPF_PAUSED,
+            },
+            WindowsAndMessaging::{
+                MessageBeep, SystemParametersInfoW, HTSYSMENU, MB_OK, SPI_GETCLIENTAREAANIMATION,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, WM_NCLBUTTONDOWN,
+            },
+        },
+    },
+    UI::Notifications::{ToastNotification, ToastNotificationManager},
 };
 use windows_registry::{Result, CURRENT_USER};
+use winit::{event_loop::EventLoopProxy, window::Window};
+
+use crate::{
+    error_msg,
+    units::PixelPos,
+    window::{
+        dropped_text, DragEvent, NotificationLevel, OsNotification, TaskbarProgress, UserEvent,
+        WindowCommand,
+    },
+};
+
+const PROXY_ICON_SUBCLASS_ID: usize = 1;
 
-use crate::error_msg;
+/// The file dragged from the icon of the title bar.
+static PROXY_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
 
 const REGISTRY_PATH_DIRECTORY: &str = "Software\\Classes\\Directory\\Background\\shell\\Neovide";
 const REGISTRY_PATH_DIRECTORY_COMMAND: &str =
     "Software\\Classes\\Directory\\Background\\shell\\Neovide\\command";
 const REGISTRY_PATH_FOLDER: &str = "Software\\Classes\\*\\shell\\Neovide";
 const REGISTRY_PATH_FOLDER_COMMAND: &str = "Software\\Classes\\*\\shell\\Neovide\\command";
+/// The toasts of apps which aren't packaged are only shown for an app id in the registry.
+const NOTIFICATION_APP_ID: &str = "Neovide.Neovide";
+const REGISTRY_PATH_NOTIFICATION_APP_ID: &str =
+    "Software\\Classes\\AppUserModelId\\Neovide.Neovide";
+const REGISTRY_PATH_URL_SCHEME: &str = "Software\\Classes\\nvim";
+const REGISTRY_PATH_URL_SCHEME_COMMAND: &str = "Software\\Classes\\nvim\\shell\\open\\command";
 
 fn get_neovide_path() -> String {
     std::env::current_exe()
@@ -73,6 +132,157 @@ pub fn unregister_right_click() {
     }
 }
 
+fn register_url_scheme() -> Result<()> {
+    let neovide_path = get_neovide_path();
+    let neovide_command = format!("\"{}\" \"%1\"", neovide_path);
+
+    let key = CURRENT_USER.create(REGISTRY_PATH_URL_SCHEME)?;
+    key.set_string("", "URL:nvim")?;
+    key.set_string("URL Protocol", "")?;
+
+    let key = CURRENT_USER.create(REGISTRY_PATH_URL_SCHEME_COMMAND)?;
+    key.set_string("", &neovide_command)?;
+
+    Ok(())
+}
+
+/// Makes Neovide open the `nvim://open?file=...` links.
+pub fn register_url_handler() {
+    if register_url_scheme().is_err() {
+        error_msg!("Could not register the nvim URL scheme.");
+    }
+}
+
+pub fn unregister_url_handler() {
+    if CURRENT_USER.remove_tree(REGISTRY_PATH_URL_SCHEME).is_err() {
+        error_msg!("Could not remove the nvim URL scheme. Possibly already removed.");
+    }
+}
+
+fn register_notification_app_id() -> Result<()> {
+    let key = CURRENT_USER.create(REGISTRY_PATH_NOTIFICATION_APP_ID)?;
+    key.set_string("DisplayName", "Neovide")?;
+    key.set_string("IconUri", &get_neovide_path())?;
+    Ok(())
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+fn toast_xml(notification: &OsNotification) -> String {
+    format!(
+        concat!(
+            "<toast{}><visual><binding template=\"ToastGeneric\">",
+            "<text>{}</text><text>{}</text>",
+            "</binding></visual>{}</toast>",
+        ),
+        // Errors stay on screen longer
+        if notification.level == NotificationLevel::Error {
+            " duration=\"long\""
+        } else {
+            ""
+        },
+        escape_xml(&notification.title),
+        escape_xml(&notification.body),
+        if notification.sound {
+            ""
+        } else {
+            "<audio silent=\"true\"/>"
+        },
+    )
+}
+
+fn show_toast(
+    notification: &OsNotification,
+    proxy: EventLoopProxy<UserEvent>,
+) -> windows::core::Result<()> {
+    let xml = XmlDocument::new()?;
+    xml.LoadXml(&HSTRING::from(toast_xml(notification)))?;
+    let toast = ToastNotification::CreateToastNotification(&xml)?;
+    toast.Activated(&TypedEventHandler::new(move |_, _| {
+        let _ = proxy.send_event(WindowCommand::FocusWindow.into());
+        Ok(())
+    }))?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(NOTIFICATION_APP_ID))?
+        .Show(&toast)
+}
+
+/// Shows the notification as a toast, clicking it focuses the window.
+pub fn show_notification(notification: &OsNotification, proxy: EventLoopProxy<UserEvent>) {
+    if let Err(error) = register_notification_app_id() {
+        log::warn!("Could not register the app id of the notifications: {error}");
+    }
+    if let Err(error) = show_toast(notification, proxy) {
+        log::warn!("Could not show the notification: {error}");
+    }
+}
+
+/// Plays the default sound of the system, the one of the bell.
+pub fn play_system_sound() {
+    if let Err(error) = unsafe { MessageBeep(MB_OK) } {
+        log::warn!("Could not play the system sound: {error}");
+    }
+}
+
+/// How many steps the progress of the taskbar is divided into.
+const TASKBAR_PROGRESS_STEPS: u64 = 1000;
+
+fn set_taskbar_progress(hwnd: HWND, progress: TaskbarProgress) -> windows::core::Result<()> {
+    let state: TBPFLAG = match progress {
+        TaskbarProgress::Hidden => TBPF_NOPROGRESS,
+        TaskbarProgress::Indeterminate => TBPF_INDETERMINATE,
+        TaskbarProgress::Normal(_) => TBPF_NORMAL,
+        TaskbarProgress::Paused(_) => TBPF_PAUSED,
+        TaskbarProgress::Error(_) => TBPF_ERROR,
+    };
+    unsafe {
+        let taskbar: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)?;
+        taskbar.HrInit()?;
+        taskbar.SetProgressState(hwnd, state)?;
+        if let Some(fraction) = progress.fraction() {
+            let completed = (fraction * TASKBAR_PROGRESS_STEPS as f32).round() as u64;
+            taskbar.SetProgressValue(hwnd, completed, TASKBAR_PROGRESS_STEPS)?;
+        }
+    }
+    Ok(())
+}
+
+/// Shows the progress on the button of the window in the taskbar, with `ITaskbarList3`.
+pub fn show_taskbar_progress(window: &Window, progress: TaskbarProgress) {
+    let Some(hwnd) = window_hwnd(window) else {
+        return;
+    };
+    if let Err(error) = set_taskbar_progress(hwnd, progress) {
+        log::warn!("Could not show the taskbar progress: {error}");
+    }
+}
+
+/// Whether "Show animations in Windows" is turned off in the accessibility settings.
+pub fn prefers_reduced_motion() -> bool {
+    let mut enabled = BOOL::from(true);
+    let result = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            Some(&mut enabled as *mut BOOL as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    result.is_ok() && !enabled.as_bool()
+}
+
 pub fn windows_fix_dpi() {
     unsafe {
         SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2)
@@ -86,3 +296,209 @@ pub fn windows_attach_to_console() {
         AttachConsole(ATTACH_PARENT_PROCESS).ok();
     }
 }
+
+fn window_hwnd(window: &Window) -> Option<HWND> {
+    match window.window_handle().ok()?.as_raw() {
+        RawWindowHandle::Win32(handle) => Some(HWND(handle.hwnd.get() as *mut _)),
+        _ => None,
+    }
+}
+
+/// Lets the file of the current buffer be dragged from the icon of the title bar, like the proxy
+/// icon of macOS. Clicking the icon still opens the window menu.
+pub fn set_proxy_file(window: &Window, file: Option<PathBuf>) {
+    *PROXY_FILE.lock().unwrap() = file;
+    let Some(hwnd) = window_hwnd(window) else {
+        return;
+    };
+    // Installing the same subclass again only updates it
+    unsafe {
+        if !SetWindowSubclass(hwnd, Some(proxy_icon_proc), PROXY_ICON_SUBCLASS_ID, 0).as_bool() {
+            log::warn!("Could not handle drags from the title bar icon");
+        }
+    }
+}
+
+unsafe extern "system" fn proxy_icon_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    _data: usize,
+) -> LRESULT {
+    if msg == WM_NCLBUTTONDOWN && wparam.0 as u32 == HTSYSMENU {
+        let file = PROXY_FILE.lock().unwrap().clone();
+        // The position is in screen coordinates, like DragDetect wants it
+        let point = POINT {
+            x: (lparam.0 & 0xffff) as i16 as i32,
+            y: ((lparam.0 >> 16) & 0xffff) as i16 as i32,
+        };
+        if let Some(file) = file.filter(|_| DragDetect(hwnd, point).as_bool()) {
+            if let Err(error) = drag_file(hwnd, &file) {
+                log::warn!("Could not drag {}: {error}", file.display());
+            }
+            return LRESULT(0);
+        }
+    }
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
+
+unsafe fn drag_file(hwnd: HWND, file: &Path) -> windows::core::Result<()> {
+    let item: IShellItem = SHCreateItemFromParsingName(&HSTRING::from(file), None::<&IBindCtx>)?;
+    let data_object: IDataObject = item.BindToHandler(None::<&IBindCtx>, &BHID_DataObject)?;
+    SHDoDragDrop(
+        hwnd,
+        &data_object,
+        None::<&IDropSource>,
+        DROPEFFECT_COPY | DROPEFFECT_LINK,
+    )?;
+    Ok(())
+}
+
+/// Accepts text and links dragged onto the window, besides files. It replaces the drop target of
+/// winit, which only accepts files and doesn't tell where they are dropped.
+pub fn register_drop_target(window: &Window, proxy: EventLoopProxy<UserEvent>) {
+    let Some(hwnd) = window_hwnd(window) else {
+        return;
+    };
+    let target: IDropTarget = DropTarget {
+        hwnd,
+        proxy,
+        effect: Cell::new(DROPEFFECT_NONE),
+    }
+    .into();
+    // winit registered its own target, which has already initialized OLE for the window
+    unsafe {
+        let _ = RevokeDragDrop(hwnd);
+        if let Err(error) = RegisterDragDrop(hwnd, &target) {
+            log::warn!("Could not accept dragged text: {error}");
+        }
+    }
+}
+
+#[implement(IDropTarget)]
+struct DropTarget {
+    hwnd: HWND,
+    proxy: EventLoopProxy<UserEvent>,
+    /// Whether the dragged data can be dropped, as told to Windows on every move.
+    effect: Cell<DROPEFFECT>,
+}
+
+impl DropTarget {
+    fn send(&self, event: DragEvent) {
+        let _ = self.proxy.send_event(WindowCommand::Drag(event).into());
+    }
+}
+
+impl IDropTarget_Impl for DropTarget_Impl {
+    fn DragEnter(
+        &self,
+        data: Option<&IDataObject>,
+        _keys: MODIFIERKEYS_FLAGS,
+        _point: &POINTL,
+        effect: *mut DROPEFFECT,
+    ) -> windows::core::Result<()> {
+        let accepted = data.is_some_and(|data| unsafe {
+            has_format(data, CF_HDROP.0) || has_format(data, CF_UNICODETEXT.0)
+        });
+        self.effect.set(if accepted {
+            DROPEFFECT_COPY
+        } else {
+            DROPEFFECT_NONE
+        });
+        if accepted {
+            self.send(DragEvent::Hovered);
+        }
+        unsafe { *effect = self.effect.get() };
+        Ok(())
+    }
+
+    fn DragOver(
+        &self,
+        _keys: MODIFIERKEYS_FLAGS,
+        _point: &POINTL,
+        effect: *mut DROPEFFECT,
+    ) -> windows::core::Result<()> {
+        unsafe { *effect = self.effect.get() };
+        Ok(())
+    }
+
+    fn DragLeave(&self) -> windows::core::Result<()> {
+        self.send(DragEvent::Left);
+        Ok(())
+    }
+
+    fn Drop(
+        &self,
+        data: Option<&IDataObject>,
+        _keys: MODIFIERKEYS_FLAGS,
+        point: &POINTL,
+        effect: *mut DROPEFFECT,
+    ) -> windows::core::Result<()> {
+        unsafe { *effect = self.effect.get() };
+        let Some(data) = data else {
+            self.send(DragEvent::Left);
+            return Ok(());
+        };
+        let event = unsafe {
+            if let Some(files) = dropped_files(data) {
+                Some(DragEvent::Files(files))
+            } else {
+                // The point is on the screen, while the grid is in the client area
+                let mut point = POINT {
+                    x: point.x,
+                    y: point.y,
+                };
+                let _ = ScreenToClient(self.hwnd, &mut point);
+                let position = PixelPos::new(point.x as f32, point.y as f32);
+                dropped_unicode_text(data).map(|text| DragEvent::Text { text, position })
+            }
+        };
+        self.send(event.unwrap_or(DragEvent::Left));
+        Ok(())
+    }
+}
+
+fn global_format(format: u16) -> FORMATETC {
+    FORMATETC {
+        cfFormat: format,
+        ptd: std::ptr::null_mut(),
+        dwAspect: DVASPECT_CONTENT.0,
+        lindex: -1,
+        tymed: TYMED_HGLOBAL.0 as u32,
+    }
+}
+
+unsafe fn has_format(data: &IDataObject, format: u16) -> bool {
+    data.QueryGetData(&global_format(format)).is_ok()
+}
+
+unsafe fn dropped_files(data: &IDataObject) -> Option<Vec<PathBuf>> {
+    let mut medium = data.GetData(&global_format(CF_HDROP.0)).ok()?;
+    let hdrop = HDROP(medium.u.hGlobal.0);
+    let count = DragQueryFileW(hdrop, u32::MAX, None);
+    let files = (0..count)
+        .map(|index| {
+            let length = DragQueryFileW(hdrop, index, None) as usize;
+            let mut path = vec![0; length + 1];
+            DragQueryFileW(hdrop, index, Some(&mut path));
+            PathBuf::from(String::from_utf16_lossy(&path[..length]))
+        })
+        .collect();
+    ReleaseStgMedium(&mut medium);
+    Some(files)
+}
+
+unsafe fn dropped_unicode_text(data: &IDataObject) -> Option<String> {
+    let mut medium = data.GetData(&global_format(CF_UNICODETEXT.0)).ok()?;
+    let hglobal = medium.u.hGlobal;
+    let pointer = GlobalLock(hglobal) as *const u16;
+    let text = (!pointer.is_null()).then(|| {
+        let length = (0..).take_while(|&index| *pointer.add(index) != 0).count();
+        String::from_utf16_lossy(std::slice::from_raw_parts(pointer, length))
+    });
+    let _ = GlobalUnlock(hglobal);
+    ReleaseStgMedium(&mut medium);
+    text.and_then(|text| dropped_text(&text))
+}